@@ -0,0 +1,119 @@
+//! Benchmarks of the parser and the evaluator.
+//!
+//! Run with `cargo bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rhai::{Engine, Scope, INT};
+
+const SCRIPT_LOOP: &str = r#"
+    let x = 0;
+    let i = 0;
+    while i < 10000 {
+        x = x + i * 2 - 1;
+        i += 1;
+    }
+    x
+"#;
+
+const SCRIPT_FN_CALLS: &str = r#"
+    fn add(x, y) { x + y }
+
+    let sum = 0;
+    for i in range(0, 1000) {
+        sum = add(sum, i);
+    }
+    sum
+"#;
+
+const SCRIPT_FIBONACCI: &str = r#"
+    fn fib(n) {
+        if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+    }
+    fib(15)
+"#;
+
+const SCRIPT_ARRAYS: &str = r#"
+    let a = [];
+    for i in range(0, 1000) {
+        a.push(i);
+    }
+    let sum = 0;
+    for x in a {
+        sum += x;
+    }
+    a[500] = sum;
+    a.len()
+"#;
+
+const SCRIPT_STRINGS: &str = r#"
+    let s = "";
+    for i in range(0, 1000) {
+        s += "x" + i;
+    }
+    s.len()
+"#;
+
+fn bench_parse(c: &mut Criterion) {
+    let engine = Engine::new();
+    let mut group = c.benchmark_group("parse");
+
+    for (name, script) in [
+        ("loop", SCRIPT_LOOP),
+        ("fn_calls", SCRIPT_FN_CALLS),
+        ("fibonacci", SCRIPT_FIBONACCI),
+        ("arrays", SCRIPT_ARRAYS),
+        ("strings", SCRIPT_STRINGS),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| engine.compile(black_box(script)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_eval(c: &mut Criterion) {
+    let mut engine = Engine::new();
+    let mut group = c.benchmark_group("eval");
+
+    for (name, script) in [
+        ("arithmetic_loop", SCRIPT_LOOP),
+        ("fn_calls", SCRIPT_FN_CALLS),
+        ("fibonacci", SCRIPT_FIBONACCI),
+        ("arrays", SCRIPT_ARRAYS),
+        ("strings", SCRIPT_STRINGS),
+    ] {
+        let ast = engine.compile(script).unwrap();
+
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                engine
+                    .eval_ast_with_scope::<INT>(&mut Scope::new(), black_box(&ast))
+                    .unwrap()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_call_fn(c: &mut Criterion) {
+    let mut engine = Engine::new();
+    let ast = engine.compile("fn add(x, y) { x + y }").unwrap();
+
+    c.bench_function("call_fn", |b| {
+        b.iter(|| {
+            engine
+                .call_fn::<_, INT>(
+                    &mut Scope::new(),
+                    &ast,
+                    "add",
+                    (black_box(40 as INT), 2 as INT),
+                )
+                .unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, bench_parse, bench_eval, bench_call_fn);
+criterion_main!(benches);
@@ -1,6 +1,6 @@
 use rhai::{Engine, EvalAltResult, INT};
 
-fn main() -> Result<(), EvalAltResult> {
+fn main() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     let result = engine.eval::<INT>("40 + 2")?;
@@ -139,7 +139,7 @@ fn main() {
 
         if let Err(err) = engine
             .compile_with_scope(&scope, &script)
-            .map_err(EvalAltResult::ErrorParsing)
+            .map_err(|err| Box::new(EvalAltResult::ErrorParsing(err)))
             .and_then(|r| {
                 ast_u = r.clone();
 
@@ -161,9 +161,9 @@ fn main() {
                 // Evaluate
                 let result = engine
                     .consume_ast_with_scope(&mut scope, &main_ast)
-                    .or_else(|err| match err {
+                    .or_else(|err| match *err {
                         EvalAltResult::Return(_, _) => Ok(()),
-                        err => Err(err),
+                        _ => Err(err),
                     });
 
                 // Throw away all the statements, leaving only the functions
@@ -173,7 +173,7 @@ fn main() {
             })
         {
             println!();
-            print_error(&input, err);
+            print_error(&input, *err);
             println!();
         }
     }
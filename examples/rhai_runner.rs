@@ -1,54 +1,9 @@
-use rhai::{Engine, EvalAltResult, Position};
+use rhai::Engine;
 
 #[cfg(not(feature = "no_optimize"))]
 use rhai::OptimizationLevel;
 
-use std::{env, fs::File, io::Read, iter, process::exit};
-
-fn eprint_error(input: &str, err: EvalAltResult) {
-    fn eprint_line(lines: &[&str], line: usize, pos: usize, err: &str) {
-        let line_no = format!("{}: ", line);
-        let pos_text = format!(" (line {}, position {})", line, pos);
-
-        eprintln!("{}{}", line_no, lines[line - 1]);
-        eprintln!(
-            "{:>1$} {2}",
-            "^",
-            line_no.len() + pos,
-            err.replace(&pos_text, "")
-        );
-        eprintln!("");
-    }
-
-    let lines: Vec<_> = input.split('\n').collect();
-
-    // Print error
-    let pos = if err.position().is_eof() {
-        let last = lines[lines.len() - 1];
-        Position::new(lines.len(), last.len() + 1)
-    } else {
-        err.position()
-    };
-
-    match pos {
-        p if p.is_eof() => panic!("should not be EOF"),
-        p if p.is_none() => {
-            // No position
-            eprintln!("{}", err);
-        }
-        p => {
-            // Specific position
-            let err_text = match err {
-                EvalAltResult::ErrorRuntime(err, _) if !err.is_empty() => {
-                    format!("Runtime error: {}", err)
-                }
-                err => err.to_string(),
-            };
-
-            eprint_line(&lines, p.line().unwrap(), p.position().unwrap(), &err_text)
-        }
-    }
-}
+use std::{env, fs::File, io::Read, process::exit};
 
 fn main() {
     for filename in env::args().skip(1) {
@@ -78,7 +33,7 @@ fn main() {
             eprintln!("{:=<1$}", "", filename.len());
             eprintln!("");
 
-            eprint_error(&contents, err);
+            eprintln!("{}", err.to_pretty_string(&contents));
         }
     }
 }
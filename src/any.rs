@@ -2,10 +2,20 @@
 
 use crate::stdlib::{
     any::{type_name, TypeId},
+    borrow::Cow,
     boxed::Box,
     fmt,
+    string::String,
+    sync::Arc,
+    vec::Vec,
 };
 
+#[cfg(not(feature = "no_index"))]
+use crate::engine::{Array, Blob};
+
+#[cfg(not(feature = "no_object"))]
+use crate::engine::Map;
+
 /// An raw value of any type.
 ///
 /// Currently, `Variant` is not `Send` nor `Sync`, so it can practically be any type.
@@ -115,6 +125,252 @@ impl Variant {
             None
         }
     }
+
+    /// Get the text of a string value without copying it.
+    ///
+    /// Besides `String`, this covers read-only strings passed in by the host without copying:
+    /// `&'static str`, `Cow<'static, str>` and `Arc<str>`.
+    /// Returns `None` if the value is not a string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rhai::{Any, Dynamic};
+    /// use std::borrow::Cow;
+    ///
+    /// let x: Dynamic = "hello".to_string().into_dynamic();
+    /// let y: Dynamic = Cow::Borrowed("world").into_dynamic();
+    ///
+    /// assert_eq!(x.as_str(), Some("hello"));
+    /// assert_eq!(y.as_str(), Some("world"));
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        if let Some(s) = self.downcast_ref::<String>() {
+            return Some(s);
+        }
+        if let Some(&s) = self.downcast_ref::<&'static str>() {
+            return Some(s);
+        }
+        if let Some(s) = self.downcast_ref::<Cow<'static, str>>() {
+            return Some(s);
+        }
+        self.downcast_ref::<Arc<str>>().map(|s| &**s)
+    }
+
+    /// Get the bytes of a BLOB value without copying them.
+    ///
+    /// Besides `Blob`, this covers read-only bytes passed in by the host without copying:
+    /// `&'static [u8]`, `Cow<'static, [u8]>` and `Arc<[u8]>`.
+    /// Returns `None` if the value is not a BLOB.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        #[cfg(not(feature = "no_index"))]
+        {
+            if let Some(b) = self.downcast_ref::<Blob>() {
+                return Some(b);
+            }
+        }
+        if let Some(&b) = self.downcast_ref::<&'static [u8]>() {
+            return Some(b);
+        }
+        if let Some(b) = self.downcast_ref::<Cow<'static, [u8]>>() {
+            return Some(b);
+        }
+        self.downcast_ref::<Arc<[u8]>>().map(|b| &**b)
+    }
+}
+
+/// A step in a path into nested object maps and arrays.
+enum PathSegment<'a> {
+    /// A property of an object map, e.g. `.foo`
+    #[cfg_attr(feature = "no_object", allow(dead_code))]
+    Property(&'a str),
+    /// An index into an array, e.g. `[42]`
+    #[cfg_attr(feature = "no_index", allow(dead_code))]
+    Index(usize),
+}
+
+/// Split a path such as `a.b[2].c` into segments.  Returns `None` if the path is malformed.
+fn parse_path(path: &str) -> Option<Vec<PathSegment<'_>>> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+
+    while !rest.is_empty() {
+        if let Some(index) = rest.strip_prefix('[') {
+            let end = index.find(']')?;
+            segments.push(PathSegment::Index(index[..end].parse().ok()?));
+            rest = &index[end + 1..];
+        } else {
+            // The first property is not preceded by a dot
+            let name = if segments.is_empty() {
+                rest
+            } else {
+                rest.strip_prefix('.')?
+            };
+            let end = name.find(['.', '[']).unwrap_or(name.len());
+
+            if end == 0 {
+                return None;
+            }
+
+            segments.push(PathSegment::Property(&name[..end]));
+            rest = &name[end..];
+        }
+    }
+
+    Some(segments)
+}
+
+impl Variant {
+    /// Get a reference to the value at the end of a path into nested object maps and arrays,
+    /// e.g. `a.b[2].c`.  An empty path refers to the value itself.
+    /// Returns `None` if the path is malformed or does not lead to a value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # #[cfg(not(feature = "no_index"))]
+    /// # #[cfg(not(feature = "no_object"))]
+    /// # {
+    /// use rhai::{Any, Engine, Map, INT};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// let result = engine.eval::<Map>("#{ a: #{ b: [1, 2, #{ c: 42 }] } }")?.into_dynamic();
+    ///
+    /// let value = result.read_path("a.b[2].c").and_then(|v| v.downcast_ref::<INT>());
+    /// assert_eq!(value, Some(&42));
+    ///
+    /// assert!(result.read_path("a.b[3].c").is_none());
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        all(feature = "no_index", feature = "no_object"),
+        allow(unused_variables)
+    )]
+    pub fn read_path(&self, path: &str) -> Option<&Variant> {
+        parse_path(path)?
+            .into_iter()
+            .try_fold(self, |value, segment| match segment {
+                #[cfg(not(feature = "no_object"))]
+                PathSegment::Property(name) => {
+                    value.downcast_ref::<Map>()?.get(name).map(AsRef::as_ref)
+                }
+                #[cfg(not(feature = "no_index"))]
+                PathSegment::Index(index) => {
+                    value.downcast_ref::<Array>()?.get(index).map(AsRef::as_ref)
+                }
+                #[allow(unreachable_patterns)]
+                _ => None,
+            })
+    }
+
+    /// Get a mutable reference to the value at the end of a path into nested object maps and arrays,
+    /// e.g. `a.b[2].c`.  An empty path refers to the value itself.
+    /// Returns `None` if the path is malformed or does not lead to a value.
+    pub fn read_path_mut(&mut self, path: &str) -> Option<&mut Variant> {
+        parse_path(path)?.into_iter().try_fold(self, walk_mut)
+    }
+
+    /// Write a value to the end of a path into nested object maps and arrays, e.g. `a.b[2].c`.
+    ///
+    /// All but the last step of the path must already exist.  The last step may add a new property
+    /// to an object map, but an array index must be within bounds.
+    /// Returns `false` (and leaves everything untouched) if the path is malformed or invalid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # #[cfg(not(feature = "no_index"))]
+    /// # #[cfg(not(feature = "no_object"))]
+    /// # {
+    /// use rhai::{Any, Engine, Map, INT};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// let mut result = engine.eval::<Map>("#{ a: [1, 2, 3] }")?.into_dynamic();
+    ///
+    /// assert!(result.write_path("a[1]", (42 as INT).into_dynamic()));
+    /// assert!(result.write_path("b", "hello".to_string().into_dynamic()));
+    /// assert!(!result.write_path("a[3]", (0 as INT).into_dynamic()));
+    ///
+    /// assert_eq!(result.read_path("a[1]").and_then(|v| v.downcast_ref::<INT>()), Some(&42));
+    /// assert_eq!(result.read_path("b").and_then(|v| v.downcast_ref::<String>()).unwrap(), "hello");
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        all(feature = "no_index", feature = "no_object"),
+        allow(unused_variables)
+    )]
+    pub fn write_path(&mut self, path: &str, value: Dynamic) -> bool {
+        let mut segments = match parse_path(path) {
+            Some(segments) => segments,
+            None => return false,
+        };
+
+        let last = match segments.pop() {
+            Some(last) => last,
+            None => return false,
+        };
+
+        let target = match segments.into_iter().try_fold(self, walk_mut) {
+            Some(target) => target,
+            None => return false,
+        };
+
+        match last {
+            #[cfg(not(feature = "no_object"))]
+            PathSegment::Property(name) => match target.downcast_mut::<Map>() {
+                Some(map) => {
+                    map.insert(name.into(), value);
+                    true
+                }
+                None => false,
+            },
+            #[cfg(not(feature = "no_index"))]
+            PathSegment::Index(index) => {
+                match target
+                    .downcast_mut::<Array>()
+                    .and_then(|a| a.get_mut(index))
+                {
+                    Some(item) => {
+                        *item = value;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+}
+
+/// Take one step along a path into nested object maps and arrays.
+#[cfg_attr(
+    all(feature = "no_index", feature = "no_object"),
+    allow(unused_variables)
+)]
+fn walk_mut<'a>(value: &'a mut Variant, segment: PathSegment) -> Option<&'a mut Variant> {
+    match segment {
+        #[cfg(not(feature = "no_object"))]
+        PathSegment::Property(name) => value
+            .downcast_mut::<Map>()?
+            .get_mut(name)
+            .map(AsMut::as_mut),
+        #[cfg(not(feature = "no_index"))]
+        PathSegment::Index(index) => value
+            .downcast_mut::<Array>()?
+            .get_mut(index)
+            .map(AsMut::as_mut),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
 }
 
 impl fmt::Debug for Variant {
@@ -141,6 +397,9 @@ pub trait AnyExt: Sized {
     /// Panics if the cast fails (e.g. the type of the actual value is not the same as the specified type).
     fn cast<T: Any + Clone>(self) -> T;
 
+    /// Take a string value as a `Cow<'static, str>`.
+    fn into_cow_str(self) -> Result<Cow<'static, str>, Self>;
+
     /// This trait may only be implemented by `rhai`.
     #[doc(hidden)]
     fn _closed(&self) -> _Private;
@@ -188,6 +447,41 @@ impl AnyExt for Dynamic {
         self.try_cast::<T>().expect("cast failed")
     }
 
+    /// Take a string value as a `Cow<'static, str>`.
+    ///
+    /// A `String` is moved into `Cow::Owned` and a `&'static str` becomes `Cow::Borrowed`, so neither
+    /// is copied.  Only the text of an `Arc<str>` is copied.
+    /// Returns the value itself if it is not a string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rhai::{Any, AnyExt, Dynamic};
+    /// use std::borrow::Cow;
+    ///
+    /// let x: Dynamic = "hello".into_dynamic();
+    ///
+    /// assert!(matches!(x.into_cow_str().unwrap(), Cow::Borrowed("hello")));
+    /// ```
+    fn into_cow_str(self) -> Result<Cow<'static, str>, Self> {
+        let value = match self.try_cast::<String>() {
+            Ok(s) => return Ok(Cow::Owned(s)),
+            Err(value) => value,
+        };
+        let value = match value.try_cast::<&'static str>() {
+            Ok(s) => return Ok(Cow::Borrowed(s)),
+            Err(value) => value,
+        };
+        let value = match value.try_cast::<Cow<'static, str>>() {
+            Ok(s) => return Ok(s),
+            Err(value) => value,
+        };
+        match value.downcast_ref::<Arc<str>>() {
+            Some(s) => Ok(Cow::Owned(s.to_string())),
+            None => Err(value),
+        }
+    }
+
     fn _closed(&self) -> _Private {
         _Private
     }
@@ -1,27 +1,54 @@
 //! Module that defines the extern API of `Engine`.
 
-use crate::any::{Any, AnyExt, Dynamic};
+use crate::any::{Any, AnyExt, Dynamic, Variant};
+use crate::builtin::to_json;
 use crate::call::FuncArgs;
-use crate::engine::{make_getter, make_setter, Engine, FnAny, FnSpec};
+use crate::engine::{make_getter, make_setter, Engine, FnAny, FnSpec, SharedFnAny};
 use crate::error::ParseError;
 use crate::fn_register::RegisterFn;
-use crate::parser::{lex, parse, parse_global_expr, Position, AST};
+use crate::parser::{
+    lex, lex_with_comments, lex_with_doc_comments, parse, parse_global_expr, validate, Comment,
+    Position, Token, TokenIterator, AST,
+};
 use crate::result::EvalAltResult;
 use crate::scope::Scope;
 
+#[cfg(feature = "rand")]
+use crate::engine::with_rng;
+
+#[cfg(not(feature = "no_object"))]
+use crate::engine::{Map, FUNC_TO_STRING, KEYWORD_DEBUG, KEYWORD_ENV, KEYWORD_PRINT};
+
+#[cfg(not(feature = "no_module"))]
+use crate::module::{ModuleResolver, NativeModule};
+
 #[cfg(not(feature = "no_optimize"))]
 use crate::optimize::optimize_into_ast;
 
+#[cfg(feature = "serde")]
+use crate::de::from_dynamic;
+
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+
+#[cfg(not(feature = "no_object"))]
+use crate::stdlib::fmt::{Debug, Display};
 use crate::stdlib::{
     any::{type_name, TypeId},
     boxed::Box,
     collections::HashMap,
+    format,
     string::{String, ToString},
     vec::Vec,
 };
 #[cfg(not(feature = "no_std"))]
 use crate::stdlib::{fs::File, io::prelude::*, path::PathBuf};
 
+#[cfg(feature = "sync")]
+use crate::stdlib::sync::{Arc, Mutex};
+#[cfg(not(feature = "sync"))]
+use crate::stdlib::{cell::RefCell, rc::Rc};
+
 // Define callback function types
 #[cfg(feature = "sync")]
 pub trait ObjectGetCallback<T, U>: Fn(&mut T) -> U + Send + Sync + 'static {}
@@ -65,13 +92,25 @@ impl<'e> Engine<'e> {
     pub(crate) fn register_fn_raw(&mut self, fn_name: &str, args: Vec<TypeId>, f: Box<FnAny>) {
         let spec = FnSpec {
             name: fn_name.to_string().into(),
-            args,
+            args: args.into_iter().collect(),
         };
 
-        if self.functions.is_none() {
-            self.functions = Some(HashMap::new());
-        }
-        self.functions.as_mut().unwrap().insert(spec, f);
+        self.functions_mut().insert(spec, f.into());
+    }
+
+    /// Get mutable access to the registered functions, copying them first if they are shared
+    /// with a duplicate of the `Engine`.
+    pub(crate) fn functions_mut(&mut self) -> &mut HashMap<FnSpec<'e>, SharedFnAny> {
+        self.fn_index = None;
+        #[cfg(feature = "jit")]
+        self.jit.clear();
+
+        let functions = self.functions.get_or_insert_with(Default::default);
+
+        #[cfg(feature = "sync")]
+        return Arc::make_mut(functions);
+        #[cfg(not(feature = "sync"))]
+        return Rc::make_mut(functions);
     }
 
     /// Register a custom type for use with the `Engine`.
@@ -90,7 +129,7 @@ impl<'e> Engine<'e> {
     ///     fn update(&mut self, offset: i64)   { self.field += offset; }
     /// }
     ///
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::{Engine, RegisterFn};
     ///
     /// let mut engine = Engine::new();
@@ -130,7 +169,7 @@ impl<'e> Engine<'e> {
     ///     fn new() -> Self { TestStruct { field: 1 } }
     /// }
     ///
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::{Engine, RegisterFn};
     ///
     /// let mut engine = Engine::new();
@@ -160,28 +199,144 @@ impl<'e> Engine<'e> {
     /// ```
     #[cfg(not(feature = "no_object"))]
     pub fn register_type_with_name<T: Any + Clone>(&mut self, name: &str) {
-        if self.type_names.is_none() {
-            self.type_names = Some(HashMap::new());
-        }
+        self.set_type_name::<T>(name);
+    }
 
+    /// Set the pretty-print name of a type.
+    pub(crate) fn set_type_name<T: Any>(&mut self, name: &str) {
         // Add the pretty-print type name into the map
         self.type_names
-            .as_mut()
-            .unwrap()
+            .get_or_insert_with(HashMap::new)
             .insert(type_name::<T>().to_string(), name.to_string());
     }
 
+    /// Register a custom type for use with the `Engine`, using its `Display` and `Debug`
+    /// implementations for the `to_string`, `print` and `debug` functions.
+    /// The type must implement `Clone`, `Display` and `Debug`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::fmt;
+    ///
+    /// #[derive(Debug, Clone)]
+    /// struct Point {
+    ///     x: i64,
+    ///     y: i64,
+    /// }
+    ///
+    /// impl fmt::Display for Point {
+    ///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    ///         write!(f, "({}, {})", self.x, self.y)
+    ///     }
+    /// }
+    ///
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, RegisterFn};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// // Register the custom type, together with `to_string`, `print` and `debug`.
+    /// engine.register_type_with_display::<Point>();
+    ///
+    /// engine.register_fn("point", |x: i64, y: i64| Point { x, y });
+    ///
+    /// assert_eq!(engine.eval::<String>("to_string(point(1, 2))")?, "(1, 2)");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_object"))]
+    pub fn register_type_with_display<T: Any + Clone + Display + Debug>(&mut self) {
+        self.register_type::<T>();
+        self.register_fn(KEYWORD_PRINT, |x: &mut T| x.to_string());
+        self.register_fn(FUNC_TO_STRING, |x: &mut T| x.to_string());
+        self.register_fn(KEYWORD_DEBUG, |x: &mut T| format!("{:?}", x));
+    }
+
+    /// Register a Rust `enum` type for use with the `Engine`, with a pretty-print name for
+    /// the `type_of` function. The type must implement `Clone`, `PartialEq` and `Debug`.
+    ///
+    /// Each variant in `variants` gets a constructor function (taking no arguments) with the
+    /// specified name, which is usually used for unit variants. Constructors for variants
+    /// carrying data can be registered with `register_fn` as usual.
+    ///
+    /// The following functions are also registered for the type:
+    ///
+    /// * `==` and `!=`, using its `PartialEq` implementation,
+    /// * `variant`, which returns the name of the variant (e.g. `"Red"`),
+    /// * `is_variant`, which tests whether the value is a variant of a particular name.
+    ///
+    /// Variant names are taken from the `Debug` implementation, so it should be derived.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// #[derive(Debug, Clone, PartialEq)]
+    /// enum Shape {
+    ///     Empty,
+    ///     Circle(i64),
+    /// }
+    ///
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, RegisterFn};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_enum_type("Shape", &[("Empty", Shape::Empty)]);
+    /// engine.register_fn("Circle", Shape::Circle);
+    ///
+    /// assert!(engine.eval::<bool>("let s = Circle(42); s.is_variant(\"Circle\")")?);
+    /// assert!(engine.eval::<bool>("Empty() != Circle(1)")?);
+    /// assert_eq!(engine.eval::<String>("let s = Empty(); s.variant()")?, "Empty");
+    /// assert_eq!(engine.eval::<String>("type_of(Empty())")?, "Shape");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_object"))]
+    pub fn register_enum_type<T: Any + Clone + PartialEq + Debug>(
+        &mut self,
+        name: &str,
+        variants: &[(&str, T)],
+    ) {
+        fn variant_name<T: Debug>(x: &T) -> String {
+            let text = format!("{:?}", x);
+            let end = text
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(text.len());
+            text[..end].to_string()
+        }
+
+        self.register_type_with_name::<T>(name);
+
+        for (variant, value) in variants {
+            let value = value.clone();
+            self.register_fn(variant, move || value.clone());
+        }
+
+        self.register_fn("==", |x: &mut T, y: T| *x == y);
+        self.register_fn("!=", |x: &mut T, y: T| *x != y);
+        self.register_fn("variant", |x: &mut T| variant_name(x));
+        self.register_fn("is_variant", |x: &mut T, name: String| {
+            variant_name(x) == name
+        });
+    }
+
     /// Register an iterator adapter for a type with the `Engine`.
     /// This is an advanced feature.
     pub fn register_iterator<T: Any, F: IteratorCallback>(&mut self, f: F) {
         if self.type_iterators.is_none() {
-            self.type_iterators = Some(HashMap::new());
+            self.type_iterators = Some(Default::default());
         }
 
+        #[cfg(feature = "sync")]
+        let f = Arc::new(f);
+        #[cfg(not(feature = "sync"))]
+        let f = Rc::new(f);
+
         self.type_iterators
             .as_mut()
             .unwrap()
-            .insert(TypeId::of::<T>(), Box::new(f));
+            .insert(TypeId::of::<T>(), f);
     }
 
     /// Register a getter function for a member of a registered type with the `Engine`.
@@ -203,7 +358,7 @@ impl<'e> Engine<'e> {
     ///     fn get_field(&mut self) -> i64  { self.field }
     /// }
     ///
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::{Engine, RegisterFn};
     ///
     /// let mut engine = Engine::new();
@@ -245,7 +400,7 @@ impl<'e> Engine<'e> {
     ///     fn set_field(&mut self, new_val: i64)   { self.field = new_val; }
     /// }
     ///
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::{Engine, RegisterFn};
     ///
     /// let mut engine = Engine::new();
@@ -296,7 +451,7 @@ impl<'e> Engine<'e> {
     ///     fn set_field(&mut self, new_val: i64)   { self.field = new_val; }
     /// }
     ///
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::{Engine, RegisterFn};
     ///
     /// let mut engine = Engine::new();
@@ -326,12 +481,72 @@ impl<'e> Engine<'e> {
         self.register_set(name, set_fn);
     }
 
+    /// Tokenize a string into a stream of `Token`'s, exactly as the `Engine` does when parsing scripts.
+    ///
+    /// This is useful for external tooling, such as syntax highlighters and formatters.
+    /// Invalid input does not stop the stream - it shows up as `Token::LexError` instead, as do
+    /// disabled symbols and anything not allowed by the dialect of the `Engine`.  Custom keywords
+    /// show up as `Token::Custom`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rhai::{Engine, Token};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let tokens: Vec<_> = engine.lex("let x = 42;").map(|(token, _)| token).collect();
+    ///
+    /// assert_eq!(tokens[0], Token::Let);
+    /// assert_eq!(tokens[1], Token::Identifier("x".into()));
+    /// assert_eq!(tokens.len(), 5);
+    /// ```
+    pub fn lex<'s>(&'s self, input: &'s str) -> impl Iterator<Item = (Token, Position)> + 's {
+        self.tokenize(if self.comments {
+            lex_with_comments(input)
+        } else {
+            lex(input)
+        })
+    }
+
+    /// Apply the disabled symbols, custom keywords and dialect of the `Engine` to a token stream.
+    fn tokenize<'a>(&'a self, tokens: TokenIterator<'a>) -> TokenIterator<'a> {
+        tokens
+            .with_disabled_symbols(&self.disabled_symbols)
+            .with_custom_keywords(&self.custom_keywords)
+            .with_dialect(self.dialect.as_ref())
+    }
+
+    /// Tokenize a script to be compiled, capturing doc-comments if enabled.
+    fn tokenize_script<'a>(&'a self, input: &'a str) -> TokenIterator<'a> {
+        self.tokenize(if self.doc_comments {
+            lex_with_doc_comments(input)
+        } else {
+            lex(input)
+        })
+    }
+
+    /// Collect all the comments in a script, if comments are kept.
+    fn collect_comments(&self, input: &str, start: Position) -> Vec<Comment> {
+        if !self.comments {
+            return vec![];
+        }
+
+        lex_with_comments(input)
+            .with_start_position(start)
+            .filter_map(|(token, pos)| match token {
+                Token::Comment(text) => Some(Comment::new(text, pos)),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Compile a string into an `AST`, which can be used later for evaluation.
     ///
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::Engine;
     ///
     /// let mut engine = Engine::new();
@@ -356,7 +571,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// # #[cfg(not(feature = "no_optimize"))]
     /// # {
     /// use rhai::{Engine, Scope, OptimizationLevel};
@@ -387,20 +602,95 @@ impl<'e> Engine<'e> {
     /// # }
     /// ```
     pub fn compile_with_scope(&self, scope: &Scope, input: &str) -> Result<AST, ParseError> {
-        let tokens_stream = lex(input);
-        parse(&mut tokens_stream.peekable(), self, scope)
+        let tokens_stream = self.tokenize_script(input);
+
+        let mut ast = parse(tokens_stream, self, scope)?;
+        ast.3 = self.collect_comments(input, Position::new(1, 1));
+        Ok(ast)
+    }
+
+    /// Compile a script fragment embedded in a larger document (e.g. a code block in a markdown file)
+    /// into an `AST`.
+    ///
+    /// `start` is the position in the document of the first character of the fragment.
+    /// All positions reported - in parse errors, runtime errors and to the `on_debug` callback -
+    /// refer to the document instead of the fragment. Only the first line of the fragment is offset
+    /// by the column of `start`; the lines that follow are assumed to start at the first column.
+    ///
+    /// `source` is the name of the document, which is set as the source name of the `AST`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rhai::{Engine, Position};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// let document = "# Title\n\n```rhai\nlet x = 42;\nx + y\n```";
+    ///
+    /// // The script starts at line 4 of the document
+    /// let ast = engine
+    ///     .compile_fragment("README.md", Position::new(4, 1), "let x = 42;\nx + y")
+    ///     .unwrap();
+    ///
+    /// assert_eq!(ast.source(), Some("README.md"));
+    ///
+    /// let err = engine.eval_ast::<i64>(&ast).unwrap_err();
+    /// assert_eq!(err.position(), Position::new(5, 5));
+    /// assert!(err.to_pretty_string(document).contains("5 | x + y"));
+    /// ```
+    pub fn compile_fragment(
+        &self,
+        source: &str,
+        start: Position,
+        input: &str,
+    ) -> Result<AST, ParseError> {
+        let tokens_stream = self.tokenize_script(input).with_start_position(start);
+
+        let mut ast = parse(tokens_stream, self, &Scope::new())?;
+        ast.set_source(source);
+        ast.3 = self.collect_comments(input, start);
+        Ok(ast)
+    }
+
+    /// Check a script for syntax errors without compiling or evaluating it.
+    ///
+    /// Unlike `compile`, parsing does not stop at the first error - after each error, parsing resumes
+    /// at the next line that starts at the first column, and all the errors found are returned.
+    ///
+    /// This never panics on any input, so it can be used to cheaply reject malformed scripts
+    /// from untrusted sources.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// assert!(engine.validate("let x = 40 + 2;").is_ok());
+    ///
+    /// let errors = engine.validate("let x = ;\nlet y = 42;\nlet z = (1 + ;").unwrap_err();
+    ///
+    /// assert_eq!(errors.len(), 2);
+    /// assert_eq!(errors[0].position().line(), Some(1));
+    /// assert_eq!(errors[1].position().line(), Some(3));
+    /// ```
+    pub fn validate(&self, input: &str) -> Result<(), Vec<ParseError>> {
+        let tokens_stream = self.tokenize_script(input);
+        validate(tokens_stream, self)
     }
 
     /// Read the contents of a file into a string.
     #[cfg(not(feature = "no_std"))]
-    fn read_file(path: PathBuf) -> Result<String, EvalAltResult> {
+    fn read_file(path: PathBuf) -> Result<String, Box<EvalAltResult>> {
         let mut f = File::open(path.clone())
-            .map_err(|err| EvalAltResult::ErrorReadingScriptFile(path.clone(), err))?;
+            .map_err(|err| Box::new(EvalAltResult::ErrorReadingScriptFile(path.clone(), err)))?;
 
         let mut contents = String::new();
 
         f.read_to_string(&mut contents)
-            .map_err(|err| EvalAltResult::ErrorReadingScriptFile(path.clone(), err))?;
+            .map_err(|err| Box::new(EvalAltResult::ErrorReadingScriptFile(path.clone(), err)))?;
 
         Ok(contents)
     }
@@ -410,7 +700,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```no_run
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::Engine;
     ///
     /// let mut engine = Engine::new();
@@ -426,7 +716,7 @@ impl<'e> Engine<'e> {
     /// # }
     /// ```
     #[cfg(not(feature = "no_std"))]
-    pub fn compile_file(&self, path: PathBuf) -> Result<AST, EvalAltResult> {
+    pub fn compile_file(&self, path: PathBuf) -> Result<AST, Box<EvalAltResult>> {
         self.compile_file_with_scope(&Scope::new(), path)
     }
 
@@ -437,7 +727,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```no_run
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// # #[cfg(not(feature = "no_optimize"))]
     /// # {
     /// use rhai::{Engine, Scope, OptimizationLevel};
@@ -465,10 +755,13 @@ impl<'e> Engine<'e> {
         &self,
         scope: &Scope,
         path: PathBuf,
-    ) -> Result<AST, EvalAltResult> {
+    ) -> Result<AST, Box<EvalAltResult>> {
+        let source = path.to_string_lossy().into_owned();
+
         Self::read_file(path).and_then(|contents| {
-            self.compile_with_scope(scope, &contents)
-                .map_err(|err| err.into())
+            let mut ast = self.compile_with_scope(scope, &contents)?;
+            ast.set_source(&source);
+            Ok(ast)
         })
     }
 
@@ -478,7 +771,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::Engine;
     ///
     /// let mut engine = Engine::new();
@@ -505,7 +798,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// # #[cfg(not(feature = "no_optimize"))]
     /// # {
     /// use rhai::{Engine, Scope, OptimizationLevel};
@@ -540,8 +833,11 @@ impl<'e> Engine<'e> {
         scope: &Scope,
         input: &str,
     ) -> Result<AST, ParseError> {
-        let tokens_stream = lex(input);
-        parse_global_expr(&mut tokens_stream.peekable(), self, scope)
+        let tokens_stream = self.tokenize(lex(input));
+
+        let mut ast = parse_global_expr(tokens_stream, self, scope)?;
+        ast.3 = self.collect_comments(input, Position::new(1, 1));
+        Ok(ast)
     }
 
     /// Evaluate a script file.
@@ -549,7 +845,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```no_run
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::Engine;
     ///
     /// let mut engine = Engine::new();
@@ -560,8 +856,9 @@ impl<'e> Engine<'e> {
     /// # }
     /// ```
     #[cfg(not(feature = "no_std"))]
-    pub fn eval_file<T: Any + Clone>(&mut self, path: PathBuf) -> Result<T, EvalAltResult> {
-        Self::read_file(path).and_then(|contents| self.eval::<T>(&contents))
+    pub fn eval_file<T: Any + Clone>(&mut self, path: PathBuf) -> Result<T, Box<EvalAltResult>> {
+        self.compile_file(path)
+            .and_then(|ast| self.eval_ast::<T>(&ast))
     }
 
     /// Evaluate a script file with own scope.
@@ -569,7 +866,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```no_run
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::{Engine, Scope};
     ///
     /// let mut engine = Engine::new();
@@ -588,8 +885,9 @@ impl<'e> Engine<'e> {
         &mut self,
         scope: &mut Scope,
         path: PathBuf,
-    ) -> Result<T, EvalAltResult> {
-        Self::read_file(path).and_then(|contents| self.eval_with_scope::<T>(scope, &contents))
+    ) -> Result<T, Box<EvalAltResult>> {
+        self.compile_file(path)
+            .and_then(|ast| self.eval_ast_with_scope::<T>(scope, &ast))
     }
 
     /// Evaluate a string.
@@ -597,7 +895,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::Engine;
     ///
     /// let mut engine = Engine::new();
@@ -606,7 +904,7 @@ impl<'e> Engine<'e> {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn eval<T: Any + Clone>(&mut self, input: &str) -> Result<T, EvalAltResult> {
+    pub fn eval<T: Any + Clone>(&mut self, input: &str) -> Result<T, Box<EvalAltResult>> {
         self.eval_with_scope(&mut Scope::new(), input)
     }
 
@@ -615,7 +913,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::{Engine, Scope};
     ///
     /// let mut engine = Engine::new();
@@ -636,17 +934,59 @@ impl<'e> Engine<'e> {
         &mut self,
         scope: &mut Scope,
         input: &str,
-    ) -> Result<T, EvalAltResult> {
+    ) -> Result<T, Box<EvalAltResult>> {
         let ast = self.compile(input).map_err(EvalAltResult::ErrorParsing)?;
         self.eval_ast_with_scope(scope, &ast)
     }
 
+    /// Evaluate a string, then deserialize the result (e.g. an object map) into a Rust type
+    /// implementing `serde::Deserialize`.
+    ///
+    /// See [`from_dynamic`](fn.from_dynamic.html) for how values map to Rust types.
+    /// A result that does not fit the type is an `ErrorRuntime` describing the mismatch.
+    ///
+    /// Only available under the `serde` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # #[cfg(not(feature = "no_object"))]
+    /// # {
+    /// use rhai::Engine;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     name: String,
+    ///     retries: i64,
+    ///     verbose: Option<bool>,
+    /// }
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// let config: Config = engine.eval_into(r#"#{ name: "server", retries: 3 }"#)?;
+    ///
+    /// assert_eq!(config.name, "server");
+    /// assert_eq!(config.retries, 3);
+    /// assert_eq!(config.verbose, None);
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn eval_into<T: DeserializeOwned>(&mut self, input: &str) -> Result<T, Box<EvalAltResult>> {
+        let ast = self.compile(input).map_err(EvalAltResult::ErrorParsing)?;
+        let result = self.eval_ast_with_scope_raw(&mut Scope::new(), &ast)?;
+        from_dynamic(&result)
+    }
+
     /// Evaluate a string containing an expression.
     ///
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::Engine;
     ///
     /// let mut engine = Engine::new();
@@ -655,7 +995,10 @@ impl<'e> Engine<'e> {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn eval_expression<T: Any + Clone>(&mut self, input: &str) -> Result<T, EvalAltResult> {
+    pub fn eval_expression<T: Any + Clone>(
+        &mut self,
+        input: &str,
+    ) -> Result<T, Box<EvalAltResult>> {
         self.eval_expression_with_scope(&mut Scope::new(), input)
     }
 
@@ -664,7 +1007,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::{Engine, Scope};
     ///
     /// let mut engine = Engine::new();
@@ -681,7 +1024,7 @@ impl<'e> Engine<'e> {
         &mut self,
         scope: &mut Scope,
         input: &str,
-    ) -> Result<T, EvalAltResult> {
+    ) -> Result<T, Box<EvalAltResult>> {
         let ast = self
             .compile_expression(input)
             .map_err(EvalAltResult::ErrorParsing)?;
@@ -694,7 +1037,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::Engine;
     ///
     /// let mut engine = Engine::new();
@@ -707,7 +1050,7 @@ impl<'e> Engine<'e> {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn eval_ast<T: Any + Clone>(&mut self, ast: &AST) -> Result<T, EvalAltResult> {
+    pub fn eval_ast<T: Any + Clone>(&mut self, ast: &AST) -> Result<T, Box<EvalAltResult>> {
         self.eval_ast_with_scope(&mut Scope::new(), ast)
     }
 
@@ -716,7 +1059,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::{Engine, Scope};
     ///
     /// let mut engine = Engine::new();
@@ -744,35 +1087,49 @@ impl<'e> Engine<'e> {
         &mut self,
         scope: &mut Scope,
         ast: &AST,
-    ) -> Result<T, EvalAltResult> {
-        self.eval_ast_with_scope_raw(scope, ast)?
+    ) -> Result<T, Box<EvalAltResult>> {
+        let result = self.eval_ast_with_scope_raw(scope, ast)?;
+
+        let result = if self.unit_discard && TypeId::of::<T>() == TypeId::of::<()>() {
+            ().into_dynamic()
+        } else {
+            result
+        };
+
+        result
             .try_cast::<T>()
-            .map_err(|a| {
-                EvalAltResult::ErrorMismatchOutputType(
-                    self.map_type_name((*a).type_name()).to_string(),
-                    Position::none(),
-                )
-            })
+            .map_err(|a| self.make_type_mismatch_err::<T>(&*a, Position::none()))
     }
 
     pub(crate) fn eval_ast_with_scope_raw(
         &mut self,
         scope: &mut Scope,
         ast: &AST,
-    ) -> Result<Dynamic, EvalAltResult> {
-        let statements = {
-            let AST(statements, functions) = ast;
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let (statements, nodes) = {
+            let AST(statements, functions, source, _, nodes) = ast;
             self.fn_lib = Some(functions.clone());
-            statements
+            self.source = source.clone();
+            (statements, nodes)
         };
 
-        let result = statements
-            .iter()
-            .try_fold(().into_dynamic(), |_, stmt| self.eval_stmt(scope, stmt, 0));
+        let is_outermost = self.begin_stack_tracking();
+
+        let result = statements.iter().try_fold(().into_dynamic(), |_, stmt| {
+            self.eval_stmt(scope, nodes, stmt, 0)
+        });
+
+        self.end_stack_tracking(is_outermost);
 
         self.fn_lib = None;
+        self.source = None;
+
+        #[cfg(not(feature = "no_module"))]
+        {
+            self.imports = Default::default();
+        }
 
-        result.or_else(|err| match err {
+        result.or_else(|err| match *err {
             EvalAltResult::Return(out, _) => Ok(out),
             _ => Err(err),
         })
@@ -781,8 +1138,9 @@ impl<'e> Engine<'e> {
     /// Evaluate a file, but throw away the result and only return error (if any).
     /// Useful for when you don't need the result, but still need to keep track of possible errors.
     #[cfg(not(feature = "no_std"))]
-    pub fn consume_file(&mut self, path: PathBuf) -> Result<(), EvalAltResult> {
-        Self::read_file(path).and_then(|contents| self.consume(&contents))
+    pub fn consume_file(&mut self, path: PathBuf) -> Result<(), Box<EvalAltResult>> {
+        self.compile_file(path)
+            .and_then(|ast| self.consume_ast(&ast))
     }
 
     /// Evaluate a file with own scope, but throw away the result and only return error (if any).
@@ -792,13 +1150,14 @@ impl<'e> Engine<'e> {
         &mut self,
         scope: &mut Scope,
         path: PathBuf,
-    ) -> Result<(), EvalAltResult> {
-        Self::read_file(path).and_then(|contents| self.consume_with_scope(scope, &contents))
+    ) -> Result<(), Box<EvalAltResult>> {
+        self.compile_file_with_scope(scope, path)
+            .and_then(|ast| self.consume_ast_with_scope(scope, &ast))
     }
 
     /// Evaluate a string, but throw away the result and only return error (if any).
     /// Useful for when you don't need the result, but still need to keep track of possible errors.
-    pub fn consume(&mut self, input: &str) -> Result<(), EvalAltResult> {
+    pub fn consume(&mut self, input: &str) -> Result<(), Box<EvalAltResult>> {
         self.consume_with_scope(&mut Scope::new(), input)
     }
 
@@ -808,18 +1167,17 @@ impl<'e> Engine<'e> {
         &mut self,
         scope: &mut Scope,
         input: &str,
-    ) -> Result<(), EvalAltResult> {
-        let tokens_stream = lex(input);
+    ) -> Result<(), Box<EvalAltResult>> {
+        let tokens_stream = self.tokenize(lex(input));
 
-        let ast = parse(&mut tokens_stream.peekable(), self, scope)
-            .map_err(EvalAltResult::ErrorParsing)?;
+        let ast = parse(tokens_stream, self, scope).map_err(EvalAltResult::ErrorParsing)?;
 
         self.consume_ast_with_scope(scope, &ast)
     }
 
     /// Evaluate an AST, but throw away the result and only return error (if any).
     /// Useful for when you don't need the result, but still need to keep track of possible errors.
-    pub fn consume_ast(&mut self, ast: &AST) -> Result<(), EvalAltResult> {
+    pub fn consume_ast(&mut self, ast: &AST) -> Result<(), Box<EvalAltResult>> {
         self.consume_ast_with_scope(&mut Scope::new(), ast)
     }
 
@@ -829,31 +1187,92 @@ impl<'e> Engine<'e> {
         &mut self,
         scope: &mut Scope,
         ast: &AST,
-    ) -> Result<(), EvalAltResult> {
-        let statements = {
-            let AST(statements, functions) = ast;
+    ) -> Result<(), Box<EvalAltResult>> {
+        let (statements, nodes) = {
+            let AST(statements, functions, source, _, nodes) = ast;
             self.fn_lib = Some(functions.clone());
-            statements
+            self.source = source.clone();
+            (statements, nodes)
         };
 
-        let result = statements
-            .iter()
-            .try_fold(().into_dynamic(), |_, stmt| self.eval_stmt(scope, stmt, 0));
+        let is_outermost = self.begin_stack_tracking();
+
+        let result = statements.iter().try_fold(().into_dynamic(), |_, stmt| {
+            self.eval_stmt(scope, nodes, stmt, 0)
+        });
+
+        self.end_stack_tracking(is_outermost);
 
         self.fn_lib = None;
+        self.source = None;
 
-        result.map(|_| ()).or_else(|err| match err {
+        #[cfg(not(feature = "no_module"))]
+        {
+            self.imports = Default::default();
+        }
+
+        result.map(|_| ()).or_else(|err| match *err {
             EvalAltResult::Return(_, _) => Ok(()),
             _ => Err(err),
         })
     }
 
+    /// Run a script for its side effects, throwing away its result (whatever its type)
+    /// and only returning error (if any).  Same as `consume`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, Scope};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// // The result of the last statement (42) is discarded
+    /// engine.run("let x = 40; x + 2")?;
+    ///
+    /// let mut scope = Scope::new();
+    /// engine.run_with_scope(&mut scope, "let x = 40; x + 2")?;
+    /// assert_eq!(scope.get_value::<i64>("x").expect("variable x should exist"), 40);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run(&mut self, input: &str) -> Result<(), Box<EvalAltResult>> {
+        self.consume(input)
+    }
+
+    /// Run a script with own scope for its side effects, throwing away its result and only
+    /// returning error (if any).  Same as `consume_with_scope`.
+    pub fn run_with_scope(
+        &mut self,
+        scope: &mut Scope,
+        input: &str,
+    ) -> Result<(), Box<EvalAltResult>> {
+        self.consume_with_scope(scope, input)
+    }
+
+    /// Run an `AST` for its side effects, throwing away its result and only returning error (if any).
+    /// Same as `consume_ast`.
+    pub fn run_ast(&mut self, ast: &AST) -> Result<(), Box<EvalAltResult>> {
+        self.consume_ast(ast)
+    }
+
+    /// Run an `AST` with own scope for its side effects, throwing away its result and only
+    /// returning error (if any).  Same as `consume_ast_with_scope`.
+    pub fn run_ast_with_scope(
+        &mut self,
+        scope: &mut Scope,
+        ast: &AST,
+    ) -> Result<(), Box<EvalAltResult>> {
+        self.consume_ast_with_scope(scope, ast)
+    }
+
     /// Call a script function defined in an `AST` with no argument.
     ///
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// # #[cfg(not(feature = "no_stdlib"))]
     /// # #[cfg(not(feature = "no_function"))]
     /// # {
@@ -880,7 +1299,7 @@ impl<'e> Engine<'e> {
         scope: &mut Scope,
         ast: &AST,
         name: &str,
-    ) -> Result<T, EvalAltResult> {
+    ) -> Result<T, Box<EvalAltResult>> {
         self.call_fn_internal(scope, ast, name, vec![])
     }
 
@@ -889,7 +1308,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// # #[cfg(not(feature = "no_stdlib"))]
     /// # #[cfg(not(feature = "no_function"))]
     /// # {
@@ -917,7 +1336,7 @@ impl<'e> Engine<'e> {
         ast: &AST,
         name: &str,
         arg: A,
-    ) -> Result<T, EvalAltResult> {
+    ) -> Result<T, Box<EvalAltResult>> {
         self.call_fn_internal(scope, ast, name, vec![arg.into_dynamic()])
     }
 
@@ -926,7 +1345,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// # #[cfg(not(feature = "no_stdlib"))]
     /// # #[cfg(not(feature = "no_function"))]
     /// # {
@@ -954,7 +1373,7 @@ impl<'e> Engine<'e> {
         ast: &AST,
         name: &str,
         args: A,
-    ) -> Result<T, EvalAltResult> {
+    ) -> Result<T, Box<EvalAltResult>> {
         self.call_fn_internal(scope, ast, name, args.into_vec())
     }
 
@@ -965,26 +1384,190 @@ impl<'e> Engine<'e> {
         ast: &AST,
         name: &str,
         mut arg_values: Vec<Dynamic>,
-    ) -> Result<T, EvalAltResult> {
+    ) -> Result<T, Box<EvalAltResult>> {
         let mut args: Vec<_> = arg_values.iter_mut().map(Dynamic::as_mut).collect();
 
+        // Private functions can only be called from within the script
+        if matches!(ast.1.get_function(name, args.len()), Some(fn_def) if fn_def.private) {
+            let types_list: Vec<_> = args
+                .iter()
+                .map(|x| self.map_type_name((*x).type_name()))
+                .collect();
+
+            return Err(Box::new(EvalAltResult::ErrorFunctionNotFound(
+                format!("{} ({})", name, types_list.join(", ")),
+                Position::none(),
+            )));
+        }
+
         self.fn_lib = Some(ast.1.clone());
+        self.source = ast.2.clone();
+
+        let is_outermost = self.begin_stack_tracking();
+
+        let result = self.call_fn_raw(Some(scope), name, &mut args, None, Position::none(), 0);
 
-        let result = self
-            .call_fn_raw(Some(scope), name, &mut args, None, Position::none(), 0)?
-            .try_cast()
-            .map_err(|a| {
-                EvalAltResult::ErrorMismatchOutputType(
-                    self.map_type_name((*a).type_name()).into(),
-                    Position::none(),
-                )
-            });
+        self.end_stack_tracking(is_outermost);
+
+        let result = result?
+            .try_cast::<T>()
+            .map_err(|a| self.make_type_mismatch_err::<T>(&*a, Position::none()));
 
         self.fn_lib = None;
+        self.source = None;
+
+        #[cfg(not(feature = "no_module"))]
+        {
+            self.imports = Default::default();
+        }
 
         result
     }
 
+    /// Convert a value into JSON text.
+    ///
+    /// Only built-in types (and arrays and object maps containing only built-in types) are supported.
+    /// Object map properties are written in sorted order, `()` becomes `null`, and so do floating-point
+    /// numbers that are not finite. The same conversion is available to scripts via `to_json`.
+    ///
+    /// Returns `ErrorRuntime` if the value (or any value nested within it) is of an unsupported type.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # #[cfg(not(feature = "no_object"))]
+    /// # #[cfg(not(feature = "no_index"))]
+    /// # {
+    /// use rhai::{Any, Engine, Map};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// let state = engine.eval::<Map>(r#"#{ name: "Bob", score: 42, items: [true, ()] }"#)?;
+    /// let state = state.into_dynamic();
+    ///
+    /// assert_eq!(engine.to_json(&state)?, r#"{"items":[true,null],"name":"Bob","score":42}"#);
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_json(&self, value: &Dynamic) -> Result<String, Box<EvalAltResult>> {
+        self.to_json_raw(value.as_ref(), Position::none())
+    }
+
+    /// Convert a value into JSON text, raising an error at a position if it cannot be converted.
+    pub(crate) fn to_json_raw(
+        &self,
+        value: &Variant,
+        pos: Position,
+    ) -> Result<String, Box<EvalAltResult>> {
+        to_json(value).map_err(|name| {
+            Box::new(EvalAltResult::ErrorRuntime(
+                format!("Cannot convert '{}' to JSON", self.map_type_name(name)),
+                pos,
+            ))
+        })
+    }
+
+    /// Seed the random number generator used by the random number functions
+    /// (e.g. `rand`, `rand_float`, `shuffle` and `sample`).
+    ///
+    /// By default, the random number generator is seeded from system entropy. After seeding,
+    /// the same script run on the same `Engine` produces the same sequence of random numbers,
+    /// which is useful for replays and tests. The sequence is only guaranteed to be the same
+    /// for the same version of the `rand` crate.
+    ///
+    /// Only available under the `rand` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # #[cfg(not(feature = "no_stdlib"))]
+    /// # {
+    /// use rhai::{Engine, INT};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.set_rand_seed(42);
+    /// let first = engine.eval::<INT>("rand()")?;
+    ///
+    /// engine.set_rand_seed(42);
+    /// let second = engine.eval::<INT>("rand()")?;
+    ///
+    /// assert_eq!(first, second);
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "rand")]
+    pub fn set_rand_seed(&mut self, seed: u64) {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        with_rng(&self.rng, |rng| *rng = StdRng::seed_from_u64(seed));
+    }
+
+    /// Set the module resolution service used by the `Engine` to load modules for `import` statements.
+    /// Pass `None` to disable loading modules.
+    ///
+    /// By default, `Engine::new` loads modules from script files via `FileModuleResolver`
+    /// (except under `no_std`), and `Engine::new_raw` has no module resolver.
+    ///
+    /// Not available under the `no_module` feature.
+    #[cfg(not(feature = "no_module"))]
+    pub fn set_module_resolver(&mut self, resolver: Option<impl ModuleResolver + 'static>) {
+        #[cfg(feature = "sync")]
+        let resolver = resolver.map(|r| Arc::new(r) as Arc<dyn ModuleResolver>);
+        #[cfg(not(feature = "sync"))]
+        let resolver = resolver.map(|r| Rc::new(r) as Rc<dyn ModuleResolver>);
+
+        self.module_resolver = resolver;
+    }
+
+    /// Control whether compiling a script also loads all the modules it imports, via the module
+    /// resolver, to make sure that broken imports are caught during compilation instead of when
+    /// the script runs.
+    ///
+    /// Compilation then fails with `ParseErrorType::BadImport` if an imported module cannot be
+    /// found or compiled, if a name imported via `import ... from` or re-exported via `export` does
+    /// not exist, or if a function called via the namespace of an imported module (e.g. `m::foo(x)`)
+    /// does not exist in that module with the same number of parameters.  Import validation is off
+    /// by default.
+    ///
+    /// Not available under the `no_module` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, ParseErrorType, StaticModuleResolver};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// let mut resolver = StaticModuleResolver::new();
+    /// resolver.insert("math", engine.compile("fn double(x) { x * 2 }")?);
+    /// engine.set_module_resolver(Some(resolver));
+    ///
+    /// // Broken imports are only found when the script runs
+    /// assert!(engine.compile(r#"import "maths" as m; m::double(21)"#).is_ok());
+    ///
+    /// engine.enable_import_validation(true);
+    ///
+    /// assert!(engine.compile(r#"import "math" as m; m::double(21)"#).is_ok());
+    ///
+    /// let err = engine.compile(r#"import "maths" as m; m::double(21)"#).unwrap_err();
+    /// assert!(matches!(err.error_type(), ParseErrorType::BadImport(_)));
+    ///
+    /// let err = engine.compile(r#"import "math" as m; m::triple(21)"#).unwrap_err();
+    /// assert!(matches!(err.error_type(), ParseErrorType::BadImport(_)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_module"))]
+    pub fn enable_import_validation(&mut self, enable: bool) {
+        self.import_validation = enable
+    }
+
     /// Optimize the `AST` with constants defined in an external Scope.
     /// An optimized copy of the `AST` is returned while the original `AST` is consumed.
     ///
@@ -998,12 +1581,16 @@ impl<'e> Engine<'e> {
     /// (i.e. with `scope.push_constant(...)`). Then, the `AST is cloned and the copy re-optimized before running.
     #[cfg(not(feature = "no_optimize"))]
     pub fn optimize_ast(&self, scope: &Scope, ast: AST) -> AST {
-        optimize_into_ast(
+        let mut optimized = optimize_into_ast(
             self,
             scope,
             ast.0,
+            ast.4,
             ast.1.iter().map(|fn_def| fn_def.as_ref().clone()).collect(),
-        )
+        );
+        optimized.2 = ast.2;
+        optimized.3 = ast.3;
+        optimized
     }
 
     /// Override default action of `print` (print to stdout using `println!`)
@@ -1011,7 +1598,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::Engine;
     ///
     /// let mut result = String::from("");
@@ -1035,7 +1622,7 @@ impl<'e> Engine<'e> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::Engine;
     ///
     /// let mut result = String::from("");
@@ -1057,50 +1644,513 @@ impl<'e> Engine<'e> {
 
     /// Override default action of `debug` (print to stdout using `println!`)
     ///
+    /// The callback receives the text, the source name of the running script (if any)
+    /// and the position of the `debug` call.  Functions loaded from modules have the path
+    /// of the module as their source.
+    ///
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::Engine;
     ///
     /// let mut result = String::from("");
     /// {
     /// let mut engine = Engine::new();
     ///
+    /// let mut ast = engine.compile(r#"debug("hello");"#)?;
+    /// ast.set_source("world");
+    ///
     /// // Override action of 'debug' function
-    /// engine.on_debug(|s| result.push_str(s));
-    /// engine.consume(r#"debug("hello");"#)?;
+    /// engine.on_debug(|s, source, pos| {
+    ///     result.push_str(&format!("{} @ {:?} | {}", source.unwrap_or(""), pos, s))
+    /// });
+    /// engine.consume_ast(&ast)?;
     /// }
-    /// assert_eq!(result, "\"hello\"");
+    /// assert_eq!(result, "world @ (1:1) | \"hello\"");
     /// # Ok(())
     /// # }
     /// ```
     #[cfg(feature = "sync")]
-    pub fn on_debug(&mut self, callback: impl FnMut(&str) + Send + Sync + 'e) {
+    pub fn on_debug(
+        &mut self,
+        callback: impl FnMut(&str, Option<&str>, Position) + Send + Sync + 'e,
+    ) {
         self.on_debug = Some(Box::new(callback));
     }
     /// Override default action of `debug` (print to stdout using `println!`)
     ///
+    /// The callback receives the text, the source name of the running script (if any)
+    /// and the position of the `debug` call.  Functions loaded from modules have the path
+    /// of the module as their source.
+    ///
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::Engine;
     ///
     /// let mut result = String::from("");
     /// {
     /// let mut engine = Engine::new();
     ///
+    /// let mut ast = engine.compile(r#"debug("hello");"#)?;
+    /// ast.set_source("world");
+    ///
     /// // Override action of 'debug' function
-    /// engine.on_debug(|s| result.push_str(s));
-    /// engine.consume(r#"debug("hello");"#)?;
+    /// engine.on_debug(|s, source, pos| {
+    ///     result.push_str(&format!("{} @ {:?} | {}", source.unwrap_or(""), pos, s))
+    /// });
+    /// engine.consume_ast(&ast)?;
     /// }
-    /// assert_eq!(result, "\"hello\"");
+    /// assert_eq!(result, "world @ (1:1) | \"hello\"");
     /// # Ok(())
     /// # }
     /// ```
     #[cfg(not(feature = "sync"))]
-    pub fn on_debug(&mut self, callback: impl FnMut(&str) + 'e) {
+    pub fn on_debug(&mut self, callback: impl FnMut(&str, Option<&str>, Position) + 'e) {
         self.on_debug = Some(Box::new(callback));
     }
+    /// Register a filter to be called whenever a script defines a variable via `let` or `const`.
+    ///
+    /// The filter receives the variable's name, whether it is a constant, the current nesting level
+    /// of function calls (0 = global) and the current `Scope`.
+    /// Returning `false` forbids the definition, and evaluation fails with `ErrorForbiddenVariable`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, EvalAltResult};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// // Forbid variables named 'secret' and cap the number of variables in scope
+    /// engine.on_def_var(|name, _, _, scope| name != "secret" && scope.len() < 10);
+    ///
+    /// engine.consume("let x = 42;")?;
+    ///
+    /// match *engine.consume("let secret = 42;").expect_err("should error") {
+    ///     EvalAltResult::ErrorForbiddenVariable(name, _) => assert_eq!(name, "secret"),
+    ///     _ => panic!(),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "sync")]
+    pub fn on_def_var(
+        &mut self,
+        callback: impl FnMut(&str, bool, usize, &Scope) -> bool + Send + Sync + 'e,
+    ) {
+        self.on_def_var = Some(Box::new(callback));
+    }
+    /// Register a filter to be called whenever a script defines a variable via `let` or `const`.
+    ///
+    /// The filter receives the variable's name, whether it is a constant, the current nesting level
+    /// of function calls (0 = global) and the current `Scope`.
+    /// Returning `false` forbids the definition, and evaluation fails with `ErrorForbiddenVariable`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, EvalAltResult};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// // Forbid variables named 'secret' and cap the number of variables in scope
+    /// engine.on_def_var(|name, _, _, scope| name != "secret" && scope.len() < 10);
+    ///
+    /// engine.consume("let x = 42;")?;
+    ///
+    /// match *engine.consume("let secret = 42;").expect_err("should error") {
+    ///     EvalAltResult::ErrorForbiddenVariable(name, _) => assert_eq!(name, "secret"),
+    ///     _ => panic!(),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "sync"))]
+    pub fn on_def_var(&mut self, callback: impl FnMut(&str, bool, usize, &Scope) -> bool + 'e) {
+        self.on_def_var = Some(Box::new(callback));
+    }
+    /// Register a callback to audit every call a script makes to a native function
+    /// (i.e. a built-in function or a function registered with the `Engine`), including operators,
+    /// the functions handled by the `Engine` itself (e.g. `eval` and `format`) and access to
+    /// object map properties.  Calls to script-defined functions are not audited.
+    ///
+    /// The callback receives the function's name, the type names of the arguments and the position
+    /// of the call, before the call is made.
+    /// Returning `false` forbids the call, and evaluation fails with `ErrorForbiddenFunction`.
+    ///
+    /// While the callback is set, the optimizer does not evaluate native function calls ahead of time,
+    /// so set the callback before compiling scripts to have all calls audited.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, EvalAltResult};
+    ///
+    /// let mut log = Vec::new();
+    /// {
+    /// let mut engine = Engine::new();
+    ///
+    /// // Log every call, and forbid calls to 'print'
+    /// engine.on_call(|name, arg_types, pos| {
+    ///     log.push(format!("{}({}) at {}", name, arg_types.join(", "), pos));
+    ///     name != "print"
+    /// });
+    ///
+    /// engine.consume("let x = 40 + 2;")?;
+    ///
+    /// match *engine.consume("print(42);").expect_err("should error") {
+    ///     EvalAltResult::ErrorForbiddenFunction(name, _) => assert_eq!(name, "print"),
+    ///     _ => panic!(),
+    /// }
+    /// }
+    /// assert_eq!(log[0], "+(i64, i64) at line 1, position 12");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "sync")]
+    pub fn on_call(
+        &mut self,
+        callback: impl FnMut(&str, &[&str], Position) -> bool + Send + Sync + 'e,
+    ) {
+        let callback = Mutex::new(callback);
+        self.on_call = Some(Arc::new(move |name: &str, arg_types: &[&str], pos| {
+            (*callback.lock().unwrap())(name, arg_types, pos)
+        }));
+    }
+    /// Register a callback to audit every call a script makes to a native function
+    /// (i.e. a built-in function or a function registered with the `Engine`), including operators,
+    /// the functions handled by the `Engine` itself (e.g. `eval` and `format`) and access to
+    /// object map properties.  Calls to script-defined functions are not audited.
+    ///
+    /// The callback receives the function's name, the type names of the arguments and the position
+    /// of the call, before the call is made.
+    /// Returning `false` forbids the call, and evaluation fails with `ErrorForbiddenFunction`.
+    ///
+    /// While the callback is set, the optimizer does not evaluate native function calls ahead of time,
+    /// so set the callback before compiling scripts to have all calls audited.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, EvalAltResult};
+    ///
+    /// let mut log = Vec::new();
+    /// {
+    /// let mut engine = Engine::new();
+    ///
+    /// // Log every call, and forbid calls to 'print'
+    /// engine.on_call(|name, arg_types, pos| {
+    ///     log.push(format!("{}({}) at {}", name, arg_types.join(", "), pos));
+    ///     name != "print"
+    /// });
+    ///
+    /// engine.consume("let x = 40 + 2;")?;
+    ///
+    /// match *engine.consume("print(42);").expect_err("should error") {
+    ///     EvalAltResult::ErrorForbiddenFunction(name, _) => assert_eq!(name, "print"),
+    ///     _ => panic!(),
+    /// }
+    /// }
+    /// assert_eq!(log[0], "+(i64, i64) at line 1, position 12");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "sync"))]
+    pub fn on_call(&mut self, callback: impl FnMut(&str, &[&str], Position) -> bool + 'e) {
+        let callback = RefCell::new(callback);
+        self.on_call = Some(Rc::new(move |name: &str, arg_types: &[&str], pos| {
+            (*callback.borrow_mut())(name, arg_types, pos)
+        }));
+    }
+
+    /// Tag a native function (i.e. a built-in function or a function registered with the `Engine`)
+    /// with the capabilities it requires, such as `"fs"`, `"net"` or `"unsafe"`.
+    /// Tags apply to all functions with the same name, and replace any previous tags.
+    ///
+    /// A tagged function can only be called by scripts when all of its capabilities are granted
+    /// via `set_capabilities`. Tagged functions are never evaluated ahead of time by the optimizer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, EvalAltResult, RegisterFn};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_fn("read_file", |path: String| format!("contents of {}", path));
+    /// engine.tag_fn("read_file", &["fs"]);
+    ///
+    /// // User macros - no capabilities
+    /// engine.set_capabilities(Some(&[]));
+    ///
+    /// match *engine.eval::<String>(r#"read_file("foo.txt")"#).expect_err("should error") {
+    ///     EvalAltResult::ErrorForbiddenFunction(name, _) => assert_eq!(name, "read_file"),
+    ///     _ => panic!(),
+    /// }
+    ///
+    /// // Admin scripts - allowed to access the file system
+    /// engine.set_capabilities(Some(&["fs"]));
+    ///
+    /// assert_eq!(engine.eval::<String>(r#"read_file("foo.txt")"#)?, "contents of foo.txt");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tag_fn(&mut self, name: &str, tags: &[&str]) {
+        if tags.is_empty() {
+            self.fn_tags.remove(name);
+        } else {
+            self.fn_tags.insert(
+                name.to_string(),
+                tags.iter().map(|tag| tag.to_string()).collect(),
+            );
+        }
+    }
+
+    /// Register an alias for a native function, e.g. the old name of a renamed function, so that
+    /// existing scripts keep working.
+    ///
+    /// Scripts calling `alias` call `name` instead, unless a function named `alias` (with the same
+    /// number and types of parameters) exists, which takes precedence.  Use `on_fn_alias` to be
+    /// notified (e.g. to log a deprecation warning) whenever a script calls a function via an alias.
+    ///
+    /// An alias leading back to itself (directly or via other aliases) is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, RegisterFn, INT};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_fn("area", |w: INT, h: INT| w * h);
+    /// engine.register_fn_alias("calc_area", "area");
+    ///
+    /// assert_eq!(engine.eval::<INT>("calc_area(6, 7)")?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_fn_alias(&mut self, alias: &str, name: &str) {
+        let mut target = name;
+
+        while target != alias {
+            match self.fn_aliases.get(target) {
+                Some(next) => target = next,
+                None => {
+                    self.fn_aliases.insert(alias.into(), name.into());
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Register a callback to be notified whenever a script calls a native function via an alias
+    /// (see `register_fn_alias`), e.g. to warn about the use of deprecated names.
+    ///
+    /// The callback receives the alias, the name of the function called instead, the source name
+    /// of the running script (if any) and the position of the call.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, RegisterFn, INT};
+    ///
+    /// let mut warnings = Vec::new();
+    /// {
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_fn("area", |w: INT, h: INT| w * h);
+    /// engine.register_fn_alias("calc_area", "area");
+    ///
+    /// engine.on_fn_alias(|alias, name, _, pos| {
+    ///     warnings.push(format!("{:?}: '{}' is deprecated, use '{}'", pos, alias, name))
+    /// });
+    ///
+    /// engine.eval::<INT>("calc_area(6, 7)")?;
+    /// }
+    /// assert_eq!(warnings, ["(1:1): 'calc_area' is deprecated, use 'area'"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "sync")]
+    pub fn on_fn_alias(
+        &mut self,
+        callback: impl FnMut(&str, &str, Option<&str>, Position) + Send + Sync + 'e,
+    ) {
+        self.on_fn_alias = Some(Box::new(callback));
+    }
+    /// Register a callback to be notified whenever a script calls a native function via an alias
+    /// (see `register_fn_alias`), e.g. to warn about the use of deprecated names.
+    ///
+    /// The callback receives the alias, the name of the function called instead, the source name
+    /// of the running script (if any) and the position of the call.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, RegisterFn, INT};
+    ///
+    /// let mut warnings = Vec::new();
+    /// {
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_fn("area", |w: INT, h: INT| w * h);
+    /// engine.register_fn_alias("calc_area", "area");
+    ///
+    /// engine.on_fn_alias(|alias, name, _, pos| {
+    ///     warnings.push(format!("{:?}: '{}' is deprecated, use '{}'", pos, alias, name))
+    /// });
+    ///
+    /// engine.eval::<INT>("calc_area(6, 7)")?;
+    /// }
+    /// assert_eq!(warnings, ["(1:1): 'calc_area' is deprecated, use 'area'"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "sync"))]
+    pub fn on_fn_alias(&mut self, callback: impl FnMut(&str, &str, Option<&str>, Position) + 'e) {
+        self.on_fn_alias = Some(Box::new(callback));
+    }
+
+    /// Set the `ENV` object map, a read-only variable visible to all scripts (including inside
+    /// script-defined functions) evaluated from now on.  This is the place for build or runtime
+    /// configuration which scripts need, instead of pushing it into every `Scope`.
+    ///
+    /// Scripts only see the keys put into the map by the host: nothing from the process
+    /// environment is exposed unless the host adds it.  Scripts cannot modify `ENV` or its
+    /// properties (this fails with `ErrorAssignmentToConstant`), and changes to a copy of it
+    /// (e.g. `let env = ENV;`) are not visible to other scripts.  A script variable named `ENV`
+    /// shadows it.
+    ///
+    /// Not available under the `no_object` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, EvalAltResult, Map};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// let mut env = Map::new();
+    /// env.insert("MODE".into(), Box::new("release".to_string()));
+    /// engine.set_env(env);
+    ///
+    /// engine.set_env_var("BUILD", 42_i64);
+    ///
+    /// assert_eq!(engine.eval::<String>("ENV.MODE")?, "release");
+    /// assert_eq!(engine.eval::<i64>(r#"fn build() { ENV.BUILD } build()"#)?, 42);
+    ///
+    /// assert!(matches!(
+    ///     *engine.eval::<()>("ENV.MODE = 1").expect_err("should error"),
+    ///     EvalAltResult::ErrorAssignmentToConstant(..)
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_object"))]
+    pub fn set_env(&mut self, env: Map) {
+        self.globals
+            .insert(KEYWORD_ENV.into(), (Box::new(env) as Dynamic).into());
+    }
+
+    /// Register a constant visible to all scripts (and script-defined functions) evaluated from
+    /// now on, without pushing it into every `Scope` - e.g. a version number or a feature flag.
+    ///
+    /// Like `ENV`, global constants cannot be modified by scripts (this fails with
+    /// `ErrorAssignmentToConstant`), and a script variable (or function parameter) of the same name
+    /// shadows them.  Global constants of primitive types (integers, floating-point numbers,
+    /// characters, strings and booleans) also take part in constant folding when scripts are
+    /// optimized, so changing their values does not affect scripts compiled earlier.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, INT};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_global_constant("VERSION", 3 as INT);
+    /// engine.register_global_constant("BETA", false);
+    ///
+    /// assert_eq!(engine.eval::<INT>("if BETA { 0 } else { VERSION * 10 }")?, 30);
+    /// assert_eq!(engine.eval::<INT>("let VERSION = 1; VERSION")?, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_global_constant<T: Any + Clone>(&mut self, name: &str, value: T) {
+        self.globals
+            .insert(name.into(), (Box::new(value) as Dynamic).into());
+    }
+
+    /// Register a module of constants, native functions and sub-modules provided by the host,
+    /// under a namespace.  Scripts access its contents via namespace-qualified names
+    /// (e.g. `math::consts::PI`) without importing it.
+    ///
+    /// Like global constants, the constants of the module cannot be modified by scripts.
+    /// Registering a module again with the same name adds to (or replaces) the contents
+    /// registered earlier.
+    ///
+    /// Not available under the `no_module` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, NativeModule, RegisterFn, INT};
+    ///
+    /// let mut units = NativeModule::new();
+    /// units.set_constant("KB", 1024 as INT);
+    /// units.register_fn("kb", |x: INT| x * 1024);
+    ///
+    /// let mut engine = Engine::new();
+    /// engine.register_module("units", units);
+    ///
+    /// assert_eq!(engine.eval::<INT>("units::kb(2) / units::KB")?, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_module"))]
+    pub fn register_module(&mut self, name: &str, module: NativeModule) {
+        module.register_into(self, name);
+    }
+
+    /// Set a property of the `ENV` object map, creating the map if it is not set.
+    /// See `set_env`.
+    ///
+    /// Not available under the `no_object` feature.
+    #[cfg(not(feature = "no_object"))]
+    pub fn set_env_var<T: Any + Clone>(&mut self, key: &str, value: T) {
+        let env = self
+            .globals
+            .entry(KEYWORD_ENV.into())
+            .or_insert_with(|| (Box::new(Map::new()) as Dynamic).into());
+
+        match env.downcast_mut::<Map>() {
+            Some(env) => {
+                env.insert(key.into(), Box::new(value));
+            }
+            None => panic!("ENV should be an object map"),
+        }
+    }
+
+    /// Set the capabilities granted to scripts evaluated from now on.
+    ///
+    /// Calling a native function tagged (via `tag_fn`) with a capability that is not granted fails
+    /// with `ErrorForbiddenFunction`. Pass `None` (the default) to grant all capabilities.
+    pub fn set_capabilities(&mut self, capabilities: Option<&[&str]>) {
+        self.capabilities =
+            capabilities.map(|caps| caps.iter().map(|cap| cap.to_string()).collect());
+    }
 }
@@ -0,0 +1,141 @@
+//! Module which registers the array math package: arithmetic between arrays and numbers
+//! broadcast over the elements of arrays, and statistics over arrays of numbers.
+
+use crate::any::Dynamic;
+use crate::engine::{Array, Engine};
+use crate::fn_register::RegisterResultFn;
+use crate::parser::{Position, FLOAT, INT};
+use crate::result::EvalAltResult;
+
+use crate::stdlib::{boxed::Box, format, ops::Add, ops::Div, ops::Mul, ops::Sub, vec::Vec};
+
+/// Get an element of an array of numbers as a floating-point number.
+fn to_float(value: &Dynamic, index: usize) -> Result<FLOAT, Box<EvalAltResult>> {
+    if let Some(&x) = value.downcast_ref::<FLOAT>() {
+        Ok(x)
+    } else if let Some(&x) = value.downcast_ref::<INT>() {
+        Ok(x as FLOAT)
+    } else {
+        Err(Box::new(EvalAltResult::ErrorArithmetic(
+            format!("Array element {} is not a number", index),
+            None,
+            Position::none(),
+        )))
+    }
+}
+
+/// Get all the elements of an array of numbers as floating-point numbers.
+fn to_floats(list: &Array) -> Result<Vec<FLOAT>, Box<EvalAltResult>> {
+    list.iter()
+        .enumerate()
+        .map(|(index, value)| to_float(value, index))
+        .collect()
+}
+
+/// Apply a function to each element of an array of numbers.
+fn broadcast(list: &Array, f: impl Fn(FLOAT) -> FLOAT) -> Result<Array, Box<EvalAltResult>> {
+    list.iter()
+        .enumerate()
+        .map(|(index, value)| Ok(Box::new(f(to_float(value, index)?)) as Dynamic))
+        .collect()
+}
+
+/// Make sure that two arrays have the same length.
+fn check_lengths(x: &Array, y: &Array) -> Result<(), Box<EvalAltResult>> {
+    if x.len() == y.len() {
+        Ok(())
+    } else {
+        Err(Box::new(EvalAltResult::ErrorArithmetic(
+            format!("Arrays have different lengths: {} and {}", x.len(), y.len()),
+            None,
+            Position::none(),
+        )))
+    }
+}
+
+/// Apply a function to each pair of elements of two arrays of numbers of the same length.
+fn zip(
+    x: &Array,
+    y: &Array,
+    f: impl Fn(FLOAT, FLOAT) -> FLOAT,
+) -> Result<Array, Box<EvalAltResult>> {
+    check_lengths(x, y)?;
+
+    x.iter()
+        .zip(y.iter())
+        .enumerate()
+        .map(|(index, (a, b))| {
+            let result = f(to_float(a, index)?, to_float(b, index)?);
+            Ok(Box::new(result) as Dynamic)
+        })
+        .collect()
+}
+
+/// Get the elements of an array of numbers, failing if the array is empty.
+fn non_empty(list: &Array, fn_name: &str) -> Result<Vec<FLOAT>, Box<EvalAltResult>> {
+    if list.is_empty() {
+        return Err(Box::new(EvalAltResult::ErrorArithmetic(
+            format!("Cannot calculate {} of an empty array", fn_name),
+            None,
+            Position::none(),
+        )));
+    }
+
+    to_floats(list)
+}
+
+impl Engine<'_> {
+    /// Register the array math package.
+    pub(crate) fn register_array_math(&mut self) {
+        macro_rules! reg_op {
+            ($op:expr, $func:expr, $($scalar:ty),*) => (
+                $(
+                    self.register_result_fn($op, |x: &mut Array, y: $scalar| {
+                        broadcast(x, |a| $func(a, y as FLOAT))
+                    });
+                    self.register_result_fn($op, |x: $scalar, y: Array| {
+                        broadcast(&y, |b| $func(x as FLOAT, b))
+                    });
+                )*
+            )
+        }
+
+        reg_op!("+", FLOAT::add, FLOAT, INT);
+        reg_op!("-", FLOAT::sub, FLOAT, INT);
+        reg_op!("*", FLOAT::mul, FLOAT, INT);
+        reg_op!("/", FLOAT::div, FLOAT, INT);
+
+        // '+' between two arrays keeps concatenating them
+        self.register_result_fn("-", |x: &mut Array, y: Array| zip(x, &y, FLOAT::sub));
+        self.register_result_fn("*", |x: &mut Array, y: Array| zip(x, &y, FLOAT::mul));
+        self.register_result_fn("/", |x: &mut Array, y: Array| zip(x, &y, FLOAT::div));
+
+        self.register_result_fn("sum", |list: &mut Array| {
+            Ok(to_floats(list)?.into_iter().sum::<FLOAT>())
+        });
+        self.register_result_fn("mean", |list: &mut Array| {
+            let items = non_empty(list, "mean")?;
+            Ok(items.iter().sum::<FLOAT>() / items.len() as FLOAT)
+        });
+        self.register_result_fn("min", |list: &mut Array| {
+            Ok(non_empty(list, "min")?
+                .into_iter()
+                .fold(FLOAT::INFINITY, FLOAT::min))
+        });
+        self.register_result_fn("max", |list: &mut Array| {
+            Ok(non_empty(list, "max")?
+                .into_iter()
+                .fold(FLOAT::NEG_INFINITY, FLOAT::max))
+        });
+        self.register_result_fn("dot", |x: &mut Array, y: Array| {
+            check_lengths(x, &y)?;
+
+            x.iter()
+                .zip(y.iter())
+                .enumerate()
+                .try_fold(0.0, |sum, (index, (a, b))| {
+                    Ok(sum + to_float(a, index)? * to_float(b, index)?)
+                })
+        });
+    }
+}
@@ -0,0 +1,42 @@
+//! Module which lets scripts work with read-only data passed in by the host without copying,
+//! e.g. `&'static str` tables or `Arc<[u8]>` buffers.
+//!
+//! Such values are only copied into an owned `String` (or `Blob`) when a script needs one:
+//! when no function accepts the borrowed type directly, or when the value is modified in place.
+
+use crate::any::{Any, Dynamic, Variant};
+
+use crate::stdlib::string::{String, ToString};
+
+#[cfg(not(feature = "no_index"))]
+use crate::engine::Blob;
+
+/// Get an owned copy (i.e. a `String` or `Blob`) of a string or BLOB borrowed from the host.
+/// Returns `None` for all other values, including owned strings and BLOB's.
+pub(crate) fn to_owned_value(value: &Variant) -> Option<Dynamic> {
+    if value.is::<String>() {
+        return None;
+    }
+    if let Some(s) = value.as_str() {
+        return Some(s.to_string().into_dynamic());
+    }
+
+    #[cfg(not(feature = "no_index"))]
+    {
+        if value.is::<Blob>() {
+            return None;
+        }
+        if let Some(b) = value.as_bytes() {
+            return Some(b.to_vec().into_dynamic());
+        }
+    }
+
+    None
+}
+
+/// Replace a string or BLOB borrowed from the host with an owned copy, in place.
+pub(crate) fn make_owned(value: &mut Dynamic) {
+    if let Some(owned) = to_owned_value(value.as_ref()) {
+        *value = owned;
+    }
+}
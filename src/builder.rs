@@ -0,0 +1,348 @@
+//! Module which defines `EngineBuilder`, for configuring an `Engine` in one chained expression.
+
+use crate::any::Any;
+use crate::builtin::OverflowMode;
+use crate::dialect::Dialect;
+use crate::engine::Engine;
+use crate::parser::{Position, Shadowing};
+use crate::scope::Scope;
+
+#[cfg(not(feature = "no_object"))]
+use crate::engine::Map;
+
+#[cfg(not(feature = "no_module"))]
+use crate::module::{ModuleResolver, NativeModule};
+
+#[cfg(not(feature = "no_optimize"))]
+use crate::optimize::OptimizationLevel;
+
+/// A builder which configures an `Engine` via chained method calls, then hands it over via `build`.
+///
+/// Each method does the same as the `Engine` method of a similar name, e.g. `with_max_call_levels`
+/// calls `Engine::set_max_call_levels`.  For anything else (e.g. registering functions), use
+/// `configure`.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Engine, RegisterFn, INT};
+///
+/// let mut output = Vec::new();
+/// {
+/// let mut engine = Engine::builder()
+///     .with_max_call_levels(32)
+///     .with_disabled_symbol("eval")
+///     .with_global_constant("LIMIT", 10 as INT)
+///     .configure(|engine| engine.register_fn("double", |x: INT| x * 2))
+///     .on_print(|s| output.push(s.to_string()))
+///     .build();
+///
+/// engine.consume("print(double(LIMIT))")?;
+/// assert!(engine.compile(r#"eval("1")"#).is_err());
+/// }
+/// assert_eq!(output, ["20"]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct EngineBuilder<'e>(Engine<'e>);
+
+impl Default for EngineBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'e> Engine<'e> {
+    /// Start configuring a new `Engine` (as created by `Engine::new`) via an `EngineBuilder`.
+    pub fn builder() -> EngineBuilder<'e> {
+        EngineBuilder::new()
+    }
+}
+
+impl<'e> EngineBuilder<'e> {
+    /// Start configuring a new `Engine`, as created by `Engine::new`.
+    pub fn new() -> Self {
+        Self(Engine::new())
+    }
+
+    /// Start configuring a new `Engine` with minimal configurations, as created by `Engine::new_raw`.
+    pub fn new_raw() -> Self {
+        Self(Engine::new_raw())
+    }
+
+    /// Finish configuring and return the `Engine`.
+    pub fn build(self) -> Engine<'e> {
+        self.0
+    }
+
+    /// Configure the `Engine` directly, e.g. to register functions or types.
+    pub fn configure(mut self, f: impl FnOnce(&mut Engine<'e>)) -> Self {
+        f(&mut self.0);
+        self
+    }
+
+    /// Set the optimization level.  See `Engine::set_optimization_level`.
+    ///
+    /// Not available under the `no_optimize` feature.
+    #[cfg(not(feature = "no_optimize"))]
+    pub fn with_optimization_level(mut self, optimization_level: OptimizationLevel) -> Self {
+        self.0.set_optimization_level(optimization_level);
+        self
+    }
+
+    /// Set the maximum levels of function calls.  See `Engine::set_max_call_levels`.
+    pub fn with_max_call_levels(mut self, levels: usize) -> Self {
+        self.0.set_max_call_levels(levels);
+        self
+    }
+
+    /// Set the maximum number of bytes of native stack.  See `Engine::set_max_stack_size`.
+    pub fn with_max_stack_size(mut self, bytes: usize) -> Self {
+        self.0.set_max_stack_size(bytes);
+        self
+    }
+
+    /// Set the maximum nesting depth of expressions and statements.
+    /// See `Engine::set_max_expr_depth`.
+    pub fn with_max_expr_depth(mut self, depth: usize) -> Self {
+        self.0.set_max_expr_depth(depth);
+        self
+    }
+
+    /// Set the maximum number of bytes in a BLOB.  See `Engine::set_max_blob_size`.
+    ///
+    /// Not available under the `no_index` feature.
+    #[cfg(not(feature = "no_index"))]
+    pub fn with_max_blob_size(mut self, bytes: usize) -> Self {
+        self.0.set_max_blob_size(bytes);
+        self
+    }
+
+    /// Set the number of decimal places shown for floating-point numbers.
+    /// See `Engine::set_float_precision`.
+    ///
+    /// Not available under the `no_float` feature.
+    #[cfg(not(feature = "no_float"))]
+    pub fn with_float_precision(mut self, precision: Option<usize>) -> Self {
+        self.0.set_float_precision(precision);
+        self
+    }
+
+    /// Control how integer arithmetic handles overflow.  See `Engine::set_overflow_mode`.
+    pub fn with_overflow_mode(mut self, mode: OverflowMode) -> Self {
+        self.0.set_overflow_mode(mode);
+        self
+    }
+
+    /// Control whether doc-comments are kept.  See `Engine::enable_doc_comments`.
+    ///
+    /// Not available under the `no_function` feature.
+    #[cfg(not(feature = "no_function"))]
+    pub fn with_doc_comments(mut self, enable: bool) -> Self {
+        self.0.enable_doc_comments(enable);
+        self
+    }
+
+    /// Control whether all comments are kept.  See `Engine::enable_comments`.
+    pub fn with_comments(mut self, enable: bool) -> Self {
+        self.0.enable_comments(enable);
+        self
+    }
+
+    /// Control whether results are discarded when evaluating for `()`.
+    /// See `Engine::enable_unit_discard`.
+    pub fn with_unit_discard(mut self, enable: bool) -> Self {
+        self.0.enable_unit_discard(enable);
+        self
+    }
+
+    /// Control whether integer arguments are widened.  See `Engine::enable_int_widening`.
+    pub fn with_int_widening(mut self, enable: bool) -> Self {
+        self.0.enable_int_widening(enable);
+        self
+    }
+
+    /// Disable a keyword, operator or identifier.  See `Engine::disable_symbol`.
+    pub fn with_disabled_symbol(mut self, symbol: &str) -> Self {
+        self.0.disable_symbol(symbol);
+        self
+    }
+
+    /// Reserve names.  See `Engine::set_reserved_symbols`.
+    pub fn with_reserved_symbols<S: AsRef<str>>(
+        mut self,
+        symbols: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.0.set_reserved_symbols(symbols);
+        self
+    }
+
+    /// Control whether variables may be redeclared.  See `Engine::set_shadowing`.
+    pub fn with_shadowing(mut self, shadowing: Shadowing) -> Self {
+        self.0.set_shadowing(shadowing);
+        self
+    }
+
+    /// Restrict scripts to a language dialect.  See `Engine::set_dialect`.
+    pub fn with_dialect(mut self, dialect: &Dialect) -> Self {
+        self.0.set_dialect(dialect);
+        self
+    }
+
+    /// Seed the random number generator.  See `Engine::set_rand_seed`.
+    ///
+    /// Only available under the `rand` feature.
+    #[cfg(feature = "rand")]
+    pub fn with_rand_seed(mut self, seed: u64) -> Self {
+        self.0.set_rand_seed(seed);
+        self
+    }
+
+    /// Set the module resolution service.  See `Engine::set_module_resolver`.
+    ///
+    /// Not available under the `no_module` feature.
+    #[cfg(not(feature = "no_module"))]
+    pub fn with_module_resolver(mut self, resolver: Option<impl ModuleResolver + 'static>) -> Self {
+        self.0.set_module_resolver(resolver);
+        self
+    }
+
+    /// Control whether imports are validated during compilation.
+    /// See `Engine::enable_import_validation`.
+    ///
+    /// Not available under the `no_module` feature.
+    #[cfg(not(feature = "no_module"))]
+    pub fn with_import_validation(mut self, enable: bool) -> Self {
+        self.0.enable_import_validation(enable);
+        self
+    }
+
+    /// Register a module provided by the host.  See `Engine::register_module`.
+    ///
+    /// Not available under the `no_module` feature.
+    #[cfg(not(feature = "no_module"))]
+    pub fn with_module(mut self, name: &str, module: NativeModule) -> Self {
+        self.0.register_module(name, module);
+        self
+    }
+
+    /// Register a constant visible to all scripts.  See `Engine::register_global_constant`.
+    pub fn with_global_constant<T: Any + Clone>(mut self, name: &str, value: T) -> Self {
+        self.0.register_global_constant(name, value);
+        self
+    }
+
+    /// Set the `ENV` object map.  See `Engine::set_env`.
+    ///
+    /// Not available under the `no_object` feature.
+    #[cfg(not(feature = "no_object"))]
+    pub fn with_env(mut self, env: Map) -> Self {
+        self.0.set_env(env);
+        self
+    }
+
+    /// Set a property of the `ENV` object map.  See `Engine::set_env_var`.
+    ///
+    /// Not available under the `no_object` feature.
+    #[cfg(not(feature = "no_object"))]
+    pub fn with_env_var<T: Any + Clone>(mut self, key: &str, value: T) -> Self {
+        self.0.set_env_var(key, value);
+        self
+    }
+
+    /// Register an alias for a native function.  See `Engine::register_fn_alias`.
+    pub fn with_fn_alias(mut self, alias: &str, name: &str) -> Self {
+        self.0.register_fn_alias(alias, name);
+        self
+    }
+
+    /// Set the capabilities granted to scripts.  See `Engine::set_capabilities`.
+    pub fn with_capabilities(mut self, capabilities: Option<&[&str]>) -> Self {
+        self.0.set_capabilities(capabilities);
+        self
+    }
+
+    /// Override the default action of `print`.  See `Engine::on_print`.
+    #[cfg(feature = "sync")]
+    pub fn on_print(mut self, callback: impl FnMut(&str) + Send + Sync + 'e) -> Self {
+        self.0.on_print(callback);
+        self
+    }
+    /// Override the default action of `print`.  See `Engine::on_print`.
+    #[cfg(not(feature = "sync"))]
+    pub fn on_print(mut self, callback: impl FnMut(&str) + 'e) -> Self {
+        self.0.on_print(callback);
+        self
+    }
+
+    /// Override the default action of `debug`.  See `Engine::on_debug`.
+    #[cfg(feature = "sync")]
+    pub fn on_debug(
+        mut self,
+        callback: impl FnMut(&str, Option<&str>, Position) + Send + Sync + 'e,
+    ) -> Self {
+        self.0.on_debug(callback);
+        self
+    }
+    /// Override the default action of `debug`.  See `Engine::on_debug`.
+    #[cfg(not(feature = "sync"))]
+    pub fn on_debug(mut self, callback: impl FnMut(&str, Option<&str>, Position) + 'e) -> Self {
+        self.0.on_debug(callback);
+        self
+    }
+
+    /// Register a filter for variable definitions.  See `Engine::on_def_var`.
+    #[cfg(feature = "sync")]
+    pub fn on_def_var(
+        mut self,
+        callback: impl FnMut(&str, bool, usize, &Scope) -> bool + Send + Sync + 'e,
+    ) -> Self {
+        self.0.on_def_var(callback);
+        self
+    }
+    /// Register a filter for variable definitions.  See `Engine::on_def_var`.
+    #[cfg(not(feature = "sync"))]
+    pub fn on_def_var(
+        mut self,
+        callback: impl FnMut(&str, bool, usize, &Scope) -> bool + 'e,
+    ) -> Self {
+        self.0.on_def_var(callback);
+        self
+    }
+
+    /// Register a callback auditing calls to native functions.  See `Engine::on_call`.
+    #[cfg(feature = "sync")]
+    pub fn on_call(
+        mut self,
+        callback: impl FnMut(&str, &[&str], Position) -> bool + Send + Sync + 'e,
+    ) -> Self {
+        self.0.on_call(callback);
+        self
+    }
+    /// Register a callback auditing calls to native functions.  See `Engine::on_call`.
+    #[cfg(not(feature = "sync"))]
+    pub fn on_call(mut self, callback: impl FnMut(&str, &[&str], Position) -> bool + 'e) -> Self {
+        self.0.on_call(callback);
+        self
+    }
+
+    /// Register a callback notified of calls via aliases.  See `Engine::on_fn_alias`.
+    #[cfg(feature = "sync")]
+    pub fn on_fn_alias(
+        mut self,
+        callback: impl FnMut(&str, &str, Option<&str>, Position) + Send + Sync + 'e,
+    ) -> Self {
+        self.0.on_fn_alias(callback);
+        self
+    }
+    /// Register a callback notified of calls via aliases.  See `Engine::on_fn_alias`.
+    #[cfg(not(feature = "sync"))]
+    pub fn on_fn_alias(
+        mut self,
+        callback: impl FnMut(&str, &str, Option<&str>, Position) + 'e,
+    ) -> Self {
+        self.0.on_fn_alias(callback);
+        self
+    }
+}
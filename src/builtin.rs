@@ -1,14 +1,50 @@
 //! Helper module that allows registration of the _core library_ and
 //! _standard library_ of utility functions.
 
-use crate::any::{Any, Dynamic};
-use crate::engine::{Engine, FUNC_TO_STRING, KEYWORD_DEBUG, KEYWORD_PRINT};
+use crate::any::{Any, Dynamic, Variant};
+use crate::engine::{
+    Engine, FnAny, FnSpec, SharedFnAny, FUNC_TO_STRING, KEYWORD_DEBUG, KEYWORD_PRINT,
+};
+
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_stdlib"))]
+use crate::{engine::FnCallArgs, stdlib::mem};
+
+#[cfg(any(
+    not(feature = "no_float"),
+    all(not(feature = "no_index"), not(feature = "no_stdlib")),
+    feature = "jit"
+))]
+use crate::stdlib::any::TypeId;
+
+#[cfg(feature = "rand")]
+use crate::engine::with_rng;
 use crate::fn_register::{RegisterDynamicFn, RegisterFn, RegisterResultFn};
 use crate::parser::{Position, INT};
-use crate::result::EvalAltResult;
+use crate::result::{ArithmeticOp, EvalAltResult};
+use crate::shared::SharedValue;
+
+#[cfg(not(feature = "no_std"))]
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use crate::stdlib::time::Instant;
+
+#[cfg(not(feature = "no_std"))]
+#[cfg(target_arch = "wasm32")]
+#[cfg(feature = "wasm-bindgen")]
+pub(crate) use instant::Instant;
+
+#[cfg(not(feature = "no_stdlib"))]
+use crate::parser::INT_BITS;
+
+#[cfg(not(feature = "no_stdlib"))]
+use crate::stdlib::{cmp::Ordering, convert::TryFrom, fmt::Write};
+
+#[cfg(not(feature = "no_index"))]
+use crate::engine::{Array, Blob};
 
 #[cfg(not(feature = "no_index"))]
-use crate::engine::Array;
+#[cfg(not(feature = "no_stdlib"))]
+use crate::memo::MemoKey;
 
 #[cfg(not(feature = "no_object"))]
 use crate::engine::Map;
@@ -16,21 +52,57 @@ use crate::engine::Map;
 #[cfg(not(feature = "no_float"))]
 use crate::parser::FLOAT;
 
+#[cfg(feature = "unchecked")]
+use crate::stdlib::ops::{Shl, Shr};
+
 use num_traits::{
-    identities::Zero, CheckedAdd, CheckedDiv, CheckedMul, CheckedNeg, CheckedRem, CheckedShl,
-    CheckedShr, CheckedSub,
+    identities::Zero, Bounded, CheckedAdd, CheckedDiv, CheckedMul, CheckedNeg, CheckedRem,
+    CheckedShl, CheckedShr, CheckedSub, SaturatingAdd, SaturatingMul, SaturatingSub, WrappingAdd,
+    WrappingMul, WrappingNeg, WrappingSub,
 };
 
 use crate::stdlib::{
     boxed::Box,
+    char,
+    collections::HashSet,
     fmt::{Debug, Display},
     format,
-    ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Range, Rem, Shl, Shr, Sub},
+    ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Range, Rem, Sub},
     string::{String, ToString},
     vec::Vec,
     {i32, i64, u32},
 };
 
+/// How the arithmetic operators on integers handle overflow.  Set via `Engine::set_overflow_mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverflowMode {
+    /// Overflow is an error (the default).
+    /// Under the `unchecked` feature, overflow is not checked at all and may panic.
+    Checked,
+    /// Results wrap around at the bounds of the type, e.g. `i64::MAX + 1 == i64::MIN`.
+    Wrapping,
+    /// Results stop at the bounds of the type, e.g. `i64::MAX + 1 == i64::MAX`.
+    Saturating,
+}
+
+/// Are two registered functions the same function?
+fn is_same_fn(a: &SharedFnAny, b: &SharedFnAny) -> bool {
+    // Only compare the addresses, as the same function may be referred to via different vtables
+    (&**a as *const FnAny).cast::<u8>() == (&**b as *const FnAny).cast::<u8>()
+}
+
+/// Make an error for an arithmetic operator (or numeric function) failing on its operands.
+fn op_err(msg: String, operator: &str, operands: Vec<Dynamic>) -> Box<EvalAltResult> {
+    Box::new(EvalAltResult::ErrorArithmetic(
+        msg,
+        Some(Box::new(ArithmeticOp {
+            operator: operator.into(),
+            operands,
+        })),
+        Position::none(),
+    ))
+}
+
 macro_rules! reg_op {
     ($self:expr, $x:expr, $op:expr, $( $y:ty ),*) => (
         $(
@@ -39,11 +111,10 @@ macro_rules! reg_op {
     )
 }
 
-#[cfg(not(feature = "unchecked"))]
 macro_rules! reg_op_result {
     ($self:expr, $x:expr, $op:expr, $( $y:ty ),*) => (
         $(
-            $self.register_result_fn($x, $op as fn(x: $y, y: $y)->Result<$y,EvalAltResult>);
+            $self.register_result_fn($x, $op as fn(x: $y, y: $y)->Result<$y,Box<EvalAltResult>>);
         )*
     )
 }
@@ -52,130 +123,252 @@ macro_rules! reg_op_result {
 macro_rules! reg_op_result1 {
     ($self:expr, $x:expr, $op:expr, $v:ty, $( $y:ty ),*) => (
         $(
-            $self.register_result_fn($x, $op as fn(x: $y, y: $v)->Result<$y,EvalAltResult>);
+            $self.register_result_fn($x, $op as fn(x: $y, y: $v)->Result<$y,Box<EvalAltResult>>);
         )*
     )
 }
 
+// Checked add
+#[cfg(not(feature = "unchecked"))]
+fn add<T: Any + Display + CheckedAdd>(x: T, y: T) -> Result<T, Box<EvalAltResult>> {
+    x.checked_add(&y).ok_or_else(|| {
+        op_err(
+            format!("Addition overflow: {} + {}", x, y),
+            "+",
+            vec![x.into_dynamic(), y.into_dynamic()],
+        )
+    })
+}
+// Checked subtract
+#[cfg(not(feature = "unchecked"))]
+fn sub<T: Any + Display + CheckedSub>(x: T, y: T) -> Result<T, Box<EvalAltResult>> {
+    x.checked_sub(&y).ok_or_else(|| {
+        op_err(
+            format!("Subtraction underflow: {} - {}", x, y),
+            "-",
+            vec![x.into_dynamic(), y.into_dynamic()],
+        )
+    })
+}
+// Checked multiply
+#[cfg(not(feature = "unchecked"))]
+fn mul<T: Any + Display + CheckedMul>(x: T, y: T) -> Result<T, Box<EvalAltResult>> {
+    x.checked_mul(&y).ok_or_else(|| {
+        op_err(
+            format!("Multiplication overflow: {} * {}", x, y),
+            "*",
+            vec![x.into_dynamic(), y.into_dynamic()],
+        )
+    })
+}
+// Checked divide
+#[cfg(not(feature = "unchecked"))]
+fn div<T>(x: T, y: T) -> Result<T, Box<EvalAltResult>>
+where
+    T: Any + Display + CheckedDiv + PartialEq + Zero,
+{
+    // Detect division by zero
+    if y == T::zero() {
+        return Err(op_err(
+            format!("Division by zero: {} / {}", x, y),
+            "/",
+            vec![x.into_dynamic(), y.into_dynamic()],
+        ));
+    }
+
+    x.checked_div(&y).ok_or_else(|| {
+        op_err(
+            format!("Division overflow: {} / {}", x, y),
+            "/",
+            vec![x.into_dynamic(), y.into_dynamic()],
+        )
+    })
+}
+// Checked negative - e.g. -(i32::MIN) will overflow i32::MAX
+#[cfg(not(feature = "unchecked"))]
+fn neg<T: Any + Display + CheckedNeg>(x: T) -> Result<T, Box<EvalAltResult>> {
+    x.checked_neg().ok_or_else(|| {
+        op_err(
+            format!("Negation overflow: -{}", x),
+            "-",
+            vec![x.into_dynamic()],
+        )
+    })
+}
+// Checked absolute
+#[cfg(not(feature = "unchecked"))]
+fn abs<T>(x: T) -> Result<T, Box<EvalAltResult>>
+where
+    T: Any + Display + CheckedNeg + PartialOrd + Zero,
+{
+    // FIX - We don't use Signed::abs() here because, contrary to documentation, it panics
+    //       when the number is ::MIN instead of returning ::MIN itself.
+    if x >= <T as Zero>::zero() {
+        Ok(x)
+    } else {
+        x.checked_neg().ok_or_else(|| {
+            op_err(
+                format!("Negation overflow: -{}", x),
+                "abs",
+                vec![x.into_dynamic()],
+            )
+        })
+    }
+}
+// Unchecked add - may panic on overflow
+#[cfg(any(feature = "unchecked", not(feature = "no_float")))]
+fn add_u<T: Add>(x: T, y: T) -> <T as Add>::Output {
+    x + y
+}
+// Unchecked subtract - may panic on underflow
+#[cfg(any(feature = "unchecked", not(feature = "no_float")))]
+fn sub_u<T: Sub>(x: T, y: T) -> <T as Sub>::Output {
+    x - y
+}
+// Unchecked multiply - may panic on overflow
+#[cfg(any(feature = "unchecked", not(feature = "no_float")))]
+fn mul_u<T: Mul>(x: T, y: T) -> <T as Mul>::Output {
+    x * y
+}
+// Unchecked divide - may panic when dividing by zero
+#[cfg(any(feature = "unchecked", not(feature = "no_float")))]
+fn div_u<T: Div>(x: T, y: T) -> <T as Div>::Output {
+    x / y
+}
+// Unchecked negative - may panic on overflow
+#[cfg(any(feature = "unchecked", not(feature = "no_float")))]
+fn neg_u<T: Neg>(x: T) -> <T as Neg>::Output {
+    -x
+}
+// Unchecked absolute - may panic on overflow
+#[cfg(any(feature = "unchecked", not(feature = "no_float")))]
+fn abs_u<T>(x: T) -> <T as Neg>::Output
+where
+    T: Neg + PartialOrd + Default + Into<<T as Neg>::Output>,
+{
+    // Numbers should default to zero
+    if x < Default::default() {
+        -x
+    } else {
+        x.into()
+    }
+}
+// Checked modulo
+#[cfg(not(feature = "unchecked"))]
+fn modulo<T: Any + Display + CheckedRem>(x: T, y: T) -> Result<T, Box<EvalAltResult>> {
+    x.checked_rem(&y).ok_or_else(|| {
+        op_err(
+            format!("Modulo division by zero or overflow: {} % {}", x, y),
+            "%",
+            vec![x.into_dynamic(), y.into_dynamic()],
+        )
+    })
+}
+// Unchecked modulo - may panic if dividing by zero
+#[cfg(any(feature = "unchecked", not(feature = "no_float")))]
+fn modulo_u<T: Rem>(x: T, y: T) -> <T as Rem>::Output {
+    x % y
+}
+// Wrapping add - overflows wrap around
+fn add_w<T: WrappingAdd>(x: T, y: T) -> T {
+    x.wrapping_add(&y)
+}
+// Wrapping subtract - underflows wrap around
+fn sub_w<T: WrappingSub>(x: T, y: T) -> T {
+    x.wrapping_sub(&y)
+}
+// Wrapping multiply - overflows wrap around
+fn mul_w<T: WrappingMul>(x: T, y: T) -> T {
+    x.wrapping_mul(&y)
+}
+// Wrapping divide - e.g. i32::MIN / -1 wraps around to i32::MIN
+fn div_w<T>(x: T, y: T) -> Result<T, Box<EvalAltResult>>
+where
+    T: Any + Display + CheckedDiv + PartialEq + Zero,
+{
+    // Division by zero is still an error
+    if y == T::zero() {
+        return Err(op_err(
+            format!("Division by zero: {} / {}", x, y),
+            "/",
+            vec![x.into_dynamic(), y.into_dynamic()],
+        ));
+    }
+
+    Ok(x.checked_div(&y).unwrap_or(x))
+}
+// Modulo not failing on overflow - e.g. i32::MIN % -1 is zero, whether wrapping or saturating
+fn modulo_nz<T>(x: T, y: T) -> Result<T, Box<EvalAltResult>>
+where
+    T: Any + Display + CheckedRem + PartialEq + Zero,
+{
+    // Division by zero is still an error
+    if y == T::zero() {
+        return Err(op_err(
+            format!("Modulo division by zero: {} % {}", x, y),
+            "%",
+            vec![x.into_dynamic(), y.into_dynamic()],
+        ));
+    }
+
+    Ok(x.checked_rem(&y).unwrap_or_else(T::zero))
+}
+// Wrapping negative - e.g. -(i32::MIN) wraps around to i32::MIN
+fn neg_w<T: WrappingNeg>(x: T) -> T {
+    x.wrapping_neg()
+}
+// Wrapping absolute - e.g. abs(i32::MIN) wraps around to i32::MIN
+fn abs_w<T: WrappingNeg + PartialOrd + Zero>(x: T) -> T {
+    if x >= T::zero() {
+        x
+    } else {
+        x.wrapping_neg()
+    }
+}
+// Saturating add - overflows stop at the maximum (or minimum) value
+fn add_s<T: SaturatingAdd>(x: T, y: T) -> T {
+    x.saturating_add(&y)
+}
+// Saturating subtract - underflows stop at the minimum (or maximum) value
+fn sub_s<T: SaturatingSub>(x: T, y: T) -> T {
+    x.saturating_sub(&y)
+}
+// Saturating multiply - overflows stop at the maximum (or minimum) value
+fn mul_s<T: SaturatingMul>(x: T, y: T) -> T {
+    x.saturating_mul(&y)
+}
+// Saturating divide - e.g. i32::MIN / -1 stops at i32::MAX
+fn div_s<T>(x: T, y: T) -> Result<T, Box<EvalAltResult>>
+where
+    T: Any + Display + CheckedDiv + PartialEq + Zero + Bounded,
+{
+    // Division by zero is still an error
+    if y == T::zero() {
+        return Err(op_err(
+            format!("Division by zero: {} / {}", x, y),
+            "/",
+            vec![x.into_dynamic(), y.into_dynamic()],
+        ));
+    }
+
+    Ok(x.checked_div(&y).unwrap_or_else(T::max_value))
+}
+// Saturating negative - e.g. -(i32::MIN) stops at i32::MAX
+fn neg_s<T: CheckedNeg + Bounded>(x: T) -> T {
+    x.checked_neg().unwrap_or_else(T::max_value)
+}
+// Saturating absolute - e.g. abs(i32::MIN) stops at i32::MAX
+fn abs_s<T: CheckedNeg + Bounded + PartialOrd + Zero>(x: T) -> T {
+    if x >= T::zero() {
+        x
+    } else {
+        neg_s(x)
+    }
+}
+
 impl Engine<'_> {
     /// Register the core built-in library.
     pub(crate) fn register_core_lib(&mut self) {
-        // Checked add
-        #[cfg(not(feature = "unchecked"))]
-        fn add<T: Display + CheckedAdd>(x: T, y: T) -> Result<T, EvalAltResult> {
-            x.checked_add(&y).ok_or_else(|| {
-                EvalAltResult::ErrorArithmetic(
-                    format!("Addition overflow: {} + {}", x, y),
-                    Position::none(),
-                )
-            })
-        }
-        // Checked subtract
-        #[cfg(not(feature = "unchecked"))]
-        fn sub<T: Display + CheckedSub>(x: T, y: T) -> Result<T, EvalAltResult> {
-            x.checked_sub(&y).ok_or_else(|| {
-                EvalAltResult::ErrorArithmetic(
-                    format!("Subtraction underflow: {} - {}", x, y),
-                    Position::none(),
-                )
-            })
-        }
-        // Checked multiply
-        #[cfg(not(feature = "unchecked"))]
-        fn mul<T: Display + CheckedMul>(x: T, y: T) -> Result<T, EvalAltResult> {
-            x.checked_mul(&y).ok_or_else(|| {
-                EvalAltResult::ErrorArithmetic(
-                    format!("Multiplication overflow: {} * {}", x, y),
-                    Position::none(),
-                )
-            })
-        }
-        // Checked divide
-        #[cfg(not(feature = "unchecked"))]
-        fn div<T>(x: T, y: T) -> Result<T, EvalAltResult>
-        where
-            T: Display + CheckedDiv + PartialEq + Zero,
-        {
-            // Detect division by zero
-            if y == T::zero() {
-                return Err(EvalAltResult::ErrorArithmetic(
-                    format!("Division by zero: {} / {}", x, y),
-                    Position::none(),
-                ));
-            }
-
-            x.checked_div(&y).ok_or_else(|| {
-                EvalAltResult::ErrorArithmetic(
-                    format!("Division overflow: {} / {}", x, y),
-                    Position::none(),
-                )
-            })
-        }
-        // Checked negative - e.g. -(i32::MIN) will overflow i32::MAX
-        #[cfg(not(feature = "unchecked"))]
-        fn neg<T: Display + CheckedNeg>(x: T) -> Result<T, EvalAltResult> {
-            x.checked_neg().ok_or_else(|| {
-                EvalAltResult::ErrorArithmetic(
-                    format!("Negation overflow: -{}", x),
-                    Position::none(),
-                )
-            })
-        }
-        // Checked absolute
-        #[cfg(not(feature = "unchecked"))]
-        fn abs<T: Display + CheckedNeg + PartialOrd + Zero>(x: T) -> Result<T, EvalAltResult> {
-            // FIX - We don't use Signed::abs() here because, contrary to documentation, it panics
-            //       when the number is ::MIN instead of returning ::MIN itself.
-            if x >= <T as Zero>::zero() {
-                Ok(x)
-            } else {
-                x.checked_neg().ok_or_else(|| {
-                    EvalAltResult::ErrorArithmetic(
-                        format!("Negation overflow: -{}", x),
-                        Position::none(),
-                    )
-                })
-            }
-        }
-        // Unchecked add - may panic on overflow
-        #[cfg(any(feature = "unchecked", not(feature = "no_float")))]
-        fn add_u<T: Add>(x: T, y: T) -> <T as Add>::Output {
-            x + y
-        }
-        // Unchecked subtract - may panic on underflow
-        #[cfg(any(feature = "unchecked", not(feature = "no_float")))]
-        fn sub_u<T: Sub>(x: T, y: T) -> <T as Sub>::Output {
-            x - y
-        }
-        // Unchecked multiply - may panic on overflow
-        #[cfg(any(feature = "unchecked", not(feature = "no_float")))]
-        fn mul_u<T: Mul>(x: T, y: T) -> <T as Mul>::Output {
-            x * y
-        }
-        // Unchecked divide - may panic when dividing by zero
-        #[cfg(any(feature = "unchecked", not(feature = "no_float")))]
-        fn div_u<T: Div>(x: T, y: T) -> <T as Div>::Output {
-            x / y
-        }
-        // Unchecked negative - may panic on overflow
-        #[cfg(any(feature = "unchecked", not(feature = "no_float")))]
-        fn neg_u<T: Neg>(x: T) -> <T as Neg>::Output {
-            -x
-        }
-        // Unchecked absolute - may panic on overflow
-        #[cfg(any(feature = "unchecked", not(feature = "no_float")))]
-        fn abs_u<T>(x: T) -> <T as Neg>::Output
-        where
-            T: Neg + PartialOrd + Default + Into<<T as Neg>::Output>,
-        {
-            // Numbers should default to zero
-            if x < Default::default() {
-                -x
-            } else {
-                x.into()
-            }
-        }
-
         // Comparison operators
         fn lt<T: PartialOrd>(x: T, y: T) -> bool {
             x < y
@@ -220,37 +413,41 @@ impl Engine<'_> {
 
         // Checked left-shift
         #[cfg(not(feature = "unchecked"))]
-        fn shl<T: Display + CheckedShl>(x: T, y: INT) -> Result<T, EvalAltResult> {
+        fn shl<T: Any + Display + CheckedShl>(x: T, y: INT) -> Result<T, Box<EvalAltResult>> {
             // Cannot shift by a negative number of bits
             if y < 0 {
-                return Err(EvalAltResult::ErrorArithmetic(
+                return Err(op_err(
                     format!("Left-shift by a negative number: {} << {}", x, y),
-                    Position::none(),
+                    "<<",
+                    vec![x.into_dynamic(), y.into_dynamic()],
                 ));
             }
 
             CheckedShl::checked_shl(&x, y as u32).ok_or_else(|| {
-                EvalAltResult::ErrorArithmetic(
+                op_err(
                     format!("Left-shift by too many bits: {} << {}", x, y),
-                    Position::none(),
+                    "<<",
+                    vec![x.into_dynamic(), y.into_dynamic()],
                 )
             })
         }
         // Checked right-shift
         #[cfg(not(feature = "unchecked"))]
-        fn shr<T: Display + CheckedShr>(x: T, y: INT) -> Result<T, EvalAltResult> {
+        fn shr<T: Any + Display + CheckedShr>(x: T, y: INT) -> Result<T, Box<EvalAltResult>> {
             // Cannot shift by a negative number of bits
             if y < 0 {
-                return Err(EvalAltResult::ErrorArithmetic(
+                return Err(op_err(
                     format!("Right-shift by a negative number: {} >> {}", x, y),
-                    Position::none(),
+                    ">>",
+                    vec![x.into_dynamic(), y.into_dynamic()],
                 ));
             }
 
             CheckedShr::checked_shr(&x, y as u32).ok_or_else(|| {
-                EvalAltResult::ErrorArithmetic(
-                    format!("Right-shift by too many bits: {} % {}", x, y),
-                    Position::none(),
+                op_err(
+                    format!("Right-shift by too many bits: {} >> {}", x, y),
+                    ">>",
+                    vec![x.into_dynamic(), y.into_dynamic()],
                 )
             })
         }
@@ -264,41 +461,29 @@ impl Engine<'_> {
         fn shr_u<T: Shr<T>>(x: T, y: T) -> <T as Shr<T>>::Output {
             x.shr(y)
         }
-        // Checked modulo
-        #[cfg(not(feature = "unchecked"))]
-        fn modulo<T: Display + CheckedRem>(x: T, y: T) -> Result<T, EvalAltResult> {
-            x.checked_rem(&y).ok_or_else(|| {
-                EvalAltResult::ErrorArithmetic(
-                    format!("Modulo division by zero or overflow: {} % {}", x, y),
-                    Position::none(),
-                )
-            })
-        }
-        // Unchecked modulo - may panic if dividing by zero
-        #[cfg(any(feature = "unchecked", not(feature = "no_float")))]
-        fn modulo_u<T: Rem>(x: T, y: T) -> <T as Rem>::Output {
-            x % y
-        }
         // Checked power
         #[cfg(not(feature = "unchecked"))]
-        fn pow_i_i(x: INT, y: INT) -> Result<INT, EvalAltResult> {
+        fn pow_i_i(x: INT, y: INT) -> Result<INT, Box<EvalAltResult>> {
             #[cfg(not(feature = "only_i32"))]
             {
                 if y > (u32::MAX as INT) {
-                    Err(EvalAltResult::ErrorArithmetic(
+                    Err(op_err(
                         format!("Integer raised to too large an index: {} ~ {}", x, y),
-                        Position::none(),
+                        "~",
+                        vec![x.into_dynamic(), y.into_dynamic()],
                     ))
                 } else if y < 0 {
-                    Err(EvalAltResult::ErrorArithmetic(
+                    Err(op_err(
                         format!("Integer raised to a negative index: {} ~ {}", x, y),
-                        Position::none(),
+                        "~",
+                        vec![x.into_dynamic(), y.into_dynamic()],
                     ))
                 } else {
                     x.checked_pow(y as u32).ok_or_else(|| {
-                        EvalAltResult::ErrorArithmetic(
+                        op_err(
                             format!("Power overflow: {} ~ {}", x, y),
-                            Position::none(),
+                            "~",
+                            vec![x.into_dynamic(), y.into_dynamic()],
                         )
                     })
                 }
@@ -307,15 +492,17 @@ impl Engine<'_> {
             #[cfg(feature = "only_i32")]
             {
                 if y < 0 {
-                    Err(EvalAltResult::ErrorArithmetic(
+                    Err(op_err(
                         format!("Integer raised to a negative index: {} ~ {}", x, y),
-                        Position::none(),
+                        "~",
+                        vec![x.into_dynamic(), y.into_dynamic()],
                     ))
                 } else {
                     x.checked_pow(y as u32).ok_or_else(|| {
-                        EvalAltResult::ErrorArithmetic(
+                        op_err(
                             format!("Power overflow: {} ~ {}", x, y),
-                            Position::none(),
+                            "~",
+                            vec![x.into_dynamic(), y.into_dynamic()],
                         )
                     })
                 }
@@ -334,12 +521,13 @@ impl Engine<'_> {
         // Checked power
         #[cfg(not(feature = "unchecked"))]
         #[cfg(not(feature = "no_float"))]
-        fn pow_f_i(x: FLOAT, y: INT) -> Result<FLOAT, EvalAltResult> {
+        fn pow_f_i(x: FLOAT, y: INT) -> Result<FLOAT, Box<EvalAltResult>> {
             // Raise to power that is larger than an i32
             if y > (i32::MAX as INT) {
-                return Err(EvalAltResult::ErrorArithmetic(
+                return Err(op_err(
                     format!("Number raised to too large an index: {} ~ {}", x, y),
-                    Position::none(),
+                    "~",
+                    vec![x.into_dynamic(), y.into_dynamic()],
                 ));
             }
 
@@ -352,39 +540,7 @@ impl Engine<'_> {
             x.powi(y as i32)
         }
 
-        #[cfg(not(feature = "unchecked"))]
-        {
-            reg_op_result!(self, "+", add, INT);
-            reg_op_result!(self, "-", sub, INT);
-            reg_op_result!(self, "*", mul, INT);
-            reg_op_result!(self, "/", div, INT);
-
-            #[cfg(not(feature = "only_i32"))]
-            #[cfg(not(feature = "only_i64"))]
-            {
-                reg_op_result!(self, "+", add, i8, u8, i16, u16, i32, i64, u32, u64);
-                reg_op_result!(self, "-", sub, i8, u8, i16, u16, i32, i64, u32, u64);
-                reg_op_result!(self, "*", mul, i8, u8, i16, u16, i32, i64, u32, u64);
-                reg_op_result!(self, "/", div, i8, u8, i16, u16, i32, i64, u32, u64);
-            }
-        }
-
-        #[cfg(feature = "unchecked")]
-        {
-            reg_op!(self, "+", add_u, INT);
-            reg_op!(self, "-", sub_u, INT);
-            reg_op!(self, "*", mul_u, INT);
-            reg_op!(self, "/", div_u, INT);
-
-            #[cfg(not(feature = "only_i32"))]
-            #[cfg(not(feature = "only_i64"))]
-            {
-                reg_op!(self, "+", add_u, i8, u8, i16, u16, i32, i64, u32, u64);
-                reg_op!(self, "-", sub_u, i8, u8, i16, u16, i32, i64, u32, u64);
-                reg_op!(self, "*", mul_u, i8, u8, i16, u16, i32, i64, u32, u64);
-                reg_op!(self, "/", div_u, i8, u8, i16, u16, i32, i64, u32, u64);
-            }
-        }
+        self.register_int_arithmetic();
 
         #[cfg(not(feature = "no_float"))]
         {
@@ -430,6 +586,21 @@ impl Engine<'_> {
                 reg_cmp!(self, "==", eq, f32, f64);
                 reg_cmp!(self, "!=", ne, f32, f64);
             }
+
+            // Containers are compared structurally
+            #[cfg(not(feature = "no_index"))]
+            {
+                self.register_fn("==", |x: &mut Array, y: Array| values_equal(&*x, &y));
+                self.register_fn("!=", |x: &mut Array, y: Array| !values_equal(&*x, &y));
+                self.register_fn("==", |x: &mut Blob, y: Blob| *x == y);
+                self.register_fn("!=", |x: &mut Blob, y: Blob| *x != y);
+            }
+
+            #[cfg(not(feature = "no_object"))]
+            {
+                self.register_fn("==", |x: &mut Map, y: Map| values_equal(&*x, &y));
+                self.register_fn("!=", |x: &mut Map, y: Map| !values_equal(&*x, &y));
+            }
         }
 
         // `&&` and `||` are treated specially as they short-circuit.
@@ -456,14 +627,12 @@ impl Engine<'_> {
         {
             reg_op_result1!(self, "<<", shl, INT, INT);
             reg_op_result1!(self, ">>", shr, INT, INT);
-            reg_op_result!(self, "%", modulo, INT);
 
             #[cfg(not(feature = "only_i32"))]
             #[cfg(not(feature = "only_i64"))]
             {
                 reg_op_result1!(self, "<<", shl, i64, i8, u8, i16, u16, i32, i64, u32, u64);
                 reg_op_result1!(self, ">>", shr, i64, i8, u8, i16, u16, i32, i64, u32, u64);
-                reg_op_result!(self, "%", modulo, i8, u8, i16, u16, i32, i64, u32, u64);
             }
         }
 
@@ -471,14 +640,12 @@ impl Engine<'_> {
         {
             reg_op!(self, "<<", shl_u, INT, INT);
             reg_op!(self, ">>", shr_u, INT, INT);
-            reg_op!(self, "%", modulo_u, INT);
 
             #[cfg(not(feature = "only_i32"))]
             #[cfg(not(feature = "only_i64"))]
             {
                 reg_op!(self, "<<", shl_u, i64, i8, u8, i16, u16, i32, i64, u32, u64);
                 reg_op!(self, ">>", shr_u, i64, i8, u8, i16, u16, i32, i64, u32, u64);
-                reg_op!(self, "%", modulo_u, i8, u8, i16, u16, i32, i64, u32, u64);
             }
         }
 
@@ -513,41 +680,6 @@ impl Engine<'_> {
                 )
             }
 
-            #[cfg(not(feature = "unchecked"))]
-            macro_rules! reg_un_result {
-                ($self:expr, $x:expr, $op:expr, $( $y:ty ),*) => (
-                    $(
-                        $self.register_result_fn($x, $op as fn(x: $y)->Result<$y,EvalAltResult>);
-                    )*
-                )
-            }
-
-            #[cfg(not(feature = "unchecked"))]
-            {
-                reg_un_result!(self, "-", neg, INT);
-                reg_un_result!(self, "abs", abs, INT);
-
-                #[cfg(not(feature = "only_i32"))]
-                #[cfg(not(feature = "only_i64"))]
-                {
-                    reg_un_result!(self, "-", neg, i8, i16, i32, i64);
-                    reg_un_result!(self, "abs", abs, i8, i16, i32, i64);
-                }
-            }
-
-            #[cfg(feature = "unchecked")]
-            {
-                reg_un!(self, "-", neg_u, INT);
-                reg_un!(self, "abs", abs_u, INT);
-
-                #[cfg(not(feature = "only_i32"))]
-                #[cfg(not(feature = "only_i64"))]
-                {
-                    reg_un!(self, "-", neg_u, i8, i16, i32, i64);
-                    reg_un!(self, "abs", abs_u, i8, i16, i32, i64);
-                }
-            }
-
             #[cfg(not(feature = "no_float"))]
             {
                 reg_un!(self, "-", neg_u, f32, f64);
@@ -611,11 +743,28 @@ impl Engine<'_> {
                 reg_fn1!(self, FUNC_TO_STRING, to_debug, String, Array);
                 reg_fn1!(self, KEYWORD_DEBUG, to_debug, String, Array);
 
-                // Register array iterator
+                // Register array iterator, which copies the items one by one as they are reached
+                // (rather than the whole array up front, in case it is shared)
                 self.register_iterator::<Array, _>(|a: &Dynamic| {
-                    Box::new(a.downcast_ref::<Array>().unwrap().clone().into_iter())
+                    let arr = a.downcast_ref::<Array>().unwrap().clone();
+                    Box::new((0..arr.len()).map(move |i| arr[i].clone()))
                         as Box<dyn Iterator<Item = Dynamic>>
                 });
+
+                reg_fn1!(self, KEYWORD_PRINT, to_debug, String, Blob);
+                reg_fn1!(self, FUNC_TO_STRING, to_debug, String, Blob);
+                reg_fn1!(self, KEYWORD_DEBUG, to_debug, String, Blob);
+
+                // Register BLOB iterator, which iterates through the bytes as integers
+                self.register_iterator::<Blob, _>(|a: &Dynamic| {
+                    Box::new(
+                        a.downcast_ref::<Blob>()
+                            .unwrap()
+                            .clone()
+                            .into_iter()
+                            .map(|b| (b as INT).into_dynamic()),
+                    ) as Box<dyn Iterator<Item = Dynamic>>
+                });
             }
 
             #[cfg(not(feature = "no_object"))]
@@ -631,14 +780,16 @@ impl Engine<'_> {
                 });
 
                 // Register map access functions
+                #[cfg(not(feature = "no_index"))]
                 self.register_fn("keys", |map: Map| {
                     map.into_iter()
                         .map(|(k, _)| k.into_dynamic())
-                        .collect::<Vec<_>>()
+                        .collect::<Array>()
                 });
 
+                #[cfg(not(feature = "no_index"))]
                 self.register_fn("values", |map: Map| {
-                    map.into_iter().map(|(_, v)| v).collect::<Vec<_>>()
+                    map.into_iter().map(|(_, v)| v).collect::<Array>()
                 });
             }
         }
@@ -648,6 +799,7 @@ impl Engine<'_> {
         where
             Range<T>: Iterator<Item = T>,
         {
+            engine.set_type_name::<Range<T>>("range");
             engine.register_iterator::<Range<T>, _>(|a: &Dynamic| {
                 Box::new(
                     a.downcast_ref::<Range<T>>()
@@ -659,7 +811,7 @@ impl Engine<'_> {
         }
 
         reg_range::<INT>(self);
-        self.register_fn("range", |i1: INT, i2: INT| (i1..i2));
+        self.register_fn("range", |i1: INT, i2: INT| i1..i2);
 
         #[cfg(not(feature = "only_i32"))]
         #[cfg(not(feature = "only_i64"))]
@@ -707,6 +859,7 @@ impl Engine<'_> {
             T: Any + Clone + PartialOrd,
             StepRange<T>: Iterator<Item = T>,
         {
+            engine.set_type_name::<StepRange<T>>("range");
             engine.register_iterator::<StepRange<T>, _>(|a: &Dynamic| {
                 Box::new(
                     a.downcast_ref::<StepRange<T>>()
@@ -736,6 +889,165 @@ impl Engine<'_> {
 
             reg_step!(self, "range", i8, u8, i16, u16, i32, i64, u32, u64);
         }
+
+        // Register lazy pipelines
+        self.register_pipeline();
+
+        // Keep the operators which compiled code may implement itself
+        #[cfg(feature = "jit")]
+        {
+            self.builtin_ops = self
+                .functions
+                .iter()
+                .flat_map(|functions| functions.iter())
+                .filter(|(spec, _)| crate::jit::OPERATORS.contains(&&*spec.name))
+                .map(|(spec, func)| (spec.clone(), func.clone()))
+                .collect();
+        }
+    }
+
+    /// Register the arithmetic operators on integers (`+`, `-`, `*`, `/`, `%`, unary `-` and `abs`),
+    /// following the overflow mode of the `Engine`.
+    ///
+    /// Operators registered by a previous call are replaced, but custom functions registered by
+    /// the host for these operators are kept.
+    pub(crate) fn register_int_arithmetic(&mut self) {
+        // Register the operators into an empty registry first
+        let functions = self.functions.take();
+        self.register_int_ops();
+        let ops = self.functions.take().unwrap_or_default();
+        self.functions = functions;
+
+        for (spec, func) in ops.iter() {
+            let current = self
+                .functions
+                .as_ref()
+                .and_then(|functions| functions.get(spec));
+
+            let is_custom = match (current, self.int_arithmetic.get(spec)) {
+                (Some(current), Some(builtin)) => !is_same_fn(current, builtin),
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+
+            if !is_custom {
+                self.functions_mut().insert(spec.clone(), func.clone());
+                self.int_arithmetic.insert(spec.clone(), func.clone());
+            }
+        }
+    }
+
+    /// Register the arithmetic operators on integers following the overflow mode of the `Engine`,
+    /// replacing any existing functions.
+    fn register_int_ops(&mut self) {
+        macro_rules! reg_un {
+            ($self:expr, $x:expr, $op:expr, $( $y:ty ),*) => (
+                $(
+                    $self.register_fn($x, $op as fn(x: $y)->$y);
+                )*
+            )
+        }
+
+        #[cfg(not(feature = "unchecked"))]
+        macro_rules! reg_un_result {
+            ($self:expr, $x:expr, $op:expr, $( $y:ty ),*) => (
+                $(
+                    $self.register_result_fn($x, $op as fn(x: $y)->Result<$y,Box<EvalAltResult>>);
+                )*
+            )
+        }
+
+        match self.overflow_mode {
+            #[cfg(not(feature = "unchecked"))]
+            OverflowMode::Checked => {
+                reg_op_result!(self, "+", add, INT);
+                reg_op_result!(self, "-", sub, INT);
+                reg_op_result!(self, "*", mul, INT);
+                reg_op_result!(self, "/", div, INT);
+                reg_op_result!(self, "%", modulo, INT);
+                reg_un_result!(self, "-", neg, INT);
+                reg_un_result!(self, "abs", abs, INT);
+
+                #[cfg(not(feature = "only_i32"))]
+                #[cfg(not(feature = "only_i64"))]
+                {
+                    reg_op_result!(self, "+", add, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op_result!(self, "-", sub, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op_result!(self, "*", mul, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op_result!(self, "/", div, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op_result!(self, "%", modulo, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_un_result!(self, "-", neg, i8, i16, i32, i64);
+                    reg_un_result!(self, "abs", abs, i8, i16, i32, i64);
+                }
+            }
+
+            #[cfg(feature = "unchecked")]
+            OverflowMode::Checked => {
+                reg_op!(self, "+", add_u, INT);
+                reg_op!(self, "-", sub_u, INT);
+                reg_op!(self, "*", mul_u, INT);
+                reg_op!(self, "/", div_u, INT);
+                reg_op!(self, "%", modulo_u, INT);
+                reg_un!(self, "-", neg_u, INT);
+                reg_un!(self, "abs", abs_u, INT);
+
+                #[cfg(not(feature = "only_i32"))]
+                #[cfg(not(feature = "only_i64"))]
+                {
+                    reg_op!(self, "+", add_u, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op!(self, "-", sub_u, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op!(self, "*", mul_u, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op!(self, "/", div_u, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op!(self, "%", modulo_u, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_un!(self, "-", neg_u, i8, i16, i32, i64);
+                    reg_un!(self, "abs", abs_u, i8, i16, i32, i64);
+                }
+            }
+
+            OverflowMode::Wrapping => {
+                reg_op!(self, "+", add_w, INT);
+                reg_op!(self, "-", sub_w, INT);
+                reg_op!(self, "*", mul_w, INT);
+                reg_op_result!(self, "/", div_w, INT);
+                reg_op_result!(self, "%", modulo_nz, INT);
+                reg_un!(self, "-", neg_w, INT);
+                reg_un!(self, "abs", abs_w, INT);
+
+                #[cfg(not(feature = "only_i32"))]
+                #[cfg(not(feature = "only_i64"))]
+                {
+                    reg_op!(self, "+", add_w, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op!(self, "-", sub_w, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op!(self, "*", mul_w, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op_result!(self, "/", div_w, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op_result!(self, "%", modulo_nz, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_un!(self, "-", neg_w, i8, i16, i32, i64);
+                    reg_un!(self, "abs", abs_w, i8, i16, i32, i64);
+                }
+            }
+
+            OverflowMode::Saturating => {
+                reg_op!(self, "+", add_s, INT);
+                reg_op!(self, "-", sub_s, INT);
+                reg_op!(self, "*", mul_s, INT);
+                reg_op_result!(self, "/", div_s, INT);
+                reg_op_result!(self, "%", modulo_nz, INT);
+                reg_un!(self, "-", neg_s, INT);
+                reg_un!(self, "abs", abs_s, INT);
+
+                #[cfg(not(feature = "only_i32"))]
+                #[cfg(not(feature = "only_i64"))]
+                {
+                    reg_op!(self, "+", add_s, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op!(self, "-", sub_s, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op!(self, "*", mul_s, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op_result!(self, "/", div_s, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_op_result!(self, "%", modulo_nz, i8, u8, i16, u16, i32, i64, u32, u64);
+                    reg_un!(self, "-", neg_s, i8, i16, i32, i64);
+                    reg_un!(self, "abs", abs_s, i8, i16, i32, i64);
+                }
+            }
+        }
     }
 }
 
@@ -774,6 +1086,8 @@ impl Engine<'_> {
             self.register_fn("asinh", |x: FLOAT| x.asinh().to_degrees());
             self.register_fn("acosh", |x: FLOAT| x.acosh().to_degrees());
             self.register_fn("atanh", |x: FLOAT| x.atanh().to_degrees());
+            self.register_fn("atan2", |y: FLOAT, x: FLOAT| y.atan2(x).to_degrees());
+            self.register_fn("hypot", |x: FLOAT, y: FLOAT| x.hypot(y));
             self.register_fn("sqrt", |x: FLOAT| x.sqrt());
             self.register_fn("exp", |x: FLOAT| x.exp());
             self.register_fn("ln", |x: FLOAT| x.ln());
@@ -781,9 +1095,14 @@ impl Engine<'_> {
             self.register_fn("log10", |x: FLOAT| x.log10());
             self.register_fn("floor", |x: FLOAT| x.floor());
             self.register_fn("ceiling", |x: FLOAT| x.ceil());
-            self.register_fn("round", |x: FLOAT| x.ceil());
+            self.register_fn("round", |x: FLOAT| x.round());
+            self.register_fn("round_even", round_even);
             self.register_fn("int", |x: FLOAT| x.trunc());
+            self.register_fn("trunc", |x: FLOAT| x.trunc());
             self.register_fn("fraction", |x: FLOAT| x.fract());
+            self.register_fn("fract", |x: FLOAT| x.fract());
+            self.register_fn("signum", |x: FLOAT| x.signum());
+            self.register_fn("copysign", |x: FLOAT, sign: FLOAT| x.copysign(sign));
             self.register_fn("is_nan", |x: FLOAT| x.is_nan());
             self.register_fn("is_finite", |x: FLOAT| x.is_finite());
             self.register_fn("is_infinite", |x: FLOAT| x.is_infinite());
@@ -826,15 +1145,91 @@ impl Engine<'_> {
             self.register_fn("to_int", |x: u32| x as INT);
         }
 
+        // Register range-checked conversions between integer types, so that values can be
+        // passed to functions registered over narrower (or unsigned) integer types
+        macro_rules! reg_to_int_type {
+            ($name:expr, $to:ty, $($from:ty),*) => (
+                $(
+                    #[cfg(not(feature = "unchecked"))]
+                    self.register_result_fn($name, |x: $from| {
+                        <$to>::try_from(x).map_err(|_| {
+                            op_err(
+                                format!("Integer overflow: {}({})", $name, x),
+                                $name,
+                                vec![x.into_dynamic()],
+                            )
+                        })
+                    });
+
+                    #[cfg(feature = "unchecked")]
+                    self.register_fn($name, |x: $from| x as $to);
+                )*
+            )
+        }
+
+        macro_rules! reg_to_int_types {
+            ($($name:expr => $to:ty),*) => (
+                $(
+                    reg_to_int_type!($name, $to, INT);
+
+                    #[cfg(not(feature = "only_i32"))]
+                    #[cfg(not(feature = "only_i64"))]
+                    reg_to_int_type!($name, $to, i8, u8, i16, u16, i32, u32, u64);
+                )*
+            )
+        }
+
+        reg_to_int_types!(
+            "to_i8" => i8, "to_u8" => u8, "to_i16" => i16, "to_u16" => u16,
+            "to_i32" => i32, "to_u32" => u32, "to_i64" => i64, "to_u64" => u64
+        );
+
+        // Register integer arithmetic which does not depend on the overflow mode of the `Engine`:
+        // checked (returning `()` on overflow or division by zero), wrapping and saturating
+        macro_rules! reg_checked {
+            ($name:expr, $op:ident, $( $y:ty ),*) => (
+                $(
+                    self.register_dynamic_fn($name, |x: $y, y: $y| match x.$op(y) {
+                        Some(r) => r.into_dynamic(),
+                        None => ().into_dynamic(),
+                    });
+                )*
+            )
+        }
+
+        macro_rules! reg_overflow_fns {
+            ($( $y:ty ),*) => (
+                reg_checked!("checked_add", checked_add, $($y),*);
+                reg_checked!("checked_sub", checked_sub, $($y),*);
+                reg_checked!("checked_mul", checked_mul, $($y),*);
+                reg_checked!("checked_div", checked_div, $($y),*);
+                reg_op!(self, "wrapping_add", add_w, $($y),*);
+                reg_op!(self, "wrapping_sub", sub_w, $($y),*);
+                reg_op!(self, "wrapping_mul", mul_w, $($y),*);
+                reg_op_result!(self, "wrapping_div", div_w, $($y),*);
+                reg_op!(self, "saturating_add", add_s, $($y),*);
+                reg_op!(self, "saturating_sub", sub_s, $($y),*);
+                reg_op!(self, "saturating_mul", mul_s, $($y),*);
+                reg_op_result!(self, "saturating_div", div_s, $($y),*);
+            )
+        }
+
+        reg_overflow_fns!(INT);
+
+        #[cfg(not(feature = "only_i32"))]
+        #[cfg(not(feature = "only_i64"))]
+        reg_overflow_fns!(i8, u8, i16, u16, i32, i64, u32, u64);
+
         #[cfg(not(feature = "no_float"))]
         {
             #[cfg(not(feature = "unchecked"))]
             {
                 self.register_result_fn("to_int", |x: f32| {
                     if x > (i64::MAX as f32) {
-                        return Err(EvalAltResult::ErrorArithmetic(
+                        return Err(op_err(
                             format!("Integer overflow: to_int({})", x),
-                            Position::none(),
+                            "to_int",
+                            vec![x.into_dynamic()],
                         ));
                     }
 
@@ -842,9 +1237,10 @@ impl Engine<'_> {
                 });
                 self.register_result_fn("to_int", |x: FLOAT| {
                     if x > (i64::MAX as FLOAT) {
-                        return Err(EvalAltResult::ErrorArithmetic(
+                        return Err(op_err(
                             format!("Integer overflow: to_int({})", x),
-                            Position::none(),
+                            "to_int",
+                            vec![x.into_dynamic()],
                         ));
                     }
 
@@ -859,53 +1255,249 @@ impl Engine<'_> {
             }
         }
 
+        // Register the array math package
+        #[cfg(feature = "array_math")]
         #[cfg(not(feature = "no_index"))]
-        {
-            macro_rules! reg_fn3 {
-                ($self:expr, $x:expr, $op:expr, $v:ty, $w:ty, $r:ty, $( $y:ty ),*) => (
-                    $(
-                        $self.register_fn($x, $op as fn(x: $v, y: $w, z: $y)->$r);
-                    )*
-                )
-            }
+        #[cfg(not(feature = "no_float"))]
+        self.register_array_math();
 
-            // Register array utility functions
-            fn push<T: Any>(list: &mut Array, item: T) {
-                list.push(Box::new(item));
-            }
-            fn pad<T: Any + Clone>(list: &mut Array, len: INT, item: T) {
-                if len >= 0 {
-                    while list.len() < len as usize {
-                        push(list, item.clone());
-                    }
-                }
-            }
+        // Register the matrix package
+        #[cfg(feature = "matrix")]
+        #[cfg(not(feature = "no_index"))]
+        #[cfg(not(feature = "no_float"))]
+        self.register_matrix();
 
-            reg_fn2x!(self, "push", push, &mut Array, (), INT, bool, char);
-            reg_fn2x!(self, "push", push, &mut Array, (), String, Array, ());
-            reg_fn3!(self, "pad", pad, &mut Array, INT, (), INT, bool, char);
-            reg_fn3!(self, "pad", pad, &mut Array, INT, (), String, Array, ());
+        // Register the date/time package
+        #[cfg(feature = "datetime")]
+        self.register_datetime();
 
-            self.register_fn("append", |list: &mut Array, array: Array| {
-                list.extend(array)
-            });
-            self.register_fn("+", |mut list: Array, array: Array| {
-                list.extend(array);
-                list
-            });
+        // Register the regular expression package
+        #[cfg(feature = "regex")]
+        self.register_regex();
 
-            #[cfg(not(feature = "only_i32"))]
-            #[cfg(not(feature = "only_i64"))]
-            {
-                reg_fn2x!(self, "push", push, &mut Array, (), i8, u8, i16, u16);
-                reg_fn2x!(self, "push", push, &mut Array, (), i32, i64, u32, u64);
+        // Register the hash package
+        #[cfg(feature = "hash")]
+        self.register_hash();
+
+        // Register random number functions
+        #[cfg(feature = "rand")]
+        {
+            use rand::Rng;
+
+            let rng = self.rng.clone();
+            self.register_fn("rand", move || with_rng(&rng, |rng| rng.gen::<INT>()));
+            let rng = self.rng.clone();
+            self.register_result_fn("rand", move |range: Range<INT>| {
+                if range.start >= range.end {
+                    return Err(Box::new(EvalAltResult::ErrorRuntime(
+                        format!("Empty range: {}..{}", range.start, range.end),
+                        Position::none(),
+                    )));
+                }
+                Ok(with_rng(&rng, |rng| rng.gen_range(range)))
+            });
+
+            #[cfg(not(feature = "no_float"))]
+            {
+                let rng = self.rng.clone();
+                self.register_fn("rand_float", move || {
+                    with_rng(&rng, |rng| rng.gen::<FLOAT>())
+                });
+            }
+        }
+
+        // Register timestamp functions
+        #[cfg(not(feature = "no_std"))]
+        #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-bindgen"))]
+        {
+            #[cfg(not(feature = "no_float"))]
+            fn seconds(d: crate::stdlib::time::Duration) -> FLOAT {
+                d.as_secs_f64() as FLOAT
+            }
+            #[cfg(feature = "no_float")]
+            fn seconds(d: crate::stdlib::time::Duration) -> INT {
+                d.as_secs() as INT
+            }
+
+            self.register_fn("timestamp", Instant::now);
+            self.register_fn("elapsed", |ts: &mut Instant| seconds(ts.elapsed()));
+            #[cfg(not(feature = "no_object"))]
+            self.register_get("elapsed", |ts: &mut Instant| seconds(ts.elapsed()));
+            self.register_fn("-", |ts1: Instant, ts2: Instant| {
+                seconds(ts1.saturating_duration_since(ts2))
+            });
+
+            self.register_fn("==", |x: Instant, y: Instant| x == y);
+            self.register_fn("!=", |x: Instant, y: Instant| x != y);
+            self.register_fn("<", |x: Instant, y: Instant| x < y);
+            self.register_fn("<=", |x: Instant, y: Instant| x <= y);
+            self.register_fn(">", |x: Instant, y: Instant| x > y);
+            self.register_fn(">=", |x: Instant, y: Instant| x >= y);
+        }
+
+        // Register bit-field functions
+        {
+            fn bit_mask(start: INT, len: INT) -> Result<INT, Box<EvalAltResult>> {
+                if start < 0 || start as usize >= INT_BITS {
+                    return Err(Box::new(EvalAltResult::ErrorBitFieldBounds(
+                        INT_BITS,
+                        start,
+                        Position::none(),
+                    )));
+                }
+
+                let len = (len.max(0) as usize).min(INT_BITS - start as usize);
+
+                // `1 << (INT_BITS - 1)` is the minimum integer, so wrap to get the mask of all other bits
+                Ok(if len >= INT_BITS {
+                    !0
+                } else {
+                    ((1 as INT) << len).wrapping_sub(1)
+                })
+            }
+
+            self.register_result_fn("get_bits", |x: INT, start: INT, len: INT| {
+                bit_mask(start, len).map(|mask| (x >> start) & mask)
+            });
+            self.register_result_fn(
+                "set_bits",
+                |x: &mut INT, start: INT, len: INT, value: INT| {
+                    bit_mask(start, len).map(|mask| {
+                        *x = (*x & !(mask << start)) | ((value & mask) << start);
+                    })
+                },
+            );
+        }
+
+        #[cfg(not(feature = "no_index"))]
+        {
+            macro_rules! reg_fn3 {
+                ($self:expr, $x:expr, $op:expr, $v:ty, $w:ty, $r:ty, $( $y:ty ),*) => (
+                    $(
+                        $self.register_fn($x, $op as fn(x: $v, y: $w, z: $y)->$r);
+                    )*
+                )
+            }
+
+            // Register array utility functions that move their arguments into the array.
+            // Arguments other than the object of a method call are always temporary values,
+            // so they are taken (leaving default values behind) instead of cloned.
+            macro_rules! type_ids {
+                (( $( $v:ty ),* ), $y:ty) => (
+                    vec![$(TypeId::of::<$v>(),)* TypeId::of::<$y>()]
+                )
+            }
+            macro_rules! reg_move {
+                ($self:expr, $x:expr, $op:ident, $v:tt, $( $y:ty ),*) => (
+                    $(
+                        $self.register_fn_raw($x, type_ids!($v, $y), Box::new($op::<$y>));
+                    )*
+                )
+            }
+
+            fn take<T: Any + Default>(arg: &mut Variant) -> T {
+                mem::take(arg.downcast_mut::<T>().unwrap())
+            }
+            fn push<T: Any + Default>(
+                args: &mut FnCallArgs,
+                _: Position,
+            ) -> Result<Dynamic, Box<EvalAltResult>> {
+                let item = take::<T>(args[1]);
+                args[0]
+                    .downcast_mut::<Array>()
+                    .unwrap()
+                    .push(Box::new(item));
+                Ok(().into_dynamic())
+            }
+            fn insert<T: Any + Default>(
+                args: &mut FnCallArgs,
+                _: Position,
+            ) -> Result<Dynamic, Box<EvalAltResult>> {
+                let index = *args[1].downcast_ref::<INT>().unwrap();
+                let item = take::<T>(args[2]);
+                let list = args[0].downcast_mut::<Array>().unwrap();
+                let index = (index.max(0) as usize).min(list.len());
+                list.insert(index, Box::new(item));
+                Ok(().into_dynamic())
+            }
+            fn pad<T: Any + Clone>(list: &mut Array, len: INT, item: T) {
+                if len >= 0 && list.len() < len as usize {
+                    list.resize(len as usize, Box::new(item));
+                }
+            }
+
+            reg_move!(
+                self,
+                "push",
+                push,
+                (Array),
+                INT,
+                bool,
+                char,
+                String,
+                Array,
+                ()
+            );
+            reg_move!(
+                self,
+                "insert",
+                insert,
+                (Array, INT),
+                INT,
+                bool,
+                char,
+                String,
+                Array,
+                ()
+            );
+            reg_fn3!(self, "pad", pad, &mut Array, INT, (), INT, bool, char);
+            reg_fn3!(self, "pad", pad, &mut Array, INT, (), String, Array, ());
+
+            reg_move!(self, "push", push, (Array), Blob);
+            reg_move!(self, "insert", insert, (Array, INT), Blob);
+
+            #[cfg(not(feature = "no_object"))]
+            {
+                reg_move!(self, "push", push, (Array), Map);
+                reg_move!(self, "insert", insert, (Array, INT), Map);
+                reg_fn3!(self, "pad", pad, &mut Array, INT, (), Map);
+            }
+
+            self.register_fn_raw(
+                "append",
+                vec![TypeId::of::<Array>(), TypeId::of::<Array>()],
+                Box::new(|args: &mut FnCallArgs, _| {
+                    let array = take::<Array>(args[1]);
+                    args[0].downcast_mut::<Array>().unwrap().extend(array);
+                    Ok(().into_dynamic())
+                }),
+            );
+            // Both operands of an operator are temporary values
+            self.register_fn_raw(
+                "+",
+                vec![TypeId::of::<Array>(), TypeId::of::<Array>()],
+                Box::new(|args: &mut FnCallArgs, _| {
+                    let mut list = take::<Array>(args[0]);
+                    list.extend(take::<Array>(args[1]));
+                    Ok(list.into_dynamic())
+                }),
+            );
+
+            #[cfg(not(feature = "only_i32"))]
+            #[cfg(not(feature = "only_i64"))]
+            {
+                reg_move!(self, "push", push, (Array), i8, u8, i16, u16);
+                reg_move!(self, "push", push, (Array), i32, i64, u32, u64);
+                reg_move!(self, "insert", insert, (Array, INT), i8, u8, i16, u16);
+                reg_move!(self, "insert", insert, (Array, INT), i32, i64, u32, u64);
                 reg_fn3!(self, "pad", pad, &mut Array, INT, (), i8, u8, i16, u16);
                 reg_fn3!(self, "pad", pad, &mut Array, INT, (), i32, u32, i64, u64);
             }
 
             #[cfg(not(feature = "no_float"))]
             {
-                reg_fn2x!(self, "push", push, &mut Array, (), f32, f64);
+                reg_move!(self, "push", push, (Array), f32, f64);
+                reg_move!(self, "insert", insert, (Array, INT), f32, f64);
                 reg_fn3!(self, "pad", pad, &mut Array, INT, (), f32, f64);
             }
 
@@ -913,12 +1505,36 @@ impl Engine<'_> {
                 list.pop().unwrap_or_else(|| ().into_dynamic())
             });
             self.register_dynamic_fn("shift", |list: &mut Array| {
-                if !list.is_empty() {
+                if list.is_empty() {
                     ().into_dynamic()
                 } else {
                     list.remove(0)
                 }
             });
+            self.register_fn("take", |list: &mut Array| mem::take(list));
+            self.register_fn("drain", |list: &mut Array, start: INT| {
+                let start = (start.max(0) as usize).min(list.len());
+                list.drain(start..).collect::<Array>()
+            });
+            self.register_fn("drain", |list: &mut Array, start: INT, len: INT| {
+                let start = (start.max(0) as usize).min(list.len());
+                let end = start + (len.max(0) as usize).min(list.len() - start);
+                list.drain(start..end).collect::<Array>()
+            });
+            self.register_result_fn("chunks", |list: &mut Array, size: INT| {
+                let size = positive_size(size, "chunks")?;
+                Ok(list
+                    .chunks(size)
+                    .map(|chunk| chunk.iter().cloned().collect::<Array>().into_dynamic())
+                    .collect::<Array>())
+            });
+            self.register_result_fn("windows", |list: &mut Array, size: INT| {
+                let size = positive_size(size, "windows")?;
+                Ok(list
+                    .windows(size)
+                    .map(|window| window.iter().cloned().collect::<Array>().into_dynamic())
+                    .collect::<Array>())
+            });
             self.register_fn("len", |list: &mut Array| list.len() as INT);
             self.register_fn("clear", |list: &mut Array| list.clear());
             self.register_fn("truncate", |list: &mut Array, len: INT| {
@@ -926,19 +1542,151 @@ impl Engine<'_> {
                     list.truncate(len as usize);
                 }
             });
+            self.register_fn("reverse", |list: &mut Array| list.reverse());
+            self.register_fn("dedup", |list: &mut Array| {
+                list.dedup_by(|x, y| values_equal(x.as_ref(), y.as_ref()))
+            });
+            // Register functions looking for an element, of any built-in type
+            macro_rules! reg_search {
+                ($( $y:ty ),*) => (
+                    $(
+                        self.register_fn("contains", |list: &mut Array, item: $y| {
+                            list.iter().any(|x| values_equal(x.as_ref(), &item))
+                        });
+                    )*
+                )
+            }
+
+            reg_search!(INT, bool, char, String, Array, Blob, ());
+
+            #[cfg(not(feature = "only_i32"))]
+            #[cfg(not(feature = "only_i64"))]
+            reg_search!(i8, u8, i16, u16, i32, i64, u32, u64);
+
+            #[cfg(not(feature = "no_float"))]
+            reg_search!(f32, f64);
+
+            #[cfg(not(feature = "no_object"))]
+            reg_search!(Map);
+
+            // Register set-like operations
+            self.register_fn("union", |list: &mut Array, other: Array| {
+                let mut seen = ElementSet::default();
+                list.iter()
+                    .chain(other.iter())
+                    .filter(|&x| seen.insert(x.as_ref()))
+                    .cloned()
+                    .collect::<Array>()
+            });
+            self.register_fn("intersection", |list: &mut Array, other: Array| {
+                let others = ElementSet::from(&other);
+                let mut seen = ElementSet::default();
+                list.iter()
+                    .filter(|&x| others.contains(x.as_ref()) && seen.insert(x.as_ref()))
+                    .cloned()
+                    .collect::<Array>()
+            });
+            self.register_fn("difference", |list: &mut Array, other: Array| {
+                let others = ElementSet::from(&other);
+                let mut seen = ElementSet::default();
+                list.iter()
+                    .filter(|&x| !others.contains(x.as_ref()) && seen.insert(x.as_ref()))
+                    .cloned()
+                    .collect::<Array>()
+            });
+
+            #[cfg(feature = "rand")]
+            {
+                use rand::seq::SliceRandom;
+
+                let rng = self.rng.clone();
+                self.register_fn("shuffle", move |list: &mut Array| {
+                    with_rng(&rng, |rng| list.shuffle(rng))
+                });
+                let rng = self.rng.clone();
+                self.register_dynamic_fn("sample", move |list: &mut Array| {
+                    with_rng(&rng, |rng| list.choose(rng).cloned())
+                        .unwrap_or_else(|| ().into_dynamic())
+                });
+                let rng = self.rng.clone();
+                self.register_fn("sample", move |list: &mut Array, n: INT| {
+                    with_rng(&rng, |rng| {
+                        list.choose_multiple(rng, n.max(0) as usize)
+                            .cloned()
+                            .collect::<Array>()
+                    })
+                });
+            }
+        }
+
+        // Register BLOB functions
+        #[cfg(not(feature = "no_index"))]
+        {
+            self.register_fn("blob", Blob::new);
+
+            // Functions growing BLOB's, which enforce the maximum BLOB size
+            self.register_blob_growth();
+
+            self.register_dynamic_fn("pop", |blob: &mut Blob| match blob.pop() {
+                Some(b) => (b as INT).into_dynamic(),
+                None => ().into_dynamic(),
+            });
+            self.register_fn("len", |blob: &mut Blob| blob.len() as INT);
+            self.register_fn("clear", |blob: &mut Blob| blob.clear());
+            self.register_fn("truncate", |blob: &mut Blob, len: INT| {
+                if len >= 0 {
+                    blob.truncate(len as usize);
+                }
+            });
+            self.register_fn("reverse", |blob: &mut Blob| blob.reverse());
+            self.register_fn("extract", |blob: &mut Blob, start: INT| {
+                let start = (start.max(0) as usize).min(blob.len());
+                blob[start..].to_vec()
+            });
+            self.register_fn("extract", |blob: &mut Blob, start: INT, len: INT| {
+                let start = (start.max(0) as usize).min(blob.len());
+                let end = start + (len.max(0) as usize).min(blob.len() - start);
+                blob[start..end].to_vec()
+            });
+
+            // Conversions
+            self.register_fn("as_string", |blob: &mut Blob| {
+                String::from_utf8_lossy(blob).into_owned()
+            });
+            self.register_fn("to_array", |blob: &mut Blob| {
+                blob.iter()
+                    .map(|&b| (b as INT).into_dynamic())
+                    .collect::<Array>()
+            });
+            self.register_fn("to_hex", |blob: &mut Blob| encode_hex(blob));
+            self.register_fn("to_base64", |blob: &mut Blob| encode_base64(blob));
+            self.register_fn("url_encode", |blob: &mut Blob| encode_url(blob));
+
+            // Strings are encoded as their UTF-8 bytes
+            self.register_fn("to_hex", |s: &mut String| encode_hex(s.as_bytes()));
+            self.register_fn("to_base64", |s: &mut String| encode_base64(s.as_bytes()));
         }
 
         // Register map functions
         #[cfg(not(feature = "no_object"))]
         {
-            self.register_fn("has", |map: &mut Map, prop: String| map.contains_key(&prop));
-            self.register_fn("len", |map: &mut Map| map.len() as INT);
-            self.register_fn("clear", |map: &mut Map| map.clear());
-            self.register_fn("mixin", |map1: &mut Map, map2: Map| {
+            fn mixin(map1: &mut Map, map2: Map) {
                 map2.into_iter().for_each(|(key, value)| {
                     map1.insert(key, value);
                 });
+            }
+
+            self.register_fn("has", |map: &mut Map, prop: String| map.contains_key(&prop));
+            self.register_fn("contains", |map: &mut Map, prop: String| {
+                map.contains_key(&prop)
+            });
+            self.register_fn("len", |map: &mut Map| map.len() as INT);
+            self.register_fn("clear", |map: &mut Map| map.clear());
+            self.register_dynamic_fn("remove", |map: &mut Map, prop: String| {
+                map.remove(&prop).unwrap_or_else(|| ().into_dynamic())
             });
+            self.register_fn("mixin", mixin);
+            self.register_fn("merge", mixin);
             self.register_fn("+", |mut map1: Map, map2: Map| {
                 map2.into_iter().for_each(|(key, value)| {
                     map1.insert(key, value);
@@ -947,6 +1695,40 @@ impl Engine<'_> {
             });
         }
 
+        // Register JSON conversion functions
+        {
+            fn json<T: Any>(x: &mut T) -> Result<String, Box<EvalAltResult>> {
+                to_json(&*x).map_err(|name| {
+                    Box::new(EvalAltResult::ErrorRuntime(
+                        format!("Cannot convert '{}' to JSON", name),
+                        Position::none(),
+                    ))
+                })
+            }
+
+            macro_rules! reg_json {
+                ($self:expr, $( $y:ty ),*) => (
+                    $(
+                        $self.register_result_fn("to_json", json as fn(x: &mut $y)->Result<String, Box<EvalAltResult>>);
+                    )*
+                )
+            }
+
+            reg_json!(self, INT, bool, char, String, ());
+
+            #[cfg(not(feature = "only_i32"))]
+            #[cfg(not(feature = "only_i64"))]
+            reg_json!(self, i8, u8, i16, u16, i32, i64, u32, u64);
+
+            #[cfg(not(feature = "no_float"))]
+            reg_json!(self, f32, f64);
+
+            // Arrays and object maps are handled by the `Engine`, which shows the script names of
+            // the types that cannot be converted
+            #[cfg(not(feature = "no_index"))]
+            reg_json!(self, Blob);
+        }
+
         // Register string concatenate functions
         fn prepend<T: Display>(x: T, y: String) -> String {
             format!("{}{}", x, y)
@@ -980,8 +1762,49 @@ impl Engine<'_> {
             self.register_fn("+", |x: Array, y: String| format!("{:?}{}", x, y));
         }
 
+        // Register in-place string concatenate functions, used by `+=`
+        fn append_in_place<T: Display>(x: &mut String, y: T) {
+            write!(x, "{}", y).expect("writing to a string should not fail");
+        }
+
+        self.register_fn("+=", |x: &mut String, y: String| x.push_str(&y));
+        self.register_fn("+=", |x: &mut String, y: char| x.push(y));
+        self.register_fn("+=", |_: &mut String, _: ()| ());
+        reg_fn2x!(self, "+=", append_in_place, &mut String, (), INT, bool);
+
+        #[cfg(not(feature = "only_i32"))]
+        #[cfg(not(feature = "only_i64"))]
+        {
+            reg_fn2x!(
+                self,
+                "+=",
+                append_in_place,
+                &mut String,
+                (),
+                i8,
+                u8,
+                i16,
+                u16,
+                i32,
+                i64,
+                u32,
+                u64
+            );
+        }
+
+        #[cfg(not(feature = "no_float"))]
+        {
+            reg_fn2x!(self, "+=", append_in_place, &mut String, (), f32, f64);
+        }
+
         // Register string utility functions
         self.register_fn("len", |s: &mut String| s.chars().count() as INT);
+        self.register_fn("reserve", |s: &mut String, additional: INT| {
+            // Only a hint, so ignore failures to allocate
+            if additional > 0 {
+                let _ = s.try_reserve(additional as usize);
+            }
+        });
         self.register_fn("contains", |s: &mut String, ch: char| s.contains(ch));
         self.register_fn("contains", |s: &mut String, find: String| s.contains(&find));
         self.register_fn("clear", |s: &mut String| s.clear());
@@ -1013,5 +1836,920 @@ impl Engine<'_> {
                 *s = trimmed.to_string();
             }
         });
+        self.register_fn("url_encode", |s: &mut String| encode_url(s.as_bytes()));
+        self.register_result_fn("url_decode", |s: &mut String| {
+            decode_url(s)
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .ok_or_else(|| {
+                    Box::new(EvalAltResult::ErrorRuntime(
+                        format!("Invalid URL-encoded string: '{}'", s),
+                        Position::none(),
+                    ))
+                })
+        });
+
+        // Register string comparison functions, which are independent of any locale
+        self.register_fn("eq_ignore_case", |s: &mut String, other: String| {
+            cmp_ignore_case(s, &other) == Ordering::Equal
+        });
+        self.register_fn("cmp_ignore_case", |s: &mut String, other: String| {
+            cmp_ignore_case(s, &other) as INT
+        });
+        self.register_fn("cmp_natural", |s: &mut String, other: String| {
+            cmp_natural(s, &other) as INT
+        });
+        self.register_fn("eq_ignore_case", |ch: char, other: char| {
+            ch.to_lowercase().eq(other.to_lowercase())
+        });
+
+        // Register `cmp`, returning -1, 0 or 1, e.g. for use as the comparison function of `sort`
+        {
+            macro_rules! reg_cmp_fn {
+                ($( $y:ty ),*) => (
+                    $(
+                        self.register_fn("cmp", |x: $y, y: $y| x.cmp(&y) as INT);
+                    )*
+                )
+            }
+
+            reg_cmp_fn!(INT, char, bool);
+
+            #[cfg(not(feature = "only_i32"))]
+            #[cfg(not(feature = "only_i64"))]
+            reg_cmp_fn!(i8, u8, i16, u16, i32, i64, u32, u64);
+
+            self.register_fn("cmp", |s: &mut String, other: String| {
+                s.as_str().cmp(&other) as INT
+            });
+
+            #[cfg(not(feature = "no_float"))]
+            {
+                macro_rules! reg_cmp_float {
+                    ($( $y:ty ),*) => (
+                        $(
+                            self.register_result_fn("cmp", |x: $y, y: $y| {
+                                x.partial_cmp(&y).map(|o| o as INT).ok_or_else(|| {
+                                    op_err(
+                                        format!("Cannot compare NaN: cmp({}, {})", x, y),
+                                        "cmp",
+                                        vec![x.into_dynamic(), y.into_dynamic()],
+                                    )
+                                })
+                            });
+                        )*
+                    )
+                }
+
+                reg_cmp_float!(f32, f64);
+            }
+        }
+
+        // Register character functions
+        self.register_fn("to_upper", |ch: char| to_single_char(ch, ch.to_uppercase()));
+        self.register_fn("to_lower", |ch: char| to_single_char(ch, ch.to_lowercase()));
+        self.register_fn("is_digit", |ch: char| ch.is_ascii_digit());
+        self.register_fn("is_alpha", |ch: char| ch.is_alphabetic());
+        self.register_fn("is_whitespace", |ch: char| ch.is_whitespace());
+        self.register_result_fn("to_char", |x: INT| {
+            u32::try_from(x)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| {
+                    op_err(
+                        format!("Invalid character code: {}", x),
+                        "to_char",
+                        vec![x.into_dynamic()],
+                    )
+                })
+        });
+    }
+}
+
+impl Engine<'_> {
+    /// Replace the functions converting floating-point numbers to strings (`print`, `to_string`
+    /// and string concatenation) with ones showing the `Engine`'s float precision.
+    ///
+    /// Only functions which are already registered are replaced, so this does not add the
+    /// standard library functions to an `Engine` without them.
+    #[cfg(not(feature = "no_float"))]
+    pub(crate) fn register_float_display(&mut self) {
+        let precision = self.float_precision;
+
+        macro_rules! reg_float {
+            ($( $t:ty ),*) => (
+                $(
+                    let float = TypeId::of::<$t>();
+                    let string = TypeId::of::<String>();
+
+                    if self.is_registered(KEYWORD_PRINT, &[float]) {
+                        self.register_fn(KEYWORD_PRINT, move |x: $t| float_to_string(x, precision));
+                    }
+                    if self.is_registered(FUNC_TO_STRING, &[float]) {
+                        self.register_fn(FUNC_TO_STRING, move |x: $t| float_to_string(x, precision));
+                    }
+                    if self.is_registered("+", &[string, float]) {
+                        self.register_fn("+", move |x: String, y: $t| x + &float_to_string(y, precision));
+                        self.register_fn("+", move |x: $t, y: String| float_to_string(x, precision) + &y);
+                    }
+                    if self.is_registered("+=", &[string, float]) {
+                        self.register_fn("+=", move |x: &mut String, y: $t| {
+                            x.push_str(&float_to_string(y, precision))
+                        });
+                    }
+                )*
+            )
+        }
+
+        reg_float!(f32, f64);
     }
+
+    /// Replace the functions creating or growing BLOB's (`blob`, `push`, `append`, `+`, `pad`,
+    /// `to_blob`, `parse_hex` and `parse_base64`) with ones failing when the result would be
+    /// larger than the `Engine`'s maximum BLOB size.
+    #[cfg(not(feature = "no_index"))]
+    #[cfg(not(feature = "no_stdlib"))]
+    pub(crate) fn register_blob_growth(&mut self) {
+        let max = self.max_blob_size;
+
+        let check = move |len: usize| {
+            if len <= max {
+                Ok(())
+            } else {
+                Err(blob_err(format!(
+                    "BLOB length {} is larger than the maximum of {} bytes",
+                    len, max
+                )))
+            }
+        };
+        let to_len = move |len: INT| {
+            let len = usize::try_from(len.max(0)).unwrap_or(usize::MAX);
+            check(len).map(|_| len)
+        };
+
+        self.register_result_fn("blob", move |len: INT| Ok(vec![0_u8; to_len(len)?]));
+        self.register_result_fn("blob", move |len: INT, value: INT| {
+            Ok(vec![to_byte(value)?; to_len(len)?])
+        });
+        self.register_result_fn("push", move |blob: &mut Blob, value: INT| {
+            let value = to_byte(value)?;
+            check(blob.len() + 1)?;
+            blob.push(value);
+            Ok(())
+        });
+        self.register_result_fn("append", move |blob: &mut Blob, other: Blob| {
+            check(blob.len() + other.len())?;
+            blob.extend(other);
+            Ok(())
+        });
+        self.register_result_fn("+", move |mut blob: Blob, other: Blob| {
+            check(blob.len() + other.len())?;
+            blob.extend(other);
+            Ok(blob)
+        });
+        self.register_result_fn("pad", move |blob: &mut Blob, len: INT, value: INT| {
+            let (len, value) = (to_len(len)?, to_byte(value)?);
+            if blob.len() < len {
+                blob.resize(len, value);
+            }
+            Ok(())
+        });
+
+        self.register_result_fn("to_blob", move |s: &mut String| {
+            check(s.len())?;
+            Ok(s.as_bytes().to_vec())
+        });
+        self.register_result_fn("to_blob", move |list: &mut Array| {
+            check(list.len())?;
+            list.iter()
+                .map(|v| {
+                    v.downcast_ref::<INT>()
+                        .ok_or_else(|| {
+                            blob_err(format!(
+                                "Cannot convert an array containing '{}' to a BLOB",
+                                (**v).type_name()
+                            ))
+                        })
+                        .and_then(|&b| to_byte(b))
+                })
+                .collect::<Result<Blob, _>>()
+        });
+
+        let parse_hex = move |s: &mut String| {
+            check(s.len() / 2)?;
+            decode_hex(s).ok_or_else(|| blob_err(format!("Invalid hex string: '{}'", s)))
+        };
+        let parse_base64 = move |s: &mut String| {
+            check(s.len() / 4 * 3)?;
+            decode_base64(s).ok_or_else(|| blob_err(format!("Invalid base64 string: '{}'", s)))
+        };
+
+        self.register_result_fn("parse_hex", parse_hex);
+        self.register_result_fn("from_hex", parse_hex);
+        self.register_result_fn("parse_base64", parse_base64);
+        self.register_result_fn("from_base64", parse_base64);
+    }
+
+    /// Is there a native function registered with a particular name and parameter types?
+    #[cfg(any(
+        not(feature = "no_float"),
+        all(not(feature = "no_index"), not(feature = "no_stdlib"))
+    ))]
+    pub(crate) fn is_registered(&self, name: &str, args: &[TypeId]) -> bool {
+        matches!(&self.functions, Some(functions) if functions.contains_key(&FnSpec {
+            name: name.into(),
+            args: args.iter().copied().collect(),
+        }))
+    }
+
+    /// Is the function registered for an operator (or op-assignment) and argument types the
+    /// built-in one, i.e. not replaced by the host?
+    #[cfg(feature = "jit")]
+    pub(crate) fn is_builtin_op(&self, name: &str, args: &[TypeId]) -> bool {
+        let spec = FnSpec {
+            name: name.into(),
+            args: args.iter().copied().collect(),
+        };
+
+        let current = self
+            .functions
+            .as_ref()
+            .and_then(|functions| functions.get(&spec));
+        let builtin = self
+            .int_arithmetic
+            .get(&spec)
+            .or_else(|| self.builtin_ops.get(&spec));
+
+        match (current, builtin) {
+            (Some(current), Some(builtin)) => is_same_fn(current, builtin),
+            // Op-assignments (e.g. `+=`) without a function of their own fall back to the operator
+            (None, None) => !crate::jit::OPERATORS.contains(&name),
+            _ => false,
+        }
+    }
+}
+
+/// Round a floating-point number to the nearest integer, rounding half-way cases to the even
+/// integer (i.e. banker's rounding) instead of away from zero.
+#[cfg(not(feature = "no_float"))]
+#[cfg(not(feature = "no_stdlib"))]
+fn round_even(x: FLOAT) -> FLOAT {
+    let rounded = x.round();
+
+    if (rounded - x).abs() == 0.5 {
+        2.0 * (x / 2.0).round()
+    } else {
+        rounded
+    }
+}
+
+/// Convert a floating-point number to a string with a number of decimal places,
+/// or the shortest representation with `None`.
+#[cfg(not(feature = "no_float"))]
+fn float_to_string<T: Display>(x: T, precision: Option<usize>) -> String {
+    match precision {
+        Some(digits) => format!("{:.*}", digits, x),
+        None => x.to_string(),
+    }
+}
+
+/// Get the result of a case conversion of a character.
+///
+/// Keeps the original character if the conversion results in more than one character
+/// (e.g. `'ß'` in upper-case is `"SS"`).
+#[cfg(not(feature = "no_stdlib"))]
+fn to_single_char(ch: char, mut converted: impl Iterator<Item = char>) -> char {
+    match (converted.next(), converted.next()) {
+        (Some(c), None) => c,
+        _ => ch,
+    }
+}
+
+/// Test two values for equality.
+///
+/// Arrays and object maps are compared structurally, element by element, and shared values by
+/// the values they hold.  Values of different types, or of types that are not built-in, are
+/// never equal.
+///
+/// Nested values are compared one level at a time instead of recursively, so that comparing
+/// deeply nested values cannot overflow the native stack.  Shared values can contain themselves
+/// (e.g. `set(s, [s])` via a host function), so each pair of shared values is compared once, and
+/// taken to be equal when met again: any difference is found by the first comparison.
+pub(crate) fn values_equal(x: &Variant, y: &Variant) -> bool {
+    // Pairs of arrays, object maps or shared values still to be compared
+    let mut pending: Vec<(Dynamic, Dynamic)> = Vec::new();
+    // Pairs of shared values already compared (or being compared), by address
+    let mut shared_pairs = HashSet::new();
+
+    if !shallow_equal(x, y, &mut pending) {
+        return false;
+    }
+
+    while let Some((x, y)) = pending.pop() {
+        let is_equal = if let (Some(x), Some(y)) = (
+            x.downcast_ref::<SharedValue>(),
+            y.downcast_ref::<SharedValue>(),
+        ) {
+            // Shared values which cannot be read (because they are being modified) are not equal
+            x.ptr_eq(y)
+                || !shared_pairs.insert((x.addr(), y.addr()))
+                || x.try_read(|x| y.try_read(|y| shallow_equal(x, y, &mut pending)))
+                    .and_then(|r| r)
+                    .unwrap_or(false)
+        } else {
+            nested_equal(x.as_ref(), y.as_ref(), &mut pending)
+        };
+
+        if !is_equal {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Compare the items of two arrays or the properties of two object maps with `shallow_equal`.
+fn nested_equal(x: &Variant, y: &Variant, pending: &mut Vec<(Dynamic, Dynamic)>) -> bool {
+    #[cfg(not(feature = "no_index"))]
+    {
+        if let (Some(x), Some(y)) = (x.downcast_ref::<Array>(), y.downcast_ref::<Array>()) {
+            return x.len() == y.len()
+                && x.iter()
+                    .zip(y.iter())
+                    .all(|(a, b)| shallow_equal(a.as_ref(), b.as_ref(), pending));
+        }
+    }
+
+    #[cfg(not(feature = "no_object"))]
+    {
+        if let (Some(x), Some(y)) = (x.downcast_ref::<Map>(), y.downcast_ref::<Map>()) {
+            return x.len() == y.len()
+                && x.iter().all(|(key, a)| match y.get(key) {
+                    Some(b) => shallow_equal(a.as_ref(), b.as_ref(), pending),
+                    None => false,
+                });
+        }
+    }
+
+    false
+}
+
+/// Test two values for equality without looking into arrays, object maps or shared values.
+///
+/// Two arrays, object maps or shared values are added to `pending` to be compared later,
+/// and taken to be equal for now.
+fn shallow_equal(x: &Variant, y: &Variant, pending: &mut Vec<(Dynamic, Dynamic)>) -> bool {
+    if Any::type_id(x) != Any::type_id(y) {
+        return false;
+    }
+
+    #[cfg(not(feature = "no_index"))]
+    let is_nested = x.is::<Array>();
+    #[cfg(feature = "no_index")]
+    let is_nested = false;
+
+    #[cfg(not(feature = "no_object"))]
+    let is_nested = is_nested || x.is::<Map>();
+
+    if is_nested || x.is::<SharedValue>() {
+        // Copying arrays, object maps and shared values only copies references to them
+        pending.push((x.into_dynamic(), y.into_dynamic()));
+        return true;
+    }
+
+    macro_rules! eq_as {
+        ($( $y:ty ),*) => (
+            $(
+                if let (Some(x), Some(y)) = (x.downcast_ref::<$y>(), y.downcast_ref::<$y>()) {
+                    return x == y;
+                }
+            )*
+        )
+    }
+
+    eq_as!(INT, String, char, bool, ());
+
+    #[cfg(not(feature = "only_i32"))]
+    #[cfg(not(feature = "only_i64"))]
+    eq_as!(i8, u8, i16, u16, i32, i64, u32, u64);
+
+    #[cfg(not(feature = "no_float"))]
+    eq_as!(f32, f64);
+
+    #[cfg(not(feature = "no_index"))]
+    eq_as!(Blob);
+
+    false
+}
+
+/// Check that the size of chunks (or windows) of an array is positive.
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+fn positive_size(size: INT, fn_name: &str) -> Result<usize, Box<EvalAltResult>> {
+    if size > 0 {
+        Ok(size as usize)
+    } else {
+        Err(Box::new(EvalAltResult::ErrorRuntime(
+            format!("The size for '{}' must be positive: {}", fn_name, size),
+            Position::none(),
+        )))
+    }
+}
+
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+impl Engine<'_> {
+    /// Sort an array in the natural order of its elements, which must be of built-in types
+    /// that can be compared with each other.
+    pub(crate) fn sort_natural(
+        &self,
+        list: &mut Array,
+        pos: Position,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let mut err = None;
+
+        list.sort_by(|x, y| {
+            compare_values(x.as_ref(), y.as_ref()).unwrap_or_else(|| {
+                err = err
+                    .take()
+                    .or_else(|| Some(((**x).type_name(), (**y).type_name())));
+                Ordering::Equal
+            })
+        });
+
+        match err {
+            None => Ok(()),
+            Some((x, y)) => Err(Box::new(EvalAltResult::ErrorRuntime(
+                format!(
+                    "Cannot sort an array containing '{}' and '{}'",
+                    self.map_type_name(x),
+                    self.map_type_name(y)
+                ),
+                pos,
+            ))),
+        }
+    }
+
+    /// Binary-search a sorted array for a value of a built-in type.
+    ///
+    /// Returns the index of an element equal to the value, or `-(index + 1)` where `index` is the
+    /// position at which the value could be inserted to keep the array sorted.
+    pub(crate) fn search_sorted(
+        &self,
+        list: &Array,
+        value: &Variant,
+        pos: Position,
+    ) -> Result<INT, Box<EvalAltResult>> {
+        let mut err = None;
+
+        let result = list.binary_search_by(|x| {
+            compare_values(x.as_ref(), value).unwrap_or_else(|| {
+                err = err.take().or_else(|| Some((**x).type_name()));
+                Ordering::Equal
+            })
+        });
+
+        match err {
+            None => Ok(result.map_or_else(|n| -(n as INT) - 1, |n| n as INT)),
+            Some(x) => Err(Box::new(EvalAltResult::ErrorRuntime(
+                format!(
+                    "Cannot search an array containing '{}' for '{}'",
+                    self.map_type_name(x),
+                    self.map_type_name(value.type_name())
+                ),
+                pos,
+            ))),
+        }
+    }
+}
+
+/// A set of array elements, for set-like operations on arrays.
+///
+/// Elements which can be hashed (see `MemoKey::of_element`) are looked up in a hash set, others
+/// (e.g. custom types) by comparing them one by one.  Elements containing floating-point numbers
+/// are compared by value rather than hashed, so that `0.0` and `-0.0` (or `[0.0]` and `[-0.0]`)
+/// are the same element.
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+#[derive(Default)]
+struct ElementSet<'a> {
+    hashed: HashSet<MemoKey>,
+    others: Vec<&'a Variant>,
+}
+
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+impl<'a> ElementSet<'a> {
+    fn contains(&self, value: &Variant) -> bool {
+        match MemoKey::of_element(value) {
+            Some(key) => self.hashed.contains(&key),
+            None => self.others.iter().any(|&x| values_equal(x, value)),
+        }
+    }
+
+    /// Add an element, returning `false` if it is already in the set.
+    fn insert(&mut self, value: &'a Variant) -> bool {
+        match MemoKey::of_element(value) {
+            Some(key) => self.hashed.insert(key),
+            None if self.contains(value) => false,
+            None => {
+                self.others.push(value);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+impl<'a> From<&'a Array> for ElementSet<'a> {
+    fn from(list: &'a Array) -> Self {
+        let mut set = Self::default();
+        list.iter().for_each(|x| {
+            set.insert(x.as_ref());
+        });
+        set
+    }
+}
+
+/// Compare two strings ignoring case, via the lowercase forms of their characters
+/// (independent of any locale).
+#[cfg(not(feature = "no_stdlib"))]
+fn cmp_ignore_case(x: &str, y: &str) -> Ordering {
+    x.chars()
+        .flat_map(char::to_lowercase)
+        .cmp(y.chars().flat_map(char::to_lowercase))
+}
+
+/// Compare two strings in natural order: runs of digits are compared as numbers
+/// (so `"file2"` comes before `"file10"`) and other characters are compared ignoring case.
+///
+/// Strings which are equal in this order (e.g. `"a1"` and `"A01"`) are compared as they are,
+/// so that only identical strings are equal.
+#[cfg(not(feature = "no_stdlib"))]
+fn cmp_natural(x: &str, y: &str) -> Ordering {
+    use crate::stdlib::{iter::Peekable, str::Chars};
+
+    fn take_digits(chars: &mut Peekable<Chars>) -> String {
+        let mut digits = String::new();
+        while let Some(ch) = chars.next_if(char::is_ascii_digit) {
+            digits.push(ch);
+        }
+        digits
+    }
+
+    let (mut xs, mut ys) = (x.chars().peekable(), y.chars().peekable());
+
+    loop {
+        let ordering = match (xs.peek(), ys.peek()) {
+            (None, None) => return x.cmp(y),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) if a.is_ascii_digit() && b.is_ascii_digit() => {
+                let (a, b) = (take_digits(&mut xs), take_digits(&mut ys));
+                let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+                // Numbers without leading zeros compare by length first
+                a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+            }
+            (Some(&a), Some(&b)) => {
+                xs.next();
+                ys.next();
+                a.to_lowercase().cmp(b.to_lowercase())
+            }
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Compare two values of the same built-in type.
+///
+/// Returns `None` if the values are of different types, or of a type that is not comparable.
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+fn compare_values(x: &Variant, y: &Variant) -> Option<Ordering> {
+    macro_rules! cmp_as {
+        ($( $y:ty ),*) => (
+            $(
+                if let (Some(x), Some(y)) = (x.downcast_ref::<$y>(), y.downcast_ref::<$y>()) {
+                    return x.partial_cmp(y);
+                }
+            )*
+        )
+    }
+
+    cmp_as!(INT, String, char, bool, ());
+
+    #[cfg(not(feature = "only_i32"))]
+    #[cfg(not(feature = "only_i64"))]
+    cmp_as!(i8, u8, i16, u16, i32, i64, u32, u64);
+
+    #[cfg(not(feature = "no_float"))]
+    cmp_as!(f32, f64);
+
+    None
+}
+
+/// Convert a value into JSON text.
+///
+/// Only built-in types are supported. Object map properties are written in sorted order,
+/// `()` becomes `null`, and so do floating-point numbers that are not finite.
+///
+/// Nested arrays and object maps are written one value at a time instead of recursively, so that
+/// converting deeply nested values cannot overflow the native stack.
+///
+/// On failure, returns the type name of the (possibly nested) value that cannot be converted.
+pub(crate) fn to_json(value: &Variant) -> Result<String, &'static str> {
+    /// A part of the JSON text still to be written.
+    enum Part<'a> {
+        Value(&'a Variant),
+        #[cfg(not(feature = "no_object"))]
+        Key(&'a str),
+        Text(&'static str),
+    }
+
+    let mut json = String::new();
+    let mut pending = vec![Part::Value(value)];
+
+    while let Some(part) = pending.pop() {
+        let value = match part {
+            Part::Value(value) => value,
+            #[cfg(not(feature = "no_object"))]
+            Part::Key(key) => {
+                write_json_str(&mut json, key);
+                json.push(':');
+                continue;
+            }
+            Part::Text(text) => {
+                json.push_str(text);
+                continue;
+            }
+        };
+
+        if write_json_scalar(&mut json, value) {
+            continue;
+        }
+
+        #[cfg(not(feature = "no_object"))]
+        {
+            if let Some(map) = value.downcast_ref::<Map>() {
+                json.push('{');
+                pending.push(Part::Text("}"));
+                for (i, (key, value)) in map.iter().enumerate().rev() {
+                    pending.push(Part::Value(value.as_ref()));
+                    pending.push(Part::Key(key));
+                    if i > 0 {
+                        pending.push(Part::Text(","));
+                    }
+                }
+                continue;
+            }
+        }
+
+        #[cfg(not(feature = "no_index"))]
+        {
+            if let Some(list) = value.downcast_ref::<Array>() {
+                json.push('[');
+                pending.push(Part::Text("]"));
+                for (i, item) in list.iter().enumerate().rev() {
+                    pending.push(Part::Value(item.as_ref()));
+                    if i > 0 {
+                        pending.push(Part::Text(","));
+                    }
+                }
+                continue;
+            }
+        }
+
+        return Err(value.type_name());
+    }
+
+    Ok(json)
+}
+
+/// Write a JSON string.
+fn write_json_str(json: &mut String, s: &str) {
+    json.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => json.push(ch),
+        }
+    }
+    json.push('"');
+}
+
+/// Write a value which does not contain other values (i.e. not an array or object map) as JSON
+/// text.  Returns `false` if the value is not of such a type.
+fn write_json_scalar(json: &mut String, value: &Variant) -> bool {
+    macro_rules! write_num {
+        ($( $y:ty ),*) => (
+            $(
+                if let Some(x) = value.downcast_ref::<$y>() {
+                    json.push_str(&x.to_string());
+                    return true;
+                }
+            )*
+        )
+    }
+
+    write_num!(INT);
+
+    #[cfg(not(feature = "only_i32"))]
+    #[cfg(not(feature = "only_i64"))]
+    write_num!(i8, u8, i16, u16, i32, i64, u32, u64);
+
+    #[cfg(not(feature = "no_float"))]
+    {
+        if let Some(x) = value.downcast_ref::<f32>() {
+            json.push_str(&if x.is_finite() {
+                x.to_string()
+            } else {
+                "null".into()
+            });
+            return true;
+        }
+        if let Some(x) = value.downcast_ref::<f64>() {
+            json.push_str(&if x.is_finite() {
+                x.to_string()
+            } else {
+                "null".into()
+            });
+            return true;
+        }
+    }
+
+    #[cfg(not(feature = "no_index"))]
+    {
+        if let Some(blob) = value.downcast_ref::<Blob>() {
+            json.push('[');
+            for (i, b) in blob.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                json.push_str(&b.to_string());
+            }
+            json.push(']');
+            return true;
+        }
+    }
+
+    if let Some(x) = value.downcast_ref::<bool>() {
+        json.push_str(if *x { "true" } else { "false" });
+    } else if value.is::<()>() {
+        json.push_str("null");
+    } else if let Some(s) = value.downcast_ref::<String>() {
+        write_json_str(json, s);
+    } else if let Some(ch) = value.downcast_ref::<char>() {
+        write_json_str(json, &ch.to_string());
+    } else {
+        return false;
+    }
+
+    true
+}
+
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode bytes as base64 text (standard alphabet, with padding).
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 4 / 3 + 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0_u32, |n, (i, &b)| n | (b as u32) << (16 - i * 8));
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(BASE64_CHARS[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+
+    s
+}
+
+/// Decode base64 text (standard alphabet, padding optional) into bytes.
+///
+/// Returns `None` if the text is not valid base64.
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(s.len() * 3 / 4);
+    let mut n = 0_u32;
+
+    for (i, ch) in s.bytes().enumerate() {
+        let v = BASE64_CHARS.iter().position(|&c| c == ch)? as u32;
+        n = n << 6 | v;
+
+        if i % 4 == 3 {
+            bytes.extend_from_slice(&[(n >> 16) as u8, (n >> 8) as u8, n as u8]);
+            n = 0;
+        }
+    }
+
+    match s.len() % 4 {
+        0 => (),
+        2 => bytes.push((n >> 4) as u8),
+        3 => bytes.extend_from_slice(&[(n >> 10) as u8, (n >> 2) as u8]),
+        _ => return None,
+    }
+
+    Some(bytes)
+}
+
+/// Encode bytes as hex text (two lower-case hex digits per byte).
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode hex text (two hex digits per byte, either case) into bytes.
+///
+/// Returns `None` if the text is not valid hex.
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 == 1 {
+        return None;
+    }
+
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let hi = (pair[0] as char).to_digit(16)?;
+            let lo = (pair[1] as char).to_digit(16)?;
+            Some((hi << 4 | lo) as u8)
+        })
+        .collect()
+}
+
+/// Create a runtime error for a BLOB function.
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+fn blob_err(msg: String) -> Box<EvalAltResult> {
+    Box::new(EvalAltResult::ErrorRuntime(msg, Position::none()))
+}
+
+/// Convert an integer into a byte of a BLOB.
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+fn to_byte(value: INT) -> Result<u8, Box<EvalAltResult>> {
+    u8::try_from(value)
+        .map_err(|_| blob_err(format!("Byte value {} is not between 0 and 255", value)))
+}
+
+/// Percent-encode bytes for use in a URL, keeping only unreserved characters
+/// (ASCII letters and digits, `-`, `_`, `.` and `~`) as they are.
+#[cfg(not(feature = "no_stdlib"))]
+fn encode_url(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len());
+
+    for &b in bytes {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                s.push(b as char)
+            }
+            _ => s.push_str(&format!("%{:02X}", b)),
+        }
+    }
+
+    s
+}
+
+/// Decode percent-encoded text into bytes.  `+` is kept as it is.
+///
+/// Returns `None` if a `%` is not followed by two hex digits.
+#[cfg(not(feature = "no_stdlib"))]
+fn decode_url(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hi = (iter.next()? as char).to_digit(16)?;
+            let lo = (iter.next()? as char).to_digit(16)?;
+            bytes.push((hi << 4 | lo) as u8);
+        } else {
+            bytes.push(b);
+        }
+    }
+
+    Some(bytes)
 }
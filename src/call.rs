@@ -4,7 +4,7 @@
 
 use crate::any::{Any, Dynamic};
 
-use crate::stdlib::{string::String, vec, vec::Vec};
+use crate::stdlib::vec::Vec;
 
 /// Trait that represent arguments to a function call.
 /// Any data type that can be converted into a `Vec` of `Dynamic` values can be used
@@ -36,6 +36,7 @@ macro_rules! impl_args {
     (@pop) => {
     };
     (@pop $head:ident) => {
+        impl_args!();
     };
     (@pop $head:ident $(, $tail:ident)+) => {
         impl_args!($($tail),*);
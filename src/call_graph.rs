@@ -0,0 +1,155 @@
+//! Module which extracts the static call graph of an `AST`.
+
+use crate::parser::{Expr, Nodes, Position, AST};
+use crate::visitor::{walk_stmt, ASTNode, ASTVisitor};
+
+use crate::stdlib::{
+    collections::{HashMap, HashSet},
+    ptr,
+    string::String,
+    vec::Vec,
+};
+
+/// A function call found in a script.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FnCall {
+    /// Name of the function called.  Operators are also function calls (e.g. `+`), and functions
+    /// in modules have qualified names (e.g. `math::sqrt`).
+    pub name: String,
+    /// Number of arguments, including the object of a method call.
+    pub args: usize,
+    /// Position of the call.
+    pub pos: Position,
+}
+
+/// The static call graph of an `AST`, i.e. the function calls made by its top-level statements and
+/// by each script-defined function.  See `AST::call_graph`.
+///
+/// The graph only contains calls that appear in the script.  Functions can also be called
+/// dynamically (e.g. by the host via `Engine::call_fn`, or by name via `eval` or `sort`),
+/// which the graph does not know about.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallGraph {
+    /// Function calls made by the top-level statements, in script order.
+    pub main: Vec<FnCall>,
+    /// Function calls made by each script-defined function (keyed by the name and number of
+    /// parameters of the function), in script order.
+    pub functions: HashMap<(String, usize), Vec<FnCall>>,
+}
+
+impl CallGraph {
+    /// Is there a script-defined function with a particular name and number of parameters?
+    pub fn is_script_fn(&self, name: &str, params: usize) -> bool {
+        self.functions.contains_key(&(name.into(), params))
+    }
+
+    /// Get all calls to functions that are not script-defined (i.e. built-in, registered or
+    /// missing functions, or functions in modules), in script order.
+    ///
+    /// Checking these against the functions registered by the host detects missing bindings.
+    pub fn external_calls(&self) -> Vec<&FnCall> {
+        let mut calls: Vec<_> = self
+            .functions
+            .values()
+            .flatten()
+            .chain(&self.main)
+            .filter(|call| !self.is_script_fn(&call.name, call.args))
+            .collect();
+
+        calls.sort_by_key(|call| call.pos);
+        calls
+    }
+
+    /// Get the name and number of parameters of all script-defined functions that can never be
+    /// called, directly or indirectly, from the top-level statements.
+    pub fn unreachable_functions(&self) -> Vec<(&str, usize)> {
+        let mut reached = HashSet::new();
+        let mut pending: Vec<_> = self.main.iter().collect();
+
+        while let Some(call) = pending.pop() {
+            let key = (call.name.clone(), call.args);
+
+            if let Some(calls) = self.functions.get(&key) {
+                if reached.insert(key) {
+                    pending.extend(calls);
+                }
+            }
+        }
+
+        let mut result: Vec<_> = self
+            .functions
+            .keys()
+            .filter(|key| !reached.contains(*key))
+            .map(|(name, params)| (name.as_str(), *params))
+            .collect();
+
+        result.sort();
+        result
+    }
+}
+
+/// Collect the function calls in a part of an `AST`.
+#[derive(Default)]
+struct CallCollector {
+    /// Function calls found.
+    calls: Vec<FnCall>,
+    /// Expressions on the right of a `.`, which are method calls with the object as first argument.
+    methods: Vec<*const Expr>,
+}
+
+impl ASTVisitor for CallCollector {
+    #[cfg_attr(feature = "no_object", allow(unused_variables))]
+    fn enter(&mut self, node: ASTNode, nodes: &Nodes) -> bool {
+        match node {
+            #[cfg(not(feature = "no_object"))]
+            ASTNode::Expr(Expr::Dot(_, rhs, _)) => {
+                let rhs = &nodes[*rhs];
+                self.methods.push(rhs);
+
+                // In a chain, the method is on the left
+                match rhs {
+                    Expr::Dot(lhs, _, _) => self.methods.push(&nodes[*lhs]),
+                    #[cfg(not(feature = "no_index"))]
+                    Expr::Index(lhs, _, _) => self.methods.push(&nodes[*lhs]),
+                    _ => (),
+                }
+            }
+
+            ASTNode::Expr(expr @ Expr::FunctionCall(name, args, _, _, pos)) => {
+                let is_method = self.methods.iter().any(|&method| ptr::eq(method, expr));
+
+                self.calls.push(FnCall {
+                    name: name.to_string(),
+                    args: args.len() + if is_method { 1 } else { 0 },
+                    pos: *pos,
+                });
+            }
+
+            _ => (),
+        }
+
+        true
+    }
+}
+
+/// Build the call graph of an `AST`.
+pub(crate) fn call_graph(ast: &AST) -> CallGraph {
+    let mut graph = CallGraph::default();
+
+    for fn_def in ast.1.iter() {
+        let mut collector = CallCollector::default();
+        walk_stmt(&fn_def.nodes, &fn_def.body, &mut collector);
+
+        graph
+            .functions
+            .insert((fn_def.name.clone(), fn_def.params.len()), collector.calls);
+    }
+
+    let mut collector = CallCollector::default();
+    for stmt in &ast.0 {
+        walk_stmt(&ast.4, stmt, &mut collector);
+    }
+    graph.main = collector.calls;
+
+    graph
+}
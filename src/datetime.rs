@@ -0,0 +1,199 @@
+//! Module which defines the `DateTime` type, a calendar date and time backed by the `chrono`
+//! crate, and registers its functions.
+
+// `INT` may be `i32` (under `only_i32`), so casting or converting it is not always unnecessary.
+#![allow(clippy::unnecessary_cast, clippy::useless_conversion)]
+
+use crate::engine::{Engine, FUNC_TO_STRING, KEYWORD_DEBUG, KEYWORD_PRINT};
+use crate::fn_register::{RegisterFn, RegisterResultFn};
+use crate::parser::{Position, INT};
+use crate::result::EvalAltResult;
+
+#[cfg(not(feature = "no_float"))]
+use crate::parser::FLOAT;
+
+use chrono::{Datelike, Duration, FixedOffset, Months, TimeZone, Timelike, Utc};
+
+use crate::stdlib::{boxed::Box, convert::TryFrom, fmt::Write, format, string::String};
+
+/// A calendar date and time with a time zone offset.
+///
+/// Only available under the `datetime` feature.
+pub type DateTime = chrono::DateTime<FixedOffset>;
+
+/// Make a runtime error.
+fn runtime_err(msg: String) -> Box<EvalAltResult> {
+    Box::new(EvalAltResult::ErrorRuntime(msg, Position::none()))
+}
+
+/// Make an error for a date/time out of the supported range.
+fn out_of_range() -> Box<EvalAltResult> {
+    Box::new(EvalAltResult::ErrorArithmetic(
+        "Date/time out of range".into(),
+        None,
+        Position::none(),
+    ))
+}
+
+/// Build a date/time in UTC from its components.
+fn from_components(
+    year: INT,
+    month: INT,
+    day: INT,
+    hour: INT,
+    minute: INT,
+    second: INT,
+) -> Result<DateTime, Box<EvalAltResult>> {
+    // Components out of range become invalid values, which are rejected by `chrono`
+    let to_i32 = |x: INT| i32::try_from(x).unwrap_or(i32::MAX);
+    let to_u32 = |x: INT| u32::try_from(x).unwrap_or(u32::MAX);
+
+    Utc.with_ymd_and_hms(
+        to_i32(year),
+        to_u32(month),
+        to_u32(day),
+        to_u32(hour),
+        to_u32(minute),
+        to_u32(second),
+    )
+    .single()
+    .map(|dt| dt.fixed_offset())
+    .ok_or_else(|| {
+        runtime_err(format!(
+            "Invalid date/time: {}-{}-{} {}:{}:{}",
+            year, month, day, hour, minute, second
+        ))
+    })
+}
+
+/// Add a duration to a date/time.
+fn add(dt: &DateTime, duration: Option<Duration>) -> Result<DateTime, Box<EvalAltResult>> {
+    duration
+        .and_then(|d| dt.checked_add_signed(d))
+        .ok_or_else(out_of_range)
+}
+
+/// Get a number of seconds as a duration.
+fn secs(seconds: INT) -> Option<Duration> {
+    Duration::try_seconds(seconds as i64)
+}
+
+/// Get a fractional number of seconds as a duration.
+#[cfg(not(feature = "no_float"))]
+fn secs_float(seconds: FLOAT) -> Option<Duration> {
+    let nanos = seconds * 1e9;
+
+    if nanos.is_finite() && nanos.abs() < i64::MAX as f64 {
+        Some(Duration::nanoseconds(nanos as i64))
+    } else {
+        None
+    }
+}
+
+/// Get the duration between two date/times as a number of seconds.
+#[cfg(not(feature = "no_float"))]
+fn seconds_between(x: &DateTime, y: &DateTime) -> FLOAT {
+    let d = x.signed_duration_since(*y);
+    d.num_seconds() as FLOAT + d.subsec_nanos() as FLOAT / 1e9
+}
+#[cfg(feature = "no_float")]
+fn seconds_between(x: &DateTime, y: &DateTime) -> INT {
+    x.signed_duration_since(*y).num_seconds() as INT
+}
+
+impl Engine<'_> {
+    /// Register the date/time package.
+    pub(crate) fn register_datetime(&mut self) {
+        // Constructors
+        self.register_fn("now", || Utc::now().fixed_offset());
+        self.register_result_fn("parse_datetime", |s: String| {
+            DateTime::parse_from_rfc3339(&s)
+                .map_err(|err| runtime_err(format!("Invalid date/time '{}': {}", s, err)))
+        });
+        self.register_result_fn("datetime", |year: INT, month: INT, day: INT| {
+            from_components(year, month, day, 0, 0, 0)
+        });
+        self.register_result_fn(
+            "datetime",
+            |year: INT, month: INT, day: INT, hour: INT, minute: INT, second: INT| {
+                from_components(year, month, day, hour, minute, second)
+            },
+        );
+
+        // Components
+        macro_rules! reg_component {
+            ($name:expr, $func:expr) => {
+                self.register_fn($name, |dt: &mut DateTime| $func(dt) as INT);
+                #[cfg(not(feature = "no_object"))]
+                self.register_get($name, |dt: &mut DateTime| $func(dt) as INT);
+            };
+        }
+
+        reg_component!("year", |dt: &DateTime| dt.year());
+        reg_component!("month", |dt: &DateTime| dt.month());
+        reg_component!("day", |dt: &DateTime| dt.day());
+        reg_component!("hour", |dt: &DateTime| dt.hour());
+        reg_component!("minute", |dt: &DateTime| dt.minute());
+        reg_component!("second", |dt: &DateTime| dt.second());
+        reg_component!("weekday", |dt: &DateTime| dt.weekday().number_from_monday());
+        reg_component!("day_of_year", |dt: &DateTime| dt.ordinal());
+        reg_component!("unix_time", |dt: &DateTime| dt.timestamp());
+
+        // Arithmetic
+        self.register_result_fn("+", |dt: DateTime, seconds: INT| add(&dt, secs(seconds)));
+        self.register_result_fn("-", |dt: DateTime, seconds: INT| {
+            add(&dt, secs(seconds).map(|d| -d))
+        });
+        #[cfg(not(feature = "no_float"))]
+        {
+            self.register_result_fn("+", |dt: DateTime, seconds: FLOAT| {
+                add(&dt, secs_float(seconds))
+            });
+            self.register_result_fn("-", |dt: DateTime, seconds: FLOAT| {
+                add(&dt, secs_float(-seconds))
+            });
+        }
+        self.register_fn("-", |x: DateTime, y: DateTime| seconds_between(&x, &y));
+
+        self.register_result_fn("add_days", |dt: &mut DateTime, days: INT| {
+            add(dt, Duration::try_days(days as i64))
+        });
+        self.register_result_fn("add_months", |dt: &mut DateTime, months: INT| {
+            let n = u32::try_from(months.unsigned_abs()).map_err(|_| out_of_range())?;
+
+            let result = if months >= 0 {
+                dt.checked_add_months(Months::new(n))
+            } else {
+                dt.checked_sub_months(Months::new(n))
+            };
+            result.ok_or_else(out_of_range)
+        });
+
+        // Comparisons
+        self.register_fn("==", |x: DateTime, y: DateTime| x == y);
+        self.register_fn("!=", |x: DateTime, y: DateTime| x != y);
+        self.register_fn("<", |x: DateTime, y: DateTime| x < y);
+        self.register_fn("<=", |x: DateTime, y: DateTime| x <= y);
+        self.register_fn(">", |x: DateTime, y: DateTime| x > y);
+        self.register_fn(">=", |x: DateTime, y: DateTime| x >= y);
+
+        // Formatting
+        self.register_result_fn("format", |dt: &mut DateTime, fmt: String| {
+            let mut result = String::new();
+            write!(result, "{}", dt.format(&fmt))
+                .map_err(|_| runtime_err(format!("Invalid date/time format: '{}'", fmt)))?;
+            Ok(result)
+        });
+
+        fn to_string(dt: &mut DateTime) -> String {
+            dt.to_rfc3339()
+        }
+
+        self.register_fn("to_rfc3339", to_string);
+        self.register_fn(KEYWORD_PRINT, to_string);
+        self.register_fn(FUNC_TO_STRING, to_string);
+        self.register_fn(KEYWORD_DEBUG, to_string);
+        self.register_fn("+", |s: String, dt: DateTime| s + &dt.to_rfc3339());
+        self.register_fn("+", |dt: DateTime, s: String| dt.to_rfc3339() + &s);
+    }
+}
@@ -0,0 +1,337 @@
+//! Module which implements deserializing `Dynamic` values into Rust types via `serde`, e.g. to turn
+//! the object map returned by a configuration script into a Rust struct.
+
+use crate::any::{Dynamic, Variant};
+use crate::parser::Position;
+use crate::result::EvalAltResult;
+
+#[cfg(not(feature = "no_index"))]
+use crate::engine::{Array, Blob};
+
+#[cfg(not(feature = "no_object"))]
+use crate::engine::Map;
+
+use serde::de::IntoDeserializer;
+use serde::de::{
+    self,
+    value::{SeqDeserializer as ByteSeqDeserializer, StrDeserializer},
+    DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::stdlib::{
+    boxed::Box,
+    fmt,
+    string::{String, ToString},
+};
+
+#[cfg(not(feature = "no_index"))]
+use crate::stdlib::slice;
+
+#[cfg(not(feature = "no_object"))]
+use crate::stdlib::collections::btree_map;
+
+impl de::Error for Box<EvalAltResult> {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Box::new(EvalAltResult::ErrorRuntime(
+            msg.to_string(),
+            Position::none(),
+        ))
+    }
+}
+
+/// Deserialize a `Dynamic` value (e.g. an object map returned by a script) into a Rust type
+/// implementing `serde::Deserialize`.
+///
+/// Unit, booleans, characters, strings, numbers, arrays, BLOB's and object maps are supported.
+/// Object maps deserialize into structs and maps, and arrays into sequences and tuples.
+/// `()` deserializes into `None`, and enums are either strings (unit variants) or object maps
+/// with a single property named after the variant.
+///
+/// Only available under the `serde` feature.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// # #[cfg(not(feature = "no_object"))]
+/// # {
+/// use rhai::{from_dynamic, Any, Engine, Map};
+/// use std::collections::HashMap;
+///
+/// let mut engine = Engine::new();
+/// let value = engine.eval::<Map>(r#"#{ a: 1, b: 2 }"#)?.into_dynamic();
+///
+/// let map: HashMap<String, i64> = from_dynamic(&value)?;
+/// assert_eq!(map["b"], 2);
+/// # }
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_dynamic<T: DeserializeOwned>(value: &Dynamic) -> Result<T, Box<EvalAltResult>> {
+    T::deserialize(DynamicDeserializer(value.as_ref(), 0))
+}
+
+/// The maximum nesting depth of the values deserialized, so that deserializing deeply nested
+/// arrays or object maps fails instead of overflowing the native stack.
+const MAX_DEPTH: usize = 128;
+
+/// Deserializer of a property or variant name.
+fn name_deserializer(name: &str) -> StrDeserializer<'_, Box<EvalAltResult>> {
+    name.into_deserializer()
+}
+
+/// Deserializer reading a value (by reference), nested at a depth within the value deserialized.
+#[derive(Clone, Copy)]
+struct DynamicDeserializer<'a>(&'a Variant, usize);
+
+impl<'a> DynamicDeserializer<'a> {
+    /// Make a deserializer reading a value nested at a depth, or fail if it is nested too deeply.
+    fn new(value: &'a Variant, depth: usize) -> Result<Self, Box<EvalAltResult>> {
+        if depth > MAX_DEPTH {
+            Err(de::Error::custom(format_args!(
+                "cannot deserialize values nested more than {} levels deep",
+                MAX_DEPTH
+            )))
+        } else {
+            Ok(Self(value, depth))
+        }
+    }
+}
+
+impl<'de, 'a> Deserializer<'de> for DynamicDeserializer<'a> {
+    type Error = Box<EvalAltResult>;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let value = self.0;
+
+        macro_rules! visit {
+            ($($type:ty => $visit:ident),*) => {
+                $(
+                    if let Some(&x) = value.downcast_ref::<$type>() {
+                        return visitor.$visit(x);
+                    }
+                )*
+            };
+        }
+
+        if value.is::<()>() {
+            return visitor.visit_unit();
+        }
+        if let Some(s) = value.downcast_ref::<String>() {
+            return visitor.visit_str(s);
+        }
+
+        visit!(bool => visit_bool, char => visit_char);
+        visit!(i64 => visit_i64, i32 => visit_i32, i16 => visit_i16, i8 => visit_i8);
+        visit!(u64 => visit_u64, u32 => visit_u32, u16 => visit_u16, u8 => visit_u8);
+
+        #[cfg(not(feature = "no_float"))]
+        visit!(f64 => visit_f64, f32 => visit_f32);
+
+        #[cfg(not(feature = "no_index"))]
+        {
+            if let Some(arr) = value.downcast_ref::<Array>() {
+                return visitor.visit_seq(ArrayDeserializer(arr.iter(), self.1 + 1));
+            }
+            if let Some(blob) = value.downcast_ref::<Blob>() {
+                return ByteSeqDeserializer::<_, Self::Error>::new(blob.iter().cloned())
+                    .deserialize_any(visitor);
+            }
+        }
+
+        #[cfg(not(feature = "no_object"))]
+        {
+            if let Some(map) = value.downcast_ref::<Map>() {
+                return visitor.visit_map(MapDeserializer {
+                    iter: map.iter(),
+                    value: None,
+                    depth: self.1 + 1,
+                });
+            }
+        }
+
+        Err(de::Error::custom(format_args!(
+            "cannot deserialize a value of type {}",
+            value.type_name()
+        )))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.0.is::<()>() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(DynamicDeserializer::new(self.0, self.1 + 1)?)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(DynamicDeserializer::new(self.0, self.1 + 1)?)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        // "variant" - unit variant
+        if let Some(s) = self.0.downcast_ref::<String>() {
+            return visitor.visit_enum(name_deserializer(s));
+        }
+
+        // #{ variant: value } - variant with data
+        #[cfg(not(feature = "no_object"))]
+        {
+            if let Some(map) = self.0.downcast_ref::<Map>() {
+                if map.len() == 1 {
+                    let (variant, value) = map.iter().next().unwrap();
+
+                    return visitor.visit_enum(EnumDeserializer {
+                        variant,
+                        value: value.as_ref(),
+                        depth: self.1 + 1,
+                    });
+                }
+            }
+        }
+
+        Err(de::Error::custom(format_args!(
+            "expecting a string or an object map with one property for an enum, not {}",
+            self.0.type_name()
+        )))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Access to the items of an array, and their nesting depth.
+#[cfg(not(feature = "no_index"))]
+struct ArrayDeserializer<'a>(slice::Iter<'a, Dynamic>, usize);
+
+#[cfg(not(feature = "no_index"))]
+impl<'de, 'a> SeqAccess<'de> for ArrayDeserializer<'a> {
+    type Error = Box<EvalAltResult>;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        match self.0.next() {
+            Some(item) => seed
+                .deserialize(DynamicDeserializer::new(item.as_ref(), self.1)?)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+/// Access to the properties of an object map.
+#[cfg(not(feature = "no_object"))]
+struct MapDeserializer<'a> {
+    iter: btree_map::Iter<'a, String, Dynamic>,
+    /// Value of the property whose name was read last.
+    value: Option<&'a Dynamic>,
+    /// Nesting depth of the values of the properties.
+    depth: usize,
+}
+
+#[cfg(not(feature = "no_object"))]
+impl<'de, 'a> MapAccess<'de> for MapDeserializer<'a> {
+    type Error = Box<EvalAltResult>;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.iter.next() {
+            Some((name, value)) => {
+                self.value = Some(value);
+                seed.deserialize(name_deserializer(name)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let value = self
+            .value
+            .take()
+            .expect("a property name should be read first");
+        seed.deserialize(DynamicDeserializer::new(value.as_ref(), self.depth)?)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Access to an enum variant with data, written as an object map with a single property.
+#[cfg(not(feature = "no_object"))]
+struct EnumDeserializer<'a> {
+    variant: &'a str,
+    value: &'a Variant,
+    /// Nesting depth of the value of the variant.
+    depth: usize,
+}
+
+#[cfg(not(feature = "no_object"))]
+impl<'de, 'a> EnumAccess<'de> for EnumDeserializer<'a> {
+    type Error = Box<EvalAltResult>;
+    type Variant = DynamicDeserializer<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant = seed.deserialize(name_deserializer(self.variant))?;
+        Ok((variant, DynamicDeserializer::new(self.value, self.depth)?))
+    }
+}
+
+#[cfg(not(feature = "no_object"))]
+impl<'de, 'a> VariantAccess<'de> for DynamicDeserializer<'a> {
+    type Error = Box<EvalAltResult>;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+}
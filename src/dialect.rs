@@ -0,0 +1,171 @@
+//! Module which defines `Dialect`, a profile of the language features allowed in scripts.
+
+use crate::parser::Token;
+
+use crate::stdlib::{borrow::Cow, collections::HashSet};
+
+/// A profile of the keywords, operators and literal forms allowed in scripts, so that the same
+/// `Engine` can power both the full scripting language and a locked-down language
+/// (e.g. for formulas).
+///
+/// A `Dialect` is built via `const` methods, so that it can be defined once as a constant and
+/// applied via `Engine::set_dialect` (or `EngineBuilder::with_dialect`).
+///
+/// Scripts using anything not allowed by the dialect fail to compile with `LexError::DisabledSymbol`.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Dialect, Engine, INT};
+///
+/// const FORMULA: Dialect = Dialect::FULL
+///     .allow_only(&["+", "-", "*", "/", "(", ")", ",", "true", "false", "if", "else", "{", "}", ">", "<"])
+///     .disable(&["eval", "print", "debug"])
+///     .without_strings();
+///
+/// let mut engine = Engine::builder().with_dialect(&FORMULA).build();
+///
+/// assert_eq!(engine.eval::<INT>("if 2 > 1 { (1 + 2) * 3 } else { 0 }")?, 9);
+///
+/// assert!(engine.compile("let x = 1;").is_err());
+/// assert!(engine.compile("while true {}").is_err());
+/// assert!(engine.compile(r#"print("hello")"#).is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Dialect {
+    allowed_symbols: Option<&'static [&'static str]>,
+    disabled_symbols: &'static [&'static str],
+    strings: bool,
+    chars: bool,
+    floats: bool,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+impl Dialect {
+    /// The full language, with nothing disabled.
+    pub const FULL: Self = Self {
+        allowed_symbols: None,
+        disabled_symbols: &[],
+        strings: true,
+        chars: true,
+        floats: true,
+    };
+
+    /// Only allow the specified keywords, operators and punctuation (e.g. `"if"`, `"+"`, `"("`),
+    /// disabling all others.  Identifiers are not affected.
+    pub const fn allow_only(self, symbols: &'static [&'static str]) -> Self {
+        Self {
+            allowed_symbols: Some(symbols),
+            ..self
+        }
+    }
+
+    /// Disable the specified keywords, operators or identifiers (e.g. `"eval"`),
+    /// as `Engine::disable_symbol` does.
+    pub const fn disable(self, symbols: &'static [&'static str]) -> Self {
+        Self {
+            disabled_symbols: symbols,
+            ..self
+        }
+    }
+
+    /// Disable string literals.
+    pub const fn without_strings(self) -> Self {
+        Self {
+            strings: false,
+            ..self
+        }
+    }
+
+    /// Disable character literals.
+    pub const fn without_chars(self) -> Self {
+        Self {
+            chars: false,
+            ..self
+        }
+    }
+
+    /// Disable floating-point literals.
+    ///
+    /// Not available under the `no_float` feature.
+    #[cfg(not(feature = "no_float"))]
+    pub const fn without_floats(self) -> Self {
+        Self {
+            floats: false,
+            ..self
+        }
+    }
+}
+
+/// A `Dialect` applied to an `Engine`, with symbols kept in hash sets for quick look-ups
+/// during lexing.
+#[derive(Debug, Clone)]
+pub(crate) struct DialectRules {
+    allowed_symbols: Option<HashSet<&'static str>>,
+    disabled_symbols: HashSet<&'static str>,
+    dialect: Dialect,
+}
+
+impl DialectRules {
+    pub fn new(dialect: &Dialect) -> Self {
+        Self {
+            allowed_symbols: dialect
+                .allowed_symbols
+                .map(|symbols| symbols.iter().copied().collect()),
+            disabled_symbols: dialect.disabled_symbols.iter().copied().collect(),
+            dialect: *dialect,
+        }
+    }
+
+    /// Get the name of the symbol or literal form of a token, if it is not allowed.
+    pub fn check(&self, token: &Token) -> Option<Cow<'static, str>> {
+        match token {
+            Token::StringConst(_) if !self.dialect.strings => Some("string".into()),
+            Token::CharConstant(_) if !self.dialect.chars => Some("character".into()),
+            #[cfg(not(feature = "no_float"))]
+            Token::FloatConstant(_) if !self.dialect.floats => Some("floating-point number".into()),
+
+            Token::IntegerConstant(_)
+            | Token::StringConst(_)
+            | Token::CharConstant(_)
+            | Token::Label(_)
+            | Token::DocComment(_)
+            | Token::Comment(_)
+            | Token::LexError(_) => None,
+            #[cfg(not(feature = "no_float"))]
+            Token::FloatConstant(_) => None,
+
+            // Identifiers can only be disabled
+            Token::Identifier(name) | Token::Custom(name, _) => {
+                if self.disabled_symbols.contains(name.as_str()) {
+                    Some(name.clone().into())
+                } else {
+                    None
+                }
+            }
+
+            token => {
+                let symbol = token.syntax();
+
+                let allowed = match &self.allowed_symbols {
+                    Some(allowed) => allowed.contains(symbol.as_ref()),
+                    None => true,
+                };
+
+                if allowed && !self.disabled_symbols.contains(symbol.as_ref()) {
+                    None
+                } else {
+                    Some(symbol.into_owned().into())
+                }
+            }
+        }
+    }
+}
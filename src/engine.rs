@@ -1,23 +1,87 @@
 //! Main module defining the script evaluation `Engine`.
 
 use crate::any::{Any, AnyExt, Dynamic, Variant};
-use crate::parser::{Expr, FnDef, Position, ReturnType, Stmt, AST, INT};
+use crate::borrowed::{make_owned, to_owned_value};
+use crate::builtin::OverflowMode;
+use crate::dialect::{Dialect, DialectRules};
+use crate::memo::MemoCache;
+use crate::parser::{
+    lex, CustomKeyword, Expr, FnDef, Nodes, Position, ReturnType, Shadowing, Stmt, Token,
+    WithNodes, AST, INT, MAX_EXPR_DEPTH,
+};
+use crate::pipeline::Pipeline;
 use crate::result::EvalAltResult;
 use crate::scope::{EntryRef as ScopeSource, EntryType as ScopeEntryType, Scope};
 
+use crate::shared::{Shared, SharedValue};
+use crate::widening::widen_int_args;
+
+#[cfg(not(feature = "no_std"))]
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm-bindgen"))]
+use crate::builtin::Instant;
+
+#[cfg(not(feature = "no_index"))]
+use crate::parser::INT_BITS;
+
+#[cfg(not(feature = "no_module"))]
+use crate::module::{Imports, ModuleResolver, SharedModule};
+
+#[cfg(not(feature = "no_module"))]
+#[cfg(not(feature = "no_std"))]
+use crate::module::FileModuleResolver;
+
 #[cfg(not(feature = "no_optimize"))]
 use crate::optimize::OptimizationLevel;
 
+#[cfg(feature = "jit")]
+use crate::jit::Jit;
+
+#[cfg(feature = "matrix")]
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_float"))]
+use crate::matrix::{get_row, set_row, Matrix};
+
+#[cfg(feature = "datetime")]
+use crate::datetime::DateTime;
+
+#[cfg(feature = "regex")]
+use ::regex::Regex;
+
+#[cfg(not(feature = "no_object"))]
+use crate::stdlib::collections::BTreeMap;
+
+#[cfg(not(feature = "no_index"))]
+use crate::stdlib::convert::TryFrom;
+
+#[cfg(not(feature = "no_module"))]
+use crate::stdlib::mem;
+
+#[cfg(not(feature = "sync"))]
+use crate::stdlib::rc::Rc;
+
+use smallstr::SmallString;
+use smallvec::{smallvec, SmallVec};
+
+#[cfg(feature = "rand")]
+use rand::{rngs::StdRng, SeedableRng};
+
+#[cfg(feature = "rand")]
+#[cfg(feature = "sync")]
+use crate::stdlib::sync::Mutex;
+
+#[cfg(feature = "rand")]
+#[cfg(not(feature = "sync"))]
+use crate::stdlib::cell::RefCell;
+
 use crate::stdlib::{
     any::{type_name, TypeId},
     borrow::Cow,
     boxed::Box,
     cmp::Ordering,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     format,
-    iter::once,
+    iter::{once, successors},
     ops::{Deref, DerefMut},
-    rc::Rc,
     string::{String, ToString},
     sync::Arc,
     vec,
@@ -26,35 +90,137 @@ use crate::stdlib::{
 
 /// An dynamic array of `Dynamic` values.
 ///
+/// Arrays are copy-on-write, so copying an array is cheap until one of the copies is modified.
+///
+/// Not available under the `no_index` feature.
+#[cfg(not(feature = "no_index"))]
+pub type Array = Shared<Vec<Dynamic>>;
+
+/// A BLOB (binary large object), which is a dynamic array of bytes.
+///
 /// Not available under the `no_index` feature.
 #[cfg(not(feature = "no_index"))]
-pub type Array = Vec<Dynamic>;
+pub type Blob = Vec<u8>;
 
-/// An dynamic hash map of `Dynamic` values with `String` keys.
+/// An dynamic map of `Dynamic` values with `String` keys.
+///
+/// Object maps are copy-on-write, so copying an object map is cheap until one of the copies is modified.
+/// Properties are always iterated in sorted order of their names, so that the results of scripts
+/// (e.g. printing all properties) are deterministic.
 ///
 /// Not available under the `no_object` feature.
 #[cfg(not(feature = "no_object"))]
-pub type Map = HashMap<String, Dynamic>;
+pub type Map = Shared<BTreeMap<String, Dynamic>>;
 
 pub type FnCallArgs<'a> = [&'a mut Variant];
 
+/// A random number generator shared between an `Engine` and its random number functions.
+#[cfg(feature = "rand")]
+#[cfg(feature = "sync")]
+pub(crate) type SharedRng = Arc<Mutex<StdRng>>;
+/// A random number generator shared between an `Engine` and its random number functions.
+#[cfg(feature = "rand")]
+#[cfg(not(feature = "sync"))]
+pub(crate) type SharedRng = Rc<RefCell<StdRng>>;
+
+/// Create a new random number generator seeded from system entropy.
+#[cfg(feature = "rand")]
+fn new_shared_rng() -> SharedRng {
+    #[cfg(feature = "sync")]
+    return Arc::new(Mutex::new(StdRng::from_entropy()));
+    #[cfg(not(feature = "sync"))]
+    return Rc::new(RefCell::new(StdRng::from_entropy()));
+}
+
+/// Run a closure with mutable access to a shared random number generator.
+#[cfg(feature = "rand")]
+pub(crate) fn with_rng<T>(rng: &SharedRng, f: impl FnOnce(&mut StdRng) -> T) -> T {
+    #[cfg(feature = "sync")]
+    return f(&mut rng.lock().unwrap());
+    #[cfg(not(feature = "sync"))]
+    return f(&mut rng.borrow_mut());
+}
+
+#[cfg(feature = "sync")]
+pub type FnAny =
+    dyn Fn(&mut FnCallArgs, Position) -> Result<Dynamic, Box<EvalAltResult>> + Send + Sync;
+#[cfg(not(feature = "sync"))]
+pub type FnAny = dyn Fn(&mut FnCallArgs, Position) -> Result<Dynamic, Box<EvalAltResult>>;
+
+/// A registered native function, shared by an `Engine` and its duplicates.
+#[cfg(feature = "sync")]
+pub(crate) type SharedFnAny = Arc<FnAny>;
+/// A registered native function, shared by an `Engine` and its duplicates.
+#[cfg(not(feature = "sync"))]
+pub(crate) type SharedFnAny = Rc<FnAny>;
+
+/// A callback auditing calls to native functions, shared between an `Engine` and its duplicates.
+#[cfg(feature = "sync")]
+pub(crate) type SharedCallFilter<'e> =
+    Arc<dyn Fn(&str, &[&str], Position) -> bool + Send + Sync + 'e>;
+/// A callback auditing calls to native functions, shared between an `Engine` and its duplicates.
+#[cfg(not(feature = "sync"))]
+pub(crate) type SharedCallFilter<'e> = Rc<dyn Fn(&str, &[&str], Position) -> bool + 'e>;
+
+#[cfg(feature = "sync")]
+type AliasCallback<'e> = dyn FnMut(&str, &str, Option<&str>, Position) + Send + Sync + 'e;
+#[cfg(not(feature = "sync"))]
+type AliasCallback<'e> = dyn FnMut(&str, &str, Option<&str>, Position) + 'e;
+
 #[cfg(feature = "sync")]
-pub type FnAny = dyn Fn(&mut FnCallArgs, Position) -> Result<Dynamic, EvalAltResult> + Send + Sync;
+type DebugCallback<'e> = dyn FnMut(&str, Option<&str>, Position) + Send + Sync + 'e;
 #[cfg(not(feature = "sync"))]
-pub type FnAny = dyn Fn(&mut FnCallArgs, Position) -> Result<Dynamic, EvalAltResult>;
+type DebugCallback<'e> = dyn FnMut(&str, Option<&str>, Position) + 'e;
+
+#[cfg(feature = "sync")]
+type DefVarCallback<'e> = dyn FnMut(&str, bool, usize, &Scope) -> bool + Send + Sync + 'e;
+#[cfg(not(feature = "sync"))]
+type DefVarCallback<'e> = dyn FnMut(&str, bool, usize, &Scope) -> bool + 'e;
 
 #[cfg(feature = "sync")]
 type IteratorFn = dyn Fn(&Dynamic) -> Box<dyn Iterator<Item = Dynamic>> + Send + Sync;
 #[cfg(not(feature = "sync"))]
 type IteratorFn = dyn Fn(&Dynamic) -> Box<dyn Iterator<Item = Dynamic>>;
 
+/// A registered type iterator, shared by an `Engine` and its duplicates.
+#[cfg(feature = "sync")]
+type SharedIteratorFn = Arc<IteratorFn>;
+/// A registered type iterator, shared by an `Engine` and its duplicates.
+#[cfg(not(feature = "sync"))]
+type SharedIteratorFn = Rc<IteratorFn>;
+
 pub const MAX_CALL_STACK_DEPTH: usize = 64;
+pub const MAX_STACK_SIZE: usize = 1024 * 1024;
+#[cfg(not(feature = "no_index"))]
+pub const MAX_BLOB_SIZE: usize = 16 * 1024 * 1024;
 pub const KEYWORD_PRINT: &str = "print";
 pub const KEYWORD_DEBUG: &str = "debug";
 pub const KEYWORD_DUMP_AST: &str = "dump_ast";
 pub const KEYWORD_TYPE_OF: &str = "type_of";
 pub const KEYWORD_EVAL: &str = "eval";
+pub const KEYWORD_IS_DEF_VAR: &str = "is_def_var";
+pub const KEYWORD_IS_DEF_FN: &str = "is_def_fn";
+#[cfg(not(feature = "no_object"))]
+pub const KEYWORD_ENV: &str = "ENV";
+pub const KEYWORD_FN_METADATA_LIST: &str = "get_fn_metadata_list";
 pub const FUNC_TO_STRING: &str = "to_string";
+pub const FUNC_FORMAT: &str = "format";
+#[cfg(not(feature = "no_stdlib"))]
+pub const FUNC_TO_JSON: &str = "to_json";
+#[cfg(not(feature = "no_index"))]
+pub const FUNC_SORT: &str = "sort";
+#[cfg(not(feature = "no_index"))]
+pub const FUNC_BINARY_SEARCH: &str = "binary_search";
+#[cfg(not(feature = "no_object"))]
+pub const FUNC_FILTER: &str = "filter";
+pub const FUNC_ITER: &str = "iter";
+#[cfg(not(feature = "no_index"))]
+pub const FUNC_COLLECT: &str = "collect";
+pub const FUNC_COUNT: &str = "count";
+pub const FUNC_SHARED: &str = "shared";
+pub const FUNC_SHARED_GET: &str = "get";
+pub const FUNC_SHARED_SET: &str = "set";
+pub const FUNC_MEMOIZE: &str = "memoize";
 pub const FUNC_GETTER: &str = "get$";
 pub const FUNC_SETTER: &str = "set$";
 
@@ -64,8 +230,13 @@ enum IndexSourceType {
     Expression,
     String,
     Array,
+    Blob,
+    BitField,
     #[cfg(not(feature = "no_object"))]
     Map,
+    #[cfg(feature = "matrix")]
+    #[cfg(not(feature = "no_float"))]
+    Matrix,
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
@@ -95,6 +266,11 @@ impl IndexValue {
     }
 }
 
+/// Values indexed into along a chain of indexing (e.g. `x` and `x[i]` in `x[i][j]`), each with
+/// the index value into it.
+#[cfg(not(feature = "no_index"))]
+type IndexChain = Vec<(Dynamic, IndexValue)>;
+
 #[derive(Debug)]
 enum Target<'a> {
     Scope(ScopeSource<'a>),
@@ -116,10 +292,31 @@ impl<'a> Target<'a> {
     }
 }
 
+/// A vector which keeps up to four items inline.  Most function calls have few arguments, so
+/// this avoids allocations on the hot path of calling functions.
+pub(crate) type StaticVec<T> = SmallVec<[T; 4]>;
+
+/// A string which keeps up to 16 bytes inline, for names of variables and functions which are
+/// created while running scripts.
+pub(crate) type Identifier = SmallString<[u8; 16]>;
+
+/// Statistics about the work done by an evaluation, for profiling scripts and guarding against
+/// performance regressions in the evaluator.  See `Engine::last_eval_stats`.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct EvalStats {
+    /// Number of statements and expressions evaluated.
+    pub operations: u64,
+    /// Number of function calls, both script-defined and native (including operators such as `+`).
+    pub fn_calls: u64,
+    /// Number of namespace-qualified calls to functions of imported modules (e.g. `m::f(x)`)
+    /// which reused the function found by an earlier call from the same place in the script.
+    pub cached_calls: u64,
+}
+
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub struct FnSpec<'a> {
     pub name: Cow<'a, str>,
-    pub args: Vec<TypeId>,
+    pub args: StaticVec<TypeId>,
 }
 
 /// A type that holds a library of script-defined functions.
@@ -187,15 +384,12 @@ impl FunctionsLib {
         } else {
             let mut functions = self.clone();
 
+            // Keep the list sorted, so that functions with the same name but different numbers of
+            // parameters can all be found
             other.iter().cloned().for_each(|fn_def| {
-                if let Some((n, _)) = functions
-                    .iter()
-                    .enumerate()
-                    .find(|(_, f)| f.name == fn_def.name && f.params.len() == fn_def.params.len())
-                {
-                    functions[n] = fn_def;
-                } else {
-                    functions.push(fn_def);
+                match functions.binary_search_by(|f| f.compare(&fn_def.name, fn_def.params.len())) {
+                    Ok(n) => functions[n] = fn_def,
+                    Err(n) => functions.insert(n, fn_def),
                 }
             });
 
@@ -229,7 +423,7 @@ impl DerefMut for FunctionsLib {
 /// Rhai main scripting engine.
 ///
 /// ```
-/// # fn main() -> Result<(), rhai::EvalAltResult> {
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
 /// use rhai::Engine;
 ///
 /// let mut engine = Engine::new();
@@ -244,7 +438,13 @@ impl DerefMut for FunctionsLib {
 /// Currently, `Engine` is neither `Send` nor `Sync`. Turn on the `sync` feature to make it `Send + Sync`.
 pub struct Engine<'e> {
     /// A hashmap containing all compiled functions known to the engine.
-    pub(crate) functions: Option<HashMap<FnSpec<'e>, Box<FnAny>>>,
+    /// It is shared with duplicates of the engine until either one registers a new function.
+    #[cfg(feature = "sync")]
+    pub(crate) functions: Option<Arc<HashMap<FnSpec<'e>, SharedFnAny>>>,
+    /// A hashmap containing all compiled functions known to the engine.
+    /// It is shared with duplicates of the engine until either one registers a new function.
+    #[cfg(not(feature = "sync"))]
+    pub(crate) functions: Option<Rc<HashMap<FnSpec<'e>, SharedFnAny>>>,
 
     /// A hashmap containing all script-defined functions.
     #[cfg(feature = "sync")]
@@ -254,7 +454,7 @@ pub struct Engine<'e> {
     pub(crate) fn_lib: Option<Rc<FunctionsLib>>,
 
     /// A hashmap containing all iterators known to the engine.
-    pub(crate) type_iterators: Option<HashMap<TypeId, Box<IteratorFn>>>,
+    pub(crate) type_iterators: Option<Shared<HashMap<TypeId, SharedIteratorFn>>>,
     /// A hashmap mapping type names to pretty-print names.
     pub(crate) type_names: Option<HashMap<String, String>>,
 
@@ -266,11 +466,22 @@ pub struct Engine<'e> {
     pub(crate) on_print: Option<Box<dyn FnMut(&str) + 'e>>,
 
     /// Closure for implementing the `debug` command.
-    #[cfg(feature = "sync")]
-    pub(crate) on_debug: Option<Box<dyn FnMut(&str) + Send + Sync + 'e>>,
-    /// Closure for implementing the `debug` command.
-    #[cfg(not(feature = "sync"))]
-    pub(crate) on_debug: Option<Box<dyn FnMut(&str) + 'e>>,
+    pub(crate) on_debug: Option<Box<DebugCallback<'e>>>,
+
+    /// Closure for filtering variable definitions.
+    pub(crate) on_def_var: Option<Box<DefVarCallback<'e>>>,
+
+    /// Closure for auditing calls to native functions.
+    pub(crate) on_call: Option<SharedCallFilter<'e>>,
+
+    /// Capability tags of native functions, keyed by function name.
+    pub(crate) fn_tags: HashMap<String, Vec<String>>,
+    /// Aliases of native functions (e.g. old names of renamed functions), mapped to the functions' names.
+    pub(crate) fn_aliases: HashMap<String, String>,
+    /// Closure notified whenever a script calls a native function via an alias.
+    pub(crate) on_fn_alias: Option<Box<AliasCallback<'e>>>,
+    /// Capabilities granted to scripts, or `None` for all capabilities.
+    pub(crate) capabilities: Option<Vec<String>>,
 
     /// Optimize the AST after compilation.
     #[cfg(not(feature = "no_optimize"))]
@@ -278,6 +489,90 @@ pub struct Engine<'e> {
 
     /// Maximum levels of call-stack to prevent infinite recursion.
     pub(crate) max_call_stack_depth: usize,
+    /// Maximum number of bytes of native stack used during an evaluation.
+    pub(crate) max_stack_size: usize,
+    /// Maximum nesting depth of expressions and statements in a script.
+    pub(crate) max_expr_depth: usize,
+    /// Maximum number of bytes in a BLOB created or grown by a script.
+    #[cfg(not(feature = "no_index"))]
+    pub(crate) max_blob_size: usize,
+    /// Address of the native stack at the start of the outermost evaluation currently running.
+    pub(crate) stack_base: Option<usize>,
+    /// Statistics of the outermost evaluation currently running, or the last one.
+    pub(crate) stats: EvalStats,
+    /// Cached results of the script-defined functions memoized (via `memoize`) during the
+    /// outermost evaluation currently running.
+    pub(crate) memo: MemoCache,
+    /// Number of decimal places shown when converting floating-point numbers to strings.
+    #[cfg(not(feature = "no_float"))]
+    pub(crate) float_precision: Option<usize>,
+    /// How the arithmetic operators on integers handle overflow.
+    pub(crate) overflow_mode: OverflowMode,
+    /// The arithmetic operators on integers registered for the overflow mode, which are replaced
+    /// when it changes.
+    pub(crate) int_arithmetic: HashMap<FnSpec<'e>, SharedFnAny>,
+    /// The operators registered with the core library, which compiled code may implement itself
+    /// as long as they are not replaced by the host.
+    #[cfg(feature = "jit")]
+    pub(crate) builtin_ops: HashMap<FnSpec<'e>, SharedFnAny>,
+
+    /// Capture doc-comments on script-defined functions during compilation?
+    pub(crate) doc_comments: bool,
+    /// Keep all comments in the `AST` during compilation?
+    pub(crate) comments: bool,
+    /// Discard non-`()` results when evaluating a script for `()`?
+    pub(crate) unit_discard: bool,
+    /// Convert integer arguments to other integer types to find a matching function?
+    pub(crate) int_widening: bool,
+    /// Registered functions grouped by name, used to find functions over other integer types.
+    /// It is built on the first call needing it, and dropped whenever a function is registered.
+    pub(crate) fn_index: Option<HashMap<String, Vec<FnSpec<'e>>>>,
+
+    /// Source name of the script currently running, if any.
+    pub(crate) source: Option<String>,
+
+    /// Pending tail call (function name and arguments) to be run in place of the current
+    /// script-defined function.
+    pub(crate) tail_call: Option<(Identifier, StaticVec<Dynamic>)>,
+
+    /// Keywords, operators and identifiers that are not allowed in scripts.
+    pub(crate) disabled_symbols: HashSet<String>,
+    /// Names that cannot be used for variables or functions in scripts.
+    pub(crate) reserved_symbols: HashSet<String>,
+    /// Which variable declarations may reuse the names of variables already declared.
+    pub(crate) shadowing: Shadowing,
+    /// Keywords, operators and literal forms allowed in scripts, if restricted via a `Dialect`.
+    pub(crate) dialect: Option<DialectRules>,
+    /// Identifiers that are custom keywords mapping to functions.
+    pub(crate) custom_keywords: HashMap<String, CustomKeyword>,
+    /// Read-only variables provided by the host (e.g. `ENV`), visible to all scripts.
+    /// They are shared so that scripts can read (e.g. index) them in place, without copying them.
+    pub(crate) globals: HashMap<String, Shared<Dynamic>>,
+
+    /// Random number generator shared by all the random number functions.
+    #[cfg(feature = "rand")]
+    pub(crate) rng: SharedRng,
+
+    /// Module resolution service used to load modules for `import` statements.
+    #[cfg(not(feature = "no_module"))]
+    #[cfg(feature = "sync")]
+    pub(crate) module_resolver: Option<Arc<dyn ModuleResolver>>,
+    /// Module resolution service used to load modules for `import` statements.
+    #[cfg(not(feature = "no_module"))]
+    #[cfg(not(feature = "sync"))]
+    pub(crate) module_resolver: Option<Rc<dyn ModuleResolver>>,
+
+    /// Load imported modules during compilation, to check that imports can be resolved?
+    #[cfg(not(feature = "no_module"))]
+    pub(crate) import_validation: bool,
+
+    /// Modules and functions imported by the running script.
+    #[cfg(not(feature = "no_module"))]
+    pub(crate) imports: Imports,
+
+    /// JIT compiler state.
+    #[cfg(feature = "jit")]
+    pub(crate) jit: Jit,
 }
 
 impl Default for Engine<'_> {
@@ -286,9 +581,33 @@ impl Default for Engine<'_> {
         let type_names = [
             #[cfg(not(feature = "no_index"))]
             (type_name::<Array>(), "array"),
+            #[cfg(not(feature = "no_index"))]
+            (type_name::<Blob>(), "blob"),
             #[cfg(not(feature = "no_object"))]
             (type_name::<Map>(), "map"),
+            #[cfg(feature = "matrix")]
+            #[cfg(not(feature = "no_index"))]
+            #[cfg(not(feature = "no_float"))]
+            (type_name::<Matrix>(), "matrix"),
+            (type_name::<Pipeline>(), "pipeline"),
             (type_name::<String>(), "string"),
+            (type_name::<&str>(), "string"),
+            (type_name::<Cow<str>>(), "string"),
+            (type_name::<Arc<str>>(), "string"),
+            #[cfg(not(feature = "no_index"))]
+            (type_name::<&[u8]>(), "blob"),
+            #[cfg(not(feature = "no_index"))]
+            (type_name::<Cow<[u8]>>(), "blob"),
+            #[cfg(not(feature = "no_index"))]
+            (type_name::<Arc<[u8]>>(), "blob"),
+            (type_name::<SharedValue>(), "shared"),
+            #[cfg(not(feature = "no_std"))]
+            #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-bindgen"))]
+            (type_name::<Instant>(), "timestamp"),
+            #[cfg(feature = "datetime")]
+            (type_name::<DateTime>(), "datetime"),
+            #[cfg(feature = "regex")]
+            (type_name::<Regex>(), "regex"),
             (type_name::<Dynamic>(), "dynamic"),
         ]
         .iter()
@@ -302,7 +621,13 @@ impl Default for Engine<'_> {
             type_iterators: None,
             type_names: Some(type_names),
             on_print: Some(Box::new(default_print)), // default print/debug implementations
-            on_debug: Some(Box::new(default_print)),
+            on_debug: Some(Box::new(default_debug)),
+            on_def_var: None,
+            on_call: None,
+            fn_tags: HashMap::new(),
+            fn_aliases: HashMap::new(),
+            on_fn_alias: None,
+            capabilities: None,
 
             #[cfg(not(feature = "no_optimize"))]
             #[cfg(not(feature = "optimize_full"))]
@@ -313,6 +638,58 @@ impl Default for Engine<'_> {
             optimization_level: OptimizationLevel::Full,
 
             max_call_stack_depth: MAX_CALL_STACK_DEPTH,
+            max_stack_size: MAX_STACK_SIZE,
+            max_expr_depth: MAX_EXPR_DEPTH,
+            #[cfg(not(feature = "no_index"))]
+            max_blob_size: MAX_BLOB_SIZE,
+            stack_base: None,
+            stats: Default::default(),
+            memo: Default::default(),
+            #[cfg(not(feature = "no_float"))]
+            float_precision: None,
+            overflow_mode: OverflowMode::Checked,
+            int_arithmetic: HashMap::new(),
+            #[cfg(feature = "jit")]
+            builtin_ops: HashMap::new(),
+            doc_comments: false,
+            comments: false,
+            unit_discard: false,
+            int_widening: false,
+            fn_index: None,
+            source: None,
+            tail_call: None,
+            disabled_symbols: HashSet::new(),
+            reserved_symbols: HashSet::new(),
+            shadowing: Shadowing::Allowed,
+            dialect: None,
+            custom_keywords: HashMap::new(),
+            globals: HashMap::new(),
+
+            #[cfg(feature = "rand")]
+            rng: new_shared_rng(),
+
+            #[cfg(not(feature = "no_module"))]
+            #[cfg(not(feature = "no_std"))]
+            #[cfg(feature = "sync")]
+            module_resolver: Some(Arc::new(FileModuleResolver::new())),
+
+            #[cfg(not(feature = "no_module"))]
+            #[cfg(not(feature = "no_std"))]
+            #[cfg(not(feature = "sync"))]
+            module_resolver: Some(Rc::new(FileModuleResolver::new())),
+
+            #[cfg(not(feature = "no_module"))]
+            #[cfg(feature = "no_std")]
+            module_resolver: None,
+
+            #[cfg(not(feature = "no_module"))]
+            import_validation: false,
+
+            #[cfg(not(feature = "no_module"))]
+            imports: Default::default(),
+
+            #[cfg(feature = "jit")]
+            jit: Default::default(),
         };
 
         engine.register_core_lib();
@@ -372,6 +749,12 @@ impl Engine<'_> {
             type_names: None,
             on_print: None,
             on_debug: None,
+            on_def_var: None,
+            on_call: None,
+            fn_tags: HashMap::new(),
+            fn_aliases: HashMap::new(),
+            on_fn_alias: None,
+            capabilities: None,
 
             #[cfg(not(feature = "no_optimize"))]
             #[cfg(not(feature = "optimize_full"))]
@@ -382,6 +765,47 @@ impl Engine<'_> {
             optimization_level: OptimizationLevel::Full,
 
             max_call_stack_depth: MAX_CALL_STACK_DEPTH,
+            max_stack_size: MAX_STACK_SIZE,
+            max_expr_depth: MAX_EXPR_DEPTH,
+            #[cfg(not(feature = "no_index"))]
+            max_blob_size: MAX_BLOB_SIZE,
+            stack_base: None,
+            stats: Default::default(),
+            memo: Default::default(),
+            #[cfg(not(feature = "no_float"))]
+            float_precision: None,
+            overflow_mode: OverflowMode::Checked,
+            int_arithmetic: HashMap::new(),
+            #[cfg(feature = "jit")]
+            builtin_ops: HashMap::new(),
+            doc_comments: false,
+            comments: false,
+            unit_discard: false,
+            int_widening: false,
+            fn_index: None,
+            source: None,
+            tail_call: None,
+            disabled_symbols: HashSet::new(),
+            reserved_symbols: HashSet::new(),
+            shadowing: Shadowing::Allowed,
+            dialect: None,
+            custom_keywords: HashMap::new(),
+            globals: HashMap::new(),
+
+            #[cfg(feature = "rand")]
+            rng: new_shared_rng(),
+
+            #[cfg(not(feature = "no_module"))]
+            module_resolver: None,
+
+            #[cfg(not(feature = "no_module"))]
+            import_validation: false,
+
+            #[cfg(not(feature = "no_module"))]
+            imports: Default::default(),
+
+            #[cfg(feature = "jit")]
+            jit: Default::default(),
         };
 
         engine.register_core_lib();
@@ -392,6 +816,108 @@ impl Engine<'_> {
         engine
     }
 
+    /// Create a new `Engine` with the same configuration as this one - i.e. the same registered
+    /// functions, type iterators, type names, limits, options, symbols, globals and module resolver.
+    ///
+    /// This is cheap even for an `Engine` with many registered functions: the function registry,
+    /// type iterators and module resolver are shared (not copied) by the two engines.  Functions
+    /// registered afterwards into either engine are not visible in the other.
+    ///
+    /// The `on_call` callback (if any) is also shared, so calls made by scripts running in either
+    /// engine are audited by it.  Other closures cannot be copied, so the new `Engine` has the
+    /// default `print` and `debug` implementations (or none, if this `Engine` has neither), and no
+    /// `on_def_var` or `on_fn_alias` callbacks.  It also has its own random number generator, seeded from system entropy.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, RegisterFn, INT};
+    ///
+    /// let mut template = Engine::new();
+    /// template.register_fn("triple", |x: INT| x * 3);
+    /// template.set_max_call_levels(16);
+    ///
+    /// // Create an engine per worker from the template
+    /// let mut engine = template.duplicate();
+    ///
+    /// assert_eq!(engine.eval::<INT>("triple(14)")?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn duplicate(&self) -> Self {
+        let is_raw = self.on_print.is_none() && self.on_debug.is_none();
+
+        Engine {
+            functions: self.functions.clone(),
+            fn_lib: None,
+            type_iterators: self.type_iterators.clone(),
+            type_names: self.type_names.clone(),
+            on_print: if is_raw {
+                None
+            } else {
+                Some(Box::new(default_print))
+            },
+            on_debug: if is_raw {
+                None
+            } else {
+                Some(Box::new(default_debug))
+            },
+            on_def_var: None,
+            on_call: self.on_call.clone(),
+            fn_tags: self.fn_tags.clone(),
+            fn_aliases: self.fn_aliases.clone(),
+            on_fn_alias: None,
+            capabilities: self.capabilities.clone(),
+
+            #[cfg(not(feature = "no_optimize"))]
+            optimization_level: self.optimization_level,
+
+            max_call_stack_depth: self.max_call_stack_depth,
+            max_stack_size: self.max_stack_size,
+            max_expr_depth: self.max_expr_depth,
+            #[cfg(not(feature = "no_index"))]
+            max_blob_size: self.max_blob_size,
+            stack_base: None,
+            stats: Default::default(),
+            memo: Default::default(),
+            #[cfg(not(feature = "no_float"))]
+            float_precision: self.float_precision,
+            overflow_mode: self.overflow_mode,
+            int_arithmetic: self.int_arithmetic.clone(),
+            #[cfg(feature = "jit")]
+            builtin_ops: self.builtin_ops.clone(),
+            doc_comments: self.doc_comments,
+            comments: self.comments,
+            unit_discard: self.unit_discard,
+            int_widening: self.int_widening,
+            fn_index: None,
+            source: None,
+            tail_call: None,
+            disabled_symbols: self.disabled_symbols.clone(),
+            reserved_symbols: self.reserved_symbols.clone(),
+            shadowing: self.shadowing,
+            dialect: self.dialect.clone(),
+            custom_keywords: self.custom_keywords.clone(),
+            globals: self.globals.clone(),
+
+            #[cfg(feature = "rand")]
+            rng: new_shared_rng(),
+
+            #[cfg(not(feature = "no_module"))]
+            module_resolver: self.module_resolver.clone(),
+
+            #[cfg(not(feature = "no_module"))]
+            import_validation: self.import_validation,
+
+            #[cfg(not(feature = "no_module"))]
+            imports: Default::default(),
+
+            #[cfg(feature = "jit")]
+            jit: Default::default(),
+        }
+    }
+
     /// Control whether and how the `Engine` will optimize an AST after compilation
     ///
     /// Not available under the `no_optimize` feature.
@@ -406,168 +932,842 @@ impl Engine<'_> {
         self.max_call_stack_depth = levels
     }
 
-    /// Call a registered function
-    #[cfg(not(feature = "no_optimize"))]
-    pub(crate) fn call_ext_fn_raw(
-        &self,
-        fn_name: &str,
-        args: &mut FnCallArgs,
-        pos: Position,
-    ) -> Result<Option<Dynamic>, EvalAltResult> {
-        let spec = FnSpec {
-            name: fn_name.into(),
-            args: args.iter().map(|a| Any::type_id(&**a)).collect(),
-        };
-
-        // Search built-in's and external functions
-        if let Some(functions) = &self.functions {
-            if let Some(func) = functions.get(&spec) {
-                // Run external function
-                Ok(Some(func(args, pos)?))
-            } else {
-                Ok(None)
-            }
-        } else {
-            Ok(None)
-        }
+    /// Set the maximum number of bytes of native stack that evaluating a script may use.
+    /// Deeply-nested evaluations exceeding it fail with `EvalAltResult::ErrorStackOverflow`
+    /// instead of crashing the host process.
+    ///
+    /// The default is `MAX_STACK_SIZE` (1MB).  Set it well below the stack size of the thread
+    /// running the `Engine` (usually 2MB for threads other than the main thread).
+    ///
+    /// Unoptimized (debug) builds use many times more native stack per level of function calls
+    /// than release builds, so deep recursion may need a larger limit (and a larger thread stack).
+    pub fn set_max_stack_size(&mut self, bytes: usize) {
+        self.max_stack_size = bytes
     }
 
-    /// Universal method for calling functions either registered with the `Engine` or written in Rhai
-    pub(crate) fn call_fn_raw(
-        &mut self,
-        scope: Option<&mut Scope>,
-        fn_name: &str,
-        args: &mut FnCallArgs,
-        def_val: Option<&Dynamic>,
-        pos: Position,
-        level: usize,
-    ) -> Result<Dynamic, EvalAltResult> {
-        // First search in script-defined functions (can override built-in)
-        if let Some(fn_lib_arc) = &self.fn_lib {
-            if let Some(fn_def) = fn_lib_arc.clone().get_function(fn_name, args.len()) {
-                match scope {
-                    // Extern scope passed in which is not empty
-                    Some(scope) if scope.len() > 0 => {
-                        let scope_len = scope.len();
-
-                        scope.extend(
-                            // Put arguments into scope as variables - variable name is copied
-                            // TODO - avoid copying variable name
-                            fn_def
-                                .params
-                                .iter()
-                                .zip(args.into_iter().map(|x| (*x).into_dynamic()))
-                                .map(|(name, value)| (name.clone(), ScopeEntryType::Normal, value)),
-                        );
-
-                        // Evaluate the function at one higher level of call depth
-                        let result = self.eval_stmt(scope, &fn_def.body, level + 1).or_else(
-                            |err| match err {
-                                // Convert return statement to return value
-                                EvalAltResult::Return(x, _) => Ok(x),
-                                err => Err(err.set_position(pos)),
-                            },
-                        );
-
-                        scope.rewind(scope_len);
+    /// Set the maximum nesting depth of expressions and statements (e.g. `((((...))))`) in a script.
+    /// Deeper scripts fail to compile with `ParseErrorType::ExprTooDeep` instead of overflowing
+    /// the stack while parsing.
+    ///
+    /// The default is `MAX_EXPR_DEPTH` (128).  Long flat expressions (e.g. `1 + 2 + ... + 1000`)
+    /// are not limited.
+    pub fn set_max_expr_depth(&mut self, depth: usize) {
+        self.max_expr_depth = depth
+    }
 
-                        return result;
-                    }
-                    // No new scope - create internal scope
-                    _ => {
-                        let mut scope = Scope::new();
-
-                        scope.extend(
-                            // Put arguments into scope as variables
-                            fn_def
-                                .params
-                                .iter()
-                                .zip(args.into_iter().map(|x| (*x).into_dynamic()))
-                                .map(|(name, value)| (name, ScopeEntryType::Normal, value)),
-                        );
+    /// Set the maximum number of bytes in a BLOB created or grown by a script (e.g. via `blob`,
+    /// `push`, `append`, `+` or `parse_hex`).  Going over it fails with a runtime error.
+    ///
+    /// The default is `MAX_BLOB_SIZE` (16MB).
+    ///
+    /// This replaces any custom functions registered with the names of the standard library
+    /// functions creating or growing BLOB's.
+    ///
+    /// Not available under the `no_index` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::Engine;
+    ///
+    /// let mut engine = Engine::new();
+    /// engine.set_max_blob_size(4);
+    ///
+    /// assert!(engine.eval::<()>("let x = blob(3); x.push(1)").is_ok());
+    /// assert!(engine.eval::<()>("let x = blob(4); x.push(1)").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_index"))]
+    pub fn set_max_blob_size(&mut self, bytes: usize) {
+        self.max_blob_size = bytes;
 
-                        // Evaluate the function at one higher level of call depth
-                        return self.eval_stmt(&mut scope, &fn_def.body, level + 1).or_else(
-                            |err| match err {
-                                // Convert return statement to return value
-                                EvalAltResult::Return(x, _) => Ok(x),
-                                err => Err(err.set_position(pos)),
-                            },
-                        );
-                    }
-                }
+        #[cfg(not(feature = "no_stdlib"))]
+        {
+            if self.is_registered("blob", &[TypeId::of::<INT>()]) {
+                self.register_blob_growth();
             }
         }
+    }
 
-        let spec = FnSpec {
-            name: fn_name.into(),
-            args: args.iter().map(|a| Any::type_id(&**a)).collect(),
-        };
-
-        // Argument must be a string
-        fn cast_to_string(r: &Variant, pos: Position) -> Result<&str, EvalAltResult> {
-            r.downcast_ref::<String>()
-                .map(String::as_str)
-                .ok_or_else(|| EvalAltResult::ErrorMismatchOutputType(r.type_name().into(), pos))
-        }
+    /// Set the number of decimal places shown when converting floating-point numbers to strings,
+    /// e.g. with `print`, `to_string` or by adding them to strings.
+    ///
+    /// The default, `None`, shows the shortest representation that converts back to the same number,
+    /// so `0.1 + 0.2` shows as `0.30000000000000004`.
+    ///
+    /// This replaces any custom `print` or `to_string` functions registered for `f32` or `f64`.
+    ///
+    /// Not available under the `no_float` feature.
+    #[cfg(not(feature = "no_float"))]
+    pub fn set_float_precision(&mut self, precision: Option<usize>) {
+        self.float_precision = precision;
+        self.register_float_display();
+    }
 
-        // Search built-in's and external functions
-        if let Some(functions) = &self.functions {
-            if let Some(func) = functions.get(&spec) {
-                // Run external function
-                let result = func(args, pos)?;
+    /// Control how the arithmetic operators on integers (`+`, `-`, `*`, `/`, `%`, unary `-` and `abs`)
+    /// handle overflow: by failing with `EvalAltResult::ErrorArithmetic` (the default), by wrapping
+    /// around or by saturating at the bounds of the type.
+    ///
+    /// Wrapping and saturating arithmetic never fail on overflow, so no overflow checks are paid for.
+    /// Division (or modulo) by zero is still an error.
+    ///
+    /// Custom functions registered for these operators on integers are kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, OverflowMode};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// assert!(engine.eval::<i64>("9223372036854775807 + 1").is_err());
+    ///
+    /// engine.set_overflow_mode(OverflowMode::Wrapping);
+    /// assert_eq!(engine.eval::<i64>("9223372036854775807 + 1")?, i64::MIN);
+    ///
+    /// engine.set_overflow_mode(OverflowMode::Saturating);
+    /// assert_eq!(engine.eval::<i64>("9223372036854775807 + 1")?, i64::MAX);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_overflow_mode(&mut self, mode: OverflowMode) {
+        self.overflow_mode = mode;
+        self.register_int_arithmetic();
+    }
 
-                // See if the function match print/debug (which requires special processing)
-                return Ok(match fn_name {
-                    KEYWORD_PRINT if self.on_print.is_some() => {
-                        self.on_print.as_deref_mut().unwrap()(cast_to_string(result.as_ref(), pos)?)
-                            .into_dynamic()
-                    }
-                    KEYWORD_DEBUG if self.on_debug.is_some() => {
-                        self.on_debug.as_deref_mut().unwrap()(cast_to_string(result.as_ref(), pos)?)
-                            .into_dynamic()
-                    }
-                    KEYWORD_PRINT | KEYWORD_DEBUG => ().into_dynamic(),
-                    _ => result,
-                });
-            }
+    /// Start tracking the native stack used by an evaluation, if not already inside one.
+    ///
+    /// Returns `true` if this is the outermost evaluation, which must call `end_stack_tracking`
+    /// when done.
+    pub(crate) fn begin_stack_tracking(&mut self) -> bool {
+        if self.stack_base.is_some() {
+            false
+        } else {
+            self.stack_base = Some(stack_address());
+            self.stats = Default::default();
+            true
         }
+    }
 
-        if let Some(prop) = extract_prop_from_getter(fn_name) {
-            #[cfg(not(feature = "no_object"))]
-            {
-                // Map property access
-                if let Some(map) = args[0].downcast_ref::<Map>() {
-                    return Ok(map.get(prop).cloned().unwrap_or_else(|| ().into_dynamic()));
-                }
-            }
+    /// Stop tracking the native stack used by the outermost evaluation.
+    pub(crate) fn end_stack_tracking(&mut self, is_outermost: bool) {
+        if is_outermost {
+            self.stack_base = None;
+            self.memo.clear();
+        }
+    }
 
-            // Getter function not found
-            return Err(EvalAltResult::ErrorDotExpr(
-                format!("- property '{}' unknown or write-only", prop),
-                pos,
-            ));
+    /// Is the native stack used by the current evaluation over the limit?
+    fn is_stack_exhausted(&self) -> bool {
+        match self.stack_base {
+            Some(base) => base.abs_diff(stack_address()) > self.max_stack_size,
+            None => false,
         }
+    }
 
-        if let Some(prop) = extract_prop_from_setter(fn_name) {
-            #[cfg(not(feature = "no_object"))]
-            {
-                let value = args[1].into_dynamic();
+    /// Get statistics about the work done by the last evaluation (e.g. via `eval` or `call_fn`),
+    /// including any nested evaluations (e.g. via `eval` in the script).
+    ///
+    /// The numbers only depend on the script (and the functions it calls), not on the speed of the
+    /// machine, so they are useful for detecting performance regressions in tests.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::Engine;
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.eval::<i64>("let x = 0; while x < 10 { x += 1; } x")?;
+    ///
+    /// let stats = engine.last_eval_stats();
+    /// assert!(stats.operations > 30);
+    /// assert!(stats.fn_calls >= 20);      // '<' and '+' for each loop iteration
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn last_eval_stats(&self) -> EvalStats {
+        self.stats
+    }
 
-                // Map property update
-                if let Some(map) = args[0].downcast_mut::<Map>() {
-                    map.insert(prop.to_string(), value);
-                    return Ok(().into_dynamic());
-                }
-            }
+    /// Control whether doc-comments (`///` or `/** ... */`) preceding script-defined functions
+    /// are kept during compilation.  They are available via `AST::iter_functions`.
+    ///
+    /// Doc-comments are not kept by default.
+    #[cfg(not(feature = "no_function"))]
+    pub fn enable_doc_comments(&mut self, enable: bool) {
+        self.doc_comments = enable
+    }
 
-            // Setter function not found
-            return Err(EvalAltResult::ErrorDotExpr(
-                format!("- property '{}' unknown or read-only", prop),
-                pos,
+    /// Control whether all comments in a script are kept during compilation, together with
+    /// their positions.  They are available via `AST::comments`, and are also returned by
+    /// `Engine::lex` as `Token::Comment`.
+    ///
+    /// This is useful for external tooling, such as formatters, that must not lose comments.
+    /// Comments are not kept by default.
+    pub fn enable_comments(&mut self, enable: bool) {
+        self.comments = enable
+    }
+
+    /// Control whether evaluating a script for `()` (e.g. `eval::<()>`) discards the result of
+    /// the script when it is not `()`, instead of failing with `ErrorMismatchOutputType`.
+    ///
+    /// This is useful for scripts run only for their side effects, whose last statement may
+    /// happen to return a value.  Results are not discarded by default; alternatively, use
+    /// `Engine::run` which always discards the result.
+    pub fn enable_unit_discard(&mut self, enable: bool) {
+        self.unit_discard = enable
+    }
+
+    /// Control whether a function call with integer arguments whose types do not match any
+    /// function falls back to a function registered over other integer types, converting the
+    /// arguments when this does not lose data.
+    ///
+    /// For example, a script can then pass an `INT` to a Rust function taking a `u8`, as long as
+    /// the value fits.  Converted arguments are passed by value, so a function taking its first
+    /// argument by `&mut` does not update the original.  Integer widening is off by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, RegisterFn};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_fn("double", |x: u8| x as u32 * 2);
+    /// assert!(engine.eval::<u32>("double(100)").is_err());
+    ///
+    /// engine.enable_int_widening(true);
+    /// assert_eq!(engine.eval::<u32>("double(100)")?, 200);
+    /// assert!(engine.eval::<u32>("double(1000)").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn enable_int_widening(&mut self, enable: bool) {
+        self.int_widening = enable
+    }
+
+    /// Disable a particular keyword, operator or identifier in scripts.
+    ///
+    /// Any script containing the symbol fails to compile, including scripts run via `eval`.
+    /// Scripts already compiled into an `AST` are not affected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::Engine;
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.disable_symbol("eval");
+    ///
+    /// assert!(engine.compile(r#"eval("40 + 2")"#).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn disable_symbol(&mut self, symbol: &str) {
+        self.disabled_symbols.insert(symbol.into());
+    }
+
+    /// Reserve names so that scripts cannot use them for variables, constants, function
+    /// parameters, imports or functions, replacing any names previously reserved.
+    ///
+    /// Unlike `Engine::disable_symbol`, reserved names are still allowed as property names,
+    /// object map keys and inside strings.  This keeps names free for future host APIs
+    /// without the risk of existing scripts breaking when they are added.
+    ///
+    /// Scripts already compiled into an `AST` are not affected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, ParseErrorType};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.set_reserved_symbols(["spawn", "despawn"]);
+    ///
+    /// let err = engine.compile("let spawn = 42;").expect_err("should error");
+    /// assert_eq!(*err.error_type(), ParseErrorType::ReservedSymbol("spawn".into()));
+    ///
+    /// assert!(engine.compile("fn despawn(x) { x }").is_err());
+    /// assert!(engine.compile("spawn(1)").is_err());
+    ///
+    /// // Reserved names can still be used as properties
+    /// assert_eq!(engine.eval::<i64>("let x = #{ spawn: 42 }; x.spawn")?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_reserved_symbols<S: AsRef<str>>(&mut self, symbols: impl IntoIterator<Item = S>) {
+        self.reserved_symbols = symbols.into_iter().map(|s| s.as_ref().into()).collect();
+    }
+
+    /// Control whether scripts may declare variables (via `let`, `const` or `for`) reusing the names
+    /// of variables already declared, which hides the earlier variables.
+    ///
+    /// Under `Shadowing::NotInSameBlock`, redeclaring a variable in the same block fails to compile,
+    /// but shadowing a variable of an outer block is allowed.  Under `Shadowing::Forbidden`, both fail.
+    /// Parameters of script-defined functions (and loop variables) count as variables declared in the
+    /// function (or loop) body.
+    /// Compilation fails with `ParseErrorType::VariableShadowed`.  Shadowing is allowed by default.
+    ///
+    /// Variables pushed into a `Scope` by the host are not considered.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, ParseErrorType, Shadowing};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.set_shadowing(Shadowing::NotInSameBlock);
+    ///
+    /// let err = engine.compile("let x = 1; let x = x + 1;").expect_err("should error");
+    /// assert_eq!(*err.error_type(), ParseErrorType::VariableShadowed("x".into()));
+    ///
+    /// assert!(engine.compile("let x = 1; if x > 0 { let x = 2; }").is_ok());
+    ///
+    /// engine.set_shadowing(Shadowing::Forbidden);
+    ///
+    /// assert!(engine.compile("let x = 1; if x > 0 { let x = 2; }").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_shadowing(&mut self, shadowing: Shadowing) {
+        self.shadowing = shadowing
+    }
+
+    /// Restrict the keywords, operators and literal forms allowed in scripts to those of a `Dialect`,
+    /// replacing any dialect previously set.  Symbols disabled via `Engine::disable_symbol` stay
+    /// disabled.
+    ///
+    /// Any script using something not allowed fails to compile, including scripts run via `eval`.
+    /// Scripts already compiled into an `AST` are not affected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Dialect, Engine, LexError, ParseErrorType};
+    ///
+    /// const NO_LOOPS: Dialect = Dialect::FULL.disable(&["while", "loop", "for"]);
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.set_dialect(&NO_LOOPS);
+    ///
+    /// let err = engine.compile("loop { }").expect_err("should error");
+    /// assert_eq!(
+    ///     *err.error_type(),
+    ///     ParseErrorType::BadInput(LexError::DisabledSymbol("loop".into()).to_string())
+    /// );
+    ///
+    /// engine.set_dialect(&Dialect::FULL);
+    ///
+    /// assert!(engine.compile("loop { }").is_ok());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_dialect(&mut self, dialect: &Dialect) {
+        self.dialect = if *dialect == Dialect::FULL {
+            None
+        } else {
+            Some(DialectRules::new(dialect))
+        };
+    }
+
+    /// Register a custom keyword which calls the function of the same name, so that a script can
+    /// write `sqrt x` instead of `sqrt(x)` (`CustomKeyword::Prefix`), or `a dot b` instead of
+    /// `dot(a, b)` (`CustomKeyword::Infix`).  The function itself is registered separately
+    /// (or defined in the script) as usual.
+    ///
+    /// The keyword must be a valid identifier which is not already a keyword.  Once registered,
+    /// it can no longer be used as a variable or property name, or to call the function with
+    /// parentheses, in scripts.
+    /// Scripts already compiled into an `AST` are not affected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{CustomKeyword, Engine, RegisterFn};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_fn("double", |x: i64| x * 2);
+    /// engine.register_fn("plus", |x: i64, y: i64| x + y);
+    ///
+    /// engine.register_custom_keyword("double", CustomKeyword::Prefix)?;
+    /// // Same precedence as '+'
+    /// engine.register_custom_keyword("plus", CustomKeyword::Infix(80))?;
+    ///
+    /// assert_eq!(engine.eval::<i64>("double 20 plus 2")?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_custom_keyword(
+        &mut self,
+        keyword: &str,
+        kind: CustomKeyword,
+    ) -> Result<(), String> {
+        match lex(keyword)
+            .map(|(token, _)| token)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [Token::Identifier(name)] if name == keyword => (),
+            _ => return Err(format!("'{}' is not a valid custom keyword", keyword)),
+        }
+
+        if kind == CustomKeyword::Infix(0) {
+            return Err(format!(
+                "Custom keyword '{}' must have a precedence",
+                keyword
             ));
         }
 
+        self.custom_keywords.insert(keyword.into(), kind);
+        Ok(())
+    }
+
+    /// Call a registered function
+    #[cfg(not(feature = "no_optimize"))]
+    pub(crate) fn call_ext_fn_raw(
+        &self,
+        fn_name: &str,
+        args: &mut FnCallArgs,
+        pos: Position,
+    ) -> Result<Option<Dynamic>, Box<EvalAltResult>> {
+        // Calls cannot be audited during optimization, so do not make any.
+        // Functions requiring capabilities are never called ahead of time.
+        if self.on_call.is_some() || self.fn_tags.contains_key(fn_name) {
+            return Ok(None);
+        }
+
+        let spec = FnSpec {
+            name: fn_name.into(),
+            args: args.iter().map(|a| Any::type_id(&**a)).collect(),
+        };
+
+        // Search built-in's and external functions
+        if let Some(functions) = &self.functions {
+            if let Some(func) = functions.get(&spec) {
+                // Run external function
+                Ok(Some(func(args, pos)?))
+            } else {
+                Ok(None)
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Call a script-defined function.
+    ///
+    /// Calls to script-defined functions in tail position within the function body are run
+    /// in a loop, reusing the current call level instead of recursing.
+    fn call_script_fn(
+        &mut self,
+        scope: Option<&mut Scope>,
+        fn_def: &FnDef,
+        args: &mut FnCallArgs,
+        pos: Position,
+        level: usize,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        // Guard against too many levels of function calls
+        if level >= self.max_call_stack_depth {
+            return Err(Box::new(EvalAltResult::ErrorStackOverflow(pos)));
+        }
+
+        // Run compiled code if possible
+        #[cfg(feature = "jit")]
+        {
+            if let Some(result) = self.call_jit_fn(fn_def, args, level) {
+                return Ok(result);
+            }
+        }
+
+        let mut local_scope = Scope::new();
+
+        // Extern scope passed in which is not empty
+        let mut extern_scope = scope.filter(|scope| !scope.is_empty());
+
+        let mut values: StaticVec<_> = args.iter().map(|x| (*x).into_dynamic()).collect();
+        let mut next_fn: Option<Identifier> = None;
+
+        loop {
+            let scope = match extern_scope.as_deref_mut() {
+                Some(scope) => scope,
+                // No new scope - use internal scope
+                None => &mut local_scope,
+            };
+            let scope_len = scope.len();
+
+            let fn_lib = self.fn_lib.clone();
+
+            let fn_def = match &next_fn {
+                None => fn_def,
+                Some(name) => fn_lib
+                    .as_ref()
+                    .and_then(|lib| lib.get_function(name, values.len()))
+                    .unwrap(),
+            };
+
+            // Put arguments into scope as variables - variable name is copied (inline if short)
+            for (name, value) in fn_def.params.iter().zip(values.drain(..)) {
+                scope.push_dynamic_value(name.as_str(), ScopeEntryType::Normal, value, false);
+            }
+
+            // Evaluate the function at one higher level of call depth
+            let result = self
+                .eval_fn_body(scope, &fn_def.nodes, &fn_def.body, level + 1)
+                .or_else(|err| match *err {
+                    // Convert return statement to return value
+                    EvalAltResult::Return(x, _) => Ok(x),
+                    _ => Err(err.set_position(pos)),
+                });
+
+            scope.rewind(scope_len);
+
+            match self.tail_call.take() {
+                // Run the tail call in place of this function, without the extern scope
+                // (just like any other call made by this function)
+                Some((name, args)) if result.is_ok() => {
+                    next_fn = Some(name);
+                    values = args;
+                    extern_scope = None;
+                }
+                _ => return result,
+            }
+        }
+    }
+
+    /// Evaluate the body of a script-defined function.
+    ///
+    /// Calls to script-defined functions in tail position are not run but left in `tail_call`.
+    fn eval_fn_body(
+        &mut self,
+        scope: &mut Scope,
+        nodes: &Nodes,
+        stmt: &Stmt,
+        level: usize,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        match stmt {
+            Stmt::Block(block, _) if !block.is_empty() => {
+                let prev_len = scope.len();
+                let (last, block) = nodes[*block].split_last().unwrap();
+
+                let result = block
+                    .iter()
+                    .try_for_each(|stmt| self.eval_stmt(scope, nodes, stmt, level).map(|_| ()))
+                    .and_then(|_| self.eval_fn_body(scope, nodes, last, level));
+
+                scope.rewind(prev_len);
+
+                result
+            }
+
+            Stmt::IfThenElse(guard, if_body, else_body) => self
+                .eval_expr(scope, nodes, &nodes[*guard], level)?
+                .try_cast::<bool>()
+                .map_err(|_| {
+                    Box::new(EvalAltResult::ErrorLogicGuard(
+                        nodes[*guard].position(nodes),
+                    ))
+                })
+                .and_then(|guard_val| {
+                    if guard_val {
+                        self.eval_fn_body(scope, nodes, &nodes[*if_body], level)
+                    } else if let Some(stmt) = else_body {
+                        self.eval_fn_body(scope, nodes, &nodes[*stmt], level)
+                    } else {
+                        Ok(().into_dynamic())
+                    }
+                }),
+
+            Stmt::Expr(expr) if self.is_tail_call(&nodes[*expr]) => {
+                self.set_tail_call(scope, nodes, &nodes[*expr], level)?;
+                Ok(().into_dynamic())
+            }
+
+            _ => self.eval_stmt(scope, nodes, stmt, level),
+        }
+    }
+
+    /// Is an expression a call to a script-defined function?
+    fn is_tail_call(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::FunctionCall(fn_name, args_expr_list, _, _, _) => {
+                matches!(&self.fn_lib, Some(lib) if lib.has_function(fn_name, args_expr_list.len()))
+            }
+            _ => false,
+        }
+    }
+
+    /// Evaluate the arguments of a call to a script-defined function and leave it in `tail_call`.
+    fn set_tail_call(
+        &mut self,
+        scope: &mut Scope,
+        nodes: &Nodes,
+        expr: &Expr,
+        level: usize,
+    ) -> Result<(), Box<EvalAltResult>> {
+        if let Expr::FunctionCall(fn_name, args_expr_list, _, _, _) = expr {
+            let values = nodes[*args_expr_list]
+                .iter()
+                .map(|expr| self.eval_expr(scope, nodes, expr, level))
+                .collect::<Result<StaticVec<_>, _>>()?;
+
+            self.tail_call = Some((fn_name.as_ref().into(), values));
+        }
+
+        Ok(())
+    }
+
+    /// Call a script-defined function of an imported module, with the module's own functions
+    /// library and imports.
+    #[cfg(not(feature = "no_module"))]
+    pub(crate) fn call_module_fn(
+        &mut self,
+        module: &SharedModule,
+        fn_def: &FnDef,
+        args: &mut FnCallArgs,
+        pos: Position,
+        level: usize,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let fn_lib = self.fn_lib.replace(module.functions.clone());
+        let imports = mem::replace(&mut self.imports, module.imports.clone());
+        let source = self.source.replace(module.path.clone());
+
+        let result = self.call_script_fn(None, fn_def, args, pos, level);
+
+        self.fn_lib = fn_lib;
+        self.imports = imports;
+        self.source = source;
+
+        result
+    }
+
+    /// Universal method for calling functions either registered with the `Engine` or written in Rhai
+    pub(crate) fn call_fn_raw(
+        &mut self,
+        scope: Option<&mut Scope>,
+        fn_name: &str,
+        args: &mut FnCallArgs,
+        def_val: Option<&Dynamic>,
+        pos: Position,
+        level: usize,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        self.stats.fn_calls += 1;
+
+        // First search in script-defined functions (can override built-in)
+        if let Some(fn_lib_arc) = &self.fn_lib {
+            if let Some(fn_def) = fn_lib_arc.clone().get_function(fn_name, args.len()) {
+                // Look up the result of a memoized function
+                let key = self.memo.key(fn_lib_arc, fn_def, args);
+
+                if let Some(result) = key.as_ref().and_then(|key| self.memo.get(key)) {
+                    return Ok(result.clone());
+                }
+
+                let result = self.call_script_fn(scope, fn_def, args, pos, level)?;
+
+                if let Some(key) = key {
+                    self.memo.insert(key, result.clone());
+                }
+
+                return Ok(result);
+            }
+        }
+
+        // Then search in imported modules
+        #[cfg(not(feature = "no_module"))]
+        {
+            if let Some((module, name)) = self.imports.find_function(fn_name, args.len()) {
+                let (module, name) = (module.clone(), name.to_string());
+                let fn_def = module.functions.get_function(&name, args.len()).unwrap();
+
+                return self.call_module_fn(&module, fn_def, args, pos, level);
+            }
+        }
+
+        // Audit every other call, including calls to the functions handled by the `Engine` itself
+        self.check_call(fn_name, args, pos)?;
+
+        self.call_native_fn(fn_name, args, def_val, pos, level)
+    }
+
+    /// Call a built-in or external function, or one handled by the `Engine` itself (e.g. `format`),
+    /// without auditing the call.
+    ///
+    /// A call is resolved by trying, in order:
+    ///
+    /// 1. a registered function matching the argument types exactly;
+    /// 2. an alias of the function (see `Engine::register_fn_alias`);
+    /// 3. a registered function over other integer types (if integer widening is enabled);
+    /// 4. a registered function over owned copies of strings and BLOB's borrowed from the host;
+    /// 5. a function handled by the `Engine` itself (see `engine_fns`);
+    /// 6. a property getter or setter of an object map;
+    /// 7. the default value of the call, if any.
+    pub(crate) fn call_native_fn(
+        &mut self,
+        fn_name: &str,
+        args: &mut FnCallArgs,
+        def_val: Option<&Dynamic>,
+        pos: Position,
+        level: usize,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let spec = FnSpec {
+            name: fn_name.into(),
+            args: args.iter().map(|a| Any::type_id(&**a)).collect(),
+        };
+
+        // Search built-in's and external functions
+        if let Some(functions) = &self.functions {
+            if let Some(func) = functions.get(&spec) {
+                // Run external function
+                let result = func(args, pos)?;
+
+                // See if the function match print/debug (which requires special processing)
+                return Ok(match fn_name {
+                    KEYWORD_PRINT if self.on_print.is_some() => {
+                        let text = self.cast_to_string(result.as_ref(), pos)?;
+                        self.on_print.as_deref_mut().unwrap()(text);
+                        ().into_dynamic()
+                    }
+                    KEYWORD_DEBUG if self.on_debug.is_some() => {
+                        let text = self.cast_to_string(result.as_ref(), pos)?;
+                        self.on_debug.as_deref_mut().unwrap()(text, self.source.as_deref(), pos);
+                        ().into_dynamic()
+                    }
+                    KEYWORD_PRINT | KEYWORD_DEBUG => ().into_dynamic(),
+                    _ => result,
+                });
+            }
+
+            // Call a function via its alias
+            if let Some(name) = self.fn_aliases.get(fn_name).cloned() {
+                if let Some(callback) = self.on_fn_alias.as_mut() {
+                    callback(fn_name, &name, self.source.as_deref(), pos);
+                }
+                return self.call_native_fn(&name, args, def_val, pos, level);
+            }
+
+            // Convert integer arguments to match a function over other integer types
+            if self.int_widening {
+                let index = self.fn_index.get_or_insert_with(|| {
+                    let mut index: HashMap<_, Vec<_>> = HashMap::new();
+                    for spec in functions.keys() {
+                        index
+                            .entry(spec.name.to_string())
+                            .or_default()
+                            .push(spec.clone());
+                    }
+                    index
+                });
+                let specs = index.get(fn_name).into_iter().flatten();
+
+                if let Some(mut values) = widen_int_args(specs, fn_name, args) {
+                    let mut args: StaticVec<_> = values.iter_mut().map(|v| v.as_mut()).collect();
+                    return self.call_native_fn(fn_name, &mut args, def_val, pos, level);
+                }
+            }
+
+            // Copy strings and BLOB's borrowed from the host to match a function over owned ones
+            let mut owned: StaticVec<_> = args.iter().map(|arg| to_owned_value(&**arg)).collect();
+
+            if owned.iter().any(Option::is_some) {
+                let mut args: StaticVec<&mut Variant> = args
+                    .iter_mut()
+                    .zip(owned.iter_mut())
+                    .map(|(arg, owned)| match owned {
+                        Some(value) => value.as_mut(),
+                        None => &mut **arg,
+                    })
+                    .collect();
+                return self.call_native_fn(fn_name, &mut args, def_val, pos, level);
+            }
+        }
+
+        // Functions handled by the `Engine` itself (e.g. `format`)
+        if let Some(result) = self.call_engine_fn(fn_name, args, pos, level) {
+            return result;
+        }
+
+        if let Some(prop) = extract_prop_from_getter(fn_name) {
+            #[cfg(not(feature = "no_object"))]
+            {
+                // Map property access
+                if let Some(map) = args[0].downcast_ref::<Map>() {
+                    return Ok(map.get(prop).cloned().unwrap_or_else(|| ().into_dynamic()));
+                }
+
+                // Shared map property access
+                if let Some(shared) = args[0].downcast_ref::<SharedValue>() {
+                    if let Some(value) = shared
+                        .try_read(|v| {
+                            v.downcast_ref::<Map>().map(|map| {
+                                map.get(prop).cloned().unwrap_or_else(|| ().into_dynamic())
+                            })
+                        })
+                        .map_err(|err| err.set_position(pos))?
+                    {
+                        return Ok(value);
+                    }
+                }
+            }
+
+            // Getter function not found
+            return Err(Box::new(EvalAltResult::ErrorDotExpr(
+                format!("- property '{}' unknown or write-only", prop),
+                pos,
+            )));
+        }
+
+        if let Some(prop) = extract_prop_from_setter(fn_name) {
+            #[cfg(not(feature = "no_object"))]
+            {
+                let value = args[1].into_dynamic();
+
+                // Map property update
+                if let Some(map) = args[0].downcast_mut::<Map>() {
+                    map.insert(prop.to_string(), value);
+                    return Ok(().into_dynamic());
+                }
+
+                // Shared map property update
+                if let Some(shared) = args[0].downcast_ref::<SharedValue>() {
+                    if shared.is_contained_in(value.as_ref()) {
+                        return Err(shared_cycle(pos));
+                    }
+                    if shared
+                        .try_write(|v| {
+                            v.downcast_mut::<Map>()
+                                .map(|map| map.insert(prop.to_string(), value))
+                                .is_some()
+                        })
+                        .map_err(|err| err.set_position(pos))?
+                    {
+                        return Ok(().into_dynamic());
+                    }
+                }
+            }
+
+            // Setter function not found
+            return Err(Box::new(EvalAltResult::ErrorDotExpr(
+                format!("- property '{}' unknown or read-only", prop),
+                pos,
+            )));
+        }
+
         if let Some(val) = def_val {
             // Return default value
             return Ok(val.clone());
@@ -580,10 +1780,126 @@ impl Engine<'_> {
             .map(|name| self.map_type_name(name))
             .collect();
 
-        Err(EvalAltResult::ErrorFunctionNotFound(
+        Err(Box::new(EvalAltResult::ErrorFunctionNotFound(
             format!("{} ({})", fn_name, types_list.join(", ")),
             pos,
-        ))
+        )))
+    }
+
+    /// Sort an array using a comparison function, which can be script-defined or registered.
+    ///
+    /// The comparison function is called with two elements and must return a number which is
+    /// negative, zero or positive when the first element is less than, equal to or greater than
+    /// the second respectively.
+    #[cfg(not(feature = "no_index"))]
+    pub(crate) fn sort_array(
+        &mut self,
+        list: &mut Array,
+        cmp_fn: &str,
+        pos: Position,
+        level: usize,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let mut err = None;
+
+        list.sort_by(|x, y| {
+            if err.is_some() {
+                return Ordering::Equal;
+            }
+
+            let (mut x, mut y) = (x.clone(), y.clone());
+            let mut args = [x.as_mut(), y.as_mut()];
+
+            match self
+                .call_fn_raw(None, cmp_fn, &mut args, None, pos, level)
+                .and_then(|r| {
+                    r.try_cast::<INT>()
+                        .map_err(|r| self.make_type_mismatch_err::<INT>(&*r, pos))
+                }) {
+                Ok(r) => r.cmp(&0),
+                Err(e) => {
+                    err = Some(e);
+                    Ordering::Equal
+                }
+            }
+        });
+
+        err.map_or(Ok(()), Err)
+    }
+
+    /// Binary-search a sorted array for a value using a comparison function, which can be
+    /// script-defined or registered.
+    ///
+    /// The comparison function is called with an element and the value, as for `sort_array`.
+    /// Returns the index of an element equal to the value, or `-(index + 1)` where `index` is the
+    /// position at which the value could be inserted to keep the array sorted.
+    #[cfg(not(feature = "no_index"))]
+    pub(crate) fn search_array(
+        &mut self,
+        list: &Array,
+        value: Dynamic,
+        cmp_fn: &str,
+        pos: Position,
+        level: usize,
+    ) -> Result<INT, Box<EvalAltResult>> {
+        let mut err = None;
+
+        let result = list.binary_search_by(|x| {
+            if err.is_some() {
+                return Ordering::Equal;
+            }
+
+            let (mut x, mut y) = (x.clone(), value.clone());
+            let mut args = [x.as_mut(), y.as_mut()];
+
+            match self
+                .call_fn_raw(None, cmp_fn, &mut args, None, pos, level)
+                .and_then(|r| {
+                    r.try_cast::<INT>()
+                        .map_err(|r| self.make_type_mismatch_err::<INT>(&*r, pos))
+                }) {
+                Ok(r) => r.cmp(&0),
+                Err(e) => {
+                    err = Some(e);
+                    Ordering::Equal
+                }
+            }
+        });
+
+        match err {
+            None => Ok(result.map_or_else(|n| -(n as INT) - 1, |n| n as INT)),
+            Some(e) => Err(e),
+        }
+    }
+
+    /// Filter a map using a predicate function, which can be script-defined or registered,
+    /// returning a new map with only the properties for which the predicate returns `true`.
+    ///
+    /// The predicate function is called with the name and value of each property.
+    #[cfg(not(feature = "no_object"))]
+    pub(crate) fn filter_map(
+        &mut self,
+        map: &Map,
+        filter_fn: &str,
+        pos: Position,
+        level: usize,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let mut result = Map::new();
+
+        for (key, value) in map {
+            let (mut k, mut v) = (key.clone().into_dynamic(), value.clone());
+            let mut args = [k.as_mut(), v.as_mut()];
+
+            let keep = self
+                .call_fn_raw(None, filter_fn, &mut args, None, pos, level)?
+                .try_cast::<bool>()
+                .map_err(|r| self.make_type_mismatch_err::<bool>(&*r, pos))?;
+
+            if keep {
+                result.insert(key.clone(), value.clone());
+            }
+        }
+
+        Ok(result)
     }
 
     /// Chain-evaluate a dot setter.
@@ -591,21 +1907,22 @@ impl Engine<'_> {
     fn get_dot_val_helper(
         &mut self,
         scope: &mut Scope,
+        nodes: &Nodes,
         target: Target,
         dot_rhs: &Expr,
         level: usize,
-    ) -> Result<Dynamic, EvalAltResult> {
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
         match dot_rhs {
             // xxx.fn_name(arg_expr_list)
-            Expr::FunctionCall(fn_name, arg_expr_list, def_val, pos) => {
-                let mut values = arg_expr_list
+            Expr::FunctionCall(fn_name, arg_expr_list, def_val, _, pos) => {
+                let mut values = nodes[*arg_expr_list]
                     .iter()
-                    .map(|arg_expr| self.eval_expr(scope, arg_expr, level))
-                    .collect::<Result<Vec<_>, _>>()?;
+                    .map(|arg_expr| self.eval_expr(scope, nodes, arg_expr, level))
+                    .collect::<Result<StaticVec<_>, _>>()?;
 
                 let this_ptr = target.get_mut(scope);
 
-                let mut args: Vec<_> = once(this_ptr)
+                let mut args: StaticVec<_> = once(this_ptr)
                     .chain(values.iter_mut().map(Dynamic::as_mut))
                     .collect();
 
@@ -623,7 +1940,9 @@ impl Engine<'_> {
             // xxx.idx_lhs[idx_expr]
             #[cfg(not(feature = "no_index"))]
             Expr::Index(idx_lhs, idx_expr, op_pos) => {
-                let value = match idx_lhs.as_ref() {
+                let (idx_lhs, idx_expr) = (&nodes[*idx_lhs], &nodes[*idx_expr]);
+
+                let value = match idx_lhs {
                     // xxx.id[idx_expr]
                     Expr::Property(id, pos) => {
                         let mut args = [target.get_mut(scope)];
@@ -631,35 +1950,55 @@ impl Engine<'_> {
                     }
                     // xxx.???[???][idx_expr]
                     Expr::Index(_, _, _) => {
-                        self.get_dot_val_helper(scope, target, idx_lhs, level)?
+                        self.get_dot_val_helper(scope, nodes, target, idx_lhs, level)?
                     }
                     // Syntax error
                     _ => {
-                        return Err(EvalAltResult::ErrorDotExpr(
+                        return Err(Box::new(EvalAltResult::ErrorDotExpr(
                             "".to_string(),
-                            dot_rhs.position(),
-                        ))
+                            dot_rhs.position(nodes),
+                        )))
                     }
                 };
 
-                self.get_indexed_value(scope, &value, idx_expr, *op_pos, level)
+                self.get_indexed_value(scope, nodes, &value, idx_expr, *op_pos, level)
                     .map(|(val, _, _)| val)
             }
 
             // xxx.dot_lhs.rhs
-            Expr::Dot(dot_lhs, rhs, _) => match dot_lhs.as_ref() {
+            Expr::Dot(dot_lhs, rhs, _) => match (&nodes[*dot_lhs], &nodes[*rhs]) {
                 // xxx.id.rhs
-                Expr::Property(id, pos) => {
+                (Expr::Property(id, pos), rhs) => {
                     let mut args = [target.get_mut(scope)];
                     self.call_fn_raw(None, &make_getter(id), &mut args, None, *pos, 0)
                         .and_then(|mut val| {
-                            self.get_dot_val_helper(scope, Target::from(val.as_mut()), rhs, level)
+                            self.get_dot_val_helper(
+                                scope,
+                                nodes,
+                                Target::from(val.as_mut()),
+                                rhs,
+                                level,
+                            )
                         })
                 }
+                // xxx.fn_name(arg_expr_list).rhs
+                (dot_lhs @ Expr::FunctionCall(_, _, _, _, _), rhs) => self
+                    .get_dot_val_helper(scope, nodes, target, dot_lhs, level)
+                    .and_then(|mut val| {
+                        self.get_dot_val_helper(
+                            scope,
+                            nodes,
+                            Target::from(val.as_mut()),
+                            rhs,
+                            level,
+                        )
+                    }),
                 // xxx.idx_lhs[idx_expr].rhs
                 #[cfg(not(feature = "no_index"))]
-                Expr::Index(idx_lhs, idx_expr, op_pos) => {
-                    let val = match idx_lhs.as_ref() {
+                (Expr::Index(idx_lhs, idx_expr, op_pos), rhs) => {
+                    let (idx_lhs, idx_expr) = (&nodes[*idx_lhs], &nodes[*idx_expr]);
+
+                    let val = match idx_lhs {
                         // xxx.id[idx_expr].rhs
                         Expr::Property(id, pos) => {
                             let mut args = [target.get_mut(scope)];
@@ -667,34 +2006,40 @@ impl Engine<'_> {
                         }
                         // xxx.???[???][idx_expr].rhs
                         Expr::Index(_, _, _) => {
-                            self.get_dot_val_helper(scope, target, idx_lhs, level)?
+                            self.get_dot_val_helper(scope, nodes, target, idx_lhs, level)?
                         }
                         // Syntax error
                         _ => {
-                            return Err(EvalAltResult::ErrorDotExpr(
+                            return Err(Box::new(EvalAltResult::ErrorDotExpr(
                                 "".to_string(),
-                                dot_rhs.position(),
-                            ))
+                                dot_rhs.position(nodes),
+                            )))
                         }
                     };
 
-                    self.get_indexed_value(scope, &val, idx_expr, *op_pos, level)
+                    self.get_indexed_value(scope, nodes, &val, idx_expr, *op_pos, level)
                         .and_then(|(mut val, _, _)| {
-                            self.get_dot_val_helper(scope, Target::from(val.as_mut()), rhs, level)
+                            self.get_dot_val_helper(
+                                scope,
+                                nodes,
+                                Target::from(val.as_mut()),
+                                rhs,
+                                level,
+                            )
                         })
                 }
                 // Syntax error
-                _ => Err(EvalAltResult::ErrorDotExpr(
+                (dot_lhs, _) => Err(Box::new(EvalAltResult::ErrorDotExpr(
                     "".to_string(),
-                    dot_lhs.position(),
-                )),
+                    dot_lhs.position(nodes),
+                ))),
             },
 
             // Syntax error
-            _ => Err(EvalAltResult::ErrorDotExpr(
+            _ => Err(Box::new(EvalAltResult::ErrorDotExpr(
                 "".to_string(),
-                dot_rhs.position(),
-            )),
+                dot_rhs.position(nodes),
+            ))),
         }
     }
 
@@ -703,39 +2048,54 @@ impl Engine<'_> {
     fn get_dot_val(
         &mut self,
         scope: &mut Scope,
+        nodes: &Nodes,
         dot_lhs: &Expr,
         dot_rhs: &Expr,
         level: usize,
-    ) -> Result<Dynamic, EvalAltResult> {
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
         match dot_lhs {
+            // global.???
+            Expr::Variable(id, _) if self.is_global(scope, id) => {
+                let mut val = self.search_globals(scope, id).unwrap();
+                self.get_dot_val_helper(scope, nodes, Target::from(val.as_mut()), dot_rhs, level)
+            }
+
             // id.???
             Expr::Variable(id, pos) => {
-                let (entry, _) = Self::search_scope(scope, id, *pos)?;
+                let entry = scope.get_ref(id).ok_or_else(|| {
+                    Box::new(EvalAltResult::ErrorVariableNotFound(id.into(), *pos))
+                })?;
 
                 // Avoid referencing scope which is used below as mut
                 let entry = ScopeSource { name: id, ..entry };
 
                 // This is a variable property access (potential function call).
                 // Use a direct index into `scope` to directly mutate the variable value.
-                self.get_dot_val_helper(scope, Target::from_src(entry), dot_rhs, level)
+                self.get_dot_val_helper(scope, nodes, Target::from_src(entry), dot_rhs, level)
             }
 
             // idx_lhs[idx_expr].???
             #[cfg(not(feature = "no_index"))]
             Expr::Index(idx_lhs, idx_expr, op_pos) => {
+                let (idx_lhs, idx_expr) = (&nodes[*idx_lhs], &nodes[*idx_expr]);
                 let (idx_src_type, src, idx, mut val) =
-                    self.eval_index_expr(scope, idx_lhs, idx_expr, *op_pos, level)?;
-                let value =
-                    self.get_dot_val_helper(scope, Target::from(val.as_mut()), dot_rhs, level);
+                    self.eval_index_expr(scope, nodes, idx_lhs, idx_expr, *op_pos, level)?;
+                let value = self.get_dot_val_helper(
+                    scope,
+                    nodes,
+                    Target::from(val.as_mut()),
+                    dot_rhs,
+                    level,
+                );
 
                 // In case the expression mutated `target`, we need to update it back into the scope because it is cloned.
                 if let Some(src) = src {
                     match src.typ {
                         ScopeEntryType::Constant => {
-                            return Err(EvalAltResult::ErrorAssignmentToConstant(
+                            return Err(Box::new(EvalAltResult::ErrorAssignmentToConstant(
                                 src.name.to_string(),
-                                idx_lhs.position(),
-                            ));
+                                idx_lhs.position(nodes),
+                            )));
                         }
                         ScopeEntryType::Normal => {
                             Self::update_indexed_var_in_scope(
@@ -743,7 +2103,7 @@ impl Engine<'_> {
                                 scope,
                                 src,
                                 idx,
-                                (val, dot_rhs.position()),
+                                (val, dot_rhs.position(nodes)),
                             )?;
                         }
                     }
@@ -754,21 +2114,73 @@ impl Engine<'_> {
 
             // {expr}.???
             expr => {
-                let mut val = self.eval_expr(scope, expr, level)?;
-                self.get_dot_val_helper(scope, Target::from(val.as_mut()), dot_rhs, level)
+                let mut val = self.eval_expr(scope, nodes, expr, level)?;
+                self.get_dot_val_helper(scope, nodes, Target::from(val.as_mut()), dot_rhs, level)
             }
         }
     }
 
+    /// Is a name a read-only variable provided by the host, which is not shadowed by a variable
+    /// in the scope?
+    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+    fn is_global(&self, scope: &Scope, id: &str) -> bool {
+        self.globals.contains_key(id) && !scope.contains(id)
+    }
+
+    /// Get the value of a read-only variable provided by the host, if it is not shadowed by a
+    /// variable in the scope
+    fn search_globals(&self, scope: &Scope, id: &str) -> Option<Dynamic> {
+        if scope.contains(id) {
+            None
+        } else {
+            self.globals.get(id).map(|value| (**value).clone())
+        }
+    }
+
+    /// Make the error for assigning to a variable not in the scope, which may be a read-only
+    /// variable provided by the host
+    fn assignment_err(&self, id: &str, pos: Position, op_pos: Position) -> Box<EvalAltResult> {
+        if self.globals.contains_key(id) {
+            Box::new(EvalAltResult::ErrorAssignmentToConstant(id.into(), op_pos))
+        } else {
+            Box::new(EvalAltResult::ErrorVariableNotFound(id.into(), pos))
+        }
+    }
+
     /// Search for a variable within the scope, returning its value and index inside the Scope
     fn search_scope<'a>(
         scope: &'a Scope,
         id: &str,
         begin: Position,
-    ) -> Result<(ScopeSource<'a>, Dynamic), EvalAltResult> {
+    ) -> Result<(ScopeSource<'a>, Dynamic), Box<EvalAltResult>> {
         scope
             .get(id)
-            .ok_or_else(|| EvalAltResult::ErrorVariableNotFound(id.into(), begin))
+            .ok_or_else(|| Box::new(EvalAltResult::ErrorVariableNotFound(id.into(), begin)))
+    }
+
+    /// Search for a variable to be updated via an assignment, returning its entry in the scope
+    /// and a copy of its value.  Constants and read-only global variables cannot be updated.
+    #[cfg(not(feature = "no_index"))]
+    fn search_scope_for_update<'a>(
+        &self,
+        scope: &Scope,
+        id: &'a str,
+        pos: Position,
+        op_pos: Position,
+    ) -> Result<(ScopeSource<'a>, Dynamic), Box<EvalAltResult>> {
+        if !scope.contains(id) {
+            return Err(self.assignment_err(id, pos, op_pos));
+        }
+
+        let (entry, target) = Self::search_scope(scope, id, pos)?;
+
+        match entry.typ {
+            ScopeEntryType::Constant => Err(Box::new(EvalAltResult::ErrorAssignmentToConstant(
+                id.to_string(),
+                op_pos,
+            ))),
+            ScopeEntryType::Normal => Ok((ScopeSource { name: id, ..entry }, target)),
+        }
     }
 
     /// Get the value at the indexed position of a base type
@@ -776,27 +2188,70 @@ impl Engine<'_> {
     fn get_indexed_value(
         &mut self,
         scope: &mut Scope,
+        nodes: &Nodes,
         val: &Dynamic,
         idx_expr: &Expr,
         op_pos: Position,
         level: usize,
-    ) -> Result<(Dynamic, IndexSourceType, IndexValue), EvalAltResult> {
-        let idx_pos = idx_expr.position();
+    ) -> Result<(Dynamic, IndexSourceType, IndexValue), Box<EvalAltResult>> {
+        let idx_pos = idx_expr.position(nodes);
 
         // val_array[idx]
         if let Some(arr) = val.downcast_ref::<Array>() {
             let idx = self
-                .eval_expr(scope, idx_expr, level)?
+                .eval_expr(scope, nodes, idx_expr, level)?
                 .try_cast::<INT>()
-                .map_err(|_| EvalAltResult::ErrorNumericIndexExpr(idx_expr.position()))?;
+                .map_err(|_| {
+                    Box::new(EvalAltResult::ErrorNumericIndexExpr(
+                        idx_expr.position(nodes),
+                    ))
+                })?;
 
             return if idx >= 0 {
                 arr.get(idx as usize)
                     .cloned()
                     .map(|v| (v, IndexSourceType::Array, IndexValue::from_num(idx)))
-                    .ok_or_else(|| EvalAltResult::ErrorArrayBounds(arr.len(), idx, idx_pos))
+                    .ok_or_else(|| {
+                        Box::new(EvalAltResult::ErrorArrayBounds(arr.len(), idx, idx_pos))
+                    })
             } else {
-                Err(EvalAltResult::ErrorArrayBounds(arr.len(), idx, idx_pos))
+                Err(Box::new(EvalAltResult::ErrorArrayBounds(
+                    arr.len(),
+                    idx,
+                    idx_pos,
+                )))
+            };
+        }
+
+        // val_blob[idx]
+        if let Some(blob) = val.as_bytes() {
+            let idx = self
+                .eval_expr(scope, nodes, idx_expr, level)?
+                .try_cast::<INT>()
+                .map_err(|_| {
+                    Box::new(EvalAltResult::ErrorNumericIndexExpr(
+                        idx_expr.position(nodes),
+                    ))
+                })?;
+
+            return if idx >= 0 {
+                blob.get(idx as usize)
+                    .map(|&b| {
+                        (
+                            (b as INT).into_dynamic(),
+                            IndexSourceType::Blob,
+                            IndexValue::from_num(idx),
+                        )
+                    })
+                    .ok_or_else(|| {
+                        Box::new(EvalAltResult::ErrorArrayBounds(blob.len(), idx, idx_pos))
+                    })
+            } else {
+                Err(Box::new(EvalAltResult::ErrorArrayBounds(
+                    blob.len(),
+                    idx,
+                    idx_pos,
+                )))
             };
         }
 
@@ -805,9 +2260,13 @@ impl Engine<'_> {
             // val_map[idx]
             if let Some(map) = val.downcast_ref::<Map>() {
                 let idx = self
-                    .eval_expr(scope, idx_expr, level)?
+                    .eval_expr(scope, nodes, idx_expr, level)?
                     .try_cast::<String>()
-                    .map_err(|_| EvalAltResult::ErrorStringIndexExpr(idx_expr.position()))?;
+                    .map_err(|_| {
+                        Box::new(EvalAltResult::ErrorStringIndexExpr(
+                            idx_expr.position(nodes),
+                        ))
+                    })?;
 
                 return Ok((
                     map.get(&idx).cloned().unwrap_or_else(|| ().into_dynamic()),
@@ -818,11 +2277,15 @@ impl Engine<'_> {
         }
 
         // val_string[idx]
-        if let Some(s) = val.downcast_ref::<String>() {
+        if let Some(s) = val.as_str() {
             let idx = self
-                .eval_expr(scope, idx_expr, level)?
+                .eval_expr(scope, nodes, idx_expr, level)?
                 .try_cast::<INT>()
-                .map_err(|_| EvalAltResult::ErrorNumericIndexExpr(idx_expr.position()))?;
+                .map_err(|_| {
+                    Box::new(EvalAltResult::ErrorNumericIndexExpr(
+                        idx_expr.position(nodes),
+                    ))
+                })?;
 
             return if idx >= 0 {
                 s.chars()
@@ -835,22 +2298,80 @@ impl Engine<'_> {
                         )
                     })
                     .ok_or_else(|| {
-                        EvalAltResult::ErrorStringBounds(s.chars().count(), idx, idx_pos)
+                        Box::new(EvalAltResult::ErrorStringBounds(
+                            s.chars().count(),
+                            idx,
+                            idx_pos,
+                        ))
                     })
             } else {
-                Err(EvalAltResult::ErrorStringBounds(
+                Err(Box::new(EvalAltResult::ErrorStringBounds(
                     s.chars().count(),
                     idx,
                     idx_pos,
+                )))
+            };
+        }
+
+        // val_matrix[row]
+        #[cfg(feature = "matrix")]
+        #[cfg(not(feature = "no_float"))]
+        {
+            if let Some(m) = val.downcast_ref::<Matrix>() {
+                let idx = self
+                    .eval_expr(scope, nodes, idx_expr, level)?
+                    .try_cast::<INT>()
+                    .map_err(|_| {
+                        Box::new(EvalAltResult::ErrorNumericIndexExpr(
+                            idx_expr.position(nodes),
+                        ))
+                    })?;
+
+                return if idx >= 0 && (idx as usize) < m.nrows() {
+                    Ok((
+                        get_row(m, idx as usize).into_dynamic(),
+                        IndexSourceType::Matrix,
+                        IndexValue::from_num(idx),
+                    ))
+                } else {
+                    Err(Box::new(EvalAltResult::ErrorArrayBounds(
+                        m.nrows(),
+                        idx,
+                        idx_pos,
+                    )))
+                };
+            }
+        }
+
+        // val_int[bit]
+        if let Some(&n) = val.downcast_ref::<INT>() {
+            let idx = self
+                .eval_expr(scope, nodes, idx_expr, level)?
+                .try_cast::<INT>()
+                .map_err(|_| {
+                    Box::new(EvalAltResult::ErrorNumericIndexExpr(
+                        idx_expr.position(nodes),
+                    ))
+                })?;
+
+            return if idx >= 0 && (idx as usize) < INT_BITS {
+                Ok((
+                    (n & (1 << idx) != 0).into_dynamic(),
+                    IndexSourceType::BitField,
+                    IndexValue::from_num(idx),
                 ))
+            } else {
+                Err(Box::new(EvalAltResult::ErrorBitFieldBounds(
+                    INT_BITS, idx, idx_pos,
+                )))
             };
         }
 
         // Error - cannot be indexed
-        Err(EvalAltResult::ErrorIndexingType(
+        Err(Box::new(EvalAltResult::ErrorIndexingType(
             self.map_type_name(val.type_name()).to_string(),
             op_pos,
-        ))
+        )))
     }
 
     /// Evaluate an index expression
@@ -858,6 +2379,7 @@ impl Engine<'_> {
     fn eval_index_expr<'a>(
         &mut self,
         scope: &mut Scope,
+        nodes: &Nodes,
         lhs: &'a Expr,
         idx_expr: &Expr,
         op_pos: Position,
@@ -869,11 +2391,11 @@ impl Engine<'_> {
             IndexValue,
             Dynamic,
         ),
-        EvalAltResult,
+        Box<EvalAltResult>,
     > {
         match lhs {
             // id[idx_expr]
-            Expr::Variable(id, _) => {
+            Expr::Variable(id, _) if !self.is_global(scope, id) => {
                 let (
                     ScopeSource {
                         typ: src_type,
@@ -881,10 +2403,10 @@ impl Engine<'_> {
                         ..
                     },
                     val,
-                ) = Self::search_scope(scope, &id, lhs.position())?;
+                ) = Self::search_scope(scope, &id, lhs.position(nodes))?;
 
                 let (val, idx_src_type, idx) =
-                    self.get_indexed_value(scope, &val, idx_expr, op_pos, level)?;
+                    self.get_indexed_value(scope, nodes, &val, idx_expr, op_pos, level)?;
 
                 Ok((
                     idx_src_type,
@@ -898,11 +2420,19 @@ impl Engine<'_> {
                 ))
             }
 
+            // global[idx_expr] - index into the read-only value in place, without copying it
+            Expr::Variable(id, _) => {
+                let val = self.globals[id.as_str()].clone();
+
+                self.get_indexed_value(scope, nodes, &val, idx_expr, op_pos, level)
+                    .map(|(val, _, idx)| (IndexSourceType::Expression, None, idx, val))
+            }
+
             // (expr)[idx_expr]
             expr => {
-                let val = self.eval_expr(scope, expr, level)?;
+                let val = self.eval_expr(scope, nodes, expr, level)?;
 
-                self.get_indexed_value(scope, &val, idx_expr, op_pos, level)
+                self.get_indexed_value(scope, nodes, &val, idx_expr, op_pos, level)
                     .map(|(val, _, idx)| (IndexSourceType::Expression, None, idx, val))
             }
         }
@@ -922,6 +2452,26 @@ impl Engine<'_> {
         }
     }
 
+    /// Set or clear a bit in an integer, the new value of which must be a boolean
+    #[cfg(not(feature = "no_index"))]
+    fn set_bit(
+        n: &mut INT,
+        idx: usize,
+        new_val: Dynamic,
+        pos: Position,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let value = new_val
+            .try_cast::<bool>()
+            .map_err(|_| Box::new(EvalAltResult::ErrorBooleanArgMismatch("[]".into(), pos)))?;
+
+        if value {
+            *n |= 1 << idx;
+        } else {
+            *n &= !(1 << idx);
+        }
+        Ok(())
+    }
+
     /// Update the value at an index position in a variable inside the scope
     #[cfg(not(feature = "no_index"))]
     fn update_indexed_var_in_scope(
@@ -930,7 +2480,7 @@ impl Engine<'_> {
         src: ScopeSource,
         idx: IndexValue,
         new_val: (Dynamic, Position),
-    ) -> Result<Dynamic, EvalAltResult> {
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
         match idx_src_type {
             // array_id[idx] = val
             IndexSourceType::Array => {
@@ -939,6 +2489,29 @@ impl Engine<'_> {
                 Ok(().into_dynamic())
             }
 
+            // blob_id[idx] = val
+            IndexSourceType::Blob => {
+                make_owned(scope.get_mut(src));
+                let blob = scope.get_mut_by_type::<Blob>(src);
+                let pos = new_val.1;
+                // Value must be an integer
+                let byte = new_val
+                    .0
+                    .try_cast::<INT>()
+                    .ok()
+                    .and_then(|byte| u8::try_from(byte).ok())
+                    .ok_or_else(|| Box::new(EvalAltResult::ErrorByteMismatch(pos)))?;
+                blob[idx.as_num()] = byte;
+                Ok(().into_dynamic())
+            }
+
+            // int_id[bit] = val
+            IndexSourceType::BitField => {
+                let n = scope.get_mut_by_type::<INT>(src);
+                Self::set_bit(n, idx.as_num(), new_val.0, new_val.1)?;
+                Ok(().into_dynamic())
+            }
+
             // map_id[idx] = val
             #[cfg(not(feature = "no_object"))]
             IndexSourceType::Map => {
@@ -949,17 +2522,27 @@ impl Engine<'_> {
 
             // string_id[idx] = val
             IndexSourceType::String => {
+                make_owned(scope.get_mut(src));
                 let s = scope.get_mut_by_type::<String>(src);
                 let pos = new_val.1;
                 // Value must be a character
                 let ch = new_val
                     .0
                     .try_cast::<char>()
-                    .map_err(|_| EvalAltResult::ErrorCharMismatch(pos))?;
+                    .map_err(|_| Box::new(EvalAltResult::ErrorCharMismatch(pos)))?;
                 Self::str_replace_char(s, idx.as_num(), ch);
                 Ok(().into_dynamic())
             }
 
+            // matrix_id[row] = val
+            #[cfg(feature = "matrix")]
+            #[cfg(not(feature = "no_float"))]
+            IndexSourceType::Matrix => {
+                let m = scope.get_mut_by_type::<Matrix>(src);
+                set_row(m, idx.as_num(), new_val.0, new_val.1)?;
+                Ok(().into_dynamic())
+            }
+
             IndexSourceType::Expression => panic!("expression cannot be indexed for update"),
         }
     }
@@ -971,12 +2554,26 @@ impl Engine<'_> {
         idx: IndexValue,
         new_val: Dynamic,
         pos: Position,
-    ) -> Result<Dynamic, EvalAltResult> {
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        // Copy a string or BLOB borrowed from the host before modifying it
+        make_owned(&mut target);
+
         if let Some(arr) = target.downcast_mut::<Array>() {
             arr[idx.as_num()] = new_val;
             return Ok(target);
         }
 
+        if let Some(blob) = target.downcast_mut::<Blob>() {
+            // Value must be an integer
+            let byte = new_val
+                .try_cast::<INT>()
+                .ok()
+                .and_then(|byte| u8::try_from(byte).ok())
+                .ok_or_else(|| Box::new(EvalAltResult::ErrorByteMismatch(pos)))?;
+            blob[idx.as_num()] = byte;
+            return Ok(target);
+        }
+
         #[cfg(not(feature = "no_object"))]
         {
             if let Some(map) = target.downcast_mut::<Map>() {
@@ -989,13 +2586,72 @@ impl Engine<'_> {
             // Value must be a character
             let ch = new_val
                 .try_cast::<char>()
-                .map_err(|_| EvalAltResult::ErrorCharMismatch(pos))?;
+                .map_err(|_| Box::new(EvalAltResult::ErrorCharMismatch(pos)))?;
             Self::str_replace_char(s, idx.as_num(), ch);
             return Ok(target);
         }
 
+        if let Some(n) = target.downcast_mut::<INT>() {
+            Self::set_bit(n, idx.as_num(), new_val, pos)?;
+            return Ok(target);
+        }
+
+        #[cfg(feature = "matrix")]
+        #[cfg(not(feature = "no_float"))]
+        {
+            if let Some(m) = target.downcast_mut::<Matrix>() {
+                set_row(m, idx.as_num(), new_val, pos)?;
+                return Ok(target);
+            }
+        }
+
         // All other variable types should be an error
-        panic!("array, map or string source type expected for indexing")
+        panic!("array, BLOB, map, string or integer source type expected for indexing")
+    }
+
+    /// Get the values along a chain of index positions (e.g. `[i][j]` in `x[i][j]`) inside `target`,
+    /// evaluating each index expression once.
+    ///
+    /// Returns each value indexed into (starting with `target`) together with its index value,
+    /// and the value at the end of the chain.
+    #[cfg(not(feature = "no_index"))]
+    fn get_indexed_chain(
+        &mut self,
+        scope: &mut Scope,
+        nodes: &Nodes,
+        target: Dynamic,
+        idx_exprs: &[(&Expr, Position)],
+        level: usize,
+    ) -> Result<(IndexChain, Dynamic), Box<EvalAltResult>> {
+        let mut chain = Vec::with_capacity(idx_exprs.len());
+        let mut value = target;
+
+        for &(idx_expr, op_pos) in idx_exprs {
+            let (item, _, idx) =
+                self.get_indexed_value(scope, nodes, &value, idx_expr, op_pos, level)?;
+            chain.push((value, idx));
+            value = item;
+        }
+
+        Ok((chain, value))
+    }
+
+    /// Write a new value at the end of a chain of index positions (as returned by
+    /// `get_indexed_chain`), writing back every value along the chain.
+    ///
+    /// Returns the updated value at the start of the chain.
+    #[cfg(not(feature = "no_index"))]
+    fn update_indexed_chain(
+        chain: IndexChain,
+        new_val: Dynamic,
+        pos: Position,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        chain
+            .into_iter()
+            .rev()
+            .try_fold(new_val, |val, (target, idx)| {
+                Self::update_indexed_value(target, idx, val, pos)
+            })
     }
 
     /// Chain-evaluate a dot setter
@@ -1003,11 +2659,12 @@ impl Engine<'_> {
     fn set_dot_val_helper(
         &mut self,
         scope: &mut Scope,
+        nodes: &Nodes,
         this_ptr: &mut Variant,
         dot_rhs: &Expr,
         new_val: (&mut Dynamic, Position),
         level: usize,
-    ) -> Result<Dynamic, EvalAltResult> {
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
         match dot_rhs {
             // xxx.id
             Expr::Property(id, pos) => {
@@ -1016,17 +2673,16 @@ impl Engine<'_> {
             }
 
             // xxx.lhs[idx_expr]
-            // TODO - Allow chaining of indexing!
             #[cfg(not(feature = "no_index"))]
-            Expr::Index(lhs, idx_expr, op_pos) => match lhs.as_ref() {
-                // xxx.id[idx_expr]
-                Expr::Property(id, pos) => self
+            Expr::Index(_, _, op_pos) => match split_index_chain(nodes, dot_rhs) {
+                // xxx.id[idx_expr]...[idx_expr]
+                (Expr::Property(id, pos), idx_exprs) => self
                     .call_fn_raw(None, &make_getter(id), &mut [this_ptr], None, *pos, 0)
                     .and_then(|val| {
-                        let (_, _, idx) =
-                            self.get_indexed_value(scope, &val, idx_expr, *op_pos, level)?;
+                        let (chain, _) =
+                            self.get_indexed_chain(scope, nodes, val, &idx_exprs, level)?;
 
-                        Self::update_indexed_value(val, idx, new_val.0.clone(), new_val.1)
+                        Self::update_indexed_chain(chain, new_val.0.clone(), new_val.1)
                     })
                     .and_then(|mut val| {
                         let mut args = [this_ptr, val.as_mut()];
@@ -1034,19 +2690,19 @@ impl Engine<'_> {
                     }),
 
                 // All others - syntax error for setters chain
-                _ => Err(EvalAltResult::ErrorDotExpr(
+                _ => Err(Box::new(EvalAltResult::ErrorDotExpr(
                     "for assignment".to_string(),
                     *op_pos,
-                )),
+                ))),
             },
 
             // xxx.lhs.{...}
-            Expr::Dot(lhs, rhs, _) => match lhs.as_ref() {
+            Expr::Dot(lhs, rhs, _) => match (&nodes[*lhs], &nodes[*rhs]) {
                 // xxx.id.rhs
-                Expr::Property(id, pos) => {
+                (Expr::Property(id, pos), rhs) => {
                     self.call_fn_raw(None, &make_getter(id), &mut [this_ptr], None, *pos, 0)
                         .and_then(|mut val| {
-                            self.set_dot_val_helper(scope, val.as_mut(), rhs, new_val, level)
+                            self.set_dot_val_helper(scope, nodes, val.as_mut(), rhs, new_val, level)
                                 .map(|_| val) // Discard Ok return value
                         })
                         .and_then(|mut val| {
@@ -1056,22 +2712,23 @@ impl Engine<'_> {
                 }
 
                 // xxx.lhs[idx_expr].rhs
-                // TODO - Allow chaining of indexing!
                 #[cfg(not(feature = "no_index"))]
-                Expr::Index(lhs, idx_expr, op_pos) => match lhs.as_ref() {
-                    // xxx.id[idx_expr].rhs
-                    Expr::Property(id, pos) => {
+                (lhs @ Expr::Index(_, _, op_pos), rhs) => match split_index_chain(nodes, lhs) {
+                    // xxx.id[idx_expr]...[idx_expr].rhs
+                    (Expr::Property(id, pos), idx_exprs) => {
                         self.call_fn_raw(None, &make_getter(id), &mut [this_ptr], None, *pos, 0)
                             .and_then(|v| {
-                                let (mut value, _, idx) =
-                                    self.get_indexed_value(scope, &v, idx_expr, *op_pos, level)?;
+                                let (chain, mut value) =
+                                    self.get_indexed_chain(scope, nodes, v, &idx_exprs, level)?;
 
                                 let val_pos = new_val.1;
                                 let this_ptr = value.as_mut();
-                                self.set_dot_val_helper(scope, this_ptr, rhs, new_val, level)?;
+                                self.set_dot_val_helper(
+                                    scope, nodes, this_ptr, rhs, new_val, level,
+                                )?;
 
-                                // In case the expression mutated `target`, we need to update it back into the scope because it is cloned.
-                                Self::update_indexed_value(v, idx, value, val_pos)
+                                // The indexed values are copies, so write them all back
+                                Self::update_indexed_chain(chain, value, val_pos)
                             })
                             .and_then(|mut v| {
                                 let mut args = [this_ptr, v.as_mut()];
@@ -1080,54 +2737,59 @@ impl Engine<'_> {
                     }
 
                     // All others - syntax error for setters chain
-                    _ => Err(EvalAltResult::ErrorDotExpr(
+                    _ => Err(Box::new(EvalAltResult::ErrorDotExpr(
                         "for assignment".to_string(),
                         *op_pos,
-                    )),
+                    ))),
                 },
 
                 // All others - syntax error for setters chain
-                _ => Err(EvalAltResult::ErrorDotExpr(
+                (lhs, _) => Err(Box::new(EvalAltResult::ErrorDotExpr(
                     "for assignment".to_string(),
-                    lhs.position(),
-                )),
+                    lhs.position(nodes),
+                ))),
             },
 
             // Syntax error
-            _ => Err(EvalAltResult::ErrorDotExpr(
+            _ => Err(Box::new(EvalAltResult::ErrorDotExpr(
                 "for assignment".to_string(),
-                dot_rhs.position(),
-            )),
+                dot_rhs.position(nodes),
+            ))),
         }
     }
 
     // Evaluate a dot chain setter
     #[cfg(not(feature = "no_object"))]
+    #[allow(clippy::too_many_arguments)]
     fn set_dot_val(
         &mut self,
         scope: &mut Scope,
+        nodes: &Nodes,
         dot_lhs: &Expr,
         dot_rhs: &Expr,
         new_val: (&mut Dynamic, Position),
         op_pos: Position,
         level: usize,
-    ) -> Result<Dynamic, EvalAltResult> {
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
         match dot_lhs {
             // id.???
             Expr::Variable(id, pos) => {
+                if !scope.contains(id) {
+                    return Err(self.assignment_err(id, *pos, op_pos));
+                }
+
                 let (entry, mut target) = Self::search_scope(scope, id, *pos)?;
 
                 match entry.typ {
-                    ScopeEntryType::Constant => Err(EvalAltResult::ErrorAssignmentToConstant(
-                        id.to_string(),
-                        op_pos,
+                    ScopeEntryType::Constant => Err(Box::new(
+                        EvalAltResult::ErrorAssignmentToConstant(id.to_string(), op_pos),
                     )),
                     _ => {
                         // Avoid referencing scope which is used below as mut
                         let entry = ScopeSource { name: id, ..entry };
                         let this_ptr = target.as_mut();
-                        let value =
-                            self.set_dot_val_helper(scope, this_ptr, dot_rhs, new_val, level);
+                        let value = self
+                            .set_dot_val_helper(scope, nodes, this_ptr, dot_rhs, new_val, level);
 
                         // In case the expression mutated `target`, we need to update it back into the scope because it is cloned.
                         *scope.get_mut(entry) = target;
@@ -1137,34 +2799,76 @@ impl Engine<'_> {
                 }
             }
 
+            // lhs[idx_expr]...[idx_expr].???
+            #[cfg(not(feature = "no_index"))]
+            Expr::Index(lhs, _, _) if matches!(nodes[*lhs], Expr::Index(_, _, _)) => {
+                match split_index_chain(nodes, dot_lhs) {
+                    (Expr::Variable(id, pos), idx_exprs) => {
+                        let (entry, target) =
+                            self.search_scope_for_update(scope, id, *pos, op_pos)?;
+                        let (chain, mut value) =
+                            self.get_indexed_chain(scope, nodes, target, &idx_exprs, level)?;
+
+                        let val_pos = new_val.1;
+                        let this_ptr = value.as_mut();
+                        let result = self
+                            .set_dot_val_helper(scope, nodes, this_ptr, dot_rhs, new_val, level)?;
+
+                        // The indexed values are copies, so write them all back into the scope
+                        *scope.get_mut(entry) = Self::update_indexed_chain(chain, value, val_pos)?;
+
+                        Ok(result)
+                    }
+
+                    // All others - syntax error for setters chain
+                    (expr, _) => Err(Box::new(EvalAltResult::ErrorDotExpr(
+                        "for assignment".to_string(),
+                        expr.position(nodes),
+                    ))),
+                }
+            }
+
             // lhs[idx_expr].???
-            // TODO - Allow chaining of indexing!
             #[cfg(not(feature = "no_index"))]
             Expr::Index(lhs, idx_expr, op_pos) => {
+                let (lhs, idx_expr) = (&nodes[*lhs], &nodes[*idx_expr]);
                 let (idx_src_type, src, idx, mut target) =
-                    self.eval_index_expr(scope, lhs, idx_expr, *op_pos, level)?;
+                    self.eval_index_expr(scope, nodes, lhs, idx_expr, *op_pos, level)?;
+
+                // A copy of a read-only variable cannot be written back
+                let src = match (src, lhs) {
+                    (Some(src), _) => src,
+                    (None, Expr::Variable(name, pos)) => {
+                        return Err(self.assignment_err(name, *pos, *pos))
+                    }
+                    (None, _) => {
+                        return Err(Box::new(EvalAltResult::ErrorAssignmentToUnknownLHS(
+                            lhs.position(nodes),
+                        )))
+                    }
+                };
+
                 let val_pos = new_val.1;
                 let this_ptr = target.as_mut();
-                let value = self.set_dot_val_helper(scope, this_ptr, dot_rhs, new_val, level);
+                let value =
+                    self.set_dot_val_helper(scope, nodes, this_ptr, dot_rhs, new_val, level);
 
                 // In case the expression mutated `target`, we need to update it back into the scope because it is cloned.
-                if let Some(src) = src {
-                    match src.typ {
-                        ScopeEntryType::Constant => {
-                            return Err(EvalAltResult::ErrorAssignmentToConstant(
-                                src.name.to_string(),
-                                lhs.position(),
-                            ));
-                        }
-                        ScopeEntryType::Normal => {
-                            Self::update_indexed_var_in_scope(
-                                idx_src_type,
-                                scope,
-                                src,
-                                idx,
-                                (target, val_pos),
-                            )?;
-                        }
+                match src.typ {
+                    ScopeEntryType::Constant => {
+                        return Err(Box::new(EvalAltResult::ErrorAssignmentToConstant(
+                            src.name.to_string(),
+                            lhs.position(nodes),
+                        )));
+                    }
+                    ScopeEntryType::Normal => {
+                        Self::update_indexed_var_in_scope(
+                            idx_src_type,
+                            scope,
+                            src,
+                            idx,
+                            (target, val_pos),
+                        )?;
                     }
                 }
 
@@ -1172,10 +2876,10 @@ impl Engine<'_> {
             }
 
             // Syntax error
-            _ => Err(EvalAltResult::ErrorDotExpr(
+            _ => Err(Box::new(EvalAltResult::ErrorDotExpr(
                 "for assignment".to_string(),
-                dot_lhs.position(),
-            )),
+                dot_lhs.position(nodes),
+            ))),
         }
     }
 
@@ -1183,9 +2887,18 @@ impl Engine<'_> {
     fn eval_expr(
         &mut self,
         scope: &mut Scope,
+        nodes: &Nodes,
         expr: &Expr,
         level: usize,
-    ) -> Result<Dynamic, EvalAltResult> {
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        if self.is_stack_exhausted() {
+            return Err(Box::new(EvalAltResult::ErrorStackOverflow(
+                expr.position(nodes),
+            )));
+        }
+
+        self.stats.operations += 1;
+
         match expr {
             #[cfg(not(feature = "no_float"))]
             Expr::FloatConstant(f, _) => Ok(f.into_dynamic()),
@@ -1193,32 +2906,40 @@ impl Engine<'_> {
             Expr::IntegerConstant(i, _) => Ok(i.into_dynamic()),
             Expr::StringConstant(s, _) => Ok(s.into_dynamic()),
             Expr::CharConstant(c, _) => Ok(c.into_dynamic()),
-            Expr::Variable(id, pos) => Self::search_scope(scope, id, *pos).map(|(_, val)| val),
+            Expr::Variable(id, pos) => match self.search_globals(scope, id) {
+                Some(val) => Ok(val),
+                None => Self::search_scope(scope, id, *pos).map(|(_, val)| val),
+            },
             Expr::Property(_, _) => panic!("unexpected property."),
 
             // lhs[idx_expr]
             #[cfg(not(feature = "no_index"))]
             Expr::Index(lhs, idx_expr, op_pos) => self
-                .eval_index_expr(scope, lhs, idx_expr, *op_pos, level)
+                .eval_index_expr(
+                    scope,
+                    nodes,
+                    &nodes[*lhs],
+                    &nodes[*idx_expr],
+                    *op_pos,
+                    level,
+                )
                 .map(|(_, _, _, x)| x),
 
             // Statement block
-            Expr::Stmt(stmt, _) => self.eval_stmt(scope, stmt, level),
+            Expr::Stmt(stmt, _) => self.eval_stmt(scope, nodes, &nodes[*stmt], level),
 
             // lhs = rhs
             Expr::Assignment(lhs, rhs, op_pos) => {
-                let mut rhs_val = self.eval_expr(scope, rhs, level)?;
+                let (lhs, rhs) = (&nodes[*lhs], &nodes[*rhs]);
+                let mut rhs_val = self.eval_expr(scope, nodes, rhs, level)?;
 
-                match lhs.as_ref() {
+                match lhs {
                     // name = rhs
                     Expr::Variable(name, pos) => match scope
-                        .get(name)
-                        .ok_or_else(|| EvalAltResult::ErrorVariableNotFound(name.clone(), *pos))?
-                        .0
+                        .get_ref(name)
+                        .ok_or_else(|| self.assignment_err(name, *pos, *op_pos))?
                     {
-                        entry
-                        @
-                        ScopeSource {
+                        entry @ ScopeSource {
                             typ: ScopeEntryType::Normal,
                             ..
                         } => {
@@ -1232,38 +2953,68 @@ impl Engine<'_> {
                         ScopeSource {
                             typ: ScopeEntryType::Constant,
                             ..
-                        } => Err(EvalAltResult::ErrorAssignmentToConstant(
+                        } => Err(Box::new(EvalAltResult::ErrorAssignmentToConstant(
                             name.to_string(),
                             *op_pos,
-                        )),
+                        ))),
                     },
 
+                    // idx_lhs[idx_expr]...[idx_expr] = rhs
+                    #[cfg(not(feature = "no_index"))]
+                    Expr::Index(idx_lhs, _, _)
+                        if matches!(nodes[*idx_lhs], Expr::Index(_, _, _)) =>
+                    {
+                        match split_index_chain(nodes, lhs) {
+                            (Expr::Variable(id, pos), idx_exprs) => {
+                                let (entry, target) =
+                                    self.search_scope_for_update(scope, id, *pos, *op_pos)?;
+                                let (chain, _) = self
+                                    .get_indexed_chain(scope, nodes, target, &idx_exprs, level)?;
+
+                                // The indexed values are copies, so write them all back into the scope
+                                *scope.get_mut(entry) = Self::update_indexed_chain(
+                                    chain,
+                                    rhs_val,
+                                    rhs.position(nodes),
+                                )?;
+
+                                Ok(().into_dynamic())
+                            }
+                            (expr, _) => Err(Box::new(EvalAltResult::ErrorAssignmentToUnknownLHS(
+                                expr.position(nodes),
+                            ))),
+                        }
+                    }
+
                     // idx_lhs[idx_expr] = rhs
                     #[cfg(not(feature = "no_index"))]
                     Expr::Index(idx_lhs, idx_expr, op_pos) => {
+                        let (idx_lhs, idx_expr) = (&nodes[*idx_lhs], &nodes[*idx_expr]);
                         let (idx_src_type, src, idx, _) =
-                            self.eval_index_expr(scope, idx_lhs, idx_expr, *op_pos, level)?;
+                            self.eval_index_expr(scope, nodes, idx_lhs, idx_expr, *op_pos, level)?;
 
                         if let Some(src) = src {
                             match src.typ {
                                 ScopeEntryType::Constant => {
-                                    Err(EvalAltResult::ErrorAssignmentToConstant(
+                                    Err(Box::new(EvalAltResult::ErrorAssignmentToConstant(
                                         src.name.to_string(),
-                                        idx_lhs.position(),
-                                    ))
+                                        idx_lhs.position(nodes),
+                                    )))
                                 }
                                 ScopeEntryType::Normal => Ok(Self::update_indexed_var_in_scope(
                                     idx_src_type,
                                     scope,
                                     src,
                                     idx,
-                                    (rhs_val, rhs.position()),
+                                    (rhs_val, rhs.position(nodes)),
                                 )?),
                             }
+                        } else if let Expr::Variable(name, pos) = idx_lhs {
+                            Err(self.assignment_err(name, *pos, *pos))
                         } else {
-                            Err(EvalAltResult::ErrorAssignmentToUnknownLHS(
-                                idx_lhs.position(),
-                            ))
+                            Err(Box::new(EvalAltResult::ErrorAssignmentToUnknownLHS(
+                                idx_lhs.position(nodes),
+                            )))
                         }
                     }
 
@@ -1271,33 +3022,41 @@ impl Engine<'_> {
                     #[cfg(not(feature = "no_object"))]
                     Expr::Dot(dot_lhs, dot_rhs, _) => self.set_dot_val(
                         scope,
-                        dot_lhs,
-                        dot_rhs,
-                        (&mut rhs_val, rhs.position()),
+                        nodes,
+                        &nodes[*dot_lhs],
+                        &nodes[*dot_rhs],
+                        (&mut rhs_val, rhs.position(nodes)),
                         *op_pos,
                         level,
                     ),
 
                     // Error assignment to constant
-                    expr if expr.is_constant() => Err(EvalAltResult::ErrorAssignmentToConstant(
-                        expr.get_constant_str(),
-                        lhs.position(),
-                    )),
+                    expr if expr.is_constant(nodes) => {
+                        Err(Box::new(EvalAltResult::ErrorAssignmentToConstant(
+                            expr.get_constant_str(nodes),
+                            lhs.position(nodes),
+                        )))
+                    }
 
                     // Syntax error
-                    _ => Err(EvalAltResult::ErrorAssignmentToUnknownLHS(lhs.position())),
+                    _ => Err(Box::new(EvalAltResult::ErrorAssignmentToUnknownLHS(
+                        lhs.position(nodes),
+                    ))),
                 }
             }
 
             #[cfg(not(feature = "no_object"))]
-            Expr::Dot(lhs, rhs, _) => self.get_dot_val(scope, lhs, rhs, level),
+            Expr::Dot(lhs, rhs, _) => {
+                self.get_dot_val(scope, nodes, &nodes[*lhs], &nodes[*rhs], level)
+            }
 
             #[cfg(not(feature = "no_index"))]
             Expr::Array(contents, _) => {
                 let mut arr = Array::new();
 
-                contents.into_iter().try_for_each(|item| {
-                    self.eval_expr(scope, item, level).map(|val| arr.push(val))
+                nodes[*contents].iter().try_for_each(|item| {
+                    self.eval_expr(scope, nodes, item, level)
+                        .map(|val| arr.push(val))
                 })?;
 
                 Ok(Box::new(arr))
@@ -1308,39 +3067,45 @@ impl Engine<'_> {
                 let mut map = Map::new();
 
                 contents.into_iter().try_for_each(|item| {
-                    self.eval_expr(scope, &item.1, level).map(|val| {
-                        map.insert(item.0.clone(), val);
-                    })
+                    self.eval_expr(scope, nodes, &nodes[item.1], level)
+                        .map(|val| {
+                            map.insert(item.0.clone(), val);
+                        })
                 })?;
 
                 Ok(Box::new(map))
             }
 
-            Expr::FunctionCall(fn_name, args_expr_list, def_val, pos) => {
+            #[cfg_attr(feature = "no_module", allow(unused_variables))]
+            Expr::FunctionCall(fn_name, args_expr_list, def_val, slot, pos) => {
+                let args_expr_list = &nodes[*args_expr_list];
+
                 // Has a system function an override?
-                fn has_override(engine: &Engine, name: &str) -> bool {
+                fn has_override(engine: &Engine, name: &str, args: StaticVec<TypeId>) -> bool {
+                    let params = args.len();
+
                     (engine.functions.is_some() && {
                         engine.functions.as_ref().unwrap().contains_key(&FnSpec {
                             name: name.into(),
-                            args: vec![TypeId::of::<String>()],
+                            args,
                         })
                     }) || (engine.fn_lib.is_some()
-                        && engine.fn_lib.as_ref().unwrap().has_function(name, 1))
+                        && engine.fn_lib.as_ref().unwrap().has_function(name, params))
                 }
 
-                match fn_name.as_str() {
+                match fn_name.as_ref() {
                     // Dump AST
                     KEYWORD_DUMP_AST => {
                         let pos = if args_expr_list.is_empty() {
                             *pos
                         } else {
-                            args_expr_list[0].position()
+                            args_expr_list[0].position(nodes)
                         };
 
                         // Change the argument to a debug dump of the expressions
                         let mut result = args_expr_list
                             .iter()
-                            .map(|expr| format!("{:#?}", expr))
+                            .map(|expr| format!("{:#?}", WithNodes(expr, nodes)))
                             .collect::<Vec<_>>()
                             .join("\n")
                             .into_dynamic();
@@ -1352,32 +3117,95 @@ impl Engine<'_> {
 
                     // type_of
                     KEYWORD_TYPE_OF
-                        if args_expr_list.len() == 1 && !has_override(self, KEYWORD_TYPE_OF) =>
+                        if args_expr_list.len() == 1
+                            && !has_override(
+                                self,
+                                KEYWORD_TYPE_OF,
+                                smallvec![TypeId::of::<String>()],
+                            ) =>
                     {
-                        let r = self.eval_expr(scope, &args_expr_list[0], level)?;
+                        let mut r = self.eval_expr(scope, nodes, &args_expr_list[0], level)?;
+                        self.check_call(KEYWORD_TYPE_OF, &[r.as_mut()], *pos)?;
+
                         Ok(self
                             .map_type_name((*r).type_name())
                             .to_string()
                             .into_dynamic())
                     }
 
+                    // is_def_var
+                    KEYWORD_IS_DEF_VAR
+                        if args_expr_list.len() == 1
+                            && !has_override(
+                                self,
+                                KEYWORD_IS_DEF_VAR,
+                                smallvec![TypeId::of::<String>()],
+                            ) =>
+                    {
+                        let mut r = self.eval_expr(scope, nodes, &args_expr_list[0], level)?;
+                        self.check_call(KEYWORD_IS_DEF_VAR, &[r.as_mut()], *pos)?;
+
+                        let pos = args_expr_list[0].position(nodes);
+                        let name = self.cast_to_string(r.as_ref(), pos)?;
+
+                        Ok(
+                            (scope.contains(name) || self.globals.contains_key(name))
+                                .into_dynamic(),
+                        )
+                    }
+
+                    // is_def_fn
+                    KEYWORD_IS_DEF_FN
+                        if args_expr_list.len() == 2
+                            && !has_override(
+                                self,
+                                KEYWORD_IS_DEF_FN,
+                                smallvec![TypeId::of::<String>(), TypeId::of::<INT>()],
+                            ) =>
+                    {
+                        let mut r = self.eval_expr(scope, nodes, &args_expr_list[0], level)?;
+                        let mut n = self.eval_expr(scope, nodes, &args_expr_list[1], level)?;
+                        self.check_call(KEYWORD_IS_DEF_FN, &[r.as_mut(), n.as_mut()], *pos)?;
+
+                        let pos = args_expr_list[0].position(nodes);
+                        let name = self.cast_to_string(r.as_ref(), pos)?;
+
+                        let pos = args_expr_list[1].position(nodes);
+                        let params = match n.downcast_ref::<INT>() {
+                            Some(&n) if n >= 0 => n as usize,
+                            Some(_) => return Ok(false.into_dynamic()),
+                            None => return Err(self.make_type_mismatch_err::<INT>(&*n, pos)),
+                        };
+
+                        Ok(self.has_function(name, params).into_dynamic())
+                    }
+
+                    // get_fn_metadata_list
+                    #[cfg(not(feature = "no_index"))]
+                    #[cfg(not(feature = "no_object"))]
+                    KEYWORD_FN_METADATA_LIST
+                        if args_expr_list.is_empty()
+                            && !has_override(self, KEYWORD_FN_METADATA_LIST, smallvec![]) =>
+                    {
+                        self.check_call(KEYWORD_FN_METADATA_LIST, &[], *pos)?;
+                        Ok(self.fn_metadata_list().into_dynamic())
+                    }
+
                     // eval
                     KEYWORD_EVAL
-                        if args_expr_list.len() == 1 && !has_override(self, KEYWORD_EVAL) =>
+                        if args_expr_list.len() == 1
+                            && !has_override(
+                                self,
+                                KEYWORD_EVAL,
+                                smallvec![TypeId::of::<String>()],
+                            ) =>
                     {
-                        let pos = args_expr_list[0].position();
-                        let r = self.eval_expr(scope, &args_expr_list[0], level)?;
+                        let mut r = self.eval_expr(scope, nodes, &args_expr_list[0], level)?;
+                        self.check_call(KEYWORD_EVAL, &[r.as_mut()], *pos)?;
 
                         // Get the script text by evaluating the expression
-                        let script =
-                            r.downcast_ref::<String>()
-                                .map(String::as_str)
-                                .ok_or_else(|| {
-                                    EvalAltResult::ErrorMismatchOutputType(
-                                        r.type_name().into(),
-                                        pos,
-                                    )
-                                })?;
+                        let pos = args_expr_list[0].position(nodes);
+                        let script = self.cast_to_string(r.as_ref(), pos)?;
 
                         // Compile the script text
                         #[cfg(not(feature = "no_optimize"))]
@@ -1409,13 +3237,25 @@ impl Engine<'_> {
                             } else {
                                 ast.1
                             },
+                            self.source.clone(),
+                            vec![],
+                            ast.4,
                         );
 
+                        // Keep the current imports, which are reset after evaluating the AST
+                        #[cfg(not(feature = "no_module"))]
+                        let imports = self.imports.clone();
+
                         // Evaluate the AST
                         let result = self
                             .eval_ast_with_scope_raw(scope, &merged)
                             .map_err(|err| err.set_position(pos));
 
+                        #[cfg(not(feature = "no_module"))]
+                        {
+                            self.imports = imports;
+                        }
+
                         // Update the new functions library if there are new functions
                         self.fn_lib = if !merged.1.is_empty() {
                             Some(merged.1)
@@ -1430,12 +3270,30 @@ impl Engine<'_> {
                     _ => {
                         let mut values = args_expr_list
                             .iter()
-                            .map(|expr| self.eval_expr(scope, expr, level))
-                            .collect::<Result<Vec<_>, _>>()?;
+                            .map(|expr| self.eval_expr(scope, nodes, expr, level))
+                            .collect::<Result<StaticVec<_>, _>>()?;
 
-                        let mut arg_values: Vec<_> =
+                        let mut arg_values: StaticVec<_> =
                             values.iter_mut().map(Dynamic::as_mut).collect();
 
+                        // Namespace-qualified call to a function of an imported module
+                        #[cfg(not(feature = "no_module"))]
+                        {
+                            if let Some((module, index)) =
+                                self.resolve_qualified_call(slot, fn_name, arg_values.len())
+                            {
+                                self.stats.fn_calls += 1;
+                                let fn_def = module.functions[index].clone();
+                                return self.call_module_fn(
+                                    &module,
+                                    &fn_def,
+                                    &mut arg_values,
+                                    *pos,
+                                    level,
+                                );
+                            }
+                        }
+
                         let def_val = def_val.as_ref();
 
                         self.call_fn_raw(None, fn_name, &mut arg_values, def_val, *pos, level)
@@ -1443,35 +3301,35 @@ impl Engine<'_> {
                 }
             }
 
-            Expr::And(lhs, rhs) => Ok(Box::new(
+            Expr::And(lhs, rhs, _) => Ok(Box::new(
                 self
-                    .eval_expr(scope, &*lhs, level)?
+                    .eval_expr(scope, nodes, &nodes[*lhs], level)?
                     .try_cast::<bool>()
                     .map_err(|_| {
-                        EvalAltResult::ErrorBooleanArgMismatch("AND".into(), lhs.position())
+                        Box::new(EvalAltResult::ErrorBooleanArgMismatch("AND".into(), nodes[*lhs].position(nodes)))
                     })?
                     && // Short-circuit using &&
                 self
-                    .eval_expr(scope, &*rhs, level)?
+                    .eval_expr(scope, nodes, &nodes[*rhs], level)?
                     .try_cast::<bool>()
                     .map_err(|_| {
-                        EvalAltResult::ErrorBooleanArgMismatch("AND".into(), rhs.position())
+                        Box::new(EvalAltResult::ErrorBooleanArgMismatch("AND".into(), nodes[*rhs].position(nodes)))
                     })?,
             )),
 
-            Expr::Or(lhs, rhs) => Ok(Box::new(
+            Expr::Or(lhs, rhs, _) => Ok(Box::new(
                 self
-                    .eval_expr(scope, &*lhs, level)?
+                    .eval_expr(scope, nodes, &nodes[*lhs], level)?
                     .try_cast::<bool>()
                     .map_err(|_| {
-                        EvalAltResult::ErrorBooleanArgMismatch("OR".into(), lhs.position())
+                        Box::new(EvalAltResult::ErrorBooleanArgMismatch("OR".into(), nodes[*lhs].position(nodes)))
                     })?
                     || // Short-circuit using ||
                 self
-                    .eval_expr(scope, &*rhs, level)?
+                    .eval_expr(scope, nodes, &nodes[*rhs], level)?
                     .try_cast::<bool>()
                     .map_err(|_| {
-                        EvalAltResult::ErrorBooleanArgMismatch("OR".into(), rhs.position())
+                        Box::new(EvalAltResult::ErrorBooleanArgMismatch("OR".into(), nodes[*rhs].position(nodes)))
                     })?,
             )),
 
@@ -1481,22 +3339,103 @@ impl Engine<'_> {
         }
     }
 
+    /// Evaluate `name op= expr` (which is parsed into `name = name op expr`) as a statement.
+    ///
+    /// As the value of the statement is discarded, the variable is modified in place if there is
+    /// an `op=` function (e.g. `+=` for strings) for the types of the operands, avoiding a copy of
+    /// the value.  Otherwise, it is evaluated as `name = name op expr`.
+    fn eval_op_assignment_stmt(
+        &mut self,
+        scope: &mut Scope,
+        nodes: &Nodes,
+        expr: &Expr,
+        level: usize,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let (name, pos, op, arg_expr, fn_pos, op_pos) = match expr {
+            Expr::Assignment(lhs, rhs, op_pos) => match (&nodes[*lhs], &nodes[*rhs]) {
+                (Expr::Variable(name, pos), Expr::FunctionCall(op, args, _, _, fn_pos)) => {
+                    (name, *pos, op, &nodes[*args][1], *fn_pos, *op_pos)
+                }
+                _ => panic!("expecting op-assignment"),
+            },
+            _ => panic!("expecting op-assignment"),
+        };
+
+        let entry = scope
+            .get_ref(name)
+            .ok_or_else(|| self.assignment_err(name, pos, op_pos))?;
+
+        // Avoid referencing scope which is used below as mut
+        let entry = ScopeSource { name, ..entry };
+
+        let mut arg = self.eval_expr(scope, nodes, arg_expr, level)?;
+
+        let mut op_assignment = Identifier::from(op.as_ref());
+        op_assignment.push('=');
+
+        let spec = FnSpec {
+            name: op_assignment.as_str().into(),
+            args: smallvec![Any::type_id(&**scope.get_mut(entry)), Any::type_id(&*arg)],
+        };
+
+        // Modify the variable in place
+        if entry.typ == ScopeEntryType::Normal
+            && matches!(&self.functions, Some(functions) if functions.contains_key(&spec))
+        {
+            let mut args = [scope.get_mut(entry).as_mut(), arg.as_mut()];
+            self.call_fn_raw(None, &op_assignment, &mut args, None, fn_pos, level)?;
+            return Ok(());
+        }
+
+        // name = op(name, expr)
+        let mut value = scope.get_mut(entry).clone();
+        let mut args = [value.as_mut(), arg.as_mut()];
+        let value = self.call_fn_raw(None, op, &mut args, None, fn_pos, level)?;
+
+        match entry.typ {
+            ScopeEntryType::Normal => {
+                *scope.get_mut(entry) = value;
+                Ok(())
+            }
+            ScopeEntryType::Constant => Err(Box::new(EvalAltResult::ErrorAssignmentToConstant(
+                name.to_string(),
+                op_pos,
+            ))),
+        }
+    }
+
     /// Evaluate a statement
     pub(crate) fn eval_stmt(
         &mut self,
         scope: &mut Scope,
+        nodes: &Nodes,
         stmt: &Stmt,
         level: usize,
-    ) -> Result<Dynamic, EvalAltResult> {
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        if self.is_stack_exhausted() {
+            return Err(Box::new(EvalAltResult::ErrorStackOverflow(
+                stmt.position(nodes),
+            )));
+        }
+
+        self.stats.operations += 1;
+
         match stmt {
             // No-op
             Stmt::Noop(_) => Ok(().into_dynamic()),
 
+            // name op= rhs as a statement
+            Stmt::Expr(expr) if is_op_assignment(nodes, &nodes[*expr]) => {
+                self.eval_op_assignment_stmt(scope, nodes, &nodes[*expr], level)?;
+                Ok(().into_dynamic())
+            }
+
             // Expression as statement
             Stmt::Expr(expr) => {
-                let result = self.eval_expr(scope, expr, level)?;
+                let expr = &nodes[*expr];
+                let result = self.eval_expr(scope, nodes, expr, level)?;
 
-                Ok(if !matches!(expr.as_ref(), Expr::Assignment(_, _, _)) {
+                Ok(if !matches!(expr, Expr::Assignment(_, _, _)) {
                     result
                 } else {
                     // If it is an assignment, erase the result at the root
@@ -1508,8 +3447,8 @@ impl Engine<'_> {
             Stmt::Block(block, _) => {
                 let prev_len = scope.len();
 
-                let result = block.iter().try_fold(().into_dynamic(), |_, stmt| {
-                    self.eval_stmt(scope, stmt, level)
+                let result = nodes[*block].iter().try_fold(().into_dynamic(), |_, stmt| {
+                    self.eval_stmt(scope, nodes, stmt, level)
                 });
 
                 scope.rewind(prev_len);
@@ -1519,147 +3458,366 @@ impl Engine<'_> {
 
             // If-else statement
             Stmt::IfThenElse(guard, if_body, else_body) => self
-                .eval_expr(scope, guard, level)?
+                .eval_expr(scope, nodes, &nodes[*guard], level)?
                 .try_cast::<bool>()
-                .map_err(|_| EvalAltResult::ErrorLogicGuard(guard.position()))
+                .map_err(|_| {
+                    Box::new(EvalAltResult::ErrorLogicGuard(
+                        nodes[*guard].position(nodes),
+                    ))
+                })
                 .and_then(|guard_val| {
                     if guard_val {
-                        self.eval_stmt(scope, if_body, level)
+                        self.eval_stmt(scope, nodes, &nodes[*if_body], level)
                     } else if let Some(stmt) = else_body {
-                        self.eval_stmt(scope, stmt.as_ref(), level)
+                        self.eval_stmt(scope, nodes, &nodes[*stmt], level)
                     } else {
                         Ok(().into_dynamic())
                     }
                 }),
 
             // While loop
-            Stmt::While(guard, body) => loop {
-                match self.eval_expr(scope, guard, level)?.try_cast::<bool>() {
-                    Ok(guard_val) if guard_val => match self.eval_stmt(scope, body, level) {
-                        Ok(_) | Err(EvalAltResult::ErrorLoopBreak(false, _)) => (),
-                        Err(EvalAltResult::ErrorLoopBreak(true, _)) => return Ok(().into_dynamic()),
-                        Err(x) => return Err(x),
-                    },
+            Stmt::While(guard, body, label) => loop {
+                match self
+                    .eval_expr(scope, nodes, &nodes[*guard], level)?
+                    .try_cast::<bool>()
+                {
+                    Ok(guard_val) if guard_val => {
+                        match self.eval_stmt(scope, nodes, &nodes[*body], level) {
+                            Ok(_) => (),
+                            Err(err) => match *err {
+                                EvalAltResult::ErrorLoopBreak(false, ref target, _)
+                                    if is_loop_target(target, label) => {}
+                                EvalAltResult::ErrorLoopBreak(true, ref target, _)
+                                    if is_loop_target(target, label) =>
+                                {
+                                    return Ok(().into_dynamic())
+                                }
+                                _ => return Err(err),
+                            },
+                        }
+                    }
                     Ok(_) => return Ok(().into_dynamic()),
-                    Err(_) => return Err(EvalAltResult::ErrorLogicGuard(guard.position())),
+                    Err(_) => {
+                        return Err(Box::new(EvalAltResult::ErrorLogicGuard(
+                            nodes[*guard].position(nodes),
+                        )))
+                    }
                 }
             },
 
             // Loop statement
-            Stmt::Loop(body) => loop {
-                match self.eval_stmt(scope, body, level) {
-                    Ok(_) | Err(EvalAltResult::ErrorLoopBreak(false, _)) => (),
-                    Err(EvalAltResult::ErrorLoopBreak(true, _)) => return Ok(().into_dynamic()),
-                    Err(x) => return Err(x),
+            Stmt::Loop(body, label) => loop {
+                match self.eval_stmt(scope, nodes, &nodes[*body], level) {
+                    Ok(_) => (),
+                    Err(err) => match *err {
+                        EvalAltResult::ErrorLoopBreak(false, ref target, _)
+                            if is_loop_target(target, label) => {}
+                        EvalAltResult::ErrorLoopBreak(true, ref target, _)
+                            if is_loop_target(target, label) =>
+                        {
+                            return Ok(().into_dynamic())
+                        }
+                        _ => return Err(err),
+                    },
                 }
             },
 
             // For loop
-            Stmt::For(name, expr, body) => {
-                let arr = self.eval_expr(scope, expr, level)?;
-                let tid = Any::type_id(&*arr);
-
-                if let Some(type_iterators) = &self.type_iterators {
-                    if let Some(iter_fn) = type_iterators.get(&tid) {
-                        // Add the loop variable - variable name is copied
-                        // TODO - avoid copying variable name
-                        scope.push(name.clone(), ());
-
-                        let entry = ScopeSource {
-                            name,
-                            index: scope.len() - 1,
-                            typ: ScopeEntryType::Normal,
-                        };
+            Stmt::For(name, expr, body, label) => {
+                let (expr, body) = (&nodes[*expr], &nodes[*body]);
+                let mut arr = self.eval_expr(scope, nodes, expr, level)?;
+
+                // Iterate over a copy of a string or BLOB borrowed from the host,
+                // unless there is an iterator for the borrowed type itself
+                if !matches!(&self.type_iterators, Some(t) if t.contains_key(&Any::type_id(&*arr)))
+                {
+                    make_owned(&mut arr);
+                }
 
-                        for a in iter_fn(&arr) {
-                            *scope.get_mut(entry) = a;
+                // A pipeline runs its steps on the values one at a time as they are reached
+                if let Some(mut values) = self.iterate(&arr) {
+                    // Add the loop variable - variable name is copied (inline if short)
+                    scope.push_dynamic_value(
+                        name.as_str(),
+                        ScopeEntryType::Normal,
+                        ().into_dynamic(),
+                        false,
+                    );
+
+                    let entry = ScopeSource {
+                        name,
+                        index: scope.len() - 1,
+                        typ: ScopeEntryType::Normal,
+                    };
 
-                            match self.eval_stmt(scope, body, level) {
-                                Ok(_) | Err(EvalAltResult::ErrorLoopBreak(false, _)) => (),
-                                Err(EvalAltResult::ErrorLoopBreak(true, _)) => break,
-                                Err(x) => return Err(x),
-                            }
-                        }
+                    while let Some(a) = self.next_value(&mut values, expr.position(nodes), level)? {
+                        *scope.get_mut(entry) = a;
 
-                        scope.rewind(scope.len() - 1);
-                        Ok(().into_dynamic())
-                    } else {
-                        Err(EvalAltResult::ErrorFor(expr.position()))
+                        match self.eval_stmt(scope, nodes, body, level) {
+                            Ok(_) => (),
+                            Err(err) => match *err {
+                                EvalAltResult::ErrorLoopBreak(false, ref target, _)
+                                    if is_loop_target(target, label) => {}
+                                EvalAltResult::ErrorLoopBreak(true, ref target, _)
+                                    if is_loop_target(target, label) =>
+                                {
+                                    break
+                                }
+                                _ => return Err(err),
+                            },
+                        }
                     }
+
+                    scope.rewind(scope.len() - 1);
+                    Ok(().into_dynamic())
                 } else {
-                    Err(EvalAltResult::ErrorFor(expr.position()))
+                    Err(Box::new(EvalAltResult::ErrorFor(expr.position(nodes))))
                 }
             }
 
             // Continue statement
-            Stmt::Continue(pos) => Err(EvalAltResult::ErrorLoopBreak(false, *pos)),
+            Stmt::Continue(label, pos) => Err(Box::new(EvalAltResult::ErrorLoopBreak(
+                false,
+                label.clone(),
+                *pos,
+            ))),
 
             // Break statement
-            Stmt::Break(pos) => Err(EvalAltResult::ErrorLoopBreak(true, *pos)),
+            Stmt::Break(label, pos) => Err(Box::new(EvalAltResult::ErrorLoopBreak(
+                true,
+                label.clone(),
+                *pos,
+            ))),
 
             // Empty return
             Stmt::ReturnWithVal(None, ReturnType::Return, pos) => {
-                Err(EvalAltResult::Return(().into_dynamic(), *pos))
+                Err(Box::new(EvalAltResult::Return(().into_dynamic(), *pos)))
+            }
+
+            // Return the result of a call to a script-defined function - run as a tail call
+            Stmt::ReturnWithVal(Some(a), ReturnType::Return, pos)
+                if level > 0 && self.is_tail_call(&nodes[*a]) =>
+            {
+                self.set_tail_call(scope, nodes, &nodes[*a], level)?;
+                Err(Box::new(EvalAltResult::Return(().into_dynamic(), *pos)))
             }
 
             // Return value
-            Stmt::ReturnWithVal(Some(a), ReturnType::Return, pos) => Err(EvalAltResult::Return(
-                self.eval_expr(scope, a, level)?,
-                *pos,
+            Stmt::ReturnWithVal(Some(a), ReturnType::Return, pos) => Err(Box::new(
+                EvalAltResult::Return(self.eval_expr(scope, nodes, &nodes[*a], level)?, *pos),
             )),
 
             // Empty throw
             Stmt::ReturnWithVal(None, ReturnType::Exception, pos) => {
-                Err(EvalAltResult::ErrorRuntime("".into(), *pos))
+                Err(Box::new(EvalAltResult::ErrorRuntime("".into(), *pos)))
             }
 
             // Throw value
             Stmt::ReturnWithVal(Some(a), ReturnType::Exception, pos) => {
-                let val = self.eval_expr(scope, a, level)?;
-                Err(EvalAltResult::ErrorRuntime(
+                let val = self.eval_expr(scope, nodes, &nodes[*a], level)?;
+                Err(Box::new(EvalAltResult::ErrorRuntime(
                     val.try_cast::<String>().unwrap_or_else(|_| "".to_string()),
                     *pos,
-                ))
+                )))
             }
 
             // Let statement
-            Stmt::Let(name, Some(expr), _) => {
-                let val = self.eval_expr(scope, expr, level)?;
-                // TODO - avoid copying variable name in inner block?
-                scope.push_dynamic_value(name.clone(), ScopeEntryType::Normal, val, false);
+            Stmt::Let(name, Some(expr), pos) => {
+                let val = self.eval_expr(scope, nodes, &nodes[*expr], level)?;
+                self.check_def_var(scope, name, false, *pos, level)?;
+                scope.push_dynamic_value(name.as_str(), ScopeEntryType::Normal, val, false);
                 Ok(().into_dynamic())
             }
 
-            Stmt::Let(name, None, _) => {
-                // TODO - avoid copying variable name in inner block?
-                scope.push(name.clone(), ());
+            Stmt::Let(name, None, pos) => {
+                self.check_def_var(scope, name, false, *pos, level)?;
+                scope.push_dynamic_value(
+                    name.as_str(),
+                    ScopeEntryType::Normal,
+                    ().into_dynamic(),
+                    false,
+                );
                 Ok(().into_dynamic())
             }
 
             // Const statement
-            Stmt::Const(name, expr, _) if expr.is_constant() => {
-                let val = self.eval_expr(scope, expr, level)?;
-                // TODO - avoid copying variable name in inner block?
-                scope.push_dynamic_value(name.clone(), ScopeEntryType::Constant, val, true);
+            Stmt::Const(name, expr, pos) if nodes[*expr].is_constant(nodes) => {
+                let val = self.eval_expr(scope, nodes, &nodes[*expr], level)?;
+                self.check_def_var(scope, name, true, *pos, level)?;
+                scope.push_dynamic_value(name.as_str(), ScopeEntryType::Constant, val, true);
                 Ok(().into_dynamic())
             }
 
             Stmt::Const(_, _, _) => panic!("constant expression not constant!"),
+
+            // Import statement
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Import(_, _, _) | Stmt::ImportFrom(_, _, _) => {
+                let mut imports = mem::take(&mut self.imports);
+                let result = self.import_symbols(&mut imports, stmt);
+                self.imports = imports;
+                result.map(|_| ().into_dynamic())
+            }
+
+            // Export statement - only meaningful within a module
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Export(_, _) => Ok(().into_dynamic()),
         }
     }
 
-    /// Map a type_name into a pretty-print name
-    pub(crate) fn map_type_name<'a>(&'a self, name: &'a str) -> &'a str {
-        if self.type_names.is_none() {
-            name
-        } else {
-            self.type_names
-                .as_ref()
-                .unwrap()
-                .get(name)
-                .map(String::as_str)
-                .unwrap_or(name)
+    /// Run the variable definition filter (if any) on a new variable about to be added to the scope.
+    fn check_def_var(
+        &mut self,
+        scope: &Scope,
+        name: &str,
+        is_const: bool,
+        pos: Position,
+        level: usize,
+    ) -> Result<(), Box<EvalAltResult>> {
+        if let Some(filter) = self.on_def_var.as_mut() {
+            if !filter(name, is_const, level, scope) {
+                return Err(Box::new(EvalAltResult::ErrorForbiddenVariable(
+                    name.to_string(),
+                    pos,
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run the audit callback (if any) on a call to a native function about to be made,
+    /// then check that the function does not require capabilities that are not granted.
+    ///
+    /// Every call not to a script-defined function must be checked here exactly once, including
+    /// calls to the functions handled by the `Engine` itself (e.g. `eval` and `format`).
+    fn check_call(
+        &mut self,
+        fn_name: &str,
+        args: &FnCallArgs,
+        pos: Position,
+    ) -> Result<(), Box<EvalAltResult>> {
+        if let Some(filter) = &self.on_call {
+            let arg_types: Vec<_> = args
+                .iter()
+                .map(|a| self.map_type_name(Any::type_name(&**a)))
+                .collect();
+
+            if !filter(fn_name, &arg_types, pos) {
+                return Err(Box::new(EvalAltResult::ErrorForbiddenFunction(
+                    fn_name.to_string(),
+                    pos,
+                )));
+            }
+        }
+
+        if let Some(capabilities) = &self.capabilities {
+            // A function called via an alias also requires the capabilities of the aliased names
+            let mut tags = successors(Some(fn_name), |name| {
+                self.fn_aliases.get(*name).map(String::as_str)
+            })
+            .filter_map(|name| self.fn_tags.get(name))
+            .flatten();
+
+            if !tags.all(|tag| capabilities.contains(tag)) {
+                return Err(Box::new(EvalAltResult::ErrorForbiddenFunction(
+                    fn_name.to_string(),
+                    pos,
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Map a type_name into a pretty-print name.
+    ///
+    /// Built-in types map to script-friendly names (e.g. `alloc::string::String` to `string`),
+    /// and custom types registered via `register_type_with_name` map to their registered names.
+    /// Unknown types map to themselves.
+    ///
+    /// This is the mapping used in error messages and by the `type_of` function.
+    pub fn map_type_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.type_names
+            .as_ref()
+            .and_then(|type_names| type_names.get(name))
+            .map(String::as_str)
+            .unwrap_or(name)
+    }
+
+    /// Cast a value to a string, raising a type mismatch error (with a pretty-print type name) if it is not.
+    pub(crate) fn cast_to_string<'a>(
+        &self,
+        r: &'a Variant,
+        pos: Position,
+    ) -> Result<&'a str, Box<EvalAltResult>> {
+        r.downcast_ref::<String>()
+            .map(String::as_str)
+            .ok_or_else(|| self.make_type_mismatch_err::<String>(r, pos))
+    }
+
+    /// Make an `ErrorMismatchOutputType` for a value which is not of the required type `T`,
+    /// with pretty-print names for both types.
+    pub(crate) fn make_type_mismatch_err<T: Any>(
+        &self,
+        value: &Variant,
+        pos: Position,
+    ) -> Box<EvalAltResult> {
+        Box::new(EvalAltResult::ErrorMismatchOutputType(
+            self.map_type_name(type_name::<T>()).into(),
+            self.map_type_name(value.type_name()).into(),
+            pos,
+        ))
+    }
+
+    /// Is a function with a particular name and number of parameters available,
+    /// either script-defined, imported from a module or registered?
+    fn has_function(&self, name: &str, params: usize) -> bool {
+        if matches!(&self.fn_lib, Some(fn_lib) if fn_lib.has_function(name, params)) {
+            return true;
+        }
+
+        #[cfg(not(feature = "no_module"))]
+        {
+            if self.imports.find_function(name, params).is_some() {
+                return true;
+            }
+        }
+
+        matches!(&self.functions, Some(functions)
+            if functions.keys().any(|spec| spec.name == name && spec.args.len() == params))
+    }
+
+    /// Describe all script-defined functions available to the running script, including those
+    /// imported from modules, as an array of object maps.
+    #[cfg(not(feature = "no_index"))]
+    #[cfg(not(feature = "no_object"))]
+    fn fn_metadata_list(&self) -> Array {
+        let mut list: Vec<(String, &FnDef, &str)> = Vec::new();
+
+        if let Some(fn_lib) = &self.fn_lib {
+            list.extend(fn_lib.iter().map(|f| (f.name.clone(), f.as_ref(), "")));
         }
+
+        #[cfg(not(feature = "no_module"))]
+        self.imports.list_functions("", &mut list);
+
+        list.into_iter()
+            .map(|(name, f, source)| {
+                let mut map = Map::new();
+                map.insert("name".into(), name.into_dynamic());
+                map.insert(
+                    "params".into(),
+                    f.params
+                        .iter()
+                        .map(|p| p.clone().into_dynamic())
+                        .collect::<Array>()
+                        .into_dynamic(),
+                );
+                map.insert("source".into(), source.to_string().into_dynamic());
+                map.into_dynamic()
+            })
+            .collect()
     }
 
     /// Clean up all script-defined functions within the `Engine`.
@@ -1668,13 +3826,88 @@ impl Engine<'_> {
     }
 }
 
-/// Print/debug to stdout
+/// Get the current address of the native stack.
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0_u8;
+    &marker as *const u8 as usize
+}
+
+/// Split a chain of indexing (e.g. `x[i][j]`) into the expression being indexed (e.g. `x`) and
+/// the index expressions (e.g. `i` and `j`), each with the position of its `[`.
+#[cfg(not(feature = "no_index"))]
+fn split_index_chain<'a>(
+    nodes: &'a Nodes,
+    expr: &'a Expr,
+) -> (&'a Expr, Vec<(&'a Expr, Position)>) {
+    let mut idx_exprs = Vec::new();
+    let mut expr = expr;
+
+    while let Expr::Index(lhs, idx_expr, op_pos) = expr {
+        idx_exprs.push((&nodes[*idx_expr], *op_pos));
+        expr = &nodes[*lhs];
+    }
+
+    idx_exprs.reverse();
+    (expr, idx_exprs)
+}
+
+/// Is the expression `name = name op expr`, which is what `name op= expr` is parsed into?
+pub(crate) fn is_op_assignment(nodes: &Nodes, expr: &Expr) -> bool {
+    match expr {
+        Expr::Assignment(lhs, rhs, _) => match (&nodes[*lhs], &nodes[*rhs]) {
+            (Expr::Variable(name, _), Expr::FunctionCall(op, args, None, _, _))
+                if args.len() == 2 =>
+            {
+                matches!(
+                    op.as_ref(),
+                    "+" | "-" | "*" | "/" | "%" | "~" | "<<" | ">>" | "&" | "|" | "^"
+                ) && matches!(&nodes[*args][0], Expr::Variable(arg, _) if arg == name)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Make an error for storing a value containing a shared value into the shared value itself,
+/// which would then never be freed.
+pub(crate) fn shared_cycle(pos: Position) -> Box<EvalAltResult> {
+    Box::new(EvalAltResult::ErrorRuntime(
+        "Cannot store a value containing a shared value into the shared value itself".into(),
+        pos,
+    ))
+}
+
+/// Is a loop with an optional label the target of a `break` or `continue` with an optional label?
+///
+/// An unlabeled `break` or `continue` always targets the innermost loop.
+fn is_loop_target(target: &Option<String>, label: &Option<String>) -> bool {
+    target.is_none() || target == label
+}
+
+/// Print to stdout
 #[cfg(not(feature = "no_std"))]
 #[cfg(not(feature = "no_stdlib"))]
 fn default_print(s: &str) {
     println!("{}", s);
 }
 
+/// Print/debug to stdout, prefixed by the source and position of the `debug` call
+#[cfg(not(feature = "no_std"))]
+#[cfg(not(feature = "no_stdlib"))]
+fn default_debug(s: &str, source: Option<&str>, pos: Position) {
+    match source {
+        Some(source) => println!("{} @ {:?} | {}", source, pos, s),
+        None if pos.is_none() => println!("{}", s),
+        None => println!("{:?} | {}", pos, s),
+    }
+}
+
 /// No-op
 #[cfg(any(feature = "no_std", feature = "no_stdlib"))]
 fn default_print(_: &str) {}
+
+/// No-op
+#[cfg(any(feature = "no_std", feature = "no_stdlib"))]
+fn default_debug(_: &str, _: Option<&str>, _: Position) {}
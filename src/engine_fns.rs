@@ -0,0 +1,477 @@
+//! Module implementing the functions handled by the `Engine` itself (e.g. `format` and `sort`),
+//! as they need access to the `Engine` or take arguments of any type, and cannot be registered
+//! as ordinary native functions.
+
+use crate::any::{Any, Dynamic, Variant};
+use crate::borrowed::make_owned;
+use crate::builtin::values_equal;
+use crate::engine::{
+    shared_cycle, Engine, FnCallArgs, FUNC_COUNT, FUNC_FORMAT, FUNC_ITER, FUNC_MEMOIZE,
+    FUNC_SHARED, FUNC_SHARED_GET, FUNC_SHARED_SET, FUNC_TO_STRING, KEYWORD_DEBUG, KEYWORD_PRINT,
+};
+use crate::format::format_string;
+use crate::parser::Position;
+use crate::pipeline::Pipeline;
+use crate::result::EvalAltResult;
+use crate::shared::SharedValue;
+
+#[cfg(not(feature = "no_index"))]
+use crate::engine::{Array, Blob, FUNC_BINARY_SEARCH, FUNC_COLLECT, FUNC_SORT};
+
+#[cfg(not(feature = "no_object"))]
+use crate::engine::{Map, FUNC_FILTER};
+
+#[cfg(not(feature = "no_stdlib"))]
+use crate::engine::FUNC_TO_JSON;
+
+use crate::stdlib::{boxed::Box, string::String, string::ToString};
+
+/// Result of a function handled by the `Engine`, or `None` if the arguments are not of the types
+/// it handles.
+type EngineFnResult = Option<Result<Dynamic, Box<EvalAltResult>>>;
+
+/// A function handled by the `Engine`, called with the function name, arguments, position of the
+/// call and current level of function calls.
+type EngineFn = fn(&mut Engine, &str, &mut FnCallArgs, Position, usize) -> EngineFnResult;
+
+/// The functions handled by the `Engine`: name, number of parameters (`None` for any number)
+/// and function.
+///
+/// They are only tried for calls matching no registered function (including the built-in ones
+/// and any registered by the host), so registering a function of the same name and parameter
+/// types overrides them.  The functions matching the name and number of arguments of a call are
+/// tried in order, until one of them handles the types of the arguments.
+const ENGINE_FNS: &[(&str, Option<usize>, EngineFn)] = &[
+    #[cfg(not(feature = "no_stdlib"))]
+    (FUNC_TO_JSON, Some(1), to_json),
+    #[cfg(not(feature = "no_stdlib"))]
+    #[cfg(not(feature = "no_index"))]
+    (FUNC_SORT, Some(1), sort),
+    #[cfg(not(feature = "no_stdlib"))]
+    #[cfg(not(feature = "no_index"))]
+    (FUNC_BINARY_SEARCH, Some(2), binary_search),
+    #[cfg(not(feature = "no_index"))]
+    (FUNC_SORT, Some(2), sort_with),
+    #[cfg(not(feature = "no_index"))]
+    (FUNC_BINARY_SEARCH, Some(3), binary_search_with),
+    #[cfg(not(feature = "no_object"))]
+    (FUNC_FILTER, Some(2), filter),
+    (FUNC_FORMAT, None, format),
+    (FUNC_ITER, Some(1), iter),
+    #[cfg(not(feature = "no_index"))]
+    (FUNC_COLLECT, Some(1), collect),
+    (FUNC_COUNT, Some(1), count),
+    (FUNC_SHARED, Some(1), shared),
+    (FUNC_SHARED_GET, Some(1), shared_get),
+    (FUNC_SHARED_SET, Some(2), shared_set),
+    (KEYWORD_PRINT, Some(1), shared_as_value),
+    (KEYWORD_DEBUG, Some(1), shared_as_value),
+    (FUNC_TO_STRING, Some(1), shared_as_value),
+    ("==", Some(2), shared_eq),
+    ("!=", Some(2), shared_eq),
+    (FUNC_MEMOIZE, Some(1), memoize),
+    ("is_int", Some(1), is_type),
+    #[cfg(not(feature = "no_float"))]
+    ("is_float", Some(1), is_type),
+    ("is_bool", Some(1), is_type),
+    ("is_char", Some(1), is_type),
+    ("is_string", Some(1), is_type),
+    #[cfg(not(feature = "no_index"))]
+    ("is_array", Some(1), is_type),
+    #[cfg(not(feature = "no_index"))]
+    ("is_blob", Some(1), is_type),
+    #[cfg(not(feature = "no_object"))]
+    ("is_map", Some(1), is_type),
+    ("is_shared", Some(1), is_type),
+    ("is_unit", Some(1), is_type),
+];
+
+impl Engine<'_> {
+    /// Call a function handled by the `Engine` itself.
+    ///
+    /// Returns `None` if there is no such function for the name and types of the arguments.
+    pub(crate) fn call_engine_fn(
+        &mut self,
+        fn_name: &str,
+        args: &mut FnCallArgs,
+        pos: Position,
+        level: usize,
+    ) -> EngineFnResult {
+        let num_args = args.len();
+
+        ENGINE_FNS
+            .iter()
+            .filter(|(name, params, _)| {
+                *name == fn_name && (params.is_none() || *params == Some(num_args))
+            })
+            .find_map(|(_, _, func)| func(self, fn_name, args, pos, level))
+    }
+}
+
+/// Convert an array or object map into JSON text: `to_json(value)`
+#[cfg(not(feature = "no_stdlib"))]
+fn to_json(
+    engine: &mut Engine,
+    _: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    _: usize,
+) -> EngineFnResult {
+    #[cfg(not(feature = "no_index"))]
+    let is_json_container = args[0].is::<Array>();
+    #[cfg(feature = "no_index")]
+    let is_json_container = false;
+
+    #[cfg(not(feature = "no_object"))]
+    let is_json_container = is_json_container || args[0].is::<Map>();
+
+    if !is_json_container {
+        return None;
+    }
+
+    Some(
+        engine
+            .to_json_raw(&*args[0], pos)
+            .map(|json| json.into_dynamic()),
+    )
+}
+
+/// Sort an array in the natural order of its elements: `sort(array)`
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+fn sort(
+    engine: &mut Engine,
+    _: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    _: usize,
+) -> EngineFnResult {
+    let list = args[0].downcast_mut::<Array>()?;
+    Some(engine.sort_natural(list, pos).map(|_| ().into_dynamic()))
+}
+
+/// Binary-search a sorted array for a value of a built-in type: `binary_search(array, value)`
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(not(feature = "no_index"))]
+fn binary_search(
+    engine: &mut Engine,
+    _: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    _: usize,
+) -> EngineFnResult {
+    let list = args[0].downcast_ref::<Array>()?;
+
+    Some(
+        engine
+            .search_sorted(list, &*args[1], pos)
+            .map(|index| index.into_dynamic()),
+    )
+}
+
+/// Sort an array with a comparison function: `sort(array, "fn_name")`
+#[cfg(not(feature = "no_index"))]
+fn sort_with(
+    engine: &mut Engine,
+    _: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    level: usize,
+) -> EngineFnResult {
+    let cmp_fn = args[1].downcast_ref::<String>()?.clone();
+    let list = args[0].downcast_mut::<Array>()?;
+
+    Some(
+        engine
+            .sort_array(list, &cmp_fn, pos, level)
+            .map(|_| ().into_dynamic()),
+    )
+}
+
+/// Binary-search a sorted array with a comparison function:
+/// `binary_search(array, value, "fn_name")`
+#[cfg(not(feature = "no_index"))]
+fn binary_search_with(
+    engine: &mut Engine,
+    _: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    level: usize,
+) -> EngineFnResult {
+    let cmp_fn = args[2].downcast_ref::<String>()?.clone();
+    let value = args[1].into_dynamic();
+    let list = args[0].downcast_ref::<Array>()?;
+
+    Some(
+        engine
+            .search_array(list, value, &cmp_fn, pos, level)
+            .map(|index| index.into_dynamic()),
+    )
+}
+
+/// Filter a map with a predicate function: `filter(map, "fn_name")`
+#[cfg(not(feature = "no_object"))]
+fn filter(
+    engine: &mut Engine,
+    _: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    level: usize,
+) -> EngineFnResult {
+    let filter_fn = args[1].downcast_ref::<String>()?.clone();
+    let map = args[0].downcast_ref::<Map>()?;
+
+    Some(
+        engine
+            .filter_map(map, &filter_fn, pos, level)
+            .map(|map| map.into_dynamic()),
+    )
+}
+
+/// Build a string from a template: `format(template, args...)`
+fn format(
+    engine: &mut Engine,
+    _: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    level: usize,
+) -> EngineFnResult {
+    let template = args.first()?.downcast_ref::<String>()?.clone();
+
+    Some(
+        format_string(
+            &template,
+            &args[1..],
+            |value| {
+                let mut value = value.into_dynamic();
+                let r = engine.call_fn_raw(
+                    None,
+                    FUNC_TO_STRING,
+                    &mut [value.as_mut()],
+                    None,
+                    pos,
+                    level,
+                )?;
+                engine
+                    .cast_to_string(r.as_ref(), pos)
+                    .map(ToString::to_string)
+            },
+            pos,
+        )
+        .map(|s| s.into_dynamic()),
+    )
+}
+
+/// Create a lazy pipeline over an iterable value: `iter(value)`
+fn iter(
+    engine: &mut Engine,
+    _: &str,
+    args: &mut FnCallArgs,
+    _: Position,
+    _: usize,
+) -> EngineFnResult {
+    let mut source = args[0].into_dynamic();
+
+    if source.is::<Pipeline>() {
+        return Some(Ok(source));
+    }
+
+    let is_iterable = |engine: &Engine, source: &Dynamic| matches!(&engine.type_iterators, Some(t) if t.contains_key(&Any::type_id(&**source)));
+
+    // Iterate over a copy of a string or BLOB borrowed from the host,
+    // unless there is an iterator for the borrowed type itself
+    if !is_iterable(engine, &source) {
+        make_owned(&mut source);
+    }
+
+    if is_iterable(engine, &source) {
+        Some(Ok(Pipeline::new(source).into_dynamic()))
+    } else {
+        None
+    }
+}
+
+/// Run a lazy pipeline, collecting its values into an array: `collect(pipeline)`
+#[cfg(not(feature = "no_index"))]
+fn collect(
+    engine: &mut Engine,
+    _: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    level: usize,
+) -> EngineFnResult {
+    let pipeline = args[0].downcast_ref::<Pipeline>()?;
+
+    Some(
+        engine
+            .collect_pipeline(pipeline, pos, level)
+            .map(|list| list.into_dynamic()),
+    )
+}
+
+/// Run a lazy pipeline, counting its values: `count(pipeline)`
+fn count(
+    engine: &mut Engine,
+    _: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    level: usize,
+) -> EngineFnResult {
+    let pipeline = args[0].downcast_ref::<Pipeline>()?;
+
+    Some(
+        engine
+            .count_pipeline(pipeline, pos, level)
+            .map(|count| count.into_dynamic()),
+    )
+}
+
+/// Create a shared value: `shared(value)`
+fn shared(_: &mut Engine, _: &str, args: &mut FnCallArgs, _: Position, _: usize) -> EngineFnResult {
+    Some(Ok(
+        SharedValue::from_dynamic(args[0].into_dynamic()).into_dynamic()
+    ))
+}
+
+/// Get a copy of a shared value: `get(shared)`
+fn shared_get(
+    _: &mut Engine,
+    _: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    _: usize,
+) -> EngineFnResult {
+    let shared = args[0].downcast_ref::<SharedValue>()?;
+
+    Some(
+        shared
+            .try_read(|value| value.into_dynamic())
+            .map_err(|err| err.set_position(pos)),
+    )
+}
+
+/// Replace a shared value: `set(shared, value)`
+fn shared_set(
+    _: &mut Engine,
+    _: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    _: usize,
+) -> EngineFnResult {
+    let shared = args[0].downcast_ref::<SharedValue>()?;
+
+    if shared.is_contained_in(&*args[1]) {
+        return Some(Err(shared_cycle(pos)));
+    }
+
+    let value = args[1].into_dynamic();
+
+    Some(
+        shared
+            .try_write(|v| *v = value)
+            .map(|_| ().into_dynamic())
+            .map_err(|err| err.set_position(pos)),
+    )
+}
+
+/// Print a shared value, or convert it to a string, as the value itself:
+/// `print(shared)`, `debug(shared)`, `to_string(shared)`
+fn shared_as_value(
+    engine: &mut Engine,
+    fn_name: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    level: usize,
+) -> EngineFnResult {
+    let shared = args[0].downcast_ref::<SharedValue>()?;
+
+    let mut value = match shared.try_read(|value| value.into_dynamic()) {
+        Ok(value) => value,
+        Err(err) => return Some(Err(err.set_position(pos))),
+    };
+
+    Some(engine.call_native_fn(fn_name, &mut [value.as_mut()], None, pos, level))
+}
+
+/// Compare shared values: `shared1 == shared2`, `shared1 != shared2`
+fn shared_eq(
+    _: &mut Engine,
+    fn_name: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    _: usize,
+) -> EngineFnResult {
+    let shared = args[0].downcast_ref::<SharedValue>()?;
+    let other = args[1].downcast_ref::<SharedValue>()?;
+
+    let is_equal = shared.ptr_eq(other)
+        || match shared
+            .try_read(|x| other.try_read(|y| values_equal(x, y)))
+            .and_then(|r| r)
+        {
+            Ok(is_equal) => is_equal,
+            Err(err) => return Some(Err(err.set_position(pos))),
+        };
+
+    Some(Ok((is_equal == (fn_name == "==")).into_dynamic()))
+}
+
+/// Cache the results of a script-defined function: `memoize("fn_name")`
+fn memoize(
+    engine: &mut Engine,
+    _: &str,
+    args: &mut FnCallArgs,
+    pos: Position,
+    _: usize,
+) -> EngineFnResult {
+    let name = args[0].downcast_ref::<String>()?;
+
+    if !matches!(&engine.fn_lib, Some(lib) if engine.memo.memoize(lib, name)) {
+        return Some(Err(Box::new(EvalAltResult::ErrorFunctionNotFound(
+            name.clone(),
+            pos,
+        ))));
+    }
+
+    Some(Ok(().into_dynamic()))
+}
+
+/// Check the type of a value: `is_int(value)`, `is_string(value)` etc.
+fn is_type(
+    _: &mut Engine,
+    fn_name: &str,
+    args: &mut FnCallArgs,
+    _: Position,
+    _: usize,
+) -> EngineFnResult {
+    let value: &Variant = &*args[0];
+
+    let result = match fn_name {
+        "is_int" => {
+            value.is::<i8>()
+                || value.is::<u8>()
+                || value.is::<i16>()
+                || value.is::<u16>()
+                || value.is::<i32>()
+                || value.is::<u32>()
+                || value.is::<i64>()
+                || value.is::<u64>()
+        }
+        #[cfg(not(feature = "no_float"))]
+        "is_float" => value.is::<f32>() || value.is::<f64>(),
+        "is_bool" => value.is::<bool>(),
+        "is_char" => value.is::<char>(),
+        "is_string" => value.is::<String>(),
+        #[cfg(not(feature = "no_index"))]
+        "is_array" => value.is::<Array>(),
+        #[cfg(not(feature = "no_index"))]
+        "is_blob" => value.is::<Blob>(),
+        #[cfg(not(feature = "no_object"))]
+        "is_map" => value.is::<Map>(),
+        "is_shared" => value.is::<SharedValue>(),
+        "is_unit" => value.is::<()>(),
+        _ => return None,
+    };
+
+    Some(Ok(result.into_dynamic()))
+}
@@ -2,7 +2,13 @@
 
 use crate::parser::Position;
 
-use crate::stdlib::{char, error::Error, fmt, string::String};
+use crate::stdlib::{
+    char,
+    error::Error,
+    fmt, format,
+    string::{String, ToString},
+    vec::Vec,
+};
 
 /// Error when tokenizing the script text.
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
@@ -19,6 +25,9 @@ pub enum LexError {
     MalformedChar(String),
     /// An identifier is in an invalid format.
     MalformedIdentifier(String),
+    /// A symbol (keyword, operator or identifier) that has been disabled via `Engine::disable_symbol`,
+    /// or a symbol or literal form not allowed by the `Dialect` set via `Engine::set_dialect`.
+    DisabledSymbol(String),
 }
 
 impl Error for LexError {}
@@ -32,6 +41,7 @@ impl fmt::Display for LexError {
             Self::MalformedChar(s) => write!(f, "Invalid character: '{}'", s),
             Self::MalformedIdentifier(s) => write!(f, "Variable name is not proper: '{}'", s),
             Self::UnterminatedString => write!(f, "Open string is not terminated"),
+            Self::DisabledSymbol(s) => write!(f, "'{}' is disabled", s),
         }
     }
 }
@@ -67,7 +77,7 @@ pub enum ParseErrorType {
     VariableExpected,
     /// Missing an expression. Wrapped value is the expression type.
     ExprExpected(String),
-    /// Defining a function `fn` in an appropriate place (e.g. inside another function).
+    /// Defining a function `fn` in an inappropriate place (e.g. inside a block).
     ///
     /// Not available under the `no_function` feature.
     #[cfg(not(feature = "no_function"))]
@@ -94,12 +104,33 @@ pub enum ParseErrorType {
     FnMissingBody(String),
     /// Assignment to an inappropriate LHS (left-hand-side) expression.
     AssignmentToInvalidLHS,
-    /// Assignment to a copy of a value.
+    /// Assignment into a temporary copy of a value (e.g. the result of a function call or a literal),
+    /// which would have no effect.
     AssignmentToCopy,
     /// Assignment to an a constant variable.
     AssignmentToConstant(String),
     /// Break statement not inside a loop.
     LoopBreak,
+    /// Break or continue statement targeting a loop label that does not exist.
+    /// Wrapped value is the name of the label.
+    UndefinedLabel(String),
+    /// A loop label not followed by a loop.  Wrapped value is the name of the label.
+    LabelWithoutLoop(String),
+    /// Expressions or statements nested too deeply.
+    ExprTooDeep,
+    /// A variable or function name that has been reserved via `Engine::set_reserved_symbols`.
+    /// Wrapped value is the name.
+    ReservedSymbol(String),
+    /// A variable declaration reusing the name of a variable already declared, where forbidden
+    /// via `Engine::set_shadowing`.  Wrapped value is the name.
+    VariableShadowed(String),
+    /// An `import` or `export` statement, or a namespace-qualified call to a function in an imported
+    /// module, that cannot be resolved when imports are validated during compilation
+    /// (see `Engine::enable_import_validation`).  Wrapped value is the error message.
+    ///
+    /// Not available under the `no_module` feature.
+    #[cfg(not(feature = "no_module"))]
+    BadImport(String),
 }
 
 impl ParseErrorType {
@@ -129,6 +160,28 @@ impl ParseError {
         self.1
     }
 
+    /// Format the error together with the offending line of the script source,
+    /// with a `^` marker under the position of the error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let source = "let x = 42;\nlet y = (x + 1;";
+    /// let err = engine.compile(source).unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.to_pretty_string(source),
+    ///     "Expecting ')' for a matching ( in this expression (line 2, position 15)\n  |\n2 | let y = (x + 1;\n  |               ^"
+    /// );
+    /// ```
+    pub fn to_pretty_string(&self, source: &str) -> String {
+        pretty_error(self.to_string(), source, self.1)
+    }
+
     pub(crate) fn desc(&self) -> &str {
         match &self.0 {
             ParseErrorType::BadInput(p) => p,
@@ -153,11 +206,18 @@ impl ParseError {
             #[cfg(not(feature = "no_function"))]
             ParseErrorType::FnMissingBody(_) => "Expecting body statement block for function declaration",
             #[cfg(not(feature = "no_function"))]
-            ParseErrorType::WrongFnDefinition => "Function definitions must be at global level and cannot be inside a block or another function",
+            ParseErrorType::WrongFnDefinition => "Function definitions must be at global level or directly inside the body of another function, and cannot be inside a block",
             ParseErrorType::AssignmentToInvalidLHS => "Cannot assign to this expression",
             ParseErrorType::AssignmentToCopy => "Cannot assign to this expression because it will only be changing a copy of the value",
             ParseErrorType::AssignmentToConstant(_) => "Cannot assign to a constant variable.",
-            ParseErrorType::LoopBreak => "Break statement should only be used inside a loop"
+            ParseErrorType::LoopBreak => "Break statement should only be used inside a loop",
+            ParseErrorType::UndefinedLabel(_) => "Break or continue to an undefined loop label",
+            ParseErrorType::LabelWithoutLoop(_) => "A label must be followed by a loop",
+            ParseErrorType::ExprTooDeep => "Expression exceeds maximum nesting depth",
+            ParseErrorType::ReservedSymbol(_) => "Name is reserved and cannot be used as a variable or function",
+            ParseErrorType::VariableShadowed(_) => "Variable is already declared",
+            #[cfg(not(feature = "no_module"))]
+            ParseErrorType::BadImport(_) => "Cannot resolve import"
         }
     }
 }
@@ -204,6 +264,20 @@ impl fmt::Display for ParseError {
 
             ParseErrorType::MissingToken(token, s) => write!(f, "Expecting '{}' {}", token, s)?,
 
+            ParseErrorType::UndefinedLabel(s) => {
+                write!(f, "Break or continue to undefined loop label '{}", s)?
+            }
+            ParseErrorType::LabelWithoutLoop(s) => {
+                write!(f, "Expecting a loop after the label '{}", s)?
+            }
+            ParseErrorType::ReservedSymbol(s) => write!(f, "'{}' is a reserved name", s)?,
+            ParseErrorType::VariableShadowed(s) => {
+                write!(f, "Variable '{}' is already declared", s)?
+            }
+
+            #[cfg(not(feature = "no_module"))]
+            ParseErrorType::BadImport(s) => write!(f, "{}: {}", self.desc(), s)?,
+
             ParseErrorType::AssignmentToConstant(s) if s.is_empty() => {
                 write!(f, "{}", self.desc())?
             }
@@ -223,3 +297,47 @@ impl fmt::Display for ParseError {
         }
     }
 }
+
+/// Append to an error message the line of the script source at a position,
+/// with a `^` marker under the column.
+///
+/// The message is returned unchanged if the position is not within the source.
+pub(crate) fn pretty_error(message: String, source: &str, pos: Position) -> String {
+    let lines: Vec<_> = source.lines().collect();
+
+    let (line, column) = if pos.is_eof() {
+        // Point just after the end of the script
+        match lines.last() {
+            Some(last) => (lines.len(), last.chars().count() + 1),
+            None => return message,
+        }
+    } else {
+        match (pos.line(), pos.position()) {
+            (Some(line), Some(column)) if line > 0 && line <= lines.len() => (line, column),
+            _ => return message,
+        }
+    };
+
+    let text = lines[line - 1];
+    let line_no = line.to_string();
+
+    // Keep tabs so that the marker lines up with the source line
+    let mut chars = text.chars();
+    let indent: String = (1..column)
+        .map(|_| match chars.next() {
+            Some('\t') => '\t',
+            _ => ' ',
+        })
+        .collect();
+
+    format!(
+        "{}\n{:w$} |\n{} | {}\n{:w$} | {}^",
+        message,
+        "",
+        line_no,
+        text,
+        "",
+        indent,
+        w = line_no.len()
+    )
+}
@@ -0,0 +1,119 @@
+//! Module that defines `ScriptedEventHandler`, which bundles an `Engine`, a compiled script and its state.
+#![cfg(not(feature = "no_function"))]
+
+use crate::any::Any;
+use crate::call::FuncArgs;
+use crate::engine::Engine;
+use crate::parser::AST;
+use crate::result::EvalAltResult;
+use crate::scope::Scope;
+
+use crate::stdlib::boxed::Box;
+
+/// A script driven by events.
+///
+/// The script is compiled and its top level is run once to initialize state (e.g. via `let` statements).
+/// Events are then handled by calling the script function with the same name as the event.
+/// All handlers share the same `Scope`, so state initialized by the top level and modified by one
+/// handler is visible to all later handlers.
+///
+/// Not available under the `no_function` feature.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// # #[cfg(not(feature = "no_stdlib"))]
+/// # {
+/// use rhai::{Engine, ScriptedEventHandler};
+///
+/// let mut handler = ScriptedEventHandler::new(
+///     Engine::new(),
+///     r"
+///         let score = 0;
+///
+///         fn on_hit(points) { score += points; score }
+///     ",
+/// )?;
+///
+/// assert_eq!(handler.fire::<_, i64>("on_hit", (10_i64,))?, 10);
+/// assert_eq!(handler.fire::<_, i64>("on_hit", (32_i64,))?, 42);
+///
+/// assert!(handler.has_handler("on_hit", 1));
+/// assert!(!handler.has_handler("on_miss", 0));
+/// # }
+/// # Ok(())
+/// # }
+/// ```
+pub struct ScriptedEventHandler<'e> {
+    /// The `Engine` running the script.
+    engine: Engine<'e>,
+    /// The compiled script.
+    ast: AST,
+    /// State shared by all event handlers.
+    scope: Scope<'static>,
+}
+
+impl<'e> ScriptedEventHandler<'e> {
+    /// Compile a script and run its top level to initialize state.
+    pub fn new(engine: Engine<'e>, script: &str) -> Result<Self, Box<EvalAltResult>> {
+        Self::new_with_scope(engine, Scope::new(), script)
+    }
+
+    /// Compile a script and run its top level to initialize state, starting with an initial `Scope`.
+    ///
+    /// Constants in the `Scope` are propagated into the script during compilation.
+    pub fn new_with_scope(
+        mut engine: Engine<'e>,
+        mut scope: Scope<'static>,
+        script: &str,
+    ) -> Result<Self, Box<EvalAltResult>> {
+        let ast = engine.compile_with_scope(&scope, script)?;
+        engine.consume_ast_with_scope(&mut scope, &ast)?;
+
+        Ok(Self { engine, ast, scope })
+    }
+
+    /// Does the script handle a certain event with the specified number of arguments?
+    ///
+    /// Private functions do not handle events.
+    pub fn has_handler(&self, event: &str, num_args: usize) -> bool {
+        matches!(self.ast.1.get_function(event, num_args), Some(f) if !f.private)
+    }
+
+    /// Fire an event by calling the script function with the same name as the event.
+    ///
+    /// Returns `ErrorFunctionNotFound` if the script does not handle the event.
+    pub fn fire<A: FuncArgs, T: Any + Clone>(
+        &mut self,
+        event: &str,
+        args: A,
+    ) -> Result<T, Box<EvalAltResult>> {
+        self.engine.call_fn(&mut self.scope, &self.ast, event, args)
+    }
+
+    /// Get a reference to the `Engine`.
+    pub fn engine(&self) -> &Engine<'e> {
+        &self.engine
+    }
+
+    /// Get a mutable reference to the `Engine`, e.g. to register more functions.
+    pub fn engine_mut(&mut self) -> &mut Engine<'e> {
+        &mut self.engine
+    }
+
+    /// Get a reference to the state shared by all event handlers.
+    pub fn scope(&self) -> &Scope<'static> {
+        &self.scope
+    }
+
+    /// Get a mutable reference to the state shared by all event handlers.
+    pub fn scope_mut(&mut self) -> &mut Scope<'static> {
+        &mut self.scope
+    }
+
+    /// Get a reference to the compiled script.
+    pub fn ast(&self) -> &AST {
+        &self.ast
+    }
+}
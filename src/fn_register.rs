@@ -7,8 +7,17 @@ use crate::engine::{Engine, FnCallArgs};
 use crate::parser::Position;
 use crate::result::EvalAltResult;
 
+#[cfg(not(feature = "no_module"))]
+use crate::module::NativeModule;
+
 use crate::stdlib::{any::TypeId, boxed::Box, string::ToString, vec};
 
+#[cfg(feature = "sync")]
+use crate::stdlib::sync::Mutex;
+
+#[cfg(not(feature = "sync"))]
+use crate::stdlib::cell::RefCell;
+
 /// A trait to register custom functions with the `Engine`.
 pub trait RegisterFn<FN, ARGS, RET> {
     /// Register a custom function with the `Engine`.
@@ -16,7 +25,7 @@ pub trait RegisterFn<FN, ARGS, RET> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::{Engine, RegisterFn};
     ///
     /// // Normal function
@@ -48,7 +57,7 @@ pub trait RegisterDynamicFn<FN, ARGS> {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// use rhai::{Engine, Dynamic, RegisterDynamicFn};
     ///
     /// // Function that returns a Dynamic value
@@ -68,7 +77,7 @@ pub trait RegisterDynamicFn<FN, ARGS> {
     fn register_dynamic_fn(&mut self, name: &str, f: FN);
 }
 
-/// A trait to register fallible custom functions returning Result<_, EvalAltResult> with the `Engine`.
+/// A trait to register fallible custom functions returning Result<_, Box<EvalAltResult>> with the `Engine`.
 pub trait RegisterResultFn<FN, ARGS, RET> {
     /// Register a custom fallible function with the `Engine`.
     ///
@@ -78,7 +87,7 @@ pub trait RegisterResultFn<FN, ARGS, RET> {
     /// use rhai::{Engine, RegisterResultFn, EvalAltResult};
     ///
     /// // Normal function
-    /// fn div(x: i64, y: i64) -> Result<i64, EvalAltResult> {
+    /// fn div(x: i64, y: i64) -> Result<i64, Box<EvalAltResult>> {
     ///     if y == 0 {
     ///         // '.into()' automatically converts to 'EvalAltResult::ErrorRuntime'
     ///         Err("division by zero!".into())
@@ -98,6 +107,68 @@ pub trait RegisterResultFn<FN, ARGS, RET> {
     fn register_result_fn(&mut self, name: &str, f: FN);
 }
 
+/// A trait to register custom functions that mutate the state they capture (`FnMut` closures)
+/// with the `Engine`.
+///
+/// The closure is kept behind a `RefCell` (or a `Mutex` under the `sync` feature, so calls from
+/// different threads take turns), so there is no need to wrap the captured state in one.
+pub trait RegisterFnMut<FN, ARGS, RET> {
+    /// Register a custom function which mutates the state it captures with the `Engine`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, RegisterFnMut, INT};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// // You must use the trait rhai::RegisterFnMut to get this method.
+    /// let mut total = 0;
+    /// engine.register_fn_mut("add_to_total", move |x: INT| {
+    ///     total += x;
+    ///     total
+    /// });
+    ///
+    /// assert_eq!(engine.eval::<INT>("add_to_total(40); add_to_total(2)")?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn register_fn_mut(&mut self, name: &str, f: FN);
+}
+
+/// A trait to register single-use custom functions (`FnOnce` closures) with the `Engine`, e.g. to
+/// let a script resume a host operation exactly once.
+///
+/// The function is consumed by its first call.  Calling it again fails with
+/// `EvalAltResult::ErrorFunctionConsumed`.
+pub trait RegisterFnOnce<FN, ARGS, RET> {
+    /// Register a single-use custom function with the `Engine`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, EvalAltResult, RegisterFnOnce, INT};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// // You must use the trait rhai::RegisterFnOnce to get this method.
+    /// let reply = String::from("done");
+    /// engine.register_fn_once("resume", move |x: INT| format!("{} {}", reply, x));
+    ///
+    /// assert_eq!(engine.eval::<String>("resume(42)")?, "done 42");
+    ///
+    /// assert!(matches!(
+    ///     *engine.eval::<String>("resume(42)").expect_err("should error"),
+    ///     EvalAltResult::ErrorFunctionConsumed(name, _) if name == "resume"
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn register_fn_once(&mut self, name: &str, f: FN);
+}
+
 // These types are used to build a unique _marker_ tuple type for each combination
 // of function parameter types in order to make each trait implementation unique.
 // That is because stable Rust currently does not allow distinguishing implementations
@@ -136,6 +207,12 @@ macro_rules! def_register {
     //                   ^ function parameter marker type (T, Ref<T> or Mut<T>)
     //                               ^ function parameter actual type
     //                                            ^ dereferencing function
+        def_register!(imp_for Engine<'_>; $($par => $mark => $param => $clone),*);
+        #[cfg(not(feature = "no_module"))]
+        def_register!(imp_for NativeModule; $($par => $mark => $param => $clone),*);
+    };
+    (imp_for $target:ty; $($par:ident => $mark:ty => $param:ty => $clone:expr),*) => {
+    //       ^ type to register the functions into (Engine or NativeModule)
         impl<
             $($par: Any + Clone,)*
 
@@ -146,7 +223,7 @@ macro_rules! def_register {
             FN: Fn($($param),*) -> RET + 'static,
 
             RET: Any
-        > RegisterFn<FN, ($($mark,)*), RET> for Engine<'_>
+        > RegisterFn<FN, ($($mark,)*), RET> for $target
         {
             fn register_fn(&mut self, name: &str, f: FN) {
                 let fn_name = name.to_string();
@@ -156,7 +233,7 @@ macro_rules! def_register {
                     const NUM_ARGS: usize = count_args!($($par)*);
 
                     if args.len() != NUM_ARGS {
-                        return Err(EvalAltResult::ErrorFunctionArgsMismatch(fn_name.clone(), NUM_ARGS, args.len(), pos));
+                        return Err(Box::new(EvalAltResult::ErrorFunctionArgsMismatch(fn_name.clone(), NUM_ARGS, args.len(), pos)));
                     }
 
                     #[allow(unused_variables, unused_mut)]
@@ -183,7 +260,7 @@ macro_rules! def_register {
 
             #[cfg(not(feature = "sync"))]
             FN: Fn($($param),*) -> Dynamic + 'static,
-        > RegisterDynamicFn<FN, ($($mark,)*)> for Engine<'_>
+        > RegisterDynamicFn<FN, ($($mark,)*)> for $target
         {
             fn register_dynamic_fn(&mut self, name: &str, f: FN) {
                 let fn_name = name.to_string();
@@ -193,7 +270,7 @@ macro_rules! def_register {
                     const NUM_ARGS: usize = count_args!($($par)*);
 
                     if args.len() != NUM_ARGS {
-                        return Err(EvalAltResult::ErrorFunctionArgsMismatch(fn_name.clone(), NUM_ARGS, args.len(), pos));
+                        return Err(Box::new(EvalAltResult::ErrorFunctionArgsMismatch(fn_name.clone(), NUM_ARGS, args.len(), pos)));
                     }
 
                     #[allow(unused_variables, unused_mut)]
@@ -215,12 +292,12 @@ macro_rules! def_register {
             $($par: Any + Clone,)*
 
             #[cfg(feature = "sync")]
-            FN: Fn($($param),*) -> Result<RET, EvalAltResult> + Send + Sync + 'static,
+            FN: Fn($($param),*) -> Result<RET, Box<EvalAltResult>> + Send + Sync + 'static,
             #[cfg(not(feature = "sync"))]
-            FN: Fn($($param),*) -> Result<RET, EvalAltResult> + 'static,
+            FN: Fn($($param),*) -> Result<RET, Box<EvalAltResult>> + 'static,
 
             RET: Any
-        > RegisterResultFn<FN, ($($mark,)*), RET> for Engine<'_>
+        > RegisterResultFn<FN, ($($mark,)*), RET> for $target
         {
             fn register_result_fn(&mut self, name: &str, f: FN) {
                 let fn_name = name.to_string();
@@ -230,7 +307,7 @@ macro_rules! def_register {
                     const NUM_ARGS: usize = count_args!($($par)*);
 
                     if args.len() != NUM_ARGS {
-                        return Err(EvalAltResult::ErrorFunctionArgsMismatch(fn_name.clone(), NUM_ARGS, args.len(), pos));
+                        return Err(Box::new(EvalAltResult::ErrorFunctionArgsMismatch(fn_name.clone(), NUM_ARGS, args.len(), pos)));
                     }
 
                     #[allow(unused_variables, unused_mut)]
@@ -249,6 +326,109 @@ macro_rules! def_register {
             }
         }
 
+        impl<
+            $($par: Any + Clone,)*
+
+            #[cfg(feature = "sync")]
+            FN: FnMut($($param),*) -> RET + Send + 'static,
+
+            #[cfg(not(feature = "sync"))]
+            FN: FnMut($($param),*) -> RET + 'static,
+
+            RET: Any
+        > RegisterFnMut<FN, ($($mark,)*), RET> for $target
+        {
+            fn register_fn_mut(&mut self, name: &str, f: FN) {
+                let fn_name = name.to_string();
+
+                #[cfg(feature = "sync")]
+                let f = Mutex::new(f);
+                #[cfg(not(feature = "sync"))]
+                let f = RefCell::new(f);
+
+                let func = move |args: &mut FnCallArgs, pos: Position| {
+                    // Check for length at the beginning to avoid per-element bound checks.
+                    const NUM_ARGS: usize = count_args!($($par)*);
+
+                    if args.len() != NUM_ARGS {
+                        return Err(Box::new(EvalAltResult::ErrorFunctionArgsMismatch(fn_name.clone(), NUM_ARGS, args.len(), pos)));
+                    }
+
+                    #[allow(unused_variables, unused_mut)]
+                    let mut drain = args.iter_mut();
+                    $(
+                    // Downcast every element, return in case of a type mismatch
+                    let $par = drain.next().unwrap().downcast_mut::<$par>().unwrap();
+                    )*
+
+                    // Under `sync`, a panic in an earlier call (poisoning the lock) does not make
+                    // the function unusable
+                    #[cfg(feature = "sync")]
+                    let mut f = f.lock().unwrap_or_else(|err| err.into_inner());
+                    #[cfg(not(feature = "sync"))]
+                    let mut f = f.borrow_mut();
+
+                    // Call the user-supplied function using ($clone) to
+                    // potentially clone the value, otherwise pass the reference.
+                    let r = (&mut *f)($(($clone)($par)),*);
+                    Ok(Box::new(r) as Dynamic)
+                };
+                self.register_fn_raw(name, vec![$(TypeId::of::<$par>()),*], Box::new(func));
+            }
+        }
+
+        impl<
+            $($par: Any + Clone,)*
+
+            #[cfg(feature = "sync")]
+            FN: FnOnce($($param),*) -> RET + Send + 'static,
+
+            #[cfg(not(feature = "sync"))]
+            FN: FnOnce($($param),*) -> RET + 'static,
+
+            RET: Any
+        > RegisterFnOnce<FN, ($($mark,)*), RET> for $target
+        {
+            fn register_fn_once(&mut self, name: &str, f: FN) {
+                let fn_name = name.to_string();
+
+                #[cfg(feature = "sync")]
+                let f = Mutex::new(Some(f));
+                #[cfg(not(feature = "sync"))]
+                let f = RefCell::new(Some(f));
+
+                let func = move |args: &mut FnCallArgs, pos: Position| {
+                    // Check for length at the beginning to avoid per-element bound checks.
+                    const NUM_ARGS: usize = count_args!($($par)*);
+
+                    if args.len() != NUM_ARGS {
+                        return Err(Box::new(EvalAltResult::ErrorFunctionArgsMismatch(fn_name.clone(), NUM_ARGS, args.len(), pos)));
+                    }
+
+                    // Take the function out, so that it can only be called once
+                    #[cfg(feature = "sync")]
+                    let f = f.lock().unwrap_or_else(|err| err.into_inner()).take();
+                    #[cfg(not(feature = "sync"))]
+                    let f = f.borrow_mut().take();
+
+                    let f = f.ok_or_else(|| Box::new(EvalAltResult::ErrorFunctionConsumed(fn_name.clone(), pos)))?;
+
+                    #[allow(unused_variables, unused_mut)]
+                    let mut drain = args.iter_mut();
+                    $(
+                    // Downcast every element, return in case of a type mismatch
+                    let $par = drain.next().unwrap().downcast_mut::<$par>().unwrap();
+                    )*
+
+                    // Call the user-supplied function using ($clone) to
+                    // potentially clone the value, otherwise pass the reference.
+                    let r = f($(($clone)($par)),*);
+                    Ok(Box::new(r) as Dynamic)
+                };
+                self.register_fn_raw(name, vec![$(TypeId::of::<$par>()),*], Box::new(func));
+            }
+        }
+
         //def_register!(imp_pop $($par => $mark => $param),*);
     };
     ($p0:ident $(, $p:ident)*) => {
@@ -0,0 +1,296 @@
+//! Module implementing the `format` function, which builds a string out of a template with placeholders.
+
+use crate::any::Variant;
+use crate::parser::{Position, INT};
+use crate::result::EvalAltResult;
+
+#[cfg(not(feature = "no_float"))]
+use crate::parser::FLOAT;
+
+use crate::stdlib::{
+    boxed::Box,
+    format,
+    iter::Peekable,
+    str::Chars,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// The maximum width and precision in a format specification, which limit the size of the
+/// formatted text.
+const MAX_WIDTH: usize = 1000;
+
+/// A parsed placeholder specification, i.e. the part after the `:` in `{0:>8.2}`.
+#[derive(Debug, Clone, Default)]
+struct FormatSpec {
+    /// Character used to fill up to the width.
+    fill: Option<char>,
+    /// Alignment: `<`, `>` or `^`.
+    align: Option<char>,
+    /// Always show the sign of numbers.
+    sign: bool,
+    /// Pad numbers with zeros after the sign.
+    zeros: bool,
+    /// Minimum number of characters.
+    width: usize,
+    /// Number of decimal places for floating-point numbers, maximum number of characters for others.
+    precision: Option<usize>,
+    /// Number formatting: `x`, `X`, `o`, `b` or `e`.
+    kind: Option<char>,
+}
+
+/// Build a string from a template, replacing each placeholder with the matching argument.
+///
+/// `to_string` is used to convert arguments that are not a primitive type, and floating-point numbers
+/// without a precision (so that they follow the `Engine`'s float precision).
+pub(crate) fn format_string(
+    template: &str,
+    args: &[&mut Variant],
+    mut to_string: impl FnMut(&Variant) -> Result<String, Box<EvalAltResult>>,
+    pos: Position,
+) -> Result<String, Box<EvalAltResult>> {
+    let error = |msg: String| Box::new(EvalAltResult::ErrorRuntime(msg, pos));
+
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    let mut next_arg = 0;
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '}' => return Err(error("Unmatched '}' in format string".into())),
+            '{' => {
+                let placeholder = read_placeholder(&mut chars)
+                    .ok_or_else(|| error("Unterminated placeholder in format string".into()))?;
+
+                let (index, spec) = match placeholder.find(':') {
+                    Some(p) => (&placeholder[..p], &placeholder[p + 1..]),
+                    None => (placeholder.as_str(), ""),
+                };
+
+                let index = if index.is_empty() {
+                    next_arg += 1;
+                    next_arg - 1
+                } else {
+                    index
+                        .parse::<usize>()
+                        .map_err(|_| error(format!("Invalid placeholder '{{{}}}'", placeholder)))?
+                };
+
+                let spec = parse_spec(spec).ok_or_else(|| {
+                    error(format!(
+                        "Invalid format specification '{{{}}}'",
+                        placeholder
+                    ))
+                })?;
+
+                if spec.width > MAX_WIDTH || matches!(spec.precision, Some(p) if p > MAX_WIDTH) {
+                    return Err(error(format!(
+                        "Width and precision cannot exceed {} in '{{{}}}'",
+                        MAX_WIDTH, placeholder
+                    )));
+                }
+
+                let value = args.get(index).ok_or_else(|| {
+                    error(format!(
+                        "Format argument {} is missing: only {} given",
+                        index,
+                        args.len()
+                    ))
+                })?;
+
+                let text = format_value(&**value, &spec, &mut to_string)?
+                    .map_err(|msg| error(format!("{} '{{{}}}'", msg, placeholder)))?;
+
+                result.push_str(&text);
+            }
+            ch => result.push(ch),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Read the text of a placeholder up to the closing `}`.
+fn read_placeholder(chars: &mut Peekable<Chars>) -> Option<String> {
+    let mut placeholder = String::new();
+
+    loop {
+        match chars.next()? {
+            '}' => return Some(placeholder),
+            '{' => return None,
+            ch => placeholder.push(ch),
+        }
+    }
+}
+
+/// Parse a format specification in the form `[[fill]align][+][0][width][.precision][kind]`.
+fn parse_spec(spec: &str) -> Option<FormatSpec> {
+    let chars: Vec<_> = spec.chars().collect();
+    let is_align = |ch: Option<&char>| matches!(ch, Some('<') | Some('>') | Some('^'));
+
+    let mut result = FormatSpec::default();
+    let mut i = 0;
+
+    if is_align(chars.get(1)) {
+        result.fill = Some(chars[0]);
+        result.align = Some(chars[1]);
+        i = 2;
+    } else if is_align(chars.first()) {
+        result.align = Some(chars[0]);
+        i = 1;
+    }
+
+    if chars.get(i) == Some(&'+') {
+        result.sign = true;
+        i += 1;
+    }
+    if chars.get(i) == Some(&'0') {
+        result.zeros = true;
+        i += 1;
+    }
+
+    // `None` if the number is too large, `Some(None)` if there are no digits
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while matches!(chars.get(*i), Some(ch) if ch.is_ascii_digit()) {
+            *i += 1;
+        }
+        if start == *i {
+            return Some(None);
+        }
+        chars[start..*i]
+            .iter()
+            .collect::<String>()
+            .parse::<usize>()
+            .ok()
+            .map(Some)
+    };
+
+    result.width = digits(&mut i)?.unwrap_or(0);
+
+    if chars.get(i) == Some(&'.') {
+        i += 1;
+        result.precision = Some(digits(&mut i)??);
+    }
+
+    if let Some(&ch @ 'x') | Some(&ch @ 'X') | Some(&ch @ 'o') | Some(&ch @ 'b') | Some(&ch @ 'e') =
+        chars.get(i)
+    {
+        result.kind = Some(ch);
+        i += 1;
+    }
+
+    if i < chars.len() {
+        None
+    } else {
+        Some(result)
+    }
+}
+
+/// Format a single value according to a specification.
+///
+/// The inner `Err` holds a description of a specification that does not fit the value.
+fn format_value(
+    value: &Variant,
+    spec: &FormatSpec,
+    to_string: &mut impl FnMut(&Variant) -> Result<String, Box<EvalAltResult>>,
+) -> Result<Result<String, &'static str>, Box<EvalAltResult>> {
+    let mut is_number = true;
+
+    let text = if let Some(&n) = value.downcast_ref::<INT>() {
+        match spec.kind {
+            Some('x') => format!("{:x}", n),
+            Some('X') => format!("{:X}", n),
+            Some('o') => format!("{:o}", n),
+            Some('b') => format!("{:b}", n),
+            Some('e') => format!("{:e}", n),
+            _ => n.to_string(),
+        }
+    } else {
+        #[cfg(not(feature = "no_float"))]
+        let float = value.downcast_ref::<FLOAT>().copied();
+        #[cfg(feature = "no_float")]
+        let float: Option<INT> = None;
+
+        match (float, spec.kind) {
+            #[cfg(not(feature = "no_float"))]
+            (Some(f), Some('e')) => match spec.precision {
+                Some(p) => format!("{:.*e}", p, f),
+                None => format!("{:e}", f),
+            },
+            #[cfg(not(feature = "no_float"))]
+            (Some(f), None) => match spec.precision {
+                Some(p) => format!("{:.*}", p, f),
+                None => to_string(value)?,
+            },
+            (Some(_), _) => return Ok(Err("Floating-point numbers cannot be formatted as")),
+            (None, Some(_)) => return Ok(Err("Only numbers can be formatted as")),
+            (None, None) => {
+                is_number = false;
+
+                let text = if let Some(s) = value.downcast_ref::<String>() {
+                    s.clone()
+                } else if let Some(ch) = value.downcast_ref::<char>() {
+                    ch.to_string()
+                } else if let Some(b) = value.downcast_ref::<bool>() {
+                    b.to_string()
+                } else if value.is::<()>() {
+                    String::new()
+                } else {
+                    to_string(value)?
+                };
+
+                match spec.precision {
+                    Some(p) => text.chars().take(p).collect(),
+                    None => text,
+                }
+            }
+        }
+    };
+
+    Ok(Ok(pad(text, spec, is_number)))
+}
+
+/// Add the sign and padding required by a specification.
+fn pad(mut text: String, spec: &FormatSpec, is_number: bool) -> String {
+    if is_number && spec.sign && !text.starts_with('-') {
+        text.insert(0, '+');
+    }
+
+    let len = text.chars().count();
+
+    if len >= spec.width {
+        return text;
+    }
+
+    let padding = spec.width - len;
+
+    if is_number && spec.zeros && spec.align.is_none() {
+        let sign = if text.starts_with('+') || text.starts_with('-') {
+            1
+        } else {
+            0
+        };
+        text.insert_str(sign, &"0".repeat(padding));
+        return text;
+    }
+
+    let fill = spec.fill.unwrap_or(' ');
+    let fill = |n| fill.to_string().repeat(n);
+
+    match spec.align {
+        Some('<') => text + &fill(padding),
+        Some('^') => fill(padding / 2) + &text + &fill(padding - padding / 2),
+        Some(_) => fill(padding) + &text,
+        None if is_number => fill(padding) + &text,
+        None => text + &fill(padding),
+    }
+}
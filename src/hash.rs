@@ -0,0 +1,51 @@
+//! Module which registers the hash package: cryptographic hashes and checksums of strings and
+//! BLOB's, backed by the `sha2`, `sha1`, `md-5` and `crc32fast` crates.
+
+use crate::engine::Engine;
+use crate::fn_register::RegisterFn;
+
+#[cfg(not(feature = "no_index"))]
+use crate::engine::Blob;
+
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::stdlib::{format, string::String, vec::Vec};
+
+/// Calculate the CRC-32 checksum of bytes, as four big-endian bytes.
+fn crc32(bytes: &[u8]) -> Vec<u8> {
+    crc32fast::hash(bytes).to_be_bytes().to_vec()
+}
+
+/// Encode a hash as hex text (two lower-case hex digits per byte).
+fn to_hex(hash: &[u8]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl Engine<'_> {
+    /// Register the hash package.
+    pub(crate) fn register_hash(&mut self) {
+        macro_rules! reg_hash {
+            ($name:expr, $blob_name:expr, $func:expr) => {
+                self.register_fn($name, |s: &mut String| to_hex(&$func(s.as_bytes())));
+
+                #[cfg(not(feature = "no_index"))]
+                {
+                    self.register_fn($name, |blob: &mut Blob| to_hex(&$func(blob)));
+                    self.register_fn($blob_name, |s: &mut String| -> Blob {
+                        $func(s.as_bytes()).to_vec()
+                    });
+                    self.register_fn($blob_name, |blob: &mut Blob| -> Blob {
+                        $func(blob).to_vec()
+                    });
+                }
+            };
+        }
+
+        reg_hash!("sha256", "sha256_blob", Sha256::digest);
+        reg_hash!("sha1", "sha1_blob", Sha1::digest);
+        reg_hash!("md5", "md5_blob", Md5::digest);
+        reg_hash!("crc32", "crc32_blob", crc32);
+    }
+}
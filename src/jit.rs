@@ -0,0 +1,1310 @@
+//! Module implementing the experimental JIT compiler for numeric script-defined functions.
+//!
+//! Script-defined functions are compiled to machine code via Cranelift when they are called with
+//! only integer, floating-point and boolean arguments, and their bodies only use those types
+//! (with the standard arithmetic, comparison and logic operators) plus calls to other functions
+//! of the same kind.  Everything else is left to the interpreter.
+//!
+//! Compiled code has no side effects, so whenever it runs into a situation which the interpreter
+//! turns into an error (e.g. arithmetic overflow, division by zero or too many levels of function
+//! calls), it simply bails out and the call is run again by the interpreter.
+//!
+//! Compiled code counts the operations and function calls it runs in the same way as the
+//! interpreter, so the `EvalStats` of an evaluation do not depend on whether code is compiled.
+
+// `INT` may be `i32` (under `only_i32`), so casting it to `i64` is not always unnecessary.
+#![allow(clippy::unnecessary_cast)]
+
+use crate::any::{Any, Dynamic, Variant};
+use crate::engine::{is_op_assignment, Engine, FnCallArgs, FunctionsLib};
+use crate::parser::{Expr, ExprId, FnDef, ReturnType, Stmt, StmtId, INT};
+
+#[cfg(not(feature = "no_float"))]
+use crate::parser::FLOAT;
+
+#[cfg(feature = "unchecked")]
+use crate::builtin::OverflowMode;
+
+use crate::stdlib::{any::TypeId, collections::HashMap, mem, ptr, vec::Vec};
+
+#[cfg(feature = "sync")]
+use crate::stdlib::sync::{Arc, Weak};
+
+#[cfg(not(feature = "sync"))]
+use crate::stdlib::rc::{Rc, Weak};
+
+use cranelift_codegen::ir::{
+    condcodes::IntCC, types, AbiParam, Block, InstBuilder, MemFlags, Type, Value,
+};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, FuncId, Linkage, Module};
+
+#[cfg(not(feature = "no_float"))]
+use cranelift_codegen::ir::condcodes::FloatCC;
+
+#[cfg(feature = "sync")]
+type FnLib = Arc<FunctionsLib>;
+#[cfg(not(feature = "sync"))]
+type FnLib = Rc<FunctionsLib>;
+
+/// Functions library, function definition and argument types of a compiled function.
+///
+/// The addresses of the functions library and function definition cannot be reused by others
+/// while the `Jit` keeps a weak reference to the functions library.
+type FnKey = (usize, usize, Vec<JitType>);
+
+/// Entry point of a compiled function: arguments, state, remaining call levels.
+type EntryFn = extern "C" fn(*const u64, *mut JitState, i64) -> u64;
+
+/// State of a call to a compiled function, updated by the compiled code.
+#[repr(C)]
+#[derive(Debug, Default)]
+struct JitState {
+    /// Set when bailing out, in which case the counts below are discarded.
+    bail: u8,
+    /// Number of statements and expressions evaluated.
+    operations: u64,
+    /// Number of function calls, including operators.
+    fn_calls: u64,
+}
+
+/// Checks whether the function registered for an operator and argument types is the built-in one.
+type IsBuiltin<'a> = dyn Fn(&str, &[TypeId]) -> bool + 'a;
+
+/// Operators which compiled code may implement itself, instead of calling the registered functions.
+pub(crate) const OPERATORS: &[&str] = &[
+    "+", "-", "*", "/", "%", "&", "|", "^", "!", "==", "!=", "<", "<=", ">", ">=",
+];
+
+/// Type of a value in compiled code.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+enum JitType {
+    Int,
+    #[cfg(not(feature = "no_float"))]
+    Float,
+    Bool,
+}
+
+impl JitType {
+    /// Possible return types of a compiled function, in the order they are tried.
+    const ALL: &'static [JitType] = &[
+        JitType::Int,
+        #[cfg(not(feature = "no_float"))]
+        JitType::Float,
+        JitType::Bool,
+    ];
+
+    /// Get the type of a value, if it is supported by compiled code.
+    fn of(value: &Variant) -> Option<Self> {
+        if value.is::<INT>() {
+            return Some(JitType::Int);
+        }
+        #[cfg(not(feature = "no_float"))]
+        {
+            if value.is::<FLOAT>() {
+                return Some(JitType::Float);
+            }
+        }
+        if value.is::<bool>() {
+            return Some(JitType::Bool);
+        }
+        None
+    }
+
+    /// Get the Cranelift type representing this type.
+    fn ir(self) -> Type {
+        match self {
+            #[cfg(not(feature = "only_i32"))]
+            JitType::Int => types::I64,
+            #[cfg(feature = "only_i32")]
+            JitType::Int => types::I32,
+            #[cfg(not(feature = "no_float"))]
+            JitType::Float => types::F64,
+            JitType::Bool => types::I8,
+        }
+    }
+
+    /// Encode a value into the 64 bits passed to the entry point of a compiled function.
+    fn encode(self, value: &Variant) -> u64 {
+        match self {
+            JitType::Int => *value.downcast_ref::<INT>().unwrap() as i64 as u64,
+            #[cfg(not(feature = "no_float"))]
+            JitType::Float => value.downcast_ref::<FLOAT>().unwrap().to_bits(),
+            JitType::Bool => *value.downcast_ref::<bool>().unwrap() as u64,
+        }
+    }
+
+    /// Get the type id of the values of this type.
+    fn type_id(self) -> TypeId {
+        match self {
+            JitType::Int => TypeId::of::<INT>(),
+            #[cfg(not(feature = "no_float"))]
+            JitType::Float => TypeId::of::<FLOAT>(),
+            JitType::Bool => TypeId::of::<bool>(),
+        }
+    }
+
+    /// Decode the 64 bits returned from the entry point of a compiled function.
+    fn decode(self, bits: u64) -> Dynamic {
+        match self {
+            JitType::Int => (bits as i64 as INT).into_dynamic(),
+            #[cfg(not(feature = "no_float"))]
+            JitType::Float => FLOAT::from_bits(bits).into_dynamic(),
+            JitType::Bool => (bits != 0).into_dynamic(),
+        }
+    }
+}
+
+/// A compiled script-defined function.
+#[derive(Debug, Clone, Copy)]
+struct Compiled {
+    /// Id of the compiled function, for calls from other compiled functions.
+    id: FuncId,
+    /// Return type of the compiled function.
+    ret: JitType,
+    /// Address of the entry point called by the `Engine`.
+    entry: usize,
+}
+
+/// JIT compiler state kept by an `Engine`.
+#[derive(Default)]
+pub(crate) struct Jit {
+    /// Cranelift module holding all the compiled code, created on first use.
+    module: Option<JITModule>,
+    /// Is the host machine not supported by Cranelift?
+    unsupported: bool,
+    /// Compiled functions, or `None` for functions that cannot be compiled.
+    compiled: HashMap<FnKey, Option<Compiled>>,
+    /// Functions currently being compiled.
+    compiling: Vec<FnKey>,
+    /// Functions libraries with compiled functions.  Weak references do not keep them alive,
+    /// but keep their addresses from being reused until the compiled functions are removed.
+    libs: Vec<Weak<FunctionsLib>>,
+    /// Counter for unique names of compiled functions.
+    counter: usize,
+}
+
+// `Jit` is only ever used via `&mut`, so sharing references to it between threads is safe.
+#[cfg(feature = "sync")]
+unsafe impl Sync for Jit {}
+
+impl Jit {
+    /// Get the Cranelift module, creating it if necessary.
+    fn module(&mut self) -> Option<&mut JITModule> {
+        if self.module.is_none() && !self.unsupported {
+            let mut flags = settings::builder();
+            flags.set("use_colocated_libcalls", "false").unwrap();
+            flags.set("is_pic", "false").unwrap();
+            flags.set("opt_level", "speed").unwrap();
+
+            match cranelift_native::builder()
+                .map_err(|_| ())
+                .and_then(|isa| isa.finish(settings::Flags::new(flags)).map_err(|_| ()))
+            {
+                Ok(isa) => {
+                    let builder = JITBuilder::with_isa(isa, default_libcall_names());
+                    self.module = Some(JITModule::new(builder));
+                }
+                Err(_) => self.unsupported = true,
+            }
+        }
+
+        self.module.as_mut()
+    }
+
+    /// Get a compiled version of a script-defined function for a list of argument types,
+    /// compiling it if necessary.
+    fn get_compiled(
+        &mut self,
+        lib: &FnLib,
+        fn_def: &FnDef,
+        arg_types: Vec<JitType>,
+        is_builtin: &IsBuiltin,
+    ) -> Option<Compiled> {
+        let key = (lib_address(lib), fn_def as *const FnDef as usize, arg_types);
+
+        if let Some(compiled) = self.compiled.get(&key) {
+            return *compiled;
+        }
+
+        // Mutually-recursive functions are not compiled
+        if self.compiling.contains(&key) {
+            return None;
+        }
+
+        if !self.libs.iter().any(|l| l.as_ptr() as usize == key.0) {
+            self.prune();
+
+            #[cfg(feature = "sync")]
+            self.libs.push(Arc::downgrade(lib));
+            #[cfg(not(feature = "sync"))]
+            self.libs.push(Rc::downgrade(lib));
+        }
+
+        self.module()?;
+
+        self.compiling.push(key.clone());
+
+        // Try each possible return type until the function compiles
+        let compiled = JitType::ALL
+            .iter()
+            .find_map(|&ret| self.compile(lib, fn_def, &key.2, ret, is_builtin));
+
+        self.compiling.pop();
+        self.compiled.insert(key, compiled);
+
+        compiled
+    }
+
+    /// Remove all compiled functions, as the functions registered with the `Engine` have changed.
+    ///
+    /// Compiled code is never running at this point, so the memory holding it is freed.
+    pub(crate) fn clear(&mut self) {
+        self.compiled.clear();
+        self.libs.clear();
+
+        if let Some(module) = self.module.take() {
+            unsafe { module.free_memory() };
+        }
+    }
+
+    /// Remove the compiled functions of functions libraries which have been dropped.
+    ///
+    /// Once no compiled functions are left, the memory holding all the compiled code is freed.
+    fn prune(&mut self) {
+        if self.libs.iter().all(|l| l.strong_count() > 0) {
+            return;
+        }
+
+        self.libs.retain(|l| l.strong_count() > 0);
+
+        let libs = &self.libs;
+        self.compiled
+            .retain(|key, _| libs.iter().any(|l| l.as_ptr() as usize == key.0));
+
+        if self.compiled.is_empty() && self.compiling.is_empty() {
+            if let Some(module) = self.module.take() {
+                // No compiled code is running, as compiled functions only call one another
+                unsafe { module.free_memory() };
+            }
+        }
+    }
+
+    /// Compile a script-defined function for a list of argument types and a return type.
+    fn compile(
+        &mut self,
+        lib: &FnLib,
+        fn_def: &FnDef,
+        arg_types: &[JitType],
+        ret: JitType,
+        is_builtin: &IsBuiltin,
+    ) -> Option<Compiled> {
+        self.counter += 1;
+        let name = format!("{}#{}", fn_def.name, self.counter);
+
+        let module = self.module.as_mut().unwrap();
+        let ptr_type = module.target_config().pointer_type();
+
+        let mut sig = module.make_signature();
+        sig.params.push(AbiParam::new(ptr_type));
+        sig.params.push(AbiParam::new(types::I64));
+        sig.params
+            .extend(arg_types.iter().map(|t| AbiParam::new(t.ir())));
+        sig.returns.push(AbiParam::new(ret.ir()));
+
+        let id = module.declare_function(&name, Linkage::Local, &sig).ok()?;
+
+        let mut ctx = module.make_context();
+        let mut fn_ctx = FunctionBuilderContext::new();
+        ctx.func.signature = sig;
+
+        FnCompiler::new(
+            self,
+            lib,
+            fn_def,
+            arg_types,
+            id,
+            ret,
+            is_builtin,
+            &mut ctx.func,
+            &mut fn_ctx,
+        )
+        .compile()?;
+
+        let module = self.module.as_mut().unwrap();
+        module.define_function(id, &mut ctx).ok()?;
+        module.clear_context(&mut ctx);
+
+        // Generate the entry point
+        let mut sig = module.make_signature();
+        sig.params.push(AbiParam::new(ptr_type));
+        sig.params.push(AbiParam::new(ptr_type));
+        sig.params.push(AbiParam::new(types::I64));
+        sig.returns.push(AbiParam::new(types::I64));
+
+        let entry_id = module
+            .declare_function(&format!("{}#entry", name), Linkage::Local, &sig)
+            .ok()?;
+
+        ctx.func.signature = sig;
+        build_entry(module, id, arg_types, ret, &mut ctx.func, &mut fn_ctx);
+
+        module.define_function(entry_id, &mut ctx).ok()?;
+        module.clear_context(&mut ctx);
+        module.finalize_definitions().ok()?;
+
+        Some(Compiled {
+            id,
+            ret,
+            entry: module.get_finalized_function(entry_id) as usize,
+        })
+    }
+}
+
+/// Get the address of a functions library, which uniquely identifies it while it is alive.
+fn lib_address(lib: &FnLib) -> usize {
+    &**lib as *const FunctionsLib as usize
+}
+
+/// Build the entry point of a compiled function, which takes the arguments packed in an array
+/// of 64-bit values and returns the result as a 64-bit value.
+fn build_entry(
+    module: &mut JITModule,
+    id: FuncId,
+    arg_types: &[JitType],
+    ret: JitType,
+    func: &mut cranelift_codegen::ir::Function,
+    fn_ctx: &mut FunctionBuilderContext,
+) {
+    let mut builder = FunctionBuilder::new(func, fn_ctx);
+    let block = builder.create_block();
+
+    builder.append_block_params_for_function_params(block);
+    builder.switch_to_block(block);
+
+    let params = builder.block_params(block).to_vec();
+    let (args, state, levels) = (params[0], params[1], params[2]);
+    let mut call_args = vec![state, levels];
+
+    for (i, t) in arg_types.iter().enumerate() {
+        let offset = (i * mem::size_of::<u64>()) as i32;
+
+        call_args.push(match t {
+            #[cfg(not(feature = "no_float"))]
+            JitType::Float => builder
+                .ins()
+                .load(types::F64, MemFlags::trusted(), args, offset),
+            _ => {
+                let value = builder
+                    .ins()
+                    .load(types::I64, MemFlags::trusted(), args, offset);
+                if t.ir() == types::I64 {
+                    value
+                } else {
+                    builder.ins().ireduce(t.ir(), value)
+                }
+            }
+        });
+    }
+
+    let callee = module.declare_func_in_func(id, builder.func);
+    let call = builder.ins().call(callee, &call_args);
+    let result = builder.inst_results(call)[0];
+
+    let result = match ret {
+        #[cfg(not(feature = "no_float"))]
+        JitType::Float => builder.ins().bitcast(types::I64, MemFlags::new(), result),
+        JitType::Bool => builder.ins().uextend(types::I64, result),
+        JitType::Int if ret.ir() == types::I64 => result,
+        JitType::Int => builder.ins().sextend(types::I64, result),
+    };
+
+    builder.ins().return_(&[result]);
+    builder.seal_all_blocks();
+    builder.finalize();
+}
+
+/// Compiler of the body of a script-defined function.
+struct FnCompiler<'a> {
+    jit: &'a mut Jit,
+    lib: &'a FnLib,
+    fn_def: &'a FnDef,
+    arg_types: &'a [JitType],
+    id: FuncId,
+    ret: JitType,
+    is_builtin: &'a IsBuiltin<'a>,
+    builder: FunctionBuilder<'a>,
+    /// Pointer to the `JitState` of the call.
+    state: Value,
+    /// Remaining levels of function calls allowed.
+    levels: Value,
+    /// Variables holding the function parameters.
+    params: Vec<Variable>,
+    /// Start of the function body, where self tail calls jump to.
+    body: Option<Block>,
+    /// Block which bails out, created when first needed.
+    bail: Option<Block>,
+    /// Variables in scope: name, variable, type and whether it is a constant.
+    vars: Vec<(&'a str, Variable, JitType, bool)>,
+    /// Enclosing loops: where `continue` and `break` jump to, and the loop label (if any).
+    loops: Vec<(Block, Block, Option<&'a str>)>,
+    /// Number of variables declared.
+    num_vars: usize,
+    /// Operations and function calls in the current block which are not yet added to the state.
+    stats: (i64, i64),
+}
+
+impl<'a> FnCompiler<'a> {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        jit: &'a mut Jit,
+        lib: &'a FnLib,
+        fn_def: &'a FnDef,
+        arg_types: &'a [JitType],
+        id: FuncId,
+        ret: JitType,
+        is_builtin: &'a IsBuiltin<'a>,
+        func: &'a mut cranelift_codegen::ir::Function,
+        fn_ctx: &'a mut FunctionBuilderContext,
+    ) -> Self {
+        let mut builder = FunctionBuilder::new(func, fn_ctx);
+        let entry = builder.create_block();
+
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+
+        let params = builder.block_params(entry).to_vec();
+
+        Self {
+            jit,
+            lib,
+            fn_def,
+            arg_types,
+            id,
+            ret,
+            is_builtin,
+            builder,
+            state: params[0],
+            levels: params[1],
+            params: Vec::new(),
+            body: None,
+            bail: None,
+            vars: Vec::new(),
+            loops: Vec::new(),
+            num_vars: 0,
+            stats: (0, 0),
+        }
+    }
+
+    /// Compile the function.
+    fn compile(mut self) -> Option<()> {
+        let fn_def = self.fn_def;
+        let entry = self.builder.current_block().unwrap();
+        let values = self.builder.block_params(entry)[2..].to_vec();
+
+        // Guard against too many levels of function calls
+        let overflow = self
+            .builder
+            .ins()
+            .icmp_imm(IntCC::SignedLessThanOrEqual, self.levels, 0);
+        self.bail_if(overflow);
+
+        for ((name, &t), value) in fn_def.params.iter().zip(self.arg_types).zip(values) {
+            let var = self.declare_var(t);
+            self.builder.def_var(var, value);
+            self.params.push(var);
+            self.vars.push((name, var, t, false));
+        }
+
+        let body = self.builder.create_block();
+        self.jump(body, &[]);
+        self.builder.switch_to_block(body);
+        self.body = Some(body);
+
+        self.compile_tail(&fn_def.body)?;
+
+        if let Some(bail) = self.bail {
+            self.builder.switch_to_block(bail);
+            let one = self.builder.ins().iconst(types::I8, 1);
+            self.builder
+                .ins()
+                .store(MemFlags::trusted(), one, self.state, 0);
+            let zero = self.zero(self.ret);
+            self.builder.ins().return_(&[zero]);
+        }
+
+        self.builder.seal_all_blocks();
+        self.builder.finalize();
+
+        Some(())
+    }
+
+    /// Declare a new variable.
+    fn declare_var(&mut self, t: JitType) -> Variable {
+        let var = Variable::from_u32(self.num_vars as u32);
+        self.num_vars += 1;
+        self.builder.declare_var(var, t.ir());
+        var
+    }
+
+    /// Get a zero value of a type.
+    fn zero(&mut self, t: JitType) -> Value {
+        match t {
+            #[cfg(not(feature = "no_float"))]
+            JitType::Float => self.builder.ins().f64const(0.0),
+            _ => self.builder.ins().iconst(t.ir(), 0),
+        }
+    }
+
+    /// Continue in a new block which is not reachable, after a jump or a return.
+    fn switch_to_unreachable(&mut self) {
+        let block = self.builder.create_block();
+        self.builder.switch_to_block(block);
+    }
+
+    /// Get the block which bails out.
+    fn bail_block(&mut self) -> Block {
+        let builder = &mut self.builder;
+        *self.bail.get_or_insert_with(|| builder.create_block())
+    }
+
+    /// Count operations and function calls run in the current block.
+    fn count(&mut self, operations: i64, fn_calls: i64) {
+        self.stats.0 += operations;
+        self.stats.1 += fn_calls;
+    }
+
+    /// Add the operations and function calls counted in the current block to the state,
+    /// before leaving the block.
+    fn flush_stats(&mut self) {
+        let (operations, fn_calls) = mem::take(&mut self.stats);
+        let counts = [
+            (operations, mem::offset_of!(JitState, operations)),
+            (fn_calls, mem::offset_of!(JitState, fn_calls)),
+        ];
+
+        for (count, offset) in counts {
+            if count > 0 {
+                let offset = offset as i32;
+                let value =
+                    self.builder
+                        .ins()
+                        .load(types::I64, MemFlags::trusted(), self.state, offset);
+                let value = self.builder.ins().iadd_imm(value, count);
+                self.builder
+                    .ins()
+                    .store(MemFlags::trusted(), value, self.state, offset);
+            }
+        }
+    }
+
+    /// Jump to a block.
+    fn jump(&mut self, block: Block, args: &[Value]) {
+        self.flush_stats();
+        self.builder.ins().jump(block, args);
+    }
+
+    /// Branch to one of two blocks depending on a condition.
+    fn brif(
+        &mut self,
+        cond: Value,
+        then_block: Block,
+        then_args: &[Value],
+        else_block: Block,
+        else_args: &[Value],
+    ) {
+        self.flush_stats();
+        self.builder
+            .ins()
+            .brif(cond, then_block, then_args, else_block, else_args);
+    }
+
+    /// Bail out.  The counted operations and function calls are discarded, as the call is run
+    /// again by the interpreter.
+    fn bail(&mut self) {
+        let bail = self.bail_block();
+        self.stats = (0, 0);
+        self.builder.ins().jump(bail, &[]);
+        self.switch_to_unreachable();
+    }
+
+    /// Bail out if a condition is true.
+    fn bail_if(&mut self, cond: Value) {
+        let bail = self.bail_block();
+        let next = self.builder.create_block();
+        self.builder.ins().brif(cond, bail, &[], next, &[]);
+        self.builder.switch_to_block(next);
+    }
+
+    /// Get a child expression of the function body.
+    fn expr(&self, id: &ExprId) -> &'a Expr {
+        &self.fn_def.nodes[*id]
+    }
+
+    /// Get a child statement of the function body.
+    fn stmt(&self, id: &StmtId) -> &'a Stmt {
+        &self.fn_def.nodes[*id]
+    }
+
+    /// Compile a statement in tail position - i.e. whose value is the function's return value.
+    fn compile_tail(&mut self, stmt: &'a Stmt) -> Option<()> {
+        let nodes = &self.fn_def.nodes;
+
+        match stmt {
+            Stmt::Block(block, _) if !block.is_empty() => {
+                let vars_len = self.vars.len();
+                let (last, block) = nodes[*block].split_last().unwrap();
+
+                block.iter().try_for_each(|stmt| self.compile_stmt(stmt))?;
+                self.compile_tail(last)?;
+
+                self.vars.truncate(vars_len);
+                Some(())
+            }
+
+            Stmt::IfThenElse(guard, if_body, else_body) => {
+                let guard = self.compile_bool(self.expr(guard))?;
+                let (if_block, else_block) =
+                    (self.builder.create_block(), self.builder.create_block());
+
+                self.brif(guard, if_block, &[], else_block, &[]);
+
+                self.builder.switch_to_block(if_block);
+                self.compile_tail(self.stmt(if_body))?;
+
+                self.builder.switch_to_block(else_block);
+                match else_body {
+                    Some(stmt) => self.compile_tail(self.stmt(stmt)),
+                    // Returns ()
+                    None => {
+                        self.bail();
+                        Some(())
+                    }
+                }
+            }
+
+            Stmt::Expr(expr) if !matches!(nodes[*expr], Expr::Assignment(_, _, _)) => {
+                let expr = self.expr(expr);
+
+                // Tail calls are not counted as statements by the interpreter either
+                if !self.is_tail_call(expr) {
+                    self.count(1, 0);
+                }
+                self.compile_return(expr)
+            }
+
+            // Returns ()
+            _ => {
+                self.compile_stmt(stmt)?;
+                self.bail();
+                Some(())
+            }
+        }
+    }
+
+    /// Is an expression a call to a script-defined function?
+    fn is_tail_call(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::FunctionCall(fn_name, args_expr_list, _, _, _) => self
+                .lib
+                .get_function(fn_name, args_expr_list.len())
+                .is_some(),
+            _ => false,
+        }
+    }
+
+    /// Compile returning the value of an expression.
+    fn compile_return(&mut self, expr: &'a Expr) -> Option<()> {
+        if let Expr::FunctionCall(fn_name, args_expr_list, _, _, _) = expr {
+            if let Some(fn_def) = self.lib.get_function(fn_name, args_expr_list.len()) {
+                let args = self.fn_def.nodes[*args_expr_list]
+                    .iter()
+                    .map(|expr| self.compile_expr(expr))
+                    .collect::<Option<Vec<_>>>()?;
+
+                let arg_types: Vec<_> = args.iter().map(|(_, t)| *t).collect();
+
+                if ptr::eq(fn_def, self.fn_def) && arg_types == self.arg_types {
+                    // Self tail call - jump back to the start of the function body
+                    for (&var, (value, _)) in self.params.iter().zip(args) {
+                        self.builder.def_var(var, value);
+                    }
+                    self.jump(self.body.unwrap(), &[]);
+                    self.switch_to_unreachable();
+                    return Some(());
+                }
+
+                let (value, t) = self.compile_call(fn_def, args)?;
+                return self.return_value(value, t);
+            }
+        }
+
+        let (value, t) = self.compile_expr(expr)?;
+        self.return_value(value, t)
+    }
+
+    /// Return a value from the function.
+    fn return_value(&mut self, value: Value, t: JitType) -> Option<()> {
+        if t != self.ret {
+            return None;
+        }
+
+        self.flush_stats();
+        self.builder.ins().return_(&[value]);
+        self.switch_to_unreachable();
+        Some(())
+    }
+
+    /// Compile a statement.
+    fn compile_stmt(&mut self, stmt: &'a Stmt) -> Option<()> {
+        let nodes = &self.fn_def.nodes;
+
+        self.count(1, 0);
+
+        match stmt {
+            Stmt::Noop(_) => Some(()),
+
+            Stmt::Expr(expr) if is_op_assignment(nodes, &nodes[*expr]) => {
+                self.compile_op_assignment(self.expr(expr))
+            }
+
+            Stmt::Expr(expr) => match self.expr(expr) {
+                Expr::Assignment(lhs, rhs, _) => {
+                    self.count(1, 0);
+                    self.compile_assignment(self.expr(lhs), self.expr(rhs))
+                }
+                expr => self.compile_expr(expr).map(|_| ()),
+            },
+
+            Stmt::Block(block, _) => {
+                let vars_len = self.vars.len();
+                nodes[*block]
+                    .iter()
+                    .try_for_each(|stmt| self.compile_stmt(stmt))?;
+                self.vars.truncate(vars_len);
+                Some(())
+            }
+
+            Stmt::Let(name, Some(expr), _) => self.compile_let(name, self.expr(expr), false),
+            Stmt::Const(name, expr, _) => self.compile_let(name, self.expr(expr), true),
+
+            Stmt::IfThenElse(guard, if_body, else_body) => {
+                let guard = self.compile_bool(self.expr(guard))?;
+                let if_block = self.builder.create_block();
+                let else_block = self.builder.create_block();
+                let next = self.builder.create_block();
+
+                self.brif(guard, if_block, &[], else_block, &[]);
+
+                self.builder.switch_to_block(if_block);
+                self.compile_stmt(self.stmt(if_body))?;
+                self.jump(next, &[]);
+
+                self.builder.switch_to_block(else_block);
+                if let Some(stmt) = else_body {
+                    self.compile_stmt(self.stmt(stmt))?;
+                }
+                self.jump(next, &[]);
+
+                self.builder.switch_to_block(next);
+                Some(())
+            }
+
+            Stmt::While(guard, body, label) => {
+                let start = self.builder.create_block();
+                let body_block = self.builder.create_block();
+                let next = self.builder.create_block();
+
+                self.jump(start, &[]);
+                self.builder.switch_to_block(start);
+
+                let guard = self.compile_bool(self.expr(guard))?;
+                self.brif(guard, body_block, &[], next, &[]);
+
+                self.builder.switch_to_block(body_block);
+                self.compile_loop_body(self.stmt(body), label, start, next)
+            }
+
+            Stmt::Loop(body, label) => {
+                let start = self.builder.create_block();
+                let next = self.builder.create_block();
+
+                self.jump(start, &[]);
+                self.builder.switch_to_block(start);
+                self.compile_loop_body(self.stmt(body), label, start, next)
+            }
+
+            Stmt::Continue(label, _) => {
+                let (start, _) = self.find_loop(label)?;
+                self.jump(start, &[]);
+                self.switch_to_unreachable();
+                Some(())
+            }
+
+            Stmt::Break(label, _) => {
+                let (_, next) = self.find_loop(label)?;
+                self.jump(next, &[]);
+                self.switch_to_unreachable();
+                Some(())
+            }
+
+            Stmt::ReturnWithVal(Some(expr), ReturnType::Return, _) => {
+                self.compile_return(self.expr(expr))
+            }
+
+            // Returning () and throwing exceptions are left to the interpreter
+            Stmt::ReturnWithVal(_, _, _) => {
+                self.bail();
+                Some(())
+            }
+
+            _ => None,
+        }
+    }
+
+    /// Find where a `continue` or `break` with an optional label jumps to.
+    fn find_loop(&self, label: &Option<String>) -> Option<(Block, Block)> {
+        self.loops
+            .iter()
+            .rev()
+            .find(|(_, _, l)| label.is_none() || *l == label.as_deref())
+            .map(|&(start, next, _)| (start, next))
+    }
+
+    /// Compile the body of a loop.
+    fn compile_loop_body(
+        &mut self,
+        body: &'a Stmt,
+        label: &'a Option<String>,
+        start: Block,
+        next: Block,
+    ) -> Option<()> {
+        self.loops.push((start, next, label.as_deref()));
+        self.compile_stmt(body)?;
+        self.loops.pop();
+
+        self.jump(start, &[]);
+        self.builder.switch_to_block(next);
+        Some(())
+    }
+
+    /// Compile a `let` or `const` statement.
+    fn compile_let(&mut self, name: &'a str, expr: &'a Expr, is_const: bool) -> Option<()> {
+        let (value, t) = self.compile_expr(expr)?;
+        let var = self.declare_var(t);
+
+        self.builder.def_var(var, value);
+        self.vars.push((name, var, t, is_const));
+        Some(())
+    }
+
+    /// Compile an assignment to a variable.
+    fn compile_assignment(&mut self, lhs: &'a Expr, rhs: &'a Expr) -> Option<()> {
+        match lhs {
+            Expr::Variable(name, _) => {
+                let (value, t) = self.compile_expr(rhs)?;
+                let &(_, var, var_type, is_const) =
+                    self.vars.iter().rev().find(|(n, _, _, _)| n == name)?;
+
+                // Changing the type of a variable is left to the interpreter
+                if is_const || t != var_type {
+                    return None;
+                }
+
+                self.builder.def_var(var, value);
+                Some(())
+            }
+            _ => None,
+        }
+    }
+
+    /// Compile `name op= expr`, which is parsed into `name = name op expr`.
+    fn compile_op_assignment(&mut self, expr: &'a Expr) -> Option<()> {
+        let (name, op, arg_expr) = match expr {
+            Expr::Assignment(lhs, rhs, _) => match (self.expr(lhs), self.expr(rhs)) {
+                (Expr::Variable(name, _), Expr::FunctionCall(op, args, _, _, _)) => {
+                    (name, op, &self.fn_def.nodes[*args][1])
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        let (arg, arg_type) = self.compile_expr(arg_expr)?;
+        let &(_, var, var_type, is_const) =
+            self.vars.iter().rev().find(|(n, _, _, _)| n == name)?;
+
+        // The interpreter calls the op-assignment function (e.g. `+=`) instead, if there is one
+        let op_assignment = format!("{}=", op);
+        let arg_types = [var_type.type_id(), arg_type.type_id()];
+
+        if is_const || !(self.is_builtin)(&op_assignment, &arg_types) {
+            return None;
+        }
+
+        let x = self.builder.use_var(var);
+        let (value, t) = self.compile_operator(op, &[(x, var_type), (arg, arg_type)])?;
+
+        if t != var_type {
+            return None;
+        }
+
+        self.count(0, 1);
+        self.builder.def_var(var, value);
+        Some(())
+    }
+
+    /// Compile an expression which must be boolean.
+    fn compile_bool(&mut self, expr: &'a Expr) -> Option<Value> {
+        match self.compile_expr(expr)? {
+            (value, JitType::Bool) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Compile an expression.
+    fn compile_expr(&mut self, expr: &'a Expr) -> Option<(Value, JitType)> {
+        self.count(1, 0);
+
+        match expr {
+            Expr::IntegerConstant(i, _) => Some((
+                self.builder.ins().iconst(JitType::Int.ir(), *i as i64),
+                JitType::Int,
+            )),
+
+            #[cfg(not(feature = "no_float"))]
+            Expr::FloatConstant(f, _) => Some((self.builder.ins().f64const(*f), JitType::Float)),
+
+            Expr::True(_) => Some((self.builder.ins().iconst(types::I8, 1), JitType::Bool)),
+            Expr::False(_) => Some((self.builder.ins().iconst(types::I8, 0), JitType::Bool)),
+
+            Expr::Variable(name, _) => {
+                let &(_, var, t, _) = self.vars.iter().rev().find(|(n, _, _, _)| n == name)?;
+                Some((self.builder.use_var(var), t))
+            }
+
+            Expr::And(lhs, rhs, _) => {
+                self.compile_short_circuit(self.expr(lhs), self.expr(rhs), true)
+            }
+            Expr::Or(lhs, rhs, _) => {
+                self.compile_short_circuit(self.expr(lhs), self.expr(rhs), false)
+            }
+
+            Expr::FunctionCall(fn_name, args_expr_list, _, _, _) => {
+                let args = self.fn_def.nodes[*args_expr_list]
+                    .iter()
+                    .map(|expr| self.compile_expr(expr))
+                    .collect::<Option<Vec<_>>>()?;
+
+                self.count(0, 1);
+
+                if let Some(fn_def) = self.lib.get_function(fn_name, args.len()) {
+                    self.compile_call(fn_def, args)
+                } else {
+                    self.compile_operator(fn_name, &args)
+                }
+            }
+
+            _ => None,
+        }
+    }
+
+    /// Compile `&&` (`is_and` is `true`) or `||` (`is_and` is `false`).
+    fn compile_short_circuit(
+        &mut self,
+        lhs: &'a Expr,
+        rhs: &'a Expr,
+        is_and: bool,
+    ) -> Option<(Value, JitType)> {
+        let lhs = self.compile_bool(lhs)?;
+        let rhs_block = self.builder.create_block();
+        let next = self.builder.create_block();
+        self.builder.append_block_param(next, types::I8);
+
+        if is_and {
+            self.brif(lhs, rhs_block, &[], next, &[lhs]);
+        } else {
+            self.brif(lhs, next, &[lhs], rhs_block, &[]);
+        }
+
+        self.builder.switch_to_block(rhs_block);
+        let rhs = self.compile_bool(rhs)?;
+        self.jump(next, &[rhs]);
+
+        self.builder.switch_to_block(next);
+        Some((self.builder.block_params(next)[0], JitType::Bool))
+    }
+
+    /// Compile a call to a script-defined function.
+    fn compile_call(
+        &mut self,
+        fn_def: &'a FnDef,
+        args: Vec<(Value, JitType)>,
+    ) -> Option<(Value, JitType)> {
+        let arg_types: Vec<_> = args.iter().map(|(_, t)| *t).collect();
+
+        let (id, ret) = if ptr::eq(fn_def, self.fn_def) && arg_types == self.arg_types {
+            (self.id, self.ret)
+        } else {
+            let compiled = self
+                .jit
+                .get_compiled(self.lib, fn_def, arg_types, self.is_builtin)?;
+            (compiled.id, compiled.ret)
+        };
+
+        let module = self.jit.module.as_mut().unwrap();
+        let callee = module.declare_func_in_func(id, self.builder.func);
+
+        let levels = self.builder.ins().iadd_imm(self.levels, -1);
+        let mut call_args = vec![self.state, levels];
+        call_args.extend(args.into_iter().map(|(value, _)| value));
+
+        let call = self.builder.ins().call(callee, &call_args);
+        let result = self.builder.inst_results(call)[0];
+
+        // Bail out if the called function bailed out
+        let bail = self
+            .builder
+            .ins()
+            .load(types::I8, MemFlags::trusted(), self.state, 0);
+        self.bail_if(bail);
+
+        Some((result, ret))
+    }
+
+    /// Compile an operator.
+    fn compile_operator(
+        &mut self,
+        op: &str,
+        args: &[(Value, JitType)],
+    ) -> Option<(Value, JitType)> {
+        // Operators replaced by the host are left to the interpreter
+        let arg_types: Vec<_> = args.iter().map(|&(_, t)| t.type_id()).collect();
+
+        if !(self.is_builtin)(op, &arg_types) {
+            return None;
+        }
+
+        match args {
+            &[(x, JitType::Int)] => self.compile_int_unary(op, x),
+            #[cfg(not(feature = "no_float"))]
+            &[(x, JitType::Float)] => self.compile_float_unary(op, x),
+            &[(x, JitType::Bool)] if op == "!" => {
+                Some((self.builder.ins().bxor_imm(x, 1), JitType::Bool))
+            }
+            &[(x, JitType::Int), (y, JitType::Int)] => self.compile_int_op(op, x, y),
+            #[cfg(not(feature = "no_float"))]
+            &[(x, JitType::Float), (y, JitType::Float)] => self.compile_float_op(op, x, y),
+            &[(x, JitType::Bool), (y, JitType::Bool)] => {
+                let ins = self.builder.ins();
+
+                let value = match op {
+                    "==" => ins.icmp(IntCC::Equal, x, y),
+                    "!=" => ins.icmp(IntCC::NotEqual, x, y),
+                    "&" => ins.band(x, y),
+                    "|" => ins.bor(x, y),
+                    _ => return None,
+                };
+
+                Some((value, JitType::Bool))
+            }
+            _ => None,
+        }
+    }
+
+    /// Compile a unary integer operator.
+    fn compile_int_unary(&mut self, op: &str, x: Value) -> Option<(Value, JitType)> {
+        match op {
+            "-" => {
+                #[cfg(not(feature = "unchecked"))]
+                {
+                    let overflow = self
+                        .builder
+                        .ins()
+                        .icmp_imm(IntCC::Equal, x, INT::MIN as i64);
+                    self.bail_if(overflow);
+                }
+
+                Some((self.builder.ins().ineg(x), JitType::Int))
+            }
+            _ => None,
+        }
+    }
+
+    /// Compile a binary integer operator.
+    fn compile_int_op(&mut self, op: &str, x: Value, y: Value) -> Option<(Value, JitType)> {
+        let value = match op {
+            #[cfg(not(feature = "unchecked"))]
+            "+" | "-" | "*" => {
+                let (value, overflow) = match op {
+                    "+" => self.builder.ins().sadd_overflow(x, y),
+                    "-" => self.builder.ins().ssub_overflow(x, y),
+                    _ => self.builder.ins().smul_overflow(x, y),
+                };
+                self.bail_if(overflow);
+                value
+            }
+            #[cfg(feature = "unchecked")]
+            "+" => self.builder.ins().iadd(x, y),
+            #[cfg(feature = "unchecked")]
+            "-" => self.builder.ins().isub(x, y),
+            #[cfg(feature = "unchecked")]
+            "*" => self.builder.ins().imul(x, y),
+
+            "/" | "%" => {
+                // Division by zero and overflow are left to the interpreter
+                let zero = self.builder.ins().icmp_imm(IntCC::Equal, y, 0);
+                self.bail_if(zero);
+                let min = self
+                    .builder
+                    .ins()
+                    .icmp_imm(IntCC::Equal, x, INT::MIN as i64);
+                let minus_one = self.builder.ins().icmp_imm(IntCC::Equal, y, -1);
+                let overflow = self.builder.ins().band(min, minus_one);
+                self.bail_if(overflow);
+
+                if op == "/" {
+                    self.builder.ins().sdiv(x, y)
+                } else {
+                    self.builder.ins().srem(x, y)
+                }
+            }
+
+            "&" => self.builder.ins().band(x, y),
+            "|" => self.builder.ins().bor(x, y),
+            "^" => self.builder.ins().bxor(x, y),
+
+            _ => {
+                let cond = match op {
+                    "==" => IntCC::Equal,
+                    "!=" => IntCC::NotEqual,
+                    "<" => IntCC::SignedLessThan,
+                    "<=" => IntCC::SignedLessThanOrEqual,
+                    ">" => IntCC::SignedGreaterThan,
+                    ">=" => IntCC::SignedGreaterThanOrEqual,
+                    _ => return None,
+                };
+
+                return Some((self.builder.ins().icmp(cond, x, y), JitType::Bool));
+            }
+        };
+
+        Some((value, JitType::Int))
+    }
+
+    /// Compile a unary floating-point operator.
+    #[cfg(not(feature = "no_float"))]
+    fn compile_float_unary(&mut self, op: &str, x: Value) -> Option<(Value, JitType)> {
+        match op {
+            "-" => Some((self.builder.ins().fneg(x), JitType::Float)),
+            _ => None,
+        }
+    }
+
+    /// Compile a binary floating-point operator.
+    #[cfg(not(feature = "no_float"))]
+    fn compile_float_op(&mut self, op: &str, x: Value, y: Value) -> Option<(Value, JitType)> {
+        let ins = self.builder.ins();
+
+        let value = match op {
+            "+" => ins.fadd(x, y),
+            "-" => ins.fsub(x, y),
+            "*" => ins.fmul(x, y),
+            "/" => ins.fdiv(x, y),
+            _ => {
+                let cond = match op {
+                    "==" => FloatCC::Equal,
+                    "!=" => FloatCC::NotEqual,
+                    "<" => FloatCC::LessThan,
+                    "<=" => FloatCC::LessThanOrEqual,
+                    ">" => FloatCC::GreaterThan,
+                    ">=" => FloatCC::GreaterThanOrEqual,
+                    _ => return None,
+                };
+
+                return Some((ins.fcmp(cond, x, y), JitType::Bool));
+            }
+        };
+
+        Some((value, JitType::Float))
+    }
+}
+
+impl Engine<'_> {
+    /// Call a script-defined function via the JIT compiler.
+    ///
+    /// Returns `None` if the function cannot be compiled for the types of the arguments,
+    /// or if the compiled code bails out, in which case the function must be run by the interpreter.
+    pub(crate) fn call_jit_fn(
+        &mut self,
+        fn_def: &FnDef,
+        args: &FnCallArgs,
+        level: usize,
+    ) -> Option<Dynamic> {
+        // Calls must be audited, variable definitions must be checked and results of memoized
+        // functions must be cached by the interpreter
+        if self.on_call.is_some()
+            || self.capabilities.is_some()
+            || self.on_def_var.is_some()
+            || !self.memo.is_empty()
+        {
+            return None;
+        }
+
+        // Under the `unchecked` feature, compiled code wraps around on overflow
+        #[cfg(feature = "unchecked")]
+        {
+            if self.overflow_mode == OverflowMode::Saturating {
+                return None;
+            }
+        }
+
+        // Operators imported from modules are left to the interpreter
+        #[cfg(not(feature = "no_module"))]
+        {
+            if OPERATORS
+                .iter()
+                .any(|op| self.imports.functions.contains_key(*op))
+            {
+                return None;
+            }
+        }
+
+        let lib = self.fn_lib.clone()?;
+
+        let arg_types = args
+            .iter()
+            .map(|arg| JitType::of(&**arg))
+            .collect::<Option<Vec<_>>>()?;
+
+        // Take the compiler state out of the `Engine`, which is checked for replaced operators
+        let mut jit = mem::take(&mut self.jit);
+        let is_builtin = |op: &str, args: &[TypeId]| self.is_builtin_op(op, args);
+        let compiled = jit.get_compiled(&lib, fn_def, arg_types.clone(), &is_builtin);
+        self.jit = jit;
+        let compiled = compiled?;
+
+        let values: Vec<_> = args
+            .iter()
+            .zip(arg_types)
+            .map(|(arg, t)| t.encode(&**arg))
+            .collect();
+
+        let levels = (self.max_call_stack_depth - level) as i64;
+        let mut state = JitState::default();
+
+        // The entry point has the signature built by `build_entry`
+        let entry: EntryFn = unsafe { mem::transmute(compiled.entry as *const u8) };
+        let result = entry(values.as_ptr(), &mut state, levels);
+
+        if state.bail == 0 {
+            self.stats.operations += state.operations;
+            self.stats.fn_calls += state.fn_calls;
+            Some(compiled.ret.decode(result))
+        } else {
+            None
+        }
+    }
+}
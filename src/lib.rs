@@ -19,7 +19,7 @@
 //! ```,no_run
 //! use rhai::{Engine, EvalAltResult, RegisterFn};
 //!
-//! fn main() -> Result<(), EvalAltResult>
+//! fn main() -> Result<(), Box<EvalAltResult>>
 //! {
 //!     fn compute_something(x: i64) -> bool {
 //!         (x % 40) == 0
@@ -42,15 +42,19 @@
 //! | ------------- | -------------------------------------------------------------------------------------------------------------------------------------------------------- |
 //! | `no_stdlib`   | Exclude the standard library of utility functions in the build, and only include the minimum necessary functionalities. Standard types are not affected. |
 //! | `unchecked`   | Exclude arithmetic checking (such as overflows and division by zero). Beware that a bad script may panic the entire system!                              |
-//! | `no_function` | Disable script-defined functions if not needed.                                                                                                          |
+//! | `no_function` | Disable script-defined functions if not needed. Implies `no_module`.                                                                                     |
 //! | `no_index`    | Disable arrays and indexing features if not needed.                                                                                                      |
 //! | `no_object`   | Disable support for custom types and objects.                                                                                                            |
+//! | `no_module`   | Disable loading modules via `import` statements.                                                                                                         |
 //! | `no_float`    | Disable floating-point numbers and math if not needed.                                                                                                   |
 //! | `no_optimize` | Disable the script optimizer.                                                                                                                            |
 //! | `only_i32`    | Set the system integer type to `i32` and disable all other integer types. `INT` is set to `i32`.                                                         |
 //! | `only_i64`    | Set the system integer type to `i64` and disable all other integer types. `INT` is set to `i64`.                                                         |
 //! | `no_std`      | Build for `no-std`. Notice that additional dependencies will be pulled in to replace `std` features.                                                     |
 //! | `sync`        | Restrict all values types to those that are `Send + Sync`. Under this feature, `Engine`, `Scope` and `AST` are all `Send + Sync`.                        |
+//! | `jit`         | _Experimental_ - JIT-compile numeric script-defined functions to machine code via Cranelift. Not available under `no_std`.                               |
+//! | `wasm-bindgen` | Enable timestamp functions on `wasm32-unknown-unknown` via the browser's `performance.now()`.                                                           |
+//! | `unicode-xid-ident` | Allow Unicode letters and digits in identifiers, normalized to NFKC form.                                                                          |
 //!
 //! [Check out the README on GitHub for details on the Rhai language!](https://github.com/jonathandturner/rhai)
 
@@ -61,34 +65,109 @@ extern crate alloc;
 
 mod any;
 mod api;
+#[cfg(feature = "array_math")]
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_float"))]
+mod array_math;
+mod borrowed;
+mod builder;
 mod builtin;
 mod call;
+mod call_graph;
+#[cfg(feature = "datetime")]
+mod datetime;
+#[cfg(feature = "serde")]
+mod de;
+mod dialect;
 mod engine;
+mod engine_fns;
 mod error;
+mod event_handler;
 mod fn_register;
+mod format;
+#[cfg(feature = "hash")]
+mod hash;
+#[cfg(feature = "jit")]
+mod jit;
+#[cfg(feature = "matrix")]
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_float"))]
+mod matrix;
+mod memo;
+mod module;
 mod optimize;
 mod parser;
+mod pipeline;
+#[cfg(feature = "regex")]
+mod regex;
 mod result;
 mod scope;
+mod shared;
 mod stdlib;
+mod visitor;
+mod widening;
 
 pub use any::{Any, AnyExt, Dynamic, Variant};
+pub use builder::EngineBuilder;
+pub use builtin::OverflowMode;
 pub use call::FuncArgs;
-pub use engine::Engine;
-pub use error::{ParseError, ParseErrorType};
-pub use fn_register::{RegisterDynamicFn, RegisterFn, RegisterResultFn};
-pub use parser::{Position, AST, INT};
-pub use result::EvalAltResult;
+pub use call_graph::{CallGraph, FnCall};
+pub use dialect::Dialect;
+pub use engine::{Engine, EvalStats};
+pub use error::{LexError, ParseError, ParseErrorType};
+pub use fn_register::{
+    RegisterDynamicFn, RegisterFn, RegisterFnMut, RegisterFnOnce, RegisterResultFn,
+};
+pub use parser::{
+    Comment, CustomKeyword, Expr, ExprId, ExprList, FnDef, Nodes, Position, ReturnType, Shadowing,
+    Stmt, StmtId, StmtList, Token, AST, INT,
+};
+pub use pipeline::Pipeline;
+pub use result::{ArithmeticOp, EvalAltResult};
 pub use scope::Scope;
+pub use shared::{Shared, SharedValue};
+pub use visitor::{ASTNode, ASTVisitor};
 
 #[cfg(not(feature = "no_index"))]
-pub use engine::Array;
+pub use engine::{Array, Blob};
 
 #[cfg(not(feature = "no_object"))]
 pub use engine::Map;
 
+#[cfg(feature = "matrix")]
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_float"))]
+pub use matrix::Matrix;
+
+#[cfg(feature = "datetime")]
+pub use datetime::DateTime;
+
+#[cfg(feature = "serde")]
+pub use de::from_dynamic;
+
 #[cfg(not(feature = "no_float"))]
 pub use parser::FLOAT;
 
+#[cfg(not(feature = "no_function"))]
+pub use event_handler::ScriptedEventHandler;
+
+#[cfg(not(feature = "no_function"))]
+pub use parser::ScriptFnMetadata;
+
+#[cfg(not(feature = "no_module"))]
+pub use module::{
+    ModuleResolver, ModuleResolversCollection, NativeModule, SourceModuleResolver,
+    StaticModuleResolver,
+};
+
+#[cfg(not(feature = "no_module"))]
+#[cfg(not(feature = "no_std"))]
+pub use module::FileModuleResolver;
+
+#[cfg(not(feature = "no_module"))]
+#[cfg(not(feature = "no_std"))]
+#[cfg(feature = "http_resolver")]
+pub use module::HttpModuleResolver;
+
 #[cfg(not(feature = "no_optimize"))]
 pub use optimize::OptimizationLevel;
@@ -0,0 +1,258 @@
+//! Module which defines the `Matrix` type, a two-dimensional array of floating-point numbers
+//! backed by the `ndarray` crate, and registers its functions.
+
+use crate::any::{Any, Dynamic};
+use crate::engine::{Array, Engine, FUNC_TO_STRING, KEYWORD_DEBUG, KEYWORD_PRINT};
+use crate::fn_register::{RegisterFn, RegisterResultFn};
+use crate::parser::{Position, FLOAT, INT};
+use crate::result::EvalAltResult;
+
+use ndarray::Array2;
+
+use crate::stdlib::{
+    boxed::Box,
+    format,
+    ops::{Add, Div, Mul, Sub},
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// A matrix of floating-point numbers.
+///
+/// Not available under the `no_index` or `no_float` features.  Only available under the
+/// `matrix` feature.
+pub type Matrix = Array2<FLOAT>;
+
+/// Get a number as a floating-point number.
+fn to_float(value: &Dynamic) -> Option<FLOAT> {
+    value
+        .downcast_ref::<FLOAT>()
+        .copied()
+        .or_else(|| value.downcast_ref::<INT>().map(|&x| x as FLOAT))
+}
+
+/// Get an array of numbers as floating-point numbers.
+fn to_row(value: &Dynamic) -> Option<Vec<FLOAT>> {
+    value
+        .downcast_ref::<Array>()?
+        .iter()
+        .map(to_float)
+        .collect()
+}
+
+/// Make an arithmetic error.
+fn arithmetic_err(msg: String) -> Box<EvalAltResult> {
+    Box::new(EvalAltResult::ErrorArithmetic(msg, None, Position::none()))
+}
+
+/// The maximum number of elements in a matrix created by a script.
+const MAX_ELEMENTS: usize = 1 << 24;
+
+/// Make sure that a matrix dimension is not negative.
+fn to_dim(n: INT) -> Result<usize, Box<EvalAltResult>> {
+    if n < 0 {
+        Err(arithmetic_err(format!("Invalid matrix dimension: {}", n)))
+    } else {
+        Ok(n as usize)
+    }
+}
+
+/// Make sure that a matrix shape does not have too many elements.
+fn check_size(rows: usize, cols: usize) -> Result<(usize, usize), Box<EvalAltResult>> {
+    match rows.checked_mul(cols) {
+        Some(n) if n <= MAX_ELEMENTS => Ok((rows, cols)),
+        _ => Err(arithmetic_err(format!(
+            "Matrix of {} x {} exceeds the maximum of {} elements",
+            rows, cols, MAX_ELEMENTS
+        ))),
+    }
+}
+
+/// Get the shape of a new matrix of a number of rows and columns.
+fn to_shape(rows: INT, cols: INT) -> Result<(usize, usize), Box<EvalAltResult>> {
+    check_size(to_dim(rows)?, to_dim(cols)?)
+}
+
+/// Make sure that a position is within the bounds of a matrix dimension.
+fn check_bounds(len: usize, idx: INT) -> Result<usize, Box<EvalAltResult>> {
+    if idx >= 0 && (idx as usize) < len {
+        Ok(idx as usize)
+    } else {
+        Err(Box::new(EvalAltResult::ErrorArrayBounds(
+            len,
+            idx,
+            Position::none(),
+        )))
+    }
+}
+
+/// Make sure that two matrices have the same shape, for elementwise operations.
+fn check_same_shape(x: &Matrix, y: &Matrix) -> Result<(), Box<EvalAltResult>> {
+    if x.shape() == y.shape() {
+        Ok(())
+    } else {
+        Err(arithmetic_err(format!(
+            "Matrices have different shapes: {:?} and {:?}",
+            x.shape(),
+            y.shape()
+        )))
+    }
+}
+
+/// Build a matrix from an array of rows, each an array of numbers of the same length.
+fn from_rows(rows: &Array) -> Result<Matrix, Box<EvalAltResult>> {
+    let cols = match rows.first() {
+        Some(row) => row.downcast_ref::<Array>().map_or(0, |row| row.len()),
+        None => 0,
+    };
+
+    let mut items = Vec::with_capacity(rows.len() * cols);
+
+    for (index, row) in rows.iter().enumerate() {
+        match to_row(row) {
+            Some(row) if row.len() == cols => items.extend(row),
+            _ => {
+                return Err(arithmetic_err(format!(
+                    "Matrix row {} is not an array of {} numbers",
+                    index, cols
+                )))
+            }
+        }
+    }
+
+    Ok(Matrix::from_shape_vec((rows.len(), cols), items).expect("shape should match"))
+}
+
+/// Get a row of a matrix as an array of numbers.
+pub(crate) fn get_row(matrix: &Matrix, idx: usize) -> Array {
+    matrix
+        .row(idx)
+        .iter()
+        .map(|&x| Box::new(x) as Dynamic)
+        .collect()
+}
+
+/// Replace a row of a matrix with an array of numbers of the same length.
+pub(crate) fn set_row(
+    matrix: &mut Matrix,
+    idx: usize,
+    new_val: Dynamic,
+    pos: Position,
+) -> Result<(), Box<EvalAltResult>> {
+    match to_row(&new_val) {
+        Some(row) if row.len() == matrix.ncols() => {
+            matrix
+                .row_mut(idx)
+                .iter_mut()
+                .zip(row)
+                .for_each(|(x, y)| *x = y);
+            Ok(())
+        }
+        _ => Err(Box::new(EvalAltResult::ErrorArithmetic(
+            format!("Matrix row must be an array of {} numbers", matrix.ncols()),
+            None,
+            pos,
+        ))),
+    }
+}
+
+impl Engine<'_> {
+    /// Register the matrix package.
+    pub(crate) fn register_matrix(&mut self) {
+        // Constructors
+        self.register_result_fn("matrix", |rows: Array| from_rows(&rows));
+        self.register_result_fn("zeros", |rows: INT, cols: INT| {
+            Ok(Matrix::zeros(to_shape(rows, cols)?))
+        });
+        self.register_result_fn("ones", |rows: INT, cols: INT| {
+            Ok(Matrix::ones(to_shape(rows, cols)?))
+        });
+        self.register_result_fn("identity", |n: INT| Ok(Matrix::eye(to_shape(n, n)?.0)));
+
+        // Properties and conversions
+        self.register_fn("rows", |m: &mut Matrix| m.nrows() as INT);
+        self.register_fn("cols", |m: &mut Matrix| m.ncols() as INT);
+        self.register_fn("transpose", |m: &mut Matrix| {
+            m.t().as_standard_layout().into_owned()
+        });
+        self.register_fn("to_array", |m: &mut Matrix| {
+            (0..m.nrows())
+                .map(|i| Box::new(get_row(m, i)) as Dynamic)
+                .collect::<Array>()
+        });
+        self.register_result_fn("get", |m: &mut Matrix, i: INT, j: INT| {
+            let (i, j) = (check_bounds(m.nrows(), i)?, check_bounds(m.ncols(), j)?);
+            Ok(m[(i, j)])
+        });
+
+        macro_rules! reg_set {
+            ($($t:ty),*) => (
+                $(
+                    self.register_result_fn("set", |m: &mut Matrix, i: INT, j: INT, x: $t| {
+                        let (i, j) = (check_bounds(m.nrows(), i)?, check_bounds(m.ncols(), j)?);
+                        m[(i, j)] = x as FLOAT;
+                        Ok(())
+                    });
+                )*
+            )
+        }
+
+        reg_set!(FLOAT, INT);
+
+        self.register_result_fn("matmul", |x: &mut Matrix, y: Matrix| {
+            if x.ncols() == y.nrows() {
+                check_size(x.nrows(), y.ncols())?;
+                Ok(x.dot(&y))
+            } else {
+                Err(arithmetic_err(format!(
+                    "Cannot multiply matrices with shapes {:?} and {:?}",
+                    x.shape(),
+                    y.shape()
+                )))
+            }
+        });
+
+        // Elementwise operators
+        macro_rules! reg_op {
+            ($op:expr, $func:expr, $($scalar:ty),*) => (
+                self.register_result_fn($op, |x: &mut Matrix, y: Matrix| {
+                    check_same_shape(x, &y)?;
+                    Ok(Matrix::from_shape_fn(x.dim(), |ij| $func(x[ij], y[ij])))
+                });
+                $(
+                    self.register_fn($op, |x: &mut Matrix, y: $scalar| {
+                        x.mapv(|a| $func(a, y as FLOAT))
+                    });
+                    self.register_fn($op, |x: $scalar, y: Matrix| {
+                        y.mapv(|b| $func(x as FLOAT, b))
+                    });
+                )*
+            )
+        }
+
+        reg_op!("+", FLOAT::add, FLOAT, INT);
+        reg_op!("-", FLOAT::sub, FLOAT, INT);
+        reg_op!("*", FLOAT::mul, FLOAT, INT);
+        reg_op!("/", FLOAT::div, FLOAT, INT);
+
+        self.register_fn("-", |x: &mut Matrix| x.mapv(|a| -a));
+        self.register_fn("==", |x: &mut Matrix, y: Matrix| *x == y);
+        self.register_fn("!=", |x: &mut Matrix, y: Matrix| *x != y);
+
+        // Display
+        fn to_string(m: &mut Matrix) -> String {
+            m.to_string()
+        }
+
+        self.register_fn(KEYWORD_PRINT, to_string);
+        self.register_fn(FUNC_TO_STRING, to_string);
+        self.register_fn(KEYWORD_DEBUG, to_string);
+
+        // Iterate through the rows
+        self.register_iterator::<Matrix, _>(|m: &Dynamic| {
+            let m = m.downcast_ref::<Matrix>().unwrap().clone();
+            Box::new((0..m.nrows()).map(move |i| get_row(&m, i).into_dynamic()))
+                as Box<dyn Iterator<Item = Dynamic>>
+        });
+    }
+}
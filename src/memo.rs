@@ -0,0 +1,243 @@
+//! Module implementing the `memoize` function, which caches the results of script-defined functions
+//! by the values of their arguments.
+
+use crate::any::{Dynamic, Variant};
+use crate::engine::{FnCallArgs, FunctionsLib};
+use crate::parser::{FnDef, INT};
+
+#[cfg(not(feature = "no_index"))]
+use crate::engine::{Array, Blob};
+
+#[cfg(not(feature = "no_object"))]
+use crate::engine::Map;
+
+#[cfg(not(feature = "no_float"))]
+use crate::parser::FLOAT;
+
+use crate::stdlib::{collections::HashMap, string::String, vec::Vec};
+
+#[cfg(feature = "sync")]
+use crate::stdlib::sync::Arc;
+
+#[cfg(not(feature = "sync"))]
+use crate::stdlib::rc::Rc;
+
+#[cfg(feature = "sync")]
+type FnLib = Arc<FunctionsLib>;
+#[cfg(not(feature = "sync"))]
+type FnLib = Rc<FunctionsLib>;
+
+/// The maximum number of results cached during an evaluation, for all memoized functions together.
+const MAX_RESULTS: usize = 10_000;
+
+/// The maximum size of the arguments of a cached call, counting each value (including the items
+/// of arrays and object maps) and every 16 bytes of strings and BLOB's.
+const MAX_ARGS_SIZE: usize = 256;
+
+/// The maximum nesting depth of arrays and object maps in a key, so that making, hashing and
+/// dropping keys cannot exhaust the native stack.
+const MAX_DEPTH: usize = 32;
+
+/// Functions library and function definition of a memoized function, by address.
+type FnKey = (usize, usize);
+
+/// Cached results of the script-defined functions memoized during an evaluation.
+#[derive(Debug, Default)]
+pub(crate) struct MemoCache {
+    /// Functions libraries in which functions are memoized, kept alive so that the addresses of
+    /// their functions are not reused during the evaluation.
+    libs: Vec<FnLib>,
+    /// Cached results of each memoized function, by argument values.
+    results: HashMap<FnKey, HashMap<Vec<MemoKey>, Dynamic>>,
+    /// Total number of cached results.
+    len: usize,
+}
+
+impl MemoCache {
+    /// Memoize all functions of a name (with any number of parameters) in a functions library.
+    ///
+    /// Returns `false` if there is no function of that name.
+    pub fn memoize(&mut self, lib: &FnLib, name: &str) -> bool {
+        let mut found = false;
+
+        for fn_def in lib.iter().filter(|f| f.name == name) {
+            self.results.entry(fn_key(lib, fn_def)).or_default();
+            found = true;
+        }
+
+        if found && !self.libs.iter().any(|l| FnLib::ptr_eq(l, lib)) {
+            self.libs.push(lib.clone());
+        }
+
+        found
+    }
+
+    /// Get the key of a call to a function, if it is memoized and its arguments can be cached.
+    pub fn key(
+        &self,
+        lib: &FunctionsLib,
+        fn_def: &FnDef,
+        args: &FnCallArgs,
+    ) -> Option<(FnKey, Vec<MemoKey>)> {
+        let key = fn_key(lib, fn_def);
+
+        if !self.results.contains_key(&key) {
+            return None;
+        }
+
+        let mut size = 0;
+        let args = args
+            .iter()
+            .map(|arg| MemoKey::of_limited(&**arg, &mut size, MAX_ARGS_SIZE, true, 0))
+            .collect::<Option<_>>()?;
+
+        Some((key, args))
+    }
+
+    /// Get the cached result of a call.
+    pub fn get(&self, (key, args): &(FnKey, Vec<MemoKey>)) -> Option<&Dynamic> {
+        self.results.get(key)?.get(args)
+    }
+
+    /// Cache the result of a call, unless the maximum number of results is reached.
+    pub fn insert(&mut self, (key, args): (FnKey, Vec<MemoKey>), result: Dynamic) {
+        if self.len < MAX_RESULTS {
+            if let Some(cache) = self.results.get_mut(&key) {
+                cache.insert(args, result);
+                self.len += 1;
+            }
+        }
+    }
+
+    /// Are there no memoized functions?
+    #[cfg(feature = "jit")]
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Forget all memoized functions and their results.
+    pub fn clear(&mut self) {
+        *self = Default::default();
+    }
+}
+
+/// Get the key of a function in a functions library.
+fn fn_key(lib: &FunctionsLib, fn_def: &FnDef) -> FnKey {
+    (
+        lib as *const FunctionsLib as usize,
+        fn_def as *const FnDef as usize,
+    )
+}
+
+/// The value of an argument, as part of the key to a cached result.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub(crate) enum MemoKey {
+    Unit,
+    Bool(bool),
+    Char(char),
+    Int(INT),
+    /// A floating-point number, by its bits.
+    #[cfg(not(feature = "no_float"))]
+    Float(u64),
+    String(String),
+    #[cfg(not(feature = "no_index"))]
+    Array(Vec<MemoKey>),
+    #[cfg(not(feature = "no_index"))]
+    Blob(Vec<u8>),
+    #[cfg(not(feature = "no_object"))]
+    Map(Vec<(String, MemoKey)>),
+}
+
+impl MemoKey {
+    /// Make the key of an element of a set of values, or `None` if the element must be compared
+    /// by value instead (via `values_equal`).
+    ///
+    /// This is the case for values that cannot be cached, values nested more than `MAX_DEPTH`
+    /// levels deep, and values containing floating-point numbers, whose bits do not follow `==`
+    /// (e.g. `0.0 == -0.0`).
+    #[cfg(not(feature = "no_stdlib"))]
+    #[cfg(not(feature = "no_index"))]
+    pub fn of_element(value: &Variant) -> Option<Self> {
+        Self::of_limited(value, &mut 0, usize::MAX, false, 0)
+    }
+
+    /// Make the key of a value at a nesting depth, adding its size to a total, or `None` if values
+    /// of its type cannot be cached, the total would be over a limit or the value is nested more
+    /// than `MAX_DEPTH` levels deep.
+    ///
+    /// Floating-point numbers are keyed by their bits if `floats` is `true`, otherwise values
+    /// containing them have no key.
+    fn of_limited(
+        value: &Variant,
+        size: &mut usize,
+        limit: usize,
+        floats: bool,
+        depth: usize,
+    ) -> Option<Self> {
+        *size += 1;
+
+        if *size > limit || depth > MAX_DEPTH {
+            return None;
+        }
+
+        if value.is::<()>() {
+            return Some(MemoKey::Unit);
+        }
+        if let Some(&x) = value.downcast_ref::<bool>() {
+            return Some(MemoKey::Bool(x));
+        }
+        if let Some(&x) = value.downcast_ref::<char>() {
+            return Some(MemoKey::Char(x));
+        }
+        if let Some(&x) = value.downcast_ref::<INT>() {
+            return Some(MemoKey::Int(x));
+        }
+        #[cfg(not(feature = "no_float"))]
+        {
+            if let Some(&x) = value.downcast_ref::<FLOAT>() {
+                return Some(MemoKey::Float(x.to_bits())).filter(|_| floats);
+            }
+        }
+        if let Some(x) = value.downcast_ref::<String>() {
+            *size += x.len() / 16;
+            return if *size > limit {
+                None
+            } else {
+                Some(MemoKey::String(x.clone()))
+            };
+        }
+        #[cfg(not(feature = "no_index"))]
+        {
+            if let Some(x) = value.downcast_ref::<Array>() {
+                return x
+                    .iter()
+                    .map(|item| MemoKey::of_limited(&**item, size, limit, floats, depth + 1))
+                    .collect::<Option<_>>()
+                    .map(MemoKey::Array);
+            }
+            if let Some(x) = value.downcast_ref::<Blob>() {
+                *size += x.len() / 16;
+                return if *size > limit {
+                    None
+                } else {
+                    Some(MemoKey::Blob(x.clone()))
+                };
+            }
+        }
+        #[cfg(not(feature = "no_object"))]
+        {
+            if let Some(x) = value.downcast_ref::<Map>() {
+                return x
+                    .iter()
+                    .map(|(k, v)| {
+                        MemoKey::of_limited(&**v, size, limit, floats, depth + 1)
+                            .map(|v| (k.clone(), v))
+                    })
+                    .collect::<Option<_>>()
+                    .map(MemoKey::Map);
+            }
+        }
+
+        None
+    }
+}
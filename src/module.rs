@@ -0,0 +1,1462 @@
+//! Module that defines the module system: loading modules via `import` and resolving their paths.
+#![cfg(not(feature = "no_module"))]
+
+use crate::any::{Any, Dynamic};
+use crate::engine::{Engine, FnAny, FnSpec, FunctionsLib, SharedFnAny};
+
+#[cfg(not(feature = "no_index"))]
+use crate::engine::Array;
+
+#[cfg(not(feature = "no_object"))]
+use crate::engine::Map;
+use crate::error::{ParseError, ParseErrorType};
+use crate::parser::{CallSlot, Expr, FnDef, Nodes, Position, Stmt, AST};
+use crate::result::EvalAltResult;
+use crate::visitor::{walk_stmt, ASTNode, ASTVisitor};
+
+use crate::stdlib::{
+    any::TypeId,
+    boxed::Box,
+    collections::HashMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+#[cfg(feature = "sync")]
+use crate::stdlib::sync::Arc;
+
+#[cfg(not(feature = "sync"))]
+use crate::stdlib::rc::Rc;
+
+#[cfg(not(feature = "no_std"))]
+use crate::stdlib::{
+    fs::metadata,
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::stdlib::ops::DerefMut;
+
+#[cfg(feature = "sync")]
+use crate::stdlib::sync::Mutex;
+
+#[cfg(not(feature = "sync"))]
+use crate::stdlib::cell::RefCell;
+
+#[cfg(feature = "http_resolver")]
+#[cfg(not(feature = "no_std"))]
+use crate::stdlib::time::Duration;
+
+#[cfg(feature = "http_resolver")]
+#[cfg(not(feature = "no_std"))]
+use sha2::{Digest, Sha256};
+
+/// A module shared between all the scripts and modules importing it.
+#[cfg(feature = "sync")]
+pub(crate) type SharedModule = Arc<Module>;
+/// A module shared between all the scripts and modules importing it.
+#[cfg(not(feature = "sync"))]
+pub(crate) type SharedModule = Rc<Module>;
+
+/// A compiled module loaded via an `import` statement.
+#[derive(Debug)]
+pub(crate) struct Module {
+    /// Path of the module, as specified in the `import` statement.
+    pub path: String,
+    /// Script-defined functions in the module.
+    #[cfg(feature = "sync")]
+    pub functions: Arc<FunctionsLib>,
+    /// Script-defined functions in the module.
+    #[cfg(not(feature = "sync"))]
+    pub functions: Rc<FunctionsLib>,
+    /// Modules and functions imported by the module, used when running its functions.
+    pub imports: Imports,
+    /// Imported modules and functions re-exported by the module via `export` statements.
+    pub exports: Imports,
+}
+
+impl Module {
+    /// Does the module define (publicly) or re-export a function with a particular name (any number
+    /// of parameters)?
+    fn has_function_name(&self, name: &str) -> bool {
+        self.functions.iter().any(|f| f.name == name && !f.private)
+            || self.exports.functions.contains_key(name)
+    }
+
+    /// Collect all public functions defined or re-exported by the module, prefixing their names.
+    #[cfg(not(feature = "no_index"))]
+    #[cfg(not(feature = "no_object"))]
+    fn list_functions<'a>(&'a self, prefix: &str, list: &mut Vec<(String, &'a FnDef, &'a str)>) {
+        list.extend(self.functions.iter().filter(|f| !f.private).map(|f| {
+            (
+                format!("{}{}", prefix, f.name),
+                f.as_ref(),
+                self.path.as_str(),
+            )
+        }));
+        self.exports.list_functions(prefix, list);
+    }
+
+    /// Collect all public functions with a particular name (any number of parameters) defined or
+    /// re-exported by the module, under a new name.
+    #[cfg(not(feature = "no_index"))]
+    #[cfg(not(feature = "no_object"))]
+    fn list_functions_named<'a>(
+        &'a self,
+        name: &str,
+        alias: &str,
+        list: &mut Vec<(String, &'a FnDef, &'a str)>,
+    ) {
+        list.extend(
+            self.functions
+                .iter()
+                .filter(|f| f.name == name && !f.private)
+                .map(|f| (alias.to_string(), f.as_ref(), self.path.as_str())),
+        );
+
+        if let Some((orig_name, source)) = self.exports.functions.get(name) {
+            source.list_functions_named(orig_name, alias, list);
+        }
+    }
+}
+
+/// Find a public function (with a particular number of parameters) defined or re-exported by a module.
+/// Returns the module that defines the function, plus the name of the function in that module.
+fn resolve_function<'a>(
+    module: &'a SharedModule,
+    name: &'a str,
+    params: usize,
+) -> Option<(&'a SharedModule, &'a str)> {
+    match module.functions.get_function(name, params) {
+        Some(fn_def) if fn_def.private => None,
+        Some(_) => Some((module, name)),
+        None => {
+            let (orig_name, source) = module.exports.functions.get(name)?;
+            resolve_function(source, orig_name, params)
+        }
+    }
+}
+
+/// A collection of imported modules and functions.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Imports {
+    /// Modules, keyed by alias.
+    pub modules: HashMap<String, SharedModule>,
+    /// Functions, keyed by alias, together with their names in the modules they come from.
+    pub functions: HashMap<String, (String, SharedModule)>,
+}
+
+impl Imports {
+    /// Find an imported function, either namespace-qualified (e.g. `foo::bar::baz`)
+    /// or imported by name via `import ... from`.
+    /// Returns the module that defines the function, plus the name of the function in that module.
+    pub fn find_function<'a>(
+        &'a self,
+        fn_name: &'a str,
+        params: usize,
+    ) -> Option<(&'a SharedModule, &'a str)> {
+        match fn_name.rfind("::") {
+            Some(index) => {
+                let mut path = fn_name[..index].split("::");
+                let mut module = self.modules.get(path.next()?)?;
+
+                for name in path {
+                    module = module.exports.modules.get(name)?;
+                }
+
+                resolve_function(module, &fn_name[index + 2..], params)
+            }
+            None => {
+                let (orig_name, module) = self.functions.get(fn_name)?;
+                resolve_function(module, orig_name, params)
+            }
+        }
+    }
+
+    /// Collect all imported functions, together with their names (prefixed) and the paths of the modules
+    /// defining them.
+    #[cfg(not(feature = "no_index"))]
+    #[cfg(not(feature = "no_object"))]
+    pub fn list_functions<'a>(
+        &'a self,
+        prefix: &str,
+        list: &mut Vec<(String, &'a FnDef, &'a str)>,
+    ) {
+        for (alias, module) in &self.modules {
+            module.list_functions(&format!("{}{}::", prefix, alias), list);
+        }
+        for (alias, (orig_name, module)) in &self.functions {
+            module.list_functions_named(orig_name, &format!("{}{}", prefix, alias), list);
+        }
+    }
+
+    /// Re-export imported modules and functions into a collection of exports.
+    fn export(
+        &self,
+        exports: &mut Imports,
+        names: &[(String, String)],
+        pos: Position,
+    ) -> Result<(), Box<EvalAltResult>> {
+        for (name, alias) in names {
+            if let Some(module) = self.modules.get(name) {
+                exports.modules.insert(alias.clone(), module.clone());
+            } else if let Some(function) = self.functions.get(name) {
+                exports.functions.insert(alias.clone(), function.clone());
+            } else {
+                return Err(Box::new(EvalAltResult::ErrorFunctionNotFound(
+                    name.clone(),
+                    pos,
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A module referenced without keeping it alive.
+#[cfg(feature = "sync")]
+type WeakModule = crate::stdlib::sync::Weak<Module>;
+/// A module referenced without keeping it alive.
+#[cfg(not(feature = "sync"))]
+type WeakModule = crate::stdlib::rc::Weak<Module>;
+
+/// The target of a namespace-qualified function call (e.g. `m::f(x)`).
+struct CallTarget {
+    /// Module named by the first part of the path, which the call was resolved against.
+    root: WeakModule,
+    /// Module defining the function.
+    module: WeakModule,
+    /// Index of the function in the functions library of the module.
+    index: usize,
+}
+
+/// The target of a namespace-qualified function call, cached in the call expression itself.
+///
+/// Only weak references to the modules are kept, so the AST never keeps a module alive.
+/// A weak reference also keeps the address of its module from being reused, so comparing
+/// addresses is enough to tell whether the first part of the path still names the same module.
+#[derive(Default)]
+pub(crate) struct CachedCall(
+    #[cfg(feature = "sync")] Mutex<Option<CallTarget>>,
+    #[cfg(not(feature = "sync"))] RefCell<Option<CallTarget>>,
+);
+
+impl CachedCall {
+    /// Get mutable access to the cached target.
+    fn target(&self) -> impl DerefMut<Target = Option<CallTarget>> + '_ {
+        #[cfg(feature = "sync")]
+        return self.0.lock().unwrap();
+        #[cfg(not(feature = "sync"))]
+        return self.0.borrow_mut();
+    }
+
+    /// Get the module and function index cached for a call resolved against `root`.
+    fn get(&self, root: &SharedModule) -> Option<(SharedModule, usize)> {
+        match &*self.target() {
+            #[cfg(feature = "sync")]
+            Some(target) if target.root.as_ptr() == Arc::as_ptr(root) => {
+                Some((target.module.upgrade()?, target.index))
+            }
+            #[cfg(not(feature = "sync"))]
+            Some(target) if target.root.as_ptr() == Rc::as_ptr(root) => {
+                Some((target.module.upgrade()?, target.index))
+            }
+            _ => None,
+        }
+    }
+
+    /// Cache the module and function index found for a call resolved against `root`.
+    fn set(&self, root: &SharedModule, module: &SharedModule, index: usize) {
+        #[cfg(feature = "sync")]
+        let (root, module) = (Arc::downgrade(root), Arc::downgrade(module));
+        #[cfg(not(feature = "sync"))]
+        let (root, module) = (Rc::downgrade(root), Rc::downgrade(module));
+
+        *self.target() = Some(CallTarget {
+            root,
+            module,
+            index,
+        });
+    }
+}
+
+/// A module of constants, native functions and sub-modules provided by the host, registered into
+/// an `Engine` via `Engine::register_module`.
+///
+/// Scripts access its contents via namespace-qualified names, e.g. `math::consts::PI` or
+/// `math::abs(x)`, without importing it.
+///
+/// Not available under the `no_module` feature.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Engine, NativeModule, RegisterFn, INT};
+///
+/// let mut consts = NativeModule::new();
+/// consts.set_constant("ANSWER", 42 as INT);
+///
+/// let mut math = NativeModule::new();
+/// math.register_fn("double", |x: INT| x * 2);
+/// math.set_sub_module("consts", consts);
+///
+/// let mut engine = Engine::new();
+/// engine.register_module("math", math);
+///
+/// assert_eq!(engine.eval::<INT>("math::double(math::consts::ANSWER)")?, 84);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct NativeModule {
+    /// Constants, keyed by name.
+    constants: HashMap<String, Dynamic>,
+    /// Native functions, with their names and parameter types.
+    functions: Vec<(String, Vec<TypeId>, SharedFnAny)>,
+    /// Sub-modules, keyed by name.
+    sub_modules: HashMap<String, NativeModule>,
+}
+
+impl NativeModule {
+    /// Create a new, empty `NativeModule`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Add a constant to the module, replacing any existing constant with the same name.
+    pub fn set_constant<T: Any + Clone>(&mut self, name: &str, value: T) {
+        self.constants.insert(name.into(), Box::new(value));
+    }
+    /// Get the value of a constant in the module, or `None` if it does not exist or is not of type `T`.
+    pub fn get_constant<T: Any + Clone>(&self, name: &str) -> Option<T> {
+        self.constants
+            .get(name)
+            .and_then(|value| value.downcast_ref::<T>())
+            .cloned()
+    }
+    /// Add an array constant to the module, made of the items of a Rust collection (e.g. a static
+    /// table of item definitions), replacing any existing constant with the same name.
+    ///
+    /// The items are copied into the array once.  As arrays are copy-on-write, scripts then read
+    /// (e.g. index or iterate over) the constant without copying the whole array on each access.
+    ///
+    /// Not available under the `no_index` feature.
+    #[cfg(not(feature = "no_index"))]
+    pub fn set_array<T: Any + Clone>(&mut self, name: &str, items: impl IntoIterator<Item = T>) {
+        let array: Array = items
+            .into_iter()
+            .map(|item| Box::new(item) as Dynamic)
+            .collect();
+        self.set_constant(name, array);
+    }
+    /// Add an object map constant to the module, made of the entries of a Rust collection,
+    /// replacing any existing constant with the same name.
+    ///
+    /// The entries are copied into the object map once.  As object maps are copy-on-write,
+    /// scripts then read the constant without copying the whole object map on each access.
+    ///
+    /// Not available under the `no_object` feature.
+    #[cfg(not(feature = "no_object"))]
+    pub fn set_map<K: Into<String>, T: Any + Clone>(
+        &mut self,
+        name: &str,
+        entries: impl IntoIterator<Item = (K, T)>,
+    ) {
+        let map: Map = entries
+            .into_iter()
+            .map(|(key, value)| (key.into(), Box::new(value) as Dynamic))
+            .collect();
+        self.set_constant(name, map);
+    }
+    /// Does the module contain a constant with a particular name?
+    pub fn contains_constant(&self, name: &str) -> bool {
+        self.constants.contains_key(name)
+    }
+    /// Add a sub-module to the module, replacing any existing sub-module with the same name.
+    pub fn set_sub_module(&mut self, name: &str, module: NativeModule) {
+        self.sub_modules.insert(name.into(), module);
+    }
+    /// Get a sub-module of the module.
+    pub fn get_sub_module(&self, name: &str) -> Option<&NativeModule> {
+        self.sub_modules.get(name)
+    }
+    /// Get mutable access to a sub-module of the module.
+    pub fn get_sub_module_mut(&mut self, name: &str) -> Option<&mut NativeModule> {
+        self.sub_modules.get_mut(name)
+    }
+
+    pub(crate) fn register_fn_raw(&mut self, fn_name: &str, args: Vec<TypeId>, f: Box<FnAny>) {
+        self.functions.push((fn_name.into(), args, f.into()));
+    }
+
+    /// Register the contents of the module, and of its sub-modules, into an `Engine` under names
+    /// qualified by `path`.
+    pub(crate) fn register_into(self, engine: &mut Engine, path: &str) {
+        for (name, value) in self.constants {
+            engine
+                .globals
+                .insert(format!("{}::{}", path, name), value.into());
+        }
+
+        let functions = engine.functions_mut();
+
+        for (name, args, f) in self.functions {
+            let spec = FnSpec {
+                name: format!("{}::{}", path, name).into(),
+                args: args.into_iter().collect(),
+            };
+            functions.insert(spec, f);
+        }
+
+        for (name, module) in self.sub_modules {
+            module.register_into(engine, &format!("{}::{}", path, name));
+        }
+    }
+}
+
+impl Engine<'_> {
+    /// Load a module via the module resolver, then process its `import` and `export` statements.
+    ///
+    /// Only the functions defined by the module are kept, and all other top-level statements are ignored.
+    ///
+    /// `chain` holds the paths of the modules currently being loaded, which import one another in turn.
+    /// It is used to detect circular imports.
+    fn load_module(
+        &self,
+        path: &str,
+        pos: Position,
+        chain: &mut Vec<String>,
+    ) -> Result<SharedModule, Box<EvalAltResult>> {
+        if chain.iter().any(|p| p == path) {
+            let mut chain = chain.clone();
+            chain.push(path.to_string());
+            return Err(Box::new(EvalAltResult::ErrorCircularImport(chain, pos)));
+        }
+
+        let resolver = self
+            .module_resolver
+            .as_ref()
+            .ok_or_else(|| Box::new(EvalAltResult::ErrorModuleNotFound(path.to_string(), pos)))?;
+
+        let AST(statements, functions, _, _, _) = resolver.resolve(self, path, pos)?;
+
+        let mut imports = Imports::default();
+        let mut exports = Imports::default();
+
+        chain.push(path.to_string());
+
+        let result = statements.iter().try_for_each(|stmt| match stmt {
+            Stmt::Import(_, _, _) | Stmt::ImportFrom(_, _, _) => {
+                self.import_symbols_in_chain(&mut imports, stmt, chain)
+            }
+            Stmt::Export(names, pos) => imports.export(&mut exports, names, *pos),
+            _ => Ok(()),
+        });
+
+        chain.pop();
+        result?;
+
+        let module = Module {
+            path: path.to_string(),
+            functions,
+            imports,
+            exports,
+        };
+
+        #[cfg(feature = "sync")]
+        return Ok(Arc::new(module));
+        #[cfg(not(feature = "sync"))]
+        return Ok(Rc::new(module));
+    }
+
+    /// Run an `import` statement, adding the imported modules or functions into a collection of imports.
+    pub(crate) fn import_symbols(
+        &self,
+        imports: &mut Imports,
+        stmt: &Stmt,
+    ) -> Result<(), Box<EvalAltResult>> {
+        self.import_symbols_in_chain(imports, stmt, &mut Vec::new())
+    }
+
+    /// Run an `import` statement within a chain of modules being loaded.
+    fn import_symbols_in_chain(
+        &self,
+        imports: &mut Imports,
+        stmt: &Stmt,
+        chain: &mut Vec<String>,
+    ) -> Result<(), Box<EvalAltResult>> {
+        match stmt {
+            // import "path" as alias
+            Stmt::Import(path, alias, pos) => {
+                let module = self.load_module(path, *pos, chain)?;
+                imports.modules.insert(alias.clone(), module);
+            }
+
+            // import name [as alias], ... from "path"
+            Stmt::ImportFrom(names, path, pos) => {
+                let module = self.load_module(path, *pos, chain)?;
+
+                for (name, alias) in names {
+                    if let Some(sub_module) = module.exports.modules.get(name) {
+                        imports.modules.insert(alias.clone(), sub_module.clone());
+                    } else if module.has_function_name(name) {
+                        imports
+                            .functions
+                            .insert(alias.clone(), (name.clone(), module.clone()));
+                    } else {
+                        return Err(Box::new(EvalAltResult::ErrorFunctionNotFound(
+                            name.clone(),
+                            *pos,
+                        )));
+                    }
+                }
+            }
+
+            _ => panic!("statement should be import"),
+        }
+
+        Ok(())
+    }
+
+    /// Find the script-defined function of an imported module called by a namespace-qualified
+    /// function call, returning the module and the index of the function in its functions library.
+    ///
+    /// The function found is cached in the `slot` of the call expression, and reused by later
+    /// calls as long as the first part of the path still names the same loaded module (modules
+    /// do not change once loaded, but the same alias may be re-imported).
+    pub(crate) fn resolve_qualified_call(
+        &mut self,
+        slot: &CallSlot,
+        fn_name: &str,
+        params: usize,
+    ) -> Option<(SharedModule, usize)> {
+        let cached = slot.0.as_ref()?;
+        let root = self.imports.modules.get(&fn_name[..fn_name.find("::")?])?;
+
+        if let Some(target) = cached.get(root) {
+            self.stats.cached_calls += 1;
+            return Some(target);
+        }
+
+        let (module, name) = self.imports.find_function(fn_name, params)?;
+        let index = module
+            .functions
+            .binary_search_by(|f| f.compare(name, params))
+            .ok()?;
+
+        cached.set(root, module, index);
+
+        Some((module.clone(), index))
+    }
+
+    /// Load all the modules imported by a script, and make sure that all the names it imports,
+    /// exports or calls via the namespaces of imported modules exist.
+    /// This does nothing unless import validation is enabled.
+    pub(crate) fn check_imports(
+        &self,
+        statements: &[Stmt],
+        nodes: &Nodes,
+        functions: &[FnDef],
+    ) -> Result<(), ParseError> {
+        if !self.import_validation {
+            return Ok(());
+        }
+
+        let mut checker = ImportChecker::default();
+
+        for fn_def in functions {
+            walk_stmt(&fn_def.nodes, &fn_def.body, &mut checker);
+        }
+        for stmt in statements {
+            walk_stmt(nodes, stmt, &mut checker);
+        }
+
+        if checker.imports.is_empty() && checker.exports.is_empty() {
+            return Ok(());
+        }
+
+        // Modules are checked while they are loaded, so do not check them again when they are compiled
+        let mut engine = self.duplicate();
+        engine.import_validation = false;
+
+        let mut imports = Imports::default();
+
+        for stmt in &checker.imports {
+            engine
+                .import_symbols(&mut imports, stmt)
+                .map_err(|err| import_error(&err, stmt.position(nodes)))?;
+        }
+
+        for (names, pos) in &checker.exports {
+            imports
+                .export(&mut Imports::default(), names, *pos)
+                .map_err(|err| import_error(&err, *pos))?;
+        }
+
+        for (name, params, pos) in &checker.calls {
+            let alias = &name[..name.find("::").unwrap()];
+
+            // Namespaces which are not imported may be those of registered functions
+            let is_native = matches!(&self.functions, Some(functions)
+                if functions.keys().any(|spec| spec.name == *name && spec.args.len() == *params));
+
+            if imports.modules.contains_key(alias)
+                && imports.find_function(name, *params).is_none()
+                && !is_native
+            {
+                let message = format!(
+                    "function '{}' with {} parameter(s) is not found in module '{}'",
+                    name, params, alias
+                );
+                return Err(ParseErrorType::BadImport(message).into_err(*pos));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Turn an error loading the modules imported by a script into a parse error at the position of
+/// the `import` (or `export`) statement.
+fn import_error(err: &EvalAltResult, pos: Position) -> ParseError {
+    let message = match err {
+        EvalAltResult::ErrorModuleNotFound(path, p) if *p == pos => {
+            format!("module '{}' not found", path)
+        }
+        EvalAltResult::ErrorFunctionNotFound(name, p) if *p == pos => {
+            format!("'{}' is not found", name)
+        }
+        err => err.to_string(),
+    };
+
+    ParseErrorType::BadImport(message).into_err(pos)
+}
+
+/// Find the `import` and `export` statements, and the namespace-qualified function calls,
+/// of a script.
+#[derive(Default)]
+struct ImportChecker {
+    imports: Vec<Stmt>,
+    exports: Vec<(Vec<(String, String)>, Position)>,
+    calls: Vec<(String, usize, Position)>,
+}
+
+impl ASTVisitor for ImportChecker {
+    fn enter(&mut self, node: ASTNode, _nodes: &Nodes) -> bool {
+        match node {
+            ASTNode::Stmt(stmt @ Stmt::Import(_, _, _))
+            | ASTNode::Stmt(stmt @ Stmt::ImportFrom(_, _, _)) => self.imports.push(stmt.clone()),
+            ASTNode::Stmt(Stmt::Export(names, pos)) => self.exports.push((names.clone(), *pos)),
+            ASTNode::Expr(Expr::FunctionCall(name, args, _, _, pos)) if name.contains("::") => {
+                self.calls.push((name.to_string(), args.len(), *pos))
+            }
+            _ => (),
+        }
+        true
+    }
+}
+
+/// Trait that encapsulates a module resolution service, which turns the path in an `import`
+/// statement into a compiled module.
+///
+/// Not available under the `no_module` feature.
+#[cfg(feature = "sync")]
+pub trait ModuleResolver: Send + Sync {
+    /// Resolve a module path into an `AST`.
+    ///
+    /// `pos` is the position of the `import` statement, to be used in error messages.
+    fn resolve(
+        &self,
+        engine: &Engine,
+        path: &str,
+        pos: Position,
+    ) -> Result<AST, Box<EvalAltResult>>;
+}
+
+/// Trait that encapsulates a module resolution service, which turns the path in an `import`
+/// statement into a compiled module.
+///
+/// Not available under the `no_module` feature.
+#[cfg(not(feature = "sync"))]
+pub trait ModuleResolver {
+    /// Resolve a module path into an `AST`.
+    ///
+    /// `pos` is the position of the `import` statement, to be used in error messages.
+    fn resolve(
+        &self,
+        engine: &Engine,
+        path: &str,
+        pos: Position,
+    ) -> Result<AST, Box<EvalAltResult>>;
+}
+
+/// Cached compiled module files, keyed by file path, together with their last-modified times.
+#[cfg(not(feature = "no_std"))]
+type FileModuleCache = HashMap<PathBuf, (Option<SystemTime>, AST)>;
+
+/// Module resolution service that loads module script files, which is the default for an `Engine`.
+///
+/// The module path `foo/bar` in `import "foo/bar" as x;` resolves to the file `foo/bar.rhai`
+/// under the base directory.  Module paths which are absolute or contain `..` are not found,
+/// so scripts cannot load files outside the base directory.
+///
+/// Compiled module files are cached. By default, a cached module is recompiled when the last-modified
+/// time of its file changes, so that edited modules are picked up without recreating the `Engine`.
+/// Clones of a `FileModuleResolver` share the same cache, so a clone can be kept to clear
+/// the cache of a resolver that is already set into an `Engine`.
+///
+/// Not available under the `no_module` or `no_std` features.
+///
+/// # Example
+///
+/// ```
+/// use rhai::{Engine, FileModuleResolver};
+///
+/// let mut engine = Engine::new();
+///
+/// // Load modules from the 'scripts' directory with the '.rhai' extension
+/// let resolver = FileModuleResolver::new_with_path("scripts");
+///
+/// engine.set_module_resolver(Some(resolver.clone()));
+///
+/// // Later on... force all modules to be reloaded
+/// resolver.clear_cache();
+/// ```
+#[cfg(not(feature = "no_std"))]
+#[derive(Debug, Clone)]
+pub struct FileModuleResolver {
+    path: PathBuf,
+    extension: String,
+    cache_enabled: bool,
+    check_modified: bool,
+
+    #[cfg(feature = "sync")]
+    cache: Arc<Mutex<FileModuleCache>>,
+    #[cfg(not(feature = "sync"))]
+    cache: Rc<RefCell<FileModuleCache>>,
+}
+
+#[cfg(not(feature = "no_std"))]
+impl Default for FileModuleResolver {
+    fn default() -> Self {
+        Self::new_with_path(PathBuf::new())
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl FileModuleResolver {
+    /// Create a new `FileModuleResolver` which loads module files, with the `.rhai` extension,
+    /// relative to the current directory.
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Create a new `FileModuleResolver` which loads module files, with the `.rhai` extension,
+    /// relative to a base directory.
+    pub fn new_with_path<P: Into<PathBuf>>(path: P) -> Self {
+        Self::new_with_path_and_extension(path, "rhai")
+    }
+    /// Create a new `FileModuleResolver` which loads module files, with a particular extension,
+    /// relative to a base directory.
+    pub fn new_with_path_and_extension<P: Into<PathBuf>, E: Into<String>>(
+        path: P,
+        extension: E,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            extension: extension.into(),
+            cache_enabled: true,
+            check_modified: true,
+            cache: Default::default(),
+        }
+    }
+    /// Enable or disable caching of compiled module files (default enabled).
+    ///
+    /// When caching is disabled, module files are read and compiled every time they are imported.
+    pub fn enable_cache(&mut self, enable: bool) {
+        self.cache_enabled = enable;
+    }
+    /// Is caching of compiled module files enabled?
+    pub fn is_cache_enabled(&self) -> bool {
+        self.cache_enabled
+    }
+    /// Enable or disable checking the last-modified times of cached module files (default enabled).
+    ///
+    /// When checking is disabled, cached modules are always reused without touching the file system,
+    /// until the cache is cleared.
+    pub fn enable_modified_check(&mut self, enable: bool) {
+        self.check_modified = enable;
+    }
+    /// Is a module path cached?
+    pub fn is_cached(&self, path: &str) -> bool {
+        self.cache().contains_key(&self.get_file_path(path))
+    }
+    /// Remove a module path from the cache, so its file is recompiled the next time it is imported.
+    /// Returns `true` if the module path was cached.
+    pub fn clear_cache_for_path(&self, path: &str) -> bool {
+        self.cache().remove(&self.get_file_path(path)).is_some()
+    }
+    /// Empty the cache, so all module files are recompiled the next time they are imported.
+    pub fn clear_cache(&self) {
+        self.cache().clear();
+    }
+    /// Get the path of the file for a module path.
+    fn get_file_path(&self, path: &str) -> PathBuf {
+        let mut file_path = self.path.clone();
+        file_path.push(path);
+        file_path.set_extension(&self.extension);
+        file_path
+    }
+    /// Get mutable access to the cache.
+    fn cache(&self) -> impl DerefMut<Target = FileModuleCache> + '_ {
+        #[cfg(feature = "sync")]
+        return self.cache.lock().unwrap();
+        #[cfg(not(feature = "sync"))]
+        return self.cache.borrow_mut();
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl ModuleResolver for FileModuleResolver {
+    fn resolve(
+        &self,
+        engine: &Engine,
+        path: &str,
+        pos: Position,
+    ) -> Result<AST, Box<EvalAltResult>> {
+        // Scripts cannot load files outside the base directory
+        let is_inside = Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+
+        if !is_inside {
+            return Err(Box::new(EvalAltResult::ErrorModuleNotFound(
+                path.to_string(),
+                pos,
+            )));
+        }
+
+        let file_path = self.get_file_path(path);
+
+        let compile = |file_path: PathBuf| {
+            engine.compile_file(file_path).map_err(|err| match *err {
+                EvalAltResult::ErrorReadingScriptFile(_, _) => {
+                    Box::new(EvalAltResult::ErrorModuleNotFound(path.to_string(), pos))
+                }
+                _ => err,
+            })
+        };
+
+        if !self.cache_enabled {
+            return compile(file_path);
+        }
+
+        let modified = if self.check_modified {
+            metadata(&file_path).and_then(|m| m.modified()).ok()
+        } else {
+            None
+        };
+
+        // Reuse the cached module unless its file has since been modified
+        if let Some((cached_modified, ast)) = self.cache().get(&file_path) {
+            if !self.check_modified || *cached_modified == modified {
+                return Ok(ast.clone());
+            }
+        }
+
+        // Do not keep the cache locked during compilation
+        let ast = match compile(file_path.clone()) {
+            Ok(ast) => ast,
+            Err(err) => {
+                self.cache().remove(&file_path);
+                return Err(err);
+            }
+        };
+
+        self.cache().insert(file_path, (modified, ast.clone()));
+
+        Ok(ast)
+    }
+}
+
+/// Module resolution service that serves modules added to it programmatically.
+///
+/// Not available under the `no_module` feature.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Engine, StaticModuleResolver};
+///
+/// let mut engine = Engine::new();
+///
+/// let mut resolver = StaticModuleResolver::new();
+/// resolver.insert("math", engine.compile("fn double(x) { x * 2 }")?);
+///
+/// engine.set_module_resolver(Some(resolver));
+///
+/// assert_eq!(engine.eval::<i64>(r#"import "math" as m; m::double(21)"#)?, 42);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StaticModuleResolver(HashMap<String, AST>);
+
+impl StaticModuleResolver {
+    /// Create a new, empty `StaticModuleResolver`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Add a module, replacing any existing module with the same path.
+    pub fn insert<S: Into<String>>(&mut self, path: S, ast: AST) {
+        self.0.insert(path.into(), ast);
+    }
+    /// Remove a module.
+    pub fn remove(&mut self, path: &str) -> Option<AST> {
+        self.0.remove(path)
+    }
+    /// Does a module exist with a particular path?
+    pub fn contains_path(&self, path: &str) -> bool {
+        self.0.contains_key(path)
+    }
+}
+
+impl ModuleResolver for StaticModuleResolver {
+    fn resolve(&self, _: &Engine, path: &str, pos: Position) -> Result<AST, Box<EvalAltResult>> {
+        self.0
+            .get(path)
+            .cloned()
+            .ok_or_else(|| Box::new(EvalAltResult::ErrorModuleNotFound(path.to_string(), pos)))
+    }
+}
+
+/// Module resolution service that serves modules from their script source text, e.g. embedded in
+/// the application or defined by tests, without module files.
+///
+/// Each module is compiled the first time it is imported, then the compiled module is cached.
+///
+/// Not available under the `no_module` feature.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Engine, SourceModuleResolver};
+/// use std::collections::HashMap;
+///
+/// let mut sources = HashMap::new();
+/// sources.insert("math".to_string(), "fn double(x) { x * 2 }".to_string());
+///
+/// let mut engine = Engine::new();
+/// engine.set_module_resolver(Some(SourceModuleResolver::from(sources)));
+///
+/// assert_eq!(engine.eval::<i64>(r#"import "math" as m; m::double(21)"#)?, 42);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct SourceModuleResolver {
+    sources: HashMap<String, String>,
+
+    #[cfg(feature = "sync")]
+    cache: Mutex<HashMap<String, AST>>,
+    #[cfg(not(feature = "sync"))]
+    cache: RefCell<HashMap<String, AST>>,
+}
+
+impl SourceModuleResolver {
+    /// Create a new, empty `SourceModuleResolver`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Add the source of a module, replacing any existing module with the same path.
+    pub fn insert<P: Into<String>, S: Into<String>>(&mut self, path: P, source: S) {
+        let path = path.into();
+        self.cache().remove(&path);
+        self.sources.insert(path, source.into());
+    }
+    /// Remove a module, returning its source.
+    pub fn remove(&mut self, path: &str) -> Option<String> {
+        self.cache().remove(path);
+        self.sources.remove(path)
+    }
+    /// Does a module exist with a particular path?
+    pub fn contains_path(&self, path: &str) -> bool {
+        self.sources.contains_key(path)
+    }
+    /// Has a module been compiled (i.e. imported at least once)?
+    pub fn is_compiled(&self, path: &str) -> bool {
+        self.cache().contains_key(path)
+    }
+    /// Get mutable access to the cache of compiled modules.
+    fn cache(&self) -> impl DerefMut<Target = HashMap<String, AST>> + '_ {
+        #[cfg(feature = "sync")]
+        return self.cache.lock().unwrap();
+        #[cfg(not(feature = "sync"))]
+        return self.cache.borrow_mut();
+    }
+}
+
+impl Clone for SourceModuleResolver {
+    fn clone(&self) -> Self {
+        Self {
+            sources: self.sources.clone(),
+            cache: self.cache().clone().into(),
+        }
+    }
+}
+
+impl From<HashMap<String, String>> for SourceModuleResolver {
+    fn from(sources: HashMap<String, String>) -> Self {
+        Self {
+            sources,
+            cache: Default::default(),
+        }
+    }
+}
+
+impl ModuleResolver for SourceModuleResolver {
+    fn resolve(
+        &self,
+        engine: &Engine,
+        path: &str,
+        pos: Position,
+    ) -> Result<AST, Box<EvalAltResult>> {
+        let source = self
+            .sources
+            .get(path)
+            .ok_or_else(|| Box::new(EvalAltResult::ErrorModuleNotFound(path.to_string(), pos)))?;
+
+        if let Some(ast) = self.cache().get(path) {
+            return Ok(ast.clone());
+        }
+
+        // Do not keep the cache locked during compilation
+        let ast = engine
+            .compile(source)
+            .map_err(|err| Box::new(EvalAltResult::ErrorParsing(err)))?;
+
+        self.cache().insert(path.to_string(), ast.clone());
+
+        Ok(ast)
+    }
+}
+
+/// Module resolution service that tries a list of module resolution services in order,
+/// returning the first module found.
+///
+/// Only a module that cannot be found by one resolver is looked up in the next one;
+/// any other error (e.g. a syntax error in a module file) is returned immediately.
+///
+/// Not available under the `no_module` feature.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Engine, ModuleResolversCollection, StaticModuleResolver};
+/// # #[cfg(not(feature = "no_std"))]
+/// use rhai::FileModuleResolver;
+///
+/// let mut engine = Engine::new();
+///
+/// let mut standard = StaticModuleResolver::new();
+/// standard.insert("math", engine.compile("fn double(x) { x * 2 }")?);
+///
+/// // Modules on disk override the standard ones
+/// let mut resolvers = ModuleResolversCollection::new();
+/// # #[cfg(not(feature = "no_std"))]
+/// resolvers.push(FileModuleResolver::new_with_path("scripts"));
+/// resolvers.push(standard);
+///
+/// engine.set_module_resolver(Some(resolvers));
+///
+/// assert_eq!(engine.eval::<i64>(r#"import "math" as m; m::double(21)"#)?, 42);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct ModuleResolversCollection(Vec<Box<dyn ModuleResolver>>);
+
+impl ModuleResolversCollection {
+    /// Create a new, empty `ModuleResolversCollection`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Add a module resolution service to the end of the list.
+    pub fn push(&mut self, resolver: impl ModuleResolver + 'static) {
+        self.0.push(Box::new(resolver));
+    }
+    /// Get the number of module resolution services in the list.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Is the list empty?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Remove all module resolution services.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+impl ModuleResolver for ModuleResolversCollection {
+    fn resolve(
+        &self,
+        engine: &Engine,
+        path: &str,
+        pos: Position,
+    ) -> Result<AST, Box<EvalAltResult>> {
+        for resolver in &self.0 {
+            match resolver.resolve(engine, path, pos) {
+                Err(err) if matches!(*err, EvalAltResult::ErrorModuleNotFound(_, _)) => (),
+                result => return result,
+            }
+        }
+
+        Err(Box::new(EvalAltResult::ErrorModuleNotFound(
+            path.to_string(),
+            pos,
+        )))
+    }
+}
+
+/// Cached compiled modules fetched by an `HttpModuleResolver`, keyed by URL, together with the
+/// SHA-256 checksums of their sources.
+#[cfg(feature = "http_resolver")]
+type HttpModuleCache = HashMap<String, (AST, String)>;
+
+/// Function fetching the source of a module from a URL.
+#[cfg(feature = "http_resolver")]
+#[cfg(feature = "sync")]
+type HttpFetchFn = dyn Fn(&str) -> Result<Option<String>, String> + Send + Sync;
+/// Function fetching the source of a module from a URL.
+#[cfg(feature = "http_resolver")]
+#[cfg(not(feature = "sync"))]
+type HttpFetchFn = dyn Fn(&str) -> Result<Option<String>, String>;
+
+/// Function approving the URL of a module before it is fetched.
+#[cfg(feature = "http_resolver")]
+#[cfg(feature = "sync")]
+type HttpApproveFn = dyn Fn(&str, &str) -> bool + Send + Sync;
+/// Function approving the URL of a module before it is fetched.
+#[cfg(feature = "http_resolver")]
+#[cfg(not(feature = "sync"))]
+type HttpApproveFn = dyn Fn(&str, &str) -> bool;
+
+/// Module resolution service that fetches module scripts over HTTPS.
+///
+/// A module path that is an `https://` URL is fetched as is.  Other module paths are mapped to URLs
+/// via prefixes: after `map_prefix("pkg://", "https://example.com/modules/")`, the module path
+/// `pkg://vendor/util` in `import "pkg://vendor/util" as util;` is fetched from the URL
+/// `https://example.com/modules/vendor/util.rhai`.  Module paths matching no prefix are not found,
+/// so other resolvers can be tried via `ModuleResolversCollection`.  Mapped paths cannot leave the
+/// base URL (e.g. via `..`), and modules are never fetched over plain HTTP.
+///
+/// To load only trusted code:
+///
+/// * `pin` pins a module path to the SHA-256 checksum of its source, which must match before the
+///   module is compiled.  `require_pins(true)` refuses to load any module that is not pinned.
+/// * `on_approve` sets a callback, taking the module path and URL, which approves each import of
+///   a module (cached or not) before it is fetched.
+///
+/// Compiled modules are cached, so each module is only fetched once.  Pins are also checked
+/// against cached modules, whichever module path they were fetched for.  Clones of an
+/// `HttpModuleResolver` share the same cache.
+///
+/// Not available under the `no_module` or `no_std` features.  Requires the `http_resolver` feature.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Engine, HttpModuleResolver};
+///
+/// let mut resolver = HttpModuleResolver::new();
+/// resolver.map_prefix("pkg://", "https://example.com/modules/");
+/// resolver.pin(
+///     "pkg://vendor/util",
+///     "96f1d05bdd033feb79573b17b0c818198e2c8161ef02deeb682a17db37578369",
+/// );
+/// resolver.require_pins(true);
+/// resolver.on_approve(|_path, url| url.starts_with("https://example.com/"));
+///
+/// let mut engine = Engine::new();
+/// engine.set_module_resolver(Some(resolver));
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "http_resolver")]
+#[cfg(not(feature = "no_std"))]
+#[derive(Clone)]
+pub struct HttpModuleResolver {
+    prefixes: Vec<(String, String)>,
+    extension: String,
+    pins: HashMap<String, String>,
+    pins_required: bool,
+    cache_enabled: bool,
+
+    #[cfg(feature = "sync")]
+    fetch: Option<Arc<HttpFetchFn>>,
+    #[cfg(not(feature = "sync"))]
+    fetch: Option<Rc<HttpFetchFn>>,
+
+    #[cfg(feature = "sync")]
+    approve: Option<Arc<HttpApproveFn>>,
+    #[cfg(not(feature = "sync"))]
+    approve: Option<Rc<HttpApproveFn>>,
+
+    #[cfg(feature = "sync")]
+    cache: Arc<Mutex<HttpModuleCache>>,
+    #[cfg(not(feature = "sync"))]
+    cache: Rc<RefCell<HttpModuleCache>>,
+}
+
+#[cfg(feature = "http_resolver")]
+#[cfg(not(feature = "no_std"))]
+impl Default for HttpModuleResolver {
+    fn default() -> Self {
+        Self {
+            prefixes: Vec::new(),
+            extension: "rhai".into(),
+            pins: HashMap::new(),
+            pins_required: false,
+            cache_enabled: true,
+            fetch: None,
+            approve: None,
+            cache: Default::default(),
+        }
+    }
+}
+
+#[cfg(feature = "http_resolver")]
+#[cfg(not(feature = "no_std"))]
+impl HttpModuleResolver {
+    /// Create a new `HttpModuleResolver` which fetches modules whose paths are `https://` URLs.
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Map module paths starting with a prefix (e.g. `pkg://`) to URLs under a base URL
+    /// (e.g. `https://example.com/modules/`), appending the extension (`.rhai` by default).
+    pub fn map_prefix<P: Into<String>, U: Into<String>>(&mut self, prefix: P, base_url: U) {
+        self.prefixes.push((prefix.into(), base_url.into()));
+    }
+    /// Set the extension appended to the URLs of module paths mapped via prefixes.
+    pub fn set_extension<E: Into<String>>(&mut self, extension: E) {
+        self.extension = extension.into();
+    }
+    /// Pin a module path to the SHA-256 checksum (as hex text) of its source.
+    /// A module whose source does not match the checksum fails to load.
+    ///
+    /// The checksum is verified on every import, also when the module is taken from the cache.
+    pub fn pin<P: Into<String>, H: Into<String>>(&mut self, path: P, sha256: H) {
+        self.pins.insert(path.into(), sha256.into().to_lowercase());
+    }
+    /// Control whether modules that are not pinned (via `pin`) are refused (default not).
+    pub fn require_pins(&mut self, require: bool) {
+        self.pins_required = require;
+    }
+    /// Set a callback approving each import of a module, before the module is fetched (or taken
+    /// from the cache).  It is called with the module path and its URL, and returns `false` to
+    /// refuse the module.
+    #[cfg(feature = "sync")]
+    pub fn on_approve(&mut self, callback: impl Fn(&str, &str) -> bool + Send + Sync + 'static) {
+        self.approve = Some(Arc::new(callback));
+    }
+    /// Set a callback approving each import of a module, before the module is fetched (or taken
+    /// from the cache).  It is called with the module path and its URL, and returns `false` to
+    /// refuse the module.
+    #[cfg(not(feature = "sync"))]
+    pub fn on_approve(&mut self, callback: impl Fn(&str, &str) -> bool + 'static) {
+        self.approve = Some(Rc::new(callback));
+    }
+    /// Replace the function fetching the source of a module from a URL (e.g. to add authentication,
+    /// or to use another HTTP client).  It returns `Ok(None)` if there is no module at the URL.
+    ///
+    /// By default, modules are fetched with a plain HTTPS `GET` request.
+    #[cfg(feature = "sync")]
+    pub fn set_fetcher(
+        &mut self,
+        fetch: impl Fn(&str) -> Result<Option<String>, String> + Send + Sync + 'static,
+    ) {
+        self.fetch = Some(Arc::new(fetch));
+    }
+    /// Replace the function fetching the source of a module from a URL (e.g. to add authentication,
+    /// or to use another HTTP client).  It returns `Ok(None)` if there is no module at the URL.
+    ///
+    /// By default, modules are fetched with a plain HTTPS `GET` request.
+    #[cfg(not(feature = "sync"))]
+    pub fn set_fetcher(
+        &mut self,
+        fetch: impl Fn(&str) -> Result<Option<String>, String> + 'static,
+    ) {
+        self.fetch = Some(Rc::new(fetch));
+    }
+    /// Enable or disable caching of compiled modules (default enabled).
+    ///
+    /// When caching is disabled, modules are fetched and compiled every time they are imported.
+    pub fn enable_cache(&mut self, enable: bool) {
+        self.cache_enabled = enable;
+    }
+    /// Empty the cache, so all modules are fetched again the next time they are imported.
+    pub fn clear_cache(&self) {
+        self.cache().clear();
+    }
+    /// Get the URL of a module path, or `None` if the path is not handled by this resolver.
+    ///
+    /// A path mapped via a prefix has no URL if it could leave the base URL: its segments cannot
+    /// be empty, `.` or `..`, nor contain `\`, `%`, `?` or `#`.
+    pub fn get_url(&self, path: &str) -> Option<String> {
+        if path.starts_with("https://") {
+            return Some(path.to_string());
+        }
+
+        let (prefix, base_url) = self.find_prefix(path)?;
+        let sub_path = &path[prefix.len()..];
+
+        let is_valid = sub_path.split('/').all(|segment| {
+            !matches!(segment, "" | "." | "..") && !segment.contains(['\\', '%', '?', '#'])
+        });
+
+        if !is_valid {
+            return None;
+        }
+
+        // Keep the path under the base URL even if it does not end with a '/'
+        let separator = if base_url.ends_with('/') { "" } else { "/" };
+
+        Some(format!(
+            "{}{}{}.{}",
+            base_url, separator, sub_path, self.extension
+        ))
+    }
+    /// Find the prefix (and its base URL) mapping a module path.
+    fn find_prefix(&self, path: &str) -> Option<&(String, String)> {
+        self.prefixes
+            .iter()
+            .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+    }
+    /// Get mutable access to the cache.
+    fn cache(&self) -> impl DerefMut<Target = HttpModuleCache> + '_ {
+        #[cfg(feature = "sync")]
+        return self.cache.lock().unwrap();
+        #[cfg(not(feature = "sync"))]
+        return self.cache.borrow_mut();
+    }
+}
+
+/// Fetch the source of a module with an HTTPS `GET` request.
+#[cfg(feature = "http_resolver")]
+#[cfg(not(feature = "no_std"))]
+fn http_get(url: &str) -> Result<Option<String>, String> {
+    let agent = ureq::AgentBuilder::new()
+        .https_only(true)
+        .timeout(Duration::from_secs(30))
+        .build();
+
+    match agent.get(url).call() {
+        Ok(response) => response
+            .into_string()
+            .map(Some)
+            .map_err(|err| err.to_string()),
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[cfg(feature = "http_resolver")]
+#[cfg(not(feature = "no_std"))]
+impl ModuleResolver for HttpModuleResolver {
+    fn resolve(
+        &self,
+        engine: &Engine,
+        path: &str,
+        pos: Position,
+    ) -> Result<AST, Box<EvalAltResult>> {
+        let error = |msg: String| Box::new(EvalAltResult::ErrorRuntime(msg, pos));
+        let not_found = || Box::new(EvalAltResult::ErrorModuleNotFound(path.to_string(), pos));
+
+        let url = match self.get_url(path) {
+            Some(url) => url,
+            None if self.find_prefix(path).is_some() => {
+                return Err(error(format!(
+                    "Module path '{}' is not a valid path under its base URL",
+                    path
+                )))
+            }
+            None => return Err(not_found()),
+        };
+
+        if !url.starts_with("https://") {
+            return Err(error(format!(
+                "Module '{}' must be fetched over HTTPS instead of from {}",
+                path, url
+            )));
+        }
+
+        let pin = self.pins.get(path);
+
+        if pin.is_none() && self.pins_required {
+            return Err(error(format!(
+                "Module '{}' is not pinned to a checksum",
+                path
+            )));
+        }
+
+        if matches!(&self.approve, Some(approve) if !approve(path, &url)) {
+            return Err(error(format!(
+                "Module '{}' from {} is not approved",
+                path, url
+            )));
+        }
+
+        // A module cached for another path (or before the pin was set) is fetched again
+        if self.cache_enabled {
+            if let Some((ast, checksum)) = self.cache().get(&url) {
+                if pin.is_none() || pin == Some(checksum) {
+                    return Ok(ast.clone());
+                }
+            }
+        }
+
+        // Do not keep the cache locked while fetching
+        let source = match &self.fetch {
+            Some(fetch) => fetch(&url),
+            None => http_get(&url),
+        }
+        .map_err(|err| {
+            error(format!(
+                "Cannot fetch module '{}' from {}: {}",
+                path, url, err
+            ))
+        })?
+        .ok_or_else(not_found)?;
+
+        let checksum: String = Sha256::digest(source.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+
+        if let Some(pin) = pin {
+            if checksum != *pin {
+                return Err(error(format!(
+                    "Checksum of module '{}' is {} instead of {}",
+                    path, checksum, pin
+                )));
+            }
+        }
+
+        let ast = engine
+            .compile(&source)
+            .map_err(|err| Box::new(EvalAltResult::ErrorParsing(err)))?;
+
+        if self.cache_enabled {
+            self.cache().insert(url, (ast.clone(), checksum));
+        }
+
+        Ok(ast)
+    }
+}
@@ -2,21 +2,30 @@
 
 use crate::any::{Any, Dynamic};
 use crate::engine::{
-    Engine, FunctionsLib, KEYWORD_DEBUG, KEYWORD_DUMP_AST, KEYWORD_EVAL, KEYWORD_PRINT,
-    KEYWORD_TYPE_OF,
+    Engine, FunctionsLib, KEYWORD_DEBUG, KEYWORD_DUMP_AST, KEYWORD_EVAL, KEYWORD_FN_METADATA_LIST,
+    KEYWORD_IS_DEF_FN, KEYWORD_IS_DEF_VAR, KEYWORD_PRINT, KEYWORD_TYPE_OF,
+};
+use crate::parser::{
+    map_dynamic_to_expr, Expr, ExprId, ExprList, FnDef, Nodes, Position, ReturnType, Stmt, StmtId,
+    AST,
 };
-use crate::parser::{map_dynamic_to_expr, Expr, FnDef, ReturnType, Stmt, AST};
 use crate::scope::{Entry as ScopeEntry, EntryType as ScopeEntryType, Scope};
+use crate::visitor::{walk_stmt, ASTNode, ASTVisitor};
 
 use crate::stdlib::{
-    boxed::Box,
-    rc::Rc,
+    collections::HashSet,
+    slice,
     string::{String, ToString},
-    sync::Arc,
     vec,
     vec::Vec,
 };
 
+#[cfg(feature = "sync")]
+use crate::stdlib::sync::Arc;
+
+#[cfg(not(feature = "sync"))]
+use crate::stdlib::rc::Rc;
+
 /// Level of optimization performed.
 ///
 /// Not available under the `no_optimize` feature.
@@ -39,15 +48,18 @@ struct State<'a> {
     constants: Vec<(String, Expr)>,
     /// An `Engine` instance for eager function evaluation.
     engine: &'a Engine<'a>,
+    /// Child nodes of the statements being optimized.
+    nodes: &'a mut Nodes,
 }
 
 impl<'a> State<'a> {
     /// Create a new State.
-    pub fn new(engine: &'a Engine<'a>) -> Self {
+    pub fn new(engine: &'a Engine<'a>, nodes: &'a mut Nodes) -> Self {
         Self {
             changed: false,
             constants: vec![],
             engine,
+            nodes,
         }
     }
     /// Reset the state from dirty to clean.
@@ -90,98 +102,110 @@ impl<'a> State<'a> {
 fn optimize_stmt<'a>(stmt: Stmt, state: &mut State<'a>, preserve_result: bool) -> Stmt {
     match stmt {
         // if expr { Noop }
-        Stmt::IfThenElse(expr, if_block, None) if matches!(*if_block, Stmt::Noop(_)) => {
+        Stmt::IfThenElse(expr, if_block, None)
+            if matches!(state.nodes[if_block], Stmt::Noop(_)) =>
+        {
             state.set_dirty();
 
-            let pos = expr.position();
-            let expr = optimize_expr(*expr, state);
+            let pos = state.nodes[expr].position(state.nodes);
+            let expr = optimize_expr_at(expr, state);
 
             if preserve_result {
                 // -> { expr, Noop }
-                Stmt::Block(vec![Stmt::Expr(Box::new(expr)), *if_block], pos)
+                let if_block = state.nodes.take_stmt(if_block);
+                Stmt::Block(state.nodes.add_stmts(vec![Stmt::Expr(expr), if_block]), pos)
             } else {
                 // -> expr
-                Stmt::Expr(Box::new(expr))
+                Stmt::Expr(expr)
             }
         }
         // if expr { if_block }
-        Stmt::IfThenElse(expr, if_block, None) => match *expr {
+        Stmt::IfThenElse(expr, if_block, None) => match state.nodes[expr] {
             // if false { if_block } -> Noop
             Expr::False(pos) => {
                 state.set_dirty();
                 Stmt::Noop(pos)
             }
             // if true { if_block } -> if_block
-            Expr::True(_) => optimize_stmt(*if_block, state, true),
+            Expr::True(_) => optimize_stmt(state.nodes.take_stmt(if_block), state, true),
             // if expr { if_block }
-            expr => Stmt::IfThenElse(
-                Box::new(optimize_expr(expr, state)),
-                Box::new(optimize_stmt(*if_block, state, true)),
+            _ => Stmt::IfThenElse(
+                optimize_expr_at(expr, state),
+                optimize_stmt_at(if_block, state, true),
                 None,
             ),
         },
         // if expr { if_block } else { else_block }
-        Stmt::IfThenElse(expr, if_block, Some(else_block)) => match *expr {
+        Stmt::IfThenElse(expr, if_block, Some(else_block)) => match state.nodes[expr] {
             // if false { if_block } else { else_block } -> else_block
-            Expr::False(_) => optimize_stmt(*else_block, state, true),
+            Expr::False(_) => optimize_stmt(state.nodes.take_stmt(else_block), state, true),
             // if true { if_block } else { else_block } -> if_block
-            Expr::True(_) => optimize_stmt(*if_block, state, true),
+            Expr::True(_) => optimize_stmt(state.nodes.take_stmt(if_block), state, true),
             // if expr { if_block } else { else_block }
-            expr => Stmt::IfThenElse(
-                Box::new(optimize_expr(expr, state)),
-                Box::new(optimize_stmt(*if_block, state, true)),
-                match optimize_stmt(*else_block, state, true) {
+            _ => Stmt::IfThenElse(
+                optimize_expr_at(expr, state),
+                optimize_stmt_at(if_block, state, true),
+                match optimize_stmt(state.nodes.take_stmt(else_block), state, true) {
                     stmt if matches!(stmt, Stmt::Noop(_)) => None, // Noop -> no else block
-                    stmt => Some(Box::new(stmt)),
+                    stmt => {
+                        state.nodes[else_block] = stmt;
+                        Some(else_block)
+                    }
                 },
             ),
         },
         // while expr { block }
-        Stmt::While(expr, block) => match *expr {
+        Stmt::While(expr, block, label) => match state.nodes[expr] {
             // while false { block } -> Noop
             Expr::False(pos) => {
                 state.set_dirty();
                 Stmt::Noop(pos)
             }
             // while true { block } -> loop { block }
-            Expr::True(_) => Stmt::Loop(Box::new(optimize_stmt(*block, state, false))),
+            Expr::True(_) => Stmt::Loop(optimize_stmt_at(block, state, false), label),
             // while expr { block }
-            expr => match optimize_stmt(*block, state, false) {
+            _ => match optimize_stmt(state.nodes.take_stmt(block), state, false) {
                 // while expr { break; } -> { expr; }
-                Stmt::Break(pos) => {
+                Stmt::Break(target, pos) if target.is_none() || target == label => {
                     // Only a single break statement - turn into running the guard expression once
                     state.set_dirty();
-                    let mut statements = vec![Stmt::Expr(Box::new(optimize_expr(expr, state)))];
+                    let mut statements = vec![Stmt::Expr(optimize_expr_at(expr, state))];
                     if preserve_result {
                         statements.push(Stmt::Noop(pos))
                     }
-                    Stmt::Block(statements, pos)
+                    Stmt::Block(state.nodes.add_stmts(statements), pos)
                 }
                 // while expr { block }
-                stmt => Stmt::While(Box::new(optimize_expr(expr, state)), Box::new(stmt)),
+                stmt => {
+                    state.nodes[block] = stmt;
+                    Stmt::While(optimize_expr_at(expr, state), block, label)
+                }
             },
         },
         // loop { block }
-        Stmt::Loop(block) => match optimize_stmt(*block, state, false) {
+        Stmt::Loop(block, label) => match optimize_stmt(state.nodes.take_stmt(block), state, false)
+        {
             // loop { break; } -> Noop
-            Stmt::Break(pos) => {
+            Stmt::Break(target, pos) if target.is_none() || target == label => {
                 // Only a single break statement
                 state.set_dirty();
                 Stmt::Noop(pos)
             }
             // loop { block }
-            stmt => Stmt::Loop(Box::new(stmt)),
+            stmt => {
+                state.nodes[block] = stmt;
+                Stmt::Loop(block, label)
+            }
         },
         // for id in expr { block }
-        Stmt::For(id, expr, block) => Stmt::For(
+        Stmt::For(id, expr, block, label) => Stmt::For(
             id,
-            Box::new(optimize_expr(*expr, state)),
-            Box::new(optimize_stmt(*block, state, false)),
+            optimize_expr_at(expr, state),
+            optimize_stmt_at(block, state, false),
+            label,
         ),
         // let id = expr;
-        Stmt::Let(id, Some(expr), pos) => {
-            Stmt::Let(id, Some(Box::new(optimize_expr(*expr, state))), pos)
-        }
+        Stmt::Let(id, Some(expr), pos) => Stmt::Let(id, Some(optimize_expr_at(expr, state)), pos),
         // let id;
         Stmt::Let(_, None, _) => stmt,
         // { block }
@@ -191,23 +215,24 @@ fn optimize_stmt<'a>(stmt: Stmt, state: &mut State<'a>, preserve_result: bool) -
 
             // Optimize each statement in the block
             let mut result: Vec<_> = block
-                .into_iter()
-                .map(|stmt| match stmt {
+                .ids()
+                .map(|id| match state.nodes.take_stmt(id) {
                     // Add constant into the state
                     Stmt::Const(name, value, pos) => {
-                        state.push_constant(&name, *value);
+                        let value = state.nodes.take_expr(value);
+                        state.push_constant(&name, value);
                         state.set_dirty();
                         Stmt::Noop(pos) // No need to keep constants
                     }
                     // Optimize the statement
-                    _ => optimize_stmt(stmt, state, preserve_result),
+                    stmt => optimize_stmt(stmt, state, preserve_result),
                 })
                 .collect();
 
             // Remove all raw expression statements that are pure except for the very last statement
             let last_stmt = if preserve_result { result.pop() } else { None };
 
-            result.retain(|stmt| !stmt.is_pure());
+            result.retain(|stmt| !stmt.is_pure(state.nodes));
 
             if let Some(stmt) = last_stmt {
                 result.push(stmt);
@@ -221,7 +246,11 @@ fn optimize_stmt<'a>(stmt: Stmt, state: &mut State<'a>, preserve_result: bool) -
             while let Some(expr) = result.pop() {
                 match expr {
                     Stmt::Let(_, None, _) => removed = true,
-                    Stmt::Let(_, Some(val_expr), _) if val_expr.is_pure() => removed = true,
+                    Stmt::Let(_, Some(val_expr), _)
+                        if state.nodes[val_expr].is_pure(state.nodes) =>
+                    {
+                        removed = true
+                    }
                     _ => {
                         result.push(expr);
                         break;
@@ -253,7 +282,7 @@ fn optimize_stmt<'a>(stmt: Stmt, state: &mut State<'a>, preserve_result: bool) -
                 }
 
                 match stmt {
-                    Stmt::ReturnWithVal(_, _, _) | Stmt::Break(_) => {
+                    Stmt::ReturnWithVal(_, _, _) | Stmt::Break(_, _) => {
                         dead_code = true;
                     }
                     _ => (),
@@ -281,28 +310,57 @@ fn optimize_stmt<'a>(stmt: Stmt, state: &mut State<'a>, preserve_result: bool) -
                     state.set_dirty();
                     result.remove(0)
                 }
-                _ => Stmt::Block(result, pos),
+                _ => Stmt::Block(state.nodes.replace_stmts(block, result), pos),
             }
         }
         // expr;
-        Stmt::Expr(expr) => Stmt::Expr(Box::new(optimize_expr(*expr, state))),
+        Stmt::Expr(expr) => Stmt::Expr(optimize_expr_at(expr, state)),
         // return expr;
         Stmt::ReturnWithVal(Some(expr), is_return, pos) => {
-            Stmt::ReturnWithVal(Some(Box::new(optimize_expr(*expr, state))), is_return, pos)
+            Stmt::ReturnWithVal(Some(optimize_expr_at(expr, state)), is_return, pos)
         }
         // All other statements - skip
         stmt => stmt,
     }
 }
 
+/// Optimize a child statement in place.
+fn optimize_stmt_at<'a>(id: StmtId, state: &mut State<'a>, preserve_result: bool) -> StmtId {
+    let stmt = state.nodes.take_stmt(id);
+    state.nodes[id] = optimize_stmt(stmt, state, preserve_result);
+    id
+}
+
+/// Optimize a child expression in place.
+fn optimize_expr_at<'a>(id: ExprId, state: &mut State<'a>) -> ExprId {
+    let expr = state.nodes.take_expr(id);
+    state.nodes[id] = optimize_expr(expr, state);
+    id
+}
+
+/// Optimize a list of child expressions in place.
+fn optimize_exprs_at<'a>(list: ExprList, state: &mut State<'a>) -> ExprList {
+    list.ids().for_each(|id| {
+        optimize_expr_at(id, state);
+    });
+    list
+}
+
 /// Optimize an expression.
 fn optimize_expr<'a>(expr: Expr, state: &mut State<'a>) -> Expr {
     // These keywords are handled specially
-    const DONT_EVAL_KEYWORDS: [&str; 3] = [KEYWORD_PRINT, KEYWORD_DEBUG, KEYWORD_EVAL];
+    const DONT_EVAL_KEYWORDS: [&str; 6] = [
+        KEYWORD_PRINT,
+        KEYWORD_DEBUG,
+        KEYWORD_EVAL,
+        KEYWORD_IS_DEF_VAR,
+        KEYWORD_IS_DEF_FN,
+        KEYWORD_FN_METADATA_LIST,
+    ];
 
     match expr {
         // ( stmt )
-        Expr::Stmt(stmt, pos) => match optimize_stmt(*stmt, state, true) {
+        Expr::Stmt(stmt, pos) => match optimize_stmt(state.nodes.take_stmt(stmt), state, true) {
             // ( Noop ) -> ()
             Stmt::Noop(_) => {
                 state.set_dirty();
@@ -311,156 +369,157 @@ fn optimize_expr<'a>(expr: Expr, state: &mut State<'a>) -> Expr {
             // ( expr ) -> expr
             Stmt::Expr(expr) => {
                 state.set_dirty();
-                *expr
+                state.nodes.take_expr(expr)
             }
             // ( stmt )
-            stmt => Expr::Stmt(Box::new(stmt), pos),
+            stmt_value => {
+                state.nodes[stmt] = stmt_value;
+                Expr::Stmt(stmt, pos)
+            }
         },
         // id = expr
-        Expr::Assignment(id, expr, pos) => match *expr {
-            //id = id2 = expr2
-            Expr::Assignment(id2, expr2, pos2) => match (*id, *id2) {
-                // var = var = expr2 -> var = expr2
-                (Expr::Variable(var, _), Expr::Variable(var2, _)) if var == var2 => {
-                    // Assignment to the same variable - fold
-                    state.set_dirty();
+        Expr::Assignment(id, expr, pos) => match &state.nodes[expr] {
+            // var = var = expr2 -> var = expr2
+            Expr::Assignment(id2, expr2, _)
+                if matches!(
+                    (&state.nodes[id], &state.nodes[*id2]),
+                    (Expr::Variable(var, _), Expr::Variable(var2, _)) if var == var2
+                ) =>
+            {
+                // Assignment to the same variable - fold
+                let expr2 = *expr2;
+                state.set_dirty();
 
-                    Expr::Assignment(
-                        Box::new(Expr::Variable(var, pos)),
-                        Box::new(optimize_expr(*expr2, state)),
-                        pos,
-                    )
+                if let Expr::Variable(_, var_pos) = &mut state.nodes[id] {
+                    *var_pos = pos;
                 }
-                // id1 = id2 = expr2
-                (id1, id2) => Expr::Assignment(
-                    Box::new(id1),
-                    Box::new(Expr::Assignment(
-                        Box::new(id2),
-                        Box::new(optimize_expr(*expr2, state)),
-                        pos2,
-                    )),
-                    pos,
-                ),
-            },
+
+                Expr::Assignment(id, optimize_expr_at(expr2, state), pos)
+            }
             // id = expr
-            expr => Expr::Assignment(id, Box::new(optimize_expr(expr, state)), pos),
+            _ => Expr::Assignment(id, optimize_expr_at(expr, state), pos),
         },
         // lhs.rhs
         #[cfg(not(feature = "no_object"))]
         Expr::Dot(lhs, rhs, pos) => Expr::Dot(
-            Box::new(optimize_expr(*lhs, state)),
-            Box::new(optimize_expr(*rhs, state)),
+            optimize_expr_at(lhs, state),
+            optimize_expr_at(rhs, state),
             pos,
         ),
 
         // lhs[rhs]
         #[cfg(not(feature = "no_index"))]
-        Expr::Index(lhs, rhs, pos) => match (*lhs, *rhs) {
+        Expr::Index(lhs, rhs, pos) => match (&state.nodes[lhs], &state.nodes[rhs]) {
             // array[int]
-            (Expr::Array(mut items, _), Expr::IntegerConstant(i, _))
-                if i >= 0 && (i as usize) < items.len() && items.iter().all(|x| x.is_pure()) =>
+            (Expr::Array(items, _), Expr::IntegerConstant(i, _))
+                if *i >= 0
+                    && (*i as usize) < items.len()
+                    && state.nodes[*items].iter().all(|x| x.is_pure(state.nodes)) =>
             {
                 // Array literal where everything is pure - promote the indexed item.
                 // All other items can be thrown away.
+                let item = items.ids().nth(*i as usize).expect("should get item");
                 state.set_dirty();
-                items.remove(i as usize)
+                state.nodes.take_expr(item)
             }
             // string[int]
             (Expr::StringConstant(s, pos), Expr::IntegerConstant(i, _))
-                if i >= 0 && (i as usize) < s.chars().count() =>
+                if *i >= 0 && (*i as usize) < s.chars().count() =>
             {
                 // String literal indexing - get the character
+                let ch = Expr::CharConstant(s.chars().nth(*i as usize).expect("should get char"), *pos);
                 state.set_dirty();
-                Expr::CharConstant(s.chars().nth(i as usize).expect("should get char"), pos)
+                ch
             }
             // lhs[rhs]
-            (lhs, rhs) => Expr::Index(
-                Box::new(optimize_expr(lhs, state)),
-                Box::new(optimize_expr(rhs, state)),
+            _ => Expr::Index(
+                optimize_expr_at(lhs, state),
+                optimize_expr_at(rhs, state),
                 pos,
             ),
         },
         // [ items .. ]
         #[cfg(not(feature = "no_index"))]
-        Expr::Array(items, pos) => Expr::Array(items
-                .into_iter()
-                .map(|expr| optimize_expr(expr, state))
-                .collect(), pos),
+        Expr::Array(items, pos) => Expr::Array(optimize_exprs_at(items, state), pos),
         // [ items .. ]
         #[cfg(not(feature = "no_object"))]
         Expr::Map(items, pos) => Expr::Map(items
                 .into_iter()
-                .map(|(key, expr, pos)| (key, optimize_expr(expr, state), pos))
+                .map(|(key, expr, pos)| (key, optimize_expr_at(expr, state), pos))
                 .collect(), pos),
         // lhs && rhs
-        Expr::And(lhs, rhs) => match (*lhs, *rhs) {
+        Expr::And(lhs, rhs, pos) => match (&state.nodes[lhs], &state.nodes[rhs]) {
             // true && rhs -> rhs
-            (Expr::True(_), rhs) => {
+            (Expr::True(_), _) => {
                 state.set_dirty();
-                rhs
+                state.nodes.take_expr(rhs)
             }
             // false && rhs -> false
             (Expr::False(pos), _) => {
+                let pos = *pos;
                 state.set_dirty();
                 Expr::False(pos)
             }
             // lhs && true -> lhs
-            (lhs, Expr::True(_)) => {
+            (_, Expr::True(_)) => {
                 state.set_dirty();
-                optimize_expr(lhs, state)
+                optimize_expr(state.nodes.take_expr(lhs), state)
             }
             // lhs && rhs
-            (lhs, rhs) => Expr::And(
-                Box::new(optimize_expr(lhs, state)),
-                Box::new(optimize_expr(rhs, state)),
+            _ => Expr::And(
+                optimize_expr_at(lhs, state),
+                optimize_expr_at(rhs, state),
+                pos,
             ),
         },
         // lhs || rhs
-        Expr::Or(lhs, rhs) => match (*lhs, *rhs) {
+        Expr::Or(lhs, rhs, pos) => match (&state.nodes[lhs], &state.nodes[rhs]) {
             // false || rhs -> rhs
-            (Expr::False(_), rhs) => {
+            (Expr::False(_), _) => {
                 state.set_dirty();
-                rhs
+                state.nodes.take_expr(rhs)
             }
             // true || rhs -> true
             (Expr::True(pos), _) => {
+                let pos = *pos;
                 state.set_dirty();
                 Expr::True(pos)
             }
             // lhs || false
-            (lhs, Expr::False(_)) => {
+            (_, Expr::False(_)) => {
                 state.set_dirty();
-                optimize_expr(lhs, state)
+                optimize_expr(state.nodes.take_expr(lhs), state)
             }
             // lhs || rhs
-            (lhs, rhs) => Expr::Or(
-                Box::new(optimize_expr(lhs, state)),
-                Box::new(optimize_expr(rhs, state)),
+            _ => Expr::Or(
+                optimize_expr_at(lhs, state),
+                optimize_expr_at(rhs, state),
+                pos,
             ),
         },
 
         // Do not optimize anything within dump_ast
-        Expr::FunctionCall(id, args, def_value, pos) if id == KEYWORD_DUMP_AST =>
-            Expr::FunctionCall(id, args, def_value, pos),
+        Expr::FunctionCall(id, args, def_value, slot, pos) if id == KEYWORD_DUMP_AST =>
+            Expr::FunctionCall(id, args, def_value, slot, pos),
 
         // Do not call some special keywords
-        Expr::FunctionCall(id, args, def_value, pos) if DONT_EVAL_KEYWORDS.contains(&id.as_str())=>
-            Expr::FunctionCall(id, args.into_iter().map(|a| optimize_expr(a, state)).collect(), def_value, pos),
+        Expr::FunctionCall(id, args, def_value, slot, pos) if DONT_EVAL_KEYWORDS.contains(&id.as_ref())=>
+            Expr::FunctionCall(id, optimize_exprs_at(args, state), def_value, slot, pos),
 
         // Eagerly call functions
-        Expr::FunctionCall(id, args, def_value, pos)
+        Expr::FunctionCall(id, args, def_value, slot, pos)
                 if state.engine.optimization_level == OptimizationLevel::Full // full optimizations
-                && args.iter().all(|expr| expr.is_constant()) // all arguments are constants
+                && state.nodes[args].iter().all(|expr| expr.is_constant(state.nodes)) // all arguments are constants
         => {
             // First search in script-defined functions (can override built-in)
             if let Some(fn_lib_arc) = &state.engine.fn_lib {
                 if fn_lib_arc.has_function(&id, args.len()) {
                     // A script-defined function overrides the built-in function - do not make the call
-                    return Expr::FunctionCall(id, args.into_iter().map(|a| optimize_expr(a, state)).collect(), def_value, pos);
+                    return Expr::FunctionCall(id, optimize_exprs_at(args, state), def_value, slot, pos);
                 }
             }
 
-            let mut arg_values: Vec<_> = args.iter().map(Expr::get_constant_value).collect();
+            let mut arg_values: Vec<_> = state.nodes[args].iter().map(|expr| expr.get_constant_value(state.nodes)).collect();
             let mut call_args: Vec<_> = arg_values.iter_mut().map(Dynamic::as_mut).collect();
 
             // Save the typename of the first argument if it is `type_of()`
@@ -485,19 +544,20 @@ fn optimize_expr<'a>(expr: Expr, state: &mut State<'a>) -> Expr {
                         state.set_dirty();
                         expr
                     })
-            ).flatten().unwrap_or_else(|| Expr::FunctionCall(id, args, def_value, pos))
+            ).flatten().unwrap_or_else(|| Expr::FunctionCall(id, args, def_value, slot, pos))
         }
 
         // id(args ..) -> optimize function call arguments
-        Expr::FunctionCall(id, args, def_value, pos) =>
-            Expr::FunctionCall(id, args.into_iter().map(|a| optimize_expr(a, state)).collect(), def_value, pos),
+        Expr::FunctionCall(id, args, def_value, slot, pos) =>
+            Expr::FunctionCall(id, optimize_exprs_at(args, state), def_value, slot, pos),
 
         // constant-name
         Expr::Variable(name, _) if state.contains_constant(&name) => {
             state.set_dirty();
 
-            // Replace constant with value
-            state.find_constant(&name).expect("should find constant in scope!").clone()
+            // Replace constant with a copy of its value
+            let value = state.find_constant(&name).expect("should find constant in scope!").clone();
+            state.nodes.copy_expr(&value)
         }
 
         // All other expressions - skip
@@ -505,29 +565,69 @@ fn optimize_expr<'a>(expr: Expr, state: &mut State<'a>) -> Expr {
     }
 }
 
-pub(crate) fn optimize<'a>(statements: Vec<Stmt>, engine: &Engine<'a>, scope: &Scope) -> Vec<Stmt> {
+/// Collects the names of all variables and constants declared in statements, which may shadow
+/// global constants registered on the `Engine`.
+#[derive(Default)]
+struct DeclaredNames(HashSet<String>);
+
+impl ASTVisitor for DeclaredNames {
+    fn enter(&mut self, node: ASTNode, _nodes: &Nodes) -> bool {
+        match node {
+            ASTNode::Stmt(Stmt::Let(name, _, _))
+            | ASTNode::Stmt(Stmt::Const(name, _, _))
+            | ASTNode::Stmt(Stmt::For(name, _, _, _)) => {
+                self.0.insert(name.clone());
+            }
+            _ => (),
+        }
+        true
+    }
+}
+
+pub(crate) fn optimize<'a>(
+    statements: Vec<Stmt>,
+    nodes: &mut Nodes,
+    engine: &Engine<'a>,
+    scope: &Scope,
+) -> Vec<Stmt> {
     // If optimization level is None then skip optimizing
     if engine.optimization_level == OptimizationLevel::None {
         return statements;
     }
 
+    // Find the names declared (anywhere) in the statements
+    let mut declared = DeclaredNames::default();
+    statements
+        .iter()
+        .for_each(|stmt| walk_stmt(nodes, stmt, &mut declared));
+
     // Set up the state
-    let mut state = State::new(engine);
+    let mut state = State::new(engine, nodes);
 
-    // Add constants from the scope into the state
-    scope
+    // Add global constants from the engine into the state, unless shadowed by variables
+    // in the scope or in the statements
+    engine
+        .globals
         .iter()
-        .filter(|ScopeEntry { typ, expr, .. }| {
-            // Get all the constants with definite constant expressions
-            *typ == ScopeEntryType::Constant
-                && expr.as_ref().map(Expr::is_constant).unwrap_or(false)
+        .filter(|(name, _)| !scope.contains(name) && !declared.0.contains(name.as_str()))
+        .filter_map(|(name, value)| {
+            map_dynamic_to_expr((**value).clone(), Position::none()).map(|expr| (name, expr))
         })
-        .for_each(|ScopeEntry { name, expr, .. }| {
-            state.push_constant(
-                name.as_ref(),
-                expr.as_ref().expect("should be Some(expr)").clone(),
-            )
-        });
+        .for_each(|(name, expr)| state.push_constant(name, expr));
+
+    // Add constants from the scope into the state
+    for ScopeEntry {
+        name, typ, expr, ..
+    } in scope.iter()
+    {
+        match expr {
+            // Get all the constants with definite constant expressions
+            Some(expr) if *typ == ScopeEntryType::Constant && expr.is_constant(state.nodes) => {
+                state.push_constant(name.as_ref(), expr.clone())
+            }
+            _ => (),
+        }
+    }
 
     let orig_constants_len = state.constants.len();
 
@@ -546,7 +646,8 @@ pub(crate) fn optimize<'a>(statements: Vec<Stmt>, engine: &Engine<'a>, scope: &S
             .map(|(i, stmt)| {
                 if let Stmt::Const(name, value, _) = &stmt {
                     // Load constants
-                    state.push_constant(name, value.as_ref().clone());
+                    let value = state.nodes[*value].clone();
+                    state.push_constant(name, value);
                     stmt // Keep it in the global scope
                 } else {
                     // Keep all variable declarations at this level
@@ -567,7 +668,7 @@ pub(crate) fn optimize<'a>(statements: Vec<Stmt>, engine: &Engine<'a>, scope: &S
     let last_stmt = result.pop();
 
     // Remove all pure statements at global level
-    result.retain(|stmt| !stmt.is_pure());
+    result.retain(|stmt| !stmt.is_pure(state.nodes));
 
     // Add back the last statement unless it is a lone No-op
     if let Some(stmt) = last_stmt {
@@ -584,30 +685,40 @@ pub fn optimize_into_ast(
     engine: &Engine,
     scope: &Scope,
     statements: Vec<Stmt>,
+    mut nodes: Nodes,
     functions: Vec<FnDef>,
 ) -> AST {
     let fn_lib = FunctionsLib::from_vec(
         functions
-            .iter()
-            .cloned()
+            .into_iter()
             .map(|mut fn_def| {
                 if engine.optimization_level != OptimizationLevel::None {
-                    let pos = fn_def.body.position();
+                    let pos = fn_def.body.position(&fn_def.nodes);
+
+                    // Parameters shadow global constants
+                    let mut scope = Scope::new();
+                    fn_def
+                        .params
+                        .iter()
+                        .for_each(|param| scope.push(param.as_str(), ()));
 
                     // Optimize the function body
-                    let mut body = optimize(vec![fn_def.body], engine, &Scope::new());
+                    let mut body = optimize(vec![fn_def.body], &mut fn_def.nodes, engine, &scope);
 
                     // {} -> Noop
-                    fn_def.body = match body.pop().unwrap_or_else(|| Stmt::Noop(pos)) {
+                    fn_def.body = match body.pop().unwrap_or(Stmt::Noop(pos)) {
                         // { return val; } -> val
                         Stmt::ReturnWithVal(Some(val), ReturnType::Return, _) => Stmt::Expr(val),
                         // { return; } -> ()
                         Stmt::ReturnWithVal(None, ReturnType::Return, pos) => {
-                            Stmt::Expr(Box::new(Expr::Unit(pos)))
+                            Stmt::Expr(fn_def.nodes.add_expr(Expr::Unit(pos)))
                         }
                         // All others
                         stmt => stmt,
                     };
+
+                    // Drop the nodes no longer reachable from the optimized body
+                    fn_def.nodes.compact(slice::from_mut(&mut fn_def.body));
                 }
                 fn_def
             })
@@ -618,12 +729,17 @@ pub fn optimize_into_ast(
         match engine.optimization_level {
             OptimizationLevel::None => statements,
             OptimizationLevel::Simple | OptimizationLevel::Full => {
-                optimize(statements, engine, &scope)
+                let mut statements = optimize(statements, &mut nodes, engine, &scope);
+                nodes.compact(&mut statements);
+                statements
             }
         },
         #[cfg(feature = "sync")]
         Arc::new(fn_lib),
         #[cfg(not(feature = "sync"))]
         Rc::new(fn_lib),
+        None,
+        vec![],
+        nodes,
     )
 }
@@ -1,30 +1,56 @@
 //! Main module defining the lexer and parser.
 
 use crate::any::{Any, AnyExt, Dynamic};
+use crate::call_graph::{call_graph, CallGraph};
+use crate::dialect::DialectRules;
 use crate::engine::{Engine, FunctionsLib};
 use crate::error::{LexError, ParseError, ParseErrorType};
 use crate::scope::{EntryType as ScopeEntryType, Scope};
+use crate::visitor::{walk_ast, walk_expr, walk_fn, walk_stmt, ASTNode, ASTVisitor};
+
+#[cfg(not(feature = "no_index"))]
+use crate::engine::Array;
+
+#[cfg(not(feature = "no_object"))]
+use crate::engine::Map;
+
+#[cfg(not(feature = "no_module"))]
+use crate::module::CachedCall;
 
 #[cfg(not(feature = "no_optimize"))]
 use crate::optimize::optimize_into_ast;
 
+#[cfg(feature = "unicode-xid-ident")]
+use unicode_normalization::UnicodeNormalization;
+#[cfg(feature = "unicode-xid-ident")]
+use unicode_xid::UnicodeXID;
+
 use crate::stdlib::{
     borrow::Cow,
     boxed::Box,
     char,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
     fmt, format,
     iter::Peekable,
-    ops::Add,
-    rc::Rc,
+    mem,
+    ops::{Add, Deref, DerefMut, Index, IndexMut},
     str::Chars,
     str::FromStr,
     string::{String, ToString},
-    sync::Arc,
     usize, vec,
     vec::Vec,
 };
 
+#[cfg(feature = "sync")]
+use crate::stdlib::sync::Arc;
+
+#[cfg(not(feature = "sync"))]
+use crate::stdlib::rc::Rc;
+
+#[cfg(not(feature = "no_function"))]
+use crate::stdlib::iter::once;
+
 /// The system integer type.
 ///
 /// If the `only_i32` feature is enabled, this will be `i32` instead.
@@ -37,6 +63,40 @@ pub type INT = i64;
 #[cfg(feature = "only_i32")]
 pub type INT = i32;
 
+/// Number of bits in the system integer type.
+#[cfg(any(not(feature = "no_index"), not(feature = "no_stdlib")))]
+pub(crate) const INT_BITS: usize = crate::stdlib::mem::size_of::<INT>() * 8;
+
+/// Default maximum nesting depth of expressions and statements in a script.
+/// See `Engine::set_max_expr_depth`.
+pub const MAX_EXPR_DEPTH: usize = 128;
+
+/// The nesting level of the expression or statement being parsed, together with the maximum
+/// nesting depth allowed.
+#[derive(Debug, Clone, Copy)]
+struct Level {
+    depth: usize,
+    max: usize,
+}
+
+impl Level {
+    /// The level of the global statements of a script.
+    fn top(max: usize) -> Self {
+        Self { depth: 0, max }
+    }
+}
+
+impl Add<usize> for Level {
+    type Output = Self;
+
+    fn add(self, n: usize) -> Self {
+        Self {
+            depth: self.depth + n,
+            ..self
+        }
+    }
+}
+
 /// The system floating-point type.
 ///
 /// Not available under the `no_float` feature.
@@ -170,6 +230,9 @@ pub struct AST(
     pub(crate) Vec<Stmt>,
     #[cfg(feature = "sync")] pub(crate) Arc<FunctionsLib>,
     #[cfg(not(feature = "sync"))] pub(crate) Rc<FunctionsLib>,
+    pub(crate) Option<String>,
+    pub(crate) Vec<Comment>,
+    pub(crate) Nodes,
 );
 
 impl AST {
@@ -192,7 +255,7 @@ impl AST {
     /// # Example
     ///
     /// ```
-    /// # fn main() -> Result<(), rhai::EvalAltResult> {
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
     /// # #[cfg(not(feature = "no_function"))]
     /// # {
     /// use rhai::Engine;
@@ -221,29 +284,189 @@ impl AST {
     /// # }
     /// ```
     pub fn merge(&self, other: &Self) -> Self {
-        let Self(statements, functions) = self;
+        let Self(statements, functions, source, comments, nodes) = self;
 
-        let ast = match (statements.is_empty(), other.0.is_empty()) {
+        let (ast, nodes) = match (statements.is_empty(), other.0.is_empty()) {
             (false, false) => {
                 let mut statements = statements.clone();
-                statements.extend(other.0.iter().cloned());
-                statements
+                let mut nodes = nodes.clone();
+                let offset = nodes.append(other.4.clone());
+                statements.extend(other.0.iter().cloned().map(|stmt| stmt.offset(offset)));
+                (statements, nodes)
             }
-            (false, true) => statements.clone(),
-            (true, false) => other.0.clone(),
-            (true, true) => vec![],
+            (false, true) => (statements.clone(), nodes.clone()),
+            (true, false) => (other.0.clone(), other.4.clone()),
+            (true, true) => (vec![], Nodes::default()),
         };
 
+        let mut comments = comments.clone();
+        comments.extend(other.3.iter().cloned());
+
         #[cfg(feature = "sync")]
         {
-            Self(ast, Arc::new(functions.merge(other.1.as_ref())))
+            Self(
+                ast,
+                Arc::new(functions.merge(other.1.as_ref())),
+                source.clone(),
+                comments,
+                nodes,
+            )
         }
         #[cfg(not(feature = "sync"))]
         {
-            Self(ast, Rc::new(functions.merge(other.1.as_ref())))
+            Self(
+                ast,
+                Rc::new(functions.merge(other.1.as_ref())),
+                source.clone(),
+                comments,
+                nodes,
+            )
         }
     }
 
+    /// Get an iterator over all script-defined functions in the `AST`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), rhai::ParseError> {
+    /// use rhai::Engine;
+    ///
+    /// let mut engine = Engine::new();
+    /// engine.enable_doc_comments(true);
+    ///
+    /// let ast = engine.compile(
+    ///     "
+    ///         /// Add one to a number.
+    ///         fn inc(x) { x + 1 }
+    ///     ",
+    /// )?;
+    ///
+    /// let f = ast.iter_functions().next().unwrap();
+    ///
+    /// assert_eq!(f.name, "inc");
+    /// assert_eq!(f.params, vec!["x"]);
+    /// assert_eq!(f.comments, vec!["/// Add one to a number."]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_function"))]
+    pub fn iter_functions(&self) -> impl Iterator<Item = ScriptFnMetadata<'_>> {
+        self.1.iter().map(|f| ScriptFnMetadata {
+            name: &f.name,
+            params: f.params.iter().map(String::as_str).collect(),
+            comments: f.comments.iter().map(String::as_str).collect(),
+            private: f.private,
+        })
+    }
+
+    /// Get the source name of the `AST` (e.g. the path of the script file), if any.
+    ///
+    /// The source name is passed to the `on_debug` callback together with the output of `debug`.
+    pub fn source(&self) -> Option<&str> {
+        self.2.as_deref()
+    }
+
+    /// Set the source name of the `AST` (e.g. the name of the script).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let mut ast = engine.compile("40 + 2")?;
+    /// ast.set_source("hello");
+    ///
+    /// assert_eq!(ast.source(), Some("hello"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_source(&mut self, source: &str) {
+        self.2 = Some(source.into());
+    }
+
+    /// Get all the comments in the script, in order.
+    ///
+    /// Always empty unless comments are enabled via `Engine::enable_comments`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, Position};
+    ///
+    /// let mut engine = Engine::new();
+    /// engine.enable_comments(true);
+    ///
+    /// let ast = engine.compile("let x = 42; // the answer\n/* done */")?;
+    ///
+    /// let comments = ast.comments();
+    ///
+    /// assert_eq!(comments.len(), 2);
+    /// assert_eq!(comments[0].text, "// the answer");
+    /// assert_eq!(comments[0].start, Position::new(1, 13));
+    /// assert_eq!(comments[1].text, "/* done */");
+    /// assert_eq!(comments[1].end, Position::new(2, 10));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn comments(&self) -> &[Comment] {
+        &self.3
+    }
+
+    /// Get the top-level statements of the `AST`, excluding script-defined functions.
+    pub fn statements(&self) -> &[Stmt] {
+        &self.0
+    }
+
+    /// Get the child nodes of the top-level statements of the `AST`.
+    pub fn nodes(&self) -> &Nodes {
+        &self.4
+    }
+
+    /// Visit all the nodes of the `AST`, first the script-defined functions (in no particular
+    /// order) then the top-level statements.  See `ASTVisitor` for an example.
+    pub fn walk(&self, visitor: &mut impl ASTVisitor) {
+        walk_ast(self, visitor)
+    }
+
+    /// Get the static call graph of the `AST`, i.e. the functions called by its top-level
+    /// statements and by each script-defined function.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), rhai::ParseError> {
+    /// # #[cfg(not(feature = "no_function"))]
+    /// # {
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile(
+    ///     "
+    ///         fn greet(name) { print(name) }
+    ///         fn unused() { missing_fn() }
+    ///         greet(\"world\");
+    ///     ",
+    /// )?;
+    ///
+    /// let graph = ast.call_graph();
+    ///
+    /// assert_eq!(graph.main[0].name, "greet");
+    /// assert_eq!(graph.functions[&("greet".to_string(), 1)][0].name, "print");
+    /// assert_eq!(graph.unreachable_functions(), vec![("unused", 0)]);
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_graph(&self) -> CallGraph {
+        call_graph(self)
+    }
+
     /// Clear all function definitions in the `AST`.
     pub fn clear_functions(&mut self) {
         #[cfg(feature = "sync")]
@@ -259,6 +482,7 @@ impl AST {
     /// Clear all statements in the `AST`, leaving only function definitions.
     pub fn retain_functions(&mut self) {
         self.0 = vec![];
+        self.4 = Default::default();
     }
 }
 
@@ -266,11 +490,23 @@ impl Default for AST {
     fn default() -> Self {
         #[cfg(feature = "sync")]
         {
-            Self(vec![], Arc::new(FunctionsLib::new()))
+            Self(
+                vec![],
+                Arc::new(FunctionsLib::new()),
+                None,
+                vec![],
+                Default::default(),
+            )
         }
         #[cfg(not(feature = "sync"))]
         {
-            Self(vec![], Rc::new(FunctionsLib::new()))
+            Self(
+                vec![],
+                Rc::new(FunctionsLib::new()),
+                None,
+                vec![],
+                Default::default(),
+            )
         }
     }
 }
@@ -291,8 +527,58 @@ pub struct FnDef {
     pub params: Vec<String>,
     /// Function body.
     pub body: Stmt,
+    /// Child nodes of the function body.
+    pub nodes: Nodes,
     /// Position of the function definition.
     pub pos: Position,
+    /// Doc-comments preceding the function definition, if captured.
+    pub comments: Vec<String>,
+    /// Is the function private, i.e. only callable from within the script (or module) defining it?
+    pub private: bool,
+}
+
+/// Metadata of a script-defined function.
+#[cfg(not(feature = "no_function"))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScriptFnMetadata<'a> {
+    /// Function name.
+    pub name: &'a str,
+    /// Names of function parameters.
+    pub params: Vec<&'a str>,
+    /// Doc-comments preceding the function definition, including the comment markers.
+    ///
+    /// Always empty unless doc-comments are enabled via `Engine::enable_doc_comments`.
+    pub comments: Vec<&'a str>,
+    /// Is the function private (defined with `private fn`)?
+    pub private: bool,
+}
+
+/// A comment in a script, kept during compilation when enabled via `Engine::enable_comments`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Comment {
+    /// Text of the comment, including the comment markers (e.g. `//`, `/*` and `*/`).
+    pub text: String,
+    /// Position of the first character of the comment.
+    pub start: Position,
+    /// Position of the last character of the comment.
+    pub end: Position,
+}
+
+impl Comment {
+    /// Create a `Comment` from its text and the position of its first character.
+    pub(crate) fn new(text: String, start: Position) -> Self {
+        let mut end = start;
+
+        for c in text.chars().skip(1) {
+            if c == '\n' {
+                end.new_line();
+            } else {
+                end.advance();
+            }
+        }
+
+        Self { text, start, end }
+    }
 }
 
 /// `return`/`throw` statement.
@@ -304,48 +590,649 @@ pub enum ReturnType {
     Exception,
 }
 
+/// Index of an expression in the `Nodes` of an `AST` or of a script-defined function.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct ExprId(u32);
+
+/// Index of a statement in the `Nodes` of an `AST` or of a script-defined function.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct StmtId(u32);
+
+/// A run of consecutive expressions in `Nodes` (e.g. the arguments of a function call).
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Default)]
+pub struct ExprList {
+    start: u32,
+    len: u32,
+}
+
+/// A run of consecutive statements in `Nodes` (e.g. the statements of a block).
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Default)]
+pub struct StmtList {
+    start: u32,
+    len: u32,
+}
+
+impl ExprList {
+    /// Number of expressions in the list.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Get the indices of the expressions in the list.
+    pub fn ids(&self) -> impl Iterator<Item = ExprId> {
+        (self.start..self.start + self.len).map(ExprId)
+    }
+
+    /// Is the list empty?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl StmtList {
+    /// Number of statements in the list.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Get the indices of the statements in the list.
+    pub fn ids(&self) -> impl Iterator<Item = StmtId> {
+        (self.start..self.start + self.len).map(StmtId)
+    }
+
+    /// Is the list empty?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// The nodes of an `AST`, or of the body of a script-defined function (see `FnDef::nodes`).
+///
+/// Statements and expressions do not own their child nodes, but refer to them by index
+/// (`ExprId`, `StmtId`, or `ExprList`/`StmtList` for a list of nodes) into `Nodes`, which keeps
+/// all the nodes of a script in a few large allocations.  Index `Nodes` to get a child node:
+///
+/// ```
+/// # fn main() -> Result<(), rhai::ParseError> {
+/// use rhai::{Engine, Expr, Stmt};
+///
+/// let engine = Engine::new();
+/// let ast = engine.compile("print(40 + x)")?;
+///
+/// if let Stmt::Expr(call) = &ast.statements()[0] {
+///     if let Expr::FunctionCall(name, args, _, _, _) = &ast.nodes()[*call] {
+///         assert_eq!(name, "print");
+///         assert!(matches!(&ast.nodes()[*args][0], Expr::FunctionCall(op, _, _, _, _) if op == "+"));
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Nodes {
+    exprs: Vec<Expr>,
+    stmts: Vec<Stmt>,
+}
+
+/// Offsets added to the indices of nodes moved into other `Nodes` (see `Nodes::append`).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NodeOffset {
+    exprs: u32,
+    stmts: u32,
+}
+
+impl ExprId {
+    fn offset(self, offset: NodeOffset) -> Self {
+        Self(self.0 + offset.exprs)
+    }
+}
+
+impl StmtId {
+    fn offset(self, offset: NodeOffset) -> Self {
+        Self(self.0 + offset.stmts)
+    }
+}
+
+impl ExprList {
+    fn offset(self, offset: NodeOffset) -> Self {
+        Self {
+            start: self.start + offset.exprs,
+            ..self
+        }
+    }
+}
+
+impl StmtList {
+    fn offset(self, offset: NodeOffset) -> Self {
+        Self {
+            start: self.start + offset.stmts,
+            ..self
+        }
+    }
+}
+
+/// Convert the number of nodes into an index.
+///
+/// # Panics
+///
+/// Panics if there are more nodes than can be indexed.
+fn node_index(len: usize) -> u32 {
+    u32::try_from(len).expect("too many nodes")
+}
+
+/// Where the child nodes of a node added by `Nodes::move_expr` or `Nodes::move_stmt` come from:
+/// a `&Nodes` gives copies of its nodes, while a `&mut Nodes` gives up its nodes.
+trait NodeSource {
+    fn expr(&mut self, id: ExprId) -> Expr;
+    fn stmt(&mut self, id: StmtId) -> Stmt;
+}
+
+impl NodeSource for &Nodes {
+    fn expr(&mut self, id: ExprId) -> Expr {
+        self[id].clone()
+    }
+    fn stmt(&mut self, id: StmtId) -> Stmt {
+        self[id].clone()
+    }
+}
+
+#[cfg(not(feature = "no_optimize"))]
+impl NodeSource for &mut Nodes {
+    fn expr(&mut self, id: ExprId) -> Expr {
+        self.take_expr(id)
+    }
+    fn stmt(&mut self, id: StmtId) -> Stmt {
+        self.take_stmt(id)
+    }
+}
+
+impl Nodes {
+    /// Add an expression, returning its index.
+    pub(crate) fn add_expr(&mut self, expr: Expr) -> ExprId {
+        let id = ExprId(node_index(self.exprs.len()));
+        self.exprs.push(expr);
+        id
+    }
+
+    /// Add a statement, returning its index.
+    pub(crate) fn add_stmt(&mut self, stmt: Stmt) -> StmtId {
+        let id = StmtId(node_index(self.stmts.len()));
+        self.stmts.push(stmt);
+        id
+    }
+
+    /// Add a list of expressions.
+    pub(crate) fn add_exprs(&mut self, exprs: impl IntoIterator<Item = Expr>) -> ExprList {
+        let start = self.exprs.len();
+        self.exprs.extend(exprs);
+
+        ExprList {
+            start: node_index(start),
+            len: node_index(self.exprs.len() - start),
+        }
+    }
+
+    /// Add a list of statements.
+    pub(crate) fn add_stmts(&mut self, stmts: impl IntoIterator<Item = Stmt>) -> StmtList {
+        let start = self.stmts.len();
+        self.stmts.extend(stmts);
+
+        StmtList {
+            start: node_index(start),
+            len: node_index(self.stmts.len() - start),
+        }
+    }
+
+    /// Replace a list of statements, reusing its place if the new list is not longer.
+    #[cfg(not(feature = "no_optimize"))]
+    pub(crate) fn replace_stmts(&mut self, list: StmtList, stmts: Vec<Stmt>) -> StmtList {
+        if stmts.len() > list.len() {
+            return self.add_stmts(stmts);
+        }
+
+        let len = node_index(stmts.len());
+
+        for (i, stmt) in stmts.into_iter().enumerate() {
+            self.stmts[list.start as usize + i] = stmt;
+        }
+
+        StmtList { len, ..list }
+    }
+
+    /// Copy an expression together with all its child nodes, so that the copy does not share any
+    /// child nodes with the original.
+    pub(crate) fn copy_expr(&mut self, expr: &Expr) -> Expr {
+        let mut copy = Nodes::default();
+        let expr = copy.move_expr(&mut &*self, expr.clone());
+        expr.offset(self.append(copy))
+    }
+
+    /// Keep only the nodes reachable from `statements`, e.g. after optimization has replaced some
+    /// nodes.  The remaining nodes are renumbered in the order they are reached.
+    #[cfg(not(feature = "no_optimize"))]
+    pub(crate) fn compact(&mut self, statements: &mut [Stmt]) {
+        let mut nodes = mem::take(self);
+        self.exprs.reserve(nodes.exprs.len());
+        self.stmts.reserve(nodes.stmts.len());
+
+        for stmt in statements {
+            let old = mem::replace(stmt, Stmt::Noop(Position::none()));
+            *stmt = self.move_stmt(&mut &mut nodes, old);
+        }
+    }
+
+    /// Add the child nodes of an expression, getting them from `src`.
+    fn move_expr(&mut self, src: &mut impl NodeSource, expr: Expr) -> Expr {
+        match expr {
+            Expr::Stmt(stmt, pos) => Expr::Stmt(self.move_stmt_at(src, stmt), pos),
+            Expr::FunctionCall(name, args, def_val, slot, pos) => {
+                Expr::FunctionCall(name, self.move_exprs(src, args), def_val, slot, pos)
+            }
+            Expr::Assignment(lhs, rhs, pos) => Expr::Assignment(
+                self.move_expr_at(src, lhs),
+                self.move_expr_at(src, rhs),
+                pos,
+            ),
+            #[cfg(not(feature = "no_object"))]
+            Expr::Dot(lhs, rhs, pos) => Expr::Dot(
+                self.move_expr_at(src, lhs),
+                self.move_expr_at(src, rhs),
+                pos,
+            ),
+            #[cfg(not(feature = "no_index"))]
+            Expr::Index(lhs, rhs, pos) => Expr::Index(
+                self.move_expr_at(src, lhs),
+                self.move_expr_at(src, rhs),
+                pos,
+            ),
+            #[cfg(not(feature = "no_index"))]
+            Expr::Array(items, pos) => Expr::Array(self.move_exprs(src, items), pos),
+            #[cfg(not(feature = "no_object"))]
+            Expr::Map(items, pos) => Expr::Map(
+                items
+                    .into_iter()
+                    .map(|(key, expr, pos)| (key, self.move_expr_at(src, expr), pos))
+                    .collect(),
+                pos,
+            ),
+            Expr::And(lhs, rhs, pos) => Expr::And(
+                self.move_expr_at(src, lhs),
+                self.move_expr_at(src, rhs),
+                pos,
+            ),
+            Expr::Or(lhs, rhs, pos) => Expr::Or(
+                self.move_expr_at(src, lhs),
+                self.move_expr_at(src, rhs),
+                pos,
+            ),
+            expr => expr,
+        }
+    }
+
+    /// Add the child nodes of a statement, getting them from `src`.
+    fn move_stmt(&mut self, src: &mut impl NodeSource, stmt: Stmt) -> Stmt {
+        match stmt {
+            Stmt::IfThenElse(guard, if_block, else_block) => Stmt::IfThenElse(
+                self.move_expr_at(src, guard),
+                self.move_stmt_at(src, if_block),
+                else_block.map(|stmt| self.move_stmt_at(src, stmt)),
+            ),
+            Stmt::While(guard, body, label) => Stmt::While(
+                self.move_expr_at(src, guard),
+                self.move_stmt_at(src, body),
+                label,
+            ),
+            Stmt::Loop(body, label) => Stmt::Loop(self.move_stmt_at(src, body), label),
+            Stmt::For(name, expr, body, label) => Stmt::For(
+                name,
+                self.move_expr_at(src, expr),
+                self.move_stmt_at(src, body),
+                label,
+            ),
+            Stmt::Let(name, expr, pos) => {
+                Stmt::Let(name, expr.map(|expr| self.move_expr_at(src, expr)), pos)
+            }
+            Stmt::Const(name, expr, pos) => Stmt::Const(name, self.move_expr_at(src, expr), pos),
+            Stmt::Block(statements, pos) => Stmt::Block(self.move_stmts(src, statements), pos),
+            Stmt::Expr(expr) => Stmt::Expr(self.move_expr_at(src, expr)),
+            Stmt::ReturnWithVal(expr, return_type, pos) => Stmt::ReturnWithVal(
+                expr.map(|expr| self.move_expr_at(src, expr)),
+                return_type,
+                pos,
+            ),
+            stmt => stmt,
+        }
+    }
+
+    /// Add an expression from `src`, together with its child nodes.
+    fn move_expr_at(&mut self, src: &mut impl NodeSource, id: ExprId) -> ExprId {
+        let expr = src.expr(id);
+        let expr = self.move_expr(src, expr);
+        self.add_expr(expr)
+    }
+
+    /// Add a statement from `src`, together with its child nodes.
+    fn move_stmt_at(&mut self, src: &mut impl NodeSource, id: StmtId) -> StmtId {
+        let stmt = src.stmt(id);
+        let stmt = self.move_stmt(src, stmt);
+        self.add_stmt(stmt)
+    }
+
+    /// Add a list of expressions from `src`, together with their child nodes.
+    fn move_exprs(&mut self, src: &mut impl NodeSource, list: ExprList) -> ExprList {
+        // Make room for the whole list first, to keep it in one run after the child nodes are added
+        let start = self.exprs.len();
+        self.exprs
+            .resize_with(start + list.len(), || Expr::Unit(Position::none()));
+
+        for (i, id) in list.ids().enumerate() {
+            let expr = src.expr(id);
+            self.exprs[start + i] = self.move_expr(src, expr);
+        }
+
+        ExprList {
+            start: node_index(start),
+            ..list
+        }
+    }
+
+    /// Add a list of statements from `src`, together with their child nodes.
+    fn move_stmts(&mut self, src: &mut impl NodeSource, list: StmtList) -> StmtList {
+        // Make room for the whole list first, to keep it in one run after the child nodes are added
+        let start = self.stmts.len();
+        self.stmts
+            .resize_with(start + list.len(), || Stmt::Noop(Position::none()));
+
+        for (i, id) in list.ids().enumerate() {
+            let stmt = src.stmt(id);
+            self.stmts[start + i] = self.move_stmt(src, stmt);
+        }
+
+        StmtList {
+            start: node_index(start),
+            ..list
+        }
+    }
+
+    /// Take an expression out, leaving `()` in its place.
+    #[cfg(any(not(feature = "no_optimize"), not(feature = "no_object")))]
+    pub(crate) fn take_expr(&mut self, id: ExprId) -> Expr {
+        mem::replace(&mut self[id], Expr::Unit(Position::none()))
+    }
+
+    /// Take a statement out, leaving a no-op in its place.
+    #[cfg(any(not(feature = "no_optimize"), not(feature = "no_function")))]
+    pub(crate) fn take_stmt(&mut self, id: StmtId) -> Stmt {
+        mem::replace(&mut self[id], Stmt::Noop(Position::none()))
+    }
+
+    /// Add all the nodes of another `Nodes`.  Add the returned offset (via `Stmt::offset`) to the
+    /// top-level statements referring to them.
+    pub(crate) fn append(&mut self, other: Self) -> NodeOffset {
+        let offset = NodeOffset {
+            exprs: node_index(self.exprs.len()),
+            stmts: node_index(self.stmts.len()),
+        };
+
+        self.exprs
+            .extend(other.exprs.into_iter().map(|expr| expr.offset(offset)));
+        self.stmts
+            .extend(other.stmts.into_iter().map(|stmt| stmt.offset(offset)));
+
+        offset
+    }
+}
+
+impl Index<ExprId> for Nodes {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.0 as usize]
+    }
+}
+
+impl IndexMut<ExprId> for Nodes {
+    fn index_mut(&mut self, id: ExprId) -> &mut Expr {
+        &mut self.exprs[id.0 as usize]
+    }
+}
+
+impl Index<StmtId> for Nodes {
+    type Output = Stmt;
+
+    fn index(&self, id: StmtId) -> &Stmt {
+        &self.stmts[id.0 as usize]
+    }
+}
+
+impl IndexMut<StmtId> for Nodes {
+    fn index_mut(&mut self, id: StmtId) -> &mut Stmt {
+        &mut self.stmts[id.0 as usize]
+    }
+}
+
+impl Index<ExprList> for Nodes {
+    type Output = [Expr];
+
+    fn index(&self, list: ExprList) -> &[Expr] {
+        &self.exprs[list.start as usize..(list.start + list.len) as usize]
+    }
+}
+
+impl IndexMut<ExprList> for Nodes {
+    fn index_mut(&mut self, list: ExprList) -> &mut [Expr] {
+        &mut self.exprs[list.start as usize..(list.start + list.len) as usize]
+    }
+}
+
+impl Index<StmtList> for Nodes {
+    type Output = [Stmt];
+
+    fn index(&self, list: StmtList) -> &[Stmt] {
+        &self.stmts[list.start as usize..(list.start + list.len) as usize]
+    }
+}
+
+impl IndexMut<StmtList> for Nodes {
+    fn index_mut(&mut self, list: StmtList) -> &mut [Stmt] {
+        &mut self.stmts[list.start as usize..(list.start + list.len) as usize]
+    }
+}
+
+/// A node together with the `Nodes` holding its child nodes, which formats the node with all its
+/// child nodes (instead of their indices) via `Debug`.
+pub(crate) struct WithNodes<'a, T: ?Sized>(pub &'a T, pub &'a Nodes);
+
+impl fmt::Debug for WithNodes<'_, Stmt> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let nodes = self.1;
+        let expr = |id: &ExprId| WithNodes(&nodes[*id], nodes);
+        let stmt = |id: &StmtId| WithNodes(&nodes[*id], nodes);
+
+        match self.0 {
+            Stmt::IfThenElse(guard, if_block, else_block) => f
+                .debug_tuple("IfThenElse")
+                .field(&expr(guard))
+                .field(&stmt(if_block))
+                .field(&else_block.as_ref().map(stmt))
+                .finish(),
+            Stmt::While(guard, body, label) => f
+                .debug_tuple("While")
+                .field(&expr(guard))
+                .field(&stmt(body))
+                .field(label)
+                .finish(),
+            Stmt::Loop(body, label) => f
+                .debug_tuple("Loop")
+                .field(&stmt(body))
+                .field(label)
+                .finish(),
+            Stmt::For(name, iter, body, label) => f
+                .debug_tuple("For")
+                .field(name)
+                .field(&expr(iter))
+                .field(&stmt(body))
+                .field(label)
+                .finish(),
+            Stmt::Let(name, value, pos) => f
+                .debug_tuple("Let")
+                .field(name)
+                .field(&value.as_ref().map(expr))
+                .field(pos)
+                .finish(),
+            Stmt::Const(name, value, pos) => f
+                .debug_tuple("Const")
+                .field(name)
+                .field(&expr(value))
+                .field(pos)
+                .finish(),
+            Stmt::Block(block, pos) => f
+                .debug_tuple("Block")
+                .field(&WithNodes(&nodes[*block], nodes))
+                .field(pos)
+                .finish(),
+            Stmt::Expr(value) => f.debug_tuple("Expr").field(&expr(value)).finish(),
+            Stmt::ReturnWithVal(value, return_type, pos) => f
+                .debug_tuple("ReturnWithVal")
+                .field(&value.as_ref().map(expr))
+                .field(return_type)
+                .field(pos)
+                .finish(),
+            // No child nodes
+            stmt => fmt::Debug::fmt(stmt, f),
+        }
+    }
+}
+
+impl fmt::Debug for WithNodes<'_, Expr> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let nodes = self.1;
+        let expr = |id: &ExprId| WithNodes(&nodes[*id], nodes);
+        let binary = |f: &mut fmt::Formatter, name, lhs, rhs, pos| {
+            f.debug_tuple(name)
+                .field(&expr(lhs))
+                .field(&expr(rhs))
+                .field(pos)
+                .finish()
+        };
+
+        match self.0 {
+            Expr::Stmt(stmt, pos) => f
+                .debug_tuple("Stmt")
+                .field(&WithNodes(&nodes[*stmt], nodes))
+                .field(pos)
+                .finish(),
+            Expr::FunctionCall(name, args, def_val, slot, pos) => f
+                .debug_tuple("FunctionCall")
+                .field(name)
+                .field(&WithNodes(&nodes[*args], nodes))
+                .field(def_val)
+                .field(slot)
+                .field(pos)
+                .finish(),
+            Expr::Assignment(lhs, rhs, pos) => binary(f, "Assignment", lhs, rhs, pos),
+            #[cfg(not(feature = "no_object"))]
+            Expr::Dot(lhs, rhs, pos) => binary(f, "Dot", lhs, rhs, pos),
+            #[cfg(not(feature = "no_index"))]
+            Expr::Index(lhs, rhs, pos) => binary(f, "Index", lhs, rhs, pos),
+            #[cfg(not(feature = "no_index"))]
+            Expr::Array(items, pos) => f
+                .debug_tuple("Array")
+                .field(&WithNodes(&nodes[*items], nodes))
+                .field(pos)
+                .finish(),
+            #[cfg(not(feature = "no_object"))]
+            Expr::Map(items, pos) => f
+                .debug_tuple("Map")
+                .field(
+                    &items
+                        .iter()
+                        .map(|(name, value, pos)| (name, expr(value), pos))
+                        .collect::<Vec<_>>(),
+                )
+                .field(pos)
+                .finish(),
+            Expr::And(lhs, rhs, pos) => binary(f, "And", lhs, rhs, pos),
+            Expr::Or(lhs, rhs, pos) => binary(f, "Or", lhs, rhs, pos),
+            // No child nodes
+            expr => fmt::Debug::fmt(expr, f),
+        }
+    }
+}
+
+impl fmt::Debug for WithNodes<'_, [Stmt]> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let nodes = self.1;
+        f.debug_list()
+            .entries(self.0.iter().map(|stmt| WithNodes(stmt, nodes)))
+            .finish()
+    }
+}
+
+impl fmt::Debug for WithNodes<'_, [Expr]> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let nodes = self.1;
+        f.debug_list()
+            .entries(self.0.iter().map(|expr| WithNodes(expr, nodes)))
+            .finish()
+    }
+}
+
 /// A statement.
 #[derive(Debug, Clone)]
 pub enum Stmt {
     /// No-op.
     Noop(Position),
     /// if expr { stmt } else { stmt }
-    IfThenElse(Box<Expr>, Box<Stmt>, Option<Box<Stmt>>),
-    /// while expr { stmt }
-    While(Box<Expr>, Box<Stmt>),
-    /// loop { stmt }
-    Loop(Box<Stmt>),
-    /// for id in expr { stmt }
-    For(String, Box<Expr>, Box<Stmt>),
+    IfThenElse(ExprId, StmtId, Option<StmtId>),
+    /// 'label: while expr { stmt }
+    While(ExprId, StmtId, Option<String>),
+    /// 'label: loop { stmt }
+    Loop(StmtId, Option<String>),
+    /// 'label: for id in expr { stmt }
+    For(String, ExprId, StmtId, Option<String>),
     /// let id = expr
-    Let(String, Option<Box<Expr>>, Position),
+    Let(String, Option<ExprId>, Position),
     /// const id = expr
-    Const(String, Box<Expr>, Position),
+    Const(String, ExprId, Position),
     /// { stmt; ... }
-    Block(Vec<Stmt>, Position),
+    Block(StmtList, Position),
     /// { stmt }
-    Expr(Box<Expr>),
-    /// continue
-    Continue(Position),
-    /// break
-    Break(Position),
+    Expr(ExprId),
+    /// continue 'label
+    Continue(Option<String>, Position),
+    /// break 'label
+    Break(Option<String>, Position),
     /// `return`/`throw`
-    ReturnWithVal(Option<Box<Expr>>, ReturnType, Position),
+    ReturnWithVal(Option<ExprId>, ReturnType, Position),
+    /// import "path" as alias
+    #[cfg(not(feature = "no_module"))]
+    Import(String, String, Position),
+    /// import name [as alias], ... from "path"
+    #[cfg(not(feature = "no_module"))]
+    ImportFrom(Vec<(String, String)>, String, Position),
+    /// export name [as alias], ...
+    #[cfg(not(feature = "no_module"))]
+    Export(Vec<(String, String)>, Position),
 }
 
 impl Stmt {
-    /// Get the `Position` of this statement.
-    pub fn position(&self) -> Position {
+    /// Get the `Position` of this statement, whose child nodes are in `nodes`.
+    pub fn position(&self, nodes: &Nodes) -> Position {
         match self {
             Stmt::Noop(pos)
             | Stmt::Let(_, _, pos)
             | Stmt::Const(_, _, pos)
             | Stmt::Block(_, pos)
-            | Stmt::Continue(pos)
-            | Stmt::Break(pos)
+            | Stmt::Continue(_, pos)
+            | Stmt::Break(_, pos)
             | Stmt::ReturnWithVal(_, _, pos) => *pos,
-            Stmt::IfThenElse(expr, _, _) | Stmt::Expr(expr) => expr.position(),
-            Stmt::While(_, stmt) | Stmt::Loop(stmt) | Stmt::For(_, _, stmt) => stmt.position(),
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Import(_, _, pos) | Stmt::ImportFrom(_, _, pos) | Stmt::Export(_, pos) => *pos,
+            Stmt::IfThenElse(expr, _, _) | Stmt::Expr(expr) => nodes[*expr].position(nodes),
+            Stmt::While(_, stmt, _) | Stmt::Loop(stmt, _) | Stmt::For(_, _, stmt, _) => {
+                nodes[*stmt].position(nodes)
+            }
         }
     }
 
@@ -353,9 +1240,9 @@ impl Stmt {
     pub fn is_self_terminated(&self) -> bool {
         match self {
             Stmt::IfThenElse(_, _, _)
-            | Stmt::While(_, _)
-            | Stmt::Loop(_)
-            | Stmt::For(_, _, _)
+            | Stmt::While(_, _, _)
+            | Stmt::Loop(_, _)
+            | Stmt::For(_, _, _, _)
             | Stmt::Block(_, _) => true,
 
             // A No-op requires a semicolon in order to know it is an empty statement!
@@ -364,32 +1251,105 @@ impl Stmt {
             Stmt::Let(_, _, _)
             | Stmt::Const(_, _, _)
             | Stmt::Expr(_)
-            | Stmt::Continue(_)
-            | Stmt::Break(_)
+            | Stmt::Continue(_, _)
+            | Stmt::Break(_, _)
             | Stmt::ReturnWithVal(_, _, _) => false,
+
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Import(_, _, _) | Stmt::ImportFrom(_, _, _) | Stmt::Export(_, _) => false,
         }
     }
 
     /// Is this statement _pure_?
-    pub fn is_pure(&self) -> bool {
+    pub fn is_pure(&self, nodes: &Nodes) -> bool {
         match self {
             Stmt::Noop(_) => true,
-            Stmt::Expr(expr) => expr.is_pure(),
+            Stmt::Expr(expr) => nodes[*expr].is_pure(nodes),
             Stmt::IfThenElse(guard, if_block, Some(else_block)) => {
-                guard.is_pure() && if_block.is_pure() && else_block.is_pure()
+                nodes[*guard].is_pure(nodes)
+                    && nodes[*if_block].is_pure(nodes)
+                    && nodes[*else_block].is_pure(nodes)
+            }
+            Stmt::IfThenElse(guard, block, None) | Stmt::While(guard, block, _) => {
+                nodes[*guard].is_pure(nodes) && nodes[*block].is_pure(nodes)
             }
-            Stmt::IfThenElse(guard, block, None) | Stmt::While(guard, block) => {
-                guard.is_pure() && block.is_pure()
+            Stmt::Loop(block, _) => nodes[*block].is_pure(nodes),
+            Stmt::For(_, range, block, _) => {
+                nodes[*range].is_pure(nodes) && nodes[*block].is_pure(nodes)
             }
-            Stmt::Loop(block) => block.is_pure(),
-            Stmt::For(_, range, block) => range.is_pure() && block.is_pure(),
             Stmt::Let(_, _, _) | Stmt::Const(_, _, _) => false,
-            Stmt::Block(statements, _) => statements.iter().all(Stmt::is_pure),
-            Stmt::Continue(_) | Stmt::Break(_) | Stmt::ReturnWithVal(_, _, _) => false,
+            Stmt::Block(statements, _) => nodes[*statements].iter().all(|stmt| stmt.is_pure(nodes)),
+            Stmt::Continue(_, _) | Stmt::Break(_, _) | Stmt::ReturnWithVal(_, _, _) => false,
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Import(_, _, _) | Stmt::ImportFrom(_, _, _) | Stmt::Export(_, _) => false,
+        }
+    }
+
+    /// This statement with `offset` added to the indices of its child nodes.
+    pub(crate) fn offset(self, offset: NodeOffset) -> Self {
+        match self {
+            Stmt::IfThenElse(guard, if_block, else_block) => Stmt::IfThenElse(
+                guard.offset(offset),
+                if_block.offset(offset),
+                else_block.map(|stmt| stmt.offset(offset)),
+            ),
+            Stmt::While(guard, body, label) => {
+                Stmt::While(guard.offset(offset), body.offset(offset), label)
+            }
+            Stmt::Loop(body, label) => Stmt::Loop(body.offset(offset), label),
+            Stmt::For(name, expr, body, label) => {
+                Stmt::For(name, expr.offset(offset), body.offset(offset), label)
+            }
+            Stmt::Let(name, expr, pos) => {
+                Stmt::Let(name, expr.map(|expr| expr.offset(offset)), pos)
+            }
+            Stmt::Const(name, expr, pos) => Stmt::Const(name, expr.offset(offset), pos),
+            Stmt::Block(statements, pos) => Stmt::Block(statements.offset(offset), pos),
+            Stmt::Expr(expr) => Stmt::Expr(expr.offset(offset)),
+            Stmt::ReturnWithVal(expr, return_type, pos) => {
+                Stmt::ReturnWithVal(expr.map(|expr| expr.offset(offset)), return_type, pos)
+            }
+            stmt => stmt,
         }
     }
 }
 
+/// Cache of the function called by a namespace-qualified function call (e.g. `m::f(x)`), kept in
+/// the call expression and filled in by its first call.  Other function calls have no cache.
+#[derive(Default)]
+pub struct CallSlot(#[cfg(not(feature = "no_module"))] pub(crate) Option<Box<CachedCall>>);
+
+impl CallSlot {
+    /// Create the slot of a call to the function `name`.
+    #[cfg_attr(feature = "no_module", allow(unused_variables))]
+    pub(crate) fn new(name: &str) -> Self {
+        #[cfg(not(feature = "no_module"))]
+        return Self(if name.contains("::") {
+            Some(Default::default())
+        } else {
+            None
+        });
+        #[cfg(feature = "no_module")]
+        return Self();
+    }
+}
+
+impl Clone for CallSlot {
+    /// A copy of a call expression gets an empty cache of its own.
+    fn clone(&self) -> Self {
+        #[cfg(not(feature = "no_module"))]
+        return Self(self.0.as_ref().map(|_| Default::default()));
+        #[cfg(feature = "no_module")]
+        return Self();
+    }
+}
+
+impl fmt::Debug for CallSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CallSlot")
+    }
+}
+
 /// An expression.
 #[derive(Debug, Clone)]
 pub enum Expr {
@@ -407,27 +1367,33 @@ pub enum Expr {
     /// Property access.
     Property(String, Position),
     /// { stmt }
-    Stmt(Box<Stmt>, Position),
+    Stmt(StmtId, Position),
     /// func(expr, ... )
-    FunctionCall(String, Vec<Expr>, Option<Dynamic>, Position),
+    FunctionCall(
+        Cow<'static, str>,
+        ExprList,
+        Option<Dynamic>,
+        CallSlot,
+        Position,
+    ),
     /// expr = expr
-    Assignment(Box<Expr>, Box<Expr>, Position),
+    Assignment(ExprId, ExprId, Position),
     /// lhs.rhs
     #[cfg(not(feature = "no_object"))]
-    Dot(Box<Expr>, Box<Expr>, Position),
+    Dot(ExprId, ExprId, Position),
     /// expr[expr]
     #[cfg(not(feature = "no_index"))]
-    Index(Box<Expr>, Box<Expr>, Position),
+    Index(ExprId, ExprId, Position),
     #[cfg(not(feature = "no_index"))]
     /// [ expr, ... ]
-    Array(Vec<Expr>, Position),
+    Array(ExprList, Position),
     #[cfg(not(feature = "no_object"))]
     /// #{ name:expr, ... }
-    Map(Vec<(String, Expr, Position)>, Position),
+    Map(Vec<(String, ExprId, Position)>, Position),
     /// lhs && rhs
-    And(Box<Expr>, Box<Expr>),
+    And(ExprId, ExprId, Position),
     /// lhs || rhs
-    Or(Box<Expr>, Box<Expr>),
+    Or(ExprId, ExprId, Position),
     /// true
     True(Position),
     /// false
@@ -437,12 +1403,16 @@ pub enum Expr {
 }
 
 impl Expr {
-    /// Get the `Dynamic` value of a constant expression.
+    /// Get the `Dynamic` value of a constant expression, whose child nodes are in `nodes`.
     ///
     /// # Panics
     ///
     /// Panics when the expression is not constant.
-    pub fn get_constant_value(&self) -> Dynamic {
+    #[cfg_attr(
+        all(feature = "no_index", feature = "no_object"),
+        allow(unused_variables)
+    )]
+    pub fn get_constant_value(&self, nodes: &Nodes) -> Dynamic {
         match self {
             Expr::IntegerConstant(i, _) => i.into_dynamic(),
             Expr::CharConstant(c, _) => c.into_dynamic(),
@@ -452,18 +1422,20 @@ impl Expr {
             Expr::Unit(_) => ().into_dynamic(),
 
             #[cfg(not(feature = "no_index"))]
-            Expr::Array(items, _) if items.iter().all(Expr::is_constant) => items
+            Expr::Array(items, _) if self.is_constant(nodes) => nodes[*items]
                 .iter()
-                .map(Expr::get_constant_value)
-                .collect::<Vec<_>>()
+                .map(|item| item.get_constant_value(nodes))
+                .collect::<Array>()
                 .into_dynamic(),
 
             #[cfg(not(feature = "no_object"))]
-            Expr::Map(items, _) if items.iter().all(|(_, v, _)| v.is_constant()) => items
-                .iter()
-                .map(|(k, v, _)| (k.clone(), v.get_constant_value()))
-                .collect::<HashMap<_, _>>()
-                .into_dynamic(),
+            Expr::Map(items, _) if items.iter().all(|(_, v, _)| nodes[*v].is_constant(nodes)) => {
+                items
+                    .iter()
+                    .map(|(k, v, _)| (k.clone(), nodes[*v].get_constant_value(nodes)))
+                    .collect::<Map>()
+                    .into_dynamic()
+            }
 
             #[cfg(not(feature = "no_float"))]
             Expr::FloatConstant(f, _) => f.into_dynamic(),
@@ -472,12 +1444,13 @@ impl Expr {
         }
     }
 
-    /// Get the display value of a constant expression.
+    /// Get the display value of a constant expression, whose child nodes are in `nodes`.
     ///
     /// # Panics
     ///
     /// Panics when the expression is not constant.
-    pub fn get_constant_str(&self) -> String {
+    #[cfg_attr(feature = "no_index", allow(unused_variables))]
+    pub fn get_constant_str(&self, nodes: &Nodes) -> String {
         match self {
             Expr::IntegerConstant(i, _) => i.to_string(),
             Expr::CharConstant(c, _) => c.to_string(),
@@ -487,7 +1460,7 @@ impl Expr {
             Expr::Unit(_) => "()".to_string(),
 
             #[cfg(not(feature = "no_index"))]
-            Expr::Array(items, _) if items.iter().all(Expr::is_constant) => "array".to_string(),
+            Expr::Array(_, _) if self.is_constant(nodes) => "array".to_string(),
 
             #[cfg(not(feature = "no_float"))]
             Expr::FloatConstant(f, _) => f.to_string(),
@@ -496,8 +1469,8 @@ impl Expr {
         }
     }
 
-    /// Get the `Position` of the expression.
-    pub fn position(&self) -> Position {
+    /// Get the `Position` of the expression, whose child nodes are in `nodes`.
+    pub fn position(&self, nodes: &Nodes) -> Position {
         match self {
             Expr::IntegerConstant(_, pos)
             | Expr::CharConstant(_, pos)
@@ -505,17 +1478,17 @@ impl Expr {
             | Expr::Variable(_, pos)
             | Expr::Property(_, pos)
             | Expr::Stmt(_, pos)
-            | Expr::FunctionCall(_, _, _, pos)
+            | Expr::FunctionCall(_, _, _, _, pos)
             | Expr::True(pos)
             | Expr::False(pos)
             | Expr::Unit(pos) => *pos,
 
-            Expr::Assignment(expr, _, _) | Expr::And(expr, _) | Expr::Or(expr, _) => {
-                expr.position()
+            Expr::Assignment(expr, _, _) | Expr::And(expr, _, _) | Expr::Or(expr, _, _) => {
+                nodes[*expr].position(nodes)
             }
 
             #[cfg(not(feature = "no_object"))]
-            Expr::Dot(expr, _, _) => expr.position(),
+            Expr::Dot(expr, _, _) => nodes[*expr].position(nodes),
 
             #[cfg(not(feature = "no_float"))]
             Expr::FloatConstant(_, pos) => *pos,
@@ -527,31 +1500,34 @@ impl Expr {
             Expr::Map(_, pos) => *pos,
 
             #[cfg(not(feature = "no_index"))]
-            Expr::Index(expr, _, _) => expr.position(),
+            Expr::Index(expr, _, _) => nodes[*expr].position(nodes),
         }
     }
 
     /// Is the expression pure?
     ///
     /// A pure expression has no side effects.
-    pub fn is_pure(&self) -> bool {
+    pub fn is_pure(&self, nodes: &Nodes) -> bool {
         match self {
             #[cfg(not(feature = "no_index"))]
-            Expr::Array(expressions, _) => expressions.iter().all(Expr::is_pure),
+            Expr::Array(expressions, _) => nodes[*expressions].iter().all(|x| x.is_pure(nodes)),
 
             #[cfg(not(feature = "no_index"))]
-            Expr::Index(x, y, _) => x.is_pure() && y.is_pure(),
+            Expr::Index(x, y, _) => nodes[*x].is_pure(nodes) && nodes[*y].is_pure(nodes),
 
-            Expr::And(x, y) | Expr::Or(x, y) => x.is_pure() && y.is_pure(),
+            Expr::And(x, y, _) | Expr::Or(x, y, _) => {
+                nodes[*x].is_pure(nodes) && nodes[*y].is_pure(nodes)
+            }
 
-            Expr::Stmt(stmt, _) => stmt.is_pure(),
+            Expr::Stmt(stmt, _) => nodes[*stmt].is_pure(nodes),
 
-            expr => expr.is_constant() || matches!(expr, Expr::Variable(_, _)),
+            expr => expr.is_constant(nodes) || matches!(expr, Expr::Variable(_, _)),
         }
     }
 
     /// Is the expression a constant?
-    pub fn is_constant(&self) -> bool {
+    #[cfg_attr(feature = "no_index", allow(unused_variables))]
+    pub fn is_constant(&self, nodes: &Nodes) -> bool {
         match self {
             Expr::IntegerConstant(_, _)
             | Expr::CharConstant(_, _)
@@ -565,11 +1541,64 @@ impl Expr {
 
             // An array literal is constant if all items are constant
             #[cfg(not(feature = "no_index"))]
-            Expr::Array(expressions, _) => expressions.iter().all(Expr::is_constant),
+            Expr::Array(expressions, _) => nodes[*expressions].iter().all(|x| x.is_constant(nodes)),
 
             _ => false,
         }
     }
+
+    /// This expression with `offset` added to the indices of its child nodes.
+    fn offset(self, offset: NodeOffset) -> Self {
+        match self {
+            Expr::Stmt(stmt, pos) => Expr::Stmt(stmt.offset(offset), pos),
+            Expr::FunctionCall(name, args, def_val, slot, pos) => {
+                Expr::FunctionCall(name, args.offset(offset), def_val, slot, pos)
+            }
+            Expr::Assignment(lhs, rhs, pos) => {
+                Expr::Assignment(lhs.offset(offset), rhs.offset(offset), pos)
+            }
+            #[cfg(not(feature = "no_object"))]
+            Expr::Dot(lhs, rhs, pos) => Expr::Dot(lhs.offset(offset), rhs.offset(offset), pos),
+            #[cfg(not(feature = "no_index"))]
+            Expr::Index(lhs, rhs, pos) => Expr::Index(lhs.offset(offset), rhs.offset(offset), pos),
+            #[cfg(not(feature = "no_index"))]
+            Expr::Array(items, pos) => Expr::Array(items.offset(offset), pos),
+            #[cfg(not(feature = "no_object"))]
+            Expr::Map(items, pos) => Expr::Map(
+                items
+                    .into_iter()
+                    .map(|(key, expr, pos)| (key, expr.offset(offset), pos))
+                    .collect(),
+                pos,
+            ),
+            Expr::And(lhs, rhs, pos) => Expr::And(lhs.offset(offset), rhs.offset(offset), pos),
+            Expr::Or(lhs, rhs, pos) => Expr::Or(lhs.offset(offset), rhs.offset(offset), pos),
+            expr => expr,
+        }
+    }
+}
+
+/// How a custom keyword registered via `Engine::register_custom_keyword` is used in scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CustomKeyword {
+    /// `keyword expr`, calling the function `keyword(expr)`.
+    /// Like unary operators, the keyword binds tighter than any binary operator.
+    Prefix,
+    /// `lhs keyword rhs`, calling the function `keyword(lhs, rhs)`.
+    /// Wrapped value is the precedence of the keyword as a binary operator (see `Token::precedence`).
+    Infix(u8),
+}
+
+/// Which variable declarations (via `let`, `const` or `for`) may reuse the name of a variable
+/// already declared, hiding it.  Set via `Engine::set_shadowing`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shadowing {
+    /// Variables may always be redeclared (the default).
+    Allowed,
+    /// Variables may not be redeclared in the same block, but may be shadowed in inner blocks.
+    NotInSameBlock,
+    /// Variables may not be redeclared at all, not even in inner blocks.
+    Forbidden,
 }
 
 /// Tokens.
@@ -579,6 +1608,7 @@ pub enum Token {
     #[cfg(not(feature = "no_float"))]
     FloatConstant(FLOAT),
     Identifier(String),
+    Label(String),
     CharConstant(char),
     StringConst(String),
     LeftBrace,
@@ -601,6 +1631,7 @@ pub enum Token {
     RightShift,
     SemiColon,
     Colon,
+    DoubleColon,
     Comma,
     Period,
     #[cfg(not(feature = "no_object"))]
@@ -630,6 +1661,16 @@ pub enum Token {
     And,
     #[cfg(not(feature = "no_function"))]
     Fn,
+    #[cfg(not(feature = "no_function"))]
+    Private,
+    #[cfg(not(feature = "no_module"))]
+    Import,
+    #[cfg(not(feature = "no_module"))]
+    Export,
+    #[cfg(not(feature = "no_module"))]
+    As,
+    #[cfg(not(feature = "no_module"))]
+    From,
     Continue,
     Break,
     Return,
@@ -645,20 +1686,25 @@ pub enum Token {
     XOrAssign,
     ModuloAssign,
     PowerOfAssign,
+    Custom(String, CustomKeyword),
+    DocComment(String),
+    Comment(String),
     LexError(Box<LexError>),
 }
 
 impl Token {
     /// Get the syntax of the token.
-    pub fn syntax(&self) -> Cow<str> {
+    pub fn syntax(&self) -> Cow<'_, str> {
         use self::Token::*;
 
         match self {
             IntegerConstant(i) => i.to_string().into(),
             #[cfg(not(feature = "no_float"))]
             FloatConstant(f) => f.to_string().into(),
-            Identifier(s) => s.into(),
+            Identifier(s) | Custom(s, _) => s.into(),
+            Label(s) => format!("'{}", s).into(),
             CharConstant(c) => c.to_string().into(),
+            DocComment(s) | Comment(s) => s.into(),
             LexError(err) => err.to_string().into(),
 
             token => (match token {
@@ -679,6 +1725,7 @@ impl Token {
                 Divide => "/",
                 SemiColon => ";",
                 Colon => ":",
+                DoubleColon => "::",
                 Comma => ",",
                 Period => ".",
                 #[cfg(not(feature = "no_object"))]
@@ -705,6 +1752,16 @@ impl Token {
                 And => "&&",
                 #[cfg(not(feature = "no_function"))]
                 Fn => "fn",
+                #[cfg(not(feature = "no_function"))]
+                Private => "private",
+                #[cfg(not(feature = "no_module"))]
+                Import => "import",
+                #[cfg(not(feature = "no_module"))]
+                Export => "export",
+                #[cfg(not(feature = "no_module"))]
+                As => "as",
+                #[cfg(not(feature = "no_module"))]
+                From => "from",
                 Continue => "continue",
                 Break => "break",
                 Return => "return",
@@ -785,6 +1842,7 @@ impl Token {
             Throw            |
             PowerOf          |
             In               |
+            Custom(_, _)     |
             PowerOfAssign => true,
 
             #[cfg(not(feature = "no_index"))]
@@ -832,6 +1890,8 @@ impl Token {
 
             Self::Period => 120,
 
+            Self::Custom(_, CustomKeyword::Infix(precedence)) => *precedence,
+
             _ => 0,
         }
     }
@@ -861,6 +1921,30 @@ impl Token {
     }
 }
 
+/// Is a character valid as the first letter of an identifier?
+///
+/// Under the `unicode-xid-ident` feature, this follows the Unicode `XID_Start` property.
+#[cfg(not(feature = "unicode-xid-ident"))]
+fn is_id_first_letter(ch: char) -> bool {
+    ch.is_ascii_alphabetic()
+}
+#[cfg(feature = "unicode-xid-ident")]
+fn is_id_first_letter(ch: char) -> bool {
+    UnicodeXID::is_xid_start(ch)
+}
+
+/// Is a character valid within an identifier?
+///
+/// Under the `unicode-xid-ident` feature, this follows the Unicode `XID_Continue` property.
+#[cfg(not(feature = "unicode-xid-ident"))]
+fn is_id_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+#[cfg(feature = "unicode-xid-ident")]
+fn is_id_char(ch: char) -> bool {
+    UnicodeXID::is_xid_continue(ch)
+}
+
 /// An iterator on a `Token` stream.
 pub struct TokenIterator<'a> {
     /// Can the next token be a unary operator?
@@ -869,9 +1953,55 @@ pub struct TokenIterator<'a> {
     pos: Position,
     /// The input characters stream.
     stream: Peekable<Chars<'a>>,
+    /// Doc-comments collected since the last token, if doc-comments are captured.
+    doc_comments: Option<Vec<(String, Position)>>,
+    /// Return all comments as `Token::Comment`?
+    comments: bool,
+    /// Tokens already lexed but not yet returned, in reverse order.
+    pending: Vec<(Token, Position)>,
+    /// Symbols that are disabled and must not appear in the script.
+    disabled_symbols: Option<&'a HashSet<String>>,
+    /// Identifiers that are custom keywords.
+    custom_keywords: Option<&'a HashMap<String, CustomKeyword>>,
+    /// Keywords, operators and literal forms allowed by the dialect, if restricted.
+    dialect: Option<&'a DialectRules>,
+    /// Text of the identifier or number being lexed, kept between tokens so that it is not
+    /// allocated afresh for every token.
+    buffer: String,
 }
 
 impl<'a> TokenIterator<'a> {
+    /// Turn any of the specified symbols into `Token::LexError` when encountered.
+    pub(crate) fn with_disabled_symbols(mut self, disabled: &'a HashSet<String>) -> Self {
+        if !disabled.is_empty() {
+            self.disabled_symbols = Some(disabled);
+        }
+        self
+    }
+    /// Turn any symbols or literal forms not allowed by the dialect into `Token::LexError`.
+    pub(crate) fn with_dialect(mut self, dialect: Option<&'a DialectRules>) -> Self {
+        self.dialect = dialect;
+        self
+    }
+    /// Turn identifiers that are custom keywords into `Token::Custom`.
+    pub(crate) fn with_custom_keywords(
+        mut self,
+        keywords: &'a HashMap<String, CustomKeyword>,
+    ) -> Self {
+        if !keywords.is_empty() {
+            self.custom_keywords = Some(keywords);
+        }
+        self
+    }
+    /// Start counting positions from the specified position, instead of the beginning of the script.
+    ///
+    /// Only the first line is offset by the column of `start`, later lines start at their first column.
+    pub(crate) fn with_start_position(mut self, start: Position) -> Self {
+        let line = start.line().unwrap_or(1);
+        let column = start.position().unwrap_or(1);
+        self.pos = Position::new(line, column - 1);
+        self
+    }
     /// Consume the next character.
     fn eat_next(&mut self) {
         self.stream.next();
@@ -893,14 +2023,37 @@ impl<'a> TokenIterator<'a> {
     fn new_line(&mut self) {
         self.pos.new_line()
     }
+    /// Start a buffer for the text of a comment, if comments or doc-comments are captured.
+    fn comment_buffer(&self, marker: &str) -> Option<String> {
+        if self.comments || self.doc_comments.is_some() {
+            Some(marker.into())
+        } else {
+            None
+        }
+    }
+    /// Return a comment as a token if all comments are returned.
+    /// Otherwise, keep it if it is a doc-comment and doc-comments are captured.
+    fn add_comment(&mut self, comment: Option<String>, pos: Position) -> Option<(Token, Position)> {
+        if self.comments {
+            return comment.map(|comment| (Token::Comment(comment), pos));
+        }
+
+        if let (Some(comments), Some(comment)) = (self.doc_comments.as_mut(), comment) {
+            if is_doc_comment(&comment) {
+                comments.push((comment, pos));
+            }
+        }
+
+        None
+    }
 
     /// Parse a string literal wrapped by `enclosing_char`.
     pub fn parse_string_literal(
         &mut self,
         enclosing_char: char,
     ) -> Result<String, (LexError, Position)> {
-        let mut result = Vec::new();
-        let mut escape = String::with_capacity(12);
+        let mut result = String::new();
+        let mut escape = String::new();
 
         loop {
             let next_char = self.stream.next();
@@ -990,7 +2143,7 @@ impl<'a> TokenIterator<'a> {
             }
         }
 
-        Ok(result.iter().collect())
+        Ok(result)
     }
 
     /// Get the next token.
@@ -1008,8 +2161,9 @@ impl<'a> TokenIterator<'a> {
 
                 // digit ...
                 ('0'..='9', _) => {
-                    let mut result = Vec::new();
+                    let mut result = mem::take(&mut self.buffer);
                     let mut radix_base: Option<u32> = None;
+                    result.clear();
                     result.push(c);
 
                     while let Some(&next_char) = self.stream.peek() {
@@ -1081,46 +2235,44 @@ impl<'a> TokenIterator<'a> {
                     }
 
                     // Parse number
-                    if let Some(radix) = radix_base {
-                        let out: String = result.iter().skip(2).filter(|&&c| c != '_').collect();
-
-                        return Some((
-                            INT::from_str_radix(&out, radix)
-                                .map(Token::IntegerConstant)
-                                .unwrap_or_else(|_| {
-                                    Token::LexError(Box::new(LERR::MalformedNumber(
-                                        result.iter().collect(),
-                                    )))
-                                }),
-                            pos,
-                        ));
+                    let token = if let Some(radix) = radix_base {
+                        let out: String = result.chars().skip(2).filter(|&c| c != '_').collect();
+
+                        INT::from_str_radix(&out, radix)
+                            .map(Token::IntegerConstant)
+                            .unwrap_or_else(|_| {
+                                Token::LexError(Box::new(LERR::MalformedNumber(result.clone())))
+                            })
                     } else {
-                        let out: String = result.iter().filter(|&&c| c != '_').collect();
+                        let out: Cow<str> = if result.contains('_') {
+                            result.replace('_', "").into()
+                        } else {
+                            result.as_str().into()
+                        };
                         let num = INT::from_str(&out).map(Token::IntegerConstant);
 
                         // If integer parsing is unnecessary, try float instead
                         #[cfg(not(feature = "no_float"))]
                         let num = num.or_else(|_| FLOAT::from_str(&out).map(Token::FloatConstant));
 
-                        return Some((
-                            num.unwrap_or_else(|_| {
-                                Token::LexError(Box::new(LERR::MalformedNumber(
-                                    result.iter().collect(),
-                                )))
-                            }),
-                            pos,
-                        ));
-                    }
+                        num.unwrap_or_else(|_| {
+                            Token::LexError(Box::new(LERR::MalformedNumber(result.clone())))
+                        })
+                    };
+
+                    self.buffer = result;
+                    return Some((token, pos));
                 }
 
                 // letter or underscore ...
-                ('A'..='Z', _) | ('a'..='z', _) | ('_', _) => {
-                    let mut result = Vec::new();
+                (ch, _) if ch == '_' || is_id_first_letter(ch) => {
+                    let mut result = mem::take(&mut self.buffer);
+                    result.clear();
                     result.push(c);
 
                     while let Some(&next_char) = self.stream.peek() {
                         match next_char {
-                            x if x.is_ascii_alphanumeric() || x == '_' => {
+                            x if is_id_char(x) => {
                                 result.push(x);
                                 self.eat_next();
                             }
@@ -1129,22 +2281,23 @@ impl<'a> TokenIterator<'a> {
                     }
 
                     let is_valid_identifier = result
-                        .iter()
-                        .find(|&ch| char::is_ascii_alphanumeric(ch)) // first alpha-numeric character
-                        .map(char::is_ascii_alphabetic) // is a letter
-                        .unwrap_or(false); // if no alpha-numeric at all - syntax error
-
-                    let identifier: String = result.iter().collect();
-
-                    if !is_valid_identifier {
-                        return Some((
-                            Token::LexError(Box::new(LERR::MalformedIdentifier(identifier))),
-                            pos,
-                        ));
-                    }
-
-                    return Some((
-                        match identifier.as_str() {
+                        .chars()
+                        .find(|&ch| ch != '_') // first character after leading underscores
+                        .map(is_id_first_letter) // is a letter
+                        .unwrap_or(false); // if only underscores - syntax error
+
+                    // Normalize so that identifiers that look the same are the same
+                    #[cfg(feature = "unicode-xid-ident")]
+                    let normalized: String = result.chars().nfkc().collect();
+                    #[cfg(feature = "unicode-xid-ident")]
+                    let identifier = normalized.as_str();
+                    #[cfg(not(feature = "unicode-xid-ident"))]
+                    let identifier = result.as_str();
+
+                    let token = if !is_valid_identifier {
+                        Token::LexError(Box::new(LERR::MalformedIdentifier(identifier.into())))
+                    } else {
+                        match identifier {
                             "true" => Token::True,
                             "false" => Token::False,
                             "let" => Token::Let,
@@ -1162,11 +2315,24 @@ impl<'a> TokenIterator<'a> {
 
                             #[cfg(not(feature = "no_function"))]
                             "fn" => Token::Fn,
+                            #[cfg(not(feature = "no_function"))]
+                            "private" => Token::Private,
+
+                            #[cfg(not(feature = "no_module"))]
+                            "import" => Token::Import,
+                            #[cfg(not(feature = "no_module"))]
+                            "export" => Token::Export,
+                            #[cfg(not(feature = "no_module"))]
+                            "as" => Token::As,
+                            #[cfg(not(feature = "no_module"))]
+                            "from" => Token::From,
+
+                            _ => Token::Identifier(identifier.into()),
+                        }
+                    };
 
-                            _ => Token::Identifier(identifier),
-                        },
-                        pos,
-                    ));
+                    self.buffer = result;
+                    return Some((token, pos));
                 }
 
                 // " - string literal
@@ -1184,6 +2350,38 @@ impl<'a> TokenIterator<'a> {
                         pos,
                     ));
                 }
+                // 'label - loop label, unless it is a character literal
+                ('\'', ch) if ch == '_' || is_id_first_letter(ch) => {
+                    let mut result = String::new();
+
+                    while let Some(&next_char) = self.stream.peek() {
+                        if !is_id_char(next_char) {
+                            break;
+                        }
+                        result.push(next_char);
+                        self.eat_next();
+                    }
+
+                    if self.stream.peek() != Some(&'\'') {
+                        return Some((Token::Label(result), pos));
+                    }
+
+                    self.eat_next();
+
+                    // 'label': - a character literal is never followed by a colon
+                    if self.stream.peek() == Some(&':') {
+                        return Some((Token::Label(result), pos));
+                    }
+
+                    let mut chars = result.chars();
+                    let first = chars.next();
+
+                    return Some(if chars.next().is_some() {
+                        (Token::LexError(Box::new(LERR::MalformedChar(result))), pos)
+                    } else {
+                        (Token::CharConstant(first.expect("should be Some")), pos)
+                    });
+                }
                 ('\'', _) => {
                     return Some(self.parse_string_literal('\'').map_or_else(
                         |err| (Token::LexError(Box::new(err.0)), err.1),
@@ -1248,37 +2446,56 @@ impl<'a> TokenIterator<'a> {
                 ('/', '/') => {
                     self.eat_next();
 
+                    let mut comment = self.comment_buffer("//");
+
                     while let Some(c) = self.stream.next() {
                         if c == '\n' {
                             self.new_line();
                             break;
                         }
 
+                        if let Some(comment) = comment.as_mut() {
+                            comment.push(c);
+                        }
                         self.advance();
                     }
+
+                    if let Some(token) = self.add_comment(comment, pos) {
+                        return Some(token);
+                    }
                 }
                 ('/', '*') => {
                     let mut level = 1;
 
                     self.eat_next();
 
+                    let mut comment = self.comment_buffer("/*");
+
                     while let Some(c) = self.stream.next() {
                         self.advance();
 
-                        match c {
-                            '/' => {
-                                if self.stream.next() == Some('*') {
-                                    level += 1;
+                        if let Some(comment) = comment.as_mut() {
+                            comment.push(c);
+                        }
+
+                        match (c, self.stream.peek().copied().unwrap_or('\0')) {
+                            ('/', '*') => {
+                                self.eat_next();
+                                level += 1;
+
+                                if let Some(comment) = comment.as_mut() {
+                                    comment.push('*');
                                 }
-                                self.advance();
                             }
-                            '*' => {
-                                if self.stream.next() == Some('/') {
-                                    level -= 1;
+                            ('*', '/') => {
+                                self.eat_next();
+                                level -= 1;
+
+                                if let Some(comment) = comment.as_mut() {
+                                    comment.push('/');
                                 }
-                                self.advance();
                             }
-                            '\n' => self.new_line(),
+                            ('\n', _) => self.new_line(),
                             _ => (),
                         }
 
@@ -1286,6 +2503,10 @@ impl<'a> TokenIterator<'a> {
                             break;
                         }
                     }
+
+                    if let Some(token) = self.add_comment(comment, pos) {
+                        return Some(token);
+                    }
                 }
 
                 ('/', '=') => {
@@ -1295,6 +2516,10 @@ impl<'a> TokenIterator<'a> {
                 ('/', _) => return Some((Token::Divide, pos)),
 
                 (';', _) => return Some((Token::SemiColon, pos)),
+                (':', ':') => {
+                    self.eat_next();
+                    return Some((Token::DoubleColon, pos));
+                }
                 (':', _) => return Some((Token::Colon, pos)),
                 (',', _) => return Some((Token::Comma, pos)),
                 ('.', _) => return Some((Token::Period, pos)),
@@ -1400,35 +2625,160 @@ impl<'a> Iterator for TokenIterator<'a> {
     type Item = (Token, Position);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner_next().map(|x| {
-            // Save the last token
-            self.can_be_unary = x.0.is_next_unary();
-            x
-        })
+        if let Some(x) = self.pending.pop() {
+            return Some(x);
+        }
+
+        let mut x = self.inner_next()?;
+
+        // Comments do not affect the tokens around them
+        if let Token::Comment(_) = x.0 {
+            return Some(x);
+        }
+
+        // Custom keywords are identifiers that turn into keyword tokens
+        if let (Some(keywords), Token::Identifier(name)) = (self.custom_keywords, &x.0) {
+            if let Some(&keyword) = keywords.get(name) {
+                x.0 = Token::Custom(name.clone(), keyword);
+            }
+        }
+
+        // Disabled symbols turn into errors
+        if let Some(disabled) = self.disabled_symbols {
+            let symbol = match &x.0 {
+                Token::IntegerConstant(_)
+                | Token::Label(_)
+                | Token::CharConstant(_)
+                | Token::StringConst(_)
+                | Token::DocComment(_)
+                | Token::LexError(_) => None,
+                #[cfg(not(feature = "no_float"))]
+                Token::FloatConstant(_) => None,
+                token => Some(token.syntax()),
+            };
+
+            if let Some(symbol) = symbol.filter(|s| disabled.contains(s.as_ref())) {
+                x.0 = Token::LexError(Box::new(LexError::DisabledSymbol(symbol.into_owned())));
+            }
+        }
+
+        if let Some(symbol) = self.dialect.and_then(|dialect| dialect.check(&x.0)) {
+            x.0 = Token::LexError(Box::new(LexError::DisabledSymbol(symbol.into_owned())));
+        }
+
+        // Save the last token
+        self.can_be_unary = x.0.is_next_unary();
+
+        // Doc-comments are only kept when immediately preceding a function definition
+        match self.doc_comments.as_mut() {
+            #[cfg(not(feature = "no_function"))]
+            Some(comments)
+                if !comments.is_empty() && (x.0 == Token::Fn || x.0 == Token::Private) =>
+            {
+                self.pending.push(x);
+                self.pending.extend(
+                    comments
+                        .drain(..)
+                        .rev()
+                        .map(|(comment, pos)| (Token::DocComment(comment), pos)),
+                );
+                self.pending.pop()
+            }
+            Some(comments) => {
+                comments.clear();
+                Some(x)
+            }
+            None => Some(x),
+        }
     }
 }
 
+/// Is a comment a doc-comment?
+///
+/// Doc-comments start with `///` or `/**`, but not `////` or `/***`.
+fn is_doc_comment(comment: &str) -> bool {
+    (comment.starts_with("///") && !comment.starts_with("////"))
+        || (comment.starts_with("/**") && !comment.starts_with("/***") && comment != "/**/")
+}
+
 /// Tokenize an input text stream.
 pub fn lex(input: &str) -> TokenIterator<'_> {
     TokenIterator {
         can_be_unary: true,
         pos: Position::new(1, 0),
         stream: input.chars().peekable(),
+        doc_comments: None,
+        comments: false,
+        pending: Vec::new(),
+        disabled_symbols: None,
+        custom_keywords: None,
+        dialect: None,
+        buffer: String::new(),
+    }
+}
+
+/// Tokenize an input text stream, returning all comments as `Token::Comment`.
+pub fn lex_with_comments(input: &str) -> TokenIterator<'_> {
+    TokenIterator {
+        comments: true,
+        ..lex(input)
+    }
+}
+
+/// Tokenize an input text stream, keeping doc-comments that precede function definitions
+/// as `Token::DocComment`.
+pub fn lex_with_doc_comments(input: &str) -> TokenIterator<'_> {
+    TokenIterator {
+        doc_comments: Some(Vec::new()),
+        ..lex(input)
+    }
+}
+
+/// The token stream being parsed, together with the nodes of the statements being built.
+struct ParseState<'a> {
+    /// The token stream.
+    tokens: Peekable<TokenIterator<'a>>,
+    /// Child nodes of the statements parsed, or of the body of the function being parsed.
+    nodes: Nodes,
+}
+
+impl<'a> ParseState<'a> {
+    /// Create a new `ParseState` over a token stream.
+    fn new(tokens: TokenIterator<'a>) -> Self {
+        Self {
+            tokens: tokens.peekable(),
+            nodes: Default::default(),
+        }
+    }
+}
+
+impl<'a> Deref for ParseState<'a> {
+    type Target = Peekable<TokenIterator<'a>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tokens
+    }
+}
+
+impl DerefMut for ParseState<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.tokens
     }
 }
 
 /// Parse ( expr )
 fn parse_paren_expr<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
     begin: Position,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Expr, ParseError> {
     if matches!(input.peek(), Some((Token::RightParen, _))) {
         input.next();
         return Ok(Expr::Unit(begin));
     }
 
-    let expr = parse_expr(input, allow_stmt_expr)?;
+    let expr = parse_expr(input, allow_stmt_expr, level + 1)?;
 
     match input.next() {
         // ( xxx )
@@ -1450,12 +2800,11 @@ fn parse_paren_expr<'a>(
 /// Parse a function call.
 fn parse_call_expr<'a>(
     id: String,
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
     begin: Position,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Expr, ParseError> {
-    let mut args_expr_list = Vec::new();
-
     // id()
     if let (Token::RightParen, _) = input.peek().ok_or_else(|| {
         PERR::MissingToken(
@@ -1465,11 +2814,20 @@ fn parse_call_expr<'a>(
         .into_err_eof()
     })? {
         input.next();
-        return Ok(Expr::FunctionCall(id, args_expr_list, None, begin));
+        let slot = CallSlot::new(&id);
+        return Ok(Expr::FunctionCall(
+            id.into(),
+            Default::default(),
+            None,
+            slot,
+            begin,
+        ));
     }
 
+    let mut args_expr_list = Vec::new();
+
     loop {
-        args_expr_list.push(parse_expr(input, allow_stmt_expr)?);
+        args_expr_list.push(parse_expr(input, allow_stmt_expr, level + 1)?);
 
         match input.peek().ok_or_else(|| {
             PERR::MissingToken(
@@ -1480,7 +2838,9 @@ fn parse_call_expr<'a>(
         })? {
             (Token::RightParen, _) => {
                 input.next();
-                return Ok(Expr::FunctionCall(id, args_expr_list, None, begin));
+                let slot = CallSlot::new(&id);
+                let args = input.nodes.add_exprs(args_expr_list);
+                return Ok(Expr::FunctionCall(id.into(), args, None, slot, begin));
             }
             (Token::Comma, _) => (),
             (_, pos) => {
@@ -1499,12 +2859,13 @@ fn parse_call_expr<'a>(
 /// Parse an indexing expression.
 #[cfg(not(feature = "no_index"))]
 fn parse_index_expr<'a>(
-    lhs: Box<Expr>,
-    input: &mut Peekable<TokenIterator<'a>>,
+    lhs: Expr,
+    input: &mut ParseState<'a>,
     pos: Position,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Expr, ParseError> {
-    let idx_expr = parse_expr(input, allow_stmt_expr)?;
+    let idx_expr = parse_expr(input, allow_stmt_expr, level + 1)?;
 
     // Check type of indexing - must be integer or string
     match &idx_expr {
@@ -1516,7 +2877,7 @@ fn parse_index_expr<'a>(
             ))
             .into_err(*pos))
         }
-        Expr::IntegerConstant(_, pos) => match *lhs {
+        Expr::IntegerConstant(_, pos) => match lhs {
             Expr::Array(_, _) | Expr::StringConstant(_, _) => (),
 
             #[cfg(not(feature = "no_object"))]
@@ -1530,6 +2891,8 @@ fn parse_index_expr<'a>(
             Expr::FloatConstant(_, pos)
             | Expr::CharConstant(_, pos)
             | Expr::Assignment(_, _, pos)
+            | Expr::And(_, _, pos)
+            | Expr::Or(_, _, pos)
             | Expr::Unit(pos)
             | Expr::True(pos)
             | Expr::False(pos) => {
@@ -1539,18 +2902,11 @@ fn parse_index_expr<'a>(
                 .into_err(pos))
             }
 
-            Expr::And(lhs, _) | Expr::Or(lhs, _) => {
-                return Err(PERR::MalformedIndexExpr(
-                    "Only arrays, object maps and strings can be indexed".into(),
-                )
-                .into_err(lhs.position()))
-            }
-
             _ => (),
         },
 
         // lhs[string]
-        Expr::StringConstant(_, pos) => match *lhs {
+        Expr::StringConstant(_, pos) => match lhs {
             #[cfg(not(feature = "no_object"))]
             Expr::Map(_, _) => (),
 
@@ -1563,6 +2919,8 @@ fn parse_index_expr<'a>(
             Expr::FloatConstant(_, pos)
             | Expr::CharConstant(_, pos)
             | Expr::Assignment(_, _, pos)
+            | Expr::And(_, _, pos)
+            | Expr::Or(_, _, pos)
             | Expr::Unit(pos)
             | Expr::True(pos)
             | Expr::False(pos) => {
@@ -1572,13 +2930,6 @@ fn parse_index_expr<'a>(
                 .into_err(pos))
             }
 
-            Expr::And(lhs, _) | Expr::Or(lhs, _) => {
-                return Err(PERR::MalformedIndexExpr(
-                    "Only arrays, object maps and strings can be indexed".into(),
-                )
-                .into_err(lhs.position()))
-            }
-
             _ => (),
         },
 
@@ -1605,11 +2956,11 @@ fn parse_index_expr<'a>(
             .into_err(*pos))
         }
         // lhs[??? && ???], lhs[??? || ???]
-        Expr::And(lhs, _) | Expr::Or(lhs, _) => {
+        Expr::And(_, _, pos) | Expr::Or(_, _, pos) => {
             return Err(PERR::MalformedIndexExpr(
                 "Array access expects integer index, not a boolean".into(),
             )
-            .into_err(lhs.position()))
+            .into_err(*pos))
         }
         // lhs[true], lhs[false]
         Expr::True(pos) | Expr::False(pos) => {
@@ -1632,7 +2983,9 @@ fn parse_index_expr<'a>(
     })? {
         (Token::RightBracket, _) => {
             input.next();
-            Ok(Expr::Index(lhs, Box::new(idx_expr), pos))
+            let lhs = input.nodes.add_expr(lhs);
+            let idx_expr = input.nodes.add_expr(idx_expr);
+            Ok(Expr::Index(lhs, idx_expr, pos))
         }
         (_, pos) => Err(PERR::MissingToken(
             "]".into(),
@@ -1644,16 +2997,35 @@ fn parse_index_expr<'a>(
 
 /// Parse an expression that begins with an identifier.
 fn parse_ident_expr<'a>(
-    id: String,
-    input: &mut Peekable<TokenIterator<'a>>,
+    mut id: String,
+    input: &mut ParseState<'a>,
     begin: Position,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Expr, ParseError> {
+    // id::id... - namespace-qualified name of a function or a constant provided by the host
+    while let Some((Token::DoubleColon, _)) = input.peek() {
+        input.next();
+
+        match input.next() {
+            Some((Token::Identifier(s), _)) => {
+                id.push_str("::");
+                id.push_str(&s);
+            }
+            Some((_, pos)) => {
+                return Err(
+                    PERR::BadInput(format!("Expecting a name after '{}::'", id)).into_err(pos)
+                )
+            }
+            None => return Err(PERR::UnexpectedEOF.into_err_eof()),
+        }
+    }
+
     match input.peek() {
         // id(...) - function call
         Some((Token::LeftParen, _)) => {
             input.next();
-            parse_call_expr(id, input, begin, allow_stmt_expr)
+            parse_call_expr(id, input, begin, allow_stmt_expr, level)
         }
         // id[...] - indexing
         #[cfg(not(feature = "no_index"))]
@@ -1661,10 +3033,11 @@ fn parse_ident_expr<'a>(
             let pos = *pos;
             input.next();
             parse_index_expr(
-                Box::new(Expr::Variable(id, begin)),
+                Expr::Variable(id, begin),
                 input,
                 pos,
                 allow_stmt_expr,
+                level,
             )
         }
         // id - variable
@@ -1677,15 +3050,16 @@ fn parse_ident_expr<'a>(
 /// Parse an array literal.
 #[cfg(not(feature = "no_index"))]
 fn parse_array_literal<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
     begin: Position,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Expr, ParseError> {
     let mut arr = Vec::new();
 
     if !matches!(input.peek(), Some((Token::RightBracket, _))) {
         while input.peek().is_some() {
-            arr.push(parse_expr(input, allow_stmt_expr)?);
+            arr.push(parse_expr(input, allow_stmt_expr, level + 1)?);
 
             match input.peek().ok_or_else(|| {
                 PERR::MissingToken("]".into(), "to end this array literal".into()).into_err_eof()
@@ -1710,7 +3084,7 @@ fn parse_array_literal<'a>(
     })? {
         (Token::RightBracket, _) => {
             input.next();
-            Ok(Expr::Array(arr, begin))
+            Ok(Expr::Array(input.nodes.add_exprs(arr), begin))
         }
         (_, pos) => {
             Err(PERR::MissingToken("]".into(), "to end this array literal".into()).into_err(*pos))
@@ -1721,9 +3095,10 @@ fn parse_array_literal<'a>(
 /// Parse a map literal.
 #[cfg(not(feature = "no_object"))]
 fn parse_map_literal<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
     begin: Position,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Expr, ParseError> {
     let mut map = Vec::new();
 
@@ -1768,9 +3143,9 @@ fn parse_map_literal<'a>(
                 }
             };
 
-            let expr = parse_expr(input, allow_stmt_expr)?;
+            let expr = parse_expr(input, allow_stmt_expr, level + 1)?;
 
-            map.push((name, expr, pos));
+            map.push((name, input.nodes.add_expr(expr), pos));
 
             match input.peek().ok_or_else(|| {
                 PERR::MissingToken("}".into(), "to end this object map literal".into())
@@ -1825,8 +3200,9 @@ fn parse_map_literal<'a>(
 
 /// Parse a primary expression.
 fn parse_primary<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Expr, ParseError> {
     let token = match input
         .peek()
@@ -1835,8 +3211,8 @@ fn parse_primary<'a>(
         // { - block statement as expression
         (Token::LeftBrace, pos) if allow_stmt_expr => {
             let pos = *pos;
-            return parse_block(input, false, allow_stmt_expr)
-                .map(|block| Expr::Stmt(Box::new(block), pos));
+            let block = parse_block(input, &[], allow_stmt_expr, level + 1)?;
+            return Ok(Expr::Stmt(input.nodes.add_stmt(block), pos));
         }
         _ => input.next().expect("should be a token"),
     };
@@ -1855,21 +3231,21 @@ fn parse_primary<'a>(
         }
         (Token::Identifier(s), pos) => {
             can_be_indexed = true;
-            parse_ident_expr(s, input, pos, allow_stmt_expr)
+            parse_ident_expr(s, input, pos, allow_stmt_expr, level)
         }
         (Token::LeftParen, pos) => {
             can_be_indexed = true;
-            parse_paren_expr(input, pos, allow_stmt_expr)
+            parse_paren_expr(input, pos, allow_stmt_expr, level)
         }
         #[cfg(not(feature = "no_index"))]
         (Token::LeftBracket, pos) => {
             can_be_indexed = true;
-            parse_array_literal(input, pos, allow_stmt_expr)
+            parse_array_literal(input, pos, allow_stmt_expr, level)
         }
         #[cfg(not(feature = "no_object"))]
         (Token::MapStart, pos) => {
             can_be_indexed = true;
-            parse_map_literal(input, pos, allow_stmt_expr)
+            parse_map_literal(input, pos, allow_stmt_expr, level)
         }
         (Token::True, pos) => Ok(Expr::True(pos)),
         (Token::False, pos) => Ok(Expr::False(pos)),
@@ -1884,8 +3260,9 @@ fn parse_primary<'a>(
         #[cfg(not(feature = "no_index"))]
         while let Some((Token::LeftBracket, pos)) = input.peek() {
             let pos = *pos;
+
             input.next();
-            root_expr = parse_index_expr(Box::new(root_expr), input, pos, allow_stmt_expr)?;
+            root_expr = parse_index_expr(root_expr, input, pos, allow_stmt_expr, level)?;
         }
     }
 
@@ -1894,9 +3271,12 @@ fn parse_primary<'a>(
 
 /// Parse a potential unary operator.
 fn parse_unary<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Expr, ParseError> {
+    ensure_level_within_limit(input, level)?;
+
     match input
         .peek()
         .ok_or_else(|| PERR::UnexpectedEOF.into_err_eof())?
@@ -1904,10 +3284,8 @@ fn parse_unary<'a>(
         // If statement is allowed to act as expressions
         (Token::If, pos) => {
             let pos = *pos;
-            Ok(Expr::Stmt(
-                Box::new(parse_if(input, false, allow_stmt_expr)?),
-                pos,
-            ))
+            let stmt = parse_if(input, &[], allow_stmt_expr, level + 1)?;
+            Ok(Expr::Stmt(input.nodes.add_stmt(stmt), pos))
         }
         // -expr
         (Token::UnaryMinus, pos) => {
@@ -1915,7 +3293,7 @@ fn parse_unary<'a>(
 
             input.next();
 
-            match parse_unary(input, allow_stmt_expr)? {
+            match parse_unary(input, allow_stmt_expr, level + 1)? {
                 // Negative integer
                 Expr::IntegerConstant(i, _) => i
                     .checked_neg()
@@ -1937,13 +3315,19 @@ fn parse_unary<'a>(
                 Expr::FloatConstant(f, pos) => Ok(Expr::FloatConstant(-f, pos)),
 
                 // Call negative function
-                expr => Ok(Expr::FunctionCall("-".into(), vec![expr], None, pos)),
+                expr => Ok(Expr::FunctionCall(
+                    "-".into(),
+                    input.nodes.add_exprs([expr]),
+                    None,
+                    Default::default(),
+                    pos,
+                )),
             }
         }
         // +expr
         (Token::UnaryPlus, _) => {
             input.next();
-            parse_unary(input, allow_stmt_expr)
+            parse_unary(input, allow_stmt_expr, level + 1)
         }
         // !expr
         (Token::Bang, pos) => {
@@ -1951,22 +3335,47 @@ fn parse_unary<'a>(
 
             input.next();
 
+            let expr = parse_primary(input, allow_stmt_expr, level + 1)?;
+
             Ok(Expr::FunctionCall(
                 "!".into(),
-                vec![parse_primary(input, allow_stmt_expr)?],
+                input.nodes.add_exprs([expr]),
                 Some(Box::new(false)), // NOT operator, when operating on invalid operand, defaults to false
+                Default::default(),
+                pos,
+            ))
+        }
+        // keyword expr
+        (Token::Custom(name, CustomKeyword::Prefix), pos) => {
+            let name = name.clone();
+            let pos = *pos;
+
+            input.next();
+
+            let expr = parse_unary(input, allow_stmt_expr, level + 1)?;
+
+            Ok(Expr::FunctionCall(
+                name.into(),
+                input.nodes.add_exprs([expr]),
+                None,
+                Default::default(),
                 pos,
             ))
         }
         // All other tokens
-        _ => parse_primary(input, allow_stmt_expr),
+        _ => parse_primary(input, allow_stmt_expr, level),
     }
 }
 
 /// Parse an assignment.
-fn parse_assignment(lhs: Expr, rhs: Expr, pos: Position) -> Result<Expr, ParseError> {
+fn parse_assignment(
+    nodes: &mut Nodes,
+    lhs: Expr,
+    rhs: Expr,
+    pos: Position,
+) -> Result<Expr, ParseError> {
     // Is the LHS in a valid format for an assignment target?
-    fn valid_assignment_chain(expr: &Expr, is_top: bool) -> Option<ParseError> {
+    fn valid_assignment_chain(nodes: &Nodes, expr: &Expr, is_top: bool) -> Option<ParseError> {
         match expr {
             // var
             Expr::Variable(_, _) => {
@@ -1979,88 +3388,97 @@ fn parse_assignment(lhs: Expr, rhs: Expr, pos: Position) -> Result<Expr, ParseEr
                 None
             }
 
-            // var[...]
-            #[cfg(not(feature = "no_index"))]
-            Expr::Index(idx_lhs, _, _) if matches!(idx_lhs.as_ref(), &Expr::Variable(_, _)) => {
-                assert!(is_top, "property expected but gets variable");
-                None
-            }
-            // property[...]
+            // idx_lhs[...]...[...]
             #[cfg(not(feature = "no_index"))]
-            Expr::Index(idx_lhs, _, _) if matches!(idx_lhs.as_ref(), &Expr::Property(_, _)) => {
-                assert!(!is_top, "variable expected but gets property");
-                None
+            Expr::Index(idx_lhs, _, _) => {
+                valid_assignment_target(nodes, index_root(nodes, &nodes[*idx_lhs]), is_top)
             }
 
-            // idx_lhs[...]
-            #[cfg(not(feature = "no_index"))]
-            Expr::Index(idx_lhs, _, pos) => match idx_lhs.as_ref() {
-                Expr::Index(_, _, _) => Some(ParseErrorType::AssignmentToCopy.into_err(*pos)),
-                _ => Some(ParseErrorType::AssignmentToInvalidLHS.into_err(*pos)),
-            },
-
             // dot_lhs.dot_rhs
             #[cfg(not(feature = "no_object"))]
-            Expr::Dot(dot_lhs, dot_rhs, _) => match dot_lhs.as_ref() {
-                // var.dot_rhs
-                Expr::Variable(_, _) if is_top => valid_assignment_chain(dot_rhs, false),
-                // property.dot_rhs
-                Expr::Property(_, _) if !is_top => valid_assignment_chain(dot_rhs, false),
-                // var[...]
-                #[cfg(not(feature = "no_index"))]
-                Expr::Index(idx_lhs, _, _)
-                    if matches!(idx_lhs.as_ref(), &Expr::Variable(_, _)) && is_top =>
-                {
-                    valid_assignment_chain(dot_rhs, false)
-                }
-                // property[...]
-                #[cfg(not(feature = "no_index"))]
-                Expr::Index(idx_lhs, _, _)
-                    if matches!(idx_lhs.as_ref(), &Expr::Property(_, _)) && !is_top =>
-                {
-                    valid_assignment_chain(dot_rhs, false)
-                }
-                // idx_lhs[...]
-                #[cfg(not(feature = "no_index"))]
-                Expr::Index(idx_lhs, _, _) => {
-                    Some(ParseErrorType::AssignmentToCopy.into_err(idx_lhs.position()))
-                }
+            Expr::Dot(dot_lhs, dot_rhs, _) => {
+                valid_assignment_target(nodes, index_root(nodes, &nodes[*dot_lhs]), is_top)
+                    .or_else(|| valid_assignment_chain(nodes, &nodes[*dot_rhs], false))
+            }
 
-                expr => panic!("unexpected dot expression {:#?}", expr),
-            },
+            // e.g. 42 = x
+            _ => Some(ParseErrorType::AssignmentToInvalidLHS.into_err(expr.position(nodes))),
+        }
+    }
+
+    // Can the value indexed into, or whose property is set, be updated?  Anything other than
+    // a variable (at the top of the chain) or a property is a temporary value, e.g. the result
+    // of a function call or a literal, so assigning into it would only change a copy.
+    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+    fn valid_assignment_target(nodes: &Nodes, expr: &Expr, is_top: bool) -> Option<ParseError> {
+        match expr {
+            Expr::Variable(_, _) if is_top => None,
+            Expr::Property(_, _) if !is_top => None,
+            expr => Some(ParseErrorType::AssignmentToCopy.into_err(expr.position(nodes))),
+        }
+    }
 
-            _ => Some(ParseErrorType::AssignmentToInvalidLHS.into_err(expr.position())),
+    // The value indexed into by a chain of indexing, e.g. `x` in `x[i][j]`.
+    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+    #[cfg_attr(feature = "no_index", allow(unused_variables))]
+    fn index_root<'n>(nodes: &'n Nodes, expr: &'n Expr) -> &'n Expr {
+        #[cfg(not(feature = "no_index"))]
+        {
+            if let Expr::Index(lhs, _, _) = expr {
+                return index_root(nodes, &nodes[*lhs]);
+            }
         }
+
+        expr
     }
 
-    match valid_assignment_chain(&lhs, true) {
-        None => Ok(Expr::Assignment(Box::new(lhs), Box::new(rhs), pos)),
+    match valid_assignment_chain(nodes, &lhs, true) {
+        None => Ok(Expr::Assignment(
+            nodes.add_expr(lhs),
+            nodes.add_expr(rhs),
+            pos,
+        )),
         Some(err) => Err(err),
     }
 }
 
 /// Parse an operator-assignment expression.
-fn parse_op_assignment(op: &str, lhs: Expr, rhs: Expr, pos: Position) -> Result<Expr, ParseError> {
-    let lhs_copy = lhs.clone();
+fn parse_op_assignment(
+    nodes: &mut Nodes,
+    op: &'static str,
+    lhs: Expr,
+    rhs: Expr,
+    pos: Position,
+) -> Result<Expr, ParseError> {
+    let lhs_copy = nodes.copy_expr(&lhs);
+    let args = nodes.add_exprs([lhs_copy, rhs]);
 
     // lhs op= rhs -> lhs = op(lhs, rhs)
     parse_assignment(
+        nodes,
         lhs,
-        Expr::FunctionCall(op.into(), vec![lhs_copy, rhs], None, pos),
+        Expr::FunctionCall(op.into(), args, None, Default::default(), pos),
         pos,
     )
 }
 
 /// Parse a binary expression.
 fn parse_binary_op<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
     parent_precedence: u8,
     lhs: Expr,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Expr, ParseError> {
     let mut current_lhs = lhs;
 
     loop {
+        // An operator that fails to lex (e.g. a disabled symbol) is reported as is,
+        // rather than as the end of the expression
+        if let Some((Token::LexError(err), pos)) = input.peek() {
+            return Err(PERR::BadInput(err.to_string()).into_err(*pos));
+        }
+
         let (current_precedence, bind_right) = if let Some((current_op, _)) = input.peek() {
             (current_op.precedence(), current_op.is_bind_right())
         } else {
@@ -2078,7 +3496,7 @@ fn parse_binary_op<'a>(
         if let Some((op_token, pos)) = input.next() {
             input.peek();
 
-            let rhs = parse_unary(input, allow_stmt_expr)?;
+            let rhs = parse_unary(input, allow_stmt_expr, level + 1)?;
 
             let next_precedence = if let Some((next_op, _)) = input.peek() {
                 next_op.precedence()
@@ -2091,114 +3509,197 @@ fn parse_binary_op<'a>(
             let rhs = if (current_precedence == next_precedence && bind_right)
                 || current_precedence < next_precedence
             {
-                parse_binary_op(input, current_precedence, rhs, allow_stmt_expr)?
+                parse_binary_op(input, current_precedence, rhs, allow_stmt_expr, level + 1)?
             } else {
                 // Otherwise bind to left (even if next operator has the same precedence)
                 rhs
             };
 
+            let nodes = &mut input.nodes;
+
             current_lhs = match op_token {
-                Token::Plus => Expr::FunctionCall("+".into(), vec![current_lhs, rhs], None, pos),
-                Token::Minus => Expr::FunctionCall("-".into(), vec![current_lhs, rhs], None, pos),
-                Token::Multiply => {
-                    Expr::FunctionCall("*".into(), vec![current_lhs, rhs], None, pos)
-                }
-                Token::Divide => Expr::FunctionCall("/".into(), vec![current_lhs, rhs], None, pos),
+                Token::Plus => Expr::FunctionCall(
+                    "+".into(),
+                    nodes.add_exprs([current_lhs, rhs]),
+                    None,
+                    Default::default(),
+                    pos,
+                ),
+                Token::Minus => Expr::FunctionCall(
+                    "-".into(),
+                    nodes.add_exprs([current_lhs, rhs]),
+                    None,
+                    Default::default(),
+                    pos,
+                ),
+                Token::Multiply => Expr::FunctionCall(
+                    "*".into(),
+                    nodes.add_exprs([current_lhs, rhs]),
+                    None,
+                    Default::default(),
+                    pos,
+                ),
+                Token::Divide => Expr::FunctionCall(
+                    "/".into(),
+                    nodes.add_exprs([current_lhs, rhs]),
+                    None,
+                    Default::default(),
+                    pos,
+                ),
 
-                Token::Equals => parse_assignment(current_lhs, rhs, pos)?,
-                Token::PlusAssign => parse_op_assignment("+", current_lhs, rhs, pos)?,
-                Token::MinusAssign => parse_op_assignment("-", current_lhs, rhs, pos)?,
+                Token::Equals => parse_assignment(nodes, current_lhs, rhs, pos)?,
+                Token::PlusAssign => parse_op_assignment(nodes, "+", current_lhs, rhs, pos)?,
+                Token::MinusAssign => parse_op_assignment(nodes, "-", current_lhs, rhs, pos)?,
 
                 #[cfg(not(feature = "no_object"))]
                 Token::Period => {
-                    fn check_property(expr: Expr) -> Result<Expr, ParseError> {
+                    fn check_property(nodes: &mut Nodes, expr: Expr) -> Result<Expr, ParseError> {
                         match expr {
                             // xxx.lhs.rhs
-                            Expr::Dot(lhs, rhs, pos) => Ok(Expr::Dot(
-                                Box::new(check_property(*lhs)?),
-                                Box::new(check_property(*rhs)?),
-                                pos,
-                            )),
+                            Expr::Dot(lhs, rhs, pos) => {
+                                check_property_at(nodes, lhs)?;
+                                check_property_at(nodes, rhs)?;
+                                Ok(Expr::Dot(lhs, rhs, pos))
+                            }
                             // xxx.lhs[idx]
                             #[cfg(not(feature = "no_index"))]
                             Expr::Index(lhs, idx, pos) => {
-                                Ok(Expr::Index(Box::new(check_property(*lhs)?), idx, pos))
+                                check_property_at(nodes, lhs)?;
+                                Ok(Expr::Index(lhs, idx, pos))
                             }
                             // xxx.id
                             Expr::Variable(id, pos) => Ok(Expr::Property(id, pos)),
                             // xxx.prop
                             expr @ Expr::Property(_, _) => Ok(expr),
                             // xxx.fn()
-                            expr @ Expr::FunctionCall(_, _, _, _) => Ok(expr),
-                            expr => Err(PERR::PropertyExpected.into_err(expr.position())),
+                            expr @ Expr::FunctionCall(_, _, _, _, _) => Ok(expr),
+                            expr => Err(PERR::PropertyExpected.into_err(expr.position(nodes))),
                         }
                     }
 
-                    Expr::Dot(Box::new(current_lhs), Box::new(check_property(rhs)?), pos)
+                    fn check_property_at(nodes: &mut Nodes, id: ExprId) -> Result<(), ParseError> {
+                        let expr = nodes.take_expr(id);
+                        nodes[id] = check_property(nodes, expr)?;
+                        Ok(())
+                    }
+
+                    let rhs = check_property(nodes, rhs)?;
+                    Expr::Dot(nodes.add_expr(current_lhs), nodes.add_expr(rhs), pos)
                 }
 
                 // Comparison operators default to false when passed invalid operands
                 Token::EqualsTo => Expr::FunctionCall(
                     "==".into(),
-                    vec![current_lhs, rhs],
+                    nodes.add_exprs([current_lhs, rhs]),
                     Some(Box::new(false)),
+                    Default::default(),
                     pos,
                 ),
                 Token::NotEqualsTo => Expr::FunctionCall(
                     "!=".into(),
-                    vec![current_lhs, rhs],
+                    nodes.add_exprs([current_lhs, rhs]),
                     Some(Box::new(false)),
+                    Default::default(),
                     pos,
                 ),
                 Token::LessThan => Expr::FunctionCall(
                     "<".into(),
-                    vec![current_lhs, rhs],
+                    nodes.add_exprs([current_lhs, rhs]),
                     Some(Box::new(false)),
+                    Default::default(),
                     pos,
                 ),
                 Token::LessThanEqualsTo => Expr::FunctionCall(
                     "<=".into(),
-                    vec![current_lhs, rhs],
+                    nodes.add_exprs([current_lhs, rhs]),
                     Some(Box::new(false)),
+                    Default::default(),
                     pos,
                 ),
                 Token::GreaterThan => Expr::FunctionCall(
                     ">".into(),
-                    vec![current_lhs, rhs],
+                    nodes.add_exprs([current_lhs, rhs]),
                     Some(Box::new(false)),
+                    Default::default(),
                     pos,
                 ),
                 Token::GreaterThanEqualsTo => Expr::FunctionCall(
                     ">=".into(),
-                    vec![current_lhs, rhs],
+                    nodes.add_exprs([current_lhs, rhs]),
                     Some(Box::new(false)),
+                    Default::default(),
+                    pos,
+                ),
+
+                Token::Or => Expr::Or(nodes.add_expr(current_lhs), nodes.add_expr(rhs), pos),
+                Token::And => Expr::And(nodes.add_expr(current_lhs), nodes.add_expr(rhs), pos),
+                Token::XOr => Expr::FunctionCall(
+                    "^".into(),
+                    nodes.add_exprs([current_lhs, rhs]),
+                    None,
+                    Default::default(),
+                    pos,
+                ),
+                Token::OrAssign => parse_op_assignment(nodes, "|", current_lhs, rhs, pos)?,
+                Token::AndAssign => parse_op_assignment(nodes, "&", current_lhs, rhs, pos)?,
+                Token::XOrAssign => parse_op_assignment(nodes, "^", current_lhs, rhs, pos)?,
+                Token::MultiplyAssign => parse_op_assignment(nodes, "*", current_lhs, rhs, pos)?,
+                Token::DivideAssign => parse_op_assignment(nodes, "/", current_lhs, rhs, pos)?,
+                Token::Pipe => Expr::FunctionCall(
+                    "|".into(),
+                    nodes.add_exprs([current_lhs, rhs]),
+                    None,
+                    Default::default(),
+                    pos,
+                ),
+                Token::LeftShift => Expr::FunctionCall(
+                    "<<".into(),
+                    nodes.add_exprs([current_lhs, rhs]),
+                    None,
+                    Default::default(),
+                    pos,
+                ),
+                Token::RightShift => Expr::FunctionCall(
+                    ">>".into(),
+                    nodes.add_exprs([current_lhs, rhs]),
+                    None,
+                    Default::default(),
+                    pos,
+                ),
+                Token::LeftShiftAssign => parse_op_assignment(nodes, "<<", current_lhs, rhs, pos)?,
+                Token::RightShiftAssign => parse_op_assignment(nodes, ">>", current_lhs, rhs, pos)?,
+                Token::Ampersand => Expr::FunctionCall(
+                    "&".into(),
+                    nodes.add_exprs([current_lhs, rhs]),
+                    None,
+                    Default::default(),
+                    pos,
+                ),
+                Token::Modulo => Expr::FunctionCall(
+                    "%".into(),
+                    nodes.add_exprs([current_lhs, rhs]),
+                    None,
+                    Default::default(),
+                    pos,
+                ),
+                Token::ModuloAssign => parse_op_assignment(nodes, "%", current_lhs, rhs, pos)?,
+                Token::PowerOf => Expr::FunctionCall(
+                    "~".into(),
+                    nodes.add_exprs([current_lhs, rhs]),
+                    None,
+                    Default::default(),
+                    pos,
+                ),
+                Token::PowerOfAssign => parse_op_assignment(nodes, "~", current_lhs, rhs, pos)?,
+
+                Token::Custom(name, CustomKeyword::Infix(_)) => Expr::FunctionCall(
+                    name.into(),
+                    nodes.add_exprs([current_lhs, rhs]),
+                    None,
+                    Default::default(),
                     pos,
                 ),
 
-                Token::Or => Expr::Or(Box::new(current_lhs), Box::new(rhs)),
-                Token::And => Expr::And(Box::new(current_lhs), Box::new(rhs)),
-                Token::XOr => Expr::FunctionCall("^".into(), vec![current_lhs, rhs], None, pos),
-                Token::OrAssign => parse_op_assignment("|", current_lhs, rhs, pos)?,
-                Token::AndAssign => parse_op_assignment("&", current_lhs, rhs, pos)?,
-                Token::XOrAssign => parse_op_assignment("^", current_lhs, rhs, pos)?,
-                Token::MultiplyAssign => parse_op_assignment("*", current_lhs, rhs, pos)?,
-                Token::DivideAssign => parse_op_assignment("/", current_lhs, rhs, pos)?,
-                Token::Pipe => Expr::FunctionCall("|".into(), vec![current_lhs, rhs], None, pos),
-                Token::LeftShift => {
-                    Expr::FunctionCall("<<".into(), vec![current_lhs, rhs], None, pos)
-                }
-                Token::RightShift => {
-                    Expr::FunctionCall(">>".into(), vec![current_lhs, rhs], None, pos)
-                }
-                Token::LeftShiftAssign => parse_op_assignment("<<", current_lhs, rhs, pos)?,
-                Token::RightShiftAssign => parse_op_assignment(">>", current_lhs, rhs, pos)?,
-                Token::Ampersand => {
-                    Expr::FunctionCall("&".into(), vec![current_lhs, rhs], None, pos)
-                }
-                Token::Modulo => Expr::FunctionCall("%".into(), vec![current_lhs, rhs], None, pos),
-                Token::ModuloAssign => parse_op_assignment("%", current_lhs, rhs, pos)?,
-                Token::PowerOf => Expr::FunctionCall("~".into(), vec![current_lhs, rhs], None, pos),
-                Token::PowerOfAssign => parse_op_assignment("~", current_lhs, rhs, pos)?,
                 token => return Err(PERR::UnknownOperator(token.syntax().into()).into_err(pos)),
             };
         }
@@ -2207,17 +3708,18 @@ fn parse_binary_op<'a>(
 
 /// Parse an expression.
 fn parse_expr<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Expr, ParseError> {
     // Parse a real expression
-    let lhs = parse_unary(input, allow_stmt_expr)?;
-    parse_binary_op(input, 1, lhs, allow_stmt_expr)
+    let lhs = parse_unary(input, allow_stmt_expr, level)?;
+    parse_binary_op(input, 1, lhs, allow_stmt_expr, level)
 }
 
 /// Make sure that the expression is not a statement expression (i.e. wrapped in {})
 fn ensure_not_statement_expr<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
     type_name: &str,
 ) -> Result<(), ParseError> {
     match input
@@ -2231,76 +3733,121 @@ fn ensure_not_statement_expr<'a>(
     }
 }
 
+/// Make sure that the nesting level of the expression or statement being parsed is within limits.
+fn ensure_level_within_limit<'a>(
+    input: &mut ParseState<'a>,
+    level: Level,
+) -> Result<(), ParseError> {
+    if level.depth > level.max {
+        Err(match input.peek() {
+            Some((_, pos)) => PERR::ExprTooDeep.into_err(*pos),
+            None => PERR::ExprTooDeep.into_err_eof(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
 /// Parse an if statement.
 fn parse_if<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
-    breakable: bool,
+    input: &mut ParseState<'a>,
+    loops: &[Option<String>],
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Stmt, ParseError> {
     // if ...
     input.next();
 
     // if guard { if_body }
     ensure_not_statement_expr(input, "a boolean")?;
-    let guard = parse_expr(input, allow_stmt_expr)?;
-    let if_body = parse_block(input, breakable, allow_stmt_expr)?;
+    let guard = parse_expr(input, allow_stmt_expr, level + 1)?;
+    let if_body = parse_block(input, loops, allow_stmt_expr, level + 1)?;
 
     // if guard { if_body } else ...
     let else_body = if matches!(input.peek(), Some((Token::Else, _))) {
         input.next();
 
-        Some(Box::new(if matches!(input.peek(), Some((Token::If, _))) {
+        let else_body = if matches!(input.peek(), Some((Token::If, _))) {
             // if guard { if_body } else if ...
-            parse_if(input, breakable, allow_stmt_expr)?
+            parse_if(input, loops, allow_stmt_expr, level + 1)?
         } else {
             // if guard { if_body } else { else-body }
-            parse_block(input, breakable, allow_stmt_expr)?
-        }))
+            parse_block(input, loops, allow_stmt_expr, level + 1)?
+        };
+
+        Some(input.nodes.add_stmt(else_body))
     } else {
         None
     };
 
+    let nodes = &mut input.nodes;
+
     Ok(Stmt::IfThenElse(
-        Box::new(guard),
-        Box::new(if_body),
+        nodes.add_expr(guard),
+        nodes.add_stmt(if_body),
         else_body,
     ))
 }
 
 /// Parse a while loop.
 fn parse_while<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
+    loops: &[Option<String>],
+    label: Option<String>,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Stmt, ParseError> {
     // while ...
     input.next();
 
     // while guard { body }
     ensure_not_statement_expr(input, "a boolean")?;
-    let guard = parse_expr(input, allow_stmt_expr)?;
-    let body = parse_block(input, true, allow_stmt_expr)?;
-
-    Ok(Stmt::While(Box::new(guard), Box::new(body)))
+    let guard = parse_expr(input, allow_stmt_expr, level + 1)?;
+    let body = parse_block(
+        input,
+        &enter_loop(loops, &label),
+        allow_stmt_expr,
+        level + 1,
+    )?;
+
+    let nodes = &mut input.nodes;
+
+    Ok(Stmt::While(
+        nodes.add_expr(guard),
+        nodes.add_stmt(body),
+        label,
+    ))
 }
 
 /// Parse a loop statement.
 fn parse_loop<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
+    loops: &[Option<String>],
+    label: Option<String>,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Stmt, ParseError> {
     // loop ...
     input.next();
 
     // loop { body }
-    let body = parse_block(input, true, allow_stmt_expr)?;
-
-    Ok(Stmt::Loop(Box::new(body)))
+    let body = parse_block(
+        input,
+        &enter_loop(loops, &label),
+        allow_stmt_expr,
+        level + 1,
+    )?;
+
+    Ok(Stmt::Loop(input.nodes.add_stmt(body), label))
 }
 
 /// Parse a for loop.
 fn parse_for<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
+    loops: &[Option<String>],
+    label: Option<String>,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Stmt, ParseError> {
     // for ...
     input.next();
@@ -2324,26 +3871,98 @@ fn parse_for<'a>(
     })? {
         (Token::In, _) => (),
         (_, pos) => {
-            return Err(
-                PERR::MissingToken("in".into(), "after the iteration variable".into())
-                    .into_err(pos),
-            )
+            return Err(
+                PERR::MissingToken("in".into(), "after the iteration variable".into())
+                    .into_err(pos),
+            )
+        }
+    }
+
+    // for name in expr { body }
+    ensure_not_statement_expr(input, "a boolean")?;
+    let expr = parse_expr(input, allow_stmt_expr, level + 1)?;
+    let body = parse_block(
+        input,
+        &enter_loop(loops, &label),
+        allow_stmt_expr,
+        level + 1,
+    )?;
+
+    let nodes = &mut input.nodes;
+
+    Ok(Stmt::For(
+        name,
+        nodes.add_expr(expr),
+        nodes.add_stmt(body),
+        label,
+    ))
+}
+
+/// Get the labels of the enclosing loops when entering a new loop with an optional label.
+fn enter_loop(loops: &[Option<String>], label: &Option<String>) -> Vec<Option<String>> {
+    let mut loops = loops.to_vec();
+    loops.push(label.clone());
+    loops
+}
+
+/// Parse a labeled loop.
+fn parse_labeled_loop<'a>(
+    input: &mut ParseState<'a>,
+    loops: &[Option<String>],
+    allow_stmt_expr: bool,
+    level: Level,
+) -> Result<Stmt, ParseError> {
+    // 'label ...
+    let (label, label_pos) = match input.next() {
+        Some((Token::Label(label), pos)) => (label, pos),
+        _ => panic!("token should be a label"),
+    };
+
+    // 'label: ...
+    match input.next().ok_or_else(|| {
+        PERR::MissingToken(":".into(), "after the loop label".into()).into_err_eof()
+    })? {
+        (Token::Colon, _) => (),
+        (_, pos) => {
+            return Err(PERR::MissingToken(":".into(), "after the loop label".into()).into_err(pos))
         }
     }
 
-    // for name in expr { body }
-    ensure_not_statement_expr(input, "a boolean")?;
-    let expr = parse_expr(input, allow_stmt_expr)?;
-    let body = parse_block(input, true, allow_stmt_expr)?;
+    // 'label: loop ...
+    match input.peek() {
+        Some((Token::While, _)) => parse_while(input, loops, Some(label), allow_stmt_expr, level),
+        Some((Token::Loop, _)) => parse_loop(input, loops, Some(label), allow_stmt_expr, level),
+        Some((Token::For, _)) => parse_for(input, loops, Some(label), allow_stmt_expr, level),
+        _ => Err(PERR::LabelWithoutLoop(label).into_err(label_pos)),
+    }
+}
+
+/// Parse the optional label after a `break` or `continue`, which must be one of the enclosing loops.
+fn parse_break_label<'a>(
+    input: &mut ParseState<'a>,
+    loops: &[Option<String>],
+) -> Result<Option<String>, ParseError> {
+    match input.peek() {
+        Some((Token::Label(label), pos)) => {
+            if !loops.iter().any(|l| l.as_ref() == Some(label)) {
+                return Err(PERR::UndefinedLabel(label.clone()).into_err(*pos));
+            }
 
-    Ok(Stmt::For(name, Box::new(expr), Box::new(body)))
+            match input.next() {
+                Some((Token::Label(label), _)) => Ok(Some(label)),
+                _ => panic!("token should be a label"),
+            }
+        }
+        _ => Ok(None),
+    }
 }
 
 /// Parse a variable definition statement.
 fn parse_let<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
     var_type: ScopeEntryType,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Stmt, ParseError> {
     // let/const... (specified in `var_type`)
     input.next();
@@ -2363,18 +3982,20 @@ fn parse_let<'a>(
         input.next();
 
         // let name = expr
-        let init_value = parse_expr(input, allow_stmt_expr)?;
+        let init_value = parse_expr(input, allow_stmt_expr, level + 1)?;
+
+        let nodes = &mut input.nodes;
 
         match var_type {
             // let name = expr
-            ScopeEntryType::Normal => Ok(Stmt::Let(name, Some(Box::new(init_value)), pos)),
+            ScopeEntryType::Normal => Ok(Stmt::Let(name, Some(nodes.add_expr(init_value)), pos)),
             // const name = { expr:constant }
-            ScopeEntryType::Constant if init_value.is_constant() => {
-                Ok(Stmt::Const(name, Box::new(init_value), pos))
+            ScopeEntryType::Constant if init_value.is_constant(nodes) => {
+                Ok(Stmt::Const(name, nodes.add_expr(init_value), pos))
             }
             // const name = expr - error
             ScopeEntryType::Constant => {
-                Err(PERR::ForbiddenConstantExpr(name).into_err(init_value.position()))
+                Err(PERR::ForbiddenConstantExpr(name).into_err(init_value.position(nodes)))
             }
         }
     } else {
@@ -2383,11 +4004,144 @@ fn parse_let<'a>(
     }
 }
 
+/// Parse a name in an `import` or `export` statement.
+#[cfg(not(feature = "no_module"))]
+fn parse_import_name<'a>(input: &mut ParseState<'a>) -> Result<String, ParseError> {
+    match input
+        .next()
+        .ok_or_else(|| PERR::VariableExpected.into_err_eof())?
+    {
+        (Token::Identifier(s), _) => Ok(s),
+        (Token::LexError(err), pos) => Err(PERR::BadInput(err.to_string()).into_err(pos)),
+        (_, pos) => Err(PERR::VariableExpected.into_err(pos)),
+    }
+}
+
+/// Parse a list of names, each with an optional alias, in an `import` or `export` statement.
+#[cfg(not(feature = "no_module"))]
+fn parse_import_list<'a>(input: &mut ParseState<'a>) -> Result<Vec<(String, String)>, ParseError> {
+    let mut names = Vec::new();
+
+    loop {
+        let name = parse_import_name(input)?;
+
+        // name as alias
+        let alias = if matches!(input.peek(), Some((Token::As, _))) {
+            input.next();
+            parse_import_name(input)?
+        } else {
+            name.clone()
+        };
+
+        names.push((name, alias));
+
+        if !matches!(input.peek(), Some((Token::Comma, _))) {
+            return Ok(names);
+        }
+
+        input.next();
+    }
+}
+
+/// Parse the module path string literal in an `import` statement.
+#[cfg(not(feature = "no_module"))]
+fn parse_module_path<'a>(input: &mut ParseState<'a>) -> Result<String, ParseError> {
+    match input
+        .next()
+        .ok_or_else(|| PERR::UnexpectedEOF.into_err_eof())?
+    {
+        (Token::StringConst(path), _) => Ok(path),
+        (Token::LexError(err), pos) => Err(PERR::BadInput(err.to_string()).into_err(pos)),
+        (_, pos) => Err(
+            PERR::BadInput("Expecting a string literal for the module path".into()).into_err(pos),
+        ),
+    }
+}
+
+/// Parse an import statement.
+#[cfg(not(feature = "no_module"))]
+fn parse_import<'a>(input: &mut ParseState<'a>) -> Result<Stmt, ParseError> {
+    let pos = input.next().expect("should be import").1;
+
+    // import "path" as alias
+    if matches!(input.peek(), Some((Token::StringConst(_), _))) {
+        let path = parse_module_path(input)?;
+
+        match input.next() {
+            Some((Token::As, _)) => (),
+            Some((_, pos)) => {
+                return Err(PERR::MissingToken(
+                    "as".into(),
+                    format!("to name the imported module '{}'", path),
+                )
+                .into_err(pos))
+            }
+            None => {
+                return Err(PERR::MissingToken(
+                    "as".into(),
+                    format!("to name the imported module '{}'", path),
+                )
+                .into_err_eof())
+            }
+        }
+
+        let alias = parse_import_name(input)?;
+
+        return Ok(Stmt::Import(path, alias, pos));
+    }
+
+    // import name [as alias], ... from "path"
+    let names = parse_import_list(input)?;
+
+    match input.next() {
+        Some((Token::From, _)) => (),
+        Some((_, pos)) => {
+            return Err(
+                PERR::MissingToken("from".into(), "to specify the module to import".into())
+                    .into_err(pos),
+            )
+        }
+        None => {
+            return Err(
+                PERR::MissingToken("from".into(), "to specify the module to import".into())
+                    .into_err_eof(),
+            )
+        }
+    }
+
+    let path = parse_module_path(input)?;
+
+    Ok(Stmt::ImportFrom(names, path, pos))
+}
+
+/// Parse an export statement.
+#[cfg(not(feature = "no_module"))]
+fn parse_export<'a>(input: &mut ParseState<'a>) -> Result<Stmt, ParseError> {
+    let pos = input.next().expect("should be export").1;
+
+    Ok(Stmt::Export(parse_import_list(input)?, pos))
+}
+
 /// Parse a statement block.
 fn parse_block<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
-    breakable: bool,
+    input: &mut ParseState<'a>,
+    loops: &[Option<String>],
     allow_stmt_expr: bool,
+    level: Level,
+) -> Result<Stmt, ParseError> {
+    parse_block_with_fns(input, loops, allow_stmt_expr, level, None)
+}
+
+/// Parse a statement block, allowing function definitions directly inside it when `functions`
+/// is `Some` (i.e. for the body of a function).  The function definitions are collected into
+/// `functions`, together with the functions hoisted out of their own bodies.
+#[cfg_attr(feature = "no_function", allow(unused_variables, unused_mut))]
+fn parse_block_with_fns<'a>(
+    input: &mut ParseState<'a>,
+    loops: &[Option<String>],
+    allow_stmt_expr: bool,
+    level: Level,
+    mut functions: Option<&mut Vec<FnDef>>,
 ) -> Result<Stmt, ParseError> {
     // Must start with {
     let pos = match input
@@ -2405,8 +4159,25 @@ fn parse_block<'a>(
     let mut statements = Vec::new();
 
     while !matches!(input.peek(), Some((Token::RightBrace, _))) {
+        #[cfg(not(feature = "no_function"))]
+        {
+            // Nested function definition
+            if let Some(functions) = functions.as_deref_mut() {
+                if matches!(
+                    input.peek(),
+                    Some((Token::Fn, _))
+                        | Some((Token::Private, _))
+                        | Some((Token::DocComment(_), _))
+                ) {
+                    let f = parse_fn_def(input, level + 1, functions)?;
+                    functions.push(f);
+                    continue;
+                }
+            }
+        }
+
         // Parse statements inside the block
-        let stmt = parse_stmt(input, breakable, allow_stmt_expr)?;
+        let stmt = parse_stmt(input, loops, allow_stmt_expr, level + 1)?;
 
         // See if it needs a terminating semicolon
         let need_semicolon = !stmt.is_self_terminated();
@@ -2442,7 +4213,7 @@ fn parse_block<'a>(
     })? {
         (Token::RightBrace, _) => {
             input.next();
-            Ok(Stmt::Block(statements, pos))
+            Ok(Stmt::Block(input.nodes.add_stmts(statements), pos))
         }
         (_, pos) => {
             Err(PERR::MissingToken("}".into(), "to end this statement block".into()).into_err(*pos))
@@ -2452,18 +4223,23 @@ fn parse_block<'a>(
 
 /// Parse an expression as a statement.
 fn parse_expr_stmt<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Stmt, ParseError> {
-    Ok(Stmt::Expr(Box::new(parse_expr(input, allow_stmt_expr)?)))
+    let expr = parse_expr(input, allow_stmt_expr, level + 1)?;
+    Ok(Stmt::Expr(input.nodes.add_expr(expr)))
 }
 
 /// Parse a single statement.
 fn parse_stmt<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
-    breakable: bool,
+    input: &mut ParseState<'a>,
+    loops: &[Option<String>],
     allow_stmt_expr: bool,
+    level: Level,
 ) -> Result<Stmt, ParseError> {
+    ensure_level_within_limit(input, level)?;
+
     let token = match input.peek() {
         Some(token) => token,
         None => return Ok(Stmt::Noop(Position::eof())),
@@ -2473,26 +4249,29 @@ fn parse_stmt<'a>(
         // Semicolon - empty statement
         (Token::SemiColon, pos) => Ok(Stmt::Noop(*pos)),
 
-        (Token::LeftBrace, _) => parse_block(input, breakable, allow_stmt_expr),
+        (Token::LeftBrace, _) => parse_block(input, loops, allow_stmt_expr, level),
 
         // fn ...
         #[cfg(not(feature = "no_function"))]
-        (Token::Fn, pos) => Err(PERR::WrongFnDefinition.into_err(*pos)),
+        (Token::Fn, pos) | (Token::Private, pos) | (Token::DocComment(_), pos) => {
+            Err(PERR::WrongFnDefinition.into_err(*pos))
+        }
 
-        (Token::If, _) => parse_if(input, breakable, allow_stmt_expr),
-        (Token::While, _) => parse_while(input, allow_stmt_expr),
-        (Token::Loop, _) => parse_loop(input, allow_stmt_expr),
-        (Token::For, _) => parse_for(input, allow_stmt_expr),
+        (Token::If, _) => parse_if(input, loops, allow_stmt_expr, level),
+        (Token::While, _) => parse_while(input, loops, None, allow_stmt_expr, level),
+        (Token::Loop, _) => parse_loop(input, loops, None, allow_stmt_expr, level),
+        (Token::For, _) => parse_for(input, loops, None, allow_stmt_expr, level),
+        (Token::Label(_), _) => parse_labeled_loop(input, loops, allow_stmt_expr, level),
 
-        (Token::Continue, pos) if breakable => {
+        (Token::Continue, pos) if !loops.is_empty() => {
             let pos = *pos;
             input.next();
-            Ok(Stmt::Continue(pos))
+            Ok(Stmt::Continue(parse_break_label(input, loops)?, pos))
         }
-        (Token::Break, pos) if breakable => {
+        (Token::Break, pos) if !loops.is_empty() => {
             let pos = *pos;
             input.next();
-            Ok(Stmt::Break(pos))
+            Ok(Stmt::Break(parse_break_label(input, loops)?, pos))
         }
         (Token::Continue, pos) | (Token::Break, pos) => Err(PERR::LoopBreak.into_err(*pos)),
 
@@ -2513,25 +4292,74 @@ fn parse_stmt<'a>(
                 Some((Token::SemiColon, _)) => Ok(Stmt::ReturnWithVal(None, return_type, pos)),
                 // `return` or `throw` with expression
                 Some((_, _)) => {
-                    let expr = parse_expr(input, allow_stmt_expr)?;
-                    let pos = expr.position();
-                    Ok(Stmt::ReturnWithVal(Some(Box::new(expr)), return_type, pos))
+                    let expr = parse_expr(input, allow_stmt_expr, level + 1)?;
+                    let pos = expr.position(&input.nodes);
+                    let expr = input.nodes.add_expr(expr);
+                    Ok(Stmt::ReturnWithVal(Some(expr), return_type, pos))
                 }
             }
         }
 
-        (Token::Let, _) => parse_let(input, ScopeEntryType::Normal, allow_stmt_expr),
-        (Token::Const, _) => parse_let(input, ScopeEntryType::Constant, allow_stmt_expr),
+        (Token::Let, _) => parse_let(input, ScopeEntryType::Normal, allow_stmt_expr, level),
+        (Token::Const, _) => parse_let(input, ScopeEntryType::Constant, allow_stmt_expr, level),
+
+        #[cfg(not(feature = "no_module"))]
+        (Token::Import, _) => parse_import(input),
+        #[cfg(not(feature = "no_module"))]
+        (Token::Export, _) => parse_export(input),
+
+        _ => parse_expr_stmt(input, allow_stmt_expr, level),
+    }
+}
+
+/// Parse a function definition, including the doc-comments and `private` modifier preceding it.
+/// Functions nested inside its body are hoisted into `hoisted`.
+#[cfg(not(feature = "no_function"))]
+fn parse_fn_def<'a>(
+    input: &mut ParseState<'a>,
+    level: Level,
+    hoisted: &mut Vec<FnDef>,
+) -> Result<FnDef, ParseError> {
+    // Doc-comments are always followed by a function definition
+    let mut comments = Vec::new();
+
+    while let Some((Token::DocComment(_), _)) = input.peek() {
+        if let Some((Token::DocComment(comment), _)) = input.next() {
+            comments.push(comment);
+        }
+    }
+
+    let private = matches!(input.peek(), Some((Token::Private, _)));
+
+    if private {
+        input.next();
+    }
 
-        _ => parse_expr_stmt(input, allow_stmt_expr),
+    match input.peek() {
+        Some((Token::Fn, _)) => {
+            let mut f = parse_fn(input, true, level, hoisted)?;
+            f.comments = comments;
+            f.private = private;
+            Ok(f)
+        }
+        Some((_, pos)) => {
+            Err(PERR::MissingToken("fn".into(), "after 'private'".into()).into_err(*pos))
+        }
+        None => Err(PERR::MissingToken("fn".into(), "after 'private'".into()).into_err_eof()),
     }
 }
 
 /// Parse a function definition.
+///
+/// Functions nested inside the body are hoisted into `hoisted` as private functions, named
+/// `outer$arity$inner` (which cannot clash with any function named in a script), and calls to
+/// them in the bodies of the function and of all functions nested inside it are renamed.
 #[cfg(not(feature = "no_function"))]
 fn parse_fn<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut ParseState<'a>,
     allow_stmt_expr: bool,
+    level: Level,
+    hoisted: &mut Vec<FnDef>,
 ) -> Result<FnDef, ParseError> {
     let pos = input.next().expect("should be fn").1;
 
@@ -2540,6 +4368,8 @@ fn parse_fn<'a>(
         .ok_or_else(|| PERR::FnMissingName.into_err_eof())?
     {
         (Token::Identifier(s), _) => s,
+        // Custom keywords call the function of the same name
+        (Token::Custom(s, _), _) => s,
         (_, pos) => return Err(PERR::FnMissingName.into_err(pos)),
     };
 
@@ -2601,42 +4431,186 @@ fn parse_fn<'a>(
             PERR::FnDuplicatedParam(name.to_string(), p.to_string()).into_err(pos)
         })?;
 
-    // Parse function body
+    // Parse function body into nodes of its own, collecting the functions nested inside it
+    let mut nested = Vec::new();
+
+    let outer_nodes = mem::take(&mut input.nodes);
+
     let body = match input.peek() {
-        Some((Token::LeftBrace, _)) => parse_block(input, false, allow_stmt_expr)?,
-        Some((_, pos)) => return Err(PERR::FnMissingBody(name).into_err(*pos)),
-        None => return Err(PERR::FnMissingBody(name).into_err_eof()),
+        Some((Token::LeftBrace, _)) => {
+            parse_block_with_fns(input, &[], allow_stmt_expr, level + 1, Some(&mut nested))
+        }
+        Some((_, pos)) => Err(PERR::FnMissingBody(name.clone()).into_err(*pos)),
+        None => Err(PERR::FnMissingBody(name.clone()).into_err_eof()),
     };
 
+    let mut nodes = mem::replace(&mut input.nodes, outer_nodes);
+    let body = body?;
+
+    // Hoist nested functions
+    if !nested.is_empty() {
+        let prefix = format!("{}${}$", name, params.len());
+        let locals: Vec<_> = nested
+            .iter()
+            .filter(|f| !f.name.contains('$'))
+            .map(|f| (f.name.clone(), f.params.len()))
+            .collect();
+
+        rename_local_calls(&mut nodes, &body, &prefix, &locals);
+
+        hoisted.extend(nested.into_iter().map(|mut f| {
+            rename_local_calls(&mut f.nodes, &f.body, &prefix, &locals);
+            f.name = format!("{}{}", prefix, f.name);
+            f.private = true;
+            f
+        }));
+    }
+
     Ok(FnDef {
         name,
         params: params.into_iter().map(|(p, _)| p).collect(),
         body,
+        nodes,
         pos,
+        comments: Vec::new(),
+        private: false,
     })
 }
 
+/// Rename calls to the functions nested directly inside a function (`locals`, by name and number
+/// of parameters), which are hoisted under names starting with `prefix`.  Calls to functions
+/// hoisted out of deeper levels (whose names already contain `$`) are prefixed as well.
+#[cfg(not(feature = "no_function"))]
+fn rename_local_calls(nodes: &mut Nodes, stmt: &Stmt, prefix: &str, locals: &[(String, usize)]) {
+    match stmt {
+        Stmt::IfThenElse(guard, if_block, else_block) => {
+            rename_local_calls_in_expr(nodes, *guard, false, prefix, locals);
+            rename_local_calls_at(nodes, *if_block, prefix, locals);
+            if let Some(else_block) = else_block {
+                rename_local_calls_at(nodes, *else_block, prefix, locals);
+            }
+        }
+        Stmt::While(guard, body, _) => {
+            rename_local_calls_in_expr(nodes, *guard, false, prefix, locals);
+            rename_local_calls_at(nodes, *body, prefix, locals);
+        }
+        Stmt::Loop(body, _) => rename_local_calls_at(nodes, *body, prefix, locals),
+        Stmt::For(_, expr, body, _) => {
+            rename_local_calls_in_expr(nodes, *expr, false, prefix, locals);
+            rename_local_calls_at(nodes, *body, prefix, locals);
+        }
+        Stmt::Let(_, Some(expr), _)
+        | Stmt::Const(_, expr, _)
+        | Stmt::Expr(expr)
+        | Stmt::ReturnWithVal(Some(expr), _, _) => {
+            rename_local_calls_in_expr(nodes, *expr, false, prefix, locals)
+        }
+        Stmt::Block(statements, _) => statements
+            .ids()
+            .for_each(|stmt| rename_local_calls_at(nodes, stmt, prefix, locals)),
+        _ => (),
+    }
+}
+
+/// Rename calls to hoisted nested functions in a statement held in `nodes`.
+#[cfg(not(feature = "no_function"))]
+fn rename_local_calls_at(nodes: &mut Nodes, id: StmtId, prefix: &str, locals: &[(String, usize)]) {
+    let stmt = nodes.take_stmt(id);
+    rename_local_calls(nodes, &stmt, prefix, locals);
+    nodes[id] = stmt;
+}
+
+/// Rename calls to hoisted nested functions in an expression.  `is_method` is true on the
+/// right-hand side of a dot, where function calls have the object as an extra argument.
+#[cfg(not(feature = "no_function"))]
+fn rename_local_calls_in_expr(
+    nodes: &mut Nodes,
+    id: ExprId,
+    is_method: bool,
+    prefix: &str,
+    locals: &[(String, usize)],
+) {
+    match &mut nodes[id] {
+        Expr::Stmt(stmt, _) => {
+            let stmt = *stmt;
+            rename_local_calls_at(nodes, stmt, prefix, locals);
+        }
+        Expr::FunctionCall(name, args, _, _, _) => {
+            let num_args = if is_method {
+                args.len() + 1
+            } else {
+                args.len()
+            };
+
+            if name.contains('$')
+                || locals
+                    .iter()
+                    .any(|(n, params)| n == name.as_ref() && *params == num_args)
+            {
+                *name = format!("{}{}", prefix, name).into();
+            }
+
+            args.ids()
+                .for_each(|arg| rename_local_calls_in_expr(nodes, arg, false, prefix, locals));
+        }
+        Expr::Assignment(lhs, rhs, _) | Expr::And(lhs, rhs, _) | Expr::Or(lhs, rhs, _) => {
+            let (lhs, rhs) = (*lhs, *rhs);
+            rename_local_calls_in_expr(nodes, lhs, false, prefix, locals);
+            rename_local_calls_in_expr(nodes, rhs, false, prefix, locals);
+        }
+        #[cfg(not(feature = "no_object"))]
+        Expr::Dot(lhs, rhs, _) => {
+            let (lhs, rhs) = (*lhs, *rhs);
+            rename_local_calls_in_expr(nodes, lhs, is_method, prefix, locals);
+            rename_local_calls_in_expr(nodes, rhs, true, prefix, locals);
+        }
+        #[cfg(not(feature = "no_index"))]
+        Expr::Index(lhs, rhs, _) => {
+            let (lhs, rhs) = (*lhs, *rhs);
+            rename_local_calls_in_expr(nodes, lhs, is_method, prefix, locals);
+            rename_local_calls_in_expr(nodes, rhs, false, prefix, locals);
+        }
+        #[cfg(not(feature = "no_index"))]
+        Expr::Array(items, _) => items
+            .ids()
+            .for_each(|item| rename_local_calls_in_expr(nodes, item, false, prefix, locals)),
+        #[cfg(not(feature = "no_object"))]
+        Expr::Map(items, _) => {
+            let items: Vec<_> = items.iter().map(|(_, item, _)| *item).collect();
+            items
+                .into_iter()
+                .for_each(|item| rename_local_calls_in_expr(nodes, item, false, prefix, locals));
+        }
+        _ => (),
+    }
+}
+
 pub fn parse_global_expr<'a, 'e>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: TokenIterator<'a>,
     engine: &Engine<'e>,
     scope: &Scope,
 ) -> Result<AST, ParseError> {
-    let expr = parse_expr(input, false)?;
+    let input = &mut ParseState::new(input);
+    let expr = parse_expr(input, false, Level::top(engine.max_expr_depth))?;
 
     if let Some((token, pos)) = input.peek() {
         // Return error if the expression doesn't end
         return Err(PERR::BadInput(format!("Unexpected '{}'", token.syntax())).into_err(*pos));
     }
 
+    let mut nodes = mem::take(&mut input.nodes);
+    let statements = vec![Stmt::Expr(nodes.add_expr(expr))];
+    check_reserved_symbols(engine, &statements, &nodes, &[])?;
+
     Ok(
         // Optimize AST
         #[cfg(not(feature = "no_optimize"))]
-        optimize_into_ast(engine, scope, vec![Stmt::Expr(Box::new(expr))], vec![]),
+        optimize_into_ast(engine, scope, statements, nodes, vec![]),
         //
         // Do not optimize AST if `no_optimize`
         #[cfg(feature = "no_optimize")]
         AST(
-            vec![Stmt::Expr(Box::new(expr))],
+            statements,
             #[cfg(feature = "sync")]
             {
                 Arc::new(FunctionsLib::new())
@@ -2645,28 +4619,42 @@ pub fn parse_global_expr<'a, 'e>(
             {
                 Rc::new(FunctionsLib::new())
             },
+            None,
+            vec![],
+            nodes,
         ),
     )
 }
 
-/// Parse the global level statements.
+/// Parse the global level statements, adding them and the function definitions to lists.
+///
+/// The statements and functions parsed before an error are kept.
+#[cfg_attr(feature = "no_function", allow(unused_variables))]
 fn parse_global_level<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
-) -> Result<(Vec<Stmt>, Vec<FnDef>), ParseError> {
-    let mut statements = Vec::<Stmt>::new();
-    let mut functions = Vec::<FnDef>::new();
-
+    input: &mut ParseState<'a>,
+    max_depth: usize,
+    statements: &mut Vec<Stmt>,
+    functions: &mut Vec<FnDef>,
+) -> Result<(), ParseError> {
     while input.peek().is_some() {
         #[cfg(not(feature = "no_function"))]
         {
             // Collect all the function definitions
-            if matches!(input.peek().expect("should not be None"), (Token::Fn, _)) {
-                let f = parse_fn(input, true)?;
-
-                // Ensure list is sorted
-                match functions.binary_search_by(|fn_def| fn_def.compare(&f.name, f.params.len())) {
-                    Ok(n) => functions[n] = f,        // Override previous definition
-                    Err(n) => functions.insert(n, f), // New function definition
+            if matches!(
+                input.peek().expect("should not be None"),
+                (Token::Fn, _) | (Token::Private, _) | (Token::DocComment(_), _)
+            ) {
+                let mut hoisted = Vec::new();
+                let f = parse_fn_def(input, Level::top(max_depth), &mut hoisted)?;
+
+                for f in once(f).chain(hoisted) {
+                    // Ensure list is sorted
+                    match functions
+                        .binary_search_by(|fn_def| fn_def.compare(&f.name, f.params.len()))
+                    {
+                        Ok(n) => functions[n] = f,        // Override previous definition
+                        Err(n) => functions.insert(n, f), // New function definition
+                    }
                 }
 
                 continue;
@@ -2674,7 +4662,7 @@ fn parse_global_level<'a>(
         }
 
         // Actual statement
-        let stmt = parse_stmt(input, false, true)?;
+        let stmt = parse_stmt(input, &[], true, Level::top(max_depth))?;
 
         let need_semicolon = !stmt.is_self_terminated();
 
@@ -2702,25 +4690,301 @@ fn parse_global_level<'a>(
         }
     }
 
-    Ok((statements, functions))
+    Ok(())
+}
+
+/// Skip the rest of a malformed statement, up to the next token that starts a line
+/// (and so most likely starts a new statement) after the error.
+fn skip_malformed_stmt<'a>(input: &mut ParseState<'a>, start: Option<Position>, err_pos: Position) {
+    while let Some((token, pos)) = input.peek() {
+        let is_closing = match token {
+            Token::RightBrace | Token::RightParen => true,
+            #[cfg(not(feature = "no_index"))]
+            Token::RightBracket => true,
+            _ => false,
+        };
+
+        let is_stmt_start = pos.position() == Some(1)
+            && Some(*pos) != start // always make progress
+            && (pos.line() > err_pos.line() || *pos == err_pos)
+            && !is_closing;
+
+        if is_stmt_start {
+            break;
+        }
+
+        input.next();
+    }
+}
+
+/// Run the parser on an input stream without building an AST, collecting all syntax errors.
+///
+/// After each error, parsing resumes at the next line that starts at the first column.
+/// The checks done by `parse` after parsing (e.g. for reserved symbols) are then run on all the
+/// statements parsed successfully, and their errors (if any) are added at the end.
+pub fn validate<'a>(input: TokenIterator<'a>, engine: &Engine) -> Result<(), Vec<ParseError>> {
+    let input = &mut ParseState::new(input);
+    let mut errors = Vec::new();
+    let mut statements = Vec::new();
+    let mut functions = Vec::new();
+
+    while input.peek().is_some() {
+        let start = input.peek().map(|(_, pos)| *pos);
+
+        match parse_global_level(
+            input,
+            engine.max_expr_depth,
+            &mut statements,
+            &mut functions,
+        ) {
+            Ok(_) => break,
+            Err(err) => {
+                skip_malformed_stmt(input, start, err.position());
+                errors.push(err);
+            }
+        }
+    }
+
+    let nodes = &input.nodes;
+
+    errors.extend(check_reserved_symbols(engine, &statements, nodes, &functions).err());
+    errors.extend(check_shadowing(engine, &statements, nodes, &functions).err());
+
+    #[cfg(not(feature = "no_module"))]
+    errors.extend(engine.check_imports(&statements, nodes, &functions).err());
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Find the names reserved via `Engine::set_reserved_symbols` used for variables or functions.
+struct ReservedSymbolChecker<'a> {
+    reserved: &'a HashSet<String>,
+    error: Option<ParseError>,
+}
+
+impl ReservedSymbolChecker<'_> {
+    /// Record an error if a name is reserved.  Only the first error is kept.
+    fn check(&mut self, name: &str, pos: Position) {
+        if self.error.is_none() && self.reserved.contains(name) {
+            self.error = Some(PERR::ReservedSymbol(name.into()).into_err(pos));
+        }
+    }
+}
+
+impl ASTVisitor for ReservedSymbolChecker<'_> {
+    fn enter(&mut self, node: ASTNode, nodes: &Nodes) -> bool {
+        match node {
+            ASTNode::Stmt(Stmt::Let(name, _, pos)) | ASTNode::Stmt(Stmt::Const(name, _, pos)) => {
+                self.check(name, *pos)
+            }
+            ASTNode::Stmt(Stmt::For(name, expr, _, _)) => {
+                self.check(name, nodes[*expr].position(nodes))
+            }
+            #[cfg(not(feature = "no_module"))]
+            ASTNode::Stmt(Stmt::Import(_, alias, pos)) => self.check(alias, *pos),
+            #[cfg(not(feature = "no_module"))]
+            ASTNode::Stmt(Stmt::ImportFrom(names, _, pos)) => {
+                for (_, alias) in names {
+                    self.check(alias, *pos);
+                }
+            }
+            ASTNode::Expr(Expr::Variable(name, pos)) => self.check(name, *pos),
+            ASTNode::Expr(Expr::FunctionCall(name, _, _, _, pos)) => self.check(name, *pos),
+            _ => (),
+        }
+
+        self.error.is_none()
+    }
+}
+
+/// Make sure that names reserved via `Engine::set_reserved_symbols` are not used for variables
+/// or functions.  This runs before optimization, so that no use of a reserved name is missed.
+fn check_reserved_symbols(
+    engine: &Engine,
+    statements: &[Stmt],
+    nodes: &Nodes,
+    functions: &[FnDef],
+) -> Result<(), ParseError> {
+    if engine.reserved_symbols.is_empty() {
+        return Ok(());
+    }
+
+    let mut checker = ReservedSymbolChecker {
+        reserved: &engine.reserved_symbols,
+        error: None,
+    };
+
+    for fn_def in functions {
+        checker.check(&fn_def.name, fn_def.pos);
+        for param in &fn_def.params {
+            checker.check(param, fn_def.pos);
+        }
+        walk_stmt(&fn_def.nodes, &fn_def.body, &mut checker);
+    }
+    for stmt in statements {
+        walk_stmt(nodes, stmt, &mut checker);
+    }
+
+    checker.error.map_or(Ok(()), Err)
+}
+
+/// Find variable declarations reusing the names of variables already declared.
+struct ShadowingChecker {
+    /// Names declared in each block enclosing the current node, innermost last.
+    blocks: Vec<Vec<String>>,
+    /// Blocks enclosing the script-defined function being checked, if any.
+    outer: Vec<Vec<String>>,
+    /// Names declared in the next block, i.e. function parameters or the loop variable.
+    pending: Option<Vec<String>>,
+    /// Is shadowing variables in outer blocks also an error?
+    forbid_all: bool,
+    error: Option<ParseError>,
+}
+
+impl ShadowingChecker {
+    /// Is a variable of this name already declared in the current block (or any enclosing block)?
+    fn is_shadowed(&self, name: &str) -> bool {
+        if self.forbid_all {
+            self.blocks.iter().flatten().any(|n| n == name)
+        } else {
+            self.blocks.last().into_iter().flatten().any(|n| n == name)
+        }
+    }
+
+    /// Record an error for a variable declaration.  Only the first error is kept.
+    fn shadowed(&mut self, name: &str, pos: Position) {
+        if self.error.is_none() {
+            self.error = Some(PERR::VariableShadowed(name.into()).into_err(pos));
+        }
+    }
+}
+
+impl ASTVisitor for ShadowingChecker {
+    fn enter(&mut self, node: ASTNode, nodes: &Nodes) -> bool {
+        match node {
+            // Functions cannot see the variables outside, and parameters are declared in the body
+            ASTNode::Fn(fn_def) => {
+                self.outer = mem::take(&mut self.blocks);
+                self.pending = Some(fn_def.params.clone());
+            }
+            ASTNode::Stmt(Stmt::Block(_, _)) => {
+                let names = self.pending.take().unwrap_or_default();
+                self.blocks.push(names);
+            }
+            ASTNode::Stmt(Stmt::Let(name, _, pos)) | ASTNode::Stmt(Stmt::Const(name, _, pos)) => {
+                if self.is_shadowed(name) {
+                    self.shadowed(name, *pos);
+                }
+                if let Some(block) = self.blocks.last_mut() {
+                    block.push(name.clone());
+                }
+            }
+            // The loop variable is declared in the loop body, after the expression is checked
+            ASTNode::Stmt(Stmt::For(name, expr, body, _)) => {
+                let expr = &nodes[*expr];
+                walk_expr(nodes, expr, self);
+
+                if self.forbid_all && self.is_shadowed(name) {
+                    self.shadowed(name, expr.position(nodes));
+                }
+
+                self.pending = Some(vec![name.clone()]);
+                walk_stmt(nodes, &nodes[*body], self);
+                return false;
+            }
+            _ => (),
+        }
+
+        self.error.is_none()
+    }
+
+    fn leave(&mut self, node: ASTNode) {
+        match node {
+            ASTNode::Fn(_) => self.blocks = mem::take(&mut self.outer),
+            ASTNode::Stmt(Stmt::Block(_, _)) => {
+                self.blocks.pop();
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Make sure that variable declarations do not reuse the names of variables already declared,
+/// as far as forbidden via `Engine::set_shadowing`.
+fn check_shadowing(
+    engine: &Engine,
+    statements: &[Stmt],
+    nodes: &Nodes,
+    functions: &[FnDef],
+) -> Result<(), ParseError> {
+    if engine.shadowing == Shadowing::Allowed {
+        return Ok(());
+    }
+
+    let mut checker = ShadowingChecker {
+        blocks: vec![Vec::new()],
+        outer: Vec::new(),
+        pending: None,
+        forbid_all: engine.shadowing == Shadowing::Forbidden,
+        error: None,
+    };
+
+    for fn_def in functions {
+        walk_fn(fn_def, &mut checker);
+    }
+    for stmt in statements {
+        walk_stmt(nodes, stmt, &mut checker);
+    }
+
+    checker.error.map_or(Ok(()), Err)
 }
 
 /// Run the parser on an input stream, returning an AST.
+#[cfg_attr(feature = "no_optimize", allow(unused_variables))]
 pub fn parse<'a, 'e>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: TokenIterator<'a>,
     engine: &Engine<'e>,
     scope: &Scope,
 ) -> Result<AST, ParseError> {
-    let (statements, functions) = parse_global_level(input)?;
+    let input = &mut ParseState::new(input);
+    let mut statements = Vec::new();
+    let mut functions = Vec::new();
+    parse_global_level(
+        input,
+        engine.max_expr_depth,
+        &mut statements,
+        &mut functions,
+    )?;
+
+    let nodes = mem::take(&mut input.nodes);
+
+    check_reserved_symbols(engine, &statements, &nodes, &functions)?;
+    check_shadowing(engine, &statements, &nodes, &functions)?;
+
+    #[cfg(not(feature = "no_module"))]
+    engine.check_imports(&statements, &nodes, &functions)?;
 
     Ok(
         // Optimize AST
         #[cfg(not(feature = "no_optimize"))]
-        optimize_into_ast(engine, scope, statements, functions),
+        optimize_into_ast(engine, scope, statements, nodes, functions),
         //
         // Do not optimize AST if `no_optimize`
         #[cfg(feature = "no_optimize")]
-        AST(statements, Arc::new(FunctionsLib::from_vec(functions))),
+        AST(
+            statements,
+            #[cfg(feature = "sync")]
+            Arc::new(FunctionsLib::from_vec(functions)),
+            #[cfg(not(feature = "sync"))]
+            Rc::new(FunctionsLib::from_vec(functions)),
+            None,
+            vec![],
+            nodes,
+        ),
     )
 }
 
@@ -0,0 +1,207 @@
+//! Module which defines `Pipeline`, a lazy sequence of values built via `iter`, `map`, `filter` etc.
+
+use crate::any::{Any, AnyExt, Dynamic};
+use crate::engine::Engine;
+use crate::fn_register::RegisterFn;
+use crate::parser::{Position, INT};
+use crate::result::EvalAltResult;
+
+#[cfg(not(feature = "no_index"))]
+use crate::engine::Array;
+
+use crate::stdlib::{boxed::Box, string::String, vec::Vec};
+
+/// A step of a `Pipeline`, applied to each value in turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Stage {
+    /// Replace the value with the result of a function.
+    Map(String),
+    /// Drop the value unless a function returns `true` for it.
+    Filter(String),
+    /// Drop the first number of values.
+    Skip(usize),
+    /// Stop after a number of values.
+    Take(usize),
+}
+
+/// A lazy pipeline over the values of an array, BLOB, range, or any other type with a registered
+/// iterator, created in scripts via `iter`.
+///
+/// Adding steps via `map`, `filter`, `skip` and `take` creates a new `Pipeline` without running
+/// anything.  The steps are run one value at a time only when the pipeline is iterated via a
+/// `for` loop, `collect` or `count`, so no array is built for the values in between.
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    pub(crate) source: Dynamic,
+    pub(crate) stages: Vec<Stage>,
+}
+
+impl Pipeline {
+    /// Create a new `Pipeline` without any steps.
+    pub(crate) fn new(source: Dynamic) -> Self {
+        Self {
+            source,
+            stages: Vec::new(),
+        }
+    }
+
+    /// Add a step at the end of the `Pipeline`.
+    fn then(mut self, stage: Stage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+}
+
+/// The state of an iteration over a `Pipeline`, or over any other value with a registered iterator.
+pub(crate) struct PipelineIter {
+    source: Box<dyn Iterator<Item = Dynamic>>,
+    /// The steps, with `skip` and `take` counting down as values pass.
+    stages: Vec<Stage>,
+    done: bool,
+}
+
+impl Engine<'_> {
+    /// Register the functions adding steps to a `Pipeline`.
+    ///
+    /// `iter`, `collect` and `count`, which need to look up iterators or call functions,
+    /// are handled by the `Engine`.
+    pub(crate) fn register_pipeline(&mut self) {
+        self.register_fn("map", |p: Pipeline, map_fn: String| {
+            p.then(Stage::Map(map_fn))
+        });
+        self.register_fn("filter", |p: Pipeline, filter_fn: String| {
+            p.then(Stage::Filter(filter_fn))
+        });
+        self.register_fn("skip", |p: Pipeline, n: INT| {
+            p.then(Stage::Skip(n.max(0) as usize))
+        });
+        self.register_fn("take", |p: Pipeline, n: INT| {
+            p.then(Stage::Take(n.max(0) as usize))
+        });
+    }
+
+    /// Start iterating over a `Pipeline`, or over a value with a registered iterator.
+    pub(crate) fn iterate(&self, value: &Dynamic) -> Option<PipelineIter> {
+        if let Some(pipeline) = value.downcast_ref::<Pipeline>() {
+            return self.iterate_pipeline(pipeline);
+        }
+
+        let iter_fn = self.type_iterators.as_ref()?.get(&Any::type_id(&**value))?;
+
+        Some(PipelineIter {
+            source: iter_fn(value),
+            stages: Vec::new(),
+            done: false,
+        })
+    }
+
+    /// Start iterating over a `Pipeline`.
+    fn iterate_pipeline(&self, pipeline: &Pipeline) -> Option<PipelineIter> {
+        let mut iter = self.iterate(&pipeline.source)?;
+        iter.stages = pipeline.stages.clone();
+        iter.done = iter.stages.contains(&Stage::Take(0));
+        Some(iter)
+    }
+
+    /// Get the next value of an iteration, running the steps of the `Pipeline` on values from the
+    /// source until one passes all of them.
+    pub(crate) fn next_value(
+        &mut self,
+        iter: &mut PipelineIter,
+        pos: Position,
+        level: usize,
+    ) -> Result<Option<Dynamic>, Box<EvalAltResult>> {
+        'values: while !iter.done {
+            let mut value = match iter.source.next() {
+                Some(value) => value,
+                None => break,
+            };
+
+            for stage in iter.stages.iter_mut() {
+                match stage {
+                    Stage::Map(map_fn) => {
+                        value = self.call_fn_raw(
+                            None,
+                            map_fn,
+                            &mut [value.as_mut()],
+                            None,
+                            pos,
+                            level,
+                        )?;
+                    }
+                    Stage::Filter(filter_fn) => {
+                        let mut arg = value.clone();
+
+                        let keep = self
+                            .call_fn_raw(None, filter_fn, &mut [arg.as_mut()], None, pos, level)?
+                            .try_cast::<bool>()
+                            .map_err(|r| self.make_type_mismatch_err::<bool>(&*r, pos))?;
+
+                        if !keep {
+                            continue 'values;
+                        }
+                    }
+                    Stage::Skip(n) if *n > 0 => {
+                        *n -= 1;
+                        continue 'values;
+                    }
+                    Stage::Skip(_) => (),
+                    Stage::Take(n) => {
+                        // Stop before pulling any more values from the source
+                        *n -= 1;
+                        iter.done |= *n == 0;
+                    }
+                }
+            }
+
+            return Ok(Some(value));
+        }
+
+        iter.done = true;
+        Ok(None)
+    }
+
+    /// Run a `Pipeline` to the end, passing each of its values to a callback.
+    fn run_pipeline(
+        &mut self,
+        pipeline: &Pipeline,
+        pos: Position,
+        level: usize,
+        mut callback: impl FnMut(Dynamic),
+    ) -> Result<(), Box<EvalAltResult>> {
+        let mut iter = self
+            .iterate_pipeline(pipeline)
+            .ok_or_else(|| Box::new(EvalAltResult::ErrorFor(pos)))?;
+
+        while let Some(value) = self.next_value(&mut iter, pos, level)? {
+            callback(value);
+        }
+
+        Ok(())
+    }
+
+    /// Run a `Pipeline` to the end, collecting its values into an array.
+    #[cfg(not(feature = "no_index"))]
+    pub(crate) fn collect_pipeline(
+        &mut self,
+        pipeline: &Pipeline,
+        pos: Position,
+        level: usize,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        let mut values = Vec::new();
+        self.run_pipeline(pipeline, pos, level, |value| values.push(value))?;
+        Ok(values.into_iter().collect())
+    }
+
+    /// Run a `Pipeline` to the end, counting its values.
+    pub(crate) fn count_pipeline(
+        &mut self,
+        pipeline: &Pipeline,
+        pos: Position,
+        level: usize,
+    ) -> Result<INT, Box<EvalAltResult>> {
+        let mut count = 0;
+        self.run_pipeline(pipeline, pos, level, |_| count += 1)?;
+        Ok(count)
+    }
+}
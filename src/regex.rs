@@ -0,0 +1,127 @@
+//! Module which registers the regular expression package, backed by the `regex` crate.
+
+use crate::any::{Any, Dynamic};
+use crate::engine::{Engine, FUNC_TO_STRING, KEYWORD_DEBUG, KEYWORD_PRINT};
+use crate::fn_register::{RegisterDynamicFn, RegisterFn, RegisterResultFn};
+use crate::parser::{Position, INT};
+use crate::result::EvalAltResult;
+
+#[cfg(not(feature = "no_index"))]
+use crate::engine::Array;
+
+#[cfg(not(feature = "no_object"))]
+use crate::engine::Map;
+
+use ::regex::{Captures, Regex};
+
+use crate::stdlib::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+};
+
+/// Get a capture group as a string, or `()` if the group did not participate in the match.
+fn group(captures: &Captures, index: usize) -> Dynamic {
+    match captures.get(index) {
+        Some(m) => m.as_str().to_string().into_dynamic(),
+        None => ().into_dynamic(),
+    }
+}
+
+impl Engine<'_> {
+    /// Register the regular expression package.
+    pub(crate) fn register_regex(&mut self) {
+        self.register_result_fn("regex", |pattern: String| {
+            Regex::new(&pattern).map_err(|err| {
+                Box::new(EvalAltResult::ErrorRuntime(
+                    format!("Invalid regular expression '{}': {}", pattern, err),
+                    Position::none(),
+                ))
+            })
+        });
+
+        self.register_fn("is_match", |re: &mut Regex, text: String| {
+            re.is_match(&text)
+        });
+        self.register_fn("count", |re: &mut Regex, text: String| {
+            re.find_iter(&text).count() as INT
+        });
+        self.register_fn("replace", |re: &mut Regex, text: String, rep: String| {
+            re.replace_all(&text, rep.as_str()).into_owned()
+        });
+        self.register_fn(
+            "replace_first",
+            |re: &mut Regex, text: String, rep: String| {
+                re.replace(&text, rep.as_str()).into_owned()
+            },
+        );
+
+        // The first match as a string, or () if there is none
+        self.register_dynamic_fn("find", |re: &mut Regex, text: String| {
+            match re.find(&text) {
+                Some(m) => m.as_str().to_string().into_dynamic(),
+                None => ().into_dynamic(),
+            }
+        });
+
+        #[cfg(not(feature = "no_index"))]
+        {
+            self.register_fn("find_all", |re: &mut Regex, text: String| {
+                re.find_iter(&text)
+                    .map(|m| m.as_str().to_string().into_dynamic())
+                    .collect::<Array>()
+            });
+            self.register_fn("split", |re: &mut Regex, text: String| {
+                re.split(&text)
+                    .map(|s| s.to_string().into_dynamic())
+                    .collect::<Array>()
+            });
+
+            // The capture groups of the first match as an array, or () if there is no match
+            self.register_dynamic_fn("match", |re: &mut Regex, text: String| {
+                match re.captures(&text) {
+                    Some(captures) => (0..captures.len())
+                        .map(|index| group(&captures, index))
+                        .collect::<Array>()
+                        .into_dynamic(),
+                    None => ().into_dynamic(),
+                }
+            });
+            self.register_fn("match_all", |re: &mut Regex, text: String| {
+                re.captures_iter(&text)
+                    .map(|captures| {
+                        (0..captures.len())
+                            .map(|index| group(&captures, index))
+                            .collect::<Array>()
+                            .into_dynamic()
+                    })
+                    .collect::<Array>()
+            });
+        }
+
+        // The named capture groups of the first match as an object map, or () if there is no match
+        #[cfg(not(feature = "no_object"))]
+        self.register_dynamic_fn("captures", |re: &mut Regex, text: String| {
+            match re.captures(&text) {
+                Some(captures) => re
+                    .capture_names()
+                    .enumerate()
+                    .filter_map(|(index, name)| Some((name?.to_string(), group(&captures, index))))
+                    .collect::<Map>()
+                    .into_dynamic(),
+                None => ().into_dynamic(),
+            }
+        });
+
+        fn to_string(re: &mut Regex) -> String {
+            re.as_str().to_string()
+        }
+
+        self.register_fn("pattern", to_string);
+        self.register_fn(KEYWORD_PRINT, to_string);
+        self.register_fn(FUNC_TO_STRING, to_string);
+        self.register_fn(KEYWORD_DEBUG, |re: &mut Regex| {
+            format!("regex({:?})", re.as_str())
+        });
+    }
+}
@@ -1,18 +1,49 @@
 //! Module containing error definitions for the evaluation process.
 
 use crate::any::Dynamic;
-use crate::error::ParseError;
+use crate::error::{pretty_error, ParseError};
 use crate::parser::{Position, INT};
 
 use crate::stdlib::{
+    boxed::Box,
     error::Error,
     fmt,
     string::{String, ToString},
+    vec::Vec,
 };
 
 #[cfg(not(feature = "no_std"))]
 use crate::stdlib::path::PathBuf;
 
+/// An arithmetic operation that fails, e.g. because it overflows or divides by zero.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(not(feature = "unchecked"))]
+/// # {
+/// use rhai::{Engine, EvalAltResult};
+///
+/// let mut engine = Engine::new();
+///
+/// match *engine.eval::<i64>("let x = 4000000000; x * x").unwrap_err() {
+///     EvalAltResult::ErrorArithmetic(_, Some(op), _) => {
+///         assert_eq!(op.operator, "*");
+///         assert_eq!(op.operands.len(), 2);
+///         assert_eq!(op.operands[0].downcast_ref::<i64>(), Some(&4000000000));
+///     }
+///     _ => panic!("expects arithmetic error"),
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ArithmeticOp {
+    /// Name of the operator or function, e.g. `*` or `abs`.
+    pub operator: String,
+    /// Values of the operands, in order.
+    pub operands: Vec<Dynamic>,
+}
+
 /// Evaluation result.
 ///
 /// All wrapped `Position` values represent the location in the script where the error occurs.
@@ -29,6 +60,11 @@ pub enum EvalAltResult {
 
     /// Call to an unknown function. Wrapped value is the name of the function.
     ErrorFunctionNotFound(String, Position),
+    /// An `import` statement refers to a module that cannot be found. Wrapped value is the module path.
+    ErrorModuleNotFound(String, Position),
+    /// Modules import one another in a cycle.
+    /// Wrapped value is the chain of module paths imported, starting and ending with the same path.
+    ErrorCircularImport(Vec<String>, Position),
     /// Function call has incorrect number of arguments.
     /// Wrapped values are the name of the function, the number of parameters required
     /// and the actual number of arguments passed.
@@ -37,15 +73,20 @@ pub enum EvalAltResult {
     ErrorBooleanArgMismatch(String, Position),
     /// Non-character value encountered where a character is required.
     ErrorCharMismatch(Position),
-    /// Array access out-of-bounds.
+    /// Non-integer value, or integer outside 0 to 255, encountered where a byte is required.
+    ErrorByteMismatch(Position),
+    /// Array (or BLOB) access out-of-bounds.
     /// Wrapped values are the current number of elements in the array and the index number.
     ErrorArrayBounds(usize, INT, Position),
     /// String indexing out-of-bounds.
     /// Wrapped values are the current number of characters in the string and the index number.
     ErrorStringBounds(usize, INT, Position),
-    /// Trying to index into a type that is not an array, an object map, or a string.
+    /// Bit-field indexing into an integer out-of-bounds.
+    /// Wrapped values are the number of bits in the integer and the bit index.
+    ErrorBitFieldBounds(usize, INT, Position),
+    /// Trying to index into a type that is not an array, a BLOB, an object map, a string, or an integer.
     ErrorIndexingType(String, Position),
-    /// Trying to index into an array or string with an index that is not `i64`.
+    /// Trying to index into an array, BLOB, string or integer with an index that is not `i64`.
     ErrorNumericIndexExpr(Position),
     /// Trying to index into a map with an index that is not `String`.
     ErrorStringIndexExpr(Position),
@@ -59,13 +100,25 @@ pub enum EvalAltResult {
     ErrorAssignmentToUnknownLHS(Position),
     /// Assignment to a constant variable.
     ErrorAssignmentToConstant(String, Position),
+    /// Definition of a variable forbidden by the variable definition filter.
+    /// Wrapped value is the name of the variable.
+    ErrorForbiddenVariable(String, Position),
+    /// Call to a native function forbidden by the function call audit callback,
+    /// or to a native function requiring capabilities that are not granted.
+    /// Wrapped value is the name of the function.
+    ErrorForbiddenFunction(String, Position),
+    /// Call to a single-use native function (registered via `register_fn_once`) which has
+    /// already been called.  Wrapped value is the name of the function.
+    ErrorFunctionConsumed(String, Position),
     /// Returned type is not the same as the required output type.
-    /// Wrapped value is the type of the actual result.
-    ErrorMismatchOutputType(String, Position),
+    /// Wrapped values are the name of the required type and the type of the actual result.
+    ErrorMismatchOutputType(String, String, Position),
     /// Inappropriate member access.
     ErrorDotExpr(String, Position),
-    /// Arithmetic error encountered. Wrapped value is the error message.
-    ErrorArithmetic(String, Position),
+    /// Arithmetic error encountered.
+    /// Wrapped values are the error message and, if the error is caused by an operator (or a
+    /// numeric function such as `abs`) failing on its operands, the operation.
+    ErrorArithmetic(String, Option<Box<ArithmeticOp>>, Position),
     /// Call stack over maximum limit.
     ErrorStackOverflow(Position),
     /// Run-time error encountered. Wrapped value is the error message.
@@ -74,7 +127,8 @@ pub enum EvalAltResult {
     /// Breaking out of loops - not an error if within a loop.
     /// The wrapped value, if true, means breaking clean out of the loop (i.e. a `break` statement).
     /// The wrapped value, if false, means breaking the current context (i.e. a `continue` statement).
-    ErrorLoopBreak(bool, Position),
+    /// The wrapped label, if any, is the label of the loop to break out of or continue.
+    ErrorLoopBreak(bool, Option<String>, Position),
     /// Not an error: Value returned from a script via the `return` keyword.
     /// Wrapped value is the result value.
     Return(Dynamic, Position),
@@ -88,17 +142,20 @@ impl EvalAltResult {
 
             Self::ErrorParsing(p) => p.desc(),
             Self::ErrorFunctionNotFound(_, _) => "Function not found",
+            Self::ErrorModuleNotFound(_, _) => "Module not found",
+            Self::ErrorCircularImport(_, _) => "Circular import",
             Self::ErrorFunctionArgsMismatch(_, _, _, _) => {
                 "Function call with wrong number of arguments"
             }
             Self::ErrorBooleanArgMismatch(_, _) => "Boolean operator expects boolean operands",
             Self::ErrorCharMismatch(_) => "Character expected",
+            Self::ErrorByteMismatch(_) => "Integer between 0 and 255 expected",
             Self::ErrorNumericIndexExpr(_) => {
-                "Indexing into an array or string expects an integer index"
+                "Indexing into an array, BLOB, string or integer expects an integer index"
             }
             Self::ErrorStringIndexExpr(_) => "Indexing into an object map expects a string index",
             Self::ErrorIndexingType(_, _) => {
-                "Indexing can only be performed on an array, an object map, or a string"
+                "Indexing can only be performed on an array, a BLOB, an object map, a string, or an integer"
             }
             Self::ErrorArrayBounds(_, index, _) if *index < 0 => {
                 "Array access expects non-negative index"
@@ -110,6 +167,10 @@ impl EvalAltResult {
             }
             Self::ErrorStringBounds(0, _, _) => "Indexing of empty string",
             Self::ErrorStringBounds(_, _, _) => "String index out of bounds",
+            Self::ErrorBitFieldBounds(_, index, _) if *index < 0 => {
+                "Bit-field access expects non-negative index"
+            }
+            Self::ErrorBitFieldBounds(_, _, _) => "Bit-field index out of bounds",
             Self::ErrorLogicGuard(_) => "Boolean expression expected",
             Self::ErrorFor(_) => "For loop expects array or range",
             Self::ErrorVariableNotFound(_, _) => "Variable not found",
@@ -117,19 +178,33 @@ impl EvalAltResult {
                 "Assignment to an unsupported left-hand side expression"
             }
             Self::ErrorAssignmentToConstant(_, _) => "Assignment to a constant variable",
-            Self::ErrorMismatchOutputType(_, _) => "Output type is incorrect",
+            Self::ErrorForbiddenVariable(_, _) => "Forbidden variable definition",
+            Self::ErrorForbiddenFunction(_, _) => "Forbidden function call",
+            Self::ErrorFunctionConsumed(_, _) => "Single-use function already called",
+            Self::ErrorMismatchOutputType(_, _, _) => "Output type is incorrect",
             Self::ErrorDotExpr(_, _) => "Malformed dot expression",
-            Self::ErrorArithmetic(_, _) => "Arithmetic error",
+            Self::ErrorArithmetic(_, _, _) => "Arithmetic error",
             Self::ErrorStackOverflow(_) => "Stack overflow",
             Self::ErrorRuntime(_, _) => "Runtime error",
-            Self::ErrorLoopBreak(true, _) => "Break statement not inside a loop",
-            Self::ErrorLoopBreak(false, _) => "Continue statement not inside a loop",
+            Self::ErrorLoopBreak(true, _, _) => "Break statement not inside a loop",
+            Self::ErrorLoopBreak(false, _, _) => "Continue statement not inside a loop",
             Self::Return(_, _) => "[Not Error] Function returns value",
         }
     }
 }
 
-impl Error for EvalAltResult {}
+impl Error for EvalAltResult {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            #[cfg(not(feature = "no_std"))]
+            Self::ErrorReadingScriptFile(_, err) => Some(err),
+
+            Self::ErrorParsing(err) => Some(err),
+
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Display for EvalAltResult {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -143,7 +218,9 @@ impl fmt::Display for EvalAltResult {
 
             Self::ErrorParsing(p) => write!(f, "Syntax error: {}", p),
 
-            Self::ErrorFunctionNotFound(s, pos) | Self::ErrorVariableNotFound(s, pos) => {
+            Self::ErrorFunctionNotFound(s, pos)
+            | Self::ErrorModuleNotFound(s, pos)
+            | Self::ErrorVariableNotFound(s, pos) => {
                 write!(f, "{}: '{}' ({})", desc, s, pos)
             }
             Self::ErrorDotExpr(s, pos) if !s.is_empty() => write!(f, "{} {} ({})", desc, s, pos),
@@ -161,11 +238,19 @@ impl fmt::Display for EvalAltResult {
                 write!(f, "{} ({})", if s.is_empty() { desc } else { s }, pos)
             }
 
+            Self::ErrorCircularImport(chain, pos) => {
+                write!(f, "{}: {} ({})", desc, chain.join(" -> "), pos)
+            }
             Self::ErrorAssignmentToConstant(s, pos) => write!(f, "{}: '{}' ({})", desc, s, pos),
-            Self::ErrorMismatchOutputType(s, pos) => write!(f, "{}: {} ({})", desc, s, pos),
-            Self::ErrorArithmetic(s, pos) => write!(f, "{} ({})", s, pos),
+            Self::ErrorForbiddenVariable(s, pos) => write!(f, "{}: '{}' ({})", desc, s, pos),
+            Self::ErrorForbiddenFunction(s, pos) => write!(f, "{}: '{}' ({})", desc, s, pos),
+            Self::ErrorFunctionConsumed(s, pos) => write!(f, "{}: '{}' ({})", desc, s, pos),
+            Self::ErrorMismatchOutputType(expected, actual, pos) => {
+                write!(f, "{}: {} (expecting {}) ({})", desc, actual, expected, pos)
+            }
+            Self::ErrorArithmetic(s, _, pos) => write!(f, "{} ({})", s, pos),
 
-            Self::ErrorLoopBreak(_, pos) => write!(f, "{} ({})", desc, pos),
+            Self::ErrorLoopBreak(_, _, pos) => write!(f, "{} ({})", desc, pos),
             Self::Return(_, pos) => write!(f, "{} ({})", desc, pos),
 
             Self::ErrorFunctionArgsMismatch(fn_name, 0, n, pos) => write!(
@@ -189,6 +274,13 @@ impl fmt::Display for EvalAltResult {
             Self::ErrorCharMismatch(pos) => {
                 write!(f, "string indexing expects a character value ({})", pos)
             }
+            Self::ErrorByteMismatch(pos) => {
+                write!(
+                    f,
+                    "BLOB indexing expects an integer between 0 and 255 ({})",
+                    pos
+                )
+            }
             Self::ErrorArrayBounds(_, index, pos) if *index < 0 => {
                 write!(f, "{}: {} < 0 ({})", desc, index, pos)
             }
@@ -217,6 +309,14 @@ impl fmt::Display for EvalAltResult {
                 "String index {} is out of bounds: only {} characters in the string ({})",
                 index, max, pos
             ),
+            Self::ErrorBitFieldBounds(_, index, pos) if *index < 0 => {
+                write!(f, "{}: {} < 0 ({})", desc, index, pos)
+            }
+            Self::ErrorBitFieldBounds(max, index, pos) => write!(
+                f,
+                "Bit-field index {} is out of bounds: only {} bits in the integer ({})",
+                index, max, pos
+            ),
         }
     }
 }
@@ -227,13 +327,52 @@ impl From<ParseError> for EvalAltResult {
     }
 }
 
+impl From<ParseError> for Box<EvalAltResult> {
+    fn from(err: ParseError) -> Self {
+        Box::new(EvalAltResult::ErrorParsing(err))
+    }
+}
+
 impl<T: AsRef<str>> From<T> for EvalAltResult {
     fn from(err: T) -> Self {
         Self::ErrorRuntime(err.as_ref().to_string(), Position::none())
     }
 }
 
+impl<T: AsRef<str>> From<T> for Box<EvalAltResult> {
+    fn from(err: T) -> Self {
+        Box::new(EvalAltResult::ErrorRuntime(
+            err.as_ref().to_string(),
+            Position::none(),
+        ))
+    }
+}
+
 impl EvalAltResult {
+    /// Format the error together with the offending line of the script source,
+    /// with a `^` marker under the position of the error.
+    ///
+    /// Errors without a position (or with a position outside the source) are formatted as usual.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rhai::Engine;
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// let source = "let x = 42;\nx + y";
+    /// let err = engine.eval::<i64>(source).unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.to_pretty_string(source),
+    ///     "Variable not found: 'y' (line 2, position 5)\n  |\n2 | x + y\n  |     ^"
+    /// );
+    /// ```
+    pub fn to_pretty_string(&self, source: &str) -> String {
+        pretty_error(self.to_string(), source, self.position())
+    }
+
     pub fn position(&self) -> Position {
         match self {
             #[cfg(not(feature = "no_std"))]
@@ -242,11 +381,15 @@ impl EvalAltResult {
             Self::ErrorParsing(err) => err.position(),
 
             Self::ErrorFunctionNotFound(_, pos)
+            | Self::ErrorModuleNotFound(_, pos)
+            | Self::ErrorCircularImport(_, pos)
             | Self::ErrorFunctionArgsMismatch(_, _, _, pos)
             | Self::ErrorBooleanArgMismatch(_, pos)
             | Self::ErrorCharMismatch(pos)
+            | Self::ErrorByteMismatch(pos)
             | Self::ErrorArrayBounds(_, _, pos)
             | Self::ErrorStringBounds(_, _, pos)
+            | Self::ErrorBitFieldBounds(_, _, pos)
             | Self::ErrorIndexingType(_, pos)
             | Self::ErrorNumericIndexExpr(pos)
             | Self::ErrorStringIndexExpr(pos)
@@ -255,30 +398,37 @@ impl EvalAltResult {
             | Self::ErrorVariableNotFound(_, pos)
             | Self::ErrorAssignmentToUnknownLHS(pos)
             | Self::ErrorAssignmentToConstant(_, pos)
-            | Self::ErrorMismatchOutputType(_, pos)
+            | Self::ErrorForbiddenVariable(_, pos)
+            | Self::ErrorForbiddenFunction(_, pos)
+            | Self::ErrorFunctionConsumed(_, pos)
+            | Self::ErrorMismatchOutputType(_, _, pos)
             | Self::ErrorDotExpr(_, pos)
-            | Self::ErrorArithmetic(_, pos)
+            | Self::ErrorArithmetic(_, _, pos)
             | Self::ErrorStackOverflow(pos)
             | Self::ErrorRuntime(_, pos)
-            | Self::ErrorLoopBreak(_, pos)
+            | Self::ErrorLoopBreak(_, _, pos)
             | Self::Return(_, pos) => *pos,
         }
     }
 
     /// Consume the current `EvalAltResult` and return a new one
     /// with the specified `Position`.
-    pub(crate) fn set_position(mut self, new_position: Position) -> Self {
-        match &mut self {
+    pub(crate) fn set_position(mut self: Box<Self>, new_position: Position) -> Box<Self> {
+        match self.as_mut() {
             #[cfg(not(feature = "no_std"))]
             Self::ErrorReadingScriptFile(_, _) => (),
 
             Self::ErrorParsing(ParseError(_, pos))
             | Self::ErrorFunctionNotFound(_, pos)
+            | Self::ErrorModuleNotFound(_, pos)
+            | Self::ErrorCircularImport(_, pos)
             | Self::ErrorFunctionArgsMismatch(_, _, _, pos)
             | Self::ErrorBooleanArgMismatch(_, pos)
             | Self::ErrorCharMismatch(pos)
+            | Self::ErrorByteMismatch(pos)
             | Self::ErrorArrayBounds(_, _, pos)
             | Self::ErrorStringBounds(_, _, pos)
+            | Self::ErrorBitFieldBounds(_, _, pos)
             | Self::ErrorIndexingType(_, pos)
             | Self::ErrorNumericIndexExpr(pos)
             | Self::ErrorStringIndexExpr(pos)
@@ -287,12 +437,15 @@ impl EvalAltResult {
             | Self::ErrorVariableNotFound(_, pos)
             | Self::ErrorAssignmentToUnknownLHS(pos)
             | Self::ErrorAssignmentToConstant(_, pos)
-            | Self::ErrorMismatchOutputType(_, pos)
+            | Self::ErrorForbiddenVariable(_, pos)
+            | Self::ErrorForbiddenFunction(_, pos)
+            | Self::ErrorFunctionConsumed(_, pos)
+            | Self::ErrorMismatchOutputType(_, _, pos)
             | Self::ErrorDotExpr(_, pos)
-            | Self::ErrorArithmetic(_, pos)
+            | Self::ErrorArithmetic(_, _, pos)
             | Self::ErrorStackOverflow(pos)
             | Self::ErrorRuntime(_, pos)
-            | Self::ErrorLoopBreak(_, pos)
+            | Self::ErrorLoopBreak(_, _, pos)
             | Self::Return(_, pos) => *pos = new_position,
         }
 
@@ -1,14 +1,10 @@
 //! Module that defines the `Scope` type representing a function call-stack scope.
 
 use crate::any::{Any, Dynamic};
+use crate::engine::Identifier;
 use crate::parser::{map_dynamic_to_expr, Expr, Position};
 
-use crate::stdlib::{
-    borrow::Cow,
-    iter,
-    string::{String, ToString},
-    vec::Vec,
-};
+use crate::stdlib::{borrow::Cow, iter, marker::PhantomData, vec::Vec};
 
 /// Type of an entry in the Scope.
 #[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
@@ -21,9 +17,9 @@ pub enum EntryType {
 
 /// An entry in the Scope.
 #[derive(Debug, Clone)]
-pub struct Entry<'a> {
-    /// Name of the entry.
-    pub name: Cow<'a, str>,
+pub struct Entry {
+    /// Name of the entry.  Short names are kept inline, without allocating.
+    pub name: Identifier,
     /// Type of the entry.
     pub typ: EntryType,
     /// Current value of the entry.
@@ -46,7 +42,7 @@ pub(crate) struct EntryRef<'a> {
 /// # Example
 ///
 /// ```
-/// # fn main() -> Result<(), rhai::EvalAltResult> {
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
 /// use rhai::{Engine, Scope};
 ///
 /// let mut engine = Engine::new();
@@ -69,7 +65,7 @@ pub(crate) struct EntryRef<'a> {
 ///
 /// Currently, `Scope` is neither `Send` nor `Sync`. Turn on the `sync` feature to make it `Send + Sync`.
 #[derive(Debug, Clone)]
-pub struct Scope<'a>(Vec<Entry<'a>>);
+pub struct Scope<'a>(Vec<Entry>, PhantomData<&'a ()>);
 
 impl<'a> Scope<'a> {
     /// Create a new Scope.
@@ -85,7 +81,7 @@ impl<'a> Scope<'a> {
     /// assert_eq!(my_scope.get_value::<i64>("x").unwrap(), 42);
     /// ```
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self(Vec::new(), PhantomData)
     }
 
     /// Empty the Scope.
@@ -158,7 +154,7 @@ impl<'a> Scope<'a> {
     /// assert_eq!(my_scope.get_value::<i64>("x").unwrap(), 42);
     /// ```
     pub fn push<K: Into<Cow<'a, str>>, T: Any + Clone>(&mut self, name: K, value: T) {
-        self.push_dynamic_value(name, EntryType::Normal, value.into_dynamic(), false);
+        self.push_dynamic_value(name.into(), EntryType::Normal, value.into_dynamic(), false);
     }
 
     /// Add (push) a new `Dynamic` entry to the Scope.
@@ -174,7 +170,7 @@ impl<'a> Scope<'a> {
     /// assert_eq!(my_scope.get_value::<i64>("x").unwrap(), 42);
     /// ```
     pub fn push_dynamic<K: Into<Cow<'a, str>>>(&mut self, name: K, value: Dynamic) {
-        self.push_dynamic_value(name, EntryType::Normal, value, false);
+        self.push_dynamic_value(name.into(), EntryType::Normal, value, false);
     }
 
     /// Add (push) a new constant to the Scope.
@@ -196,7 +192,7 @@ impl<'a> Scope<'a> {
     /// assert_eq!(my_scope.get_value::<i64>("x").unwrap(), 42);
     /// ```
     pub fn push_constant<K: Into<Cow<'a, str>>, T: Any + Clone>(&mut self, name: K, value: T) {
-        self.push_dynamic_value(name, EntryType::Constant, value.into_dynamic(), true);
+        self.push_dynamic_value(name.into(), EntryType::Constant, value.into_dynamic(), true);
     }
 
     /// Add (push) a new constant with a `Dynamic` value to the Scope.
@@ -219,26 +215,28 @@ impl<'a> Scope<'a> {
     /// assert_eq!(my_scope.get_value::<i64>("x").unwrap(), 42);
     /// ```
     pub fn push_constant_dynamic<K: Into<Cow<'a, str>>>(&mut self, name: K, value: Dynamic) {
-        self.push_dynamic_value(name, EntryType::Constant, value, true);
+        self.push_dynamic_value(name.into(), EntryType::Constant, value, true);
     }
 
     /// Add (push) a new entry with a `Dynamic` value to the Scope.
-    pub(crate) fn push_dynamic_value<K: Into<Cow<'a, str>>>(
+    pub(crate) fn push_dynamic_value<K: Into<Identifier>>(
         &mut self,
         name: K,
         entry_type: EntryType,
         value: Dynamic,
         map_expr: bool,
     ) {
+        let expr = if map_expr {
+            map_dynamic_to_expr(value.clone(), Position::none())
+        } else {
+            None
+        };
+
         self.0.push(Entry {
             name: name.into(),
             typ: entry_type,
-            value: value.clone(),
-            expr: if map_expr {
-                map_dynamic_to_expr(value, Position::none())
-            } else {
-                None
-            },
+            value,
+            expr,
         });
     }
 
@@ -289,11 +287,25 @@ impl<'a> Scope<'a> {
         self.0
             .iter()
             .rev() // Always search a Scope in reverse order
-            .any(|Entry { name: key, .. }| name == key)
+            .any(|Entry { name: key, .. }| key == name)
+    }
+
+    /// Find an entry in the Scope, starting from the last, without copying its value.
+    pub(crate) fn get_ref(&self, name: &str) -> Option<EntryRef<'_>> {
+        self.0
+            .iter()
+            .enumerate()
+            .rev() // Always search a Scope in reverse order
+            .find(|(_, Entry { name: key, .. })| key == name)
+            .map(|(index, Entry { name: key, typ, .. })| EntryRef {
+                name: key,
+                index,
+                typ: *typ,
+            })
     }
 
     /// Find an entry in the Scope, starting from the last.
-    pub(crate) fn get(&self, name: &str) -> Option<(EntryRef, Dynamic)> {
+    pub(crate) fn get(&self, name: &str) -> Option<(EntryRef<'_>, Dynamic)> {
         self.0
             .iter()
             .enumerate()
@@ -308,7 +320,7 @@ impl<'a> Scope<'a> {
                         ..
                     },
                 )| {
-                    if name == key {
+                    if key == name {
                         Some((
                             EntryRef {
                                 name: key,
@@ -340,7 +352,7 @@ impl<'a> Scope<'a> {
         self.0
             .iter()
             .rev()
-            .find(|Entry { name: key, .. }| name == key)
+            .find(|Entry { name: key, .. }| key == name)
             .and_then(|Entry { value, .. }| value.downcast_ref::<T>())
             .map(T::clone)
     }
@@ -428,7 +440,7 @@ where
     fn extend<T: IntoIterator<Item = (K, EntryType, Dynamic)>>(&mut self, iter: T) {
         self.0
             .extend(iter.into_iter().map(|(name, typ, value)| Entry {
-                name: name.into(),
+                name: name.into().into(),
                 typ,
                 value,
                 expr: None,
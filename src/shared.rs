@@ -0,0 +1,472 @@
+//! Module which defines the copy-on-write container used for arrays and object maps,
+//! and values shared between the host and scripts.
+
+use crate::any::{Any, Dynamic, Variant};
+use crate::parser::Position;
+use crate::result::EvalAltResult;
+
+#[cfg(not(feature = "no_index"))]
+use crate::engine::Array;
+
+#[cfg(not(feature = "no_object"))]
+use crate::engine::Map;
+use crate::stdlib::{
+    any::Any as StdAny,
+    boxed::Box,
+    collections::{BTreeMap, HashSet},
+    fmt,
+    iter::FromIterator,
+    mem,
+    ops::{Deref, DerefMut},
+    string::String,
+    vec::Vec,
+};
+
+#[cfg(feature = "sync")]
+use crate::stdlib::sync::{Arc, RwLock};
+
+#[cfg(not(feature = "sync"))]
+use crate::stdlib::{cell::RefCell, rc::Rc};
+
+/// A copy-on-write container.
+///
+/// Cloning a `Shared` value only clones a reference to the underlying data, so passing big arrays
+/// and object maps around is cheap.  The data is copied the first time a shared value is modified
+/// (via `DerefMut`), so each clone still behaves as an independent value.
+///
+/// # Example
+///
+/// ```
+/// use rhai::Shared;
+///
+/// let mut x: Shared<Vec<i64>> = vec![1, 2, 3].into();
+/// let y = x.clone();              // 'y' shares the data with 'x'
+///
+/// x.push(4);                      // 'x' gets its own copy before it is modified
+///
+/// assert_eq!(*x, vec![1, 2, 3, 4]);
+/// assert_eq!(*y, vec![1, 2, 3]);
+/// ```
+#[derive(Default)]
+pub struct Shared<T: Clone + 'static>(
+    #[cfg(feature = "sync")] Arc<T>,
+    #[cfg(not(feature = "sync"))] Rc<T>,
+);
+
+impl<T: Clone + Default + 'static> Shared<T> {
+    /// Create a new, empty `Shared` value.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<T: Clone + 'static> Shared<T> {
+    /// Take the underlying data out of the `Shared` value, copying it only if it is still shared.
+    pub fn into_inner(self) -> T {
+        let data = self.0.clone();
+        drop(self);
+
+        #[cfg(feature = "sync")]
+        return Arc::try_unwrap(data).unwrap_or_else(|data| (*data).clone());
+        #[cfg(not(feature = "sync"))]
+        return Rc::try_unwrap(data).unwrap_or_else(|data| (*data).clone());
+    }
+
+    /// Get mutable access to the underlying data, unless it is shared with other `Shared` values.
+    fn unique_data(&mut self) -> Option<&mut dyn StdAny> {
+        #[cfg(feature = "sync")]
+        return Arc::get_mut(&mut self.0).map(|data| data as _);
+        #[cfg(not(feature = "sync"))]
+        return Rc::get_mut(&mut self.0).map(|data| data as _);
+    }
+
+    /// Is the underlying data shared with other `Shared` values?
+    pub fn is_shared(&self) -> bool {
+        #[cfg(feature = "sync")]
+        return Arc::strong_count(&self.0) > 1;
+        #[cfg(not(feature = "sync"))]
+        return Rc::strong_count(&self.0) > 1;
+    }
+}
+
+impl<T: Clone + 'static> Drop for Shared<T> {
+    /// Drop the values in nested arrays and object maps one at a time instead of recursively,
+    /// so that dropping a deeply nested value cannot overflow the stack.
+    fn drop(&mut self) {
+        if let Some(data) = self.unique_data() {
+            drop_values(take_values(data));
+        }
+    }
+}
+
+/// Drop values, and the values nested in them (in arrays, object maps and shared values), one at
+/// a time instead of recursively.
+fn drop_values(mut values: Vec<Dynamic>) {
+    while let Some(mut value) = values.pop() {
+        if let Some(array) = value.downcast_mut::<Shared<Vec<Dynamic>>>() {
+            values.extend(array.unique_data().map_or_else(Vec::new, take_values));
+        } else if let Some(map) = value.downcast_mut::<Shared<BTreeMap<String, Dynamic>>>() {
+            values.extend(map.unique_data().map_or_else(Vec::new, take_values));
+        } else if let Some(shared) = value.downcast_mut::<SharedValue>() {
+            values.extend(shared.take_unique());
+        }
+        // 'value' no longer holds any values, so it is dropped here without recursion
+    }
+}
+
+/// Take all the values out of an array or object map.
+fn take_values(data: &mut dyn StdAny) -> Vec<Dynamic> {
+    if let Some(array) = data.downcast_mut::<Vec<Dynamic>>() {
+        mem::take(array)
+    } else if let Some(map) = data.downcast_mut::<BTreeMap<String, Dynamic>>() {
+        mem::take(map).into_values().collect()
+    } else {
+        Vec::new()
+    }
+}
+
+impl<T: Clone + 'static> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: Clone + 'static> From<T> for Shared<T> {
+    fn from(value: T) -> Self {
+        #[cfg(feature = "sync")]
+        return Self(Arc::new(value));
+        #[cfg(not(feature = "sync"))]
+        return Self(Rc::new(value));
+    }
+}
+
+impl<T: Clone + 'static> Deref for Shared<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Clone + 'static> DerefMut for Shared<T> {
+    /// Get mutable access to the data, copying it first if it is shared.
+    fn deref_mut(&mut self) -> &mut T {
+        #[cfg(feature = "sync")]
+        return Arc::make_mut(&mut self.0);
+        #[cfg(not(feature = "sync"))]
+        return Rc::make_mut(&mut self.0);
+    }
+}
+
+impl<T: Clone + 'static + fmt::Debug> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: Clone + 'static + PartialEq> PartialEq for Shared<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Clone + 'static + FromIterator<A>, A> FromIterator<A> for Shared<T> {
+    fn from_iter<I: IntoIterator<Item = A>>(iter: I) -> Self {
+        T::from_iter(iter).into()
+    }
+}
+
+impl<T: Clone + 'static + Extend<A>, A> Extend<A> for Shared<T> {
+    fn extend<I: IntoIterator<Item = A>>(&mut self, iter: I) {
+        self.deref_mut().extend(iter)
+    }
+}
+
+impl<T: Clone + 'static + IntoIterator> IntoIterator for Shared<T> {
+    type Item = T::Item;
+    type IntoIter = T::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_inner().into_iter()
+    }
+}
+
+impl<'a, T: Clone + 'static> IntoIterator for &'a Shared<T>
+where
+    &'a T: IntoIterator,
+{
+    type Item = <&'a T as IntoIterator>::Item;
+    type IntoIter = <&'a T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.as_ref().into_iter()
+    }
+}
+
+/// A value shared by all its clones.
+///
+/// Unlike all other values, which are copied when assigned or passed to functions, all clones of
+/// a `SharedValue` refer to the same value, so a change made via one clone is visible via all the others.
+/// This makes it possible to hand a value to a script (e.g. by pushing it into a `Scope`) and then
+/// observe or change it from Rust while (or after) the script modifies it.
+///
+/// In scripts, `shared(value)` creates a shared value, `get(x)` returns a copy of the value and
+/// `set(x, value)` replaces it.  If the value is an object map, its properties can also be accessed
+/// directly, e.g. `x.prop` and `x.prop = 42`.
+///
+/// Under the `sync` feature, the value is kept behind an `Arc<RwLock<Dynamic>>`, so a `SharedValue`
+/// is `Send + Sync` and its clones can be handed to other threads.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// # #[cfg(not(feature = "no_object"))]
+/// # {
+/// use rhai::{AnyExt, Engine, Map, Scope, SharedValue, INT};
+///
+/// let mut engine = Engine::new();
+/// let mut scope = Scope::new();
+///
+/// // A blackboard object map shared between the host and the script
+/// let board = SharedValue::new(Map::new());
+/// scope.push("board", board.clone());
+///
+/// engine.eval_with_scope::<()>(&mut scope, "board.answer = 42;")?;
+///
+/// let answer = board.read(|map| map.downcast_ref::<Map>().unwrap()["answer"].clone());
+/// assert_eq!(answer.cast::<INT>(), 42);
+///
+/// // Under the `sync` feature, the blackboard can also be mutated from another thread
+/// #[cfg(feature = "sync")]
+/// std::thread::spawn(move || {
+///     board.write(|map| {
+///         let map = map.downcast_mut::<Map>().unwrap();
+///         map.insert("done".to_string(), Box::new(true));
+///     })
+/// })
+/// .join()
+/// .unwrap();
+///
+/// # #[cfg(feature = "sync")]
+/// assert!(engine.eval_with_scope::<bool>(&mut scope, "board.done")?);
+/// # }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SharedValue(
+    #[cfg(feature = "sync")] Arc<RwLock<Dynamic>>,
+    #[cfg(not(feature = "sync"))] Rc<RefCell<Dynamic>>,
+);
+
+impl SharedValue {
+    /// Create a new `SharedValue`.
+    pub fn new<T: Any>(value: T) -> Self {
+        Self::from_dynamic(Box::new(value))
+    }
+
+    /// Create a new `SharedValue` from a `Dynamic` value.
+    pub fn from_dynamic(value: Dynamic) -> Self {
+        #[cfg(feature = "sync")]
+        return Self(Arc::new(RwLock::new(value)));
+        #[cfg(not(feature = "sync"))]
+        return Self(Rc::new(RefCell::new(value)));
+    }
+
+    /// Call a function with a reference to the value.
+    ///
+    /// Under the `sync` feature, other threads cannot modify the value while the function runs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is being modified via `write` (e.g. from within the function passed to
+    /// `write`), or under the `sync` feature, if a thread panicked while modifying the value.
+    /// Use `try_read` to get an error instead.
+    pub fn read<R>(&self, f: impl FnOnce(&Variant) -> R) -> R {
+        self.try_read(f)
+            .expect("the shared value should be readable")
+    }
+
+    /// Call a function with a mutable reference to the value.
+    ///
+    /// Under the `sync` feature, other threads cannot access the value while the function runs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is being accessed via `read` or `write` (e.g. from within the function
+    /// passed to them), or under the `sync` feature, if a thread panicked while modifying the value.
+    /// Use `try_write` to get an error instead.
+    pub fn write<R>(&self, f: impl FnOnce(&mut Dynamic) -> R) -> R {
+        self.try_write(f)
+            .expect("the shared value should be writable")
+    }
+
+    /// Call a function with a reference to the value, or return an error if the value cannot
+    /// be read (see `read`).
+    pub fn try_read<R>(&self, f: impl FnOnce(&Variant) -> R) -> Result<R, Box<EvalAltResult>> {
+        #[cfg(feature = "sync")]
+        return match self.0.read() {
+            Ok(value) => Ok(f(value.as_ref())),
+            Err(_) => Err(poisoned()),
+        };
+        #[cfg(not(feature = "sync"))]
+        return match self.0.try_borrow() {
+            Ok(value) => Ok(f(value.as_ref())),
+            Err(_) => Err(in_use()),
+        };
+    }
+
+    /// Call a function with a mutable reference to the value, or return an error if the value
+    /// cannot be modified (see `write`).
+    pub fn try_write<R>(&self, f: impl FnOnce(&mut Dynamic) -> R) -> Result<R, Box<EvalAltResult>> {
+        #[cfg(feature = "sync")]
+        return match self.0.write() {
+            Ok(mut value) => Ok(f(&mut value)),
+            Err(_) => Err(poisoned()),
+        };
+        #[cfg(not(feature = "sync"))]
+        return match self.0.try_borrow_mut() {
+            Ok(mut value) => Ok(f(&mut value)),
+            Err(_) => Err(in_use()),
+        };
+    }
+
+    /// Get a copy of the value.
+    pub fn get(&self) -> Dynamic {
+        self.read(|value| value.into_dynamic())
+    }
+
+    /// Get a copy of the value as a specific type, or `None` if it is of a different type.
+    pub fn get_as<T: Any + Clone>(&self) -> Option<T> {
+        self.read(|value| value.downcast_ref::<T>().cloned())
+    }
+
+    /// Replace the value.
+    pub fn set<T: Any>(&self, value: T) {
+        self.set_dynamic(Box::new(value))
+    }
+
+    /// Replace the value with a `Dynamic` value.
+    pub fn set_dynamic(&self, value: Dynamic) {
+        self.write(|v| *v = value)
+    }
+
+    /// Do two `SharedValue`'s refer to the same value?
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        #[cfg(feature = "sync")]
+        return Arc::ptr_eq(&self.0, &other.0);
+        #[cfg(not(feature = "sync"))]
+        return Rc::ptr_eq(&self.0, &other.0);
+    }
+
+    /// Does a value contain this `SharedValue` (or is it this `SharedValue`), directly or via
+    /// arrays, object maps or other shared values?
+    ///
+    /// Storing such a value into this `SharedValue` would make it contain itself, so that it
+    /// would never be freed.
+    pub(crate) fn is_contained_in(&self, value: &Variant) -> bool {
+        let mut pending = vec![value.into_dynamic()];
+        let mut seen = HashSet::new();
+
+        while let Some(value) = pending.pop() {
+            if let Some(shared) = value.downcast_ref::<SharedValue>() {
+                if shared.ptr_eq(self) {
+                    return true;
+                }
+                // A shared value being modified cannot be read, but it cannot be this one either
+                if seen.insert(shared.addr()) {
+                    let _ = shared.try_read(|value| pending.push(value.into_dynamic()));
+                }
+                continue;
+            }
+
+            #[cfg(not(feature = "no_index"))]
+            {
+                if let Some(list) = value.downcast_ref::<Array>() {
+                    pending.extend(list.iter().filter(|v| holds_values(v.as_ref())).cloned());
+                }
+            }
+
+            #[cfg(not(feature = "no_object"))]
+            {
+                if let Some(map) = value.downcast_ref::<Map>() {
+                    pending.extend(map.values().filter(|v| holds_values(v.as_ref())).cloned());
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Take the value out (leaving `()`), unless it is shared with other clones.
+    fn take_unique(&mut self) -> Option<Dynamic> {
+        #[cfg(feature = "sync")]
+        let value = Arc::get_mut(&mut self.0).and_then(|value| value.get_mut().ok());
+        #[cfg(not(feature = "sync"))]
+        let value = Rc::get_mut(&mut self.0).map(RefCell::get_mut);
+
+        value.map(|value| mem::replace(value, Box::new(())))
+    }
+
+    /// Get the address of the value, which is the same for all clones of a `SharedValue`.
+    pub(crate) fn addr(&self) -> usize {
+        #[cfg(feature = "sync")]
+        return Arc::as_ptr(&self.0) as usize;
+        #[cfg(not(feature = "sync"))]
+        return Rc::as_ptr(&self.0) as usize;
+    }
+}
+
+impl Drop for SharedValue {
+    /// Drop the value when this is its last clone, without recursion (see `Shared`).
+    fn drop(&mut self) {
+        if let Some(value) = self.take_unique() {
+            drop_values(vec![value]);
+        }
+    }
+}
+
+/// Is a value an array, object map or shared value, which can hold other values?
+fn holds_values(value: &Variant) -> bool {
+    #[cfg(not(feature = "no_index"))]
+    {
+        if value.is::<Array>() {
+            return true;
+        }
+    }
+
+    #[cfg(not(feature = "no_object"))]
+    {
+        if value.is::<Map>() {
+            return true;
+        }
+    }
+
+    value.is::<SharedValue>()
+}
+
+/// Make an error for a shared value which a thread panicked while modifying.
+#[cfg(feature = "sync")]
+fn poisoned() -> Box<EvalAltResult> {
+    Box::new(EvalAltResult::ErrorRuntime(
+        "Shared value is poisoned by a panic while it was modified".into(),
+        Position::none(),
+    ))
+}
+
+/// Make an error for a shared value which is already being accessed.
+#[cfg(not(feature = "sync"))]
+fn in_use() -> Box<EvalAltResult> {
+    Box::new(EvalAltResult::ErrorRuntime(
+        "Shared value is already in use".into(),
+        Position::none(),
+    ))
+}
+
+impl fmt::Debug for SharedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.try_read(|value| f.debug_tuple("SharedValue").field(&value).finish())
+            .unwrap_or_else(|_| f.write_str("SharedValue(<unavailable>)"))
+    }
+}
@@ -0,0 +1,185 @@
+//! Module which defines a visitor over the nodes of an `AST`, for static analysis of scripts.
+
+use crate::parser::{Expr, FnDef, Nodes, Stmt, AST};
+
+/// A node of an `AST`, passed to an `ASTVisitor`.
+#[derive(Debug, Clone, Copy)]
+pub enum ASTNode<'a> {
+    /// A script-defined function.  Its body is visited as a child node.
+    Fn(&'a FnDef),
+    /// A statement.
+    Stmt(&'a Stmt),
+    /// An expression.
+    Expr(&'a Expr),
+}
+
+/// A visitor over the nodes of an `AST`, used with `AST::walk`.
+///
+/// Nodes are visited depth-first in script order: `enter` is called for a node before any of its
+/// child nodes, and `leave` after all of them.  Keeping track of entered and left nodes allows a
+/// visitor to know the context of each node (e.g. whether it is inside a loop).
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), rhai::ParseError> {
+/// use rhai::{ASTNode, ASTVisitor, Engine, Expr, Nodes, Stmt};
+///
+/// /// Count calls to `spawn_entity` inside loops.
+/// #[derive(Default)]
+/// struct SpawnInLoop {
+///     loops: usize,
+///     found: usize,
+/// }
+///
+/// fn is_loop(node: ASTNode) -> bool {
+///     match node {
+///         ASTNode::Stmt(Stmt::While(..)) | ASTNode::Stmt(Stmt::Loop(..)) => true,
+///         ASTNode::Stmt(Stmt::For(..)) => true,
+///         _ => false,
+///     }
+/// }
+///
+/// impl ASTVisitor for SpawnInLoop {
+///     fn enter(&mut self, node: ASTNode, _nodes: &Nodes) -> bool {
+///         match node {
+///             _ if is_loop(node) => self.loops += 1,
+///             ASTNode::Expr(Expr::FunctionCall(name, _, _, _, _))
+///                 if name == "spawn_entity" && self.loops > 0 => self.found += 1,
+///             _ => (),
+///         }
+///         true
+///     }
+///
+///     fn leave(&mut self, node: ASTNode) {
+///         if is_loop(node) {
+///             self.loops -= 1;
+///         }
+///     }
+/// }
+///
+/// let engine = Engine::new();
+/// let ast = engine.compile("spawn_entity(); loop { spawn_entity(); break; }")?;
+///
+/// let mut lint = SpawnInLoop::default();
+/// ast.walk(&mut lint);
+///
+/// assert_eq!(lint.found, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub trait ASTVisitor {
+    /// Called when entering a node, before its child nodes.
+    /// Child nodes referred to by the node are looked up in `nodes`.
+    /// Return `false` to skip the child nodes.
+    fn enter(&mut self, _node: ASTNode, _nodes: &Nodes) -> bool {
+        true
+    }
+
+    /// Called when leaving a node, after its child nodes (even if they are skipped).
+    fn leave(&mut self, _node: ASTNode) {}
+}
+
+/// Walk all script-defined functions and statements of an `AST`.
+pub(crate) fn walk_ast(ast: &AST, visitor: &mut impl ASTVisitor) {
+    for fn_def in ast.1.iter() {
+        walk_fn(fn_def, visitor);
+    }
+    for stmt in &ast.0 {
+        walk_stmt(&ast.4, stmt, visitor);
+    }
+}
+
+/// Walk a script-defined function and its body.
+pub(crate) fn walk_fn(fn_def: &FnDef, visitor: &mut impl ASTVisitor) {
+    let node = ASTNode::Fn(fn_def);
+
+    if visitor.enter(node, &fn_def.nodes) {
+        walk_stmt(&fn_def.nodes, &fn_def.body, visitor);
+    }
+
+    visitor.leave(node);
+}
+
+/// Walk a statement and all its child nodes, which are in `nodes`.
+pub(crate) fn walk_stmt(nodes: &Nodes, stmt: &Stmt, visitor: &mut impl ASTVisitor) {
+    let node = ASTNode::Stmt(stmt);
+
+    if visitor.enter(node, nodes) {
+        match stmt {
+            Stmt::IfThenElse(guard, if_block, else_block) => {
+                walk_expr(nodes, &nodes[*guard], visitor);
+                walk_stmt(nodes, &nodes[*if_block], visitor);
+                if let Some(else_block) = else_block {
+                    walk_stmt(nodes, &nodes[*else_block], visitor);
+                }
+            }
+            Stmt::While(guard, body, _) => {
+                walk_expr(nodes, &nodes[*guard], visitor);
+                walk_stmt(nodes, &nodes[*body], visitor);
+            }
+            Stmt::Loop(body, _) => walk_stmt(nodes, &nodes[*body], visitor),
+            Stmt::For(_, expr, body, _) => {
+                walk_expr(nodes, &nodes[*expr], visitor);
+                walk_stmt(nodes, &nodes[*body], visitor);
+            }
+            Stmt::Let(_, Some(expr), _)
+            | Stmt::Const(_, expr, _)
+            | Stmt::Expr(expr)
+            | Stmt::ReturnWithVal(Some(expr), _, _) => walk_expr(nodes, &nodes[*expr], visitor),
+            Stmt::Block(statements, _) => {
+                for stmt in &nodes[*statements] {
+                    walk_stmt(nodes, stmt, visitor);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    visitor.leave(node);
+}
+
+/// Walk an expression and all its child nodes, which are in `nodes`.
+pub(crate) fn walk_expr(nodes: &Nodes, expr: &Expr, visitor: &mut impl ASTVisitor) {
+    let node = ASTNode::Expr(expr);
+
+    if visitor.enter(node, nodes) {
+        match expr {
+            Expr::Stmt(stmt, _) => walk_stmt(nodes, &nodes[*stmt], visitor),
+            Expr::FunctionCall(_, args, _, _, _) => {
+                for arg in &nodes[*args] {
+                    walk_expr(nodes, arg, visitor);
+                }
+            }
+            Expr::Assignment(lhs, rhs, _) | Expr::And(lhs, rhs, _) | Expr::Or(lhs, rhs, _) => {
+                walk_expr(nodes, &nodes[*lhs], visitor);
+                walk_expr(nodes, &nodes[*rhs], visitor);
+            }
+            #[cfg(not(feature = "no_object"))]
+            Expr::Dot(lhs, rhs, _) => {
+                walk_expr(nodes, &nodes[*lhs], visitor);
+                walk_expr(nodes, &nodes[*rhs], visitor);
+            }
+            #[cfg(not(feature = "no_index"))]
+            Expr::Index(lhs, rhs, _) => {
+                walk_expr(nodes, &nodes[*lhs], visitor);
+                walk_expr(nodes, &nodes[*rhs], visitor);
+            }
+            #[cfg(not(feature = "no_index"))]
+            Expr::Array(items, _) => {
+                for item in &nodes[*items] {
+                    walk_expr(nodes, item, visitor);
+                }
+            }
+            #[cfg(not(feature = "no_object"))]
+            Expr::Map(items, _) => {
+                for (_, item, _) in items {
+                    walk_expr(nodes, &nodes[*item], visitor);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    visitor.leave(node);
+}
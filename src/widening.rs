@@ -0,0 +1,119 @@
+//! Module implementing automatic integer widening during function resolution, i.e. calling a
+//! function registered over one integer type with an argument of another integer type.
+
+use crate::any::{Any, Dynamic, Variant};
+use crate::engine::FnSpec;
+
+use crate::stdlib::{any::TypeId, convert::TryFrom, vec::Vec};
+
+/// Information on an integer type: its `TypeId`, range of values and a conversion into it.
+struct IntType {
+    type_id: TypeId,
+    min: i128,
+    max: i128,
+    convert: fn(i128) -> Option<Dynamic>,
+}
+
+/// Get information on all supported integer types, preferred ones (wider, then signed) first.
+fn int_types() -> [IntType; 8] {
+    macro_rules! int_type {
+        ($t:ty) => {
+            IntType {
+                type_id: TypeId::of::<$t>(),
+                min: <$t>::MIN as i128,
+                max: <$t>::MAX as i128,
+                convert: |x| <$t>::try_from(x).ok().map(|x| x.into_dynamic()),
+            }
+        };
+    }
+
+    [
+        int_type!(i64),
+        int_type!(u64),
+        int_type!(i32),
+        int_type!(u32),
+        int_type!(i16),
+        int_type!(u16),
+        int_type!(i8),
+        int_type!(u8),
+    ]
+}
+
+/// Get the value of an integer of any supported type, or `None` if the value is not an integer.
+fn int_value(value: &Variant) -> Option<i128> {
+    macro_rules! int_value {
+        ($($t:ty),*) => {
+            $(
+                if let Some(&x) = value.downcast_ref::<$t>() {
+                    return Some(x as i128);
+                }
+            )*
+        };
+    }
+
+    int_value!(i64, u64, i32, u32, i16, u16, i8, u8);
+    None
+}
+
+/// Find the function, among `specs`, which can be called with `args` once integer arguments are
+/// converted (without loss) to other integer types, and return the converted arguments.
+///
+/// When several functions match, prefer the one needing the fewest conversions that may lose
+/// data for other values (e.g. `i64` to `u8`), then the fewest conversions overall, then the
+/// widest parameter types.  Returns `None` if no function matches.
+pub(crate) fn widen_int_args<'a>(
+    specs: impl Iterator<Item = &'a FnSpec<'a>>,
+    fn_name: &str,
+    args: &[&mut Variant],
+) -> Option<Vec<Dynamic>> {
+    let values: Vec<_> = args.iter().map(|arg| int_value(&**arg)).collect();
+
+    if values.iter().all(Option::is_none) {
+        return None;
+    }
+
+    let types = int_types();
+    let find_type = |type_id: TypeId| types.iter().position(|t| t.type_id == type_id);
+
+    specs
+        .filter(|spec| spec.name == fn_name && spec.args.len() == args.len())
+        .filter_map(|spec| {
+            let (mut narrowing, mut conversions) = (0, 0);
+            let mut ranks = Vec::with_capacity(args.len());
+
+            for ((arg, value), &type_id) in args.iter().zip(&values).zip(spec.args.iter()) {
+                let from = Any::type_id(&**arg);
+                let rank = find_type(type_id);
+
+                if from != type_id {
+                    let (x, from, to) = (value.as_ref()?, find_type(from)?, &types[rank?]);
+
+                    if *x < to.min || *x > to.max {
+                        return None;
+                    }
+                    if types[from].min < to.min || types[from].max > to.max {
+                        narrowing += 1;
+                    }
+                    conversions += 1;
+                }
+
+                ranks.push(rank.unwrap_or(0));
+            }
+
+            Some(((narrowing, conversions, ranks), spec))
+        })
+        .min_by(|(x, _), (y, _)| x.cmp(y))
+        .map(|(_, spec)| {
+            args.iter()
+                .zip(&values)
+                .zip(spec.args.iter())
+                .map(|((arg, value), &type_id)| match value {
+                    Some(x) if Any::type_id(&**arg) != type_id => {
+                        let to = &types[find_type(type_id).unwrap()];
+                        (to.convert)(*x).unwrap()
+                    }
+                    _ => (**arg).into_dynamic(),
+                })
+                .collect()
+        })
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "array_math")]
+#![cfg(not(feature = "no_index"))]
+#![cfg(not(feature = "no_float"))]
+use rhai::{AnyExt, Array, Engine, EvalAltResult, FLOAT, INT};
+
+fn to_floats(array: Array) -> Vec<FLOAT> {
+    array.into_iter().map(|x| x.cast::<FLOAT>()).collect()
+}
+
+#[test]
+fn test_array_math_broadcast() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        to_floats(engine.eval::<Array>("[1.0, 2.0, 3] + 0.5")?),
+        vec![1.5, 2.5, 3.5]
+    );
+    assert_eq!(
+        to_floats(engine.eval::<Array>("[1.0, 2.0, 3.0] * 2")?),
+        vec![2.0, 4.0, 6.0]
+    );
+    assert_eq!(
+        to_floats(engine.eval::<Array>("10.0 - [1.0, 2.0]")?),
+        vec![9.0, 8.0]
+    );
+    assert_eq!(
+        to_floats(engine.eval::<Array>("1 / [2.0, 4.0]")?),
+        vec![0.5, 0.25]
+    );
+    assert_eq!(
+        to_floats(engine.eval::<Array>("[1.0, 2.0, 3.0] * [4.0, 5.0, 6]")?),
+        vec![4.0, 10.0, 18.0]
+    );
+    assert_eq!(
+        to_floats(engine.eval::<Array>("[4.0, 5.0] - [1.0, 2.0]")?),
+        vec![3.0, 3.0]
+    );
+
+    // '+' between arrays still concatenates
+    assert_eq!(engine.eval::<INT>("([1.0] + [2.0]).len()")?, 2);
+
+    assert!(matches!(
+        *engine
+            .eval::<Array>("[1.0, 2.0] * [1.0]")
+            .expect_err("should error"),
+        EvalAltResult::ErrorArithmetic(_, _, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<Array>(r#"[1.0, "x"] * 2.0"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorArithmetic(_, _, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_array_math_stats() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(engine.eval::<FLOAT>("[1.0, 2.0, 3.5].sum()")?, 6.5);
+    assert_eq!(engine.eval::<FLOAT>("sum([])")?, 0.0);
+    assert_eq!(engine.eval::<FLOAT>("[1.0, 2, 6.0].mean()")?, 3.0);
+    assert_eq!(engine.eval::<FLOAT>("[3.0, -1.5, 2.0].min()")?, -1.5);
+    assert_eq!(engine.eval::<FLOAT>("[3.0, -1.5, 2.0].max()")?, 3.0);
+    assert_eq!(
+        engine.eval::<FLOAT>("dot([1.0, 2.0, 3.0], [4.0, 5.0, 6.0])")?,
+        32.0
+    );
+    assert_eq!(
+        engine.eval::<FLOAT>("let x = [3.0, 4.0]; (x * x).sum()")?,
+        25.0
+    );
+
+    assert!(engine.eval::<FLOAT>("[].mean()").is_err());
+    assert!(engine.eval::<FLOAT>("[].max()").is_err());
+    assert!(engine.eval::<FLOAT>("dot([1.0], [])").is_err());
+
+    Ok(())
+}
@@ -1,8 +1,9 @@
 #![cfg(not(feature = "no_index"))]
-use rhai::{Array, Engine, EvalAltResult, RegisterFn, INT};
+use rhai::{AnyExt, Array, Engine, EvalAltResult, RegisterFn, INT};
+use std::any::type_name;
 
 #[test]
-fn test_arrays() -> Result<(), EvalAltResult> {
+fn test_arrays() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("let x = [1, 2, 3]; x[1]")?, 2);
@@ -52,9 +53,89 @@ fn test_arrays() -> Result<(), EvalAltResult> {
     Ok(())
 }
 
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_array_move_ops() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Arguments are moved into the array, but variables passed in stay intact
+    assert_eq!(
+        engine.eval::<INT>(
+            r"
+                let x = [1, 2, 3];
+                let y = [4, 5];
+                x.append(y);
+                x.push(y);
+                x += y;
+                let z = x + y;
+                x.len() * 100 + y.len() * 10 + z.len()
+            "
+        )?,
+        830
+    );
+
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(
+        engine.eval::<INT>(
+            r"
+                let x = [];
+                let m = #{ a: 42 };
+                x.push(m);
+                x.push(#{ a: 1 });
+                x.insert(1, m);
+                x[0].a + x[1].a + x[2].a + m.a
+            "
+        )?,
+        127
+    );
+
+    assert!(engine.eval::<bool>(
+        r#"
+            let x = [1, 2, 3];
+            x.insert(0, "a");
+            x.insert(2, 'b');
+            x.insert(-1, true);
+            x.insert(100, ());
+            x.len() == 7 && x[0] && x[1] == "a" && x[2] == 1 && x[3] == 'b' && type_of(x[6]) == "()"
+        "#
+    )?);
+
+    assert_eq!(
+        engine.eval::<INT>("let x = [1, 2, 3]; let y = x.take(); x.len() * 10 + y.len()")?,
+        3
+    );
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r"
+                let x = [1, 2, 3, 4, 5];
+                let y = x.drain(1, 2);
+                let z = x.drain(1);
+                x.len() * 100 + y[0] * 10 + y[1] + z.len()
+            "
+        )?,
+        125
+    );
+
+    assert_eq!(
+        engine.eval::<INT>(
+            "let x = [1, 2, 3]; let a = x.drain(5, 2); let b = x.drain(1, -1); a.len() + b.len()"
+        )?,
+        0
+    );
+
+    assert_eq!(
+        engine.eval::<INT>("let x = [1, 2, 3]; let y = x.shift(); y * 10 + x.len()")?,
+        12
+    );
+    assert_eq!(engine.eval::<()>("let x = []; x.shift()")?, ());
+
+    Ok(())
+}
+
 #[test]
 #[cfg(not(feature = "no_object"))]
-fn test_array_with_structs() -> Result<(), EvalAltResult> {
+fn test_array_with_structs() -> Result<(), Box<EvalAltResult>> {
     #[derive(Clone)]
     struct TestStruct {
         x: INT,
@@ -102,3 +183,441 @@ fn test_array_with_structs() -> Result<(), EvalAltResult> {
 
     Ok(())
 }
+
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_array_sort() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let to_ints = |a: Array| -> Vec<INT> { a.into_iter().map(|v| v.cast::<INT>()).collect() };
+
+    assert_eq!(
+        to_ints(engine.eval::<Array>("let x = [3, 1, 2, 1]; x.sort(); x")?),
+        vec![1, 1, 2, 3]
+    );
+    assert_eq!(
+        engine
+            .eval::<Array>(r#"let x = ["b", "c", "a"]; x.sort(); x"#)?
+            .into_iter()
+            .map(|v| v.cast::<String>())
+            .collect::<Vec<_>>(),
+        vec!["a", "b", "c"]
+    );
+    assert_eq!(
+        to_ints(engine.eval::<Array>("let x = [1, 1, 2, 2, 1, 3]; x.dedup(); x")?),
+        vec![1, 2, 1, 3]
+    );
+    assert_eq!(
+        to_ints(engine.eval::<Array>("let x = [1, 2, 3]; x.reverse(); x")?),
+        vec![3, 2, 1]
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<()>(r#"let x = [1, "a"]; x.sort()"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorRuntime(msg, _) if msg.contains("'string'")
+    ));
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        assert_eq!(
+            to_ints(engine.eval::<Array>(
+                r#"
+                    fn descending(a, b) { b - a }
+                    let x = [3, 1, 4, 1, 5];
+                    x.sort("descending");
+                    x
+                "#
+            )?),
+            vec![5, 4, 3, 1, 1]
+        );
+
+        assert!(matches!(
+            *engine
+                .eval::<()>(r#"fn bad(a, b) { "oops" } let x = [1, 2]; x.sort("bad")"#)
+                .expect_err("should error"),
+            EvalAltResult::ErrorMismatchOutputType(_, t, _) if t == "string"
+        ));
+    }
+
+    engine.register_fn("by_abs", |a: INT, b: INT| a.abs() - b.abs());
+
+    assert_eq!(
+        to_ints(engine.eval::<Array>(r#"let x = [-3, 1, -2]; x.sort("by_abs"); x"#)?),
+        vec![1, -2, -3]
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_array_search() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!(engine.eval::<bool>("let x = [1, 2, 3]; x.contains(2)")?);
+    assert!(!engine.eval::<bool>(r#"let x = [1, 2, 3]; x.contains("2")"#)?);
+    assert!(engine.eval::<bool>(r#"contains([[1], "a", ()], [1])"#)?);
+    assert!(engine.eval::<bool>(r#"contains([[1], "a", ()], ())"#)?);
+
+    assert_eq!(engine.eval::<INT>("[1, 3, 5, 7].binary_search(5)")?, 2);
+    assert_eq!(engine.eval::<INT>("[1, 3, 5, 7].binary_search(4)")?, -3);
+    assert_eq!(engine.eval::<INT>("[1, 3, 5, 7].binary_search(0)")?, -1);
+    assert_eq!(engine.eval::<INT>(r#"["a", "c"].binary_search("d")"#)?, -3);
+    assert_eq!(engine.eval::<INT>("[].binary_search(1)")?, -1);
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"[1, 2].binary_search("a")"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorRuntime(msg, _)
+            if msg == format!("Cannot search an array containing '{}' for 'string'", type_name::<INT>())
+    ));
+
+    engine.register_fn("by_abs", |a: INT, b: INT| a.abs() - b.abs());
+
+    assert_eq!(
+        engine.eval::<INT>(r#"[1, -2, 3, -4].binary_search(-3, "by_abs")"#)?,
+        2
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"[1, -2, 3, -4].binary_search(5, "by_abs")"#)?,
+        -5
+    );
+
+    #[cfg(not(feature = "no_function"))]
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn by_name(item, name) { cmp(item.name, name) }
+                let people = [#{ name: "Ann" }, #{ name: "Bob" }, #{ name: "Cid" }];
+                people.binary_search("Bob", "by_name")
+            "#
+        )?,
+        1
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_array_set_ops() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let to_ints = |a: Array| -> Vec<INT> { a.into_iter().map(|v| v.cast::<INT>()).collect() };
+
+    assert_eq!(
+        to_ints(engine.eval::<Array>("union([3, 1, 3], [2, 1, 4])")?),
+        vec![3, 1, 2, 4]
+    );
+    assert_eq!(
+        to_ints(engine.eval::<Array>("[5, 1, 2, 1, 3].intersection([3, 1, 9])")?),
+        vec![1, 3]
+    );
+    assert_eq!(
+        to_ints(engine.eval::<Array>("[5, 1, 2, 1, 3].difference([3, 9])")?),
+        vec![5, 1, 2]
+    );
+    assert_eq!(
+        engine
+            .eval::<Array>(r#"union(["a", [1, 2]], [[1, 2], "b", "a"])"#)?
+            .len(),
+        3
+    );
+
+    #[cfg(not(feature = "no_float"))]
+    assert_eq!(
+        engine
+            .eval::<Array>("union([0.0, 1.5], [-0.0, 1.5])")?
+            .len(),
+        2
+    );
+
+    // Floats nested in arrays and object maps are compared by value too
+    #[cfg(not(feature = "no_float"))]
+    {
+        let script = "let a = [[0.0]]; let b = [[-0.0]];";
+
+        assert!(engine.eval::<bool>(&format!("{} a[0] == b[0]", script))?);
+        assert!(engine.eval::<bool>(&format!("{} a.contains(b[0])", script))?);
+        assert_eq!(
+            engine.eval::<INT>(&format!("{} a.intersection(b).len()", script))?,
+            1
+        );
+        assert_eq!(
+            engine.eval::<INT>(&format!("{} a.union(b).len()", script))?,
+            1
+        );
+        assert_eq!(
+            engine.eval::<INT>(&format!("{} a.difference(b).len()", script))?,
+            0
+        );
+
+        #[cfg(not(feature = "no_object"))]
+        assert_eq!(
+            engine
+                .eval::<Array>("union([#{x: 0.0}], [#{x: -0.0}, #{x: 1.0}])")?
+                .len(),
+            2
+        );
+    }
+
+    #[derive(Clone)]
+    struct Item;
+
+    engine.register_fn("item", || Item);
+
+    // Values of custom types are never equal to each other, as for `dedup`
+    assert_eq!(
+        engine
+            .eval::<Array>("union([item(), 2], [item(), 2])")?
+            .len(),
+        3
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_array_chunks() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let to_nested = |a: Array| -> Vec<Vec<INT>> {
+        a.into_iter()
+            .map(|v| {
+                v.cast::<Array>()
+                    .into_iter()
+                    .map(|v| v.cast::<INT>())
+                    .collect()
+            })
+            .collect()
+    };
+
+    assert_eq!(
+        to_nested(engine.eval::<Array>("[1, 2, 3, 4, 5].chunks(2)")?),
+        vec![vec![1, 2], vec![3, 4], vec![5]]
+    );
+    assert_eq!(
+        to_nested(engine.eval::<Array>("[1, 2, 3, 4].windows(3)")?),
+        vec![vec![1, 2, 3], vec![2, 3, 4]]
+    );
+    assert!(engine.eval::<Array>("[1, 2].windows(3)")?.is_empty());
+    assert!(engine.eval::<Array>("[].chunks(3)")?.is_empty());
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let total = 0;
+                for batch in [1, 2, 3, 4, 5, 6, 7].chunks(3) {
+                    total = total * 10 + batch.len();
+                }
+                total
+            "#
+        )?,
+        331
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<Array>("[1, 2].chunks(0)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<Array>("[1, 2].windows(-1)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+#[cfg(feature = "rand")]
+fn test_array_shuffle() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let mut result: Vec<INT> = engine
+        .eval::<Array>("let x = [1, 2, 3, 4, 5]; x.shuffle(); x")?
+        .into_iter()
+        .map(|v| v.cast::<INT>())
+        .collect();
+
+    result.sort();
+    assert_eq!(result, vec![1, 2, 3, 4, 5]);
+
+    Ok(())
+}
+
+#[test]
+fn test_array_equality() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!(engine.eval::<bool>("[1, [2, 3], \"x\"] == [1, [2, 3], \"x\"]")?);
+    assert!(engine.eval::<bool>("[1, [2, 3]] != [1, [2, 4]]")?);
+    assert!(engine.eval::<bool>("[1, 2] != [1, 2, 3]")?);
+    assert!(!engine.eval::<bool>("[1, 2] == [1, \"2\"]")?);
+    assert!(engine.eval::<bool>("let x = [1, [true, ()]]; let y = x; y == x")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_array_copy_on_write() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>("let x = [1, 2, 3]; let y = x; y[0] = 42; x[0]")?,
+        1
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = [1, [2, 3]]; let y = x[1]; y[0] = 42; x[1][0]")?,
+        2
+    );
+    #[cfg(not(feature = "no_stdlib"))]
+    assert_eq!(
+        engine.eval::<INT>("let x = [1, 2, 3]; let y = x; x.push(4); y.len()")?,
+        3
+    );
+    #[cfg(not(feature = "no_function"))]
+    assert_eq!(
+        engine.eval::<INT>("fn f(a) { a[0] = 42; a } let x = [1, 2]; f(x); x[0]")?,
+        1
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_array_deeply_nested() {
+    // Run on a thread with a small stack, which would crash if dropping the arrays recurses
+    std::thread::Builder::new()
+        .stack_size(256 * 1024)
+        .spawn(|| {
+            let mut engine = Engine::new();
+
+            let a = engine
+                .eval::<Array>("let a = []; for i in range(0, 100000) { a = [a]; } a")
+                .expect("should succeed");
+            assert_eq!(a.len(), 1);
+
+            #[cfg(not(feature = "no_object"))]
+            assert_eq!(
+                engine
+                    .eval::<INT>("let m = #{}; for i in range(0, 100000) { m = #{ x: [m] }; } 42")
+                    .expect("should succeed"),
+                42
+            );
+
+            // Neither do shared values holding each other
+            assert_eq!(
+                engine
+                    .eval::<INT>(
+                        "let s = shared(0); for i in range(0, 100000) { s = shared([s]); } 42"
+                    )
+                    .expect("should succeed"),
+                42
+            );
+            assert_eq!(
+                engine
+                    .eval::<INT>(
+                        "let s = shared(0); for i in range(0, 100000) { s = shared(s); } 42"
+                    )
+                    .expect("should succeed"),
+                42
+            );
+
+            // Comparing deeply nested arrays does not recurse either
+            let mut nested = |bottom_a: &str, bottom_b: &str, test: &str| {
+                let script = format!(
+                    "let a = {}; let b = {}; for i in range(0, 100000) {{ a = [a]; b = [b]; }} {}",
+                    bottom_a, bottom_b, test
+                );
+                engine.eval::<bool>(&script).expect("should succeed")
+            };
+
+            assert!(nested("[1]", "[1]", "a == b"));
+            assert!(nested("[1]", "[2]", "a != b"));
+            assert!(nested("[1]", "[[1]]", "a != b"));
+
+            // Nor do converting them to JSON and using them as set elements
+            #[cfg(not(feature = "no_stdlib"))]
+            {
+                assert!(nested("[]", "[]", "a.to_json().len() == 200002"));
+                assert!(nested(
+                    "[1]",
+                    "[1]",
+                    "[a].contains(b) && [a].union([b]).len() == 1"
+                ));
+                assert!(nested("[1]", "[2]", "[a].intersection([b]).len() == 0"));
+            }
+        })
+        .expect("thread should spawn")
+        .join()
+        .expect("thread should not crash");
+}
+
+#[test]
+fn test_array_nested_assignment() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>("let x = [[1, 2], [3, 4]]; x[1][0] = 42; x[1][0]")?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = [[1, 2], [3, [4, 5]]]; x[1][1][0] *= 10; x[1][1][0]")?,
+        40
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let x = [1, "abc"]; x[1][0] = 'x'; x[1]"#)?,
+        "xbc"
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = [1, 0]; x[1][3] = true; x[1]")?,
+        8
+    );
+
+    // Other copies of the value are not affected
+    assert_eq!(
+        engine.eval::<INT>("let x = [[1, 2]]; let y = x; y[0][0] = 42; x[0][0]")?,
+        1
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<()>("const x = [[1, 2]]; x[0][0] = 42;")
+            .expect_err("should error"),
+        EvalAltResult::ErrorAssignmentToConstant(name, _) if name == "x"
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<()>("let x = [[1, 2]]; x[0][5] = 42;")
+            .expect_err("should error"),
+        EvalAltResult::ErrorArrayBounds(2, 5, _)
+    ));
+    assert!(engine.compile("[[1, 2]][0][0] = 42;").is_err());
+
+    #[cfg(not(feature = "no_object"))]
+    {
+        assert_eq!(
+            engine.eval::<INT>("let x = #{a: [[1, 2], [3, 4]]}; x.a[1][1] += 38; x.a[1][1]")?,
+            42
+        );
+        assert_eq!(
+            engine.eval::<INT>("let x = [[#{a: 1}]]; x[0][0].a = 42; x[0][0].a")?,
+            42
+        );
+        assert_eq!(
+            engine.eval::<INT>("let x = #{a: [#{b: [1, 2]}]}; x.a[0].b[1] -= 2; x.a[0].b[1]")?,
+            0
+        );
+    }
+
+    Ok(())
+}
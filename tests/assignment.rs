@@ -0,0 +1,86 @@
+use rhai::{Engine, EvalAltResult, ParseErrorType, RegisterFn, INT};
+
+fn parse_error(engine: &Engine, script: &str) -> (ParseErrorType, usize) {
+    let err = engine.compile(script).expect_err("should error");
+    (err.error_type().clone(), err.position().position().unwrap())
+}
+
+#[test]
+fn test_assignment_to_temporary() {
+    let mut engine = Engine::new();
+
+    engine.register_fn("answer", || 42 as INT);
+
+    assert_eq!(
+        parse_error(&engine, "42 = 1;"),
+        (ParseErrorType::AssignmentToInvalidLHS, 1)
+    );
+    assert_eq!(
+        parse_error(&engine, "let x = 1; x + 1 = 1;"),
+        (ParseErrorType::AssignmentToInvalidLHS, 14)
+    );
+
+    #[cfg(not(feature = "no_index"))]
+    {
+        assert_eq!(
+            parse_error(&engine, "answer()[0] = 1;"),
+            (ParseErrorType::AssignmentToCopy, 1)
+        );
+        assert_eq!(
+            parse_error(&engine, "let x = 0; x = [1, 2][0] = 3;"),
+            (ParseErrorType::AssignmentToCopy, 16)
+        );
+        assert_eq!(
+            parse_error(&engine, r#"  "abc"[0][1] = 'x';"#),
+            (ParseErrorType::AssignmentToCopy, 3)
+        );
+    }
+
+    #[cfg(not(feature = "no_object"))]
+    {
+        assert_eq!(
+            parse_error(&engine, "answer().x = 1;"),
+            (ParseErrorType::AssignmentToCopy, 1)
+        );
+        assert_eq!(
+            parse_error(&engine, "#{ a: 1 }.a = 2;"),
+            (ParseErrorType::AssignmentToCopy, 1)
+        );
+        assert_eq!(
+            parse_error(&engine, "let x = #{}; x.keys().len = 2;"),
+            (ParseErrorType::AssignmentToCopy, 16)
+        );
+    }
+
+    #[cfg(not(feature = "no_index"))]
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(
+        parse_error(&engine, "let x = #{}; x.a.values()[0] = 2;"),
+        (ParseErrorType::AssignmentToCopy, 18)
+    );
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_object"))]
+fn test_assignment_into_global() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let items = engine.eval::<rhai::Array>("[#{ a: 1 }]")?;
+    engine.register_global_constant("ITEMS", items);
+
+    // Assigning into a read-only variable fails instead of changing a copy
+    assert!(matches!(
+        *engine.eval::<()>("ITEMS[0].a = 42;").expect_err("should error"),
+        EvalAltResult::ErrorAssignmentToConstant(name, _) if name == "ITEMS"
+    ));
+    assert_eq!(engine.eval::<INT>("ITEMS[0].a")?, 1);
+
+    // ... unless it is shadowed by a variable
+    assert_eq!(
+        engine.eval::<INT>("let ITEMS = [#{ a: 1 }]; ITEMS[0].a = 42; ITEMS[0].a")?,
+        42
+    );
+
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use rhai::{Engine, EvalAltResult, INT};
 
 #[test]
-fn test_binary_ops() -> Result<(), EvalAltResult> {
+fn test_binary_ops() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("10 % 4")?, 2);
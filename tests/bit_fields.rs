@@ -0,0 +1,88 @@
+#![cfg(not(feature = "no_index"))]
+use rhai::{Engine, EvalAltResult, INT};
+
+#[test]
+fn test_bit_fields() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!(engine.eval::<bool>("let x = 0b1010; x[1]")?);
+    assert!(!engine.eval::<bool>("let x = 0b1010; x[2]")?);
+    assert_eq!(
+        engine.eval::<INT>("let x = 0b1010; x[0] = true; x")?,
+        0b1011
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = 0b1010; x[3] = false; x")?,
+        0b0010
+    );
+    assert!(engine.eval::<bool>("let x = -1; x[31]")?);
+
+    assert!(matches!(
+        *engine
+            .eval::<bool>("let x = 42; let i = -1; x[i]")
+            .expect_err("should error"),
+        EvalAltResult::ErrorBitFieldBounds(_, -1, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<bool>("let x = 42; x[100]")
+            .expect_err("should error"),
+        EvalAltResult::ErrorBitFieldBounds(_, 100, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>("let x = 42; x[0] = 1; x")
+            .expect_err("should error"),
+        EvalAltResult::ErrorBooleanArgMismatch(_, _)
+    ));
+
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(
+        engine.eval::<INT>("let x = #{flags: 0}; x.flags[2] = true; x.flags")?,
+        4
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_bit_field_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(engine.eval::<INT>("get_bits(0b110110, 1, 3)")?, 0b011);
+    assert_eq!(engine.eval::<INT>("get_bits(0b110110, 4, 100)")?, 0b11);
+    assert_eq!(engine.eval::<INT>("get_bits(-1, 0, 100)")?, -1);
+    assert_eq!(
+        engine.eval::<INT>("let x = 0b110110; x.set_bits(1, 3, 0b101); x")?,
+        0b111010
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = 0; x.set_bits(2, 2, 0xff); x")?,
+        0b1100
+    );
+
+    // All bits but the sign bit
+    let len = std::mem::size_of::<INT>() * 8 - 1;
+    assert_eq!(
+        engine.eval::<INT>(&format!("get_bits(-1, 0, {})", len))?,
+        INT::MAX
+    );
+    assert_eq!(
+        engine.eval::<INT>(&format!("let x = 0; x.set_bits(0, {}, -1); x", len))?,
+        INT::MAX
+    );
+    assert_eq!(
+        engine.eval::<INT>(&format!("let x = -1; x.set_bits(1, {}, 0); x", len))?,
+        1
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>("get_bits(42, 200, 1)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorBitFieldBounds(_, 200, _)
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,227 @@
+#![cfg(not(feature = "no_index"))]
+#![cfg(not(feature = "no_stdlib"))]
+use rhai::{Blob, Engine, EvalAltResult, INT};
+
+#[test]
+fn test_blobs() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(engine.eval::<INT>("let x = blob(3, 7); x[1]")?, 7);
+    assert_eq!(
+        engine.eval::<INT>("let x = blob(3); x[1] = 0xff; x[1]")?,
+        0xff
+    );
+    assert_eq!(
+        engine.eval::<Blob>("let x = blob(2); x[0] = 42; x")?,
+        vec![42, 0]
+    );
+    assert_eq!(engine.eval::<String>("type_of(blob())")?, "blob");
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>("let x = blob(3); x[3]")
+            .expect_err("should error"),
+        EvalAltResult::ErrorArrayBounds(3, 3, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>("let x = blob(3); x[0] = 'a'; 0")
+            .expect_err("should error"),
+        EvalAltResult::ErrorByteMismatch(_)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>("let x = blob(3); x[1] = 0x1ff; 0")
+            .expect_err("should error"),
+        EvalAltResult::ErrorByteMismatch(_)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>("let x = blob(3); x[1] = -1; 0")
+            .expect_err("should error"),
+        EvalAltResult::ErrorByteMismatch(_)
+    ));
+
+    assert_eq!(
+        engine.eval::<INT>("let x = blob(4, 1); let s = 0; for b in x { s += b; } s")?,
+        4
+    );
+
+    assert!(engine.eval::<bool>("blob(2, 1) == blob(2, 1)")?);
+    assert!(engine.eval::<bool>("blob(2, 1) != blob(3, 1)")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_blob_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<Blob>("let x = blob(); x.push(1); x.push(255); x.append(blob(1, 3)); x")?,
+        vec![1, 255, 3]
+    );
+    assert_eq!(
+        engine.eval::<Blob>("blob(1, 1) + blob(2, 2)")?,
+        vec![1, 2, 2]
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = blob(5); x.truncate(2); x.len()")?,
+        2
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = blob(2); x.pad(4, 9); x.pop()")?,
+        9
+    );
+
+    assert_eq!(
+        engine.eval::<Blob>("let x = [1, 2, 3, 4, 5].to_blob(); x.extract(1, 3)")?,
+        vec![2, 3, 4]
+    );
+    assert_eq!(
+        engine.eval::<Blob>("let x = [1, 2, 3].to_blob(); x.extract(2)")?,
+        vec![3]
+    );
+    assert_eq!(
+        engine.eval::<Blob>("let x = [1, 2, 3].to_blob(); x.extract(5, 2)")?,
+        vec![]
+    );
+
+    assert_eq!(
+        engine.eval::<String>(r#"let x = "héllo".to_blob(); x.as_string()"#)?,
+        "héllo"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let x = "hi!".to_blob(); x.to_hex()"#)?,
+        "686921"
+    );
+    assert_eq!(
+        engine.eval::<Blob>(r#""0aFF".parse_hex()"#)?,
+        vec![0x0a, 0xff]
+    );
+    assert_eq!(engine.eval::<String>(r#""hi!".to_hex()"#)?, "686921");
+    assert_eq!(
+        engine.eval::<String>(r#"let x = from_hex("686921"); x.as_string()"#)?,
+        "hi!"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let x = from_base64(to_base64("héllo")); x.as_string()"#)?,
+        "héllo"
+    );
+    assert_eq!(
+        engine.eval::<String>("let x = blob(2, 32); x.push(255); x.url_encode()")?,
+        "%20%20%FF"
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = [1, 2, 3].to_blob(); let y = x.to_array(); y.len()")?,
+        3
+    );
+
+    for (text, base64) in &[
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ] {
+        assert_eq!(
+            engine.eval::<String>(&format!(r#"let x = "{}".to_blob(); x.to_base64()"#, text))?,
+            *base64
+        );
+        assert_eq!(
+            engine.eval::<String>(&format!(
+                r#"let x = "{}".parse_base64(); x.as_string()"#,
+                base64
+            ))?,
+            *text
+        );
+    }
+
+    assert!(matches!(
+        *engine
+            .eval::<Blob>(r#""abc".parse_hex()"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<Blob>(r#""Zm9v!".parse_base64()"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<Blob>(r#"[1, "x"].to_blob()"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+
+    // Bytes outside 0..=255 are errors, not truncated
+    for script in &[
+        "[1, 256].to_blob()",
+        "let x = blob(); x.push(-1); x",
+        "blob(2, 300)",
+        "let x = blob(); x.pad(2, 256); x",
+    ] {
+        assert!(matches!(
+            *engine.eval::<Blob>(script).expect_err("should error"),
+            EvalAltResult::ErrorRuntime(msg, _) if msg.contains("not between 0 and 255")
+        ));
+    }
+
+    // BLOB lengths are limited
+    for script in &[
+        "blob(1_000_000_000)",
+        "let x = blob(); x.pad(1_000_000_000, 0); x",
+    ] {
+        assert!(matches!(
+            *engine.eval::<Blob>(script).expect_err("should error"),
+            EvalAltResult::ErrorRuntime(msg, _) if msg.contains("maximum")
+        ));
+    }
+
+    assert_eq!(engine.eval::<String>("blob(2, 1).to_json()")?, "[1,1]");
+
+    Ok(())
+}
+
+#[test]
+fn test_blob_max_size() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_blob_size(4);
+
+    assert_eq!(
+        engine.eval::<Blob>("let x = blob(2); x += blob(2); x")?,
+        vec![0; 4]
+    );
+
+    for script in &[
+        "blob(5)",
+        "blob(5, 1)",
+        "let x = blob(4); x.push(1); x",
+        "let x = blob(3); x.append(blob(2)); x",
+        "blob(3) + blob(2)",
+        "let x = blob(3); x += blob(2); x",
+        "let x = blob(); x.pad(5, 0); x",
+        "\"hello\".to_blob()",
+        "[1, 2, 3, 4, 5].to_blob()",
+        "parse_hex(\"0102030405\")",
+        "parse_base64(\"AQIDBAUG\")",
+    ] {
+        assert!(matches!(
+            *engine.eval::<Blob>(script).expect_err("should error"),
+            EvalAltResult::ErrorRuntime(msg, _) if msg.contains("maximum of 4 bytes")
+        ));
+    }
+
+    // Duplicates keep the limit, and changing it does not affect the original
+    let mut engine2 = engine.duplicate();
+    assert!(engine2.eval::<Blob>("blob(5)").is_err());
+    engine2.set_max_blob_size(8);
+    assert_eq!(engine2.eval::<Blob>("blob(5)")?.len(), 5);
+    assert!(engine.eval::<Blob>("blob(5)").is_err());
+
+    Ok(())
+}
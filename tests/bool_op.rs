@@ -1,7 +1,7 @@
-use rhai::{Engine, EvalAltResult};
+use rhai::{Engine, EvalAltResult, Position};
 
 #[test]
-fn test_bool_op1() -> Result<(), EvalAltResult> {
+fn test_bool_op1() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<bool>("true && (false || true)")?, true);
@@ -11,7 +11,7 @@ fn test_bool_op1() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_bool_op2() -> Result<(), EvalAltResult> {
+fn test_bool_op2() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<bool>("false && (false || true)")?, false);
@@ -21,7 +21,7 @@ fn test_bool_op2() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_bool_op3() -> Result<(), EvalAltResult> {
+fn test_bool_op3() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert!(engine.eval::<bool>("true && (false || 123)").is_err());
@@ -33,7 +33,7 @@ fn test_bool_op3() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_bool_op_short_circuit() -> Result<(), EvalAltResult> {
+fn test_bool_op_short_circuit() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(
@@ -90,3 +90,22 @@ fn test_bool_op_no_short_circuit2() {
         )
         .is_err());
 }
+
+#[test]
+fn test_bool_op_positions() {
+    let mut engine = Engine::new();
+
+    let err = engine
+        .eval::<bool>("let x = true; let y = 42; x && y")
+        .expect_err("expects error");
+    assert!(matches!(*err, EvalAltResult::ErrorBooleanArgMismatch(_, _)));
+    assert_eq!(err.position(), Position::new(1, 32));
+
+    #[cfg(not(feature = "no_index"))]
+    {
+        let err = engine
+            .compile("let a = [1]; a[true || false]")
+            .expect_err("expects error");
+        assert_eq!(err.position(), Position::new(1, 21));
+    }
+}
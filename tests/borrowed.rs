@@ -0,0 +1,92 @@
+#![cfg(not(feature = "no_index"))]
+use rhai::{Engine, EvalAltResult, RegisterFn, Scope, INT};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+static TABLE: &str = "alpha beta gamma";
+
+#[test]
+fn test_borrowed_str() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut scope = Scope::new();
+
+    scope.push("table", TABLE);
+    scope.push("name", Cow::<'static, str>::Borrowed("delta"));
+    scope.push("shared", Arc::<str>::from("epsilon"));
+
+    #[cfg(not(feature = "no_stdlib"))]
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "table.len()")?,
+        16
+    );
+    assert_eq!(engine.eval_with_scope::<char>(&mut scope, "table[1]")?, 'l');
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, "name + \"!\"")?,
+        "delta!"
+    );
+    assert!(engine.eval_with_scope::<bool>(&mut scope, "shared == \"epsilon\"")?);
+    #[cfg(not(feature = "no_stdlib"))]
+    assert!(engine.eval_with_scope::<bool>(&mut scope, "shared.contains(\"psi\")")?);
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, "type_of(table)")?,
+        "string"
+    );
+
+    // Passing the value around does not copy the text
+    let result = engine.eval_with_scope::<&'static str>(&mut scope, "let x = table; x")?;
+    assert!(std::ptr::eq(result, TABLE));
+
+    // Functions may also be registered over the borrowed type itself
+    engine.register_fn("first_word", |s: &'static str| {
+        s.split(' ').next().unwrap_or_default().to_string()
+    });
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, "first_word(table)")?,
+        "alpha"
+    );
+
+    // Modifying the value makes a copy, leaving the host data untouched
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, "let x = table; x[0] = 'A'; x")?,
+        "Alpha beta gamma"
+    );
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, "table[0] = 'A'; table")?,
+        "Alpha beta gamma"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_borrowed_bytes() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut scope = Scope::new();
+
+    let data: Arc<[u8]> = vec![1, 2, 3, 4].into();
+    scope.push("data", data.clone());
+    scope.push("header", &b"\x7fELF"[..]);
+
+    #[cfg(not(feature = "no_stdlib"))]
+    assert_eq!(engine.eval_with_scope::<INT>(&mut scope, "data.len()")?, 4);
+    assert_eq!(engine.eval_with_scope::<INT>(&mut scope, "data[2]")?, 3);
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "header[1]")?,
+        0x45
+    );
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "let n = 0; for b in data { n += b } n")?,
+        10
+    );
+
+    let result = engine.eval_with_scope::<Arc<[u8]>>(&mut scope, "let x = data; x")?;
+    assert!(Arc::ptr_eq(&result, &data));
+
+    assert_eq!(
+        engine.eval_with_scope::<Vec<u8>>(&mut scope, "let x = data; x[0] = 42; x")?,
+        vec![42, 2, 3, 4]
+    );
+    assert_eq!(&*data, &[1, 2, 3, 4]);
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use rhai::{Engine, EngineBuilder, EvalAltResult, RegisterFn, INT};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_builder() -> Result<(), Box<EvalAltResult>> {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let output = Arc::new(Mutex::new(String::new()));
+
+    let mut engine = {
+        let calls = calls.clone();
+        let output = output.clone();
+
+        Engine::builder()
+            .with_max_call_levels(8)
+            .with_unit_discard(true)
+            .with_int_widening(true)
+            .with_disabled_symbol("while")
+            .with_reserved_symbols(["secret"])
+            .with_global_constant("LIMIT", 5 as INT)
+            .configure(|engine| engine.register_fn("half", |x: u8| x / 2))
+            .on_print(move |s| output.lock().unwrap().push_str(s))
+            .on_call(move |name, _, _| {
+                calls.lock().unwrap().push(name.to_string());
+                true
+            })
+            .build()
+    };
+
+    engine.eval::<()>("print(half(LIMIT * 2)); 42")?;
+    assert_eq!(*output.lock().unwrap(), "5");
+    assert!(calls.lock().unwrap().contains(&"half".to_string()));
+
+    assert!(engine.compile("while true {}").is_err());
+    assert!(engine.compile("let secret = 1;").is_err());
+
+    #[cfg(not(feature = "no_function"))]
+    assert!(matches!(
+        *engine
+            .eval::<INT>("fn f(x) { 1 + f(x + 1) } f(0)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorStackOverflow(_)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_builder_raw() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = EngineBuilder::new_raw()
+        .with_global_constant("X", 40 as INT)
+        .build();
+
+    assert_eq!(engine.eval::<INT>("X + 2")?, 42);
+
+    #[cfg(not(feature = "no_object"))]
+    {
+        let mut engine = Engine::builder()
+            .with_env_var("MODE", "test".to_string())
+            .build();
+        assert_eq!(engine.eval::<String>("ENV.MODE")?, "test");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use rhai::{Engine, EvalAltResult, RegisterFn, INT};
+
+#[cfg(not(feature = "no_module"))]
+use rhai::StaticModuleResolver;
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_call_audit_log() -> Result<(), Box<EvalAltResult>> {
+    let mut log = Vec::new();
+
+    {
+        let mut engine = Engine::new();
+
+        engine.register_fn("secret", |x: INT| x * 2);
+        engine.on_call(|name, arg_types, pos| {
+            log.push(format!(
+                "{}({}) @ {}",
+                name,
+                arg_types.join(", "),
+                pos.line().unwrap()
+            ));
+            true
+        });
+
+        assert_eq!(
+            engine.eval::<INT>(
+                r#"
+                    fn double(x) { secret(x) }
+                    let s = "hello";
+                    double(len(s) * 4 + 1)
+                "#
+            )?,
+            42
+        );
+    }
+
+    let int = if cfg!(feature = "only_i32") {
+        "i32"
+    } else {
+        "i64"
+    };
+
+    // Script-defined functions are not native functions and are not audited
+    assert_eq!(
+        log,
+        vec![
+            "len(string) @ 4".to_string(),
+            format!("*({}, {}) @ 4", int, int),
+            format!("+({}, {}) @ 4", int, int),
+            format!("secret({}) @ 2", int),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_call_audit_veto() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("delete_file", |_: String| true);
+    engine.on_call(|name, _, _| !name.starts_with("delete_"));
+
+    assert_eq!(engine.eval::<INT>("40 + 2")?, 42);
+
+    assert!(matches!(
+        *engine
+            .eval::<bool>(r#"delete_file("/etc/passwd")"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorForbiddenFunction(name, _) if name == "delete_file"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_call_audit_engine_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut log = Vec::new();
+
+    {
+        let mut engine = Engine::new();
+        engine.on_call(|name, _, _| {
+            log.push(name.to_string());
+            true
+        });
+
+        engine.eval::<String>(r#"let x = eval("40 + 2"); type_of(format("{}", x))"#)?;
+    }
+
+    for name in &["eval", "+", "format", "type_of"] {
+        assert!(log.iter().any(|n| n == name), "{} not audited", name);
+    }
+
+    let mut engine = Engine::new();
+    engine.on_call(|name, _, _| name != "eval" && name != "is_def_var" && name != "is_int");
+
+    for script in &[r#"eval("42")"#, r#"is_def_var("x")"#, "is_int(42)"] {
+        assert!(
+            matches!(
+                *engine.eval::<bool>(script).expect_err("should error"),
+                EvalAltResult::ErrorForbiddenFunction(_, _)
+            ),
+            "{}",
+            script
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_call_audit_callbacks() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("secret", |x: INT| x * 2);
+    engine.on_call(|name, _, _| name != "secret" && name != "memoize");
+
+    #[cfg(not(feature = "no_index"))]
+    assert!(matches!(
+        *engine
+            .eval::<()>(r#"fn cmp(a, b) { secret(a) - b } sort([3, 1, 2], "cmp")"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorForbiddenFunction(name, _) if name == "secret"
+    ));
+
+    // Native calls made by script-defined functions in tail position are audited
+    assert!(matches!(
+        *engine
+            .eval::<INT>("fn f(x) { if x > 0 { f(x - 1) } else { secret(x) } } f(3)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorForbiddenFunction(name, _) if name == "secret"
+    ));
+
+    assert!(matches!(
+        *engine
+            .eval::<()>(r#"fn f(x) { x } memoize("f")"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorForbiddenFunction(name, _) if name == "memoize"
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_object"))]
+fn test_call_audit_properties() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.on_call(|name, _, _| name != "get$secret" && name != "set$secret");
+
+    assert_eq!(engine.eval::<INT>("let x = #{a: 42}; x.a")?, 42);
+    assert!(engine
+        .eval::<INT>("let x = #{secret: 42}; x.secret")
+        .is_err());
+    assert!(engine
+        .eval::<()>("let x = #{secret: 42}; x.secret = 0;")
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_module"))]
+fn test_call_audit_modules() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut resolver = StaticModuleResolver::new();
+
+    engine.register_fn("secret", |x: INT| x * 2);
+    resolver.insert("m", engine.compile("fn f(x) { secret(x) }")?);
+    engine.set_module_resolver(Some(resolver));
+
+    engine.on_call(|name, _, _| name != "secret");
+
+    // Native calls made by functions of imported modules are audited
+    for script in &[r#"import "m" as m; m::f(1)"#, r#"import f from "m"; f(1)"#] {
+        assert!(matches!(
+            *engine.eval::<INT>(script).expect_err("should error"),
+            EvalAltResult::ErrorForbiddenFunction(name, _) if name == "secret"
+        ));
+    }
+
+    Ok(())
+}
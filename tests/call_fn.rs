@@ -2,7 +2,7 @@
 use rhai::{Engine, EvalAltResult, ParseErrorType, Scope, INT};
 
 #[test]
-fn test_fn() -> Result<(), EvalAltResult> {
+fn test_fn() -> Result<(), Box<EvalAltResult>> {
     let engine = Engine::new();
 
     // Expect duplicated parameters error
@@ -19,7 +19,7 @@ fn test_fn() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_call_fn() -> Result<(), EvalAltResult> {
+fn test_call_fn() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
     let mut scope = Scope::new();
 
@@ -57,5 +57,72 @@ fn test_call_fn() -> Result<(), EvalAltResult> {
         1
     );
 
+    // Only the function called sees the scope, not the functions it calls in turn,
+    // whether they are called in tail position or not
+    let ast = engine.compile(
+        r"
+            fn get_foo() { foo }
+            fn tail() { foo += 1; get_foo() }
+            fn not_tail() { foo += 1; let x = get_foo(); x }
+        ",
+    )?;
+
+    for name in &["tail", "not_tail"] {
+        assert!(matches!(
+            *engine
+                .call_fn0::<INT>(&mut scope, &ast, name)
+                .expect_err("should error"),
+            EvalAltResult::ErrorVariableNotFound(v, _) if v == "foo"
+        ));
+    }
+    assert_eq!(scope.get_value::<INT>("foo"), Some(3));
+
+    Ok(())
+}
+
+#[test]
+fn test_call_fn_private() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut scope = Scope::new();
+
+    let ast = engine.compile(
+        r"
+            private fn square(x) { x * x }
+            fn sum_squares(x, y) { square(x) + square(y) }
+            square(3)
+        ",
+    )?;
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 9);
+
+    let r: INT = engine.call_fn(&mut scope, &ast, "sum_squares", (3 as INT, 4 as INT))?;
+    assert_eq!(r, 25);
+
+    assert!(matches!(
+        *engine
+            .call_fn1::<_, INT>(&mut scope, &ast, "square", 3 as INT)
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f.starts_with("square")
+    ));
+
+    assert!(ast
+        .iter_functions()
+        .any(|f| f.name == "square" && f.private));
+
+    assert!(matches!(
+        engine
+            .compile("private let x = 1;")
+            .expect_err("should error")
+            .error_type(),
+        ParseErrorType::MissingToken(t, _) if t == "fn"
+    ));
+    assert_eq!(
+        *engine
+            .compile("fn foo() { if true { private fn bar() {} } }")
+            .expect_err("should error")
+            .error_type(),
+        ParseErrorType::WrongFnDefinition
+    );
+
     Ok(())
 }
@@ -0,0 +1,93 @@
+use rhai::{Engine, EvalAltResult};
+
+#[test]
+fn test_call_graph() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile("let x = foo(1, bar()); print(x + 1);")?;
+    let graph = ast.call_graph();
+
+    let calls: Vec<_> = graph
+        .main
+        .iter()
+        .map(|call| (call.name.as_str(), call.args))
+        .collect();
+
+    assert_eq!(calls, vec![("foo", 2), ("bar", 0), ("print", 1), ("+", 2)]);
+    assert!(graph.functions.is_empty());
+    assert_eq!(graph.external_calls().len(), 4);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_call_graph_functions() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r#"
+            fn start(x) { helper(x, 1) }
+            fn helper(x, y) { if x > 0 { helper(x - y, y) } else { spawn_entity(x) } }
+            fn helper(x) { 0 }
+            fn dead() { dead_helper() }
+            fn dead_helper() { missing() }
+
+            start(10);
+        "#,
+    )?;
+
+    let graph = ast.call_graph();
+
+    assert!(graph.is_script_fn("helper", 2));
+    assert!(graph.is_script_fn("helper", 1));
+    assert!(!graph.is_script_fn("helper", 3));
+
+    assert_eq!(
+        graph.unreachable_functions(),
+        vec![("dead", 0), ("dead_helper", 0), ("helper", 1)]
+    );
+
+    let external: Vec<_> = graph
+        .external_calls()
+        .into_iter()
+        .map(|call| call.name.as_str())
+        .filter(|name| name.chars().all(|ch| ch.is_alphanumeric() || ch == '_'))
+        .collect();
+
+    assert_eq!(external, vec!["spawn_entity", "missing"]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_object"))]
+#[cfg(not(feature = "no_function"))]
+fn test_call_graph_methods() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r#"
+            fn area(shape, w) { w }
+            let s = make_shape();
+            s.area(2);
+            s.scale(2).area(3);
+        "#,
+    )?;
+
+    let graph = ast.call_graph();
+
+    let calls: Vec<_> = graph
+        .main
+        .iter()
+        .map(|call| (call.name.as_str(), call.args))
+        .collect();
+
+    assert_eq!(
+        calls,
+        vec![("make_shape", 0), ("area", 2), ("scale", 2), ("area", 2)]
+    );
+    assert!(graph.unreachable_functions().is_empty());
+
+    Ok(())
+}
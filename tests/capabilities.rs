@@ -0,0 +1,153 @@
+use rhai::{Engine, EvalAltResult, RegisterFn, INT};
+
+fn make_engine() -> Engine<'static> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("read_file", |_: String| 1 as INT);
+    engine.register_fn("read_file", |_: String, _: INT| 2 as INT);
+    engine.register_fn("fetch", |_: String| 3 as INT);
+    engine.register_fn("upload", |_: String| 4 as INT);
+    engine.register_fn("peek", |x: INT| x);
+
+    engine.tag_fn("read_file", &["fs"]);
+    engine.tag_fn("fetch", &["net"]);
+    engine.tag_fn("upload", &["fs", "net"]);
+    engine.tag_fn("peek", &["unsafe"]);
+
+    engine
+}
+
+fn is_forbidden(err: EvalAltResult, fn_name: &str) -> bool {
+    matches!(err, EvalAltResult::ErrorForbiddenFunction(name, _) if name == fn_name)
+}
+
+#[test]
+fn test_capabilities() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = make_engine();
+
+    // All capabilities are granted by default
+    assert_eq!(
+        engine.eval::<INT>(r#"read_file("a") + fetch("b") + upload("c") + peek(0)"#)?,
+        8
+    );
+
+    // No capabilities
+    engine.set_capabilities(Some(&[]));
+    assert_eq!(engine.eval::<INT>("40 + 2")?, 42);
+    assert!(is_forbidden(
+        *engine
+            .eval::<INT>(r#"read_file("a")"#)
+            .expect_err("should error"),
+        "read_file"
+    ));
+    assert!(is_forbidden(
+        *engine.eval::<INT>("peek(1)").expect_err("should error"),
+        "peek"
+    ));
+
+    // Tags apply to all functions with the same name
+    engine.set_capabilities(Some(&["fs"]));
+    assert_eq!(
+        engine.eval::<INT>(r#"read_file("a") + read_file("a", 1)"#)?,
+        3
+    );
+    assert!(is_forbidden(
+        *engine
+            .eval::<INT>(r#"fetch("b")"#)
+            .expect_err("should error"),
+        "fetch"
+    ));
+
+    // All capabilities of a function must be granted
+    assert!(is_forbidden(
+        *engine
+            .eval::<INT>(r#"upload("c")"#)
+            .expect_err("should error"),
+        "upload"
+    ));
+    engine.set_capabilities(Some(&["fs", "net"]));
+    assert_eq!(engine.eval::<INT>(r#"upload("c") + fetch("b")"#)?, 7);
+
+    // Removing tags
+    engine.set_capabilities(Some(&[]));
+    engine.tag_fn("peek", &[]);
+    assert_eq!(engine.eval::<INT>("peek(42)")?, 42);
+
+    engine.set_capabilities(None);
+    assert_eq!(engine.eval::<INT>(r#"upload("c")"#)?, 4);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_capabilities_in_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = make_engine();
+    engine.set_capabilities(Some(&["net"]));
+
+    let ast = engine.compile(
+        r#"
+            fn load(path) { read_file(path) }
+            fn download(url) { fetch(url) }
+        "#,
+    )?;
+
+    assert_eq!(
+        engine.call_fn::<_, INT>(
+            &mut Default::default(),
+            &ast,
+            "download",
+            ("x".to_string(),)
+        )?,
+        3
+    );
+    assert!(is_forbidden(
+        *engine
+            .call_fn::<_, INT>(&mut Default::default(), &ast, "load", ("x".to_string(),))
+            .expect_err("should error"),
+        "read_file"
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_optimize"))]
+fn test_capabilities_optimizer() -> Result<(), Box<EvalAltResult>> {
+    use rhai::OptimizationLevel;
+
+    let mut engine = make_engine();
+    engine.set_optimization_level(OptimizationLevel::Full);
+
+    // Tagged functions are not called ahead of time, even when compiled with all capabilities
+    let ast = engine.compile("peek(42)")?;
+
+    engine.set_capabilities(Some(&[]));
+    assert!(is_forbidden(
+        *engine.eval_ast::<INT>(&ast).expect_err("should error"),
+        "peek"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_capabilities_alias() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = make_engine();
+    engine.register_fn_alias("load_file", "read_file");
+    engine.register_fn_alias("old_load_file", "load_file");
+
+    engine.set_capabilities(Some(&["fs"]));
+    assert_eq!(engine.eval::<INT>(r#"old_load_file("a")"#)?, 1);
+
+    // Functions cannot be called via an alias to get around their tags
+    engine.set_capabilities(Some(&[]));
+    assert!(is_forbidden(
+        *engine
+            .eval::<INT>(r#"old_load_file("a")"#)
+            .expect_err("should error"),
+        "old_load_file"
+    ));
+
+    Ok(())
+}
@@ -1,7 +1,7 @@
-use rhai::{Engine, EvalAltResult};
+use rhai::{Engine, EvalAltResult, INT};
 
 #[test]
-fn test_chars() -> Result<(), EvalAltResult> {
+fn test_chars() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<char>("'y'")?, 'y');
@@ -18,6 +18,44 @@ fn test_chars() -> Result<(), EvalAltResult> {
 
     assert!(engine.eval::<char>(r"'\uhello'").is_err());
     assert!(engine.eval::<char>("''").is_err());
+    assert!(engine.eval::<char>("'ab'").is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_char_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(engine.eval::<char>("to_upper('a')")?, 'A');
+    assert_eq!(engine.eval::<char>("let c = 'Z'; c.to_lower()")?, 'z');
+    assert_eq!(engine.eval::<char>("to_upper('ß')")?, 'ß');
+    assert_eq!(engine.eval::<char>("to_upper('1')")?, '1');
+
+    assert!(engine.eval::<bool>("is_digit('7')")?);
+    assert!(!engine.eval::<bool>("is_digit('x')")?);
+    assert!(engine.eval::<bool>("is_alpha('é')")?);
+    assert!(!engine.eval::<bool>("is_alpha('_')")?);
+    assert!(engine.eval::<bool>("'\\t'.is_whitespace()")?);
+    assert!(!engine.eval::<bool>("is_whitespace('a')")?);
+
+    assert_eq!(engine.eval::<INT>("to_int('A')")?, 65);
+    assert_eq!(engine.eval::<char>("to_char(0x2764)")?, '❤');
+    assert_eq!(engine.eval::<char>("to_char(to_int('a') + 1)")?, 'b');
+
+    assert!(matches!(
+        *engine
+            .eval::<char>("to_char(-1)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorArithmetic(_, _, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<char>("to_char(0xd800)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorArithmetic(_, _, _)
+    ));
 
     Ok(())
 }
@@ -1,4 +1,4 @@
-use rhai::{Engine, INT};
+use rhai::{Engine, EvalAltResult, Position, Token, INT};
 
 #[test]
 fn test_comments() {
@@ -12,3 +12,67 @@ fn test_comments() {
         .eval::<INT>("let /* I am a multiline comment, yay! */ x = 5; x")
         .is_ok());
 }
+
+#[test]
+fn test_comments_nested() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>("let x = /* outer /* inner */ still outer */ 42; x")?,
+        42
+    );
+
+    assert!(engine
+        .compile("let x = /* outer /* inner */ 42; x")
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_comments_kept() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let script = r#"
+        // first
+        let x = /* a /* nested */
+                   block */ 42;
+        x   /// last
+    "#;
+
+    assert!(engine.compile(script)?.comments().is_empty());
+
+    engine.enable_comments(true);
+
+    let ast = engine.compile(script)?;
+    let comments = ast.comments();
+
+    assert_eq!(comments.len(), 3);
+
+    assert_eq!(comments[0].text, "// first");
+    assert_eq!(comments[0].start, Position::new(2, 9));
+    assert_eq!(comments[0].end, Position::new(2, 16));
+
+    assert_eq!(
+        comments[1].text,
+        "/* a /* nested */\n                   block */"
+    );
+    assert_eq!(comments[1].start, Position::new(3, 17));
+    assert_eq!(comments[1].end, Position::new(4, 27));
+
+    assert_eq!(comments[2].text, "/// last");
+    assert_eq!(comments[2].start, Position::new(5, 13));
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    let tokens: Vec<_> = engine.lex("x // answer").map(|(token, _)| token).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Identifier("x".into()),
+            Token::Comment("// answer".into())
+        ]
+    );
+
+    Ok(())
+}
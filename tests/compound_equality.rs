@@ -1,7 +1,7 @@
 use rhai::{Engine, EvalAltResult, INT};
 
 #[test]
-fn test_or_equals() -> Result<(), EvalAltResult> {
+fn test_or_equals() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("let x = 16; x |= 74; x")?, 90);
@@ -12,7 +12,7 @@ fn test_or_equals() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_and_equals() -> Result<(), EvalAltResult> {
+fn test_and_equals() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("let x = 16; x &= 31; x")?, 16);
@@ -24,42 +24,42 @@ fn test_and_equals() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_xor_equals() -> Result<(), EvalAltResult> {
+fn test_xor_equals() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
     assert_eq!(engine.eval::<INT>("let x = 90; x ^= 12; x")?, 86);
     Ok(())
 }
 
 #[test]
-fn test_multiply_equals() -> Result<(), EvalAltResult> {
+fn test_multiply_equals() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
     assert_eq!(engine.eval::<INT>("let x = 2; x *= 3; x")?, 6);
     Ok(())
 }
 
 #[test]
-fn test_divide_equals() -> Result<(), EvalAltResult> {
+fn test_divide_equals() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
     assert_eq!(engine.eval::<INT>("let x = 6; x /= 2; x")?, 3);
     Ok(())
 }
 
 #[test]
-fn test_left_shift_equals() -> Result<(), EvalAltResult> {
+fn test_left_shift_equals() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
     assert_eq!(engine.eval::<INT>("let x = 9; x >>=1; x")?, 4);
     Ok(())
 }
 
 #[test]
-fn test_right_shift_equals() -> Result<(), EvalAltResult> {
+fn test_right_shift_equals() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
     assert_eq!(engine.eval::<INT>("let x = 4; x<<= 2; x")?, 16);
     Ok(())
 }
 
 #[test]
-fn test_modulo_equals() -> Result<(), EvalAltResult> {
+fn test_modulo_equals() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
     assert_eq!(engine.eval::<INT>("let x = 10; x %= 4; x")?, 2);
     Ok(())
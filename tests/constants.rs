@@ -1,21 +1,119 @@
-use rhai::{Engine, EvalAltResult, INT};
+use rhai::{Engine, EvalAltResult, Scope, INT};
+
+#[cfg(not(feature = "no_optimize"))]
+use rhai::OptimizationLevel;
 
 #[test]
-fn test_constant() -> Result<(), EvalAltResult> {
+fn test_constant() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("const x = 123; x")?, 123);
 
     assert!(
-        matches!(engine.eval::<INT>("const x = 123; x = 42;").expect_err("expects error"),
+        matches!(*engine.eval::<INT>("const x = 123; x = 42;").expect_err("expects error"),
         EvalAltResult::ErrorAssignmentToConstant(var, _) if var == "x")
     );
 
     #[cfg(not(feature = "no_index"))]
     assert!(
-        matches!(engine.eval::<INT>("const x = [1, 2, 3, 4, 5]; x[2] = 42;").expect_err("expects error"),
+        matches!(*engine.eval::<INT>("const x = [1, 2, 3, 4, 5]; x[2] = 42;").expect_err("expects error"),
+        EvalAltResult::ErrorAssignmentToConstant(var, _) if var == "x")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_constant_from_scope() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut scope = Scope::new();
+
+    scope.push_constant("x", 40 as INT);
+
+    assert_eq!(engine.eval_with_scope::<INT>(&mut scope, "x + 2")?, 42);
+
+    assert!(
+        matches!(*engine.eval_with_scope::<INT>(&mut scope, "x = 42;").expect_err("expects error"),
         EvalAltResult::ErrorAssignmentToConstant(var, _) if var == "x")
     );
 
+    assert!(
+        matches!(*engine.eval_with_scope::<INT>(&mut scope, "x += 2;").expect_err("expects error"),
+        EvalAltResult::ErrorAssignmentToConstant(var, _) if var == "x")
+    );
+
+    assert_eq!(scope.get_value::<INT>("x").expect("x should exist"), 40);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_optimize"))]
+fn test_constant_from_scope_folding() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut scope = Scope::new();
+
+    engine.set_optimization_level(OptimizationLevel::Full);
+    scope.push_constant("x", 40 as INT);
+
+    let ast = engine.compile_with_scope(&scope, "if x > 30 { x + 2 } else { 0 }")?;
+
+    // The constant is folded into the AST, which no longer needs the scope
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn test_constant_global() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_global_constant("VERSION", 3 as INT);
+    engine.register_global_constant("NAME", "rhai".to_string());
+
+    assert_eq!(engine.eval::<INT>("VERSION + 1")?, 4);
+    assert_eq!(engine.eval::<String>("NAME")?, "rhai");
+    assert!(engine.eval::<bool>(r#"is_def_var("VERSION")"#)?);
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        assert_eq!(engine.eval::<INT>("fn v() { VERSION } v()")?, 3);
+        assert_eq!(engine.eval::<INT>("fn v(VERSION) { VERSION } v(42)")?, 42);
+    }
+
+    // Script variables shadow global constants
+    assert_eq!(engine.eval::<INT>("let VERSION = 42; VERSION")?, 42);
+    assert_eq!(
+        engine.eval::<INT>("let x = VERSION; { let VERSION = 42; x + VERSION }")?,
+        45
+    );
+
+    assert!(
+        matches!(*engine.eval::<INT>("VERSION = 42;").expect_err("expects error"),
+        EvalAltResult::ErrorAssignmentToConstant(var, _) if var == "VERSION")
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_optimize"))]
+fn test_constant_global_folding() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.set_optimization_level(OptimizationLevel::Full);
+    engine.register_global_constant("VERSION", 3 as INT);
+
+    let ast = engine.compile("if VERSION > 2 { VERSION * 10 } else { 0 }")?;
+
+    // The constant is folded into the AST, so changing it does not affect the compiled script
+    engine.register_global_constant("VERSION", 1 as INT);
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 30);
+    assert_eq!(
+        engine.eval::<INT>("if VERSION > 2 { VERSION * 10 } else { 0 }")?,
+        0
+    );
+
     Ok(())
 }
@@ -0,0 +1,63 @@
+use rhai::{CustomKeyword, Engine, EvalAltResult, RegisterFn, INT};
+
+#[test]
+fn test_custom_keywords_prefix() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("double", |x: INT| x * 2);
+    engine.register_custom_keyword("double", CustomKeyword::Prefix)?;
+
+    assert_eq!(engine.eval::<INT>("double 21")?, 42);
+    assert_eq!(engine.eval::<INT>("let x = 20; double x + 2")?, 42);
+    assert_eq!(engine.eval::<INT>("double (20 + 1)")?, 42);
+    assert_eq!(engine.eval::<INT>("double double 10 + 2")?, 42);
+    assert_eq!(engine.eval::<INT>("double -21")?, -42);
+
+    // The keyword can no longer be used as a name
+    assert!(engine.compile("let double = 1;").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_custom_keywords_infix() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("plus", |x: INT, y: INT| x + y);
+    engine.register_fn("times", |x: INT, y: INT| x * y);
+    engine.register_custom_keyword("plus", CustomKeyword::Infix(80))?;
+    engine.register_custom_keyword("times", CustomKeyword::Infix(90))?;
+
+    assert_eq!(engine.eval::<INT>("40 plus 2")?, 42);
+    assert_eq!(engine.eval::<INT>("2 plus 4 times 10")?, 42);
+    assert_eq!(engine.eval::<INT>("(2 plus 4) times 7")?, 42);
+    assert_eq!(engine.eval::<INT>("1 plus 1 plus 40")?, 42);
+    assert_eq!(engine.eval::<INT>("1 plus -1 plus 42")?, 42);
+    assert!(engine.eval::<bool>("2 times 21 == 42")?);
+
+    #[cfg(not(feature = "no_function"))]
+    assert_eq!(
+        engine.eval::<INT>("fn times(x, y) { x * y - 1 } 1 plus 6 times 7")?,
+        42
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_custom_keywords_invalid() {
+    let mut engine = Engine::new();
+
+    assert!(engine
+        .register_custom_keyword("while", CustomKeyword::Prefix)
+        .is_err());
+    assert!(engine
+        .register_custom_keyword("++", CustomKeyword::Prefix)
+        .is_err());
+    assert!(engine
+        .register_custom_keyword("two words", CustomKeyword::Prefix)
+        .is_err());
+    assert!(engine
+        .register_custom_keyword("plus", CustomKeyword::Infix(0))
+        .is_err());
+}
@@ -0,0 +1,128 @@
+#![cfg(feature = "datetime")]
+use rhai::{DateTime, Engine, EvalAltResult, INT};
+
+#[test]
+fn test_datetime() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let dt = engine.eval::<DateTime>(r#"parse_datetime("2020-02-28T23:30:00+02:00")"#)?;
+    assert_eq!(dt.to_rfc3339(), "2020-02-28T23:30:00+02:00");
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let dt = parse_datetime("2020-02-28T23:30:15+02:00");
+                dt.year() * 10000 + dt.month() * 100 + dt.day()
+            "#
+        )?,
+        20200228
+    );
+    assert_eq!(
+        engine.eval::<INT>(
+            "let dt = datetime(2020, 3, 1, 12, 34, 56); dt.hour() + dt.minute() + dt.second()"
+        )?,
+        102
+    );
+    assert_eq!(engine.eval::<INT>("datetime(2020, 3, 1).weekday()")?, 7);
+    assert_eq!(
+        engine.eval::<INT>("datetime(2020, 3, 1).day_of_year()")?,
+        61
+    );
+    assert_eq!(
+        engine.eval::<INT>("datetime(1970, 1, 2).unix_time()")?,
+        86400
+    );
+    assert_eq!(engine.eval::<String>("type_of(now())")?, "datetime");
+
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(
+        engine.eval::<INT>("let dt = datetime(2020, 3, 1); dt.month")?,
+        3
+    );
+
+    assert!(engine
+        .eval::<DateTime>(r#"parse_datetime("yesterday")"#)
+        .is_err());
+    assert!(engine.eval::<DateTime>("datetime(2020, 2, 30)").is_err());
+
+    // Components which do not fit are errors instead of wrapping around
+    #[cfg(not(feature = "only_i32"))]
+    {
+        assert!(engine
+            .eval::<DateTime>("datetime(4294969296, 1, 1)")
+            .is_err());
+        assert!(engine
+            .eval::<DateTime>("datetime(2020, 4294967297, 1)")
+            .is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_datetime_arithmetic() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<String>(
+            r#"(parse_datetime("2020-02-28T23:30:00+02:00") + 3600).to_rfc3339()"#
+        )?,
+        "2020-02-29T00:30:00+02:00"
+    );
+    assert_eq!(
+        engine.eval::<String>("(datetime(2020, 1, 31) - 60).to_rfc3339()")?,
+        "2020-01-30T23:59:00+00:00"
+    );
+    assert_eq!(
+        engine.eval::<String>("let dt = datetime(2020, 1, 31); to_rfc3339(dt.add_months(1))")?,
+        "2020-02-29T00:00:00+00:00"
+    );
+    assert_eq!(
+        engine.eval::<String>("let dt = datetime(2020, 1, 31); to_rfc3339(dt.add_days(-31))")?,
+        "2019-12-31T00:00:00+00:00"
+    );
+    #[cfg(not(feature = "only_i32"))]
+    assert!(matches!(
+        *engine
+            .eval::<DateTime>("datetime(2020, 1, 31).add_months(4294967296)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorArithmetic(_, _, _)
+    ));
+    assert!(engine.eval::<bool>("datetime(2020, 1, 1) < datetime(2020, 1, 2)")?);
+    assert!(engine.eval::<bool>(
+        r#"datetime(2020, 1, 1, 2, 0, 0) == parse_datetime("2020-01-01T03:00:00+01:00")"#
+    )?);
+
+    #[cfg(not(feature = "no_float"))]
+    {
+        assert_eq!(
+            engine.eval::<rhai::FLOAT>("datetime(2020, 1, 2) - datetime(2020, 1, 1, 23, 0, 0)")?,
+            3600.0
+        );
+        assert_eq!(
+            engine.eval::<String>("(datetime(2020, 1, 1) + 1.5).to_rfc3339()")?,
+            "2020-01-01T00:00:01.500+00:00"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_datetime_format() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<String>(r#"datetime(2020, 3, 1, 8, 5, 0).format("%d/%m/%Y %H:%M")"#)?,
+        "01/03/2020 08:05"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#""at " + datetime(2020, 3, 1)"#)?,
+        "at 2020-03-01T00:00:00+00:00"
+    );
+    assert!(engine
+        .eval::<String>(r#"datetime(2020, 3, 1).format("%Q")"#)
+        .is_err());
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use rhai::{Engine, EvalAltResult, INT};
+
+#[test]
+fn test_def_var_forbid_name() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.on_def_var(|name, is_const, _, _| !(name == "x" && is_const));
+
+    assert_eq!(engine.eval::<INT>("let x = 42; x")?, 42);
+
+    assert!(matches!(
+        *engine.eval::<INT>("const x = 42; x").expect_err("should error"),
+        EvalAltResult::ErrorForbiddenVariable(name, _) if name == "x"
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_def_var_nesting_level() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Only allow local variables inside functions
+    engine.on_def_var(|_, _, level, _| level > 0);
+
+    assert_eq!(engine.eval::<INT>("fn foo() { let x = 42; x } foo()")?, 42);
+
+    assert!(matches!(
+        *engine.eval::<INT>("let x = 42; x").expect_err("should error"),
+        EvalAltResult::ErrorForbiddenVariable(name, _) if name == "x"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_def_var_max_count() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.on_def_var(|_, _, _, scope| scope.len() < 3);
+
+    assert_eq!(
+        engine.eval::<INT>("let x = 1; let y = 2; let z = 3; x + y + z")?,
+        6
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>("let a = 1; let b = 2; let c = 3; let d = 4; a")
+            .expect_err("should error"),
+        EvalAltResult::ErrorForbiddenVariable(name, _) if name == "d"
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use rhai::{Dialect, Engine, EvalAltResult, LexError, ParseErrorType, RegisterFn, INT};
+
+const FORMULA: Dialect = Dialect::FULL
+    .allow_only(&[
+        "+", "-", "*", "/", "(", ")", ",", ">", "<", "==", "if", "else", "{", "}",
+    ])
+    .disable(&["eval"])
+    .without_strings()
+    .without_chars();
+
+fn disabled(engine: &Engine, script: &str) -> Option<String> {
+    match engine.compile(script) {
+        Ok(_) => None,
+        Err(err) => match err.error_type() {
+            ParseErrorType::BadInput(s) => Some(s.clone()),
+            _ => panic!("unexpected error: {}", err),
+        },
+    }
+}
+
+fn error(symbol: &str) -> Option<String> {
+    Some(LexError::DisabledSymbol(symbol.into()).to_string())
+}
+
+#[test]
+fn test_dialect() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::builder()
+        .with_dialect(&FORMULA)
+        .configure(|engine| engine.register_fn("max", |x: INT, y: INT| x.max(y)))
+        .build();
+
+    assert_eq!(engine.eval::<INT>("max(2, 3) * (4 - 1)")?, 9);
+    assert_eq!(engine.eval::<INT>("if 1 > 2 { 1 } else { -1 }")?, -1);
+
+    assert_eq!(disabled(&engine, "let x = 1;"), error("let"));
+    assert_eq!(disabled(&engine, "!(1 > 2)"), error("!"));
+    assert_eq!(disabled(&engine, "1 % 2"), error("%"));
+    assert_eq!(disabled(&engine, "max(1, 2) = 1"), error("="));
+    assert_eq!(disabled(&engine, "while 1 > 2 {}"), error("while"));
+    assert_eq!(disabled(&engine, r#"eval(1)"#), error("eval"));
+    assert_eq!(disabled(&engine, r#"max("a", "b")"#), error("string"));
+    assert_eq!(disabled(&engine, "'x'"), error("character"));
+
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(disabled(&engine, "max(1, 2)[0]"), error("["));
+
+    // Symbols disabled separately stay disabled
+    engine.disable_symbol("max");
+    engine.set_dialect(&Dialect::FULL);
+    assert_eq!(engine.eval::<String>(r#"let x = "ok"; x"#)?, "ok");
+    assert_eq!(disabled(&engine, "max(1, 2)"), error("max"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_float"))]
+fn test_dialect_floats() -> Result<(), Box<EvalAltResult>> {
+    const INTEGERS: Dialect = Dialect::FULL.without_floats();
+
+    let mut engine = Engine::new();
+    assert!(engine.eval::<rhai::FLOAT>("1.5 * 2.0")? > 2.9);
+
+    engine.set_dialect(&INTEGERS);
+    assert_eq!(disabled(&engine, "1.5 * 2"), error("floating-point number"));
+    assert_eq!(engine.eval::<INT>("3 * 2")?, 6);
+
+    Ok(())
+}
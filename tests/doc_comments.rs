@@ -0,0 +1,90 @@
+#![cfg(not(feature = "no_function"))]
+use rhai::{Engine, EvalAltResult, ParseErrorType, INT};
+
+#[test]
+fn test_doc_comments() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.enable_doc_comments(true);
+
+    let ast = engine.compile(
+        r"
+            /// Hello world
+            /// This is a doc-comment
+            // Not a doc-comment
+            fn foo(x) { x + 1 }
+
+            /** Block doc-comment
+                on many lines
+             */
+            fn bar(x, y) { x * y }
+
+            //// Not a doc-comment
+            fn baz() { 42 }
+
+            /// Not attached to any function
+            let x = 1;
+
+            fn qux() { x }
+
+            foo(41)
+        ",
+    )?;
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    let functions: Vec<_> = ast.iter_functions().collect();
+    assert_eq!(functions.len(), 4);
+
+    let get = |name: &str| {
+        functions
+            .iter()
+            .find(|f| f.name == name)
+            .expect("function should exist")
+    };
+
+    assert_eq!(
+        get("foo").comments,
+        vec!["/// Hello world", "/// This is a doc-comment"]
+    );
+    assert_eq!(get("bar").params, vec!["x", "y"]);
+    assert_eq!(
+        get("bar").comments,
+        vec!["/** Block doc-comment\n                on many lines\n             */"]
+    );
+    assert!(get("baz").comments.is_empty());
+    assert!(get("qux").comments.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_doc_comments_disabled() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r"
+            /// Hello world
+            fn foo(x) { x + 1 }
+        ",
+    )?;
+
+    assert!(ast
+        .iter_functions()
+        .all(|f| f.name == "foo" && f.comments.is_empty()));
+
+    Ok(())
+}
+
+#[test]
+fn test_doc_comments_in_block() {
+    let mut engine = Engine::new();
+    engine.enable_doc_comments(true);
+
+    assert!(matches!(
+        *engine
+            .compile("{ /// Hello world\nfn foo() { 42 } }")
+            .expect_err("should error")
+            .error_type(),
+        ParseErrorType::WrongFnDefinition
+    ));
+}
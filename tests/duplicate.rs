@@ -0,0 +1,91 @@
+use rhai::{Engine, EvalAltResult, RegisterFn, INT};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_duplicate() -> Result<(), Box<EvalAltResult>> {
+    let mut template = Engine::new();
+
+    template.register_fn("triple", |x: INT| x * 3);
+    template.register_iterator::<INT, _>(|x| {
+        let n = *x.downcast_ref::<INT>().unwrap();
+        Box::new((0..n).map(|i| Box::new(i) as rhai::Dynamic))
+    });
+    template.set_max_call_levels(3);
+    template.disable_symbol("while");
+
+    let mut engine = template.duplicate();
+
+    assert_eq!(engine.eval::<INT>("triple(14)")?, 42);
+    assert_eq!(
+        engine.eval::<INT>("let s = 0; for i in 5 { s += i; } s")?,
+        10
+    );
+    assert!(engine.compile("while true {}").is_err());
+
+    #[cfg(not(feature = "no_function"))]
+    assert!(matches!(
+        *engine
+            .eval::<INT>("fn f(n) { if n == 0 { 0 } else { n + f(n - 1) } } f(10)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorStackOverflow(_)
+    ));
+
+    // Functions registered afterwards are only visible in the engine they are registered into
+    engine.register_fn("double", |x: INT| x * 2);
+    template.register_fn("half", |x: INT| x / 2);
+
+    assert_eq!(engine.eval::<INT>("double(21)")?, 42);
+    assert!(engine.eval::<INT>("half(84)").is_err());
+    assert!(template.eval::<INT>("double(21)").is_err());
+    assert_eq!(template.eval::<INT>("half(84)")?, 42);
+    assert_eq!(template.eval::<INT>("triple(14)")?, 42);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "sync")]
+fn test_duplicate_threads() {
+    let mut template = Engine::new();
+    template.register_fn("triple", |x: INT| x * 3);
+
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let mut engine = template.duplicate();
+            std::thread::spawn(move || engine.eval::<INT>(&format!("triple({})", i)).unwrap())
+        })
+        .collect();
+
+    let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+    assert_eq!(results, vec![0, 3, 6, 9]);
+}
+
+#[test]
+fn test_duplicate_on_call() -> Result<(), Box<EvalAltResult>> {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+
+    let mut template = Engine::new();
+    template.register_fn("secret", |x: INT| x);
+    template.on_call({
+        let calls = calls.clone();
+        move |name, _, _| {
+            calls.lock().unwrap().push(name.to_string());
+            name != "secret"
+        }
+    });
+
+    // The audit callback is shared by the duplicates
+    let mut engine = template.duplicate();
+
+    assert_eq!(engine.eval::<INT>("40 + 2")?, 42);
+    assert!(matches!(
+        *engine.eval::<INT>("secret(42)").expect_err("should error"),
+        EvalAltResult::ErrorForbiddenFunction(name, _) if name == "secret"
+    ));
+
+    assert_eq!(template.eval::<INT>("1 + 1")?, 2);
+    assert_eq!(*calls.lock().unwrap(), ["+", "secret", "+"]);
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+#![cfg(not(feature = "no_object"))]
+use rhai::{Engine, EvalAltResult, RegisterFn, INT};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Light {
+    Off,
+    On,
+    Dimmed(INT),
+    Colored { red: INT, green: INT, blue: INT },
+}
+
+fn make_engine() -> Engine<'static> {
+    let mut engine = Engine::new();
+
+    engine.register_enum_type("Light", &[("Off", Light::Off), ("On", Light::On)]);
+    engine.register_fn("Dimmed", Light::Dimmed);
+    engine.register_fn("Colored", |red: INT, green: INT, blue: INT| {
+        Light::Colored { red, green, blue }
+    });
+    engine.register_get("level", |x: &mut Light| match x {
+        Light::Dimmed(level) => *level,
+        Light::On => 100,
+        _ => 0,
+    });
+
+    engine
+}
+
+#[test]
+fn test_enums() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = make_engine();
+
+    assert_eq!(engine.eval::<Light>("Off()")?, Light::Off);
+    assert_eq!(engine.eval::<Light>("Dimmed(42)")?, Light::Dimmed(42));
+    assert_eq!(engine.eval::<String>("type_of(On())")?, "Light");
+
+    assert!(engine.eval::<bool>("On() == On()")?);
+    assert!(engine.eval::<bool>("On() != Off()")?);
+    assert!(engine.eval::<bool>("Dimmed(1) != Dimmed(2)")?);
+    assert!(!engine.eval::<bool>("On() == 1")?);
+
+    assert_eq!(
+        engine.eval::<String>("let x = Dimmed(1); x.variant()")?,
+        "Dimmed"
+    );
+    assert_eq!(
+        engine.eval::<String>("let x = Colored(1, 2, 3); x.variant()")?,
+        "Colored"
+    );
+    assert!(engine.eval::<bool>(r#"let x = On(); x.is_variant("On")"#)?);
+    assert!(!engine.eval::<bool>(r#"let x = On(); x.is_variant("Off")"#)?);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_enums_matching() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = make_engine();
+
+    let script = r#"
+        fn brightness(light) {
+            let v = light.variant();
+
+            if v == "Off" {
+                0
+            } else if v == "Dimmed" {
+                light.level
+            } else {
+                100
+            }
+        }
+    "#;
+
+    for (light, expected) in &[("Off()", 0), ("On()", 100), ("Dimmed(42)", 42)] {
+        assert_eq!(
+            engine.eval::<INT>(&format!("{} brightness({})", script, light))?,
+            *expected
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,72 @@
+#![cfg(not(feature = "no_object"))]
+use rhai::{Engine, EvalAltResult, Map, Scope, INT};
+
+#[test]
+fn test_env() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!(matches!(
+        *engine.eval::<INT>("ENV.x").expect_err("expects error"),
+        EvalAltResult::ErrorVariableNotFound(ref name, _) if name == "ENV"
+    ));
+    assert!(!engine.eval::<bool>(r#"is_def_var("ENV")"#)?);
+
+    let mut env = Map::new();
+    env.insert("version".into(), Box::new("1.2.3".to_string()));
+    engine.set_env(env);
+    engine.set_env_var("debug", true);
+    engine.set_env_var("level", 3 as INT);
+
+    assert_eq!(engine.eval::<String>("ENV.version")?, "1.2.3");
+    #[cfg(not(feature = "no_index"))]
+    assert!(engine.eval::<bool>(r#"ENV["debug"]"#)?);
+    assert!(engine.eval::<bool>(r#"is_def_var("ENV")"#)?);
+    #[cfg(not(feature = "no_stdlib"))]
+    assert_eq!(engine.eval::<INT>("ENV.len()")?, 3);
+    #[cfg(not(feature = "no_function"))]
+    assert_eq!(
+        engine.eval::<INT>("fn level() { ENV.level * 2 } level()")?,
+        6
+    );
+    assert_eq!(engine.eval::<INT>("let env = ENV; env.level")?, 3);
+
+    // Visible with any scope, without being pushed into it
+    let mut scope = Scope::new();
+    assert_eq!(engine.eval_with_scope::<INT>(&mut scope, "ENV.level")?, 3);
+    assert_eq!(scope.len(), 0);
+
+    // Shadowed by script variables
+    assert_eq!(engine.eval::<INT>("let ENV = 42; ENV")?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn test_env_read_only() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_env_var("level", 3 as INT);
+
+    let mut scripts = vec!["ENV = 1", "ENV += 1", "ENV.level = 1"];
+    #[cfg(not(feature = "no_index"))]
+    scripts.push(r#"ENV["level"] = 1"#);
+
+    for script in &scripts {
+        assert!(
+            matches!(
+                *engine.eval::<()>(script).expect_err("expects error"),
+                EvalAltResult::ErrorAssignmentToConstant(ref name, _) if name == "ENV"
+            ),
+            "{}",
+            script
+        );
+    }
+
+    // Changes to a copy are not visible to other scripts
+    assert_eq!(
+        engine.eval::<INT>("let env = ENV; env.level = 1; env.level")?,
+        1
+    );
+    assert_eq!(engine.eval::<INT>("ENV.level")?, 3);
+
+    Ok(())
+}
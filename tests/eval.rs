@@ -1,7 +1,7 @@
-use rhai::{Engine, EvalAltResult, Scope, INT};
+use rhai::{Engine, EvalAltResult, ParseErrorType, Scope, INT};
 
 #[test]
-fn test_eval() -> Result<(), EvalAltResult> {
+fn test_eval() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(
@@ -18,7 +18,7 @@ fn test_eval() -> Result<(), EvalAltResult> {
 
 #[test]
 #[cfg(not(feature = "no_function"))]
-fn test_eval_function() -> Result<(), EvalAltResult> {
+fn test_eval_function() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
     let mut scope = Scope::new();
 
@@ -61,7 +61,7 @@ fn test_eval_function() -> Result<(), EvalAltResult> {
 
 #[test]
 #[cfg(not(feature = "no_function"))]
-fn test_eval_override() -> Result<(), EvalAltResult> {
+fn test_eval_override() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(
@@ -77,3 +77,27 @@ fn test_eval_override() -> Result<(), EvalAltResult> {
 
     Ok(())
 }
+
+#[test]
+fn test_eval_disabled() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.disable_symbol("eval");
+
+    assert!(matches!(
+        *engine.eval::<INT>(r#"eval("40 + 2")"#).expect_err("should error"),
+        EvalAltResult::ErrorParsing(ref err) if *err.error_type() == ParseErrorType::BadInput("'eval' is disabled".into())
+    ));
+
+    // Disabling 'eval' does not affect other scripts
+    assert_eq!(engine.eval::<INT>("let evaluate = 40; evaluate + 2")?, 42);
+
+    // Strings containing the symbol are not affected
+    assert_eq!(engine.eval::<String>(r#""eval""#)?, "eval");
+
+    engine.disable_symbol("+=");
+
+    assert!(engine.compile("let x = 40; x += 2; x").is_err());
+
+    Ok(())
+}
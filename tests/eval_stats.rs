@@ -0,0 +1,107 @@
+use rhai::{Engine, EvalAltResult, EvalStats, Scope, INT};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Allocator which counts the number of allocations, to guard against allocation regressions.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Tests in this file run one at a time so that they do not disturb each other's allocation counts.
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[test]
+fn test_eval_stats() -> Result<(), Box<EvalAltResult>> {
+    let _lock = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let mut engine = Engine::new();
+
+    assert_eq!(engine.last_eval_stats(), EvalStats::default());
+
+    engine.eval::<INT>("let x = 0; while x < 100 { x += 1; } x")?;
+    let stats = engine.last_eval_stats();
+
+    // The numbers are deterministic and must not change between runs
+    engine.eval::<INT>("let x = 0; while x < 100 { x += 1; } x")?;
+    assert_eq!(engine.last_eval_stats(), stats);
+
+    // Statistics are for the last evaluation only
+    engine.eval::<INT>("let x = 0; while x < 1000 { x += 1; } x")?;
+    let more = engine.last_eval_stats();
+    assert!(more.operations > stats.operations * 9);
+    assert!(more.fn_calls > stats.fn_calls * 9);
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        let ast = engine.compile("fn add(x, y) { x + y }")?;
+        engine.call_fn::<_, INT>(&mut Scope::new(), &ast, "add", (40 as INT, 2 as INT))?;
+
+        // 'add' and '+'
+        assert_eq!(engine.last_eval_stats().fn_calls, 2);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_eval_stats_allocations() -> Result<(), Box<EvalAltResult>> {
+    let _lock = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let mut engine = Engine::new();
+    let ast = engine.compile(
+        r#"
+            fn add(x, y) { x + y }
+
+            let sum = 0;
+            let i = 0;
+            while i < 1000 {
+                sum = add(sum, i);
+                i += 1;
+            }
+            sum
+        "#,
+    )?;
+
+    // Warm up
+    engine.eval_ast_with_scope::<INT>(&mut Scope::new(), &ast)?;
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    assert_eq!(
+        engine.eval_ast_with_scope::<INT>(&mut Scope::new(), &ast)?,
+        499_500
+    );
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    let stats = engine.last_eval_stats();
+    assert!(stats.fn_calls >= 4000);
+
+    // Each operation allocates at most a few boxed values; a regression here usually means
+    // that something is being cloned or collected on every call.
+    assert!(
+        allocations < stats.operations as usize * 2,
+        "{} allocations for {} operations",
+        allocations,
+        stats.operations
+    );
+
+    Ok(())
+}
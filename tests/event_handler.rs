@@ -0,0 +1,61 @@
+#![cfg(not(feature = "no_function"))]
+use rhai::{Engine, EvalAltResult, RegisterFn, Scope, ScriptedEventHandler, INT};
+
+#[test]
+fn test_event_handler() -> Result<(), Box<EvalAltResult>> {
+    let mut handler = ScriptedEventHandler::new(
+        Engine::new(),
+        r"
+            let state = 0;
+
+            fn start() { state = 1; }
+            fn update(x) { state += x; state }
+            fn reset() { state = 0; }
+        ",
+    )?;
+
+    assert!(handler.has_handler("start", 0));
+    assert!(handler.has_handler("update", 1));
+    assert!(!handler.has_handler("update", 2));
+    assert!(!handler.has_handler("stop", 0));
+
+    handler.fire::<_, ()>("start", ())?;
+    assert_eq!(handler.fire::<_, INT>("update", (41 as INT,))?, 42);
+    assert_eq!(handler.scope().get_value::<INT>("state"), Some(42));
+
+    handler.fire::<_, ()>("reset", ())?;
+    assert_eq!(handler.scope().get_value::<INT>("state"), Some(0));
+
+    assert!(matches!(
+        *handler.fire::<_, ()>("stop", ()).expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(name, _) if name.starts_with("stop")
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_event_handler_with_scope() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.register_fn("double", |x: INT| x * 2);
+
+    let mut scope = Scope::new();
+    scope.push("base", 21 as INT);
+
+    let mut handler = ScriptedEventHandler::new_with_scope(
+        engine,
+        scope,
+        r"
+            let total = double(base);
+
+            fn query() { total }
+        ",
+    )?;
+
+    assert_eq!(handler.fire::<_, INT>("query", ())?, 42);
+
+    handler.scope_mut().set_value("total", 0 as INT);
+    assert_eq!(handler.fire::<_, INT>("query", ())?, 0);
+
+    Ok(())
+}
@@ -4,7 +4,7 @@ use rhai::{Engine, EvalAltResult, RegisterFn, FLOAT};
 const EPSILON: FLOAT = 0.000_000_000_1;
 
 #[test]
-fn test_float() -> Result<(), EvalAltResult> {
+fn test_float() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(
@@ -22,7 +22,7 @@ fn test_float() -> Result<(), EvalAltResult> {
 
 #[test]
 #[cfg(not(feature = "no_object"))]
-fn struct_with_float() -> Result<(), EvalAltResult> {
+fn struct_with_float() -> Result<(), Box<EvalAltResult>> {
     #[derive(Clone)]
     struct TestStruct {
         x: f64,
@@ -64,3 +64,61 @@ fn struct_with_float() -> Result<(), EvalAltResult> {
 
     Ok(())
 }
+
+#[test]
+fn test_float_precision() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<String>("(0.1 + 0.2).to_string()")?,
+        "0.30000000000000004"
+    );
+
+    engine.set_float_precision(Some(2));
+
+    assert_eq!(engine.eval::<String>("(0.1 + 0.2).to_string()")?, "0.30");
+    assert_eq!(
+        engine.eval::<String>(r#"format("{} {:.4}", 1.0 / 3.0, 0.5)"#)?,
+        "0.33 0.5000"
+    );
+
+    #[cfg(not(feature = "no_stdlib"))]
+    assert_eq!(
+        engine.eval::<String>(r#"let s = "x = " + 2.0 / 3.0; s += 1.5; s + "!""#)?,
+        "x = 0.671.50!"
+    );
+
+    engine.set_float_precision(None);
+
+    assert_eq!(engine.eval::<String>("(0.5).to_string()")?, "0.5");
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_float_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!((engine.eval::<FLOAT>("7.5 % 2.0")? - 1.5).abs() < EPSILON);
+    assert!((engine.eval::<FLOAT>("-7.5 % 2.0")? + 1.5).abs() < EPSILON);
+    assert!((engine.eval::<FLOAT>("hypot(3.0, 4.0)")? - 5.0).abs() < EPSILON);
+    assert!((engine.eval::<FLOAT>("atan2(1.0, -1.0)")? - 135.0).abs() < EPSILON);
+    assert_eq!(engine.eval::<FLOAT>("copysign(2.5, -0.1)")?, -2.5);
+    assert_eq!(engine.eval::<FLOAT>("trunc(-2.7)")?, -2.0);
+    assert!((engine.eval::<FLOAT>("fract(-2.75)")? + 0.75).abs() < EPSILON);
+    assert_eq!(engine.eval::<FLOAT>("signum(-0.5)")?, -1.0);
+    assert_eq!(engine.eval::<FLOAT>("let x = 3.0; x.signum()")?, 1.0);
+    assert!(engine.eval::<bool>("is_nan(sqrt(-1.0))")?);
+    assert!(!engine.eval::<bool>("is_finite(1.0 / 0.0)")?);
+
+    assert_eq!(engine.eval::<FLOAT>("round(2.4)")?, 2.0);
+    assert_eq!(engine.eval::<FLOAT>("round(2.5)")?, 3.0);
+    assert_eq!(engine.eval::<FLOAT>("round(-2.5)")?, -3.0);
+    assert_eq!(engine.eval::<FLOAT>("round_even(2.5)")?, 2.0);
+    assert_eq!(engine.eval::<FLOAT>("round_even(3.5)")?, 4.0);
+    assert_eq!(engine.eval::<FLOAT>("round_even(-2.5)")?, -2.0);
+    assert_eq!(engine.eval::<FLOAT>("round_even(2.6)")?, 3.0);
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use rhai::{Engine, EvalAltResult, RegisterFn, INT};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_fn_alias() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("scale", |x: INT, factor: INT| x * factor);
+    engine.register_fn_alias("multiply", "scale");
+    engine.register_fn_alias("times", "multiply");
+
+    assert_eq!(engine.eval::<INT>("multiply(6, 7)")?, 42);
+    assert_eq!(engine.eval::<INT>("let x = 6; x.multiply(7)")?, 42);
+    assert_eq!(engine.eval::<INT>("times(6, 7)")?, 42);
+
+    // Aliases leading back to themselves are ignored
+    engine.register_fn_alias("scale", "times");
+    assert_eq!(engine.eval::<INT>("scale(6, 7)")?, 42);
+
+    // Functions named like the alias take precedence
+    #[cfg(not(feature = "no_function"))]
+    assert_eq!(
+        engine.eval::<INT>("fn multiply(x, y) { x + y } multiply(6, 7)")?,
+        13
+    );
+    engine.register_fn("multiply", |x: INT| x * 2);
+    assert_eq!(engine.eval::<INT>("multiply(21)")?, 42);
+    assert_eq!(engine.eval::<INT>("multiply(6, 7)")?, 42);
+
+    assert!(matches!(
+        *engine.eval::<INT>(r#"times("x")"#).expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(name, _) if name.starts_with("scale")
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_fn_alias_callback() -> Result<(), Box<EvalAltResult>> {
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+
+    let mut engine = {
+        let warnings = warnings.clone();
+
+        Engine::builder()
+            .configure(|engine| engine.register_fn("area", |w: INT, h: INT| w * h))
+            .with_fn_alias("calc_area", "area")
+            .on_fn_alias(move |alias, name, source, pos| {
+                warnings.lock().unwrap().push(format!(
+                    "{}:{}: '{}' is deprecated, use '{}'",
+                    source.unwrap_or("?"),
+                    pos.line().unwrap(),
+                    alias,
+                    name
+                ))
+            })
+            .build()
+    };
+
+    let mut ast = engine.compile("let a = area(2, 3);\nlet b = calc_area(6, 7);\na + b")?;
+    ast.set_source("shapes.rhai");
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 48);
+    assert_eq!(
+        *warnings.lock().unwrap(),
+        ["shapes.rhai:2: 'calc_area' is deprecated, use 'area'"]
+    );
+
+    Ok(())
+}
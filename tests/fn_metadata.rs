@@ -0,0 +1,103 @@
+#![cfg(not(feature = "no_function"))]
+#![cfg(not(feature = "no_index"))]
+#![cfg(not(feature = "no_object"))]
+use rhai::{AnyExt, Array, Engine, EvalAltResult, Map};
+
+#[cfg(not(feature = "no_stdlib"))]
+use rhai::INT;
+
+#[test]
+fn test_fn_metadata_list() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let list = engine.eval::<Array>(
+        r#"
+            fn foo(x, y) { x + y }
+            fn bar() { 42 }
+
+            get_fn_metadata_list()
+        "#,
+    )?;
+
+    let mut list: Vec<_> = list
+        .into_iter()
+        .map(|f| {
+            let mut f = f.cast::<Map>();
+            let name = f.remove("name").unwrap().cast::<String>();
+            let params = f
+                .remove("params")
+                .unwrap()
+                .cast::<Array>()
+                .into_iter()
+                .map(|p| p.cast::<String>())
+                .collect::<Vec<_>>();
+            let source = f.remove("source").unwrap().cast::<String>();
+            (name, params, source)
+        })
+        .collect();
+
+    list.sort();
+
+    assert_eq!(
+        list,
+        vec![
+            ("bar".to_string(), vec![], "".to_string()),
+            (
+                "foo".to_string(),
+                vec!["x".to_string(), "y".to_string()],
+                "".to_string()
+            ),
+        ]
+    );
+
+    #[cfg(not(feature = "no_stdlib"))]
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn foo(x, y) { x + y }
+                let names = "";
+                for f in get_fn_metadata_list() { names += f.name + "/" + f.params.len(); }
+                if names == "foo/2" { 42 } else { 0 }
+            "#
+        )?,
+        42
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_module"))]
+fn test_fn_metadata_list_modules() -> Result<(), Box<EvalAltResult>> {
+    use rhai::StaticModuleResolver;
+
+    let mut engine = Engine::new();
+    let mut resolver = StaticModuleResolver::new();
+
+    resolver.insert("math", engine.compile("fn double(x) { x * 2 }")?);
+    engine.set_module_resolver(Some(resolver));
+
+    assert_eq!(
+        engine.eval::<String>(
+            r#"
+                import "math" as m;
+                let f = get_fn_metadata_list()[0];
+                f.name + "(" + f.params[0] + ") from " + f.source
+            "#
+        )?,
+        "m::double(x) from math"
+    );
+
+    assert_eq!(
+        engine.eval::<String>(
+            r#"
+                import double as twice from "math";
+                let f = get_fn_metadata_list()[0];
+                f.name + " from " + f.source
+            "#
+        )?,
+        "twice from math"
+    );
+
+    Ok(())
+}
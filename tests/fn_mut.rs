@@ -0,0 +1,65 @@
+use rhai::{Engine, EvalAltResult, RegisterFnMut, INT};
+
+#[test]
+fn test_fn_mut() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Captured state is mutated without a RefCell
+    let mut count = 0;
+    engine.register_fn_mut("next_id", move || {
+        count += 1;
+        count as INT
+    });
+
+    let mut log = Vec::new();
+    engine.register_fn_mut("log", move |msg: String| {
+        log.push(msg);
+        log.len() as INT
+    });
+
+    // The first parameter can be passed by reference, as with `register_fn`
+    engine.register_fn_mut("bump", |x: &mut INT| *x += 1);
+
+    assert_eq!(engine.eval::<INT>("next_id(); next_id(); next_id()")?, 3);
+    assert_eq!(engine.eval::<INT>("next_id()")?, 4);
+    assert_eq!(engine.eval::<INT>(r#"log("a"); log("b")"#)?, 2);
+    assert_eq!(engine.eval::<INT>("let x = 41; x.bump(); x")?, 42);
+
+    assert!(matches!(
+        *engine.eval::<INT>("next_id(1)").expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f.starts_with("next_id")
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "sync")]
+fn test_fn_mut_threads() {
+    use std::sync::Arc;
+
+    let mut engine = Engine::new();
+
+    let mut count = 0;
+    engine.register_fn_mut("next_id", move || {
+        count += 1;
+        count as INT
+    });
+
+    // Calls from different threads take turns
+    let engine = Arc::new(engine);
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let engine = engine.clone();
+            std::thread::spawn(move || {
+                (0..100)
+                    .map(|_| engine.duplicate().eval::<INT>("next_id()").unwrap())
+                    .max()
+                    .unwrap()
+            })
+        })
+        .collect();
+
+    let max = handles.into_iter().map(|h| h.join().unwrap()).max();
+    assert_eq!(max, Some(400));
+}
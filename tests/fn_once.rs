@@ -0,0 +1,39 @@
+use rhai::{Engine, EvalAltResult, RegisterFnOnce, INT};
+use std::sync::mpsc::channel;
+
+#[test]
+fn test_fn_once() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // A continuation which hands the result of the script back to the waiting host operation
+    let (sender, receiver) = channel();
+    engine.register_fn_once("resume", move |x: INT| sender.send(x).is_ok());
+
+    // Calls with the wrong types of arguments do not consume the function
+    assert!(matches!(
+        *engine.eval::<bool>(r#"resume("x")"#).expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f.starts_with("resume")
+    ));
+
+    assert!(engine.eval::<bool>("resume(40 + 2)")?);
+    assert_eq!(receiver.recv().unwrap(), 42);
+
+    // The function can only be called once
+    assert!(matches!(
+        *engine.eval::<bool>("resume(1)").expect_err("should error"),
+        EvalAltResult::ErrorFunctionConsumed(f, _) if f == "resume"
+    ));
+    assert!(receiver.try_recv().is_err());
+
+    // Registering it again makes it callable again
+    engine.register_fn_once("resume", |x: INT| x > 0);
+    assert!(engine.eval::<bool>("resume(1)")?);
+
+    let err = engine.eval::<bool>("resume(1)").expect_err("should error");
+    assert_eq!(
+        err.to_string(),
+        "Single-use function already called: 'resume' (line 1, position 1)"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use rhai::{Engine, EvalAltResult, Position, INT};
+
+#[test]
+fn test_fragment_positions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Fragment starting in the middle of a line, e.g. an XML attribute
+    let document = "<item value=\"40 + 2\" />\n<item check=\"let x = 1;\nx +* 2\" />";
+
+    let ast = engine.compile_fragment("items.xml", Position::new(1, 14), "40 + 2")?;
+    assert_eq!(ast.source(), Some("items.xml"));
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    // Only the first line is offset by the column
+    let err = engine
+        .compile_fragment("items.xml", Position::new(2, 14), "let x = 1;\nx +* 2")
+        .expect_err("should error");
+    assert_eq!(err.position(), Position::new(3, 4));
+    assert!(err.to_pretty_string(document).contains("3 | x +* 2"));
+
+    let ast = engine.compile_fragment("items.xml", Position::new(2, 14), "let x = 1;\nfoo(x)")?;
+    let err = engine.eval_ast::<INT>(&ast).expect_err("should error");
+    assert_eq!(err.position(), Position::new(3, 1));
+
+    let err = engine
+        .compile_fragment("items.xml", Position::new(2, 14), "let x = ;")
+        .expect_err("should error");
+    assert_eq!(err.position(), Position::new(2, 22));
+
+    Ok(())
+}
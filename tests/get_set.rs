@@ -3,7 +3,7 @@
 use rhai::{Engine, EvalAltResult, RegisterFn, INT};
 
 #[test]
-fn test_get_set() -> Result<(), EvalAltResult> {
+fn test_get_set() -> Result<(), Box<EvalAltResult>> {
     #[derive(Clone)]
     struct TestStruct {
         x: INT,
@@ -36,7 +36,7 @@ fn test_get_set() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_big_get_set() -> Result<(), EvalAltResult> {
+fn test_big_get_set() -> Result<(), Box<EvalAltResult>> {
     #[derive(Clone)]
     struct TestChild {
         x: INT,
@@ -99,3 +99,84 @@ fn test_big_get_set() -> Result<(), EvalAltResult> {
 
     Ok(())
 }
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+fn test_get_set_chain() -> Result<(), Box<EvalAltResult>> {
+    #[derive(Clone)]
+    struct TestItem {
+        field: INT,
+    }
+
+    #[derive(Clone)]
+    struct TestInner {
+        grid: rhai::Array,
+    }
+
+    #[derive(Clone)]
+    struct TestObj {
+        inner: TestInner,
+    }
+
+    let mut engine = Engine::new();
+
+    engine.register_type::<TestItem>();
+    engine.register_type::<TestInner>();
+    engine.register_type::<TestObj>();
+
+    engine.register_get_set(
+        "field",
+        |x: &mut TestItem| x.field,
+        |x: &mut TestItem, v: INT| x.field = v,
+    );
+    engine.register_get_set(
+        "grid",
+        |x: &mut TestInner| x.grid.clone(),
+        |x: &mut TestInner, v: rhai::Array| x.grid = v,
+    );
+    engine.register_get_set(
+        "inner",
+        |x: &mut TestObj| x.inner.clone(),
+        |x: &mut TestObj, v: TestInner| x.inner = v,
+    );
+    engine.register_get("copy", |x: &mut TestObj| x.inner.clone());
+
+    engine.register_fn("new_item", |field: INT| TestItem { field });
+    engine.register_fn("new_obj", || TestObj {
+        inner: TestInner {
+            grid: Vec::new().into(),
+        },
+    });
+
+    let script =
+        "let obj = new_obj(); obj.inner.grid = [[new_item(1), new_item(2)], [new_item(3)]];";
+
+    assert_eq!(
+        engine.eval::<INT>(&format!(
+            "{} obj.inner.grid[0][1].field += 40; obj.inner.grid[0][1].field",
+            script
+        ))?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>(&format!(
+            "{} obj.inner.grid[1][0] = new_item(42); obj.inner.grid[1][0].field",
+            script
+        ))?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>(&format!(
+            "{} let a = [obj]; a[0].inner.grid[1][0].field *= 14; a[0].inner.grid[1][0].field",
+            script
+        ))?,
+        42
+    );
+
+    // Chains through properties without setters cannot be assigned to
+    assert!(engine
+        .eval::<()>(&format!("{} obj.copy.grid[0][0].field = 42;", script))
+        .is_err());
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "hash")]
+use rhai::{Engine, EvalAltResult};
+
+#[cfg(not(feature = "no_index"))]
+use rhai::Blob;
+
+#[test]
+fn test_hash() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<String>(r#"sha256("abc")"#)?,
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"sha1("abc")"#)?,
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"md5("abc")"#)?,
+        "900150983cd24fb0d6963f7d28e17f72"
+    );
+    assert_eq!(engine.eval::<String>(r#"crc32("123456789")"#)?, "cbf43926");
+    assert_eq!(
+        engine.eval::<String>(r#"md5("")"#)?,
+        "d41d8cd98f00b204e9800998ecf8427e"
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+fn test_hash_blobs() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<String>(r#"let x = "abc".to_blob(); x.sha1()"#)?,
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+    assert_eq!(
+        engine.eval::<Blob>(r#"crc32_blob("123456789")"#)?,
+        vec![0xcb, 0xf4, 0x39, 0x26]
+    );
+    assert_eq!(engine.eval::<Blob>(r#"sha256_blob("abc")"#)?.len(), 32);
+    assert!(engine
+        .eval::<bool>(r#"let x = blob(3, 7); let h = x.md5_blob(); h.to_hex() == x.md5()"#)?);
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+#![cfg(not(feature = "unchecked"))]
+#![cfg(not(feature = "no_stdlib"))]
+use rhai::{Engine, EvalAltResult, RegisterFn, INT};
+
+#[test]
+fn test_int_conversions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(engine.eval::<u8>("to_u8(255)")?, 255);
+    assert_eq!(engine.eval::<i8>("to_i8(-128)")?, -128);
+    assert_eq!(engine.eval::<u16>("to_u16(65535)")?, 65535);
+    assert_eq!(engine.eval::<i32>("to_i32(-42)")?, -42);
+    assert_eq!(engine.eval::<u32>("to_u32(42)")?, 42);
+    assert_eq!(engine.eval::<i64>("to_i64(42)")?, 42);
+    assert_eq!(engine.eval::<u64>("to_u64(42)")?, 42);
+    assert_eq!(engine.eval::<String>("type_of(to_u8(1))")?, "u8");
+
+    #[cfg(not(feature = "only_i32"))]
+    #[cfg(not(feature = "only_i64"))]
+    {
+        assert_eq!(engine.eval::<i16>("to_i16(to_u8(200))")?, 200);
+        assert_eq!(engine.eval::<INT>("to_int(to_u16(300))")?, 300);
+    }
+
+    for script in &["to_u8(256)", "to_u8(-1)", "to_i8(128)", "to_u64(-1)"] {
+        assert!(matches!(
+            *engine.eval::<()>(script).expect_err("expects error"),
+            EvalAltResult::ErrorArithmetic(ref msg, Some(ref op), _)
+                if msg.starts_with("Integer overflow") && script.starts_with(op.operator.as_str())
+        ));
+    }
+
+    #[cfg(not(feature = "only_i32"))]
+    #[cfg(not(feature = "only_i64"))]
+    assert!(matches!(
+        *engine
+            .eval::<()>("to_i8(to_u8(200))")
+            .expect_err("expects error"),
+        EvalAltResult::ErrorArithmetic(_, _, _)
+    ));
+
+    // Pass values to a function registered over a narrower integer type
+    engine.register_fn("brightness", |level: u8| level as INT * 2);
+
+    assert_eq!(engine.eval::<INT>("brightness(to_u8(100))")?, 200);
+    assert!(engine.eval::<INT>("brightness(100)").is_err());
+
+    Ok(())
+}
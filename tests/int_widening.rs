@@ -0,0 +1,68 @@
+use rhai::{Engine, EvalAltResult, RegisterFn, INT};
+
+#[test]
+fn test_int_widening() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("brightness", |level: u8| level as INT * 2);
+    engine.register_fn("offset", |x: i16, y: u32| x as INT + y as INT);
+    engine.register_fn("wide", |x: i64| x + 1);
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>("brightness(100)")
+            .expect_err("expects error"),
+        EvalAltResult::ErrorFunctionNotFound(_, _)
+    ));
+
+    engine.enable_int_widening(true);
+
+    assert_eq!(engine.eval::<INT>("brightness(100)")?, 200);
+    assert_eq!(engine.eval::<INT>("offset(-5, 10)")?, 5);
+    assert_eq!(engine.eval::<i64>("wide(41)")?, 42);
+
+    // Functions registered after a call are found too
+    engine.register_fn("dim", |level: u8| level as INT / 2);
+    assert_eq!(engine.eval::<INT>("dim(100)")?, 50);
+
+    // Conversions losing data are not done
+    assert!(matches!(
+        *engine
+            .eval::<INT>("brightness(256)")
+            .expect_err("expects error"),
+        EvalAltResult::ErrorFunctionNotFound(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>("offset(1, -1)")
+            .expect_err("expects error"),
+        EvalAltResult::ErrorFunctionNotFound(_, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_int_widening_overloads() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.enable_int_widening(true);
+
+    engine.register_fn("kind", |_: u8| "u8".to_string());
+    engine.register_fn("kind", |_: i32| "i32".to_string());
+    engine.register_fn("kind", |_: u64| "u64".to_string());
+
+    // Prefer the widest parameter type that can hold the value
+    #[cfg(not(feature = "only_i32"))]
+    assert_eq!(engine.eval::<String>("kind(42)")?, "u64");
+    assert_eq!(engine.eval::<String>("kind(-42)")?, "i32");
+
+    // Prefer widening a narrow value over narrowing a wide one
+    engine.register_fn("to_byte", |x: INT| x as u8);
+    engine.register_fn("add", |x: u8, y: u8| x as INT + y as INT + 1000);
+    engine.register_fn("add", |x: INT, y: INT| x + y);
+
+    assert_eq!(engine.eval::<INT>("add(to_byte(1), 2)")?, 3);
+    assert_eq!(engine.eval::<INT>("add(to_byte(1), to_byte(2))")?, 1003);
+
+    Ok(())
+}
@@ -1,9 +1,9 @@
 #![cfg(not(feature = "no_function"))]
 
-use rhai::{Engine, EvalAltResult, INT};
+use rhai::{Engine, EvalAltResult, ParseErrorType, INT};
 
 #[test]
-fn test_internal_fn() -> Result<(), EvalAltResult> {
+fn test_internal_fn() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("fn addme(a, b) { a+b } addme(3, 4)")?, 7);
@@ -13,7 +13,7 @@ fn test_internal_fn() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_big_internal_fn() -> Result<(), EvalAltResult> {
+fn test_big_internal_fn() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(
@@ -32,7 +32,7 @@ fn test_big_internal_fn() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_internal_fn_overloading() -> Result<(), EvalAltResult> {
+fn test_internal_fn_overloading() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(
@@ -52,3 +52,207 @@ fn test_internal_fn_overloading() -> Result<(), EvalAltResult> {
 
     Ok(())
 }
+
+#[test]
+fn test_internal_fn_overloading_merged() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let ast1 = engine.compile("fn foo(x) { x } fn zzz() { 1 }")?;
+    let ast2 = engine.compile("fn foo(x, y) { x + y } fn aaa(x) { 2 }")?;
+    let ast3 = engine.compile("foo(1) * 10 + foo(2, 3) + zzz() + aaa(0)")?;
+
+    assert_eq!(engine.eval_ast::<INT>(&ast1.merge(&ast2).merge(&ast3))?, 18);
+    assert_eq!(engine.eval_ast::<INT>(&ast2.merge(&ast1).merge(&ast3))?, 18);
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn foo(x) { x }
+                eval("fn foo(x, y) { x + y } fn bar() { 100 }");
+                foo(1) * 10 + foo(2, 3) + bar()
+            "#
+        )?,
+        115
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_internal_fn_tail_call() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn sum(n, acc) {
+                    if n == 0 { return acc; }
+                    sum(n - 1, acc + n)
+                }
+
+                sum(10000, 0)
+    "#
+        )?,
+        50005000
+    );
+
+    assert!(engine.eval::<bool>(
+        r#"
+            fn is_even(n) { if n == 0 { true } else { is_odd(n - 1) } }
+            fn is_odd(n) { if n == 0 { false } else { return is_even(n - 1); } }
+
+            is_even(10000)
+    "#
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_internal_fn_stack_overflow() {
+    let mut engine = Engine::new();
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>("fn sum(n) { if n == 0 { 0 } else { n + sum(n - 1) } } sum(10000)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorStackOverflow(_)
+    ));
+
+    engine.set_max_call_levels(5);
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>("fn sum(n) { if n == 0 { 0 } else { n + sum(n - 1) } } sum(10)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorStackOverflow(_)
+    ));
+}
+
+#[test]
+#[cfg(not(feature = "jit"))] // JIT-compiled functions use little native stack
+fn test_internal_fn_native_stack_overflow() {
+    // Each call is nested deep inside an expression, using a lot of native stack per call level
+    let script = format!(
+        "fn f(n) {{ if n == 0 {{ 0 }} else {{ {}f(n - 1){} }} }} f(60)",
+        "n + (".repeat(40),
+        ")".repeat(40)
+    );
+
+    // Run on a thread with a small stack, which would crash if the native stack is exhausted
+    let is_stack_overflow = std::thread::Builder::new()
+        .stack_size(2 * 1024 * 1024)
+        .spawn(move || {
+            let mut engine = Engine::new();
+
+            match engine.eval::<INT>(&script) {
+                Err(err) => matches!(*err, EvalAltResult::ErrorStackOverflow(_)),
+                Ok(_) => false,
+            }
+        })
+        .expect("thread should spawn")
+        .join()
+        .expect("thread should not crash");
+
+    assert!(is_stack_overflow);
+
+    let mut engine = Engine::new();
+    engine.set_max_stack_size(16 * 1024);
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>("fn sum(n) { if n == 0 { 0 } else { n + sum(n - 1) } } sum(50)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorStackOverflow(_)
+    ));
+
+    engine.set_max_stack_size(1024 * 1024);
+
+    assert_eq!(
+        engine
+            .eval::<INT>("fn sum(n) { if n == 0 { 0 } else { n + sum(n - 1) } } sum(20)")
+            .expect("should succeed"),
+        210
+    );
+}
+
+#[test]
+fn test_internal_fn_nested() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn sum_of_squares(x, y) {
+                    fn square(x) { x * x }
+
+                    square(x) + square(y)
+                }
+
+                sum_of_squares(3, 4)
+            "#
+        )?,
+        25
+    );
+
+    // Nested functions can be called before their definitions, call one another (and themselves),
+    // and shadow functions at global level
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn helper(x) { x + 1000 }
+
+                fn calc(n) {
+                    let r = helper(n) + n.twice();
+
+                    fn helper(x) { fact(x) }
+                    fn fact(x) { if x <= 1 { 1 } else { x * fact(x - 1) } }
+                    fn twice(x) {
+                        fn add(a, b) { a + b }
+                        add(x, x) + helper(0)
+                    }
+
+                    r
+                }
+
+                calc(4) + helper(0)
+            "#
+        )?,
+        1033
+    );
+
+    // Nested functions are not visible outside the function defining them
+    assert!(matches!(
+        *engine
+            .eval::<INT>("fn outer() { fn inner() { 42 } inner() } outer() + inner()")
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f == "inner ()"
+    ));
+
+    // Overloads of a function can have nested functions of the same name
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn f(x) { fn g() { 1 } g() }
+                fn f(x, y) { fn g() { 2 } g() }
+                f(0) * 10 + f(0, 0)
+            "#
+        )?,
+        12
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_internal_fn_nested_in_block() {
+    let engine = Engine::new();
+
+    assert_eq!(
+        *engine
+            .compile("fn outer() { if true { fn inner() { 42 } } }")
+            .expect_err("should error")
+            .error_type(),
+        ParseErrorType::WrongFnDefinition
+    );
+}
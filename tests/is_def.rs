@@ -0,0 +1,61 @@
+use rhai::{Engine, EvalAltResult, RegisterFn, Scope, INT};
+
+#[test]
+fn test_is_def_var() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut scope = Scope::new();
+
+    scope.push("x", 42 as INT);
+
+    assert!(engine.eval_with_scope::<bool>(&mut scope, r#"is_def_var("x")"#)?);
+    assert!(!engine.eval_with_scope::<bool>(&mut scope, r#"is_def_var("y")"#)?);
+    assert!(engine.eval_with_scope::<bool>(&mut scope, r#"let y = 1; is_def_var("y")"#)?);
+    assert!(!engine.eval::<bool>(r#"{ let z = 1; } is_def_var("z")"#)?);
+
+    assert_eq!(
+        engine.eval::<INT>(r#"let size = if is_def_var("config") { config } else { 10 }; size"#)?,
+        10
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_is_def_fn() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("hello", |x: INT| x + 1);
+
+    assert!(engine.eval::<bool>(r#"is_def_fn("hello", 1)"#)?);
+    assert!(!engine.eval::<bool>(r#"is_def_fn("hello", 2)"#)?);
+    assert!(!engine.eval::<bool>(r#"is_def_fn("world", 0)"#)?);
+    assert!(!engine.eval::<bool>(r#"is_def_fn("hello", -1)"#)?);
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        assert!(engine.eval::<bool>(r#"fn foo(x, y) { x + y } is_def_fn("foo", 2)"#)?);
+        assert!(!engine.eval::<bool>(r#"fn foo(x, y) { x + y } is_def_fn("foo", 1)"#)?);
+    }
+
+    assert!(matches!(
+        *engine
+            .eval::<bool>(r#"is_def_fn("hello", "one")"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorMismatchOutputType(_, ref t, _) if t == "string"
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_is_def_override() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>(r#"fn is_def_var(x) { 42 } is_def_var("x")"#)?,
+        42
+    );
+
+    Ok(())
+}
@@ -0,0 +1,231 @@
+#![cfg(feature = "jit")]
+#![cfg(not(feature = "no_function"))]
+
+use rhai::{Dynamic, Engine, EvalAltResult, RegisterFn, INT};
+
+#[cfg(not(feature = "no_float"))]
+use rhai::FLOAT;
+
+#[test]
+fn test_jit_int() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }
+                fib(20)
+            "#
+        )?,
+        6765
+    );
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn sum_to(n) {
+                    let total = 0;
+                    let i = 0;
+                    while i < n {
+                        i += 1;
+                        if i % 3 == 0 { continue; }
+                        total += i;
+                        if total > 1000 && i > 50 { break; }
+                    }
+                    total
+                }
+                sum_to(10) + sum_to(100)
+            "#
+        )?,
+        37 + 1027
+    );
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn grid(n) {
+                    let total = 0;
+                    let x = 0;
+                    'rows: while x < n {
+                        x += 1;
+                        let y = 0;
+                        loop {
+                            y += 1;
+                            if y > x { continue 'rows; }
+                            if x * y > 20 { break 'rows; }
+                            total += y;
+                        }
+                    }
+                    total
+                }
+                grid(10)
+            "#
+        )?,
+        1 + 3 + 6 + 10 + 10
+    );
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn gcd(a, b) { if b == 0 { return a; } gcd(b, a % b) }
+                gcd(1071, 462)
+            "#
+        )?,
+        21
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_float"))]
+fn test_jit_float() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<FLOAT>(
+            r#"
+                fn halve(x, n) { if n == 0 || x < 1.0 { x } else { halve(x / 2.0, n - 1) } }
+                halve(100.0, 3)
+            "#
+        )?,
+        12.5
+    );
+
+    assert!(engine.eval::<bool>("fn is_neg(x) { -x > 0.0 } is_neg(-1.5)")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_jit_fallback() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Unsupported types are run by the interpreter
+    assert_eq!(
+        engine.eval::<String>(r#"fn add(x, y) { x + y } add(1, 2); add("a", "b")"#)?,
+        "ab"
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"fn f(x) { let s = "hello"; x + 5 } f(1)"#)?,
+        6
+    );
+
+    // Errors are reported by the interpreter
+    #[cfg(not(feature = "unchecked"))]
+    assert!(matches!(
+        *engine
+            .eval::<INT>("fn div(x, y) { x / y } div(1, 0)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorArithmetic(_, _, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>("fn deep(n) { if n == 0 { 0 } else { 1 + deep(n - 1) } } deep(1000)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorStackOverflow(_)
+    ));
+
+    #[cfg(not(feature = "unchecked"))]
+    #[cfg(not(feature = "only_i32"))]
+    assert!(matches!(
+        *engine
+            .eval::<INT>("fn double(x) { x * 2 } double(9223372036854775807)")
+            .expect_err("should error"),
+        EvalAltResult::ErrorArithmetic(_, _, _)
+    ));
+
+    // Tail calls are turned into loops
+    assert_eq!(
+        engine.eval::<INT>(
+            "fn count(n, acc) { if n == 0 { acc } else { count(n - 1, acc + 1) } } count(100000, 0)"
+        )?,
+        100000
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_jit_many_scripts() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Compiled functions of scripts which are gone are removed, and their code is freed
+    for n in 0..200 {
+        let script = format!("fn add{}(x) {{ x + {} }} add{}(1)", n % 7, n, n % 7);
+        assert_eq!(engine.eval::<INT>(&script)?, n + 1);
+    }
+
+    // Compiled functions of a script which is kept are still used
+    let ast = engine.compile("fn sq(x) { x * x } sq(3)")?;
+
+    for _ in 0..10 {
+        assert_eq!(engine.eval_ast::<INT>(&ast)?, 9);
+        assert_eq!(engine.eval::<INT>("fn sq(x) { x * x + 1 } sq(3)")?, 10);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_jit_custom_operators() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let ast = engine.compile("fn add(x, y) { x + y } add(40, 2)")?;
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    // Operators registered by the host replace the built-in ones, also in compiled functions
+    engine.register_fn("+", |x: INT, y: INT| x * y);
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 80);
+    assert_eq!(engine.eval::<INT>("fn add(x, y) { x + y } add(3, 4)")?, 12);
+
+    // So do op-assignments, which are used in place of the operators
+    engine.register_fn("-=", |x: &mut INT, y: INT| *x *= y);
+    assert_eq!(
+        engine.eval::<INT>("fn sub(x, y) { x -= y; x } sub(3, 4)")?,
+        12
+    );
+
+    #[cfg(not(feature = "no_float"))]
+    {
+        engine.register_fn("<", |x: FLOAT, y: FLOAT| x > y);
+        assert!(engine.eval::<bool>("fn less(x, y) { x < y } less(2.0, 1.0)")?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_jit_eval_stats() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Checking variable definitions keeps functions from being compiled
+    let mut interpreter = Engine::new();
+    interpreter.on_def_var(|_, _, _, _| true);
+
+    for script in &[
+        "fn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } } fib(15)",
+        "fn sum(n) { let s = 0; let i = 0; while i < n { i += 1; if i % 3 == 0 { continue; } s += i; } s } sum(50)",
+        "fn f(n) { let i = 0; loop { i += 1; if i > n && true || false { break; } } return i; } f(10) + f(3)",
+        "fn count(n, acc) { if n == 0 { acc } else { count(n - 1, acc + 1) } } count(100, 0)",
+        "fn even(n) { if n == 0 { true } else { odd(n - 1) } } fn odd(n) { if n == 0 { false } else { even(n - 1) } } even(10)",
+        "fn add(x, y) { x + y } add(1, 2); add(\"a\", \"b\"); add(3, 4)",
+        "fn div(x, y) { const z = 1; x / y + z } div(7, 0)",
+    ] {
+        let ast = engine.compile(script)?;
+
+        let result = format!("{:?}", engine.eval_ast::<Dynamic>(&ast));
+        let expected = format!("{:?}", interpreter.eval_ast::<Dynamic>(&ast));
+
+        // Compiled code counts operations and calls just like the interpreter
+        assert_eq!(result, expected, "{}", script);
+        assert_eq!(
+            engine.last_eval_stats(),
+            interpreter.last_eval_stats(),
+            "{}",
+            script
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+#![cfg(not(feature = "no_stdlib"))]
+use rhai::{Any, Engine, EvalAltResult, RegisterFn, INT};
+
+#[test]
+fn test_to_json() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(engine.eval::<String>("to_json(42)")?, "42");
+    assert_eq!(engine.eval::<String>("to_json(true)")?, "true");
+    assert_eq!(engine.eval::<String>("to_json(())")?, "null");
+    assert_eq!(engine.eval::<String>(r#""a\nb".to_json()"#)?, r#""a\nb""#);
+    assert_eq!(engine.eval::<String>("to_json('x')")?, r#""x""#);
+
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(
+        engine.eval::<String>(r#"[1, "two", [false, ()]].to_json()"#)?,
+        r#"[1,"two",[false,null]]"#
+    );
+
+    #[cfg(not(feature = "no_object"))]
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(
+        engine.eval::<String>("[#{b: 2, a: 1}].to_json()")?,
+        r#"[{"a":1,"b":2}]"#
+    );
+
+    #[cfg(not(feature = "no_object"))]
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(
+        engine.eval::<String>(r#"[[], #{}, #{x: [1, #{"y z": "w"}], a: ()}, []].to_json()"#)?,
+        r#"[[],{},{"a":null,"x":[1,{"y z":"w"}]},[]]"#
+    );
+
+    #[cfg(not(feature = "no_float"))]
+    assert_eq!(engine.eval::<String>("(1.0 / 0.0).to_json()")?, "null");
+
+    Ok(())
+}
+
+#[test]
+fn test_to_json_api() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(engine.to_json(&(42 as INT).into_dynamic())?, "42");
+    assert_eq!(
+        engine.to_json(&"hello".to_string().into_dynamic())?,
+        r#""hello""#
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_object"))]
+fn test_to_json_custom_type() -> Result<(), Box<EvalAltResult>> {
+    #[derive(Clone)]
+    struct TestStruct;
+
+    let mut engine = Engine::new();
+    engine.register_type_with_name::<TestStruct>("TestStruct");
+    engine.register_fn("new_ts", || TestStruct);
+
+    assert!(matches!(
+        *engine.to_json(&TestStruct.into_dynamic()).expect_err("should error"),
+        EvalAltResult::ErrorRuntime(msg, _) if msg == "Cannot convert 'TestStruct' to JSON"
+    ));
+
+    #[cfg(not(feature = "no_index"))]
+    {
+        let value = engine.eval::<rhai::Array>("[1, new_ts()]")?.into_dynamic();
+
+        assert!(matches!(
+            *engine.to_json(&value).expect_err("should error"),
+            EvalAltResult::ErrorRuntime(msg, _) if msg == "Cannot convert 'TestStruct' to JSON"
+        ));
+
+        assert!(matches!(
+            *engine.eval::<String>("[1, new_ts()].to_json()").expect_err("should error"),
+            EvalAltResult::ErrorRuntime(msg, _) if msg == "Cannot convert 'TestStruct' to JSON"
+        ));
+    }
+
+    assert!(matches!(
+        *engine.eval::<String>("#{ x: new_ts() }.to_json()").expect_err("should error"),
+        EvalAltResult::ErrorRuntime(msg, _) if msg == "Cannot convert 'TestStruct' to JSON"
+    ));
+
+    assert!(matches!(
+        *engine.eval::<String>("new_ts().to_json()").expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f.starts_with("to_json")
+    ));
+
+    Ok(())
+}
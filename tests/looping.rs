@@ -1,7 +1,7 @@
 use rhai::{Engine, EvalAltResult, INT};
 
 #[test]
-fn test_loop() -> Result<(), EvalAltResult> {
+fn test_loop() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(
@@ -28,3 +28,67 @@ fn test_loop() -> Result<(), EvalAltResult> {
 
     Ok(())
 }
+
+#[test]
+fn test_loop_labels() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r"
+                let found = 0;
+
+                'outer: for x in range(0, 10) {
+                    for y in range(0, 10) {
+                        if x * y == 42 {
+                            found = x * 100 + y;
+                            break 'outer;
+                        }
+                    }
+                }
+
+                found
+            "
+        )?,
+        607
+    );
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r"
+                let count = 0;
+                let x = 0;
+
+                'rows: while x < 5 {
+                    x += 1;
+                    let y = 0;
+
+                    'cols: loop {
+                        y += 1;
+                        if y > x { continue 'rows; }
+                        if y == 3 { break 'cols; }
+                        count += 1;
+                    }
+                }
+
+                count
+            "
+        )?,
+        1 + 2 + 2 + 2 + 2
+    );
+
+    // Single-letter labels are not character literals
+    assert_eq!(
+        engine.eval::<INT>("let x = 0; 'a: loop { 'b': loop { x += 1; break 'a; } } x")?,
+        1
+    );
+    assert_eq!(engine.eval::<char>("let c = 'a'; c")?, 'a');
+
+    assert!(engine.compile("'outer: loop { break 'inner; }").is_err());
+    assert!(engine.compile("'outer: let x = 42;").is_err());
+    assert!(engine
+        .compile("loop { 'outer: loop {} break 'outer; }")
+        .is_err());
+
+    Ok(())
+}
@@ -3,7 +3,7 @@
 use rhai::{AnyExt, Engine, EvalAltResult, Map, INT};
 
 #[test]
-fn test_map_indexing() -> Result<(), EvalAltResult> {
+fn test_map_indexing() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     #[cfg(not(feature = "no_index"))]
@@ -70,7 +70,7 @@ fn test_map_indexing() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_map_assign() -> Result<(), EvalAltResult> {
+fn test_map_assign() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     let x = engine.eval::<Map>(r#"let x = #{a: 1, b: true, "c$": "hello"}; x"#)?;
@@ -86,7 +86,7 @@ fn test_map_assign() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_map_return() -> Result<(), EvalAltResult> {
+fn test_map_return() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     let x = engine.eval::<Map>(r#"#{a: 1, b: true, "c$": "hello"}"#)?;
@@ -102,7 +102,8 @@ fn test_map_return() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_map_for() -> Result<(), EvalAltResult> {
+#[cfg(not(feature = "no_index"))]
+fn test_map_for() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(
@@ -123,3 +124,182 @@ fn test_map_for() -> Result<(), EvalAltResult> {
 
     Ok(())
 }
+
+#[test]
+fn test_map_order() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let map = engine.eval::<Map>("let x = #{z: 1, b: 2, y: 3}; x.a = 4; x")?;
+    assert_eq!(
+        map.keys().cloned().collect::<Vec<_>>(),
+        ["a", "b", "y", "z"]
+    );
+
+    #[cfg(not(feature = "no_index"))]
+    #[cfg(not(feature = "no_stdlib"))]
+    assert_eq!(
+        engine.eval::<String>(
+            r#"
+                let map = #{z: 1, b: 2, y: 3};
+                map["a"] = 4;
+                let s = "";
+
+                for key in keys(map) {
+                    s += key;
+                }
+                for value in values(map) {
+                    s += value;
+                }
+
+                s
+        "#
+        )?,
+        "abyz4231"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let x = #{z: 1, a: "x"}; x.to_string()"#)?,
+        engine.eval::<String>(r#"let x = #{a: "x", z: 1}; x.to_string()"#)?
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_map_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!(engine.eval::<bool>(r#"let x = #{a: 1, b: 2}; x.contains("a")"#)?);
+    assert!(!engine.eval::<bool>(r#"let x = #{a: 1, b: 2}; x.contains("z")"#)?);
+
+    assert_eq!(
+        engine.eval::<INT>(r#"let x = #{a: 1, b: 2}; let y = x.remove("b"); y + x.len()"#)?,
+        3
+    );
+    assert_eq!(
+        engine.eval::<()>(r#"let x = #{a: 1, b: 2}; x.remove("z")"#)?,
+        ()
+    );
+
+    let map = engine.eval::<Map>(r#"let x = #{a: 1, b: 2}; x.merge(#{b: 42, c: 3}); x"#)?;
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get("b").cloned().unwrap().cast::<INT>(), 42);
+
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(
+        engine.eval::<String>(r#"#{b: [1, true, ()], a: "x\ty", c: #{d: 'z'}}.to_json()"#)?,
+        r#"{"a":"x\ty","b":[1,true,null],"c":{"d":"z"}}"#
+    );
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        let map = engine.eval::<Map>(
+            r#"
+                fn small(key, value) { key != "skip" && value < 10 }
+                let x = #{a: 1, b: 20, c: 3, skip: 0};
+                x.filter("small")
+            "#,
+        )?;
+        assert_eq!(map.len(), 2);
+        assert!(map.contains_key("a") && map.contains_key("c"));
+
+        assert!(matches!(
+            *engine
+                .eval::<Map>(r#"fn bad(k, v) { 42 } #{a: 1}.filter("bad")"#)
+                .expect_err("should error"),
+            EvalAltResult::ErrorMismatchOutputType(_, t, _) if t == "i64" || t == "i32"
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_map_equality() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    #[cfg(not(feature = "no_index"))]
+    {
+        assert!(engine.eval::<bool>("#{a: 1, b: #{c: [2, 3]}} == #{b: #{c: [2, 3]}, a: 1}")?);
+        assert!(engine.eval::<bool>("#{a: 1, b: #{c: [2, 3]}} != #{a: 1, b: #{c: [2]}}")?);
+    }
+    assert!(engine.eval::<bool>("#{a: 1, b: #{c: 2}} == #{b: #{c: 2}, a: 1}")?);
+    assert!(engine.eval::<bool>("#{a: 1} != #{a: 1, b: 2}")?);
+    assert!(engine.eval::<bool>("#{a: 1} != #{b: 1}")?);
+
+    #[cfg(not(feature = "no_index"))]
+    assert!(engine.eval::<bool>("[#{a: 1}, #{b: 2}] == [#{a: 1}, #{b: 2}]")?);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+fn test_map_paths() -> Result<(), Box<EvalAltResult>> {
+    use rhai::{Any, Dynamic};
+
+    let mut engine = Engine::new();
+
+    let mut result = engine
+        .eval::<Map>(r#"#{ a: #{ b: [1, 2, #{ c: 42 }] }, d: "hello" }"#)?
+        .into_dynamic();
+
+    let read_int = |value: &Dynamic, path| {
+        value
+            .read_path(path)
+            .and_then(|v| v.downcast_ref::<INT>())
+            .cloned()
+    };
+
+    assert_eq!(read_int(&result, "a.b[2].c"), Some(42));
+    assert_eq!(read_int(&result, "a.b[0]"), Some(1));
+    assert_eq!(read_int(&result, "a.b[3]"), None);
+    assert_eq!(read_int(&result, "a.x"), None);
+    assert_eq!(read_int(&result, "d.x"), None);
+    assert_eq!(read_int(&result, "a..b"), None);
+    assert_eq!(read_int(&result, "a.b[x]"), None);
+    assert_eq!(read_int(&result, "a.b[1"), None);
+    assert!(result.read_path("").unwrap().is::<Map>());
+
+    assert!(result.write_path("a.b[2].c", (123 as INT).into_dynamic()));
+    assert!(result.write_path("a.b[2].e", (1 as INT).into_dynamic()));
+    assert!(result.write_path("a.b[0]", (0 as INT).into_dynamic()));
+    assert!(!result.write_path("a.b[3]", (0 as INT).into_dynamic()));
+    assert!(!result.write_path("x.y", (0 as INT).into_dynamic()));
+    assert!(!result.write_path("d.x", (0 as INT).into_dynamic()));
+    assert!(!result.write_path("", (0 as INT).into_dynamic()));
+
+    assert_eq!(read_int(&result, "a.b[2].c"), Some(123));
+    assert_eq!(read_int(&result, "a.b[2].e"), Some(1));
+    assert_eq!(read_int(&result, "a.b[0]"), Some(0));
+
+    *result
+        .read_path_mut("a.b[1]")
+        .and_then(|v| v.downcast_mut::<INT>())
+        .unwrap() += 40;
+
+    assert_eq!(read_int(&result, "a.b[1]"), Some(42));
+
+    Ok(())
+}
+
+#[test]
+fn test_map_copy_on_write() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>("let x = #{a: 1, b: 2}; let y = x; y.a = 42; x.a")?,
+        1
+    );
+    assert_eq!(
+        engine.eval::<INT>("let x = #{a: #{b: 2}}; let y = x; y.a.b = 42; x.a.b")?,
+        2
+    );
+    #[cfg(not(feature = "no_stdlib"))]
+    assert_eq!(
+        engine.eval::<INT>("let x = #{a: 1}; let y = x; x.c = 3; y.len()")?,
+        1
+    );
+
+    Ok(())
+}
@@ -1,7 +1,7 @@
-use rhai::{Engine, EvalAltResult, INT};
+use rhai::{AnyExt, Engine, EvalAltResult, INT};
 
 #[test]
-fn test_math() -> Result<(), EvalAltResult> {
+fn test_math() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("1 + 2")?, 3);
@@ -25,77 +25,115 @@ fn test_math() -> Result<(), EvalAltResult> {
         #[cfg(not(feature = "only_i32"))]
         {
             assert!(matches!(
-                engine
+                *engine
                     .eval::<INT>("abs(-9223372036854775808)")
                     .expect_err("expects negation overflow"),
-                EvalAltResult::ErrorArithmetic(_, _)
+                EvalAltResult::ErrorArithmetic(_, _, _)
             ));
             assert!(matches!(
-                engine
+                *engine
                     .eval::<INT>("9223372036854775807 + 1")
                     .expect_err("expects overflow"),
-                EvalAltResult::ErrorArithmetic(_, _)
+                EvalAltResult::ErrorArithmetic(_, _, _)
             ));
             assert!(matches!(
-                engine
+                *engine
                     .eval::<INT>("-9223372036854775808 - 1")
                     .expect_err("expects underflow"),
-                EvalAltResult::ErrorArithmetic(_, _)
+                EvalAltResult::ErrorArithmetic(_, _, _)
             ));
             assert!(matches!(
-                engine
+                *engine
                     .eval::<INT>("9223372036854775807 * 9223372036854775807")
                     .expect_err("expects overflow"),
-                EvalAltResult::ErrorArithmetic(_, _)
+                EvalAltResult::ErrorArithmetic(_, _, _)
             ));
             assert!(matches!(
-                engine
+                *engine
                     .eval::<INT>("9223372036854775807 / 0")
                     .expect_err("expects division by zero"),
-                EvalAltResult::ErrorArithmetic(_, _)
+                EvalAltResult::ErrorArithmetic(_, _, _)
             ));
             assert!(matches!(
-                engine
+                *engine
                     .eval::<INT>("9223372036854775807 % 0")
                     .expect_err("expects division by zero"),
-                EvalAltResult::ErrorArithmetic(_, _)
+                EvalAltResult::ErrorArithmetic(_, _, _)
             ));
         }
 
         #[cfg(feature = "only_i32")]
         {
             assert!(matches!(
-                engine
+                *engine
                     .eval::<INT>("2147483647 + 1")
                     .expect_err("expects overflow"),
-                EvalAltResult::ErrorArithmetic(_, _)
+                EvalAltResult::ErrorArithmetic(_, _, _)
             ));
             assert!(matches!(
-                engine
+                *engine
                     .eval::<INT>("-2147483648 - 1")
                     .expect_err("expects underflow"),
-                EvalAltResult::ErrorArithmetic(_, _)
+                EvalAltResult::ErrorArithmetic(_, _, _)
             ));
             assert!(matches!(
-                engine
+                *engine
                     .eval::<INT>("2147483647 * 2147483647")
                     .expect_err("expects overflow"),
-                EvalAltResult::ErrorArithmetic(_, _)
+                EvalAltResult::ErrorArithmetic(_, _, _)
             ));
             assert!(matches!(
-                engine
+                *engine
                     .eval::<INT>("2147483647 / 0")
                     .expect_err("expects division by zero"),
-                EvalAltResult::ErrorArithmetic(_, _)
+                EvalAltResult::ErrorArithmetic(_, _, _)
             ));
             assert!(matches!(
-                engine
+                *engine
                     .eval::<INT>("2147483647 % 0")
                     .expect_err("expects division by zero"),
-                EvalAltResult::ErrorArithmetic(_, _)
+                EvalAltResult::ErrorArithmetic(_, _, _)
             ));
         }
     }
 
     Ok(())
 }
+
+#[test]
+#[cfg(not(feature = "unchecked"))]
+fn test_math_error_operands() {
+    let mut engine = Engine::new();
+
+    let mut operation = |script: &str| -> (String, Vec<INT>) {
+        match *engine.eval::<INT>(script).expect_err("expects error") {
+            EvalAltResult::ErrorArithmetic(_, Some(op), _) => (
+                op.operator,
+                op.operands.into_iter().map(|x| x.cast::<INT>()).collect(),
+            ),
+            err => panic!("wrong error: {}", err),
+        }
+    };
+
+    assert_eq!(operation("let x = 42; x / 0"), ("/".into(), vec![42, 0]));
+    assert_eq!(operation("let x = 42; x % 0"), ("%".into(), vec![42, 0]));
+    assert_eq!(operation("let x = 1; x << -1"), ("<<".into(), vec![1, -1]));
+
+    #[cfg(not(feature = "only_i32"))]
+    {
+        assert_eq!(
+            operation("let x = 4000000000; x * x"),
+            ("*".into(), vec![4_000_000_000, 4_000_000_000])
+        );
+        assert_eq!(
+            operation("let x = -9223372036854775807; abs(x - 1)"),
+            ("abs".into(), vec![-9_223_372_036_854_775_808])
+        );
+    }
+
+    #[cfg(feature = "only_i32")]
+    assert_eq!(
+        operation("let x = 2000000000; x + x"),
+        ("+".into(), vec![2_000_000_000, 2_000_000_000])
+    );
+}
@@ -0,0 +1,143 @@
+#![cfg(feature = "matrix")]
+#![cfg(not(feature = "no_index"))]
+#![cfg(not(feature = "no_float"))]
+use rhai::{Engine, EvalAltResult, Matrix, Scope, FLOAT, INT};
+
+#[test]
+fn test_matrix() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let m = engine.eval::<Matrix>("matrix([[1.0, 2.0], [3, 4]])")?;
+    assert_eq!(m.shape(), &[2, 2]);
+    assert_eq!(m[(1, 0)], 3.0);
+
+    assert_eq!(
+        engine.eval::<INT>("let m = zeros(2, 3); m.rows() * 10 + m.cols()")?,
+        23
+    );
+    assert_eq!(engine.eval::<FLOAT>("identity(3).get(1, 1)")?, 1.0);
+    assert_eq!(engine.eval::<FLOAT>("ones(2, 2).get(0, 1)")?, 1.0);
+    assert_eq!(engine.eval::<String>("type_of(identity(2))")?, "matrix");
+
+    assert!(engine
+        .eval::<Matrix>("matrix([[1.0, 2.0], [3.0]])")
+        .is_err());
+    assert!(engine.eval::<Matrix>(r#"matrix([[1.0, "x"]])"#).is_err());
+    assert!(engine.eval::<FLOAT>("identity(2).get(2, 0)").is_err());
+
+    // Too many elements
+    for script in &[
+        "zeros(5000000000, 5000000000)",
+        "ones(4097, 4096)",
+        "identity(5000000000)",
+        "zeros(5000, 1).matmul(zeros(1, 5000))",
+    ] {
+        assert!(matches!(
+            *engine.eval::<Matrix>(script).expect_err("expects error"),
+            EvalAltResult::ErrorArithmetic(_, _, _)
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_matrix_indexing() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<FLOAT>("let m = matrix([[1.0, 2.0], [3.0, 4.0]]); m[1][0]")?,
+        3.0
+    );
+    assert_eq!(
+        engine.eval::<FLOAT>("let m = identity(2); m[1] = [5.0, 6]; m.get(1, 1)")?,
+        6.0
+    );
+    assert_eq!(
+        engine.eval::<FLOAT>("let m = identity(2); m.set(0, 1, 42); m[0][1]")?,
+        42.0
+    );
+    assert_eq!(
+        engine.eval::<FLOAT>("let m = identity(2); m[1][0] = 40; m[1][0] += 2.0; m[1][0]")?,
+        42.0
+    );
+    assert_eq!(
+        engine.eval::<FLOAT>("let sum = 0.0; for row in identity(3) { sum += row[0]; } sum")?,
+        1.0
+    );
+
+    assert!(engine
+        .eval::<()>("let m = identity(2); m[1] = [1.0]")
+        .is_err());
+    assert!(engine
+        .eval::<FLOAT>("let m = identity(2); m[2][0]")
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_matrix_ops() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut scope = Scope::new();
+
+    scope.push(
+        "a",
+        engine.eval::<Matrix>("matrix([[1.0, 2.0], [3.0, 4.0]])")?,
+    );
+    scope.push(
+        "b",
+        engine.eval::<Matrix>("matrix([[5.0, 6.0], [7.0, 8.0]])")?,
+    );
+
+    let eval = |engine: &mut Engine, scope: &mut Scope, script| {
+        engine
+            .eval_with_scope::<Matrix>(scope, script)
+            .map(|m| m.iter().copied().collect::<Vec<_>>())
+    };
+
+    assert_eq!(
+        eval(&mut engine, &mut scope, "a + b")?,
+        vec![6.0, 8.0, 10.0, 12.0]
+    );
+    assert_eq!(
+        eval(&mut engine, &mut scope, "b - a")?,
+        vec![4.0, 4.0, 4.0, 4.0]
+    );
+    assert_eq!(
+        eval(&mut engine, &mut scope, "a * b")?,
+        vec![5.0, 12.0, 21.0, 32.0]
+    );
+    assert_eq!(
+        eval(&mut engine, &mut scope, "a * 2")?,
+        vec![2.0, 4.0, 6.0, 8.0]
+    );
+    assert_eq!(
+        eval(&mut engine, &mut scope, "1.0 - a")?,
+        vec![0.0, -1.0, -2.0, -3.0]
+    );
+    assert_eq!(
+        eval(&mut engine, &mut scope, "-a")?,
+        vec![-1.0, -2.0, -3.0, -4.0]
+    );
+    assert_eq!(
+        eval(&mut engine, &mut scope, "a.matmul(b)")?,
+        vec![19.0, 22.0, 43.0, 50.0]
+    );
+    assert_eq!(
+        eval(&mut engine, &mut scope, "a.transpose()")?,
+        vec![1.0, 3.0, 2.0, 4.0]
+    );
+
+    assert!(engine.eval_with_scope::<bool>(&mut scope, "a.matmul(identity(2)) == a")?);
+    assert!(engine.eval_with_scope::<bool>(&mut scope, "a != b")?);
+
+    assert!(engine
+        .eval_with_scope::<Matrix>(&mut scope, "a + zeros(2, 3)")
+        .is_err());
+    assert!(engine
+        .eval_with_scope::<Matrix>(&mut scope, "a.matmul(zeros(3, 2))")
+        .is_err());
+
+    Ok(())
+}
@@ -0,0 +1,136 @@
+#![cfg(not(feature = "no_function"))]
+use rhai::{Engine, EvalAltResult, INT};
+
+#[test]
+fn test_memoize() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let script = r#"
+        fn fib(n) {
+            if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+        }
+
+        memoize("fib");
+        fib(30)
+    "#;
+
+    assert_eq!(engine.eval::<INT>(script)?, 832_040);
+    assert!(engine.last_eval_stats().fn_calls < 1000);
+
+    // Functions are only memoized during the evaluation calling memoize
+    // (calls of JIT-compiled functions are not counted)
+    #[cfg(not(feature = "jit"))]
+    {
+        engine
+            .eval::<INT>("fn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } } fib(15)")?;
+        assert!(engine.last_eval_stats().fn_calls > 1000);
+    }
+
+    // Results are cached by the values (and types) of the arguments
+    #[cfg(not(feature = "no_index"))]
+    #[cfg(not(feature = "no_stdlib"))]
+    {
+        let mut log = Vec::new();
+
+        {
+            let mut engine = Engine::new();
+            engine.on_print(|s| log.push(s.to_string()));
+
+            engine.consume(
+                r#"
+                    fn describe(x, y) { print(x + ":" + y); x }
+
+                    memoize("describe");
+                    describe(1, 'a');
+                    describe(1, "a");
+                    describe(1, 'a');
+                    describe([1, 2], ());
+                    describe([1, 2], ());
+                    describe([1, 3], ());
+                "#,
+            )?;
+        }
+
+        assert_eq!(log.len(), 4);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_memoize_errors() {
+    let mut engine = Engine::new();
+
+    assert!(matches!(
+        *engine
+            .eval::<()>(r#"memoize("unknown")"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f == "unknown"
+    ));
+}
+
+#[test]
+fn test_memoize_by_definition() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Another function of the same name is not affected
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn f(x) { x + 1 }
+                memoize("f");
+                f(1);
+                eval("fn f(x) { x * 100 } f(1)")
+            "#
+        )?,
+        100
+    );
+
+    // Functions of the same name with other numbers of parameters are memoized as well
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn f(x) { x + 1 }
+                fn f(x, y) { x + y }
+                memoize("f");
+                f(1) + f(1, 2) + f(1, 2)
+            "#
+        )?,
+        8
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_memoize_limits() -> Result<(), Box<EvalAltResult>> {
+    let mut log = Vec::new();
+
+    {
+        let mut engine = Engine::new();
+        engine.on_print(|s| log.push(s.to_string()));
+
+        // Large arguments are not cached
+        engine.consume(
+            r#"
+                fn total(list) { print("called"); list.len() }
+
+                memoize("total");
+                let small = [1, 2, 3];
+                let large = [];
+                large.pad(1000, 0);
+
+                total(small);
+                total(small);
+                total(large);
+                total(large);
+            "#,
+        )?;
+    }
+
+    assert_eq!(log.len(), 3);
+
+    Ok(())
+}
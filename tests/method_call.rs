@@ -3,7 +3,7 @@
 use rhai::{Engine, EvalAltResult, RegisterFn, INT};
 
 #[test]
-fn test_method_call() -> Result<(), EvalAltResult> {
+fn test_method_call() -> Result<(), Box<EvalAltResult>> {
     #[derive(Debug, Clone, Eq, PartialEq)]
     struct TestStruct {
         x: INT,
@@ -38,3 +38,15 @@ fn test_method_call() -> Result<(), EvalAltResult> {
 
     Ok(())
 }
+
+#[test]
+fn test_method_call_chain() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("plus", |x: INT, y: INT| x + y);
+
+    assert_eq!(engine.eval::<INT>("let x = 1; x.plus(2).plus(3)")?, 6);
+    assert_eq!(engine.eval::<INT>("let x = 1; x.plus(2).plus(3); x")?, 1);
+
+    Ok(())
+}
@@ -6,11 +6,35 @@ fn test_mismatched_op() {
     let mut engine = Engine::new();
 
     assert!(
-        matches!(engine.eval::<INT>(r#"60 + "hello""#).expect_err("expects error"),
-        EvalAltResult::ErrorMismatchOutputType(err, _) if err == "string")
+        matches!(*engine.eval::<INT>(r#"60 + "hello""#).expect_err("expects error"),
+        EvalAltResult::ErrorMismatchOutputType(_, err, _) if err == "string")
     );
 }
 
+#[test]
+fn test_mismatched_output_type() {
+    let mut engine = Engine::new();
+
+    let err = engine
+        .eval::<bool>(r#""hello""#)
+        .expect_err("expects error");
+
+    assert!(matches!(
+        &*err,
+        EvalAltResult::ErrorMismatchOutputType(expected, actual, _)
+            if expected == "bool" && actual == "string"
+    ));
+    assert_eq!(
+        err.to_string(),
+        "Output type is incorrect: string (expecting bool) (none)"
+    );
+
+    assert!(matches!(
+        *engine.eval::<String>("42").expect_err("expects error"),
+        EvalAltResult::ErrorMismatchOutputType(expected, _, _) if expected == "string"
+    ));
+}
+
 #[test]
 #[cfg(not(feature = "no_object"))]
 fn test_mismatched_op_custom_type() {
@@ -35,11 +59,29 @@ fn test_mismatched_op_custom_type() {
 
     #[cfg(feature = "only_i32")]
     assert!(
-        matches!(r, EvalAltResult::ErrorFunctionNotFound(err, _) if err == "+ (i32, TestStruct)")
+        matches!(*r, EvalAltResult::ErrorFunctionNotFound(err, _) if err == "+ (i32, TestStruct)")
     );
 
     #[cfg(not(feature = "only_i32"))]
     assert!(
-        matches!(r, EvalAltResult::ErrorFunctionNotFound(err, _) if err == "+ (i64, TestStruct)")
+        matches!(*r, EvalAltResult::ErrorFunctionNotFound(err, _) if err == "+ (i64, TestStruct)")
+    );
+
+    assert!(matches!(
+        *engine.eval::<INT>("eval(new_ts())").expect_err("expects error"),
+        EvalAltResult::ErrorMismatchOutputType(_, err, _) if err == "TestStruct"
+    ));
+    assert!(matches!(
+        *engine.eval::<TestStruct>("42").err().expect("expects error"),
+        EvalAltResult::ErrorMismatchOutputType(err, _, _) if err == "TestStruct"
+    ));
+
+    assert_eq!(
+        engine.map_type_name(std::any::type_name::<TestStruct>()),
+        "TestStruct"
+    );
+    assert_eq!(
+        engine.map_type_name(std::any::type_name::<String>()),
+        "string"
     );
 }
@@ -0,0 +1,969 @@
+#![cfg(not(feature = "no_module"))]
+use rhai::{Engine, EvalAltResult, ParseErrorType, StaticModuleResolver, INT};
+
+fn make_engine() -> Result<Engine<'static>, Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut resolver = StaticModuleResolver::new();
+
+    resolver.insert(
+        "math",
+        engine.compile(
+            r"
+                fn double(x) { x * 2 }
+                fn quadruple(x) { double(double(x)) }
+            ",
+        )?,
+    );
+    resolver.insert(
+        "utils",
+        engine.compile(
+            r#"
+                import "math" as math;
+                import double as twice from "math";
+
+                export math, twice as dbl;
+
+                fn add_one(x) { x + 1 }
+                fn double_plus_one(x) { math::double(x) + 1 }
+                fn twice_plus_one(x) { twice(x) + 1 }
+            "#,
+        )?,
+    );
+
+    engine.set_module_resolver(Some(resolver));
+
+    Ok(engine)
+}
+
+#[test]
+fn test_module_import_alias() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = make_engine()?;
+
+    assert_eq!(
+        engine.eval::<INT>(r#"import "math" as m; m::double(21)"#)?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"import "math" as m; m::quadruple(10) + 2"#)?,
+        42
+    );
+    assert_eq!(
+        engine
+            .eval::<INT>(r#"import "utils" as u; u::double_plus_one(20) + u::twice_plus_one(0)"#)?,
+        42
+    );
+
+    assert!(engine.eval::<bool>(r#"import "math" as m; is_def_fn("m::double", 1)"#)?);
+    assert!(!engine.eval::<bool>(r#"import "math" as m; is_def_fn("m::triple", 1)"#)?);
+
+    // Imports do not survive between evaluations
+    assert!(matches!(
+        *engine.eval::<INT>("m::double(21)").expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f.starts_with("m::double")
+    ));
+
+    // Functions not defined in the module
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "math" as m; m::triple(1)"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f.starts_with("m::triple")
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_module_import_selective() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = make_engine()?;
+
+    assert_eq!(
+        engine.eval::<INT>(r#"import double from "math"; double(21)"#)?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"import double as twice, quadruple from "math"; twice(1) + quadruple(10)"#
+        )?,
+        42
+    );
+
+    // Only the imported functions are available
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import double from "math"; quadruple(1)"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f.starts_with("quadruple")
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import double as twice from "math"; double(1)"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f.starts_with("double")
+    ));
+
+    // Importing a function that does not exist
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import triple from "math"; 0"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f == "triple"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_module_export() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = make_engine()?;
+
+    // Re-exported module
+    assert_eq!(
+        engine.eval::<INT>(r#"import "utils" as u; u::math::quadruple(10) + u::add_one(1)"#)?,
+        42
+    );
+
+    // Re-exported function under an alias
+    assert_eq!(
+        engine.eval::<INT>(r#"import "utils" as u; u::dbl(21)"#)?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"import dbl, math from "utils"; dbl(20) + math::double(1)"#)?,
+        42
+    );
+
+    // Items imported but not exported by a module are not visible
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "utils" as u; u::twice(21)"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f.starts_with("u::twice")
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_module_private() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut resolver = StaticModuleResolver::new();
+
+    resolver.insert(
+        "geometry",
+        engine.compile(
+            r"
+                private fn square(x) { x * x }
+                fn area(w, h) { w * h }
+                fn square_area(x) { square(x) }
+            ",
+        )?,
+    );
+    engine.set_module_resolver(Some(resolver));
+
+    // Private functions can be called from within the module
+    assert_eq!(
+        engine.eval::<INT>(r#"import "geometry" as g; g::square_area(6) + g::area(2, 3)"#)?,
+        42
+    );
+
+    // ... but are not exported
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "geometry" as g; g::square(6)"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f.starts_with("g::square")
+    ));
+    assert!(!engine.eval::<bool>(r#"import "geometry" as g; is_def_fn("g::square", 1)"#)?);
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import square from "geometry"; 0"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f == "square"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_module_errors() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = make_engine()?;
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "unknown" as u; 0"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorModuleNotFound(p, _) if p == "unknown"
+    ));
+
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert("bad", engine.compile("export nothing;")?);
+    engine.set_module_resolver(Some(resolver));
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "bad" as b; 0"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f == "nothing"
+    ));
+
+    engine.set_module_resolver(None::<StaticModuleResolver>);
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "bad" as b; 0"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorModuleNotFound(p, _) if p == "bad"
+    ));
+
+    assert!(matches!(
+        engine
+            .compile("import 42 as x;")
+            .expect_err("should error")
+            .error_type(),
+        ParseErrorType::VariableExpected
+    ));
+    assert!(matches!(
+        engine
+            .compile("import double from math;")
+            .expect_err("should error")
+            .error_type(),
+        ParseErrorType::BadInput(_)
+    ));
+    assert!(matches!(
+        engine.compile(r#"import "math";"#).expect_err("should error").error_type(),
+        ParseErrorType::MissingToken(t, _) if t == "as"
+    ));
+    assert!(matches!(
+        engine.compile(r#"import double "math";"#).expect_err("should error").error_type(),
+        ParseErrorType::MissingToken(t, _) if t == "from"
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_std"))]
+fn test_module_file_resolver() -> Result<(), Box<EvalAltResult>> {
+    use rhai::FileModuleResolver;
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("rhai-test-modules-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("greet.rhai"),
+        r#"fn greet(name) { "hello, " + name }"#,
+    )
+    .unwrap();
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(Some(FileModuleResolver::new_with_path(&dir)));
+
+    let result = engine.eval::<String>(r#"import "greet" as g; g::greet("world")"#);
+    let missing = engine.eval::<INT>(r#"import "missing" as m; 0"#);
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(result?, "hello, world");
+    assert!(matches!(
+        *missing.expect_err("should error"),
+        EvalAltResult::ErrorModuleNotFound(p, _) if p == "missing"
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_std"))]
+fn test_module_file_resolver_outside() -> Result<(), Box<EvalAltResult>> {
+    use rhai::FileModuleResolver;
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("rhai-test-outside-{}", std::process::id()));
+    fs::create_dir_all(dir.join("base")).unwrap();
+    fs::write(dir.join("secret.rhai"), "fn secret() { 42 }").unwrap();
+    fs::write(dir.join("base").join("inside.rhai"), "fn inside() { 1 }").unwrap();
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(Some(FileModuleResolver::new_with_path(dir.join("base"))));
+
+    let inside = engine.eval::<INT>(r#"import "./inside" as m; m::inside()"#);
+    let parent = engine.eval::<INT>(r#"import "../secret" as m; m::secret()"#);
+    let absolute = engine.eval::<INT>(&format!(
+        r#"import "{}" as m; m::secret()"#,
+        dir.join("secret").display()
+    ));
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(inside?, 1);
+    assert!(matches!(
+        *parent.expect_err("should error"),
+        EvalAltResult::ErrorModuleNotFound(p, _) if p == "../secret"
+    ));
+    assert!(matches!(
+        *absolute.expect_err("should error"),
+        EvalAltResult::ErrorModuleNotFound(_, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_module_circular_import() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut resolver = StaticModuleResolver::new();
+
+    resolver.insert("a", engine.compile(r#"import "b" as b; fn a() { 1 }"#)?);
+    resolver.insert("b", engine.compile(r#"import c from "c"; fn b() { 2 }"#)?);
+    resolver.insert("c", engine.compile(r#"import "a" as a; fn c() { 3 }"#)?);
+    resolver.insert(
+        "self",
+        engine.compile(r#"import "self" as me; fn f() { 4 }"#)?,
+    );
+
+    engine.set_module_resolver(Some(resolver));
+
+    let err = engine
+        .eval::<INT>(r#"import "a" as a; a::a()"#)
+        .expect_err("should error");
+
+    assert!(matches!(
+        &*err,
+        EvalAltResult::ErrorCircularImport(chain, _) if chain == &["a", "b", "c", "a"]
+    ));
+    assert!(err.to_string().contains("a -> b -> c -> a"));
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "self" as s; s::f()"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorCircularImport(chain, _) if chain == ["self", "self"]
+    ));
+
+    // Importing the same module twice is not a cycle
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert("base", engine.compile("fn one() { 1 }")?);
+    resolver.insert(
+        "x",
+        engine.compile(r#"import "base" as base; fn x() { base::one() }"#)?,
+    );
+    resolver.insert(
+        "y",
+        engine.compile(
+            r#"import "base" as base; import "x" as x; fn y() { base::one() + x::x() }"#,
+        )?,
+    );
+    engine.set_module_resolver(Some(resolver));
+
+    assert_eq!(
+        engine.eval::<INT>(r#"import "y" as y; import "base" as b; y::y() + b::one()"#)?,
+        3
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_module_import_validation() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = make_engine()?;
+
+    // Broken imports are only found when the script runs
+    assert!(engine
+        .compile(r#"import "maths" as m; m::double(1)"#)
+        .is_ok());
+
+    engine.enable_import_validation(true);
+
+    let ast = engine.compile(
+        r#"
+            import "utils" as u;
+            import quadruple from "math";
+            fn f(x) { u::dbl(x) + u::math::double(x) }
+            f(1) + quadruple(1)
+        "#,
+    )?;
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 8);
+
+    for script in &[
+        r#"import "maths" as m; 0"#,
+        r#"import triple from "math"; 0"#,
+        r#"import "math" as m; m::triple(1)"#,
+        r#"import "math" as m; m::double(1, 2)"#,
+        r#"import "utils" as u; u::math::triple(1)"#,
+        r#"fn f() { import "maths" as m; } 0"#,
+        r#"import "math" as m; export m, maths;"#,
+    ] {
+        let err = engine.compile(script).expect_err("should error");
+        assert!(
+            matches!(err.error_type(), ParseErrorType::BadImport(_)),
+            "{}: {}",
+            script,
+            err
+        );
+    }
+
+    // Namespaces which are not imported may be those of registered functions
+    assert!(engine.compile("audio::play(1)").is_ok());
+
+    // Modules with broken imports can only be compiled without import validation
+    let plain = Engine::new();
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert("a", plain.compile(r#"import "b" as b; fn a() { 1 }"#)?);
+    resolver.insert("b", plain.compile(r#"import "a" as a; fn b() { 2 }"#)?);
+    resolver.insert(
+        "c",
+        plain.compile(r#"import "missing" as m; fn c() { 3 }"#)?,
+    );
+    engine.set_module_resolver(Some(resolver));
+
+    let err = engine
+        .compile("let x = 1;\nimport \"a\" as a;")
+        .expect_err("should error");
+    assert!(err.to_string().contains("a -> b -> a"));
+    assert_eq!(err.position().line(), Some(2));
+
+    let err = engine
+        .compile(r#"import "c" as c;"#)
+        .expect_err("should error");
+    assert!(err.to_string().contains("'missing'"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_std"))]
+fn test_module_import_validation_files() -> Result<(), Box<EvalAltResult>> {
+    use rhai::FileModuleResolver;
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("rhai-test-validation-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.rhai"), r#"import "b" as b; fn a() { b::b() }"#).unwrap();
+    fs::write(dir.join("b.rhai"), r#"import "a" as a; fn b() { 2 }"#).unwrap();
+    fs::write(dir.join("c.rhai"), r#"fn c() { 3 }"#).unwrap();
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(Some(FileModuleResolver::new_with_path(&dir)));
+    engine.enable_import_validation(true);
+
+    let circular = engine.compile(r#"import "a" as a; a::a()"#);
+    let result = engine.eval::<INT>(r#"import "c" as c; c::c()"#);
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(matches!(
+        circular.expect_err("should error").error_type(),
+        ParseErrorType::BadImport(_)
+    ));
+    assert_eq!(result?, 3);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_std"))]
+fn test_module_file_resolver_cache() -> Result<(), Box<EvalAltResult>> {
+    use rhai::FileModuleResolver;
+    use std::fs::{self, File};
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    fn write_module(file: &Path, value: INT, age: u64) {
+        fs::write(file, format!("fn value() {{ {} }}", value)).unwrap();
+        File::options()
+            .write(true)
+            .open(file)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(1000 - age))
+            .unwrap();
+    }
+
+    let dir = std::env::temp_dir().join(format!("rhai-test-cache-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("m.rhai");
+    let script = r#"import "m" as m; m::value()"#;
+
+    let mut engine = Engine::new();
+    let mut resolver = FileModuleResolver::new_with_path(&dir);
+
+    let result = (|| {
+        write_module(&file, 1, 0);
+        engine.set_module_resolver(Some(resolver.clone()));
+        assert_eq!(engine.eval::<INT>(script)?, 1);
+        assert!(resolver.is_cached("m"));
+
+        // Modified files are recompiled
+        write_module(&file, 2, 10);
+        assert_eq!(engine.eval::<INT>(script)?, 2);
+
+        // Without checking modified times, cached modules are reused until the cache is cleared
+        resolver.enable_modified_check(false);
+        engine.set_module_resolver(Some(resolver.clone()));
+        write_module(&file, 3, 20);
+        assert_eq!(engine.eval::<INT>(script)?, 2);
+        assert!(resolver.clear_cache_for_path("m"));
+        assert!(!resolver.clear_cache_for_path("m"));
+        assert_eq!(engine.eval::<INT>(script)?, 3);
+        write_module(&file, 4, 30);
+        resolver.clear_cache();
+        assert!(!resolver.is_cached("m"));
+        assert_eq!(engine.eval::<INT>(script)?, 4);
+
+        // Without caching, module files are always recompiled
+        resolver.enable_cache(false);
+        resolver.clear_cache();
+        engine.set_module_resolver(Some(resolver.clone()));
+        write_module(&file, 5, 30);
+        assert_eq!(engine.eval::<INT>(script)?, 5);
+        assert!(!resolver.is_cached("m"));
+
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    result
+}
+
+#[test]
+fn test_module_resolvers_collection() -> Result<(), Box<EvalAltResult>> {
+    use rhai::{ModuleResolver, ModuleResolversCollection, Position, AST};
+
+    // A custom resolver which builds a module for any path starting with 'const_'
+    struct ConstResolver;
+
+    impl ModuleResolver for ConstResolver {
+        fn resolve(
+            &self,
+            engine: &Engine,
+            path: &str,
+            pos: Position,
+        ) -> Result<AST, Box<EvalAltResult>> {
+            match path {
+                "broken" => Err(Box::new(EvalAltResult::ErrorRuntime("broken".into(), pos))),
+                _ if path.starts_with("const_") => Ok(engine
+                    .compile(&format!("fn value() {{ {} }}", &path[6..]))
+                    .map_err(EvalAltResult::ErrorParsing)?),
+                _ => Err(Box::new(EvalAltResult::ErrorModuleNotFound(
+                    path.into(),
+                    pos,
+                ))),
+            }
+        }
+    }
+
+    let mut engine = Engine::new();
+
+    let mut overrides = StaticModuleResolver::new();
+    overrides.insert("const_1", engine.compile("fn value() { 100 }")?);
+
+    let mut standard = StaticModuleResolver::new();
+    standard.insert("math", engine.compile("fn double(x) { x * 2 }")?);
+    standard.insert("const_2", engine.compile("fn value() { 200 }")?);
+
+    let mut resolvers = ModuleResolversCollection::new();
+    assert!(resolvers.is_empty());
+    resolvers.push(overrides);
+    resolvers.push(ConstResolver);
+    resolvers.push(standard);
+    assert_eq!(resolvers.len(), 3);
+
+    engine.set_module_resolver(Some(resolvers));
+
+    // Resolvers are tried in order
+    assert_eq!(
+        engine.eval::<INT>(r#"import "const_1" as c; c::value()"#)?,
+        100
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"import "const_2" as c; c::value()"#)?,
+        2
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"import "const_42" as c; c::value()"#)?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"import "math" as m; m::double(21)"#)?,
+        42
+    );
+
+    // Errors other than a module not found are not passed over
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "broken" as b; 0"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorRuntime(s, _) if s == "broken"
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "unknown" as u; 0"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorModuleNotFound(p, _) if p == "unknown"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_module_call_cache() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert(
+        "a",
+        engine.compile("fn value() { 1 } fn double(x) { x * 2 }")?,
+    );
+    resolver.insert("b", engine.compile("fn value() { 10 }")?);
+    engine.set_module_resolver(Some(resolver));
+
+    // The function called is found once, then reused by the following calls
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                import "a" as m;
+                let sum = 0;
+                let i = 0;
+                while i < 10 { sum += m::double(i); i += 1; }
+                sum
+            "#
+        )?,
+        90
+    );
+    assert_eq!(engine.last_eval_stats().cached_calls, 9);
+
+    // The same call site calls into different modules imported under the same alias
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let sum = 0;
+                let i = 0;
+                while i < 4 {
+                    if i < 2 { import "a" as m; sum += m::value(); }
+                    else { import "b" as m; sum += m::value(); }
+                    i += 1;
+                }
+                sum
+            "#
+        )?,
+        22
+    );
+
+    let script = r#"
+        import "a" as m;
+        let sum = 0;
+        let i = 0;
+        while i < 4 {
+            if i == 2 { import "b" as m; }
+            sum += m::value();
+            i += 1;
+        }
+        sum
+    "#;
+    assert_eq!(engine.eval::<INT>(script)?, 22);
+    assert_eq!(engine.last_eval_stats().cached_calls, 2);
+
+    // The cache is kept in the AST, and only reused while the module is still imported
+    let ast = engine.compile(
+        r#"
+            import "a" as m;
+            let sum = 0;
+            let i = 0;
+            while i < 10 { sum += m::double(i); i += 1; }
+            sum
+        "#,
+    )?;
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 90);
+    assert_eq!(engine.last_eval_stats().cached_calls, 9);
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 90);
+    assert_eq!(engine.last_eval_stats().cached_calls, 9);
+    assert_eq!(engine.eval_ast::<INT>(&ast.clone())?, 90);
+    assert_eq!(engine.last_eval_stats().cached_calls, 9);
+
+    Ok(())
+}
+
+#[test]
+fn test_module_native() -> Result<(), Box<EvalAltResult>> {
+    use rhai::{NativeModule, RegisterFn};
+
+    let mut consts = NativeModule::new();
+    consts.set_constant("ANSWER", 42 as INT);
+    consts.set_constant("NAME", "rhai".to_string());
+
+    let mut math = NativeModule::new();
+    math.register_fn("double", |x: INT| x * 2);
+    math.set_constant("ONE", 1 as INT);
+    math.set_sub_module("consts", consts);
+
+    assert_eq!(math.get_constant::<INT>("ONE"), Some(1));
+    assert_eq!(math.get_constant::<bool>("ONE"), None);
+    assert!(math
+        .get_sub_module("consts")
+        .unwrap()
+        .contains_constant("ANSWER"));
+
+    math.get_sub_module_mut("consts")
+        .unwrap()
+        .register_fn("answer", || 42 as INT);
+
+    let mut engine = Engine::new();
+    engine.register_module("math", math);
+
+    assert_eq!(engine.eval::<INT>("math::consts::ANSWER")?, 42);
+    assert_eq!(engine.eval::<String>("math::consts::NAME")?, "rhai");
+    assert_eq!(engine.eval::<INT>("math::double(math::ONE)")?, 2);
+    assert_eq!(engine.eval::<INT>("math::consts::answer() + 1")?, 43);
+
+    #[cfg(not(feature = "no_function"))]
+    assert_eq!(
+        engine.eval::<INT>("fn f(x) { math::double(x) + math::consts::ANSWER } f(1)")?,
+        44
+    );
+
+    // Qualified names are not looked up unqualified
+    assert!(matches!(
+        *engine.eval::<INT>("ANSWER").expect_err("should error"),
+        EvalAltResult::ErrorVariableNotFound(s, _) if s == "ANSWER"
+    ));
+    assert!(matches!(
+        *engine.eval::<INT>("math::TWO").expect_err("should error"),
+        EvalAltResult::ErrorVariableNotFound(s, _) if s == "math::TWO"
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>("math::consts::ANSWER = 1; 0")
+            .expect_err("should error"),
+        EvalAltResult::ErrorAssignmentToConstant(s, _) if s == "math::consts::ANSWER"
+    ));
+    assert!(matches!(
+        engine
+            .compile("math::")
+            .expect_err("should error")
+            .error_type(),
+        ParseErrorType::UnexpectedEOF
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_object"))]
+fn test_module_native_tables() -> Result<(), Box<EvalAltResult>> {
+    use rhai::{Array, NativeModule, RegisterFn};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug)]
+    struct Item {
+        power: INT,
+    }
+
+    impl Clone for Item {
+        fn clone(&self) -> Self {
+            CLONES.fetch_add(1, Ordering::SeqCst);
+            Item { power: self.power }
+        }
+    }
+
+    let mut items = NativeModule::new();
+    items.set_array("TABLE", (0..100).map(|power| Item { power }));
+    items.set_map("BY_NAME", vec![("sword", Item { power: 42 })]);
+
+    let mut engine = Engine::new();
+    engine.register_module("items", items);
+    engine.register_fn("power", |item: &mut Item| item.power);
+    CLONES.store(0, Ordering::SeqCst);
+
+    // Reading the table shares it with the module instead of copying it
+    assert!(engine.eval::<Array>("items::TABLE")?.is_shared());
+    #[cfg(not(feature = "no_stdlib"))]
+    assert_eq!(engine.eval::<INT>("len(items::TABLE)")?, 100);
+    assert_eq!(CLONES.load(Ordering::SeqCst), 0);
+
+    // Indexing only copies the item
+    assert_eq!(engine.eval::<INT>("items::TABLE[42].power()")?, 42);
+    assert_eq!(
+        engine.eval::<INT>(r#"items::BY_NAME["sword"].power()"#)?,
+        42
+    );
+    assert_eq!(CLONES.load(Ordering::SeqCst), 2);
+
+    // Iterating only copies the items reached
+    assert_eq!(
+        engine.eval::<INT>(
+            "let x = 0; for item in items::TABLE { x = item.power(); if x == 2 { break; } } x"
+        )?,
+        2
+    );
+    assert_eq!(CLONES.load(Ordering::SeqCst), 5);
+
+    // The table cannot be modified
+    assert!(matches!(
+        *engine
+            .eval::<()>("items::TABLE[0] = 1")
+            .expect_err("should error"),
+        EvalAltResult::ErrorAssignmentToConstant(..)
+    ));
+    assert_eq!(engine.eval::<INT>("items::TABLE[0].power()")?, 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_module_source_resolver() -> Result<(), Box<EvalAltResult>> {
+    use rhai::{ModuleResolver, Position, SourceModuleResolver};
+    use std::collections::HashMap;
+
+    let mut sources = HashMap::new();
+    sources.insert("math".to_string(), "fn double(x) { x * 2 }".to_string());
+    sources.insert("broken".to_string(), "fn oops( {".to_string());
+
+    let mut resolver = SourceModuleResolver::from(sources);
+    resolver.insert(
+        "calc",
+        r#"import "math" as m; fn quad(x) { m::double(m::double(x)) }"#,
+    );
+
+    assert!(resolver.contains_path("math"));
+    assert!(!resolver.contains_path("unknown"));
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(Some(resolver.clone()));
+
+    assert_eq!(
+        engine.eval::<INT>(r#"import "calc" as c; c::quad(10)"#)?,
+        40
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"import "math" as m; m::double(21)"#)?,
+        42
+    );
+
+    // Modules are only compiled when imported
+    assert!(engine.compile(r#"import "broken" as b; 0"#).is_ok());
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "broken" as b; 0"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorParsing(_)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "unknown" as u; 0"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorModuleNotFound(p, _) if p == "unknown"
+    ));
+
+    // Replacing the source of a module discards the compiled module
+    assert!(!resolver.is_compiled("math"));
+    resolver.resolve(&engine, "math", Position::default())?;
+    assert!(resolver.is_compiled("math"));
+    resolver.insert("math", "fn double(x) { x + x + 1 }");
+    assert!(!resolver.is_compiled("math"));
+
+    engine.set_module_resolver(Some(resolver));
+    assert_eq!(
+        engine.eval::<INT>(r#"import "math" as m; m::double(21)"#)?,
+        43
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "http_resolver")]
+fn test_module_http_resolver() -> Result<(), Box<EvalAltResult>> {
+    use rhai::HttpModuleResolver;
+    use std::sync::{Arc, Mutex};
+
+    let fetched = Arc::new(Mutex::new(Vec::<String>::new()));
+    let log = fetched.clone();
+
+    let mut resolver = HttpModuleResolver::new();
+    resolver.map_prefix("pkg://", "https://example.com/modules/");
+    resolver.map_prefix("insecure://", "http://example.com/");
+    resolver.map_prefix("bare://", "https://example.com");
+    resolver.set_fetcher(move |url| {
+        log.lock().unwrap().push(url.to_string());
+
+        Ok(match url {
+            "https://example.com/modules/vendor/util.rhai" => Some("fn triple(x) { x * 3 }".into()),
+            "https://example.com/modules/vendor/fake.rhai" => Some("fn triple(x) { 0 }".into()),
+            "https://example.com/modules/vendor/bad.rhai" => Some("fn bad() { ".into()),
+            "https://other.com/raw.rhai" => Some("fn one() { 1 }".into()),
+            _ => None,
+        })
+    });
+    resolver.on_approve(|_, url| !url.contains("blocked"));
+
+    // SHA-256 of "fn triple(x) { x * 3 }"
+    let checksum = "96F1D05BDD033FEB79573B17B0C818198E2C8161EF02DEEB682A17DB37578369";
+    resolver.pin("pkg://vendor/util", checksum);
+    resolver.pin("pkg://vendor/fake", checksum);
+
+    assert_eq!(
+        resolver.get_url("pkg://vendor/util").as_deref(),
+        Some("https://example.com/modules/vendor/util.rhai")
+    );
+    assert_eq!(resolver.get_url("local/util"), None);
+
+    // Mapped paths cannot leave the base URL
+    assert_eq!(
+        resolver.get_url("bare://.evil.com/util").as_deref(),
+        Some("https://example.com/.evil.com/util.rhai")
+    );
+    assert_eq!(resolver.get_url("pkg://../util"), None);
+    assert_eq!(resolver.get_url("pkg://vendor/%2e%2e/util"), None);
+    assert_eq!(resolver.get_url("pkg:///util"), None);
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(Some(resolver.clone()));
+
+    assert_eq!(
+        engine.eval::<INT>(r#"import "pkg://vendor/util" as u; u::triple(14)"#)?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"import "https://other.com/raw.rhai" as r; r::one()"#)?,
+        1
+    );
+
+    // Modules are only fetched once
+    engine.eval::<()>(r#"import "pkg://vendor/util" as u;"#)?;
+    assert_eq!(fetched.lock().unwrap().len(), 2);
+
+    // Pins are also checked against modules cached for other paths
+    engine.eval::<()>(r#"import "https://example.com/modules/vendor/fake.rhai" as f;"#)?;
+
+    for (script, error) in &[
+        (r#"import "local/util" as u;"#, "Module not found"),
+        (r#"import "pkg://vendor/missing" as u;"#, "Module not found"),
+        (r#"import "pkg://vendor/bad" as u;"#, "Syntax error"),
+        (r#"import "pkg://vendor/fake" as u;"#, "Checksum of module"),
+        (
+            r#"import "insecure://util" as u;"#,
+            "must be fetched over HTTPS",
+        ),
+        (r#"import "pkg://blocked/util" as u;"#, "is not approved"),
+        (r#"import "pkg://vendor/../util" as u;"#, "not a valid path"),
+    ] {
+        let err = engine.eval::<()>(script).expect_err("should error");
+        assert!(err.to_string().contains(error), "{}: {}", script, err);
+    }
+
+    resolver.require_pins(true);
+    engine.set_module_resolver(Some(resolver));
+
+    engine.eval::<()>(r#"import "pkg://vendor/util" as u;"#)?;
+
+    let err = engine
+        .eval::<()>(r#"import "https://other.com/raw.rhai" as r;"#)
+        .expect_err("should error");
+    assert!(err.to_string().contains("is not pinned"));
+
+    Ok(())
+}
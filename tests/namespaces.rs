@@ -0,0 +1,49 @@
+use rhai::{Engine, EvalAltResult, ParseErrorType, RegisterFn, INT};
+
+#[test]
+fn test_namespaces() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("audio::play", |x: INT| x + 1);
+    engine.register_fn("video::play", |x: INT| x * 2);
+    engine.register_fn("play", |x: INT| x);
+    engine.register_fn("game::audio::volume", || 42 as INT);
+
+    assert_eq!(engine.eval::<INT>("audio::play(41)")?, 42);
+    assert_eq!(engine.eval::<INT>("video::play(21)")?, 42);
+    assert_eq!(engine.eval::<INT>("play(42)")?, 42);
+    assert_eq!(engine.eval::<INT>("game :: audio :: volume()")?, 42);
+    assert_eq!(
+        engine.eval::<INT>("let x = audio::play(1); video::play(x) + 1")?,
+        5
+    );
+
+    assert!(matches!(
+        *engine.eval::<INT>("network::play(1)").expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(f, _) if f.starts_with("network::play")
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_namespaces_parse_errors() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!(matches!(
+        engine
+            .compile("audio::42")
+            .expect_err("should error")
+            .error_type(),
+        ParseErrorType::BadInput(_)
+    ));
+
+    // A namespace-qualified name without a call is a constant
+    assert!(engine.compile("audio::play").is_ok());
+    assert!(matches!(
+        *engine.eval::<INT>("audio::play").expect_err("should error"),
+        EvalAltResult::ErrorVariableNotFound(s, _) if s == "audio::play"
+    ));
+
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use rhai::{Engine, EvalAltResult, INT};
 
 #[test]
-fn test_number_literal() -> Result<(), EvalAltResult> {
+fn test_number_literal() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("65")?, 65);
@@ -10,7 +10,7 @@ fn test_number_literal() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_hex_literal() -> Result<(), EvalAltResult> {
+fn test_hex_literal() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("let x = 0xf; x")?, 15);
@@ -20,7 +20,7 @@ fn test_hex_literal() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_octal_literal() -> Result<(), EvalAltResult> {
+fn test_octal_literal() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("let x = 0o77; x")?, 63);
@@ -30,7 +30,7 @@ fn test_octal_literal() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_binary_literal() -> Result<(), EvalAltResult> {
+fn test_binary_literal() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("let x = 0b1111; x")?, 15);
@@ -1,10 +1,10 @@
 #![cfg(not(feature = "no_optimize"))]
 
-use rhai::{Engine, EvalAltResult, OptimizationLevel, INT};
+use rhai::{Engine, EvalAltResult, OptimizationLevel, Scope, INT};
 
 #[test]
-fn test_optimizer() -> Result<(), EvalAltResult> {
-    fn run_test(engine: &mut Engine) -> Result<(), EvalAltResult> {
+fn test_optimizer() -> Result<(), Box<EvalAltResult>> {
+    fn run_test(engine: &mut Engine) -> Result<(), Box<EvalAltResult>> {
         assert_eq!(engine.eval::<INT>(r"if true { 42 } else { 123 }")?, 42);
         assert_eq!(
             engine.eval::<INT>(r"if 1 == 1 || 2 > 3 { 42 } else { 123 }")?,
@@ -30,3 +30,39 @@ fn test_optimizer() -> Result<(), EvalAltResult> {
 
     Ok(())
 }
+
+#[test]
+fn test_optimizer_scope_constants() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut scope = Scope::new();
+
+    engine.set_optimization_level(OptimizationLevel::Simple);
+
+    scope.push_constant("FEATURE_X", false);
+    scope.push_constant("FEATURE_Y", true);
+
+    let ast = engine.compile_with_scope(
+        &scope,
+        r"
+            let x = 0;
+            if FEATURE_X { x = no_such_function(); }
+            if FEATURE_Y { x += 42; }
+            x
+        ",
+    )?;
+
+    // The guarded branches are resolved at compile time, so the scope is no longer needed
+    assert!(!format!("{:?}", ast).contains("FEATURE_"));
+    assert!(!format!("{:?}", ast).contains("no_such_function"));
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    // Without optimization, the constants are resolved at run-time
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    let ast = engine.compile_with_scope(&scope, "if FEATURE_Y { 42 } else { 0 }")?;
+
+    assert!(format!("{:?}", ast).contains("FEATURE_Y"));
+    assert_eq!(engine.eval_ast_with_scope::<INT>(&mut scope, &ast)?, 42);
+
+    Ok(())
+}
@@ -0,0 +1,278 @@
+use rhai::{Engine, EvalAltResult, OverflowMode, RegisterFn, Scope, INT};
+
+fn bounds() -> Scope<'static> {
+    let mut scope = Scope::new();
+    scope.push_constant("MAX", INT::MAX);
+    scope.push_constant("MIN", INT::MIN);
+    scope
+}
+
+#[test]
+fn test_overflow_wrapping() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::builder()
+        .with_overflow_mode(OverflowMode::Wrapping)
+        .build();
+    let mut scope = bounds();
+
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MAX + 1")?,
+        INT::MIN
+    );
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MIN - 1")?,
+        INT::MAX
+    );
+    assert_eq!(engine.eval_with_scope::<INT>(&mut scope, "MAX * 2")?, -2);
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MIN / -1")?,
+        INT::MIN
+    );
+    assert_eq!(engine.eval_with_scope::<INT>(&mut scope, "MIN % -1")?, 0);
+    assert_eq!(engine.eval_with_scope::<INT>(&mut scope, "-MIN")?, INT::MIN);
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "abs(MIN)")?,
+        INT::MIN
+    );
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "let x = MAX; x += 2; x")?,
+        INT::MIN + 1
+    );
+
+    // Results without overflow are unchanged
+    assert_eq!(engine.eval::<INT>("(1 + 2) * 3 - 4 / 2 + 7 % 4")?, 10);
+    assert_eq!(engine.eval::<INT>("abs(-42)")?, 42);
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>("1 / 0")
+            .expect_err("expects division by zero"),
+        EvalAltResult::ErrorArithmetic(_, _, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>("1 % 0")
+            .expect_err("expects division by zero"),
+        EvalAltResult::ErrorArithmetic(_, _, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_overflow_saturating() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_overflow_mode(OverflowMode::Saturating);
+    let mut scope = bounds();
+
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MAX + 1")?,
+        INT::MAX
+    );
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MIN - 1")?,
+        INT::MIN
+    );
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MAX * 2")?,
+        INT::MAX
+    );
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MAX * -2")?,
+        INT::MIN
+    );
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MIN / -1")?,
+        INT::MAX
+    );
+    assert_eq!(engine.eval_with_scope::<INT>(&mut scope, "MIN % -1")?, 0);
+    assert_eq!(engine.eval_with_scope::<INT>(&mut scope, "-MIN")?, INT::MAX);
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "abs(MIN)")?,
+        INT::MAX
+    );
+
+    assert_eq!(engine.eval::<INT>("(1 + 2) * 3 - 4 / 2 + 7 % 4")?, 10);
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>("1 / 0")
+            .expect_err("expects division by zero"),
+        EvalAltResult::ErrorArithmetic(_, _, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_overflow_mode_switch() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut scope = bounds();
+
+    // Constant expressions are folded by the optimizer with the same operators
+    engine.set_overflow_mode(OverflowMode::Wrapping);
+    #[cfg(not(feature = "only_i32"))]
+    assert_eq!(engine.eval::<INT>("9223372036854775807 + 1")?, INT::MIN);
+    #[cfg(feature = "only_i32")]
+    assert_eq!(engine.eval::<INT>("2147483647 + 1")?, INT::MIN);
+
+    engine.set_overflow_mode(OverflowMode::Checked);
+
+    #[cfg(not(feature = "unchecked"))]
+    assert!(matches!(
+        *engine
+            .eval_with_scope::<INT>(&mut scope, "MAX + 1")
+            .expect_err("expects overflow"),
+        EvalAltResult::ErrorArithmetic(_, _, _)
+    ));
+
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MAX - 1")?,
+        INT::MAX - 1
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_overflow_mode_custom_operators() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut scope = bounds();
+
+    engine.register_fn("*", |x: INT, y: INT| x.wrapping_mul(y).wrapping_add(1));
+
+    // Custom functions registered for the operators are kept when the mode changes
+    engine.set_overflow_mode(OverflowMode::Saturating);
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MAX + 1")?,
+        INT::MAX
+    );
+    assert_eq!(engine.eval::<INT>("6 * 7")?, 43);
+
+    engine.set_overflow_mode(OverflowMode::Wrapping);
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MAX + 1")?,
+        INT::MIN
+    );
+    assert_eq!(engine.eval::<INT>("6 * 7")?, 43);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_overflow_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut scope = bounds();
+
+    assert_eq!(engine.eval::<INT>("checked_add(40, 2)")?, 42);
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MAX.checked_sub(1)")?,
+        INT::MAX - 1
+    );
+    assert_eq!(
+        engine.eval_with_scope::<()>(&mut scope, "checked_add(MAX, 1)")?,
+        ()
+    );
+    assert_eq!(
+        engine.eval_with_scope::<()>(&mut scope, "checked_mul(MIN, 2)")?,
+        ()
+    );
+    assert_eq!(engine.eval::<()>("checked_div(1, 0)")?, ());
+    assert_eq!(
+        engine.eval_with_scope::<String>(
+            &mut scope,
+            r#"let r = MAX.checked_add(1); if r == () { "overflow" } else { "ok" }"#
+        )?,
+        "overflow"
+    );
+
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MAX.wrapping_add(1)")?,
+        INT::MIN
+    );
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "wrapping_sub(MIN, 1)")?,
+        INT::MAX
+    );
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "wrapping_mul(MAX, 2)")?,
+        -2
+    );
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "wrapping_div(MIN, -1)")?,
+        INT::MIN
+    );
+
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "saturating_add(MAX, 1)")?,
+        INT::MAX
+    );
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MIN.saturating_sub(1)")?,
+        INT::MIN
+    );
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "saturating_mul(MIN, 2)")?,
+        INT::MIN
+    );
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "saturating_div(MIN, -1)")?,
+        INT::MAX
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>("wrapping_div(1, 0)")
+            .expect_err("expects division by zero"),
+        EvalAltResult::ErrorArithmetic(_, _, _)
+    ));
+
+    // Not affected by the overflow mode
+    engine.set_overflow_mode(OverflowMode::Saturating);
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "MAX.wrapping_add(1)")?,
+        INT::MIN
+    );
+    assert_eq!(
+        engine.eval_with_scope::<()>(&mut scope, "checked_add(MAX, 1)")?,
+        ()
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "only_i32"))]
+#[cfg(not(feature = "only_i64"))]
+fn test_overflow_other_int_types() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut scope = Scope::new();
+    scope.push_constant("x", 250_u8);
+    scope.push_constant("y", -128_i8);
+
+    engine.set_overflow_mode(OverflowMode::Wrapping);
+    assert_eq!(engine.eval_with_scope::<u8>(&mut scope, "x + x")?, 244);
+    assert_eq!(engine.eval_with_scope::<i8>(&mut scope, "-y")?, -128);
+
+    engine.set_overflow_mode(OverflowMode::Saturating);
+    assert_eq!(engine.eval_with_scope::<u8>(&mut scope, "x + x")?, 255);
+    assert_eq!(engine.eval_with_scope::<i8>(&mut scope, "-y")?, 127);
+
+    #[cfg(not(feature = "no_stdlib"))]
+    {
+        assert_eq!(
+            engine.eval_with_scope::<u8>(&mut scope, "x.wrapping_add(x)")?,
+            244
+        );
+        assert_eq!(
+            engine.eval_with_scope::<()>(&mut scope, "x.checked_add(x)")?,
+            ()
+        );
+        assert_eq!(
+            engine.eval_with_scope::<u8>(&mut scope, "x.checked_sub(x)")?,
+            0
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+#![cfg(not(feature = "no_object"))]
+
+use rhai::{Engine, EvalAltResult, Pipeline, RegisterFn, INT};
+
+#[cfg(not(feature = "no_index"))]
+use rhai::{AnyExt, Array};
+
+#[cfg(not(feature = "no_index"))]
+fn to_ints(a: Array) -> Vec<INT> {
+    a.into_iter().map(|v| v.cast::<INT>()).collect()
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+fn test_pipeline_collect() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.register_fn("double", |x: INT| x * 2);
+    engine.register_fn("is_odd", |x: INT| x % 2 == 1);
+
+    assert_eq!(
+        to_ints(engine.eval::<Array>(r#"[1, 2, 3].iter().map("double").collect()"#)?),
+        [2, 4, 6]
+    );
+    assert_eq!(
+        to_ints(engine.eval::<Array>(
+            r#"range(0, 10).iter().filter("is_odd").map("double").skip(1).take(3).collect()"#
+        )?),
+        [6, 10, 14]
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"let p = iter([1, 2, 3, 4, 5]); p.filter("is_odd").count()"#)?,
+        3
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"iter([1, 2, 3]).take(0).count() + iter([]).count()"#)?,
+        0
+    );
+
+    // Adding a step creates a new pipeline
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let p = range(0, 10).iter();
+                let odd = p.filter("is_odd");
+                p.count() * 100 + odd.count()
+            "#
+        )?,
+        1005
+    );
+
+    assert_eq!(engine.eval::<String>("type_of(iter([1]))")?, "pipeline");
+    engine.eval::<Pipeline>(r#"iter(iter([1]).map("double"))"#)?;
+
+    assert!(matches!(
+        *engine
+            .eval::<Pipeline>("iter(42)")
+            .expect_err("expects error"),
+        EvalAltResult::ErrorFunctionNotFound(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"iter([1, 2]).filter("double").count()"#)
+            .expect_err("expects error"),
+        EvalAltResult::ErrorMismatchOutputType(_, _, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_pipeline_for() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.register_fn("square", |x: INT| x * x);
+    engine.register_fn("is_even", |x: INT| x % 2 == 0);
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let sum = 0;
+                for x in range(0, 100000).iter().filter("is_even").map("square").take(4) {
+                    sum += x;
+                }
+                sum
+            "#
+        )?,
+        56
+    );
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let sum = 0;
+                for x in range(1, 10).iter().map("square") {
+                    if x > 50 { break; }
+                    if x == 4 { continue; }
+                    sum += x;
+                }
+                sum
+            "#
+        )?,
+        136
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "no_index"))]
+fn test_pipeline_lazy() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Values after the first three are never reached
+    assert_eq!(
+        to_ints(engine.eval::<Array>(
+            r#"
+                fn check(x) { if x > 3 { throw "too far"; } x + 1 }
+                range(1, 1000).iter().map("check").take(3).collect()
+            "#
+        )?),
+        [2, 3, 4]
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(
+                r#"
+                    fn check(x) { if x > 3 { throw "too far"; } x }
+                    range(1, 1000).iter().map("check").count()
+                "#
+            )
+            .expect_err("expects error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+
+    Ok(())
+}
@@ -7,7 +7,7 @@ use rhai::FLOAT;
 const EPSILON: FLOAT = 0.000_000_000_1;
 
 #[test]
-fn test_power_of() -> Result<(), EvalAltResult> {
+fn test_power_of() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("2 ~ 3")?, 8);
@@ -28,7 +28,7 @@ fn test_power_of() -> Result<(), EvalAltResult> {
 }
 
 #[test]
-fn test_power_of_equals() -> Result<(), EvalAltResult> {
+fn test_power_of_equals() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("let x = 2; x ~= 3; x")?, 8);
@@ -0,0 +1,45 @@
+use rhai::{Engine, EvalAltResult, INT};
+
+#[test]
+fn test_pretty_parse_error() {
+    let engine = Engine::new();
+
+    // Tabs are kept so that the marker lines up
+    let source = "let x = 1;\n\tlet y = x +* 2;";
+    let err = engine.compile(source).expect_err("should error");
+    assert_eq!(
+        err.to_pretty_string(source),
+        format!("{}\n  |\n2 | \tlet y = x +* 2;\n  | \t           ^", err)
+    );
+
+    // EOF points just after the end of the script
+    #[cfg(not(feature = "no_index"))]
+    {
+        let source = "let x = [1, 2";
+        let err = engine.compile(source).expect_err("should error");
+        assert_eq!(
+            err.to_pretty_string(source),
+            format!("{}\n  |\n1 | let x = [1, 2\n  |              ^", err)
+        );
+    }
+}
+
+#[test]
+fn test_pretty_eval_error() {
+    let mut engine = Engine::new();
+
+    let source = "let x = 1;\nlet y = 2;\nlet z = 3;\nlet a = 4;\nlet b = 5;\nlet c = 6;\nlet d = 7;\nlet e = 8;\nlet f = 9;\nthrow \"boom\";";
+    let err = engine.eval::<INT>(source).expect_err("should error");
+    assert_eq!(
+        err.to_pretty_string(source),
+        format!("{}\n   |\n10 | throw \"boom\";\n   |       ^", err)
+    );
+
+    // No position - formatted as usual
+    let err: Box<EvalAltResult> = "boom".into();
+    assert_eq!(err.to_pretty_string(source), err.to_string());
+
+    // Position outside the source - formatted as usual
+    let err = engine.eval::<INT>(source).expect_err("should error");
+    assert_eq!(err.to_pretty_string("42"), err.to_string());
+}
@@ -0,0 +1,67 @@
+use rhai::{Engine, EvalAltResult, Position};
+
+#[test]
+fn test_debug_position() -> Result<(), Box<EvalAltResult>> {
+    let mut log: Vec<(String, Option<String>, Position)> = Vec::new();
+
+    {
+        let mut engine = Engine::new();
+
+        engine.on_debug(|s, source, pos| log.push((s.into(), source.map(Into::into), pos)));
+
+        engine.consume("let x = 42;\n  debug(x);")?;
+
+        let mut ast = engine.compile("debug(\"hello\")")?;
+        ast.set_source("greeting");
+        engine.consume_ast(&ast)?;
+
+        // The source does not stay with the engine
+        engine.consume("debug(1)")?;
+    }
+
+    assert_eq!(log.len(), 3);
+
+    assert_eq!(log[0].0, "42");
+    assert_eq!(log[0].1, None);
+    assert_eq!(format!("{:?}", log[0].2), "(2:3)");
+
+    assert_eq!(log[1].0, "\"hello\"");
+    assert_eq!(log[1].1.as_deref(), Some("greeting"));
+    assert_eq!(format!("{:?}", log[1].2), "(1:1)");
+
+    assert_eq!(log[2].1, None);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_module"))]
+fn test_debug_module_source() -> Result<(), Box<EvalAltResult>> {
+    use rhai::StaticModuleResolver;
+
+    let mut log: Vec<(String, Option<String>)> = Vec::new();
+
+    {
+        let mut engine = Engine::new();
+        let mut resolver = StaticModuleResolver::new();
+
+        resolver.insert("greet", engine.compile("fn hello(x) { debug(x); }")?);
+        engine.set_module_resolver(Some(resolver));
+
+        engine.on_debug(|s, source, _| log.push((s.into(), source.map(Into::into))));
+
+        let mut ast = engine.compile(r#"import "greet" as g; g::hello(1); debug(2);"#)?;
+        ast.set_source("main");
+        engine.consume_ast(&ast)?;
+    }
+
+    assert_eq!(
+        log,
+        vec![
+            ("1".to_string(), Some("greet".to_string())),
+            ("2".to_string(), Some("main".to_string()))
+        ]
+    );
+
+    Ok(())
+}
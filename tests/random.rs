@@ -0,0 +1,98 @@
+#![cfg(feature = "rand")]
+#![cfg(not(feature = "no_stdlib"))]
+use rhai::{AnyExt, Engine, EvalAltResult, INT};
+
+#[test]
+fn test_rand() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.eval::<INT>("rand()")?;
+
+    for _ in 0..100 {
+        let x = engine.eval::<INT>("rand(range(1, 7))")?;
+        assert!(x >= 1 && x < 7);
+    }
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>("rand(range(5, 5))")
+            .expect_err("should error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+
+    #[cfg(not(feature = "no_float"))]
+    {
+        let x = engine.eval::<rhai::FLOAT>("rand_float()")?;
+        assert!(x >= 0.0 && x < 1.0);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+fn test_rand_sample() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let x = engine.eval::<INT>("let x = [1, 2, 3]; x.sample()")?;
+    assert!(x >= 1 && x <= 3);
+
+    engine.eval::<()>("let x = []; x.sample()")?;
+
+    let mut result: Vec<INT> = engine
+        .eval::<rhai::Array>("let x = [1, 2, 3, 4, 5]; x.sample(3)")?
+        .into_iter()
+        .map(|v| v.cast::<INT>())
+        .collect();
+    result.sort();
+    result.dedup();
+    assert_eq!(result.len(), 3);
+
+    assert_eq!(
+        engine.eval::<INT>("let x = [1, 2]; let y = x.sample(10); y.len()")?,
+        2
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+fn test_rand_seed() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let script = "let x = []; for i in range(0, 10) { x.push(rand(range(0, 1000))); } x";
+
+    engine.set_rand_seed(42);
+    let first: Vec<INT> = engine
+        .eval::<rhai::Array>(script)?
+        .into_iter()
+        .map(|v| v.cast::<INT>())
+        .collect();
+
+    engine.set_rand_seed(42);
+    let second: Vec<INT> = engine
+        .eval::<rhai::Array>(script)?
+        .into_iter()
+        .map(|v| v.cast::<INT>())
+        .collect();
+
+    assert_eq!(first, second);
+
+    // A different engine with the same seed gives the same numbers
+    let mut engine2 = Engine::new();
+    engine2.set_rand_seed(42);
+    assert_eq!(engine2.eval::<INT>("rand(range(0, 1000))")?, first[0]);
+
+    // Shuffling is also deterministic
+    engine.set_rand_seed(1);
+    let a = engine.eval::<rhai::Array>("let x = [1, 2, 3, 4, 5, 6, 7, 8]; x.shuffle(); x")?;
+    engine.set_rand_seed(1);
+    let b = engine.eval::<rhai::Array>("let x = [1, 2, 3, 4, 5, 6, 7, 8]; x.shuffle(); x")?;
+    assert_eq!(
+        a.into_iter().map(|v| v.cast::<INT>()).collect::<Vec<_>>(),
+        b.into_iter().map(|v| v.cast::<INT>()).collect::<Vec<_>>()
+    );
+
+    Ok(())
+}
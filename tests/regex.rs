@@ -0,0 +1,99 @@
+#![cfg(feature = "regex")]
+#![cfg(not(feature = "no_object"))]
+use rhai::{AnyExt, Array, Engine, EvalAltResult, INT};
+
+#[test]
+fn test_regex() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!(engine.eval::<bool>(r#"let re = regex("^\\d+$"); re.is_match("12345")"#)?);
+    assert!(!engine.eval::<bool>(r#"let re = regex("^\\d+$"); re.is_match("123a5")"#)?);
+    assert_eq!(
+        engine.eval::<INT>(r#"let re = regex("\\d+"); re.count("a1 b22 c333")"#)?,
+        3
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let re = regex("\\d+"); re.find("abc 42 def 7")"#)?,
+        "42"
+    );
+    assert!(engine.eval::<bool>(r#"let re = regex("\\d+"); re.find("abc") == ()"#)?);
+    assert_eq!(
+        engine.eval::<String>(
+            r#"let re = regex("(\\w+)@(\\w+)"); re.replace("bob@home, amy@work", "$2:$1")"#
+        )?,
+        "home:bob, work:amy"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let re = regex("o"); re.replace_first("foo", "0")"#)?,
+        "f0o"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let re = regex("a+b"); re.pattern()"#)?,
+        "a+b"
+    );
+    assert_eq!(engine.eval::<String>(r#"type_of(regex("x"))"#)?, "regex");
+
+    assert!(matches!(
+        *engine.eval::<()>(r#"regex("(unclosed")"#).expect_err("expects error"),
+        EvalAltResult::ErrorRuntime(ref msg, _) if msg.starts_with("Invalid regular expression")
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_regex_arrays() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let to_strings = |array: Array| -> Vec<String> {
+        array.iter().map(|x| x.clone().cast::<String>()).collect()
+    };
+
+    let found = engine.eval::<Array>(r#"let re = regex("\\d+"); re.find_all("a1 b22 c333")"#)?;
+    assert_eq!(to_strings(found), ["1", "22", "333"]);
+
+    let parts = engine.eval::<Array>(r#"let re = regex("\\s*,\\s*"); re.split("a , b,c")"#)?;
+    assert_eq!(to_strings(parts), ["a", "b", "c"]);
+
+    let groups =
+        engine.eval::<Array>(r#"let re = regex("(\\w+)@(\\w+)"); re.match("to: bob@home")"#)?;
+    assert_eq!(to_strings(groups), ["bob@home", "bob", "home"]);
+
+    assert!(engine.eval::<bool>(r#"let re = regex("(a)|(b)"); let m = re.match("b"); m[1] == ()"#)?);
+    assert!(engine.eval::<bool>(r#"let re = regex("x"); re.match("abc") == ()"#)?);
+
+    assert_eq!(
+        engine.eval::<String>(
+            r#"
+                let result = "";
+                let re = regex("(\\d+)x(\\d+)");
+                for m in re.match_all("2x3, 4x5") {
+                    result += m[2] + m[1] + ";";
+                }
+                result
+            "#
+        )?,
+        "32;54;"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_regex_captures() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<String>(
+            r#"
+                let re = regex("(?P<user>\\w+)@(?P<host>\\w+)");
+                let c = re.captures("mail bob@home now");
+                c.host + "/" + c.user
+            "#
+        )?,
+        "home/bob"
+    );
+    assert!(engine.eval::<bool>(r#"let re = regex("(?P<x>a)"); re.captures("b") == ()"#)?);
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+#![cfg(feature = "serde")]
+#![cfg(not(feature = "no_index"))]
+#![cfg(not(feature = "no_object"))]
+use rhai::{from_dynamic, Any, Array, Engine, EvalAltResult, Map, INT};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Server {
+    host: String,
+    port: u16,
+    tags: Vec<String>,
+    limits: HashMap<String, INT>,
+    backup: Option<String>,
+    mode: Mode,
+    route: Route,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Mode {
+    Active,
+    Passive,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+enum Route {
+    Direct,
+    Proxy(String),
+    Balanced { weights: (INT, INT) },
+}
+
+#[test]
+fn test_serde_eval_into() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let server: Server = engine.eval_into(
+        r#"
+            let limits = #{ requests: 100 };
+            limits.connections = 5;
+
+            #{
+                host: "localhost",
+                port: 8080,
+                tags: ["a", "b"],
+                limits: limits,
+                backup: (),
+                mode: "passive",
+                route: #{ Balanced: #{ weights: [1, 3] } }
+            }
+        "#,
+    )?;
+
+    assert_eq!(
+        server,
+        Server {
+            host: "localhost".into(),
+            port: 8080,
+            tags: vec!["a".into(), "b".into()],
+            limits: [
+                ("requests".to_string(), 100),
+                ("connections".to_string(), 5)
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            backup: None,
+            mode: Mode::Passive,
+            route: Route::Balanced { weights: (1, 3) },
+        }
+    );
+
+    assert_eq!(
+        engine.eval_into::<Route>(r#"#{ Proxy: "gateway" }"#)?,
+        Route::Proxy("gateway".into())
+    );
+    assert_eq!(engine.eval_into::<Route>(r#""Direct""#)?, Route::Direct);
+    assert_eq!(
+        engine.eval_into::<Option<String>>(r#"return "early"; 42"#)?,
+        Some("early".into())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_serde_from_dynamic() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let value = engine.eval::<Array>("[1, 2, 3]")?.into_dynamic();
+    assert_eq!(from_dynamic::<Vec<INT>>(&value)?, vec![1, 2, 3]);
+    assert_eq!(from_dynamic::<(INT, INT, INT)>(&value)?, (1, 2, 3));
+
+    let value = engine.eval::<Map>("#{ x: true }")?.into_dynamic();
+    assert!(from_dynamic::<HashMap<String, bool>>(&value)?["x"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_serde_mismatch() {
+    let mut engine = Engine::new();
+
+    assert!(matches!(
+        *engine.eval_into::<Server>(r#"#{ host: 42 }"#).expect_err("should error"),
+        EvalAltResult::ErrorRuntime(msg, _) if msg.contains("invalid type")
+    ));
+    assert!(matches!(
+        *engine.eval_into::<Server>(r#"#{ host: "x" }"#).expect_err("should error"),
+        EvalAltResult::ErrorRuntime(msg, _) if msg.contains("missing field")
+    ));
+    assert!(matches!(
+        *engine
+            .eval_into::<Vec<INT>>("[1, \"two\"]")
+            .expect_err("should error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+    assert!(matches!(
+        *engine.eval_into::<Mode>(r#""sleeping""#).expect_err("should error"),
+        EvalAltResult::ErrorRuntime(msg, _) if msg.contains("unknown variant")
+    ));
+}
+
+#[test]
+fn test_serde_deeply_nested() -> Result<(), Box<EvalAltResult>> {
+    #[derive(Debug, Deserialize)]
+    struct Nested(Vec<Nested>);
+
+    let mut engine = Engine::new();
+    let script = |depth| format!("let a = []; for i in range(0, {}) {{ a = [a]; }} a", depth);
+
+    assert_eq!(engine.eval_into::<Nested>(&script(10))?.0.len(), 1);
+
+    // Values nested too deeply fail instead of overflowing the stack
+    assert!(matches!(
+        *engine
+            .eval_into::<Nested>(&script(100_000))
+            .expect_err("should error"),
+        EvalAltResult::ErrorRuntime(msg, _) if msg.contains("levels deep")
+    ));
+
+    Ok(())
+}
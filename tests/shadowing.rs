@@ -0,0 +1,111 @@
+use rhai::{Engine, EvalAltResult, ParseErrorType, Scope, Shadowing, INT};
+
+fn shadowed(engine: &Engine, script: &str) -> Option<String> {
+    match engine.compile(script) {
+        Ok(_) => None,
+        Err(err) => match err.error_type() {
+            ParseErrorType::VariableShadowed(name) => Some(name.clone()),
+            _ => panic!("unexpected error: {}", err),
+        },
+    }
+}
+
+#[test]
+fn test_shadowing_same_block() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(engine.eval::<INT>("let x = 1; let x = x + 1; x")?, 2);
+
+    engine.set_shadowing(Shadowing::NotInSameBlock);
+
+    assert_eq!(
+        shadowed(&engine, "let x = 1; let x = x + 1;"),
+        Some("x".into())
+    );
+    assert_eq!(
+        shadowed(&engine, "let x = 1; const x = 2;"),
+        Some("x".into())
+    );
+    assert_eq!(
+        shadowed(&engine, "let x = 1; { let y = 1; let y = 2; }"),
+        Some("y".into())
+    );
+    assert_eq!(
+        shadowed(&engine, "let x = 1; if x > 0 { let x = 2; }"),
+        None
+    );
+    assert_eq!(
+        shadowed(&engine, "let x = 1; for x in range(0, 3) {}"),
+        None
+    );
+    assert_eq!(shadowed(&engine, "{ let x = 1; } { let x = 2; }"), None);
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        assert_eq!(
+            shadowed(&engine, "fn f(x) { let x = 1; x }"),
+            Some("x".into())
+        );
+        assert_eq!(
+            shadowed(&engine, "let x = 1; fn f() { let x = 2; x }"),
+            None
+        );
+    }
+
+    let err = engine
+        .compile("let a = 1;\nlet a = 2;")
+        .expect_err("should error");
+    assert_eq!(err.position().line(), Some(2));
+    assert_eq!(
+        err.to_string(),
+        "Variable 'a' is already declared (line 2, position 5)"
+    );
+
+    // Variables in the scope are not considered
+    let mut scope = Scope::new();
+    scope.push("x", 40 as INT);
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "let x = x + 2; x")?,
+        42
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_shadowing_forbidden() {
+    let engine = Engine::builder()
+        .with_shadowing(Shadowing::Forbidden)
+        .build();
+
+    assert_eq!(shadowed(&engine, "let x = 1; let x = 2;"), Some("x".into()));
+    assert_eq!(
+        shadowed(&engine, "let x = 1; if x > 0 { let x = 2; }"),
+        Some("x".into())
+    );
+    assert_eq!(
+        shadowed(&engine, "let x = 1; for x in range(0, 3) {}"),
+        Some("x".into())
+    );
+    assert_eq!(
+        shadowed(&engine, "for i in range(0, 3) { for i in range(0, 3) {} }"),
+        Some("i".into())
+    );
+    assert_eq!(
+        shadowed(&engine, "let y = { let x = 1; x }; let x = y;"),
+        None
+    );
+    assert_eq!(
+        shadowed(&engine, "for i in range(0, 3) {} let i = 0;"),
+        None
+    );
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        assert_eq!(
+            shadowed(&engine, "fn f(x) { if x > 0 { let x = 1; } x }"),
+            Some("x".into())
+        );
+        assert_eq!(shadowed(&engine, "fn f(x) { x } fn g(x) { x }"), None);
+    }
+}
@@ -0,0 +1,209 @@
+use rhai::{AnyExt, Engine, EvalAltResult, Scope, SharedValue, INT};
+
+#[test]
+fn test_shared_value() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>("let x = shared(1); let y = x; set(y, 42); get(x)")?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<String>("let x = shared(1); type_of(x)")?,
+        "shared"
+    );
+
+    let mut scope = Scope::new();
+    let value = SharedValue::new(1 as INT);
+    scope.push("value", value.clone());
+
+    engine.eval_with_scope::<()>(&mut scope, "set(value, get(value) + 41)")?;
+    assert_eq!(value.get_as::<INT>(), Some(42));
+
+    value.set("hello".to_string());
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, "get(value)")?,
+        "hello"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_shared_value_compare_print() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!(engine.eval::<bool>("let x = shared(1); let y = x; x == y")?);
+    assert!(engine.eval::<bool>("shared(1) == shared(1)")?);
+    assert!(engine.eval::<bool>("shared(1) != shared(2)")?);
+    assert!(!engine.eval::<bool>(r#"shared(1) == shared("1")"#)?);
+
+    assert_eq!(engine.eval::<String>("to_string(shared(42))")?, "42");
+    assert_eq!(
+        engine.eval::<String>(r#"let x = shared("hello"); x.to_string()"#)?,
+        "hello"
+    );
+
+    let mut printed = String::new();
+    {
+        let mut engine = Engine::new();
+        engine.on_print(|s| printed.push_str(s));
+        engine.eval::<()>("print(shared(42))")?;
+    }
+    assert_eq!(printed, "42");
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+fn test_shared_value_nested() -> Result<(), Box<EvalAltResult>> {
+    use rhai::{Array, Dynamic};
+
+    let mut engine = Engine::new();
+
+    assert!(engine.eval::<bool>("let s = shared(1); [s] == [s]")?);
+    assert!(engine.eval::<bool>("[shared(1)] == [shared(1)]")?);
+    assert!(engine.eval::<bool>("[[shared([1])]] != [[shared([2])]]")?);
+    assert!(!engine.eval::<bool>("[shared(1)] == [1]")?);
+
+    // A shared value cannot be stored into itself, which would keep it alive forever
+    for script in &[
+        "let s = shared(0); set(s, s)",
+        "let s = shared(0); set(s, [s])",
+        "let s = shared(0); let t = shared([s]); set(s, [1, [t]])",
+        #[cfg(not(feature = "no_object"))]
+        "let s = shared(#{}); s.me = #{ list: [s] }",
+    ] {
+        assert!(matches!(
+            *engine.eval::<()>(script).expect_err("should error"),
+            EvalAltResult::ErrorRuntime(msg, _) if msg.contains("itself")
+        ));
+    }
+
+    // Shared values containing themselves (made by the host) can still be compared
+    let (x, y) = (SharedValue::new(()), SharedValue::new(()));
+    x.set(Array::from(vec![Box::new(x.clone()) as Dynamic]));
+    y.set(Array::from(vec![Box::new(y.clone()) as Dynamic]));
+
+    let mut scope = Scope::new();
+    scope.push("x", x.clone());
+    scope.push("y", y.clone());
+
+    assert!(engine.eval_with_scope::<bool>(&mut scope, "x == y")?);
+    assert!(engine.eval_with_scope::<bool>(&mut scope, "[x, 1] == [y, 1]")?);
+    assert!(engine.eval_with_scope::<bool>(&mut scope, "[x, 1] != [y, 2]")?);
+
+    // Break the cycles, so that the values are freed
+    x.set(());
+    y.set(());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "sync")]
+fn test_shared_value_poisoned() {
+    use std::{panic, thread};
+
+    let value = SharedValue::new(1 as INT);
+
+    let poisoner = value.clone();
+    thread::spawn(move || poisoner.write(|_| panic!("poison the lock")))
+        .join()
+        .expect_err("expects panic");
+
+    assert!(value.try_read(|_| ()).is_err());
+    assert!(panic::catch_unwind(|| value.get()).is_err());
+
+    let mut engine = Engine::new();
+    let mut scope = Scope::new();
+    scope.push("value", value);
+
+    assert!(matches!(
+        *engine
+            .eval_with_scope::<INT>(&mut scope, "get(value)")
+            .expect_err("expects error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval_with_scope::<()>(&mut scope, "set(value, 42)")
+            .expect_err("expects error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+}
+
+#[test]
+#[cfg(not(feature = "no_object"))]
+fn test_shared_map() -> Result<(), Box<EvalAltResult>> {
+    use rhai::Map;
+
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>("let x = shared(#{a: 1}); let y = x; y.a = 42; x.a")?,
+        42
+    );
+
+    let mut scope = Scope::new();
+    let board = SharedValue::new(Map::new());
+    scope.push("board", board.clone());
+
+    engine.eval_with_scope::<()>(&mut scope, "board.count = 1; board.count += 1;")?;
+
+    let count = board.read(|map| map.downcast_ref::<Map>().unwrap()["count"].clone());
+    assert_eq!(count.cast::<INT>(), 2);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "sync")]
+#[cfg(not(feature = "no_object"))]
+fn test_shared_map_threads() -> Result<(), Box<EvalAltResult>> {
+    use rhai::Map;
+    use std::thread;
+
+    let board = SharedValue::new(Map::new());
+
+    // Each thread runs its own script on the same blackboard
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let board = board.clone();
+
+            thread::spawn(move || {
+                let mut engine = Engine::new();
+                let mut scope = Scope::new();
+                scope.push("board", board.clone());
+
+                for _ in 0..100 {
+                    // Lock the blackboard while updating it from Rust
+                    board.write(|map| {
+                        let map = map.downcast_mut::<Map>().unwrap();
+                        let count = map.get("rust").map_or(0, |v| v.clone().cast::<INT>());
+                        map.insert("rust".into(), Box::new(count + 1));
+                    });
+
+                    engine
+                        .eval_with_scope::<()>(&mut scope, "board.script = true;")
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+
+    handles.into_iter().for_each(|h| h.join().unwrap());
+
+    let mut engine = Engine::new();
+    let mut scope = Scope::new();
+    scope.push("board", board);
+
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "board.rust")?,
+        400
+    );
+    assert!(engine.eval_with_scope::<bool>(&mut scope, "board.script")?);
+
+    Ok(())
+}
@@ -41,7 +41,7 @@ impl CommandWrapper {
 }
 
 #[test]
-fn test_side_effects() -> Result<(), EvalAltResult> {
+fn test_side_effects() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
     let mut scope = Scope::new();
 
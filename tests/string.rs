@@ -1,7 +1,7 @@
-use rhai::{Engine, EvalAltResult};
+use rhai::{Engine, EvalAltResult, INT};
 
 #[test]
-fn test_string() -> Result<(), EvalAltResult> {
+fn test_string() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(
@@ -27,3 +27,197 @@ fn test_string() -> Result<(), EvalAltResult> {
 
     Ok(())
 }
+
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_string_append_in_place() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let s = "";
+                s.reserve(100);
+                let i = 0;
+                while i < 100 { s += "x"; s += i; s += 'c'; i += 1; }
+                s.len()
+            "#
+        )?,
+        390
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let s = "a"; s += true; s += (); s"#)?,
+        "atrue"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"let s = "a"; let t = s += "b"; s"#)?,
+        "ab"
+    );
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"const s = "a"; s += "b"; s"#)
+            .expect_err("expects error"),
+        EvalAltResult::ErrorAssignmentToConstant(_, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_string_format() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<String>(r#"format("Hello {}, you have {} points", "Bob", 42)"#)?,
+        "Hello Bob, you have 42 points"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"format("{1}-{0}-{1} {{}}", 'a', true)"#)?,
+        "true-a-true {}"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"format("[{:5}|{:<5}|{:^5}|{:*>5}]", 42, 42, "ab", "ab")"#)?,
+        "[   42|42   | ab  |***ab]"
+    );
+    assert_eq!(
+        engine.eval::<String>(
+            r#"format("{:x} {:X} {:o} {:b} {:+05} {:.3}", 255, 255, 8, 5, -7, "abcdef")"#
+        )?,
+        "ff FF 10 101 -0007 abc"
+    );
+
+    #[cfg(not(feature = "no_float"))]
+    assert_eq!(
+        engine.eval::<String>(r#"format("{:.2} {:+8.3} {:.1e}", 3.14159, 2.5, 1234.5)"#)?,
+        "3.14   +2.500 1.2e3"
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"format("{} {}", 1)"#)
+            .expect_err("expects error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"format("{:x}", "a")"#)
+            .expect_err("expects error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"format("{:?}", 1)"#)
+            .expect_err("expects error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+
+    // Width and precision are limited
+    assert_eq!(
+        engine.eval::<String>(r#"format("{:1000}", "")"#)?.len(),
+        1000
+    );
+    for template in &[
+        "{:1001}",
+        "{:.70000}",
+        "{:.70000e}",
+        "{:99999999999999999999999}",
+        "{:.99999999999999999999999}",
+    ] {
+        assert!(matches!(
+            *engine
+                .eval::<String>(&format!(r#"format("{}", 1)"#, template))
+                .expect_err("expects error"),
+            EvalAltResult::ErrorRuntime(_, _)
+        ));
+    }
+    #[cfg(not(feature = "no_float"))]
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"format("{:.70000e}", 1.0)"#)
+            .expect_err("expects error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_string_url_encoding() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<String>(r#"url_encode("a b&c=d/é~")"#)?,
+        "a%20b%26c%3Dd%2F%C3%A9~"
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"url_decode("a%20b%26c%3dd%2F%C3%A9+~")"#)?,
+        "a b&c=d/é+~"
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"url_decode("50%")"#)
+            .expect_err("expects error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<String>(r#"url_decode("%FF")"#)
+            .expect_err("expects error"),
+        EvalAltResult::ErrorRuntime(_, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_stdlib"))]
+fn test_string_compare() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!(engine.eval::<bool>(r#""Straße".eq_ignore_case("STRAßE")"#)?);
+    assert!(!engine.eval::<bool>(r#"eq_ignore_case("abc", "abd")"#)?);
+    assert!(engine.eval::<bool>("eq_ignore_case('Ä', 'ä')")?);
+
+    assert_eq!(engine.eval::<INT>(r#"cmp("apple", "banana")"#)?, -1);
+    assert_eq!(engine.eval::<INT>(r#""b".cmp("b")"#)?, 0);
+    assert_eq!(engine.eval::<INT>(r#"cmp("b", "B")"#)?, 1);
+    assert_eq!(engine.eval::<INT>(r#"cmp_ignore_case("b", "B")"#)?, 0);
+    assert_eq!(
+        engine.eval::<INT>(r#"cmp_ignore_case("apple", "Banana")"#)?,
+        -1
+    );
+    assert_eq!(engine.eval::<INT>("cmp(42, 7)")?, 1);
+    assert_eq!(engine.eval::<INT>("cmp('a', 'b')")?, -1);
+
+    #[cfg(not(feature = "no_float"))]
+    {
+        assert_eq!(engine.eval::<INT>("cmp(1.5, 2.5)")?, -1);
+        assert!(engine.eval::<INT>("cmp(sqrt(-1.0), 1.0)").is_err());
+    }
+
+    assert_eq!(engine.eval::<INT>(r#"cmp_natural("file2", "file10")"#)?, -1);
+    assert_eq!(engine.eval::<INT>(r#"cmp_natural("File10", "file9")"#)?, 1);
+    assert_eq!(engine.eval::<INT>(r#"cmp_natural("v1.02", "v1.2")"#)?, -1);
+    assert_eq!(engine.eval::<INT>(r#"cmp_natural("a10b", "a10c")"#)?, -1);
+    assert_eq!(engine.eval::<INT>(r#"cmp_natural("x", "x")"#)?, 0);
+
+    #[cfg(not(feature = "no_index"))]
+    {
+        assert_eq!(
+            engine.eval::<String>(
+                r#"
+                    let files = ["file10", "File1", "file2"];
+                    files.sort("cmp_natural");
+                    let names = ["bob", "Alice", "carol"];
+                    names.sort("cmp_ignore_case");
+                    files[0] + " " + files[1] + " " + files[2] + " / " + names[0] + " " + names[1]
+                "#
+            )?,
+            "File1 file2 file10 / Alice bob"
+        );
+    }
+
+    Ok(())
+}
@@ -5,10 +5,10 @@ fn test_throw() {
     let mut engine = Engine::new();
 
     assert!(matches!(
-        engine.eval::<()>(r#"if true { throw "hello" }"#).expect_err("expects error"),
+        *engine.eval::<()>(r#"if true { throw "hello" }"#).expect_err("expects error"),
         EvalAltResult::ErrorRuntime(s, _) if s == "hello"));
 
     assert!(matches!(
-        engine.eval::<()>(r#"throw"#).expect_err("expects error"),
+        *engine.eval::<()>(r#"throw"#).expect_err("expects error"),
         EvalAltResult::ErrorRuntime(s, _) if s == ""));
 }
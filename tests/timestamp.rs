@@ -0,0 +1,41 @@
+#![cfg(not(feature = "no_stdlib"))]
+
+use rhai::{Engine, EvalAltResult};
+
+#[cfg(not(feature = "no_float"))]
+use rhai::FLOAT;
+
+#[cfg(feature = "no_float")]
+use rhai::INT;
+
+#[test]
+fn test_timestamp() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<String>("let t = timestamp(); type_of(t)")?,
+        "timestamp"
+    );
+    assert!(engine.eval::<bool>("let t1 = timestamp(); let t2 = timestamp(); t2 >= t1")?);
+    assert!(engine.eval::<bool>("let t = timestamp(); t == t")?);
+
+    #[cfg(not(feature = "no_float"))]
+    {
+        assert!(engine.eval::<FLOAT>("let t = timestamp(); elapsed(t)")? >= 0.0);
+        assert!(
+            engine.eval::<FLOAT>("let t1 = timestamp(); let t2 = timestamp(); t2 - t1")? >= 0.0
+        );
+        assert_eq!(
+            engine.eval::<FLOAT>("let t1 = timestamp(); let t2 = timestamp(); t1 - t2")?,
+            0.0
+        );
+
+        #[cfg(not(feature = "no_object"))]
+        assert!(engine.eval::<FLOAT>("let t = timestamp(); t.elapsed")? >= 0.0);
+    }
+
+    #[cfg(feature = "no_float")]
+    assert_eq!(engine.eval::<INT>("let t = timestamp(); elapsed(t)")?, 0);
+
+    Ok(())
+}
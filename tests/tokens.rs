@@ -0,0 +1,173 @@
+use rhai::{CustomKeyword, Engine, EvalAltResult, LexError, ParseErrorType, Position, Token, INT};
+
+#[test]
+fn test_tokens() {
+    let engine = Engine::new();
+
+    let tokens: Vec<_> = engine.lex("let x = 42;\nx += 1;").collect();
+
+    assert_eq!(
+        tokens.iter().map(|(t, _)| t.clone()).collect::<Vec<_>>(),
+        vec![
+            Token::Let,
+            Token::Identifier("x".into()),
+            Token::Equals,
+            Token::IntegerConstant(42 as INT),
+            Token::SemiColon,
+            Token::Identifier("x".into()),
+            Token::PlusAssign,
+            Token::IntegerConstant(1 as INT),
+            Token::SemiColon,
+        ]
+    );
+
+    assert_eq!(tokens[0].1, Position::new(1, 1));
+    assert_eq!(tokens[5].1, Position::new(2, 1));
+    assert_eq!(tokens[6].1, Position::new(2, 3));
+}
+
+#[test]
+fn test_tokens_unary() {
+    let engine = Engine::new();
+
+    let tokens: Vec<_> = engine.lex("-x - -y").map(|(t, _)| t).collect();
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::UnaryMinus,
+            Token::Identifier("x".into()),
+            Token::Minus,
+            Token::UnaryMinus,
+            Token::Identifier("y".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_tokens_error() {
+    let engine = Engine::new();
+
+    let tokens: Vec<_> = engine.lex("let x = \"hello").map(|(t, _)| t).collect();
+
+    assert_eq!(
+        tokens.last(),
+        Some(&Token::LexError(Box::new(LexError::UnterminatedString)))
+    );
+}
+
+#[test]
+fn test_tokens_identifiers() {
+    let engine = Engine::new();
+
+    let tokens: Vec<_> = engine.lex("_x __ _1").map(|(t, _)| t).collect();
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Identifier("_x".into()),
+            Token::LexError(Box::new(LexError::MalformedIdentifier("__".into()))),
+            Token::LexError(Box::new(LexError::MalformedIdentifier("_1".into()))),
+        ]
+    );
+
+    #[cfg(not(feature = "unicode-xid-ident"))]
+    assert_eq!(
+        engine.lex("résumé").map(|(t, _)| t).collect::<Vec<_>>(),
+        vec![
+            Token::Identifier("r".into()),
+            Token::LexError(Box::new(LexError::UnexpectedChar('é'))),
+            Token::Identifier("sum".into()),
+            Token::LexError(Box::new(LexError::UnexpectedChar('é'))),
+        ]
+    );
+}
+
+#[test]
+fn test_tokens_reserved() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.set_reserved_symbols(["spawn", "world"]);
+
+    let err = engine
+        .compile("let x = 1;\nlet world = 42;")
+        .expect_err("should error");
+    assert_eq!(
+        *err.error_type(),
+        ParseErrorType::ReservedSymbol("world".into())
+    );
+    assert_eq!(err.position(), Position::new(2, 5));
+
+    assert!(engine.compile("const world = 42;").is_err());
+    assert!(engine.compile("for world in range(0, 3) {}").is_err());
+    assert!(engine.compile("world + 1").is_err());
+    assert!(engine.compile("spawn(42)").is_err());
+    assert!(engine.compile("if false { spawn(42) }").is_err());
+    assert!(engine.compile_expression("spawn(42)").is_err());
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        assert!(engine.compile("fn spawn(x) { x }").is_err());
+        assert!(engine.compile("fn foo(world) { world }").is_err());
+    }
+
+    #[cfg(not(feature = "no_object"))]
+    {
+        assert!(engine.compile("let x = 42; x.spawn()").is_err());
+
+        // Properties and map keys are not variables
+        assert_eq!(engine.eval::<INT>("let x = #{ world: 42 }; x.world")?, 42);
+    }
+
+    // Names containing reserved names and strings are not affected
+    assert_eq!(engine.eval::<INT>("let worlds = 42; worlds")?, 42);
+    assert_eq!(engine.eval::<String>(r#""spawn""#)?, "spawn");
+
+    engine.set_reserved_symbols(Vec::<String>::new());
+    assert_eq!(engine.eval::<INT>("let world = 42; world")?, 42);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "unicode-xid-ident")]
+fn test_tokens_unicode_identifiers() -> Result<(), Box<rhai::EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>("let größe = 40; let 長さ = 2; größe + 長さ")?,
+        42
+    );
+
+    // Identifiers are normalized, so composed and decomposed forms are the same variable
+    assert_eq!(engine.eval::<INT>("let caf\u{e9} = 42; cafe\u{301}")?, 42);
+
+    // ... and so are compatibility characters such as ligatures
+    assert_eq!(engine.eval::<INT>("let \u{fb01}x = 42; fix")?, 42);
+
+    // Non-letters still cannot start an identifier
+    assert!(engine.compile("let ² = 1;").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_tokens_engine_options() {
+    let mut engine = Engine::new();
+
+    engine.disable_symbol("while");
+    engine
+        .register_custom_keyword("double", CustomKeyword::Prefix)
+        .unwrap();
+
+    let tokens: Vec<_> = engine.lex("while double x").map(|(t, _)| t).collect();
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::LexError(Box::new(LexError::DisabledSymbol("while".into()))),
+            Token::Custom("double".into(), CustomKeyword::Prefix),
+            Token::Identifier("x".into()),
+        ]
+    );
+}
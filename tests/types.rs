@@ -1,7 +1,7 @@
 use rhai::{Engine, EvalAltResult, RegisterFn, INT};
 
 #[test]
-fn test_type_of() -> Result<(), EvalAltResult> {
+fn test_type_of() -> Result<(), Box<EvalAltResult>> {
     #[derive(Clone)]
     struct TestStruct {
         x: INT,
@@ -48,3 +48,104 @@ fn test_type_of() -> Result<(), EvalAltResult> {
 
     Ok(())
 }
+
+#[test]
+#[cfg(not(feature = "no_object"))]
+fn test_type_with_display() -> Result<(), Box<EvalAltResult>> {
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Clone)]
+    struct TestStruct {
+        x: INT,
+    }
+
+    impl fmt::Display for TestStruct {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "<{}>", self.x)
+        }
+    }
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let logger = log.clone();
+
+    {
+        let mut engine = Engine::new();
+        engine.register_type_with_display::<TestStruct>();
+        engine.register_fn("new_ts", |x: INT| TestStruct { x });
+
+        engine.on_print(move |s| logger.lock().unwrap().push(s.to_string()));
+
+        assert_eq!(engine.eval::<String>("to_string(new_ts(42))")?, "<42>");
+        assert_eq!(
+            engine.eval::<String>("let x = new_ts(1); x.to_string()")?,
+            "<1>"
+        );
+
+        engine.consume("print(new_ts(7))")?;
+    }
+
+    assert_eq!(*log.lock().unwrap(), vec!["<7>".to_string()]);
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let logger = log.clone();
+
+    {
+        let mut engine = Engine::new();
+        engine.register_type_with_display::<TestStruct>();
+        engine.register_fn("new_ts", |x: INT| TestStruct { x });
+
+        engine.on_debug(move |s, _, _| logger.lock().unwrap().push(s.to_string()));
+
+        engine.consume("debug(new_ts(7))")?;
+    }
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec!["TestStruct { x: 7 }".to_string()]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_type_predicates() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!(engine.eval::<bool>("is_int(42)")?);
+    assert!(!engine.eval::<bool>(r#"is_int("42")"#)?);
+    assert!(engine.eval::<bool>(r#"is_string("hello")"#)?);
+    assert!(engine.eval::<bool>("is_char('x')")?);
+    assert!(engine.eval::<bool>("is_bool(true)")?);
+    assert!(engine.eval::<bool>("is_unit(())")?);
+
+    #[cfg(not(feature = "no_object"))]
+    {
+        assert!(engine.eval::<bool>("let x = 42; x.is_int()")?);
+        assert!(engine.eval::<bool>(r#""hello".is_string()"#)?);
+        assert!(engine.eval::<bool>("'x'.is_char()")?);
+        assert!(engine.eval::<bool>("true.is_bool()")?);
+    }
+    assert!(!engine.eval::<bool>("is_unit(0)")?);
+
+    assert_eq!(engine.eval::<String>("type_of(range(0, 3))")?, "range");
+    assert_eq!(engine.eval::<String>("type_of(range(0, 9, 3))")?, "range");
+
+    #[cfg(not(feature = "no_float"))]
+    assert!(engine.eval::<bool>("let x = 1.5; is_float(x) && !is_int(x)")?);
+
+    #[cfg(not(feature = "no_index"))]
+    assert!(engine.eval::<bool>("is_array([1, 2])")?);
+
+    #[cfg(not(feature = "no_object"))]
+    assert!(engine.eval::<bool>("let x = #{a: 1}; x.is_map()")?);
+
+    #[cfg(not(feature = "no_object"))]
+    #[cfg(not(feature = "no_index"))]
+    assert!(engine.eval::<bool>("let x = #{a: 1}; !is_array(x)")?);
+
+    engine.register_fn("is_int", |_: INT| false);
+    assert!(!engine.eval::<bool>("is_int(42)")?);
+
+    Ok(())
+}
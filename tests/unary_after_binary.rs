@@ -3,7 +3,7 @@ use rhai::{Engine, EvalAltResult, INT};
 #[test]
 // TODO also add test case for unary after compound
 // Hah, turns out unary + has a good use after all!
-fn test_unary_after_binary() -> Result<(), EvalAltResult> {
+fn test_unary_after_binary() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
     assert_eq!(engine.eval::<INT>("10 % +4")?, 2);
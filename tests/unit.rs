@@ -1,22 +1,57 @@
-use rhai::{Engine, EvalAltResult};
+use rhai::{Engine, EvalAltResult, Scope, INT};
 
 #[test]
-fn test_unit() -> Result<(), EvalAltResult> {
+fn test_unit() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
     engine.eval::<()>("let x = (); x")?;
     Ok(())
 }
 
 #[test]
-fn test_unit_eq() -> Result<(), EvalAltResult> {
+fn test_unit_eq() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
     assert_eq!(engine.eval::<bool>("let x = (); let y = (); x == y")?, true);
     Ok(())
 }
 
 #[test]
-fn test_unit_with_spaces() -> Result<(), EvalAltResult> {
+fn test_unit_with_spaces() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
     engine.eval::<()>("let x = ( ); x")?;
     Ok(())
 }
+
+#[test]
+fn test_unit_discard() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    assert!(matches!(
+        *engine
+            .eval::<()>("let x = 40; x + 2")
+            .expect_err("expects error"),
+        EvalAltResult::ErrorMismatchOutputType(_, _, _)
+    ));
+
+    engine.run("let x = 40; x + 2")?;
+
+    let ast = engine.compile("let x = 40; x + 2")?;
+    engine.run_ast(&ast)?;
+
+    let mut scope = Scope::new();
+    engine.run_with_scope(&mut scope, "let x = 40; x + 2")?;
+    assert_eq!(
+        scope
+            .get_value::<INT>("x")
+            .expect("variable x should exist"),
+        40
+    );
+
+    engine.enable_unit_discard(true);
+
+    engine.eval::<()>("let x = 40; x + 2")?;
+    engine.eval_ast::<()>(&ast)?;
+    assert_eq!(engine.eval::<INT>("let x = 40; x + 2")?, 42);
+    assert!(engine.eval::<()>("let x = 40; x +").is_err());
+
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use rhai::{Engine, EvalAltResult, ParseErrorType, Shadowing, INT};
+
+#[test]
+fn test_validate() {
+    let engine = Engine::new();
+
+    assert!(engine.validate("").is_ok());
+    assert!(engine.validate("let x = 40 + 2; x * 2").is_ok());
+
+    let errors = engine
+        .validate("let x = ;\nlet y = 42;\nlet z = (1 + ;\nprint(y);\nlet w = [1, 2")
+        .expect_err("should error");
+
+    assert_eq!(
+        errors
+            .iter()
+            .map(|err| err.position().line())
+            .collect::<Vec<_>>(),
+        vec![
+            Some(1),
+            Some(3),
+            // Arrays are not supported, so the error is at the '['
+            if cfg!(feature = "no_index") {
+                Some(5)
+            } else {
+                None
+            }
+        ]
+    );
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_validate_fn() {
+    let engine = Engine::new();
+
+    let errors = engine
+        .validate(
+            r"
+fn foo(x) {
+    x +
+}
+
+fn bar(x, x) {
+    x
+}
+
+fn baz(x) {
+    x * 2
+}
+",
+        )
+        .expect_err("should error");
+
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(
+        errors[1].error_type(),
+        ParseErrorType::FnDuplicatedParam(_, _)
+    ));
+}
+
+#[test]
+fn test_validate_too_deep() {
+    let engine = Engine::new();
+
+    let script = format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000));
+    let errors = engine.validate(&script).expect_err("should error");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(*errors[0].error_type(), ParseErrorType::ExprTooDeep);
+
+    let script = "-".repeat(100_000) + "1";
+    let errors = engine.validate(&script).expect_err("should error");
+    assert_eq!(*errors[0].error_type(), ParseErrorType::ExprTooDeep);
+
+    assert!(engine.compile(&script).is_err());
+
+    let script = format!("{}1{}", "(".repeat(50), ")".repeat(50));
+    assert!(engine.validate(&script).is_ok());
+}
+
+#[test]
+fn test_validate_long_expression() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Only nesting counts towards the limit, not the length of a flat chain of operators
+    let script = "1 + ".repeat(145) + "1";
+    assert!(engine.validate(&script).is_ok());
+    assert!(engine.compile(&script).is_ok());
+
+    let script = "1 + ".repeat(49) + "1";
+    assert_eq!(engine.eval::<INT>(&script)?, 50);
+
+    Ok(())
+}
+
+#[test]
+fn test_validate_max_expr_depth() {
+    let script = format!("{}1{}", "(".repeat(12), ")".repeat(12));
+
+    assert!(Engine::new().validate(&script).is_ok());
+
+    let engine = Engine::builder().with_max_expr_depth(10).build();
+    let errors = engine.validate(&script).expect_err("should error");
+    assert_eq!(*errors[0].error_type(), ParseErrorType::ExprTooDeep);
+
+    let mut engine = Engine::new();
+    engine.set_max_expr_depth(10);
+    assert!(engine.compile(&script).is_err());
+    assert!(engine.compile("((((1))))").is_ok());
+}
+
+#[test]
+fn test_validate_compile_checks() {
+    let mut engine = Engine::new();
+    engine.set_reserved_symbols(["spawn"]);
+    engine.set_shadowing(Shadowing::NotInSameBlock);
+
+    let errors = engine
+        .validate("let spawn = 1; let x = 1; let x = 2;")
+        .expect_err("should error");
+
+    assert_eq!(errors.len(), 2);
+    assert!(errors
+        .iter()
+        .any(|err| *err.error_type() == ParseErrorType::ReservedSymbol("spawn".into())));
+    assert!(errors
+        .iter()
+        .any(|err| *err.error_type() == ParseErrorType::VariableShadowed("x".into())));
+}
+
+#[test]
+fn test_validate_garbage() {
+    const FRAGMENTS: &[&str] = &[
+        "let",
+        "const",
+        "fn",
+        "if",
+        "else",
+        "while",
+        "loop",
+        "for",
+        "in",
+        "return",
+        "throw",
+        "break",
+        "continue",
+        "import",
+        "export",
+        "as",
+        "x",
+        "foo",
+        "(",
+        ")",
+        "{",
+        "}",
+        "[",
+        "]",
+        "#{",
+        ",",
+        ";",
+        ".",
+        ":",
+        "::",
+        "=",
+        "+=",
+        "<<=",
+        "==",
+        "<",
+        "&&",
+        "!",
+        "-",
+        "*",
+        "~",
+        "1",
+        "1.5",
+        "0x",
+        "99999999999999999999",
+        "'a'",
+        "'",
+        "\"abc\"",
+        "\"",
+        "'\\u12345'",
+        "true",
+        "this",
+        "\n",
+        " ",
+        "//",
+        "/*",
+        "///",
+        "@",
+        "$",
+        "é",
+    ];
+
+    let engine = Engine::new();
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+
+    // A simple xorshift generator keeps the test deterministic
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed as usize
+    };
+
+    for _ in 0..10_000 {
+        let len = next() % 20;
+        let script: String = (0..len)
+            .map(|_| FRAGMENTS[next() % FRAGMENTS.len()])
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        // Must never panic
+        let _ = engine.validate(&script);
+    }
+}
@@ -0,0 +1,94 @@
+use rhai::{ASTNode, ASTVisitor, Engine, EvalAltResult, Expr, Nodes, Stmt};
+
+#[derive(Default)]
+struct Recorder {
+    depth: usize,
+    calls: Vec<String>,
+    functions: Vec<String>,
+    skip_loops: bool,
+}
+
+impl ASTVisitor for Recorder {
+    fn enter(&mut self, node: ASTNode, _: &Nodes) -> bool {
+        self.depth += 1;
+
+        match node {
+            ASTNode::Fn(f) => self.functions.push(f.name.clone()),
+            ASTNode::Expr(Expr::FunctionCall(name, _, _, _, _)) => {
+                self.calls.push(name.to_string())
+            }
+            ASTNode::Stmt(Stmt::Loop(_, _)) if self.skip_loops => return false,
+            _ => (),
+        }
+
+        true
+    }
+
+    fn leave(&mut self, _: ASTNode) {
+        self.depth -= 1;
+    }
+}
+
+#[test]
+fn test_visitor() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r#"
+            let x = foo(1, bar(2));
+            if x > 0 { loop { baz(); break; } }
+        "#,
+    )?;
+
+    let mut recorder = Recorder::default();
+    ast.walk(&mut recorder);
+
+    assert_eq!(recorder.depth, 0);
+    assert_eq!(recorder.calls, vec!["foo", "bar", ">", "baz"]);
+    assert!(recorder.functions.is_empty());
+    assert_eq!(ast.statements().len(), 2);
+
+    let mut recorder = Recorder {
+        skip_loops: true,
+        ..Default::default()
+    };
+    ast.walk(&mut recorder);
+
+    assert_eq!(recorder.depth, 0);
+    assert_eq!(recorder.calls, vec!["foo", "bar", ">"]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_visitor_functions() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile("fn hello(x) { print(x) } hello(42);")?;
+
+    let mut recorder = Recorder::default();
+    ast.walk(&mut recorder);
+
+    assert_eq!(recorder.functions, vec!["hello"]);
+    assert_eq!(recorder.calls, vec!["print", "hello"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_visitor_merged() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast1 = engine.compile("let x = foo(bar(1));")?;
+    let ast2 = engine.compile("if true { baz(x); }")?;
+    let ast = ast1.merge(&ast2);
+
+    let mut recorder = Recorder::default();
+    ast.walk(&mut recorder);
+
+    assert_eq!(recorder.calls, vec!["foo", "bar", "baz"]);
+    assert_eq!(ast.statements().len(), 2);
+
+    Ok(())
+}
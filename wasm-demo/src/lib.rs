@@ -0,0 +1,47 @@
+//! Browser demo for Rhai.
+//!
+//! Build with [`wasm-pack`](https://rustwasm.github.io/wasm-pack/):
+//!
+//! ```sh
+//! wasm-pack build --target web
+//! ```
+//!
+//! then serve this directory and open `index.html`.
+
+use rhai::{Engine, EvalAltResult};
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+
+/// Run a script and return everything it printed, followed by any error.
+#[wasm_bindgen]
+pub fn run_script(script: &str) -> String {
+    let mut output = String::new();
+
+    let result = {
+        let mut engine = Engine::new();
+        let lines = RefCell::new(Vec::new());
+
+        engine.on_print(|s| lines.borrow_mut().push(s.to_string()));
+        engine.on_debug(|s, _, pos| lines.borrow_mut().push(format!("[{}] {}", pos, s)));
+
+        let result = engine.consume(script).or_else(|err| match *err {
+            EvalAltResult::Return(_, _) => Ok(()),
+            _ => Err(err),
+        });
+
+        drop(engine);
+
+        for line in lines.into_inner() {
+            output.push_str(&line);
+            output.push('\n');
+        }
+
+        result
+    };
+
+    if let Err(err) = result {
+        output.push_str(&format!("Error: {}\n", err));
+    }
+
+    output
+}